@@ -0,0 +1,436 @@
+//! Procedural macros for defining local GATT services of [BlueR](https://docs.rs/bluer).
+//!
+//! Do not use this crate directly; instead enable the `macros` feature of BlueR,
+//! which re-exports the macros from `bluer::gatt::local`.
+//!
+//! [`#[derive(GattService)]`](GattService) implements `bluer::gatt::local::GattService` for a struct.
+//! Fields annotated with `#[characteristic(...)]` become characteristics, whose values are
+//! converted to and from bytes using `bluer::gatt::local::GattValue`.
+//! Methods annotated with `#[characteristic(...)]` in an impl block marked with
+//! [`#[gatt_service_impl]`](macro@gatt_service_impl) become characteristics as well.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use bluer::gatt::local::{gatt_service_impl, Application, GattService, ReqError, ReqResult, ServiceState};
+//!
+//! #[derive(GattService)]
+//! #[gatt_service(uuid = 0x180f, methods)]
+//! struct Battery {
+//!     /// Battery level in percent.
+//!     #[characteristic(uuid = 0x2a19, read, notify)]
+//!     level: u8,
+//!     /// Name of the battery, which clients may change.
+//!     #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b90", read, write)]
+//!     name: String,
+//!     /// Not exposed as a characteristic.
+//!     discharging: bool,
+//! }
+//!
+//! #[gatt_service_impl]
+//! impl Battery {
+//!     /// Whether the battery is low.
+//!     #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b91", read, notify)]
+//!     fn low(&self) -> bool {
+//!         self.level < 10
+//!     }
+//!
+//!     /// Starts or stops discharging.
+//!     #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b92", write)]
+//!     fn set_discharging(&mut self, discharging: bool) -> ReqResult<()> {
+//!         if discharging && self.level == 0 {
+//!             return Err(ReqError::NotPermitted);
+//!         }
+//!         self.discharging = discharging;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # async fn example(adapter: bluer::Adapter) -> bluer::Result<()> {
+//! let battery = ServiceState::new(Battery { level: 100, name: "main".to_string(), discharging: false });
+//! let app = Application { services: vec![battery.service()], ..Default::default() };
+//! let _app_handle = adapter.serve_gatt_application(app).await?;
+//!
+//! // Subscribed clients are notified of the changed level.
+//! battery.update(|battery| battery.level -= 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, FnArg, ImplItem,
+    ItemImpl, Lit, LitBool, Pat, ReturnType, Type,
+};
+
+/// Bluetooth base UUID, to which 16-bit and 32-bit UUIDs are added.
+const BASE_UUID: u128 = 0x00000000_0000_1000_8000_00805f9b34fb;
+
+/// Derives `bluer::gatt::local::GattService` for a struct with named fields.
+///
+/// The struct must be annotated with `#[gatt_service(...)]` containing
+///
+///   * `uuid = ...`: the service UUID, either as a string in hyphenated form,
+///     as an integer specifying a 16-bit or 32-bit assigned number
+///     or as an expression of type `bluer::Uuid`, such as a constant,
+///   * `primary = false`: optional, makes the service secondary,
+///   * `methods`: optional, includes the characteristics defined in an impl block annotated with
+///     [`#[gatt_service_impl]`](macro@gatt_service_impl).
+///
+/// Fields annotated with `#[characteristic(...)]` become characteristics.
+/// The annotation contains the characteristic `uuid` as above and at least one of the flags
+/// `read`, `write`, `write_without_response`, `notify` and `indicate`.
+/// The field type must implement `bluer::gatt::local::GattValue`.
+/// A write replaces the field value; offset writes are not supported.
+/// Notifications and indications are sent when the field value changes.
+///
+/// Other fields are not exposed.
+#[proc_macro_derive(GattService, attributes(gatt_service, characteristic))]
+pub fn derive_gatt_service(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// Defines characteristics of a GATT service by methods of an impl block.
+///
+/// Methods annotated with `#[characteristic(...)]`, taking the same arguments as
+/// on struct fields, become characteristics.
+/// Their value type must implement `bluer::gatt::local::GattValue`.
+///
+///   * A method `fn(&self) -> T` provides the value of a characteristic with
+///     the `read`, `notify` and `indicate` flags.
+///     Notifications and indications are sent when the returned value changes.
+///   * A method `fn(&mut self, value: T)`, optionally returning `ReqResult<()>`,
+///     handles writes to a characteristic with the `write` and `write_without_response` flags.
+///
+/// The struct deriving [GattService](derive@GattService) must specify `methods` in its
+/// `#[gatt_service(...)]` annotation to include these characteristics.
+#[proc_macro_attribute]
+pub fn gatt_service_impl(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(Span::call_site(), "gatt_service_impl takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let input = parse_macro_input!(input as ItemImpl);
+    service_impl(input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// Arguments of `#[gatt_service(...)]`.
+struct ServiceArgs {
+    uuid: TokenStream2,
+    primary: bool,
+    methods: bool,
+}
+
+impl ServiceArgs {
+    fn parse(attrs: &[Attribute], span: Span) -> syn::Result<Self> {
+        let attr = attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("gatt_service"))
+            .ok_or_else(|| syn::Error::new(span, "missing #[gatt_service(uuid = ...)] attribute"))?;
+
+        let mut uuid = None;
+        let mut primary = true;
+        let mut methods = false;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("uuid") {
+                uuid = Some(parse_uuid(meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("primary") {
+                primary = meta.value()?.parse::<LitBool>()?.value;
+            } else if meta.path.is_ident("methods") {
+                methods = true;
+            } else {
+                return Err(meta.error("unsupported gatt_service argument"));
+            }
+            Ok(())
+        })?;
+
+        Ok(Self {
+            uuid: uuid.ok_or_else(|| syn::Error::new(attr.span(), "missing service uuid"))?,
+            primary,
+            methods,
+        })
+    }
+}
+
+/// Arguments of `#[characteristic(...)]`.
+struct CharacteristicArgs {
+    span: Span,
+    uuid: TokenStream2,
+    read: bool,
+    write: bool,
+    write_without_response: bool,
+    notify: bool,
+    indicate: bool,
+}
+
+impl CharacteristicArgs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Option<Self>> {
+        let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("characteristic")) else {
+            return Ok(None);
+        };
+
+        let mut uuid = None;
+        let mut args = Self {
+            span: attr.span(),
+            uuid: TokenStream2::new(),
+            read: false,
+            write: false,
+            write_without_response: false,
+            notify: false,
+            indicate: false,
+        };
+        attr.parse_nested_meta(|meta| {
+            let flag = match meta.path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                Some("uuid") => {
+                    uuid = Some(parse_uuid(meta.value()?.parse()?)?);
+                    return Ok(());
+                }
+                Some("read") => &mut args.read,
+                Some("write") => &mut args.write,
+                Some("write_without_response") => &mut args.write_without_response,
+                Some("notify") => &mut args.notify,
+                Some("indicate") => &mut args.indicate,
+                _ => return Err(meta.error("unsupported characteristic argument")),
+            };
+            *flag = true;
+            Ok(())
+        })?;
+
+        args.uuid = uuid.ok_or_else(|| syn::Error::new(args.span, "missing characteristic uuid"))?;
+        if !(args.gets() || args.sets()) {
+            return Err(syn::Error::new(
+                args.span,
+                "characteristic requires at least one of read, write, write_without_response, notify and indicate",
+            ));
+        }
+        Ok(Some(args))
+    }
+
+    /// Whether the value is obtained from the state.
+    fn gets(&self) -> bool {
+        self.read || self.notify || self.indicate
+    }
+
+    /// Whether the value is written to the state.
+    fn sets(&self) -> bool {
+        self.write || self.write_without_response
+    }
+
+    /// Characteristic definition using the specified getter and setter closures.
+    fn definition(&self, get: Option<TokenStream2>, set: Option<(TokenStream2, &Type)>) -> TokenStream2 {
+        let Self { uuid, read, write, write_without_response, notify, indicate, .. } = self;
+
+        let read = match (&get, read) {
+            (Some(get), true) => quote! {
+                ::std::option::Option::Some(::bluer::gatt::local::CharacteristicRead {
+                    read: true,
+                    fun: state.read_fun(#get),
+                    ..::std::default::Default::default()
+                })
+            },
+            _ => quote! { ::std::option::Option::None },
+        };
+        let write = match set {
+            Some((set, ty)) => quote! {
+                ::std::option::Option::Some(::bluer::gatt::local::CharacteristicWrite {
+                    write: #write,
+                    write_without_response: #write_without_response,
+                    method: ::bluer::gatt::local::CharacteristicWriteMethod::Fun(state.write_fun::<#ty>(#set)),
+                    ..::std::default::Default::default()
+                })
+            },
+            None => quote! { ::std::option::Option::None },
+        };
+        let notify = match (&get, notify | indicate) {
+            (Some(get), true) => quote! {
+                ::std::option::Option::Some(::bluer::gatt::local::CharacteristicNotify {
+                    notify: #notify,
+                    indicate: #indicate,
+                    method: ::bluer::gatt::local::CharacteristicNotifyMethod::Fun(state.notify_fun(#get)),
+                    ..::std::default::Default::default()
+                })
+            },
+            _ => quote! { ::std::option::Option::None },
+        };
+
+        quote! {
+            ::bluer::gatt::local::Characteristic {
+                uuid: #uuid,
+                read: #read,
+                write: #write,
+                notify: #notify,
+                ..::std::default::Default::default()
+            }
+        }
+    }
+}
+
+/// Parses a UUID given as string, as 16-bit or 32-bit assigned number or as expression.
+fn parse_uuid(expr: Expr) -> syn::Result<TokenStream2> {
+    let uuid = match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => uuid::Uuid::parse_str(&s.value())
+            .map_err(|err| syn::Error::new(s.span(), format!("invalid UUID: {err}")))?
+            .as_u128(),
+        Expr::Lit(ExprLit { lit: Lit::Int(i), .. }) => BASE_UUID | u128::from(i.base10_parse::<u32>()?) << 96,
+        Expr::Lit(ExprLit { lit, .. }) => {
+            return Err(syn::Error::new(lit.span(), "UUID must be a string or an integer"))
+        }
+        expr => return Ok(expr.to_token_stream()),
+    };
+    let uuid = Literal::u128_unsuffixed(uuid);
+    Ok(quote! { ::bluer::Uuid::from_u128(#uuid) })
+}
+
+fn derive(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let args = ServiceArgs::parse(&input.attrs, input.ident.span())?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "GattService requires a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(syn::Error::new(input.ident.span(), "GattService can only be derived for structs")),
+    };
+
+    let mut characteristics = Vec::new();
+    for field in fields {
+        let Some(c) = CharacteristicArgs::parse(&field.attrs)? else { continue };
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let get = c.gets().then(|| {
+            quote! { |s: &Self| ::bluer::gatt::local::GattValue::to_gatt_value(&s.#name) }
+        });
+        let set = c.sets().then(|| {
+            (
+                quote! {
+                    |s: &mut Self, value: #ty| {
+                        s.#name = value;
+                        ::std::result::Result::Ok(())
+                    }
+                },
+                ty,
+            )
+        });
+        characteristics.push(c.definition(get, set));
+    }
+
+    let extend =
+        args.methods.then(|| quote! { characteristics.extend(Self::__bluer_gatt_characteristics(state)); });
+    let uuid = &args.uuid;
+    let primary = args.primary;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::bluer::gatt::local::GattService for #ident #ty_generics #where_clause {
+            fn service(state: &::bluer::gatt::local::ServiceState<Self>) -> ::bluer::gatt::local::Service {
+                #[allow(unused_mut)]
+                let mut characteristics = ::std::vec![#(#characteristics),*];
+                #extend
+                ::bluer::gatt::local::Service {
+                    uuid: #uuid,
+                    primary: #primary,
+                    characteristics,
+                    ..::std::default::Default::default()
+                }
+            }
+        }
+    })
+}
+
+fn service_impl(mut input: ItemImpl) -> syn::Result<TokenStream2> {
+    let mut characteristics = Vec::new();
+    for item in &mut input.items {
+        let ImplItem::Fn(method) = item else { continue };
+        let Some(c) = CharacteristicArgs::parse(&method.attrs)? else { continue };
+        method.attrs.retain(|attr| !attr.path().is_ident("characteristic"));
+
+        let name = &method.sig.ident;
+        let mut inputs = method.sig.inputs.iter();
+        let receiver = match inputs.next() {
+            Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => receiver,
+            _ => {
+                return Err(syn::Error::new(
+                    method.sig.span(),
+                    "characteristic method must take &self or &mut self",
+                ))
+            }
+        };
+        let value = inputs.next();
+        if let Some(arg) = inputs.next() {
+            return Err(syn::Error::new(arg.span(), "characteristic method takes at most one argument"));
+        }
+
+        let definition = match (receiver.mutability, value) {
+            (None, None) => {
+                if c.sets() {
+                    return Err(syn::Error::new(
+                        c.span,
+                        "writable characteristic requires a method taking &mut self and the value",
+                    ));
+                }
+                if let ReturnType::Default = method.sig.output {
+                    return Err(syn::Error::new(
+                        method.sig.span(),
+                        "characteristic method must return the value",
+                    ));
+                }
+                let get = quote! { |s: &Self| ::bluer::gatt::local::GattValue::to_gatt_value(&s.#name()) };
+                c.definition(Some(get), None)
+            }
+            (Some(_), Some(FnArg::Typed(arg))) => {
+                if c.gets() {
+                    return Err(syn::Error::new(
+                        c.span,
+                        "readable or notifying characteristic requires a method taking &self and returning the value",
+                    ));
+                }
+                if !matches!(*arg.pat, Pat::Ident(_)) {
+                    return Err(syn::Error::new(arg.pat.span(), "value argument must be an identifier"));
+                }
+                let ty = &*arg.ty;
+                let set = match &method.sig.output {
+                    ReturnType::Default => quote! {
+                        |s: &mut Self, value: #ty| {
+                            s.#name(value);
+                            ::std::result::Result::Ok(())
+                        }
+                    },
+                    ReturnType::Type(..) => quote! { |s: &mut Self, value: #ty| s.#name(value) },
+                };
+                c.definition(None, Some((set, ty)))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    method.sig.span(),
+                    "characteristic method must be fn(&self) -> T or fn(&mut self, value: T)",
+                ))
+            }
+        };
+        characteristics.push(definition);
+    }
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+    let self_ty = input.self_ty.to_token_stream();
+
+    Ok(quote! {
+        #input
+
+        impl #impl_generics #self_ty #where_clause {
+            #[doc(hidden)]
+            pub fn __bluer_gatt_characteristics(
+                state: &::bluer::gatt::local::ServiceState<Self>,
+            ) -> ::std::vec::Vec<::bluer::gatt::local::Characteristic> {
+                ::std::vec![#(#characteristics),*]
+            }
+        }
+    })
+}
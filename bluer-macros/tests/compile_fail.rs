@@ -0,0 +1,7 @@
+//! Checks the errors reported for invalid service definitions.
+
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,10 @@
+use bluer::gatt::local::gatt_service_impl;
+
+struct Service {
+    level: u8,
+}
+
+#[gatt_service_impl(level)]
+impl Service {}
+
+fn main() {}
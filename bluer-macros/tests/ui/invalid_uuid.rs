@@ -0,0 +1,10 @@
+use bluer::gatt::local::GattService;
+
+#[derive(GattService)]
+#[gatt_service(uuid = "not-a-uuid")]
+struct Service {
+    #[characteristic(uuid = 0x2a19, read)]
+    level: u8,
+}
+
+fn main() {}
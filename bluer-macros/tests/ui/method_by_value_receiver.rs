@@ -0,0 +1,15 @@
+use bluer::gatt::local::gatt_service_impl;
+
+struct Service {
+    level: u8,
+}
+
+#[gatt_service_impl]
+impl Service {
+    #[characteristic(uuid = 0x2a19, read)]
+    fn level(self) -> u8 {
+        self.level
+    }
+}
+
+fn main() {}
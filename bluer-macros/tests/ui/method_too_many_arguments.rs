@@ -0,0 +1,15 @@
+use bluer::gatt::local::gatt_service_impl;
+
+struct Service {
+    level: u8,
+}
+
+#[gatt_service_impl]
+impl Service {
+    #[characteristic(uuid = 0x2a19, write)]
+    fn set_level(&mut self, level: u8, offset: u8) {
+        self.level = level + offset;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use bluer::gatt::local::GattService;
+
+#[derive(GattService)]
+#[gatt_service(uuid = 0x180f)]
+struct Service {
+    #[characteristic(read)]
+    level: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bluer::gatt::local::GattService;
+
+#[derive(GattService)]
+struct Service {
+    #[characteristic(uuid = 0x2a19, read)]
+    level: u8,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use bluer::gatt::local::GattService;
+
+#[derive(GattService)]
+#[gatt_service(uuid = 0x180f)]
+enum Service {
+    Level(u8),
+}
+
+#[derive(GattService)]
+#[gatt_service(uuid = 0x180f)]
+struct Tuple(u8);
+
+fn main() {}
@@ -9,6 +9,7 @@ use tokio::{
 
 use bluer::{
     adv::{Advertisement, Type},
+    beacon::{AltBeacon, Beacon, Eddystone, EddystoneTlm, IBeacon},
     Adapter, Address, Session, Uuid,
 };
 
@@ -39,9 +40,9 @@ struct Opt {
     #[clap(long, short)]
     bind: Option<Address>,
 
-    /// Type of advertisement.
-    #[clap(long, short = 't', default_value = "peripheral")]
-    advertisement_type: AdvertisementType,
+    /// Type of advertisement [default: peripheral or broadcast for beacons].
+    #[clap(long, short = 't')]
+    advertisement_type: Option<AdvertisementType>,
 
     /// Service UUID.
     ///
@@ -85,6 +86,20 @@ struct Opt {
     #[clap(long, short = 's')]
     service_data: Vec<ServiceData>,
 
+    /// Broadcast a beacon.
+    ///
+    /// Presets:
+    /// "ibeacon:<uuid>:<major>:<minor>:<measured power>",
+    /// "eddystone-uid:<tx power>:<hex namespace>:<hex instance>",
+    /// "eddystone-url:<tx power>:<url>",
+    /// "eddystone-tlm:<battery mV>:<temperature>:<advertising count>:<uptime seconds>",
+    /// "eddystone-eid:<tx power>:<hex eid>",
+    /// "altbeacon:<company id>:<hex beacon id>:<reference rssi>" (company id is in hexadecimal).
+    ///
+    /// The advertisement type defaults to broadcast.
+    #[clap(long, allow_hyphen_values(true))]
+    beacon: Option<BeaconPreset>,
+
     /// Show detailed information.
     #[clap(short, long)]
     verbose: bool,
@@ -134,6 +149,72 @@ impl FromStr for ServiceData {
     }
 }
 
+#[derive(Clone)]
+struct BeaconPreset(Beacon);
+
+impl FromStr for BeaconPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn field<T: FromStr>(fields: &[&str], i: usize, name: &str) -> Result<T, String>
+        where
+            T::Err: std::fmt::Display,
+        {
+            let value = fields.get(i).ok_or_else(|| format!("{name} missing"))?;
+            value.parse().map_err(|err| format!("invalid {name}: {err}"))
+        }
+        fn hex_field<const N: usize>(fields: &[&str], i: usize, name: &str) -> Result<[u8; N], String> {
+            let value = fields.get(i).ok_or_else(|| format!("{name} missing"))?;
+            let data = hex::decode(value).map_err(|err| format!("invalid {name}: {err}"))?;
+            data.try_into().map_err(|_| format!("{name} must be {N} bytes long"))
+        }
+
+        let (preset, args) = s.split_once(':').unwrap_or((s, ""));
+        let fields: Vec<_> = args.split(':').collect();
+        let beacon = match preset {
+            "ibeacon" => Beacon::IBeacon(IBeacon {
+                uuid: field(&fields, 0, "UUID")?,
+                major: field(&fields, 1, "major")?,
+                minor: field(&fields, 2, "minor")?,
+                measured_power: field(&fields, 3, "measured power")?,
+            }),
+            "eddystone-uid" => Beacon::Eddystone(Eddystone::Uid {
+                tx_power: field(&fields, 0, "TX power")?,
+                namespace: hex_field(&fields, 1, "namespace")?,
+                instance: hex_field(&fields, 2, "instance")?,
+            }),
+            "eddystone-url" => {
+                let Some((tx_power, url)) = args.split_once(':') else {
+                    return Err("URL missing".to_string());
+                };
+                Beacon::Eddystone(Eddystone::Url {
+                    tx_power: tx_power.parse().map_err(|err| format!("invalid TX power: {err}"))?,
+                    url: url.to_string(),
+                })
+            }
+            "eddystone-tlm" => Beacon::Eddystone(Eddystone::Tlm(EddystoneTlm::Plain {
+                battery_voltage: field(&fields, 0, "battery voltage")?,
+                temperature: Some(field(&fields, 1, "temperature")?),
+                advertising_count: field(&fields, 2, "advertising count")?,
+                uptime: Duration::from_secs(field(&fields, 3, "uptime")?),
+            })),
+            "eddystone-eid" => Beacon::Eddystone(Eddystone::Eid {
+                tx_power: field(&fields, 0, "TX power")?,
+                eid: hex_field(&fields, 1, "EID")?,
+            }),
+            "altbeacon" => Beacon::AltBeacon(AltBeacon {
+                company_id: u16::from_str_radix(fields[0], 16)
+                    .map_err(|err| format!("invalid company id: {err}"))?,
+                beacon_id: hex_field(&fields, 1, "beacon id")?,
+                reference_rssi: field(&fields, 2, "reference RSSI")?,
+                manufacturer_reserved: 0,
+            }),
+            other => return Err(format!("unknown beacon preset {other}")),
+        };
+        Ok(Self(beacon))
+    }
+}
+
 async fn get_session_adapter(addr: Option<Address>) -> AnyResult<(Session, Adapter)> {
     let session = bluer::Session::new().await?;
     let adapter_names = session.adapter_names().await?;
@@ -179,18 +260,23 @@ async fn main() -> AnyResult<()> {
         }
     };
 
-    let le_advertisement = Advertisement {
-        advertisement_type: opt.advertisement_type.into(),
-        local_name: opt.local_name.clone(),
-        discoverable: Some(opt.discoverable),
-        duration,
-        tx_power: opt.tx_power,
-        min_interval: opt.min_interval.map(Duration::from_millis),
-        max_interval: opt.max_interval.map(Duration::from_millis),
-        manufacturer_data: opt.manufacturer_data.iter().map(|md| (md.id, md.data.clone())).collect(),
-        service_data: opt.service_data.iter().map(|sd| (sd.id, sd.data.clone())).collect(),
-        ..Default::default()
+    let mut le_advertisement = match &opt.beacon {
+        Some(BeaconPreset(beacon)) => beacon.to_advertisement()?,
+        None => Advertisement::default(),
+    };
+    le_advertisement.advertisement_type = match opt.advertisement_type {
+        Some(advertisement_type) => advertisement_type.into(),
+        None if opt.beacon.is_some() => Type::Broadcast,
+        None => Type::Peripheral,
     };
+    le_advertisement.local_name = opt.local_name.clone();
+    le_advertisement.discoverable = Some(opt.discoverable);
+    le_advertisement.duration = duration;
+    le_advertisement.tx_power = opt.tx_power;
+    le_advertisement.min_interval = opt.min_interval.map(Duration::from_millis);
+    le_advertisement.max_interval = opt.max_interval.map(Duration::from_millis);
+    le_advertisement.manufacturer_data.extend(opt.manufacturer_data.iter().map(|md| (md.id, md.data.clone())));
+    le_advertisement.service_data.extend(opt.service_data.iter().map(|sd| (sd.id, sd.data.clone())));
     if opt.verbose {
         eprintln!("{le_advertisement:?}");
     }
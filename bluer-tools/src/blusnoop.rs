@@ -0,0 +1,123 @@
+//! Capture HCI traffic and write it to a btsnoop file.
+
+use bluer::monitor_capture::{BtSnoopWriter, Capture, Direction, Packet, Record, INDEX_NONE};
+use chrono::{DateTime, Local};
+use clap::Parser;
+use futures::StreamExt;
+use std::path::PathBuf;
+use tokio::{
+    fs::File,
+    io::BufWriter,
+    signal::unix::{signal, SignalKind},
+};
+
+type AnyResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[clap(
+    name = "blusnoop",
+    about = "Capture Bluetooth HCI traffic of all controllers, optionally saving it to a btsnoop file \
+             readable by Wireshark and btmon.",
+    author = "Sebastian Urban <surban@surban.net>",
+    version = env!("CARGO_PKG_VERSION"),
+)]
+struct Opt {
+    /// Write captured records to the specified btsnoop file.
+    #[clap(long, short)]
+    write: Option<PathBuf>,
+    /// Only capture traffic of the controller with the specified index,
+    /// e.g. 0 for hci0.
+    #[clap(long, short)]
+    index: Option<u16>,
+    /// Do not print captured records.
+    #[clap(long, short)]
+    quiet: bool,
+    /// Print packet data as hex.
+    #[clap(long, short = 'x')]
+    hex: bool,
+}
+
+fn direction(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Tx => "<",
+        Direction::Rx => ">",
+    }
+}
+
+fn describe(packet: &Packet) -> (String, Option<&[u8]>) {
+    match packet {
+        Packet::NewIndex { controller_type, bus, address, name } => {
+            (format!("= New Index: {address} (type {controller_type}, bus {bus}) [{name}]"), None)
+        }
+        Packet::DeleteIndex => ("= Delete Index".to_string(), None),
+        Packet::OpenIndex => ("= Open Index".to_string(), None),
+        Packet::CloseIndex => ("= Close Index".to_string(), None),
+        Packet::IndexInfo { address, manufacturer } => {
+            (format!("= Index Info: {address} (manufacturer {manufacturer})"), None)
+        }
+        Packet::Command(data) => (format!("< HCI Command ({} bytes)", data.len()), Some(data)),
+        Packet::Event(data) => (format!("> HCI Event ({} bytes)", data.len()), Some(data)),
+        Packet::Acl(dir, data) => (format!("{} ACL Data ({} bytes)", direction(*dir), data.len()), Some(data)),
+        Packet::Sco(dir, data) => (format!("{} SCO Data ({} bytes)", direction(*dir), data.len()), Some(data)),
+        Packet::Iso(dir, data) => (format!("{} ISO Data ({} bytes)", direction(*dir), data.len()), Some(data)),
+        Packet::VendorDiag(data) => (format!("= Vendor Diagnostic ({} bytes)", data.len()), Some(data)),
+        Packet::SystemNote(note) => (format!("= Note: {note}"), None),
+        Packet::UserLogging { ident, message, .. } => (format!("= {ident}: {message}"), None),
+        Packet::Other { opcode, data } => (format!("? Opcode {opcode} ({} bytes)", data.len()), Some(data)),
+        other => (format!("? {other:?}"), None),
+    }
+}
+
+fn print_record(record: &Record, hex: bool) {
+    let time: DateTime<Local> = record.timestamp.into();
+    let index = match record.index {
+        INDEX_NONE => "-".to_string(),
+        index => format!("hci{index}"),
+    };
+    let (desc, data) = describe(&record.packet);
+    println!("{} {index:>6} {desc}", time.format("%H:%M:%S%.6f"));
+    if let (true, Some(data)) = (hex, data) {
+        println!("{}", pretty_hex::pretty_hex(&data));
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> AnyResult<()> {
+    env_logger::init();
+    let opt = Opt::parse();
+
+    let mut sig_term = signal(SignalKind::terminate())?;
+    let mut sig_int = signal(SignalKind::interrupt())?;
+
+    let mut writer = match &opt.write {
+        Some(path) => Some(BtSnoopWriter::new(BufWriter::new(File::create(path).await?)).await?),
+        None => None,
+    };
+
+    let mut capture = Capture::new().await?;
+    loop {
+        tokio::select! {
+            record = capture.next() => {
+                let Some(record) = record else { break };
+                let record = record?;
+                if opt.index.is_some_and(|index| index != record.index && record.index != INDEX_NONE) {
+                    continue;
+                }
+                if !opt.quiet {
+                    print_record(&record, opt.hex);
+                }
+                if let Some(writer) = &mut writer {
+                    writer.write_record(&record).await?;
+                }
+            }
+            _ = sig_term.recv() => break,
+            _ = sig_int.recv() => break,
+        }
+    }
+
+    if let Some(writer) = &mut writer {
+        writer.flush().await?;
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use bluer::{
         RequestAuthorization, RequestConfirmation, RequestPasskey, RequestPinCode,
     },
     gatt::{
+        emulate::Peripheral,
         local::{
             self, characteristic_control, Application, ApplicationHandle, CharacteristicControlEvent,
             CharacteristicNotify, CharacteristicWrite, Service,
@@ -32,6 +33,7 @@ use std::{
     ffi::OsString,
     fmt::{self, Display},
     iter,
+    path::PathBuf,
     process::{exit, Command, Stdio},
     str::FromStr,
     time::Duration,
@@ -40,6 +42,7 @@ use tab_pty_process::AsyncPtyMaster;
 use tokio::{
     io::{stdin, stdout, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     select,
+    signal::unix::{signal, SignalKind},
     sync::oneshot,
     time::{sleep, timeout},
 };
@@ -346,6 +349,8 @@ enum Cmd {
     /// for connections from a remote Bluetooth device and serves a program
     /// once a connection is established.
     Serve(ServeOpts),
+    /// Emulate a peripheral by serving the GATT services described in a file.
+    Emulate(EmulateOpts),
 }
 
 #[derive(Parser)]
@@ -1415,6 +1420,95 @@ impl ServeOpts {
     }
 }
 
+#[derive(Parser)]
+struct EmulateOpts {
+    /// Address of local Bluetooth adapter to use.
+    #[clap(long, short)]
+    bind: Option<Address>,
+    /// Print emulated services to standard error.
+    #[clap(long, short)]
+    verbose: bool,
+    /// Do not send LE advertisement packets.
+    #[clap(long, short = 'a')]
+    no_advertise: bool,
+    /// Local name to advertise.
+    #[clap(long, short)]
+    name: Option<String>,
+    /// Allocate handles automatically instead of using the handles from the file.
+    #[clap(long)]
+    auto_handles: bool,
+    /// Peripheral description in JSON format or, if the file name ends with .toml, in TOML format.
+    ///
+    /// A GATT database snapshot of a device can be used directly.
+    file: PathBuf,
+}
+
+impl EmulateOpts {
+    pub async fn perform(self) -> Result<()> {
+        let mut sig_term = signal(SignalKind::terminate())?;
+        let mut sig_int = signal(SignalKind::interrupt())?;
+
+        let text = tokio::fs::read_to_string(&self.file).await?;
+        let mut peripheral: Peripheral = match self.file.extension() {
+            Some(ext) if ext == "toml" => toml::from_str(&text)?,
+            _ => serde_json::from_str(&text)?,
+        };
+
+        // The Bluetooth daemon provides the GAP and GATT services itself.
+        peripheral.services.retain(|service| {
+            !matches!(
+                id::Service::try_from(service.uuid),
+                Ok(id::Service::GenericAccess | id::Service::GenericAttribute)
+            )
+        });
+        if self.auto_handles {
+            for service in &mut peripheral.services {
+                service.handle = None;
+                for characteristic in &mut service.characteristics {
+                    characteristic.handle = None;
+                    for descriptor in &mut characteristic.descriptors {
+                        descriptor.handle = None;
+                    }
+                }
+            }
+        }
+
+        let (_session, adapter) = get_session_adapter(self.bind).await?;
+        if self.verbose {
+            eprintln!("Emulating on {}", adapter.address().await?);
+            for service in &peripheral.services {
+                eprintln!("Service {}", UuidOrShort(service.uuid));
+                for characteristic in &service.characteristics {
+                    eprintln!(
+                        "    Characteristic {}: {}",
+                        UuidOrShort(characteristic.uuid),
+                        char_flags_to_vec(&characteristic.flags).join(", ")
+                    );
+                }
+            }
+        }
+
+        let le_advertisement = Advertisement {
+            service_uuids: peripheral.services.iter().filter(|s| s.primary).map(|s| s.uuid).collect(),
+            local_name: self.name.clone(),
+            discoverable: Some(true),
+            ..Default::default()
+        };
+        let _adv = if !self.no_advertise { Some(adapter.advertise(le_advertisement).await?) } else { None };
+        let _app = adapter.serve_gatt_application(peripheral.application()).await?;
+
+        if self.verbose {
+            eprintln!("Press <CTRL>-C to stop emulation");
+        }
+        select! {
+            _ = sig_term.recv() => (),
+            _ = sig_int.recv() => (),
+        }
+
+        Ok(())
+    }
+}
+
 async fn make_app(
     adapter: &Adapter, no_advertise: bool, nordic_uart: bool, service: Uuid, characteristic: Uuid,
 ) -> Result<(Option<AdvertisementHandle>, ApplicationHandle, impl Stream<Item = CharacteristicControlEvent>)> {
@@ -1649,6 +1743,7 @@ async fn main() -> Result<()> {
         Cmd::Connect(c) => c.perform().await,
         Cmd::Listen(l) => l.perform().await,
         Cmd::Serve(s) => s.perform().compat().await,
+        Cmd::Emulate(e) => e.perform().await,
     };
 
     match result {
@@ -75,11 +75,31 @@ struct ConnectOpts {
     /// Target RFCOMM profile.
     #[clap(long, short)]
     profile: Option<Uuid>,
+    /// Target service class UUID.
+    ///
+    /// The RFCOMM channel is resolved by querying the SDP server of the target device.
+    #[clap(long, short, conflicts_with_all = ["channel", "profile"])]
+    service: Option<Uuid>,
 }
 
 impl ConnectOpts {
     pub async fn perform(self) -> Result<()> {
-        let stream = match (self.channel, self.profile) {
+        let channel = match self.service {
+            Some(service) => {
+                eprintln!("Querying SDP server...");
+                let mut sdp = bluer::sdp::Client::connect(self.address).await?;
+                match sdp.rfcomm_channel(service).await? {
+                    Some(channel) => {
+                        eprintln!("Service {service} is on RFCOMM channel {channel}");
+                        Some(channel)
+                    }
+                    None => return Err(format!("service {service} has no RFCOMM channel").into()),
+                }
+            }
+            None => self.channel,
+        };
+
+        let stream = match (channel, self.profile) {
             (Some(channel), None) => {
                 let socket = Socket::new()?;
                 let local_sa = match self.bind {
@@ -151,7 +171,7 @@ impl ConnectOpts {
                 }
             }
             _ => {
-                eprintln!("either channel or profile must be specified");
+                eprintln!("either channel, profile or service must be specified");
                 exit(1);
             }
         };
@@ -253,7 +273,7 @@ impl ListenOpts {
                 req.accept()?
             }
             _ => {
-                eprintln!("either channel or profile must be specified");
+                eprintln!("either channel, profile or service must be specified");
                 exit(1);
             }
         };
@@ -349,7 +369,7 @@ impl ServeOpts {
                 eprintln!("Registered profile");
             }
             _ => {
-                eprintln!("either channel or profile must be specified");
+                eprintln!("either channel, profile or service must be specified");
                 exit(1);
             }
         };
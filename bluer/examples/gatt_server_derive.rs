@@ -0,0 +1,94 @@
+//! Serves a Bluetooth GATT application defined by a Rust type.
+
+use bluer::{
+    adv::Advertisement,
+    gatt::local::{gatt_service_impl, Application, GattService, ServiceState},
+};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    time::{interval, sleep},
+};
+
+include!("gatt.inc");
+
+/// Example service.
+#[derive(Debug, GattService)]
+#[gatt_service(uuid = SERVICE_UUID, methods)]
+struct Example {
+    /// Value that is decremented periodically and can be read, written and notified.
+    #[characteristic(uuid = CHARACTERISTIC_UUID, read, write, write_without_response, notify)]
+    value: Vec<u8>,
+    /// Number of times the value has been reset.
+    resets: u32,
+}
+
+#[gatt_service_impl]
+impl Example {
+    /// Resets the value when written.
+    #[characteristic(uuid = "00000000-0000-0000-000f-00dc0de00002", write)]
+    fn reset(&mut self, reset: bool) {
+        if reset {
+            println!("Resetting value");
+            self.value = vec![0x10, 0x01, 0x01, 0x10];
+            self.resets += 1;
+        }
+    }
+
+    /// Number of resets.
+    #[characteristic(uuid = "00000000-0000-0000-000f-00dc0de00003", read, notify)]
+    fn resets(&self) -> u32 {
+        self.resets
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> bluer::Result<()> {
+    env_logger::init();
+    let session = bluer::Session::new().await?;
+    let adapter = session.default_adapter().await?;
+    adapter.set_powered(true).await?;
+
+    println!("Advertising on Bluetooth adapter {} with address {}", adapter.name(), adapter.address().await?);
+    let mut manufacturer_data = BTreeMap::new();
+    manufacturer_data.insert(MANUFACTURER_ID, vec![0x21, 0x22, 0x23, 0x24]);
+    let le_advertisement = Advertisement {
+        service_uuids: vec![SERVICE_UUID].into_iter().collect(),
+        manufacturer_data,
+        discoverable: Some(true),
+        local_name: Some("gatt_server".to_string()),
+        ..Default::default()
+    };
+    let adv_handle = adapter.advertise(le_advertisement).await?;
+
+    println!("Serving GATT service on Bluetooth adapter {}", adapter.name());
+    let example = ServiceState::new(Example { value: vec![0x10, 0x01, 0x01, 0x10], resets: 0 });
+    let app = Application { services: vec![example.service()], ..Default::default() };
+    let app_handle = adapter.serve_gatt_application(app).await?;
+
+    println!("Service ready. Press enter to quit.");
+    let stdin = BufReader::new(tokio::io::stdin());
+    let mut lines = stdin.lines();
+    let mut decrement = interval(Duration::from_secs(5));
+    loop {
+        tokio::select! {
+            _ = lines.next_line() => break,
+            _ = decrement.tick() => {
+                println!("Decrementing each element by one");
+                example.update(|example| {
+                    for v in &mut example.value {
+                        *v = v.saturating_sub(1);
+                    }
+                });
+                println!("State is {example:?}");
+            }
+        }
+    }
+
+    println!("Removing service and advertisement");
+    drop(app_handle);
+    drop(adv_handle);
+    sleep(Duration::from_secs(1)).await;
+
+    Ok(())
+}
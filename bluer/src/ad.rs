@@ -0,0 +1,905 @@
+//! Bluetooth Advertising Data (AD) and Extended Inquiry Response (EIR) data.
+//!
+//! Advertising data consists of a sequence of [AD structures](AdStructure), each
+//! consisting of a length byte, an AD type byte and the data.
+//! The AD types are defined in the Bluetooth Core Specification Supplement, Part A.
+//!
+//! Use [parse] to decode a raw advertising data or EIR buffer and [parse_map] to
+//! decode advertising data as provided by the Bluetooth daemon, for example
+//! by [Device::advertising_data](crate::Device::advertising_data).
+//! Use [encode] to generate raw advertising data.
+
+use std::{collections::BTreeMap, fmt, string::FromUtf8Error};
+use uuid::Uuid;
+
+use crate::{Address, AddressType, UuidExt};
+
+/// Maximum length of advertising data using legacy advertising PDUs.
+pub const MAX_LEGACY_LEN: usize = 31;
+
+/// Maximum length of advertising data using extended advertising PDUs.
+pub const MAX_EXTENDED_LEN: usize = 251;
+
+/// AD type: flags.
+pub const TYPE_FLAGS: u8 = 0x01;
+/// AD type: incomplete list of 16-bit service class UUIDs.
+pub const TYPE_INCOMPLETE_SERVICE_UUIDS_16: u8 = 0x02;
+/// AD type: complete list of 16-bit service class UUIDs.
+pub const TYPE_COMPLETE_SERVICE_UUIDS_16: u8 = 0x03;
+/// AD type: incomplete list of 32-bit service class UUIDs.
+pub const TYPE_INCOMPLETE_SERVICE_UUIDS_32: u8 = 0x04;
+/// AD type: complete list of 32-bit service class UUIDs.
+pub const TYPE_COMPLETE_SERVICE_UUIDS_32: u8 = 0x05;
+/// AD type: incomplete list of 128-bit service class UUIDs.
+pub const TYPE_INCOMPLETE_SERVICE_UUIDS_128: u8 = 0x06;
+/// AD type: complete list of 128-bit service class UUIDs.
+pub const TYPE_COMPLETE_SERVICE_UUIDS_128: u8 = 0x07;
+/// AD type: shortened local name.
+pub const TYPE_SHORTENED_LOCAL_NAME: u8 = 0x08;
+/// AD type: complete local name.
+pub const TYPE_COMPLETE_LOCAL_NAME: u8 = 0x09;
+/// AD type: TX power level.
+pub const TYPE_TX_POWER_LEVEL: u8 = 0x0a;
+/// AD type: class of device.
+pub const TYPE_CLASS_OF_DEVICE: u8 = 0x0d;
+/// AD type: peripheral connection interval range.
+pub const TYPE_PERIPHERAL_CONNECTION_INTERVAL_RANGE: u8 = 0x12;
+/// AD type: list of 16-bit service solicitation UUIDs.
+pub const TYPE_SERVICE_SOLICITATION_16: u8 = 0x14;
+/// AD type: list of 128-bit service solicitation UUIDs.
+pub const TYPE_SERVICE_SOLICITATION_128: u8 = 0x15;
+/// AD type: service data with 16-bit UUID.
+pub const TYPE_SERVICE_DATA_16: u8 = 0x16;
+/// AD type: public target address.
+pub const TYPE_PUBLIC_TARGET_ADDRESS: u8 = 0x17;
+/// AD type: random target address.
+pub const TYPE_RANDOM_TARGET_ADDRESS: u8 = 0x18;
+/// AD type: appearance.
+pub const TYPE_APPEARANCE: u8 = 0x19;
+/// AD type: advertising interval.
+pub const TYPE_ADVERTISING_INTERVAL: u8 = 0x1a;
+/// AD type: LE Bluetooth device address.
+pub const TYPE_LE_DEVICE_ADDRESS: u8 = 0x1b;
+/// AD type: LE role.
+pub const TYPE_LE_ROLE: u8 = 0x1c;
+/// AD type: list of 32-bit service solicitation UUIDs.
+pub const TYPE_SERVICE_SOLICITATION_32: u8 = 0x1f;
+/// AD type: service data with 32-bit UUID.
+pub const TYPE_SERVICE_DATA_32: u8 = 0x20;
+/// AD type: service data with 128-bit UUID.
+pub const TYPE_SERVICE_DATA_128: u8 = 0x21;
+/// AD type: URI.
+pub const TYPE_URI: u8 = 0x24;
+/// AD type: LE supported features.
+pub const TYPE_LE_SUPPORTED_FEATURES: u8 = 0x27;
+/// AD type: advertising interval long.
+pub const TYPE_ADVERTISING_INTERVAL_LONG: u8 = 0x2f;
+/// AD type: broadcast name.
+pub const TYPE_BROADCAST_NAME: u8 = 0x30;
+/// AD type: manufacturer specific data.
+pub const TYPE_MANUFACTURER_SPECIFIC_DATA: u8 = 0xff;
+
+/// URI scheme name string codes.
+///
+/// The empty scheme is encoded as code 0x01 and followed by the complete URI.
+const URI_SCHEMES: &[(char, &str)] = &[('\u{16}', "http:"), ('\u{17}', "https:")];
+
+/// Advertising data error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AdError {
+    /// AD structure at the specified offset exceeds the end of the data.
+    Truncated {
+        /// Offset of the AD structure.
+        offset: usize,
+    },
+    /// Data of the AD structure has an invalid length.
+    InvalidLength {
+        /// AD type.
+        ad_type: u8,
+        /// Length of data.
+        len: usize,
+    },
+    /// Data of the AD structure is not valid UTF-8.
+    InvalidUtf8 {
+        /// AD type.
+        ad_type: u8,
+    },
+    /// UUID cannot be represented in the short form required by the AD structure.
+    InvalidUuid(Uuid),
+    /// Data of the AD structure is too long to be encoded.
+    DataTooLong {
+        /// AD type.
+        ad_type: u8,
+        /// Length of data.
+        len: usize,
+    },
+    /// Encoded advertising data exceeds the maximum length.
+    TooLong {
+        /// Length of the encoded advertising data.
+        len: usize,
+        /// Maximum length.
+        max_len: usize,
+    },
+}
+
+impl fmt::Display for AdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated { offset } => write!(f, "AD structure at offset {offset} is truncated"),
+            Self::InvalidLength { ad_type, len } => {
+                write!(f, "AD structure of type 0x{ad_type:02x} has invalid length {len}")
+            }
+            Self::InvalidUtf8 { ad_type } => write!(f, "AD structure of type 0x{ad_type:02x} is not valid UTF-8"),
+            Self::InvalidUuid(uuid) => write!(f, "UUID {uuid} has no short form"),
+            Self::DataTooLong { ad_type, len } => {
+                write!(f, "AD structure of type 0x{ad_type:02x} with {len} bytes of data is too long")
+            }
+            Self::TooLong { len, max_len } => {
+                write!(f, "advertising data of {len} bytes exceeds maximum length of {max_len} bytes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AdError {}
+
+/// Advertising flags.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flags {
+    /// LE limited discoverable mode.
+    pub le_limited_discoverable: bool,
+    /// LE general discoverable mode.
+    pub le_general_discoverable: bool,
+    /// BR/EDR not supported.
+    pub br_edr_not_supported: bool,
+    /// Simultaneous LE and BR/EDR to same device capable (controller).
+    pub le_br_edr_controller: bool,
+    /// Simultaneous LE and BR/EDR to same device capable (host).
+    pub le_br_edr_host: bool,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl Flags {
+    /// Decodes the flags from their bit representation.
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            le_limited_discoverable: bits & 0x01 != 0,
+            le_general_discoverable: bits & 0x02 != 0,
+            br_edr_not_supported: bits & 0x04 != 0,
+            le_br_edr_controller: bits & 0x08 != 0,
+            le_br_edr_host: bits & 0x10 != 0,
+            _non_exhaustive: (),
+        }
+    }
+
+    /// Encodes the flags into their bit representation.
+    pub fn to_bits(&self) -> u8 {
+        let mut bits = 0;
+        if self.le_limited_discoverable {
+            bits |= 0x01;
+        }
+        if self.le_general_discoverable {
+            bits |= 0x02;
+        }
+        if self.br_edr_not_supported {
+            bits |= 0x04;
+        }
+        if self.le_br_edr_controller {
+            bits |= 0x08;
+        }
+        if self.le_br_edr_host {
+            bits |= 0x10;
+        }
+        bits
+    }
+}
+
+/// LE supported features bit mask.
+///
+/// Bit numbers are defined in the Bluetooth Core Specification, Vol 6, Part B, section 4.6.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeFeatures(pub Vec<u8>);
+
+impl LeFeatures {
+    /// Returns whether the feature with the specified bit number is supported.
+    pub fn is_supported(&self, bit: usize) -> bool {
+        self.0.get(bit / 8).map(|byte| byte & (1 << (bit % 8)) != 0).unwrap_or_default()
+    }
+}
+
+/// AD structure of advertising data or extended inquiry response data.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum AdStructure {
+    /// Flags.
+    Flags(Flags),
+    /// List of 16-bit service class UUIDs.
+    ServiceUuids16 {
+        /// Service class UUIDs.
+        uuids: Vec<Uuid>,
+        /// Whether the list is complete.
+        complete: bool,
+    },
+    /// List of 32-bit service class UUIDs.
+    ServiceUuids32 {
+        /// Service class UUIDs.
+        uuids: Vec<Uuid>,
+        /// Whether the list is complete.
+        complete: bool,
+    },
+    /// List of 128-bit service class UUIDs.
+    ServiceUuids128 {
+        /// Service class UUIDs.
+        uuids: Vec<Uuid>,
+        /// Whether the list is complete.
+        complete: bool,
+    },
+    /// Local name of device.
+    LocalName {
+        /// Name.
+        name: String,
+        /// Whether the name is complete or shortened.
+        complete: bool,
+    },
+    /// Transmitted power level in dBm.
+    TxPowerLevel(i8),
+    /// Class of device.
+    ClassOfDevice(u32),
+    /// Preferred connection interval range of peripheral in units of 1.25 ms.
+    ///
+    /// 0xffff indicates no specific minimum or maximum.
+    PeripheralConnectionIntervalRange {
+        /// Minimum connection interval.
+        min: u16,
+        /// Maximum connection interval.
+        max: u16,
+    },
+    /// List of 16-bit service solicitation UUIDs.
+    ServiceSolicitation16(Vec<Uuid>),
+    /// List of 32-bit service solicitation UUIDs.
+    ServiceSolicitation32(Vec<Uuid>),
+    /// List of 128-bit service solicitation UUIDs.
+    ServiceSolicitation128(Vec<Uuid>),
+    /// Service data with 16-bit UUID.
+    ServiceData16 {
+        /// Service UUID.
+        uuid: Uuid,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Service data with 32-bit UUID.
+    ServiceData32 {
+        /// Service UUID.
+        uuid: Uuid,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Service data with 128-bit UUID.
+    ServiceData128 {
+        /// Service UUID.
+        uuid: Uuid,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Public target addresses.
+    PublicTargetAddress(Vec<Address>),
+    /// Random target addresses.
+    RandomTargetAddress(Vec<Address>),
+    /// External appearance of device.
+    Appearance(u16),
+    /// Advertising interval in units of 0.625 ms.
+    AdvertisingInterval(u16),
+    /// Long advertising interval in units of 0.625 ms.
+    AdvertisingIntervalLong(u32),
+    /// LE Bluetooth device address.
+    LeDeviceAddress {
+        /// Address.
+        address: Address,
+        /// Address type, either [AddressType::LePublic] or [AddressType::LeRandom].
+        address_type: AddressType,
+    },
+    /// LE role.
+    ///
+    /// 0x00: only peripheral role supported,
+    /// 0x01: only central role supported,
+    /// 0x02: peripheral and central role supported, peripheral role preferred,
+    /// 0x03: peripheral and central role supported, central role preferred.
+    LeRole(u8),
+    /// Uniform resource identifier.
+    Uri(String),
+    /// LE supported features.
+    LeSupportedFeatures(LeFeatures),
+    /// Broadcast name.
+    BroadcastName(String),
+    /// Manufacturer specific data.
+    ManufacturerData {
+        /// Company identifier.
+        company_id: u16,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// AD structure of another type.
+    Other {
+        /// AD type.
+        ad_type: u8,
+        /// Data.
+        data: Vec<u8>,
+    },
+}
+
+impl AdStructure {
+    /// Decodes an AD structure from its type and data.
+    pub fn from_raw(ad_type: u8, data: &[u8]) -> Result<Self, AdError> {
+        let invalid_len = || AdError::InvalidLength { ad_type, len: data.len() };
+        let utf8 = |data: &[u8]| {
+            String::from_utf8(data.to_vec()).map_err(|_: FromUtf8Error| AdError::InvalidUtf8 { ad_type })
+        };
+        let uuids16 = || -> Result<Vec<Uuid>, AdError> {
+            if data.len() % 2 != 0 {
+                return Err(invalid_len());
+            }
+            Ok(data.chunks(2).map(|c| Uuid::from_u16(u16::from_le_bytes([c[0], c[1]]))).collect())
+        };
+        let uuids32 = || -> Result<Vec<Uuid>, AdError> {
+            if data.len() % 4 != 0 {
+                return Err(invalid_len());
+            }
+            Ok(data.chunks(4).map(|c| Uuid::from_u32(u32::from_le_bytes([c[0], c[1], c[2], c[3]]))).collect())
+        };
+        let uuids128 = || -> Result<Vec<Uuid>, AdError> {
+            if data.len() % 16 != 0 {
+                return Err(invalid_len());
+            }
+            Ok(data.chunks(16).map(|c| Uuid::from_u128(u128::from_le_bytes(c.try_into().unwrap()))).collect())
+        };
+        let addresses = || -> Result<Vec<Address>, AdError> {
+            if data.len() % 6 != 0 {
+                return Err(invalid_len());
+            }
+            Ok(data.chunks(6).map(address_from_le).collect())
+        };
+
+        let ad = match ad_type {
+            TYPE_FLAGS => Self::Flags(Flags::from_bits(data.first().copied().unwrap_or_default())),
+            TYPE_INCOMPLETE_SERVICE_UUIDS_16 | TYPE_COMPLETE_SERVICE_UUIDS_16 => {
+                Self::ServiceUuids16 { uuids: uuids16()?, complete: ad_type == TYPE_COMPLETE_SERVICE_UUIDS_16 }
+            }
+            TYPE_INCOMPLETE_SERVICE_UUIDS_32 | TYPE_COMPLETE_SERVICE_UUIDS_32 => {
+                Self::ServiceUuids32 { uuids: uuids32()?, complete: ad_type == TYPE_COMPLETE_SERVICE_UUIDS_32 }
+            }
+            TYPE_INCOMPLETE_SERVICE_UUIDS_128 | TYPE_COMPLETE_SERVICE_UUIDS_128 => {
+                Self::ServiceUuids128 { uuids: uuids128()?, complete: ad_type == TYPE_COMPLETE_SERVICE_UUIDS_128 }
+            }
+            TYPE_SHORTENED_LOCAL_NAME | TYPE_COMPLETE_LOCAL_NAME => {
+                Self::LocalName { name: utf8(data)?, complete: ad_type == TYPE_COMPLETE_LOCAL_NAME }
+            }
+            TYPE_TX_POWER_LEVEL => match data {
+                [power] => Self::TxPowerLevel(*power as i8),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_CLASS_OF_DEVICE => match data {
+                [a, b, c] => Self::ClassOfDevice(u32::from_le_bytes([*a, *b, *c, 0])),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_PERIPHERAL_CONNECTION_INTERVAL_RANGE => match data {
+                [a, b, c, d] => Self::PeripheralConnectionIntervalRange {
+                    min: u16::from_le_bytes([*a, *b]),
+                    max: u16::from_le_bytes([*c, *d]),
+                },
+                _ => return Err(invalid_len()),
+            },
+            TYPE_SERVICE_SOLICITATION_16 => Self::ServiceSolicitation16(uuids16()?),
+            TYPE_SERVICE_SOLICITATION_32 => Self::ServiceSolicitation32(uuids32()?),
+            TYPE_SERVICE_SOLICITATION_128 => Self::ServiceSolicitation128(uuids128()?),
+            TYPE_SERVICE_DATA_16 if data.len() >= 2 => Self::ServiceData16 {
+                uuid: Uuid::from_u16(u16::from_le_bytes([data[0], data[1]])),
+                data: data[2..].to_vec(),
+            },
+            TYPE_SERVICE_DATA_32 if data.len() >= 4 => Self::ServiceData32 {
+                uuid: Uuid::from_u32(u32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                data: data[4..].to_vec(),
+            },
+            TYPE_SERVICE_DATA_128 if data.len() >= 16 => Self::ServiceData128 {
+                uuid: Uuid::from_u128(u128::from_le_bytes(data[..16].try_into().unwrap())),
+                data: data[16..].to_vec(),
+            },
+            TYPE_SERVICE_DATA_16 | TYPE_SERVICE_DATA_32 | TYPE_SERVICE_DATA_128 => return Err(invalid_len()),
+            TYPE_PUBLIC_TARGET_ADDRESS => Self::PublicTargetAddress(addresses()?),
+            TYPE_RANDOM_TARGET_ADDRESS => Self::RandomTargetAddress(addresses()?),
+            TYPE_APPEARANCE => match data {
+                [a, b] => Self::Appearance(u16::from_le_bytes([*a, *b])),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_ADVERTISING_INTERVAL => match data {
+                [a, b] => Self::AdvertisingInterval(u16::from_le_bytes([*a, *b])),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_ADVERTISING_INTERVAL_LONG => match data {
+                [a, b, c] => Self::AdvertisingIntervalLong(u32::from_le_bytes([*a, *b, *c, 0])),
+                [a, b, c, d] => Self::AdvertisingIntervalLong(u32::from_le_bytes([*a, *b, *c, *d])),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_LE_DEVICE_ADDRESS => match data {
+                [addr @ .., ty] if addr.len() == 6 => Self::LeDeviceAddress {
+                    address: address_from_le(addr),
+                    address_type: if ty & 0x01 != 0 { AddressType::LeRandom } else { AddressType::LePublic },
+                },
+                _ => return Err(invalid_len()),
+            },
+            TYPE_LE_ROLE => match data {
+                [role] => Self::LeRole(*role),
+                _ => return Err(invalid_len()),
+            },
+            TYPE_URI => {
+                let uri = utf8(data)?;
+                let mut chars = uri.chars();
+                match chars.next() {
+                    Some('\u{1}') => Self::Uri(chars.as_str().to_string()),
+                    Some(code) => match URI_SCHEMES.iter().find(|(c, _)| *c == code) {
+                        Some((_, scheme)) => Self::Uri(format!("{scheme}{}", chars.as_str())),
+                        None => Self::Other { ad_type, data: data.to_vec() },
+                    },
+                    None => return Err(invalid_len()),
+                }
+            }
+            TYPE_LE_SUPPORTED_FEATURES => Self::LeSupportedFeatures(LeFeatures(data.to_vec())),
+            TYPE_BROADCAST_NAME => Self::BroadcastName(utf8(data)?),
+            TYPE_MANUFACTURER_SPECIFIC_DATA if data.len() >= 2 => Self::ManufacturerData {
+                company_id: u16::from_le_bytes([data[0], data[1]]),
+                data: data[2..].to_vec(),
+            },
+            TYPE_MANUFACTURER_SPECIFIC_DATA => return Err(invalid_len()),
+            _ => Self::Other { ad_type, data: data.to_vec() },
+        };
+        Ok(ad)
+    }
+
+    /// Encodes the AD structure into its type and data.
+    pub fn to_raw(&self) -> Result<(u8, Vec<u8>), AdError> {
+        let mut data = Vec::new();
+        match self {
+            Self::Flags(flags) => {
+                data.push(flags.to_bits());
+            }
+            Self::ServiceUuids16 { uuids, .. } => {
+                put_uuids16(&mut data, uuids)?;
+            }
+            Self::ServiceUuids32 { uuids, .. } => {
+                put_uuids32(&mut data, uuids)?;
+            }
+            Self::ServiceUuids128 { uuids, .. } => {
+                put_uuids128(&mut data, uuids);
+            }
+            Self::LocalName { name, .. } => {
+                data.extend_from_slice(name.as_bytes());
+            }
+            Self::TxPowerLevel(power) => {
+                data.push(*power as u8);
+            }
+            Self::ClassOfDevice(class) => {
+                data.extend_from_slice(&class.to_le_bytes()[..3]);
+            }
+            Self::PeripheralConnectionIntervalRange { min, max } => {
+                data.extend_from_slice(&min.to_le_bytes());
+                data.extend_from_slice(&max.to_le_bytes());
+            }
+            Self::ServiceSolicitation16(uuids) => {
+                put_uuids16(&mut data, uuids)?;
+            }
+            Self::ServiceSolicitation32(uuids) => {
+                put_uuids32(&mut data, uuids)?;
+            }
+            Self::ServiceSolicitation128(uuids) => {
+                put_uuids128(&mut data, uuids);
+            }
+            Self::ServiceData16 { uuid, data: svc_data } => {
+                put_uuids16(&mut data, &[*uuid])?;
+                data.extend_from_slice(svc_data);
+            }
+            Self::ServiceData32 { uuid, data: svc_data } => {
+                put_uuids32(&mut data, &[*uuid])?;
+                data.extend_from_slice(svc_data);
+            }
+            Self::ServiceData128 { uuid, data: svc_data } => {
+                put_uuids128(&mut data, &[*uuid]);
+                data.extend_from_slice(svc_data);
+            }
+            Self::PublicTargetAddress(addrs) => {
+                addrs.iter().for_each(|addr| put_address(&mut data, addr));
+            }
+            Self::RandomTargetAddress(addrs) => {
+                addrs.iter().for_each(|addr| put_address(&mut data, addr));
+            }
+            Self::Appearance(appearance) => {
+                data.extend_from_slice(&appearance.to_le_bytes());
+            }
+            Self::AdvertisingInterval(interval) => {
+                data.extend_from_slice(&interval.to_le_bytes());
+            }
+            Self::AdvertisingIntervalLong(interval) => {
+                let bytes = interval.to_le_bytes();
+                if bytes[3] == 0 {
+                    data.extend_from_slice(&bytes[..3]);
+                } else {
+                    data.extend_from_slice(&bytes);
+                }
+            }
+            Self::LeDeviceAddress { address, address_type } => {
+                put_address(&mut data, address);
+                data.push(if *address_type == AddressType::LeRandom { 0x01 } else { 0x00 });
+            }
+            Self::LeRole(role) => {
+                data.push(*role);
+            }
+            Self::Uri(uri) => match URI_SCHEMES.iter().find(|(_, scheme)| uri.starts_with(scheme)) {
+                Some((code, scheme)) => {
+                    data.extend_from_slice(code.to_string().as_bytes());
+                    data.extend_from_slice(&uri.as_bytes()[scheme.len()..]);
+                }
+                None => {
+                    data.push(0x01);
+                    data.extend_from_slice(uri.as_bytes());
+                }
+            },
+            Self::LeSupportedFeatures(features) => {
+                data.extend_from_slice(&features.0);
+            }
+            Self::BroadcastName(name) => {
+                data.extend_from_slice(name.as_bytes());
+            }
+            Self::ManufacturerData { company_id, data: mfr_data } => {
+                data.extend_from_slice(&company_id.to_le_bytes());
+                data.extend_from_slice(mfr_data);
+            }
+            Self::Other { data: other_data, .. } => {
+                data.extend_from_slice(other_data);
+            }
+        }
+
+        let ad_type = self.ad_type();
+
+        if data.len() > u8::MAX as usize - 1 {
+            return Err(AdError::DataTooLong { ad_type, len: data.len() });
+        }
+        Ok((ad_type, data))
+    }
+
+    /// AD type.
+    pub fn ad_type(&self) -> u8 {
+        match self {
+            Self::Flags(_) => TYPE_FLAGS,
+            Self::ServiceUuids16 { complete: true, .. } => TYPE_COMPLETE_SERVICE_UUIDS_16,
+            Self::ServiceUuids16 { complete: false, .. } => TYPE_INCOMPLETE_SERVICE_UUIDS_16,
+            Self::ServiceUuids32 { complete: true, .. } => TYPE_COMPLETE_SERVICE_UUIDS_32,
+            Self::ServiceUuids32 { complete: false, .. } => TYPE_INCOMPLETE_SERVICE_UUIDS_32,
+            Self::ServiceUuids128 { complete: true, .. } => TYPE_COMPLETE_SERVICE_UUIDS_128,
+            Self::ServiceUuids128 { complete: false, .. } => TYPE_INCOMPLETE_SERVICE_UUIDS_128,
+            Self::LocalName { complete: true, .. } => TYPE_COMPLETE_LOCAL_NAME,
+            Self::LocalName { complete: false, .. } => TYPE_SHORTENED_LOCAL_NAME,
+            Self::TxPowerLevel(_) => TYPE_TX_POWER_LEVEL,
+            Self::ClassOfDevice(_) => TYPE_CLASS_OF_DEVICE,
+            Self::PeripheralConnectionIntervalRange { .. } => TYPE_PERIPHERAL_CONNECTION_INTERVAL_RANGE,
+            Self::ServiceSolicitation16(_) => TYPE_SERVICE_SOLICITATION_16,
+            Self::ServiceSolicitation32(_) => TYPE_SERVICE_SOLICITATION_32,
+            Self::ServiceSolicitation128(_) => TYPE_SERVICE_SOLICITATION_128,
+            Self::ServiceData16 { .. } => TYPE_SERVICE_DATA_16,
+            Self::ServiceData32 { .. } => TYPE_SERVICE_DATA_32,
+            Self::ServiceData128 { .. } => TYPE_SERVICE_DATA_128,
+            Self::PublicTargetAddress(_) => TYPE_PUBLIC_TARGET_ADDRESS,
+            Self::RandomTargetAddress(_) => TYPE_RANDOM_TARGET_ADDRESS,
+            Self::Appearance(_) => TYPE_APPEARANCE,
+            Self::AdvertisingInterval(_) => TYPE_ADVERTISING_INTERVAL,
+            Self::AdvertisingIntervalLong(_) => TYPE_ADVERTISING_INTERVAL_LONG,
+            Self::LeDeviceAddress { .. } => TYPE_LE_DEVICE_ADDRESS,
+            Self::LeRole(_) => TYPE_LE_ROLE,
+            Self::Uri(_) => TYPE_URI,
+            Self::LeSupportedFeatures(_) => TYPE_LE_SUPPORTED_FEATURES,
+            Self::BroadcastName(_) => TYPE_BROADCAST_NAME,
+            Self::ManufacturerData { .. } => TYPE_MANUFACTURER_SPECIFIC_DATA,
+            Self::Other { ad_type, .. } => *ad_type,
+        }
+    }
+
+    /// Length of the encoded AD structure in bytes, including its length and type bytes.
+    pub fn encoded_len(&self) -> Result<usize, AdError> {
+        let (_, data) = self.to_raw()?;
+        Ok(2 + data.len())
+    }
+
+    /// Appends the encoded AD structure to the buffer.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), AdError> {
+        let (ad_type, data) = self.to_raw()?;
+        buf.push(data.len() as u8 + 1);
+        buf.push(ad_type);
+        buf.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+/// Parses raw advertising data or extended inquiry response data into AD structures.
+///
+/// Parsing stops at the first AD structure of zero length, since the remainder
+/// of the data is padding.
+pub fn parse(data: &[u8]) -> Result<Vec<AdStructure>, AdError> {
+    let mut structures = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let len = data[offset] as usize;
+        if len == 0 {
+            break;
+        }
+        let Some(structure) = data.get(offset + 1..offset + 1 + len) else {
+            return Err(AdError::Truncated { offset });
+        };
+        structures.push(AdStructure::from_raw(structure[0], &structure[1..])?);
+        offset += 1 + len;
+    }
+    Ok(structures)
+}
+
+/// Parses advertising data given as a map from AD type to data into AD structures.
+///
+/// This is the representation used by the Bluetooth daemon, for example
+/// by [Device::advertising_data](crate::Device::advertising_data).
+pub fn parse_map<'a>(map: impl IntoIterator<Item = (&'a u8, &'a Vec<u8>)>) -> Result<Vec<AdStructure>, AdError> {
+    let map: BTreeMap<_, _> = map.into_iter().collect();
+    map.into_iter().map(|(ad_type, data)| AdStructure::from_raw(*ad_type, data)).collect()
+}
+
+/// Encodes AD structures into raw advertising data.
+pub fn encode(structures: &[AdStructure]) -> Result<Vec<u8>, AdError> {
+    let mut buf = Vec::new();
+    for structure in structures {
+        structure.encode_into(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// Calculates the length of the encoded AD structures and checks it against the
+/// specified maximum length, usually [MAX_LEGACY_LEN] or [MAX_EXTENDED_LEN].
+pub fn check_len(structures: &[AdStructure], max_len: usize) -> Result<usize, AdError> {
+    let mut len = 0;
+    for structure in structures {
+        len += structure.encoded_len()?;
+    }
+    if len > max_len {
+        return Err(AdError::TooLong { len, max_len });
+    }
+    Ok(len)
+}
+
+fn address_from_le(data: &[u8]) -> Address {
+    let mut addr: [u8; 6] = data.try_into().unwrap();
+    addr.reverse();
+    Address(addr)
+}
+
+fn put_address(data: &mut Vec<u8>, addr: &Address) {
+    data.extend(addr.0.iter().rev());
+}
+
+fn put_uuids16(data: &mut Vec<u8>, uuids: &[Uuid]) -> Result<(), AdError> {
+    for uuid in uuids {
+        let short = uuid.as_u16().ok_or(AdError::InvalidUuid(*uuid))?;
+        data.extend_from_slice(&short.to_le_bytes());
+    }
+    Ok(())
+}
+
+fn put_uuids32(data: &mut Vec<u8>, uuids: &[Uuid]) -> Result<(), AdError> {
+    for uuid in uuids {
+        let short = uuid.as_u32().ok_or(AdError::InvalidUuid(*uuid))?;
+        data.extend_from_slice(&short.to_le_bytes());
+    }
+    Ok(())
+}
+
+fn put_uuids128(data: &mut Vec<u8>, uuids: &[Uuid]) {
+    for uuid in uuids {
+        data.extend_from_slice(&uuid.as_u128().to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Advertising data of an iBeacon with proximity UUID e2c56db5-dffb-48d2-b060-d0f5a71096e0,
+    /// major 1, minor 2 and measured power -59 dBm.
+    #[rustfmt::skip]
+    const IBEACON: [u8; 30] = [
+        0x02, 0x01, 0x06,
+        0x1a, 0xff, 0x4c, 0x00, 0x02, 0x15,
+        0xe2, 0xc5, 0x6d, 0xb5, 0xdf, 0xfb, 0x48, 0xd2, 0xb0, 0x60, 0xd0, 0xf5, 0xa7, 0x10, 0x96, 0xe0,
+        0x00, 0x01, 0x00, 0x02, 0xc5,
+    ];
+
+    /// Advertising data of an Eddystone-URL beacon for https://example.com.
+    #[rustfmt::skip]
+    const EDDYSTONE_URL: [u8; 23] = [
+        0x03, 0x03, 0xaa, 0xfe,
+        0x0e, 0x16, 0xaa, 0xfe, 0x10, 0xeb, 0x03, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x07,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Extended inquiry response of a headset.
+    #[rustfmt::skip]
+    const EIR: [u8; 31] = [
+        0x08, 0x09, b'H', b'e', b'a', b'd', b's', b'e', b't',
+        0x07, 0x03, 0x0b, 0x11, 0x0e, 0x11, 0x1e, 0x11,
+        0x02, 0x0a, 0xf8,
+        0x04, 0x0d, 0x04, 0x04, 0x24,
+        0x03, 0x19, 0x41, 0x09,
+        0x02, 0x1c,
+    ];
+
+    fn roundtrip(data: &[u8]) -> Vec<AdStructure> {
+        let structures = parse(data).unwrap();
+        assert_eq!(encode(&structures).unwrap(), data);
+        structures
+    }
+
+    #[test]
+    fn ibeacon() {
+        let structures = roundtrip(&IBEACON);
+        assert_eq!(
+            structures,
+            [
+                AdStructure::Flags(Flags {
+                    le_general_discoverable: true,
+                    br_edr_not_supported: true,
+                    ..Default::default()
+                }),
+                AdStructure::ManufacturerData { company_id: 0x004c, data: IBEACON[7..].to_vec() },
+            ]
+        );
+        assert_eq!(check_len(&structures, MAX_LEGACY_LEN), Ok(30));
+    }
+
+    #[test]
+    fn eddystone_url() {
+        let structures = parse(&EDDYSTONE_URL).unwrap();
+        assert_eq!(
+            structures,
+            [
+                AdStructure::ServiceUuids16 { uuids: vec![Uuid::from_u16(0xfeaa)], complete: true },
+                AdStructure::ServiceData16 { uuid: Uuid::from_u16(0xfeaa), data: EDDYSTONE_URL[8..19].to_vec() },
+            ]
+        );
+        assert_eq!(encode(&structures).unwrap(), EDDYSTONE_URL[..19]);
+    }
+
+    #[test]
+    fn eir() {
+        let mut data = EIR.to_vec();
+        data.push(0x02);
+        data.resize(240, 0);
+        let structures = parse(&data).unwrap();
+        assert_eq!(
+            structures,
+            [
+                AdStructure::LocalName { name: "Headset".to_string(), complete: true },
+                AdStructure::ServiceUuids16 {
+                    uuids: vec![Uuid::from_u16(0x110b), Uuid::from_u16(0x110e), Uuid::from_u16(0x111e)],
+                    complete: true,
+                },
+                AdStructure::TxPowerLevel(-8),
+                AdStructure::ClassOfDevice(0x240404),
+                AdStructure::Appearance(0x0941),
+                AdStructure::LeRole(0x02),
+            ]
+        );
+        assert_eq!(encode(&structures).unwrap(), data[..32]);
+    }
+
+    #[test]
+    fn uuids() {
+        let uuid128 = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        #[rustfmt::skip]
+        let data = [
+            0x05, 0x04, 0x78, 0x56, 0x34, 0x12,
+            0x11, 0x07, 0x9e, 0xca, 0xdc, 0x24, 0x0e, 0xe5, 0xa9, 0xe0, 0x93, 0xf3, 0xa3, 0xb5, 0x01, 0x00, 0x40, 0x6e,
+            0x03, 0x14, 0x0d, 0x18,
+        ];
+        let structures = roundtrip(&data);
+        assert_eq!(
+            structures,
+            [
+                AdStructure::ServiceUuids32 { uuids: vec![Uuid::from_u32(0x12345678)], complete: false },
+                AdStructure::ServiceUuids128 { uuids: vec![uuid128], complete: true },
+                AdStructure::ServiceSolicitation16(vec![Uuid::from_u16(0x180d)]),
+            ]
+        );
+
+        let invalid = AdStructure::ServiceUuids16 { uuids: vec![uuid128], complete: true };
+        assert_eq!(encode(&[invalid]), Err(AdError::InvalidUuid(uuid128)));
+    }
+
+    #[test]
+    fn addresses() {
+        let address = Address::new([0x00, 0x1a, 0x7d, 0xda, 0x71, 0x13]);
+        #[rustfmt::skip]
+        let data = [
+            0x08, 0x1b, 0x13, 0x71, 0xda, 0x7d, 0x1a, 0x00, 0x01,
+            0x07, 0x17, 0x13, 0x71, 0xda, 0x7d, 0x1a, 0x00,
+        ];
+        assert_eq!(
+            roundtrip(&data),
+            [
+                AdStructure::LeDeviceAddress { address, address_type: AddressType::LeRandom },
+                AdStructure::PublicTargetAddress(vec![address]),
+            ]
+        );
+    }
+
+    #[test]
+    fn uri() {
+        let mut data = vec![0x15, 0x24, 0x17];
+        data.extend_from_slice(b"//www.bluetooth.org");
+        data.extend_from_slice(&[0x0c, 0x24, 0x01]);
+        data.extend_from_slice(b"tel:123456");
+        assert_eq!(
+            roundtrip(&data),
+            [
+                AdStructure::Uri("https://www.bluetooth.org".to_string()),
+                AdStructure::Uri("tel:123456".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn intervals() {
+        #[rustfmt::skip]
+        let data = [
+            0x05, 0x12, 0x06, 0x00, 0x80, 0x0c,
+            0x03, 0x1a, 0x20, 0x03,
+            0x04, 0x2f, 0x00, 0x00, 0x01,
+            0x05, 0x2f, 0x00, 0x00, 0x00, 0x01,
+        ];
+        assert_eq!(
+            roundtrip(&data),
+            [
+                AdStructure::PeripheralConnectionIntervalRange { min: 0x0006, max: 0x0c80 },
+                AdStructure::AdvertisingInterval(800),
+                AdStructure::AdvertisingIntervalLong(0x010000),
+                AdStructure::AdvertisingIntervalLong(0x01000000),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_map_sorted() {
+        let map: BTreeMap<u8, Vec<u8>> =
+            [(0xff, vec![0x59, 0x00, 0x01]), (0x01, vec![0x06])].into_iter().collect();
+        let structures = parse_map(&map).unwrap();
+        assert_eq!(structures[0].ad_type(), TYPE_FLAGS);
+        assert_eq!(structures[1], AdStructure::ManufacturerData { company_id: 0x0059, data: vec![0x01] });
+    }
+
+    #[test]
+    fn other() {
+        let data = [0x04, 0x2d, 0x01, 0x02, 0x03];
+        assert_eq!(roundtrip(&data), [AdStructure::Other { ad_type: 0x2d, data: vec![0x01, 0x02, 0x03] }]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse(&[0x02, 0x01, 0x06, 0x05, 0x09, b'a']), Err(AdError::Truncated { offset: 3 }));
+        assert_eq!(parse(&[0x02, 0x0a]), Err(AdError::Truncated { offset: 0 }));
+        assert_eq!(parse(&[0x04, 0x03, 0x0d, 0x18, 0x0f]), Err(AdError::InvalidLength { ad_type: 0x03, len: 3 }));
+        assert_eq!(parse(&[0x02, 0xff, 0x4c]), Err(AdError::InvalidLength { ad_type: 0xff, len: 1 }));
+        assert_eq!(parse(&[0x03, 0x09, 0xc3, 0x28]), Err(AdError::InvalidUtf8 { ad_type: 0x09 }));
+
+        let long = AdStructure::ManufacturerData { company_id: 0xffff, data: vec![0; 253] };
+        assert_eq!(long.to_raw(), Err(AdError::DataTooLong { ad_type: 0xff, len: 255 }));
+
+        let name = AdStructure::LocalName { name: "x".repeat(30), complete: true };
+        assert_eq!(check_len(&[name], MAX_LEGACY_LEN), Err(AdError::TooLong { len: 32, max_len: 31 }));
+    }
+}
@@ -25,8 +25,9 @@ use crate::{
     adv::{Advertisement, AdvertisementHandle, Capabilities, Feature, PlatformFeature, SecondaryChannel},
     all_dbus_objects, device,
     device::Device,
-    gatt,
+    gatt, media,
     monitor::MonitorManager,
+    network::NetworkServer,
     Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result, SessionInner,
     SingleSessionToken, SERVICE_NAME, TIMEOUT,
 };
@@ -303,6 +304,9 @@ impl Adapter {
     ///
     /// InvalidLength error indicates that the data
     /// provided generates a data packet which is too long.
+    /// This is checked using [Advertisement::check_len] before registration
+    /// against the [maximum advertisement length](Self::supported_advertising_capabilities)
+    /// of the controller, when it is reported by the Bluetooth daemon.
     ///
     /// The properties of this object are parsed when it is
     /// registered, and any changes are ignored.
@@ -315,6 +319,11 @@ impl Adapter {
     ///
     /// Drop the returned [AdvertisementHandle] to unregister the advertisement.
     pub async fn advertise(&self, le_advertisement: Advertisement) -> Result<AdvertisementHandle> {
+        if let Ok(Some(caps)) = self.supported_advertising_capabilities().await {
+            le_advertisement
+                .check_len(caps.max_advertisement_length.into())
+                .map_err(|err| Error { kind: ErrorKind::InvalidLength, message: err.to_string() })?;
+        }
         le_advertisement.register(self.inner.clone(), self.name.clone()).await
     }
 
@@ -344,6 +353,35 @@ impl Adapter {
         gatt_profile.register(self.inner.clone(), self.name.clone()).await
     }
 
+    /// Registers a local media endpoint.
+    ///
+    /// The endpoint is used by the Bluetooth daemon to negotiate the codec configuration
+    /// of audio streams with remote devices.
+    ///
+    /// Drop the returned [EndpointHandle](media::endpoint::EndpointHandle) to unregister the endpoint.
+    pub async fn register_media_endpoint(
+        &self, endpoint: media::endpoint::Endpoint,
+    ) -> Result<media::endpoint::EndpointHandle> {
+        endpoint.register(self.inner.clone(), self.name.clone()).await
+    }
+
+    /// Registers a local media player.
+    ///
+    /// The player can then be controlled by remote devices through AVRCP.
+    ///
+    /// Drop the returned [PlayerHandle](media::local::PlayerHandle) to unregister the player.
+    pub async fn register_media_player(
+        &self, player: media::local::Player,
+    ) -> Result<media::local::PlayerHandle> {
+        player.register(self.inner.clone(), self.name.clone()).await
+    }
+
+    /// Network server for offering personal area networking (PAN) services
+    /// to remote devices.
+    pub fn network_server(&self) -> NetworkServer {
+        NetworkServer::new(self.inner.clone(), self.name.clone())
+    }
+
     // ===========================================================================================
     // Methods
     // ===========================================================================================
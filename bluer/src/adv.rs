@@ -15,7 +15,10 @@ use std::{
 use strum::{Display, EnumString};
 use uuid::Uuid;
 
-use crate::{read_dict, Adapter, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+use crate::{
+    ad::{self, AdError, AdStructure},
+    read_dict, Adapter, Result, SessionInner, UuidExt, SERVICE_NAME, TIMEOUT,
+};
 
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.LEAdvertisingManager1";
 pub(crate) const ADVERTISEMENT_INTERFACE: &str = "org.bluez.LEAdvertisement1";
@@ -236,7 +239,133 @@ pub struct Advertisement {
     pub _non_exhaustive: (),
 }
 
+/// Splits UUIDs into those representable in 16-bit, 32-bit and only in 128-bit form.
+fn split_uuids(uuids: &BTreeSet<Uuid>) -> (Vec<Uuid>, Vec<Uuid>, Vec<Uuid>) {
+    let (mut uuids16, mut uuids32, mut uuids128) = (Vec::new(), Vec::new(), Vec::new());
+    for uuid in uuids {
+        match (uuid.as_u16(), uuid.as_u32()) {
+            (Some(_), _) => uuids16.push(*uuid),
+            (None, Some(_)) => uuids32.push(*uuid),
+            (None, None) => uuids128.push(*uuid),
+        }
+    }
+    (uuids16, uuids32, uuids128)
+}
+
 impl Advertisement {
+    /// Advertising data structures the Bluetooth daemon generates from this advertisement.
+    ///
+    /// Flags are included whenever the Bluetooth daemon may add them, i.e. for
+    /// peripheral advertisements and when discoverable is set.
+    /// A local name requested through [system_includes](Self::system_includes) is not
+    /// included, since the adapter name is not known.
+    pub fn ad_structures(&self) -> Vec<AdStructure> {
+        let mut structures = self.generated_ad_structures();
+        for (ad_type, data) in &self.advertising_data {
+            structures.push(
+                AdStructure::from_raw(*ad_type, data)
+                    .unwrap_or_else(|_| AdStructure::Other { ad_type: *ad_type, data: data.clone() }),
+            );
+        }
+        structures
+    }
+
+    /// AD structures generated from the fields of this advertisement, excluding the raw advertising data.
+    fn generated_ad_structures(&self) -> Vec<AdStructure> {
+        let mut structures = Vec::new();
+
+        if self.advertisement_type == Type::Peripheral || self.discoverable == Some(true) {
+            structures.push(AdStructure::Flags(ad::Flags {
+                le_general_discoverable: true,
+                br_edr_not_supported: true,
+                ..Default::default()
+            }));
+        }
+
+        let (uuids16, uuids32, uuids128) = split_uuids(&self.service_uuids);
+        if !uuids16.is_empty() {
+            structures.push(AdStructure::ServiceUuids16 { uuids: uuids16, complete: true });
+        }
+        if !uuids32.is_empty() {
+            structures.push(AdStructure::ServiceUuids32 { uuids: uuids32, complete: true });
+        }
+        if !uuids128.is_empty() {
+            structures.push(AdStructure::ServiceUuids128 { uuids: uuids128, complete: true });
+        }
+
+        let (uuids16, uuids32, uuids128) = split_uuids(&self.solicit_uuids);
+        if !uuids16.is_empty() {
+            structures.push(AdStructure::ServiceSolicitation16(uuids16));
+        }
+        if !uuids32.is_empty() {
+            structures.push(AdStructure::ServiceSolicitation32(uuids32));
+        }
+        if !uuids128.is_empty() {
+            structures.push(AdStructure::ServiceSolicitation128(uuids128));
+        }
+
+        for (company_id, data) in &self.manufacturer_data {
+            structures.push(AdStructure::ManufacturerData { company_id: *company_id, data: data.clone() });
+        }
+
+        for (uuid, data) in &self.service_data {
+            let data = data.clone();
+            structures.push(match (uuid.as_u16(), uuid.as_u32()) {
+                (Some(_), _) => AdStructure::ServiceData16 { uuid: *uuid, data },
+                (None, Some(_)) => AdStructure::ServiceData32 { uuid: *uuid, data },
+                (None, None) => AdStructure::ServiceData128 { uuid: *uuid, data },
+            });
+        }
+
+        if let Some(name) = &self.local_name {
+            structures.push(AdStructure::LocalName { name: name.clone(), complete: true });
+        }
+
+        if let Some(appearance) = self.appearance {
+            structures.push(AdStructure::Appearance(appearance));
+        } else if self.system_includes.contains(&Feature::Appearance) {
+            structures.push(AdStructure::Appearance(0));
+        }
+
+        if self.system_includes.contains(&Feature::TxPower) {
+            structures.push(AdStructure::TxPowerLevel(self.tx_power.unwrap_or_default() as i8));
+        }
+
+        structures
+    }
+
+    /// Checks that the advertising data fits into the specified maximum length and returns its length.
+    ///
+    /// The maximum length supported by the controller is reported by the Bluetooth daemon
+    /// in [Capabilities::max_advertisement_length].
+    /// Flags, TX power level and appearance added by the Bluetooth daemon are taken into account,
+    /// see [ad_structures](Self::ad_structures).
+    /// The local name is not taken into account, since the Bluetooth daemon
+    /// shortens it to fit into the remaining space.
+    /// Raw [advertising data](Self::advertising_data) is counted as given,
+    /// since the Bluetooth daemon passes it on unmodified.
+    ///
+    /// [Adapter::advertise] performs this check before registration, if the
+    /// Bluetooth daemon reports the supported advertising capabilities.
+    pub fn check_len(&self, max_len: usize) -> std::result::Result<usize, AdError> {
+        let mut len = 0;
+        for structure in self.generated_ad_structures() {
+            if !matches!(structure, AdStructure::LocalName { .. }) {
+                len += structure.encoded_len()?;
+            }
+        }
+        for (ad_type, data) in &self.advertising_data {
+            if data.len() > u8::MAX as usize - 1 {
+                return Err(AdError::DataTooLong { ad_type: *ad_type, len: data.len() });
+            }
+            len += 2 + data.len();
+        }
+        if len > max_len {
+            return Err(AdError::TooLong { len, max_len });
+        }
+        Ok(len)
+    }
+
     pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Self> {
         cr.register(ADVERTISEMENT_INTERFACE, |ib: &mut IfaceBuilder<Self>| {
             cr_property!(ib, "Type", la => {
@@ -305,10 +434,21 @@ impl Advertisement {
         }
 
         log::trace!("Registering advertisement at {}", &name);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        let () =
-            proxy.method_call(MANAGER_INTERFACE, "RegisterAdvertisement", (name.clone(), PropMap::new())).await?;
+        let manager_path = Adapter::dbus_path(&adapter_name)?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_name = name.clone();
+        inner
+            .register_with_daemon(&name, Some(&adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_name = reg_name.clone();
+                async move {
+                    let () = proxy
+                        .method_call(MANAGER_INTERFACE, "RegisterAdvertisement", (reg_name, PropMap::new()))
+                        .await?;
+                    Ok(())
+                }
+            })
+            .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
         let unreg_name = name.clone();
@@ -316,6 +456,7 @@ impl Advertisement {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering advertisement at {}", &unreg_name);
+            inner.unregister_with_daemon(&unreg_name).await;
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterAdvertisement", (unreg_name.clone(),)).await;
 
@@ -348,3 +489,47 @@ impl fmt::Debug for AdvertisementHandle {
         write!(f, "AdvertisementHandle {{ {} }}", &self.name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ad_structures() {
+        let adv = Advertisement {
+            advertisement_type: Type::Broadcast,
+            service_uuids: [Uuid::from_u16(0x180d), Uuid::from_u32(0x12345678), Uuid::from_u128(1)]
+                .into_iter()
+                .collect(),
+            solicit_uuids: [Uuid::from_u16(0x180f), Uuid::from_u16(0x1812)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            adv.ad_structures(),
+            vec![
+                AdStructure::ServiceUuids16 { uuids: vec![Uuid::from_u16(0x180d)], complete: true },
+                AdStructure::ServiceUuids32 { uuids: vec![Uuid::from_u32(0x12345678)], complete: true },
+                AdStructure::ServiceUuids128 { uuids: vec![Uuid::from_u128(1)], complete: true },
+                AdStructure::ServiceSolicitation16(vec![Uuid::from_u16(0x180f), Uuid::from_u16(0x1812)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_len_raw_data() {
+        // Flags with a length of two would be re-encoded into a single byte.
+        let adv = Advertisement {
+            advertisement_type: Type::Broadcast,
+            advertising_data: [(0x01, vec![0x06, 0x00]), (0x16, vec![0x0d])].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(adv.check_len(31), Ok(7));
+        assert_eq!(adv.check_len(6), Err(AdError::TooLong { len: 7, max_len: 6 }));
+
+        let adv = Advertisement {
+            advertising_data: [(0xff, vec![0; 255])].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(adv.check_len(251), Err(AdError::DataTooLong { ad_type: 0xff, len: 255 }));
+    }
+}
@@ -464,30 +464,43 @@ impl RegisteredAgent {
             cr.insert(name.clone(), &[inner.agent_token], Arc::new(self));
         }
 
-        log::trace!("Registering agent at {}", &name);
-        let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, inner.connection.clone());
-        let () = proxy.method_call(MANAGER_INTERFACE, "RegisterAgent", (name.clone(), capability)).await?;
-        let connection = inner.connection.clone();
-
+        // Spawned before registering, so that the agent is also unregistered and
+        // unpublished when registration or the default agent request fails.
         let (drop_tx, drop_rx) = oneshot::channel();
         let unreg_name = name.clone();
+        let unreg_inner = inner.clone();
+        let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, inner.connection.clone());
         tokio::spawn(async move {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering agent at {}", &unreg_name);
+            unreg_inner.unregister_with_daemon(&unreg_name).await;
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterAgent", (unreg_name.clone(),)).await;
 
             log::trace!("Unpublishing agent at {}", &unreg_name);
-            let mut cr = inner.crossroads.lock().await;
+            let mut cr = unreg_inner.crossroads.lock().await;
             let _: Option<Self> = cr.remove(&unreg_name);
         });
 
-        if request_default {
-            log::trace!("Requesting default agent for {}", &name);
-            let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, connection);
-            let () = proxy.method_call(MANAGER_INTERFACE, "RequestDefaultAgent", (name.clone(),)).await?;
-        }
+        log::trace!("Registering agent at {}", &name);
+        let reg_name = name.clone();
+        inner
+            .register_with_daemon(&name, None, move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, connection);
+                let reg_name = reg_name.clone();
+                async move {
+                    let () = proxy
+                        .method_call(MANAGER_INTERFACE, "RegisterAgent", (reg_name.clone(), capability))
+                        .await?;
+                    if request_default {
+                        log::trace!("Requesting default agent for {}", &reg_name);
+                        let () = proxy.method_call(MANAGER_INTERFACE, "RequestDefaultAgent", (reg_name,)).await?;
+                    }
+                    Ok(())
+                }
+            })
+            .await?;
 
         Ok(AgentHandle { name, _drop_tx: drop_tx })
     }
@@ -0,0 +1,961 @@
+//! GATT client.
+
+use futures::{ready, Stream};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    task::{Context, Poll},
+};
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use super::{
+    AttError, Characteristic, CharacteristicProperties, Descriptor, Include, Service, CCC_INDICATE, CCC_NOTIFY,
+    DEFAULT_MTU, MAX_MTU, MAX_VALUE_LEN, TRANSACTION_TIMEOUT, TYPE_CHARACTERISTIC,
+    TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION, TYPE_INCLUDE, TYPE_PRIMARY_SERVICE, TYPE_SECONDARY_SERVICE,
+};
+use crate::{
+    dissect::{
+        att::{opcode, ErrorCode, Pdu},
+        put_uuid, Reader,
+    },
+    l2cap::{SeqPacket, SocketAddr, CID_ATT},
+    Address, AddressType, UuidExt,
+};
+
+type Subscribers = Arc<StdMutex<HashMap<u16, Vec<mpsc::UnboundedSender<Vec<u8>>>>>>;
+
+/// Response channel of the current transaction.
+struct Transaction {
+    rx: mpsc::Receiver<Pdu>,
+    /// A request was sent but its response has not been received,
+    /// because the caller was cancelled.
+    pending: bool,
+}
+
+/// GATT client on an ATT channel.
+///
+/// Requests from multiple tasks are queued, since ATT allows only one
+/// outstanding request per connection.
+/// Notifications and indications are received in the background and
+/// delivered to the [Notifications] streams of the respective attribute handle.
+///
+/// The connection is closed when the client is dropped.
+pub struct Client {
+    socket: Arc<SeqPacket>,
+    transaction: Mutex<Transaction>,
+    /// Opcode of the request whose response is awaited.
+    expected: Arc<StdMutex<Option<u8>>>,
+    long_write: Mutex<()>,
+    mtu: Arc<AtomicU16>,
+    timed_out: AtomicBool,
+    subscribers: Subscribers,
+    reader: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client").field("socket", &self.socket).field("mtu", &self.mtu()).finish()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl Client {
+    /// Connects to the ATT channel of the specified Bluetooth LE device.
+    ///
+    /// Uses any local Bluetooth adapter.
+    pub async fn connect(addr: Address, addr_type: AddressType) -> Result<Self> {
+        if addr_type == AddressType::BrEdr {
+            return Err(Error::new(ErrorKind::InvalidInput, "ATT requires a Bluetooth LE address type"));
+        }
+        let socket = SeqPacket::connect(SocketAddr::new_fixed(addr, addr_type, CID_ATT)).await?;
+        Ok(Self::from_socket(socket))
+    }
+
+    /// Uses an established ATT channel.
+    ///
+    /// The MTU is initially [DEFAULT_MTU]; use [Self::exchange_mtu] to increase it.
+    pub fn from_socket(socket: SeqPacket) -> Self {
+        let socket = Arc::new(socket);
+        let mtu = Arc::new(AtomicU16::new(DEFAULT_MTU));
+        let subscribers = Subscribers::default();
+        let expected = Arc::new(StdMutex::new(None));
+        let (tx, rx) = mpsc::channel(1);
+        let reader =
+            tokio::spawn(Self::reader(socket.clone(), tx, expected.clone(), mtu.clone(), subscribers.clone()));
+        Self {
+            socket,
+            transaction: Mutex::new(Transaction { rx, pending: false }),
+            expected,
+            long_write: Mutex::new(()),
+            mtu,
+            timed_out: AtomicBool::new(false),
+            subscribers,
+            reader,
+        }
+    }
+
+    /// Receives PDUs from the server.
+    async fn reader(
+        socket: Arc<SeqPacket>, tx: mpsc::Sender<Pdu>, expected: Arc<StdMutex<Option<u8>>>, mtu: Arc<AtomicU16>,
+        subscribers: Subscribers,
+    ) {
+        let mut buf = vec![0; MAX_MTU.into()];
+        loop {
+            let n = match socket.recv(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            let Some(pdu) = Pdu::parse(&buf[..n]) else {
+                log::trace!("Received malformed ATT PDU: {:x?}", &buf[..n]);
+                continue;
+            };
+
+            let reply = match pdu {
+                Pdu::HandleValueNtf { handle, value } => {
+                    Self::dispatch(&subscribers, handle, value);
+                    None
+                }
+                Pdu::MultipleHandleValueNtf(entries) => {
+                    for (handle, value) in entries {
+                        Self::dispatch(&subscribers, handle, value);
+                    }
+                    None
+                }
+                Pdu::HandleValueInd { handle, value } => {
+                    Self::dispatch(&subscribers, handle, value);
+                    Some(Pdu::HandleValueCfm)
+                }
+                Pdu::ExchangeMtuReq(client_mtu) => {
+                    mtu.store(client_mtu.clamp(DEFAULT_MTU, MAX_MTU), Ordering::SeqCst);
+                    Some(Pdu::ExchangeMtuRsp(MAX_MTU))
+                }
+                Pdu::WriteCmd { .. } | Pdu::SignedWriteCmd { .. } | Pdu::HandleValueCfm => None,
+                pdu if is_request(&pdu) => Some(Pdu::ErrorRsp {
+                    request_opcode: pdu.opcode(),
+                    handle: 0,
+                    error: ErrorCode::RequestNotSupported,
+                }),
+                pdu => {
+                    // Only the response to the outstanding request is delivered.
+                    let mut expected = expected.lock().unwrap();
+                    if expected.is_some_and(|op| is_response(&pdu, op)) {
+                        *expected = None;
+                        if let Err(mpsc::error::TrySendError::Closed(_)) = tx.try_send(pdu) {
+                            break;
+                        }
+                    } else {
+                        log::trace!("Discarding unexpected ATT response {pdu:?}");
+                    }
+                    None
+                }
+            };
+
+            if let Some(reply) = reply {
+                if socket.send(&reply.to_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        // Terminate all notification streams.
+        subscribers.lock().unwrap().clear();
+    }
+
+    /// Delivers a notified or indicated value to all subscribers of a handle.
+    fn dispatch(subscribers: &Subscribers, handle: u16, value: Vec<u8>) {
+        let mut subscribers = subscribers.lock().unwrap();
+        if let Some(txs) = subscribers.get_mut(&handle) {
+            txs.retain(|tx| tx.send(value.clone()).is_ok());
+            if txs.is_empty() {
+                subscribers.remove(&handle);
+            }
+        }
+    }
+
+    /// Performs a request and waits for its response.
+    async fn request(&self, req: Pdu) -> Result<Pdu> {
+        let mut transaction = self.transaction.lock().await;
+        if self.timed_out.load(Ordering::SeqCst) {
+            return Err(AttError::Timeout.into());
+        }
+
+        if transaction.pending {
+            self.receive(&mut transaction).await?;
+        }
+
+        *self.expected.lock().unwrap() = Some(req.opcode());
+        self.socket.send(&req.to_bytes()).await?;
+        transaction.pending = true;
+        match self.receive(&mut transaction).await? {
+            Pdu::ErrorRsp { request_opcode, handle, error } => {
+                Err(AttError::ErrorResponse { request_opcode, handle, error }.into())
+            }
+            rsp => Ok(rsp),
+        }
+    }
+
+    /// Receives the response to the outstanding request.
+    async fn receive(&self, transaction: &mut Transaction) -> Result<Pdu> {
+        match tokio::time::timeout(TRANSACTION_TIMEOUT, transaction.rx.recv()).await {
+            Ok(Some(rsp)) => {
+                transaction.pending = false;
+                Ok(rsp)
+            }
+            Ok(None) => Err(Error::new(ErrorKind::ConnectionReset, "ATT channel closed")),
+            Err(_) => {
+                self.timed_out.store(true, Ordering::SeqCst);
+                Err(AttError::Timeout.into())
+            }
+        }
+    }
+
+    /// Sends a command, which has no response.
+    async fn command(&self, cmd: Pdu) -> Result<()> {
+        self.socket.send(&cmd.to_bytes()).await?;
+        Ok(())
+    }
+
+    /// Peer address of the ATT channel.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /// Current ATT MTU.
+    pub fn mtu(&self) -> u16 {
+        self.mtu.load(Ordering::SeqCst)
+    }
+
+    /// Maximum length of a value transferred in a single write or notification.
+    fn max_write_len(&self) -> usize {
+        usize::from(self.mtu()) - 3
+    }
+
+    /// Exchanges the MTU with the server, proposing the specified receive MTU.
+    ///
+    /// The MTU is clamped to the range from [DEFAULT_MTU] to [MAX_MTU].
+    /// Returns the resulting ATT MTU.
+    /// This may only be performed once per connection.
+    pub async fn exchange_mtu(&self, mtu: u16) -> Result<u16> {
+        let client_mtu = mtu.clamp(DEFAULT_MTU, MAX_MTU);
+        match self.request(Pdu::ExchangeMtuReq(client_mtu)).await? {
+            Pdu::ExchangeMtuRsp(server_mtu) => {
+                let mtu = client_mtu.min(server_mtu).max(DEFAULT_MTU);
+                self.mtu.store(mtu, Ordering::SeqCst);
+                Ok(mtu)
+            }
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Discovers all primary services.
+    pub async fn primary_services(&self) -> Result<Vec<Service>> {
+        self.services(TYPE_PRIMARY_SERVICE).await
+    }
+
+    /// Discovers all secondary services.
+    pub async fn secondary_services(&self) -> Result<Vec<Service>> {
+        self.services(TYPE_SECONDARY_SERVICE).await
+    }
+
+    /// Discovers all services of the specified group type.
+    async fn services(&self, group_type: u16) -> Result<Vec<Service>> {
+        let mut services = Vec::new();
+        let mut start_handle = 0x0001;
+        loop {
+            let req = Pdu::ReadByGroupTypeReq {
+                start_handle,
+                end_handle: 0xffff,
+                group_type: Uuid::from_u16(group_type),
+            };
+            let entries = match self.request(req).await {
+                Ok(Pdu::ReadByGroupTypeRsp(entries)) if !entries.is_empty() => entries,
+                Ok(rsp) => return Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+                Err(err) if is_not_found(&err) => break,
+                Err(err) => return Err(err),
+            };
+
+            let mut last = start_handle;
+            for (handle, end_handle, value) in entries {
+                let uuid = parse_uuid(&value)?;
+                services.push(Service { handle, end_handle, uuid, primary: group_type == TYPE_PRIMARY_SERVICE });
+                last = end_handle.max(handle);
+            }
+            match last.checked_add(1) {
+                Some(next) if next > start_handle => start_handle = next,
+                _ => break,
+            }
+        }
+        Ok(services)
+    }
+
+    /// Discovers all primary services with the specified UUID.
+    pub async fn primary_services_by_uuid(&self, uuid: Uuid) -> Result<Vec<Service>> {
+        let mut value = Vec::new();
+        put_uuid(&mut value, &uuid);
+
+        let mut services = Vec::new();
+        let mut start_handle = 0x0001;
+        loop {
+            let req = Pdu::FindByTypeValueReq {
+                start_handle,
+                end_handle: 0xffff,
+                attribute_type: TYPE_PRIMARY_SERVICE,
+                value: value.clone(),
+            };
+            let entries = match self.request(req).await {
+                Ok(Pdu::FindByTypeValueRsp(entries)) if !entries.is_empty() => entries,
+                Ok(rsp) => return Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+                Err(err) if is_not_found(&err) => break,
+                Err(err) => return Err(err),
+            };
+
+            let mut last = start_handle;
+            for (handle, end_handle) in entries {
+                services.push(Service { handle, end_handle, uuid, primary: true });
+                last = end_handle.max(handle);
+            }
+            match last.checked_add(1) {
+                Some(next) if next > start_handle => start_handle = next,
+                _ => break,
+            }
+        }
+        Ok(services)
+    }
+
+    /// Finds all included services of a service.
+    pub async fn included_services(&self, service: &Service) -> Result<Vec<Include>> {
+        let mut includes = Vec::new();
+        for (handle, value) in
+            self.read_by_type(service.handle, service.end_handle, Uuid::from_u16(TYPE_INCLUDE)).await?
+        {
+            let mut r = Reader::new(&value);
+            let (Some(service_handle), Some(end_handle)) = (r.u16(), r.u16()) else {
+                return Err(AttError::InvalidPdu.into());
+            };
+            let uuid = if r.is_empty() {
+                // 128-bit UUIDs are not contained in the include declaration.
+                let decl = self.read(service_handle).await?;
+                parse_uuid(&decl)?
+            } else {
+                parse_uuid(r.rest())?
+            };
+            includes.push(Include { handle, service_handle, end_handle, uuid });
+        }
+        Ok(includes)
+    }
+
+    /// Discovers all characteristics of a service.
+    pub async fn characteristics(&self, service: &Service) -> Result<Vec<Characteristic>> {
+        let mut characteristics: Vec<Characteristic> = Vec::new();
+        for (handle, value) in
+            self.read_by_type(service.handle, service.end_handle, Uuid::from_u16(TYPE_CHARACTERISTIC)).await?
+        {
+            let mut r = Reader::new(&value);
+            let (Some(properties), Some(value_handle)) = (r.u8(), r.u16()) else {
+                return Err(AttError::InvalidPdu.into());
+            };
+            let uuid = parse_uuid(r.rest())?;
+            if let Some(prev) = characteristics.last_mut() {
+                prev.end_handle = handle.saturating_sub(1);
+            }
+            characteristics.push(Characteristic {
+                handle,
+                value_handle,
+                end_handle: service.end_handle,
+                uuid,
+                properties: CharacteristicProperties::from_bits(properties),
+            });
+        }
+        Ok(characteristics)
+    }
+
+    /// Discovers all descriptors of a characteristic.
+    pub async fn descriptors(&self, characteristic: &Characteristic) -> Result<Vec<Descriptor>> {
+        let mut descriptors = Vec::new();
+        let Some(mut start_handle) = characteristic.value_handle.checked_add(1) else { return Ok(descriptors) };
+        while start_handle <= characteristic.end_handle {
+            let req = Pdu::FindInformationReq { start_handle, end_handle: characteristic.end_handle };
+            let entries = match self.request(req).await {
+                Ok(Pdu::FindInformationRsp(entries)) if !entries.is_empty() => entries,
+                Ok(rsp) => return Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+                Err(err) if is_not_found(&err) => break,
+                Err(err) => return Err(err),
+            };
+
+            let mut last = start_handle;
+            for (handle, uuid) in entries {
+                descriptors.push(Descriptor { handle, uuid });
+                last = last.max(handle);
+            }
+            match last.checked_add(1) {
+                Some(next) => start_handle = next,
+                None => break,
+            }
+        }
+        Ok(descriptors)
+    }
+
+    /// Reads the values of all attributes of the specified type within a handle range.
+    ///
+    /// Returns the handles and values. Values may be truncated to the MTU.
+    pub async fn read_by_type(
+        &self, start_handle: u16, end_handle: u16, uuid: Uuid,
+    ) -> Result<Vec<(u16, Vec<u8>)>> {
+        let mut values = Vec::new();
+        let mut start_handle = start_handle;
+        while start_handle <= end_handle {
+            let req = Pdu::ReadByTypeReq { start_handle, end_handle, attribute_type: uuid };
+            let entries = match self.request(req).await {
+                Ok(Pdu::ReadByTypeRsp(entries)) if !entries.is_empty() => entries,
+                Ok(rsp) => return Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+                Err(err) if is_not_found(&err) => break,
+                Err(err) => return Err(err),
+            };
+
+            let mut last = start_handle;
+            for (handle, value) in entries {
+                values.push((handle, value));
+                last = last.max(handle);
+            }
+            match last.checked_add(1) {
+                Some(next) => start_handle = next,
+                None => break,
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reads the complete value of an attribute.
+    ///
+    /// Values longer than the MTU are read using multiple read blob requests.
+    pub async fn read(&self, handle: u16) -> Result<Vec<u8>> {
+        let mut value = match self.request(Pdu::ReadReq { handle }).await? {
+            Pdu::ReadRsp(value) => value,
+            rsp => return Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        };
+
+        let max_len = usize::from(self.mtu()) - 1;
+        let mut part_len = value.len();
+        while part_len == max_len && value.len() < MAX_VALUE_LEN {
+            let offset = value.len() as u16;
+            match self.read_blob(handle, offset).await {
+                Ok(part) => {
+                    part_len = part.len();
+                    value.extend(part);
+                }
+                Err(err) if is_error_code(&err, &[ErrorCode::AttributeNotLong, ErrorCode::InvalidOffset]) => {
+                    break
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(value)
+    }
+
+    /// Reads part of the value of an attribute starting at the specified offset.
+    pub async fn read_blob(&self, handle: u16, offset: u16) -> Result<Vec<u8>> {
+        match self.request(Pdu::ReadBlobReq { handle, offset }).await? {
+            Pdu::ReadBlobRsp(value) => Ok(value),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Reads the values of multiple attributes of known fixed length.
+    ///
+    /// Returns the concatenated values, truncated to the MTU.
+    pub async fn read_multiple(&self, handles: &[u16]) -> Result<Vec<u8>> {
+        match self.request(Pdu::ReadMultipleReq(handles.to_vec())).await? {
+            Pdu::ReadMultipleRsp(values) => Ok(values),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Reads the values of multiple attributes of variable length.
+    ///
+    /// This requires support for Bluetooth 5.2 by the server.
+    pub async fn read_multiple_variable(&self, handles: &[u16]) -> Result<Vec<Vec<u8>>> {
+        match self.request(Pdu::ReadMultipleVariableReq(handles.to_vec())).await? {
+            Pdu::ReadMultipleVariableRsp(values) => Ok(values),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Writes the value of an attribute and waits for confirmation.
+    ///
+    /// Values longer than allowed by the MTU are written using [Self::write_long].
+    pub async fn write(&self, handle: u16, value: &[u8]) -> Result<()> {
+        if value.len() > self.max_write_len() {
+            return self.write_long(handle, value).await;
+        }
+        match self.request(Pdu::WriteReq { handle, value: value.to_vec() }).await? {
+            Pdu::WriteRsp => Ok(()),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Writes the value of an attribute without response.
+    ///
+    /// The value must fit into a single PDU.
+    pub async fn write_command(&self, handle: u16, value: &[u8]) -> Result<()> {
+        if value.len() > self.max_write_len() {
+            return Err(AttError::ValueTooLong.into());
+        }
+        self.command(Pdu::WriteCmd { handle, value: value.to_vec() }).await
+    }
+
+    /// Writes a long value of an attribute using prepared writes, which are then executed.
+    ///
+    /// If the server does not echo a part correctly, the prepared writes are cancelled.
+    pub async fn write_long(&self, handle: u16, value: &[u8]) -> Result<()> {
+        if value.len() > MAX_VALUE_LEN {
+            return Err(AttError::ValueTooLong.into());
+        }
+
+        let _long_write = self.long_write.lock().await;
+        let part_len = self.max_write_len() - 2;
+        let mut offset = 0;
+        for part in value.chunks(part_len) {
+            let result = match self.prepare_write(handle, offset, part).await {
+                Ok(echo) if echo == part => Ok(()),
+                Ok(_) => Err(AttError::PreparedWriteMismatch.into()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                let _ = self.execute_write(false).await;
+                return Err(err);
+            }
+            offset += part.len() as u16;
+        }
+        self.execute_write(true).await
+    }
+
+    /// Queues part of a value at the specified offset for writing by the server.
+    ///
+    /// Returns the part as echoed by the server.
+    /// Use [Self::execute_write] to write or cancel all queued values.
+    pub async fn prepare_write(&self, handle: u16, offset: u16, part: &[u8]) -> Result<Vec<u8>> {
+        match self.request(Pdu::PrepareWriteReq { handle, offset, value: part.to_vec() }).await? {
+            Pdu::PrepareWriteRsp { handle: rsp_handle, offset: rsp_offset, value }
+                if rsp_handle == handle && rsp_offset == offset =>
+            {
+                Ok(value)
+            }
+            Pdu::PrepareWriteRsp { .. } => Err(AttError::PreparedWriteMismatch.into()),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Writes all prepared values if `execute` is true, otherwise cancels them.
+    pub async fn execute_write(&self, execute: bool) -> Result<()> {
+        match self.request(Pdu::ExecuteWriteReq(execute)).await? {
+            Pdu::ExecuteWriteRsp => Ok(()),
+            rsp => Err(AttError::UnexpectedResponse(rsp.opcode()).into()),
+        }
+    }
+
+    /// Receives notifications and indications of the attribute with the specified handle.
+    ///
+    /// This does not configure the server to send them; use [Self::subscribe] for that.
+    pub fn notifications(&self, handle: u16) -> Notifications {
+        let (tx, rx) = mpsc::unbounded_channel();
+        if !self.reader.is_finished() {
+            self.subscribers.lock().unwrap().entry(handle).or_default().push(tx);
+        }
+        Notifications { handle, rx }
+    }
+
+    /// Finds the client characteristic configuration descriptor of a characteristic.
+    async fn ccc_descriptor(&self, characteristic: &Characteristic) -> Result<u16> {
+        self.descriptors(characteristic)
+            .await?
+            .into_iter()
+            .find(|desc| desc.uuid == Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION))
+            .map(|desc| desc.handle)
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, "client characteristic configuration descriptor not found")
+            })
+    }
+
+    /// Enables notifications or, if not supported, indications of a characteristic value.
+    ///
+    /// Returns the stream of notified or indicated values.
+    pub async fn subscribe(&self, characteristic: &Characteristic) -> Result<Notifications> {
+        let ccc = if characteristic.properties.notify {
+            CCC_NOTIFY
+        } else if characteristic.properties.indicate {
+            CCC_INDICATE
+        } else {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "characteristic supports neither notify nor indicate",
+            ));
+        };
+        let handle = self.ccc_descriptor(characteristic).await?;
+        let notifications = self.notifications(characteristic.value_handle);
+        self.write(handle, &ccc.to_le_bytes()).await?;
+        Ok(notifications)
+    }
+
+    /// Disables notifications and indications of a characteristic value.
+    pub async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        let handle = self.ccc_descriptor(characteristic).await?;
+        self.write(handle, &0u16.to_le_bytes()).await
+    }
+}
+
+/// Stream of values notified or indicated by the server for an attribute handle.
+///
+/// Indications are confirmed automatically upon reception.
+/// The stream ends when the ATT channel is closed.
+#[derive(Debug)]
+pub struct Notifications {
+    handle: u16,
+    rx: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+impl Notifications {
+    /// Attribute handle.
+    pub fn handle(&self) -> u16 {
+        self.handle
+    }
+}
+
+impl Stream for Notifications {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let value = ready!(self.get_mut().rx.poll_recv(cx));
+        Poll::Ready(value)
+    }
+}
+
+/// Whether the PDU is a request from the peer that requires a response.
+///
+/// Unknown PDUs without the command flag are treated as requests.
+fn is_request(pdu: &Pdu) -> bool {
+    match pdu {
+        Pdu::Other { opcode, .. } => opcode & 0x40 == 0,
+        pdu => matches!(
+            pdu.opcode(),
+            opcode::FIND_INFORMATION_REQ
+                | opcode::FIND_BY_TYPE_VALUE_REQ
+                | opcode::READ_BY_TYPE_REQ
+                | opcode::READ_REQ
+                | opcode::READ_BLOB_REQ
+                | opcode::READ_MULTIPLE_REQ
+                | opcode::READ_BY_GROUP_TYPE_REQ
+                | opcode::WRITE_REQ
+                | opcode::PREPARE_WRITE_REQ
+                | opcode::EXECUTE_WRITE_REQ
+                | opcode::READ_MULTIPLE_VARIABLE_REQ
+        ),
+    }
+}
+
+/// Whether the PDU is the response to a request with the specified opcode.
+fn is_response(pdu: &Pdu, request_opcode: u8) -> bool {
+    match pdu {
+        Pdu::ErrorRsp { request_opcode: op, .. } => *op == request_opcode,
+        pdu => pdu.opcode() == request_opcode + 1,
+    }
+}
+
+/// Parses a 16-bit or 128-bit UUID.
+fn parse_uuid(value: &[u8]) -> Result<Uuid> {
+    Reader::new(value).uuid().ok_or_else(|| AttError::InvalidPdu.into())
+}
+
+/// Whether the error is an ATT error response with one of the specified codes.
+fn is_error_code(err: &Error, codes: &[ErrorCode]) -> bool {
+    match err.get_ref().and_then(|err| err.downcast_ref::<AttError>()) {
+        Some(AttError::ErrorResponse { error, .. }) => codes.contains(error),
+        _ => false,
+    }
+}
+
+/// Whether the error signals the end of a discovery procedure.
+fn is_not_found(err: &Error) -> bool {
+    is_error_code(err, &[ErrorCode::AttributeNotFound])
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    /// Client on one end of a socket pair and the socket of the fake server.
+    fn client() -> (Client, Arc<SeqPacket>) {
+        let mut fds = [0; 2];
+        let ret = unsafe {
+            libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_NONBLOCK, 0, fds.as_mut_ptr())
+        };
+        assert_eq!(ret, 0);
+        let (socket, peer) =
+            unsafe { (SeqPacket::from_raw_fd(fds[0]).unwrap(), SeqPacket::from_raw_fd(fds[1]).unwrap()) };
+        (Client::from_socket(socket), Arc::new(peer))
+    }
+
+    async fn send(peer: &SeqPacket, pdu: Pdu) {
+        peer.send(&pdu.to_bytes()).await.unwrap();
+    }
+
+    async fn recv(peer: &SeqPacket) -> Option<Pdu> {
+        let mut buf = vec![0; MAX_MTU.into()];
+        match peer.recv(&mut buf).await {
+            Ok(0) | Err(_) => None,
+            Ok(n) => Pdu::parse(&buf[..n]),
+        }
+    }
+
+    /// Answers requests with the specified responses in order and then with errors
+    /// stating that the attribute was not found.
+    ///
+    /// Returns the received requests.
+    fn serve(peer: Arc<SeqPacket>, responses: Vec<Pdu>) -> Arc<StdMutex<Vec<Pdu>>> {
+        let requests = Arc::new(StdMutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            let mut responses = responses.into_iter();
+            while let Some(req) = recv(&peer).await {
+                let rsp = responses.next().unwrap_or(Pdu::ErrorRsp {
+                    request_opcode: req.opcode(),
+                    handle: 0,
+                    error: ErrorCode::AttributeNotFound,
+                });
+                received.lock().unwrap().push(req);
+                send(&peer, rsp).await;
+            }
+        });
+        requests
+    }
+
+    fn group_req(start_handle: u16) -> Pdu {
+        Pdu::ReadByGroupTypeReq {
+            start_handle,
+            end_handle: 0xffff,
+            group_type: Uuid::from_u16(TYPE_PRIMARY_SERVICE),
+        }
+    }
+
+    #[test]
+    fn requests() {
+        for pdu in [
+            Pdu::ReadReq { handle: 1 },
+            Pdu::WriteReq { handle: 1, value: vec![0] },
+            Pdu::ExecuteWriteReq(true),
+            group_req(1),
+            Pdu::ReadMultipleVariableReq(vec![1, 2]),
+            Pdu::Other { opcode: 0x3f, data: Vec::new() },
+        ] {
+            assert!(is_request(&pdu), "{pdu:?}");
+        }
+        for pdu in [
+            Pdu::ReadRsp(vec![0]),
+            Pdu::WriteCmd { handle: 1, value: vec![0] },
+            Pdu::HandleValueNtf { handle: 1, value: vec![0] },
+            Pdu::HandleValueInd { handle: 1, value: vec![0] },
+            Pdu::HandleValueCfm,
+            Pdu::ExchangeMtuReq(23),
+            Pdu::Other { opcode: 0x7f, data: Vec::new() },
+        ] {
+            assert!(!is_request(&pdu), "{pdu:?}");
+        }
+    }
+
+    #[test]
+    fn responses() {
+        assert!(is_response(&Pdu::ReadRsp(vec![0]), opcode::READ_REQ));
+        assert!(!is_response(&Pdu::ReadBlobRsp(vec![0]), opcode::READ_REQ));
+        assert!(is_response(
+            &Pdu::ErrorRsp { request_opcode: opcode::READ_REQ, handle: 1, error: ErrorCode::InvalidHandle },
+            opcode::READ_REQ
+        ));
+        assert!(!is_response(
+            &Pdu::ErrorRsp { request_opcode: opcode::WRITE_REQ, handle: 1, error: ErrorCode::InvalidHandle },
+            opcode::READ_REQ
+        ));
+    }
+
+    #[test]
+    fn uuids() {
+        assert_eq!(parse_uuid(&[0x0f, 0x18]).unwrap(), Uuid::from_u16(0x180f));
+        let uuid = Uuid::from_u128(0x6e400001_b5a3_f393_e0a9_e50e24dcca9e);
+        assert_eq!(parse_uuid(&uuid.as_u128().to_le_bytes()).unwrap(), uuid);
+        for value in [&[][..], &[0x0f], &[0x0f, 0x18, 0x00], &[0; 15]] {
+            let err = parse_uuid(value).unwrap_err();
+            assert!(matches!(err.get_ref().unwrap().downcast_ref(), Some(AttError::InvalidPdu)), "{value:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn services_end_of_range() {
+        let (client, peer) = client();
+        let requests = serve(
+            peer,
+            vec![Pdu::ReadByGroupTypeRsp(vec![
+                (0x0001, 0x0005, vec![0x00, 0x18]),
+                (0x0006, 0xffff, vec![0x0f, 0x18]),
+            ])],
+        );
+
+        let services = client.primary_services().await.unwrap();
+        assert_eq!(
+            services,
+            [
+                Service { handle: 0x0001, end_handle: 0x0005, uuid: Uuid::from_u16(0x1800), primary: true },
+                Service { handle: 0x0006, end_handle: 0xffff, uuid: Uuid::from_u16(0x180f), primary: true },
+            ]
+        );
+        // No request is sent beyond the last handle.
+        assert_eq!(*requests.lock().unwrap(), [group_req(0x0001)]);
+    }
+
+    #[tokio::test]
+    async fn services_not_advancing() {
+        let (client, peer) = client();
+        let requests = serve(
+            peer,
+            vec![
+                Pdu::ReadByGroupTypeRsp(vec![(0x0010, 0x0020, vec![0x00, 0x18])]),
+                Pdu::ReadByGroupTypeRsp(vec![(0x0005, 0x0006, vec![0x0f, 0x18])]),
+            ],
+        );
+
+        // A response going backwards ends discovery instead of looping forever.
+        let services = client.primary_services().await.unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(*requests.lock().unwrap(), [group_req(0x0001), group_req(0x0021)]);
+    }
+
+    #[tokio::test]
+    async fn services_by_uuid() {
+        let (client, peer) = client();
+        let requests = serve(peer, vec![Pdu::FindByTypeValueRsp(vec![(0x0001, 0x0005), (0x0010, 0x0012)])]);
+
+        let uuid = Uuid::from_u16(0x180f);
+        let services = client.primary_services_by_uuid(uuid).await.unwrap();
+        assert_eq!(
+            services,
+            [
+                Service { handle: 0x0001, end_handle: 0x0005, uuid, primary: true },
+                Service { handle: 0x0010, end_handle: 0x0012, uuid, primary: true },
+            ]
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(matches!(requests[1], Pdu::FindByTypeValueReq { start_handle: 0x0013, .. }));
+    }
+
+    #[tokio::test]
+    async fn descriptors_end_of_range() {
+        let (client, peer) = client();
+        let requests = serve(
+            peer,
+            vec![
+                Pdu::FindInformationRsp(vec![(0x0004, Uuid::from_u16(0x2902)), (0xffff, Uuid::from_u16(0x2901))]),
+                Pdu::FindInformationRsp(vec![(0x0014, Uuid::from_u16(0x2902))]),
+            ],
+        );
+
+        let characteristic = Characteristic {
+            handle: 0x0002,
+            value_handle: 0x0003,
+            end_handle: 0xffff,
+            uuid: Uuid::from_u16(0x2a19),
+            properties: CharacteristicProperties::default(),
+        };
+        assert_eq!(client.descriptors(&characteristic).await.unwrap().len(), 2);
+
+        // Discovery stops at the end handle of the characteristic.
+        let characteristic = Characteristic { value_handle: 0x0013, end_handle: 0x0014, ..characteristic };
+        let descriptors = client.descriptors(&characteristic).await.unwrap();
+        assert_eq!(descriptors, [Descriptor { handle: 0x0014, uuid: Uuid::from_u16(0x2902) }]);
+
+        // Nothing to discover after a value handle at the end of the handle range.
+        let characteristic = Characteristic { value_handle: 0xffff, ..characteristic };
+        assert!(client.descriptors(&characteristic).await.unwrap().is_empty());
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                Pdu::FindInformationReq { start_handle: 0x0004, end_handle: 0xffff },
+                Pdu::FindInformationReq { start_handle: 0x0014, end_handle: 0x0014 },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_by_type_end_of_range() {
+        let (client, peer) = client();
+        let requests = serve(peer, vec![Pdu::ReadByTypeRsp(vec![(0x0003, vec![1]), (0x0005, vec![2])])]);
+
+        let uuid = Uuid::from_u16(0x2a19);
+        let values = client.read_by_type(0x0001, 0x0005, uuid).await.unwrap();
+        assert_eq!(values, [(0x0003, vec![1]), (0x0005, vec![2])]);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [Pdu::ReadByTypeReq { start_handle: 0x0001, end_handle: 0x0005, attribute_type: uuid }]
+        );
+    }
+
+    #[tokio::test]
+    async fn unexpected_responses() {
+        let (client, peer) = client();
+        let mut notifications = client.notifications(0x0003);
+
+        // Unsolicited response, followed by a notification to know it has been received.
+        send(&peer, Pdu::ReadRsp(vec![0xde, 0xad])).await;
+        send(&peer, Pdu::HandleValueNtf { handle: 0x0003, value: vec![1] }).await;
+        assert_eq!(notifications.next().await, Some(vec![1]));
+
+        let server = tokio::spawn(async move {
+            assert_eq!(recv(&peer).await, Some(Pdu::ReadReq { handle: 0x0003 }));
+            // Responses to other requests are discarded.
+            send(&peer, Pdu::WriteRsp).await;
+            send(
+                &peer,
+                Pdu::ErrorRsp {
+                    request_opcode: opcode::WRITE_REQ,
+                    handle: 0x0003,
+                    error: ErrorCode::InvalidHandle,
+                },
+            )
+            .await;
+            send(&peer, Pdu::ReadRsp(vec![0x01, 0x02])).await;
+            peer
+        });
+        assert_eq!(client.read(0x0003).await.unwrap(), [0x01, 0x02]);
+        let peer = server.await.unwrap();
+
+        let server = tokio::spawn(async move {
+            assert_eq!(recv(&peer).await, Some(Pdu::WriteReq { handle: 0x0003, value: vec![0] }));
+            send(
+                &peer,
+                Pdu::ErrorRsp {
+                    request_opcode: opcode::WRITE_REQ,
+                    handle: 0x0003,
+                    error: ErrorCode::WriteNotPermitted,
+                },
+            )
+            .await;
+        });
+        let err = client.write(0x0003, &[0]).await.unwrap_err();
+        assert!(is_error_code(&err, &[ErrorCode::WriteNotPermitted]));
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+//! Attribute Protocol (ATT) and Generic Attribute Profile (GATT) without the Bluetooth daemon.
+//!
+//! ATT runs over the fixed L2CAP channel [CID_ATT](crate::l2cap::CID_ATT) of a Bluetooth LE
+//! connection. The [Client] implements the GATT client procedures directly on that
+//! channel, exposing attribute handles and bypassing the GATT cache of the Bluetooth daemon.
+//! The [Server] publishes a [local GATT application](crate::gatt::local::Application)
+//! on that channel; it requires the `bluetoothd` feature for the application
+//! definitions, but not a running Bluetooth daemon.
+//!
+//! PDUs are encoded and decoded using [dissect::att](crate::dissect::att).
+//!
+//! Note that the Bluetooth daemon also opens the ATT channel of each LE connection
+//! it manages. Running both GATT clients at the same time is possible, but the
+//! requests of both clients are serialized by the remote device.
+//!
+//! Refer to the Bluetooth Core Specification, Vol 3, Parts F and G for details.
+
+use std::{fmt, io, time::Duration};
+use uuid::Uuid;
+
+use crate::dissect::att::ErrorCode;
+
+mod client;
+#[cfg(feature = "bluetoothd")]
+mod server;
+
+pub use client::*;
+#[cfg(feature = "bluetoothd")]
+pub use server::Server;
+
+/// Default and minimum ATT MTU for Bluetooth LE.
+pub const DEFAULT_MTU: u16 = 23;
+
+/// Maximum ATT MTU, allowing attribute values of maximum length to be transferred in one PDU.
+pub const MAX_MTU: u16 = 517;
+
+/// Maximum length of an attribute value.
+pub const MAX_VALUE_LEN: usize = 512;
+
+/// Attribute type of a primary service declaration.
+pub const TYPE_PRIMARY_SERVICE: u16 = 0x2800;
+
+/// Attribute type of a secondary service declaration.
+pub const TYPE_SECONDARY_SERVICE: u16 = 0x2801;
+
+/// Attribute type of an include declaration.
+pub const TYPE_INCLUDE: u16 = 0x2802;
+
+/// Attribute type of a characteristic declaration.
+pub const TYPE_CHARACTERISTIC: u16 = 0x2803;
+
+/// Attribute type of the characteristic extended properties descriptor.
+pub const TYPE_CHARACTERISTIC_EXTENDED_PROPERTIES: u16 = 0x2900;
+
+/// Attribute type of the client characteristic configuration descriptor (CCCD).
+pub const TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION: u16 = 0x2902;
+
+/// Value of the client characteristic configuration descriptor enabling notifications.
+pub const CCC_NOTIFY: u16 = 0x0001;
+
+/// Value of the client characteristic configuration descriptor enabling indications.
+pub const CCC_INDICATE: u16 = 0x0002;
+
+/// Time after which an ATT transaction fails.
+pub const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// ATT error.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AttError {
+    /// Error response from the remote device.
+    ErrorResponse {
+        /// Opcode of the failed request.
+        request_opcode: u8,
+        /// Attribute handle the error refers to.
+        handle: u16,
+        /// Error code.
+        error: ErrorCode,
+    },
+    /// PDU from the remote device is malformed.
+    InvalidPdu,
+    /// Response from the remote device has the specified unexpected opcode.
+    UnexpectedResponse(u8),
+    /// The remote device did not respond within [TRANSACTION_TIMEOUT].
+    ///
+    /// No further requests can be sent on this connection.
+    Timeout,
+    /// Value echoed in a prepare write response differs from the value sent.
+    PreparedWriteMismatch,
+    /// Attribute value exceeds the maximum length.
+    ValueTooLong,
+}
+
+impl fmt::Display for AttError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ErrorResponse { request_opcode, handle, error } => {
+                write!(f, "ATT request 0x{request_opcode:02x} for handle 0x{handle:04x} failed: {error}")
+            }
+            Self::InvalidPdu => write!(f, "invalid ATT PDU"),
+            Self::UnexpectedResponse(opcode) => write!(f, "unexpected ATT response 0x{opcode:02x}"),
+            Self::Timeout => write!(f, "ATT transaction timed out"),
+            Self::PreparedWriteMismatch => write!(f, "prepared write response does not match request"),
+            Self::ValueTooLong => write!(f, "attribute value too long"),
+        }
+    }
+}
+
+impl std::error::Error for AttError {}
+
+impl From<AttError> for io::Error {
+    fn from(err: AttError) -> Self {
+        let kind = match &err {
+            AttError::ErrorResponse { error, .. } => match error {
+                ErrorCode::InvalidHandle | ErrorCode::AttributeNotFound => io::ErrorKind::NotFound,
+                ErrorCode::ReadNotPermitted
+                | ErrorCode::WriteNotPermitted
+                | ErrorCode::InsufficientAuthentication
+                | ErrorCode::InsufficientAuthorization
+                | ErrorCode::InsufficientEncryption
+                | ErrorCode::InsufficientEncryptionKeySize => io::ErrorKind::PermissionDenied,
+                ErrorCode::RequestNotSupported => io::ErrorKind::Unsupported,
+                ErrorCode::InvalidOffset | ErrorCode::InvalidAttributeValueLength => io::ErrorKind::InvalidInput,
+                _ => io::ErrorKind::Other,
+            },
+            AttError::Timeout => io::ErrorKind::TimedOut,
+            AttError::ValueTooLong => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Properties of a characteristic as found in its declaration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacteristicProperties {
+    /// Value may be broadcast.
+    pub broadcast: bool,
+    /// Value may be read.
+    pub read: bool,
+    /// Value may be written without response.
+    pub write_without_response: bool,
+    /// Value may be written.
+    pub write: bool,
+    /// Value may be notified.
+    pub notify: bool,
+    /// Value may be indicated.
+    pub indicate: bool,
+    /// Value may be written using signed writes.
+    pub authenticated_signed_writes: bool,
+    /// Extended properties are defined in the characteristic extended properties descriptor.
+    pub extended_properties: bool,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl CharacteristicProperties {
+    /// Decodes the properties from their bit representation.
+    pub fn from_bits(bits: u8) -> Self {
+        let bit = |n: u8| bits & (1 << n) != 0;
+        Self {
+            broadcast: bit(0),
+            read: bit(1),
+            write_without_response: bit(2),
+            write: bit(3),
+            notify: bit(4),
+            indicate: bit(5),
+            authenticated_signed_writes: bit(6),
+            extended_properties: bit(7),
+            _non_exhaustive: (),
+        }
+    }
+
+    /// Encodes the properties into their bit representation.
+    pub fn to_bits(&self) -> u8 {
+        [
+            self.broadcast,
+            self.read,
+            self.write_without_response,
+            self.write,
+            self.notify,
+            self.indicate,
+            self.authenticated_signed_writes,
+            self.extended_properties,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (n, set)| if *set { bits | 1 << n } else { bits })
+    }
+}
+
+/// Service found by discovery.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Service {
+    /// Handle of the service declaration.
+    pub handle: u16,
+    /// Last handle belonging to the service.
+    pub end_handle: u16,
+    /// Service UUID.
+    pub uuid: Uuid,
+    /// Whether this is a primary service.
+    pub primary: bool,
+}
+
+/// Included service found by discovery.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Include {
+    /// Handle of the include declaration.
+    pub handle: u16,
+    /// Handle of the declaration of the included service.
+    pub service_handle: u16,
+    /// Last handle belonging to the included service.
+    pub end_handle: u16,
+    /// UUID of the included service.
+    pub uuid: Uuid,
+}
+
+/// Characteristic found by discovery.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Characteristic {
+    /// Handle of the characteristic declaration.
+    pub handle: u16,
+    /// Handle of the characteristic value.
+    pub value_handle: u16,
+    /// Last handle belonging to the characteristic, including its descriptors.
+    pub end_handle: u16,
+    /// Characteristic UUID.
+    pub uuid: Uuid,
+    /// Properties.
+    pub properties: CharacteristicProperties,
+}
+
+/// Characteristic descriptor found by discovery.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Descriptor {
+    /// Descriptor handle.
+    pub handle: u16,
+    /// Descriptor UUID.
+    pub uuid: Uuid,
+}
@@ -0,0 +1,1364 @@
+//! GATT server.
+
+use futures::channel::oneshot;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Error, ErrorKind, Result},
+    mem::take,
+    num::NonZeroU16,
+    os::unix::io::FromRawFd,
+    sync::Arc,
+};
+use tokio::{net::UnixDatagram, sync::mpsc, task::JoinHandle};
+use uuid::Uuid;
+
+use super::{
+    CharacteristicProperties, CCC_INDICATE, CCC_NOTIFY, DEFAULT_MTU, MAX_MTU, MAX_VALUE_LEN, TYPE_CHARACTERISTIC,
+    TYPE_CHARACTERISTIC_EXTENDED_PROPERTIES, TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION, TYPE_PRIMARY_SERVICE,
+    TYPE_SECONDARY_SERVICE,
+};
+use crate::{
+    dissect::{
+        att::{opcode, ErrorCode, Pdu},
+        put_uuid,
+    },
+    gatt::{
+        local::{
+            Application, Characteristic, CharacteristicControlEvent, CharacteristicNotifier,
+            CharacteristicNotifyMethod, CharacteristicReadRequest, CharacteristicWriteIoRequest,
+            CharacteristicWriteMethod, CharacteristicWriteRequest, Descriptor, DescriptorReadRequest,
+            DescriptorWriteRequest, LinkType, ReqError,
+        },
+        make_socket_pair, CharacteristicWriter, WriteOp,
+    },
+    l2cap::{SecurityLevel, SeqPacket, SeqPacketListener, SocketAddr, CID_ATT},
+    Address, AddressType, UuidExt,
+};
+
+/// Maximum number of queued prepared writes per connection.
+const MAX_PREPARED_WRITES: usize = 128;
+
+/// ATT error code for an improperly configured client characteristic configuration descriptor.
+const CCC_IMPROPERLY_CONFIGURED: u8 = 0xfd;
+
+/// ATT error code for a request that is already in progress.
+const ALREADY_IN_PROGRESS: u8 = 0xfe;
+
+/// Maps an error returned by a callback to an ATT error code.
+fn req_error_code(err: ReqError, write: bool) -> ErrorCode {
+    match err {
+        ReqError::Failed => ErrorCode::UnlikelyError,
+        ReqError::InProgress => ErrorCode::from(ALREADY_IN_PROGRESS),
+        ReqError::InvalidOffset => ErrorCode::InvalidOffset,
+        ReqError::InvalidValueLength => ErrorCode::InvalidAttributeValueLength,
+        ReqError::NotPermitted if write => ErrorCode::WriteNotPermitted,
+        ReqError::NotPermitted => ErrorCode::ReadNotPermitted,
+        ReqError::NotAuthorized => ErrorCode::InsufficientAuthorization,
+        ReqError::NotSupported => ErrorCode::RequestNotSupported,
+    }
+}
+
+/// Security requirements of an attribute operation.
+#[derive(Clone, Copy, Default)]
+struct Security {
+    encrypt: bool,
+    authenticated: bool,
+    secure: bool,
+}
+
+impl Security {
+    /// Checks the requirements against the security level of the link.
+    fn check(self, level: SecurityLevel) -> std::result::Result<(), ErrorCode> {
+        if self.encrypt && level < SecurityLevel::Medium {
+            Err(ErrorCode::InsufficientEncryption)
+        } else if (self.authenticated && level < SecurityLevel::High)
+            || (self.secure && level < SecurityLevel::Fips)
+        {
+            Err(ErrorCode::InsufficientAuthentication)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Value of an attribute in the database.
+enum Value {
+    /// Constant value of a declaration.
+    Static(Vec<u8>),
+    /// Value of the characteristic with the specified index.
+    Characteristic(usize),
+    /// Client characteristic configuration of the characteristic with the specified index.
+    Ccc(usize),
+    /// Value of the descriptor with the specified index.
+    Descriptor(usize),
+}
+
+/// Attribute in the database.
+struct Attribute {
+    handle: u16,
+    attribute_type: Uuid,
+    value: Value,
+    /// Last handle of the group, if this is a service declaration.
+    group_end: Option<u16>,
+}
+
+/// Characteristic in the database.
+struct CharacteristicEntry {
+    c: Characteristic,
+    value_handle: u16,
+}
+
+/// Attribute database built from an application.
+struct Database {
+    attributes: Vec<Attribute>,
+    characteristics: Vec<CharacteristicEntry>,
+    descriptors: Vec<Descriptor>,
+}
+
+/// Allocates handles in ascending order.
+struct Allocator {
+    next: u32,
+}
+
+impl Allocator {
+    /// Allocates the requested or the next available handle.
+    fn allocate(&mut self, requested: Option<u16>) -> Result<u16> {
+        let handle = match requested {
+            Some(handle) if u32::from(handle) >= self.next => handle.into(),
+            Some(handle) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("GATT handle 0x{handle:04x} is already in use or not ascending"),
+                ))
+            }
+            None => self.next,
+        };
+        let handle = u16::try_from(handle)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "GATT attribute database is full"))?;
+        self.next = u32::from(handle) + 1;
+        Ok(handle)
+    }
+}
+
+/// Encodes a UUID in its shortest form.
+fn uuid_bytes(uuid: &Uuid) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_uuid(&mut buf, uuid);
+    buf
+}
+
+impl Database {
+    fn new(app: Application) -> Result<Self> {
+        let mut alloc = Allocator { next: 1 };
+        let mut attributes = Vec::new();
+        let mut characteristics = Vec::new();
+        let mut descriptors = Vec::new();
+
+        for mut service in app.services {
+            let handle = alloc.allocate(service.handle.map(|h| h.get()))?;
+            let _ = service.control_handle.handle_tx.send(NonZeroU16::new(handle));
+            let service_idx = attributes.len();
+            attributes.push(Attribute {
+                handle,
+                attribute_type: Uuid::from_u16(if service.primary {
+                    TYPE_PRIMARY_SERVICE
+                } else {
+                    TYPE_SECONDARY_SERVICE
+                }),
+                value: Value::Static(uuid_bytes(&service.uuid)),
+                group_end: Some(handle),
+            });
+
+            for mut c in take(&mut service.characteristics) {
+                let decl_handle = match c.handle {
+                    Some(value_handle) if value_handle.get() == 1 => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            "GATT characteristic handle must exceed 1",
+                        ))
+                    }
+                    Some(value_handle) => alloc.allocate(Some(value_handle.get() - 1))?,
+                    None => alloc.allocate(None)?,
+                };
+                let value_handle = alloc.allocate(None)?;
+                let _ = c.control_handle.handle_tx.send(NonZeroU16::new(value_handle));
+
+                let props = properties(&c);
+                let mut decl = vec![props.to_bits()];
+                decl.extend_from_slice(&value_handle.to_le_bytes());
+                decl.extend(uuid_bytes(&c.uuid));
+                attributes.push(Attribute {
+                    handle: decl_handle,
+                    attribute_type: Uuid::from_u16(TYPE_CHARACTERISTIC),
+                    value: Value::Static(decl),
+                    group_end: None,
+                });
+
+                let idx = characteristics.len();
+                attributes.push(Attribute {
+                    handle: value_handle,
+                    attribute_type: c.uuid,
+                    value: Value::Characteristic(idx),
+                    group_end: None,
+                });
+
+                if props.notify || props.indicate {
+                    attributes.push(Attribute {
+                        handle: alloc.allocate(None)?,
+                        attribute_type: Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION),
+                        value: Value::Ccc(idx),
+                        group_end: None,
+                    });
+                }
+
+                if props.extended_properties {
+                    let reliable_write = c.write.as_ref().map(|w| w.reliable_write).unwrap_or_default();
+                    let ext = u16::from(reliable_write) | u16::from(c.writable_auxiliaries) << 1;
+                    attributes.push(Attribute {
+                        handle: alloc.allocate(None)?,
+                        attribute_type: Uuid::from_u16(TYPE_CHARACTERISTIC_EXTENDED_PROPERTIES),
+                        value: Value::Static(ext.to_le_bytes().to_vec()),
+                        group_end: None,
+                    });
+                }
+
+                for d in take(&mut c.descriptors) {
+                    if d.uuid == Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION)
+                        || d.uuid == Uuid::from_u16(TYPE_CHARACTERISTIC_EXTENDED_PROPERTIES)
+                    {
+                        log::warn!("Ignoring GATT descriptor {} that is provided by the server", d.uuid);
+                        continue;
+                    }
+                    let handle = alloc.allocate(d.handle.map(|h| h.get()))?;
+                    let _ = d.control_handle.handle_tx.send(NonZeroU16::new(handle));
+                    attributes.push(Attribute {
+                        handle,
+                        attribute_type: d.uuid,
+                        value: Value::Descriptor(descriptors.len()),
+                        group_end: None,
+                    });
+                    descriptors.push(d);
+                }
+
+                characteristics.push(CharacteristicEntry { c, value_handle });
+            }
+
+            attributes[service_idx].group_end = attributes.last().map(|attr| attr.handle);
+        }
+
+        Ok(Self { attributes, characteristics, descriptors })
+    }
+
+    /// Attribute with the specified handle.
+    fn attribute(&self, handle: u16) -> Option<&Attribute> {
+        self.attributes.binary_search_by_key(&handle, |attr| attr.handle).ok().map(|idx| &self.attributes[idx])
+    }
+
+    /// Attributes within the specified handle range.
+    fn range(&self, start_handle: u16, end_handle: u16) -> impl Iterator<Item = &Attribute> {
+        let start = self.attributes.partition_point(|attr| attr.handle < start_handle);
+        self.attributes[start..].iter().take_while(move |attr| attr.handle <= end_handle)
+    }
+
+    /// Index of the characteristic with the specified value handle.
+    fn characteristic_by_value_handle(&self, handle: u16) -> Option<usize> {
+        match self.attribute(handle)?.value {
+            Value::Characteristic(idx) => Some(idx),
+            _ => None,
+        }
+    }
+}
+
+/// Properties of a characteristic for its declaration.
+fn properties(c: &Characteristic) -> CharacteristicProperties {
+    let read = c.read.as_ref();
+    let write = c.write.as_ref();
+    let notify = c.notify.as_ref();
+    CharacteristicProperties {
+        broadcast: c.broadcast,
+        read: read
+            .map(|r| r.read || r.encrypt_read || r.encrypt_authenticated_read || r.secure_read)
+            .unwrap_or_default(),
+        write_without_response: write.map(|w| w.write_without_response).unwrap_or_default(),
+        write: write
+            .map(|w| w.write || w.encrypt_write || w.encrypt_authenticated_write || w.secure_write)
+            .unwrap_or_default(),
+        notify: notify.map(|n| n.notify).unwrap_or_default(),
+        indicate: notify.map(|n| n.indicate).unwrap_or_default(),
+        authenticated_signed_writes: write.map(|w| w.authenticated_signed_writes).unwrap_or_default(),
+        extended_properties: c.writable_auxiliaries || write.map(|w| w.reliable_write).unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Native GATT server publishing a local [Application] on ATT channels.
+///
+/// This serves the application directly over the L2CAP ATT fixed channel
+/// of Bluetooth LE connections, without the Bluetooth daemon.
+/// The characteristic and descriptor callbacks of the application are
+/// invoked as they would be when publishing it through the Bluetooth daemon.
+///
+/// Handles are allocated in definition order.
+/// Handles specified in [Service::handle](crate::gatt::local::Service::handle),
+/// [Characteristic::handle] (referring to the characteristic value) and
+/// [Descriptor::handle] are honored, but must be ascending.
+/// The assigned handles are reported through the control handles.
+/// The client characteristic configuration descriptor and, if needed, the
+/// characteristic extended properties descriptor are added automatically.
+///
+/// Each client has its own client characteristic configuration.
+/// Thus a notification session is started separately for each client that
+/// enables notifications or indications.
+///
+/// The adapter name of requests passed to the callbacks is empty,
+/// since adapters are not enumerated without the Bluetooth daemon.
+#[cfg_attr(docsrs, doc(cfg(all(feature = "att", feature = "bluetoothd"))))]
+#[derive(Clone)]
+pub struct Server {
+    db: Arc<Database>,
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server").field("attributes", &self.db.attributes.len()).finish()
+    }
+}
+
+impl Server {
+    /// Builds the attribute database of the application.
+    ///
+    /// Fails if the specified handles are conflicting.
+    pub fn new(app: Application) -> Result<Self> {
+        Ok(Self { db: Arc::new(Database::new(app)?) })
+    }
+
+    /// Listens for ATT connections from Bluetooth LE devices on the local adapter with the
+    /// specified address.
+    ///
+    /// Use [Address::any] to listen on all adapters.
+    /// This fails if the Bluetooth daemon is serving the ATT channel.
+    pub async fn listen(addr: Address) -> Result<SeqPacketListener> {
+        SeqPacketListener::bind(SocketAddr::new_fixed(addr, AddressType::LePublic, CID_ATT)).await
+    }
+
+    /// Accepts connections and serves each one in a separate task.
+    ///
+    /// Returns when accepting a connection fails.
+    pub async fn serve(&self, listener: &SeqPacketListener) -> Result<()> {
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            log::trace!("Accepted ATT connection from {}", peer.addr);
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = server.serve_connection(socket).await {
+                    log::debug!("ATT connection from {} failed: {}", peer.addr, err);
+                }
+            });
+        }
+    }
+
+    /// Serves a connected ATT channel until it is closed by the client.
+    pub async fn serve_connection(&self, socket: SeqPacket) -> Result<()> {
+        let device_address = socket.peer_addr().map(|sa| sa.addr).unwrap_or_default();
+        let (notify_tx, mut notify_rx) = mpsc::unbounded_channel();
+        let mut conn = Connection {
+            db: self.db.clone(),
+            socket,
+            device_address,
+            mtu: DEFAULT_MTU,
+            ccc: HashMap::new(),
+            sessions: HashMap::new(),
+            write_io: HashMap::new(),
+            prepared: Vec::new(),
+            notify_tx,
+            indicating: None,
+            indications: VecDeque::new(),
+        };
+
+        let mut buf = vec![0; MAX_MTU.into()];
+        loop {
+            tokio::select! {
+                res = conn.socket.recv(&mut buf) => {
+                    let n = res?;
+                    if n == 0 {
+                        break;
+                    }
+                    let Some(pdu) = Pdu::parse(&buf[..n]) else {
+                        log::trace!("Received malformed ATT PDU: {:x?}", &buf[..n]);
+                        if let Some(&op) = buf.first().filter(|op| *op & 0x40 == 0) {
+                            conn.send(Pdu::ErrorRsp { request_opcode: op, handle: 0, error: ErrorCode::InvalidPdu }).await?;
+                        }
+                        continue;
+                    };
+                    if let Some(rsp) = conn.handle(pdu).await? {
+                        conn.send(rsp).await?;
+                    }
+                }
+                Some((handle, value)) = notify_rx.recv() => conn.notify(handle, value).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Notification session of a characteristic for one client.
+struct Session {
+    confirm_tx: Option<mpsc::Sender<()>>,
+    _stop_notify_rx: Option<mpsc::Receiver<()>>,
+    forwarder: Option<JoinHandle<()>>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(forwarder) = &self.forwarder {
+            forwarder.abort();
+        }
+    }
+}
+
+type AttResult<T> = std::result::Result<T, ErrorCode>;
+
+/// State of a client connection.
+struct Connection {
+    db: Arc<Database>,
+    socket: SeqPacket,
+    device_address: Address,
+    mtu: u16,
+    /// Client characteristic configuration by characteristic index.
+    ccc: HashMap<usize, u16>,
+    /// Notification sessions by characteristic index.
+    sessions: HashMap<usize, Session>,
+    /// Accepted IO write requests by characteristic index.
+    write_io: HashMap<usize, UnixDatagram>,
+    /// Queued prepared writes: handle, offset and value.
+    prepared: Vec<(u16, u16, Vec<u8>)>,
+    notify_tx: mpsc::UnboundedSender<(u16, Vec<u8>)>,
+    /// Index of characteristic with unconfirmed indication.
+    indicating: Option<usize>,
+    /// Queued indications.
+    indications: VecDeque<(usize, Vec<u8>)>,
+}
+
+impl Connection {
+    async fn send(&self, pdu: Pdu) -> Result<()> {
+        self.socket.send(&pdu.to_bytes()).await?;
+        Ok(())
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        self.socket.as_ref().security().map(|s| s.level).unwrap_or(SecurityLevel::Low)
+    }
+
+    /// Handles a PDU from the client and returns the response.
+    async fn handle(&mut self, pdu: Pdu) -> Result<Option<Pdu>> {
+        let request_opcode = pdu.opcode();
+        let rsp = match pdu {
+            Pdu::ExchangeMtuReq(client_mtu) => {
+                self.mtu = client_mtu.clamp(DEFAULT_MTU, MAX_MTU);
+                Ok(Pdu::ExchangeMtuRsp(MAX_MTU))
+            }
+            Pdu::FindInformationReq { start_handle, end_handle } => {
+                self.find_information(start_handle, end_handle)
+            }
+            Pdu::FindByTypeValueReq { start_handle, end_handle, attribute_type, value } => {
+                self.find_by_type_value(start_handle, end_handle, attribute_type, &value)
+            }
+            Pdu::ReadByTypeReq { start_handle, end_handle, attribute_type } => {
+                self.read_by_type(start_handle, end_handle, attribute_type).await
+            }
+            Pdu::ReadByGroupTypeReq { start_handle, end_handle, group_type } => {
+                self.read_by_group_type(start_handle, end_handle, group_type)
+            }
+            Pdu::ReadReq { handle } => match self.read(handle, 0).await {
+                Ok(mut value) => {
+                    value.truncate(usize::from(self.mtu) - 1);
+                    Ok(Pdu::ReadRsp(value))
+                }
+                Err(error) => Err((handle, error)),
+            },
+            Pdu::ReadBlobReq { handle, offset } => match self.read(handle, offset).await {
+                Ok(mut value) => {
+                    value.truncate(usize::from(self.mtu) - 1);
+                    Ok(Pdu::ReadBlobRsp(value))
+                }
+                Err(error) => Err((handle, error)),
+            },
+            Pdu::ReadMultipleReq(handles) => {
+                let mut values = Vec::new();
+                let mut result = Ok(());
+                for handle in handles {
+                    match self.read(handle, 0).await {
+                        Ok(value) => values.extend(value),
+                        Err(error) => {
+                            result = Err((handle, error));
+                            break;
+                        }
+                    }
+                }
+                values.truncate(usize::from(self.mtu) - 1);
+                result.map(|()| Pdu::ReadMultipleRsp(values))
+            }
+            Pdu::ReadMultipleVariableReq(handles) => {
+                let mut values = Vec::new();
+                let mut result = Ok(());
+                let mut remaining = usize::from(self.mtu) - 1;
+                for handle in handles {
+                    match self.read(handle, 0).await {
+                        Ok(mut value) => {
+                            if remaining >= 2 {
+                                value.truncate(remaining - 2);
+                                remaining -= 2 + value.len();
+                                values.push(value);
+                            }
+                        }
+                        Err(error) => {
+                            result = Err((handle, error));
+                            break;
+                        }
+                    }
+                }
+                result.map(|()| Pdu::ReadMultipleVariableRsp(values))
+            }
+            Pdu::WriteReq { handle, value } => self
+                .write(handle, value, 0, WriteOp::Request, false)
+                .await
+                .map(|()| Pdu::WriteRsp)
+                .map_err(|e| (handle, e)),
+            Pdu::WriteCmd { handle, value } => {
+                if let Err(err) = self.write(handle, value, 0, WriteOp::Command, false).await {
+                    log::trace!("ATT write command to handle 0x{handle:04x} failed: {err}");
+                }
+                return Ok(None);
+            }
+            Pdu::PrepareWriteReq { handle, offset, value } => self.prepare_write(handle, offset, value).await,
+            Pdu::ExecuteWriteReq(execute) => self.execute_write(execute).await,
+            Pdu::HandleValueCfm => {
+                self.confirm().await?;
+                return Ok(None);
+            }
+            pdu if pdu.opcode() & 0x40 != 0 || !is_request(&pdu) => return Ok(None),
+            _ => Err((0, ErrorCode::RequestNotSupported)),
+        };
+        Ok(Some(rsp.unwrap_or_else(|(handle, error)| Pdu::ErrorRsp { request_opcode, handle, error })))
+    }
+
+    fn find_information(&self, start_handle: u16, end_handle: u16) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        check_range(start_handle, end_handle)?;
+        let mut entries: Vec<(u16, Uuid)> = Vec::new();
+        let mut short = true;
+        for attr in self.db.range(start_handle, end_handle) {
+            let attr_short = attr.attribute_type.as_u16().is_some();
+            if entries.is_empty() {
+                short = attr_short;
+            } else if attr_short != short {
+                break;
+            }
+            let entry_len = if short { 4 } else { 18 };
+            if 2 + (entries.len() + 1) * entry_len > usize::from(self.mtu) {
+                break;
+            }
+            entries.push((attr.handle, attr.attribute_type));
+        }
+        if entries.is_empty() {
+            return Err((start_handle, ErrorCode::AttributeNotFound));
+        }
+        Ok(Pdu::FindInformationRsp(entries))
+    }
+
+    fn find_by_type_value(
+        &self, start_handle: u16, end_handle: u16, attribute_type: u16, value: &[u8],
+    ) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        check_range(start_handle, end_handle)?;
+        let attribute_type = Uuid::from_u16(attribute_type);
+        let mut entries = Vec::new();
+        for attr in self.db.range(start_handle, end_handle) {
+            if attr.attribute_type != attribute_type {
+                continue;
+            }
+            let matches = match &attr.value {
+                Value::Static(v) => v == value,
+                _ => false,
+            };
+            if !matches {
+                continue;
+            }
+            if 1 + (entries.len() + 1) * 4 > usize::from(self.mtu) {
+                break;
+            }
+            entries.push((attr.handle, attr.group_end.unwrap_or(attr.handle)));
+        }
+        if entries.is_empty() {
+            return Err((start_handle, ErrorCode::AttributeNotFound));
+        }
+        Ok(Pdu::FindByTypeValueRsp(entries))
+    }
+
+    async fn read_by_type(
+        &mut self, start_handle: u16, end_handle: u16, attribute_type: Uuid,
+    ) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        check_range(start_handle, end_handle)?;
+        let db = self.db.clone();
+        let max_len = (usize::from(self.mtu) - 4).min(253);
+        let mut entries: Vec<(u16, Vec<u8>)> = Vec::new();
+        for attr in db.range(start_handle, end_handle).filter(|attr| attr.attribute_type == attribute_type) {
+            let mut value = match self.read_attribute(attr, 0).await {
+                Ok(value) => value,
+                Err(error) if entries.is_empty() => return Err((attr.handle, error)),
+                Err(_) => break,
+            };
+            value.truncate(max_len);
+            if let Some((_, first)) = entries.first() {
+                if first.len() != value.len()
+                    || 2 + (entries.len() + 1) * (2 + value.len()) > usize::from(self.mtu)
+                {
+                    break;
+                }
+            }
+            entries.push((attr.handle, value));
+        }
+        if entries.is_empty() {
+            return Err((start_handle, ErrorCode::AttributeNotFound));
+        }
+        Ok(Pdu::ReadByTypeRsp(entries))
+    }
+
+    fn read_by_group_type(
+        &self, start_handle: u16, end_handle: u16, group_type: Uuid,
+    ) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        check_range(start_handle, end_handle)?;
+        if group_type != Uuid::from_u16(TYPE_PRIMARY_SERVICE)
+            && group_type != Uuid::from_u16(TYPE_SECONDARY_SERVICE)
+        {
+            return Err((start_handle, ErrorCode::UnsupportedGroupType));
+        }
+        let max_len = (usize::from(self.mtu) - 6).min(251);
+        let mut entries: Vec<(u16, u16, Vec<u8>)> = Vec::new();
+        for attr in self.db.range(start_handle, end_handle).filter(|attr| attr.attribute_type == group_type) {
+            let Value::Static(value) = &attr.value else { continue };
+            let mut value = value.clone();
+            value.truncate(max_len);
+            if let Some((_, _, first)) = entries.first() {
+                if first.len() != value.len()
+                    || 2 + (entries.len() + 1) * (4 + value.len()) > usize::from(self.mtu)
+                {
+                    break;
+                }
+            }
+            entries.push((attr.handle, attr.group_end.unwrap_or(attr.handle), value));
+        }
+        if entries.is_empty() {
+            return Err((start_handle, ErrorCode::AttributeNotFound));
+        }
+        Ok(Pdu::ReadByGroupTypeRsp(entries))
+    }
+
+    /// Reads the value of the attribute with the specified handle.
+    async fn read(&mut self, handle: u16, offset: u16) -> AttResult<Vec<u8>> {
+        let db = self.db.clone();
+        let attr = db.attribute(handle).ok_or(ErrorCode::InvalidHandle)?;
+        self.read_attribute(attr, offset).await
+    }
+
+    async fn read_attribute(&mut self, attr: &Attribute, offset: u16) -> AttResult<Vec<u8>> {
+        let static_value =
+            |value: &[u8]| value.get(usize::from(offset)..).map(|v| v.to_vec()).ok_or(ErrorCode::InvalidOffset);
+        match &attr.value {
+            Value::Static(value) => static_value(value),
+            Value::Ccc(idx) => static_value(&self.ccc.get(idx).copied().unwrap_or_default().to_le_bytes()),
+            Value::Characteristic(idx) => {
+                let c = &self.db.characteristics[*idx].c;
+                let read = c.read.as_ref().ok_or(ErrorCode::ReadNotPermitted)?;
+                if !properties(c).read {
+                    return Err(ErrorCode::ReadNotPermitted);
+                }
+                Security {
+                    encrypt: read.encrypt_read,
+                    authenticated: read.encrypt_authenticated_read,
+                    secure: read.secure_read,
+                }
+                .check(self.security_level())?;
+                let req = CharacteristicReadRequest {
+                    adapter_name: String::new(),
+                    device_address: self.device_address,
+                    offset,
+                    mtu: self.mtu,
+                    link: Some(LinkType::Le),
+                };
+                (read.fun)(req).await.map_err(|err| req_error_code(err, false))
+            }
+            Value::Descriptor(idx) => {
+                let d = &self.db.descriptors[*idx];
+                let read = d.read.as_ref().ok_or(ErrorCode::ReadNotPermitted)?;
+                if !(read.read || read.encrypt_read || read.encrypt_authenticated_read || read.secure_read) {
+                    return Err(ErrorCode::ReadNotPermitted);
+                }
+                Security {
+                    encrypt: read.encrypt_read,
+                    authenticated: read.encrypt_authenticated_read,
+                    secure: read.secure_read,
+                }
+                .check(self.security_level())?;
+                let req = DescriptorReadRequest {
+                    adapter_name: String::new(),
+                    device_address: self.device_address,
+                    offset,
+                    link: Some(LinkType::Le),
+                };
+                (read.fun)(req).await.map_err(|err| req_error_code(err, false))
+            }
+        }
+    }
+
+    /// Writes the value of the attribute with the specified handle.
+    async fn write(
+        &mut self, handle: u16, value: Vec<u8>, offset: u16, op_type: WriteOp, prepare_authorize: bool,
+    ) -> AttResult<()> {
+        let db = self.db.clone();
+        let attr = db.attribute(handle).ok_or(ErrorCode::InvalidHandle)?;
+        match &attr.value {
+            Value::Static(_) => Err(ErrorCode::WriteNotPermitted),
+            Value::Ccc(idx) => {
+                if offset != 0 || prepare_authorize {
+                    return Err(ErrorCode::InvalidOffset);
+                }
+                let ccc =
+                    <[u8; 2]>::try_from(value.as_slice()).map_err(|_| ErrorCode::InvalidAttributeValueLength)?;
+                self.configure(*idx, u16::from_le_bytes(ccc)).await
+            }
+            Value::Characteristic(idx) => {
+                let c = &db.characteristics[*idx].c;
+                let write = c.write.as_ref().ok_or(ErrorCode::WriteNotPermitted)?;
+                let props = properties(c);
+                let permitted = match op_type {
+                    WriteOp::Command => props.write_without_response,
+                    WriteOp::Request => props.write,
+                    WriteOp::Reliable => props.write || write.reliable_write,
+                };
+                if !permitted {
+                    return Err(ErrorCode::WriteNotPermitted);
+                }
+                Security {
+                    encrypt: write.encrypt_write,
+                    authenticated: write.encrypt_authenticated_write,
+                    secure: write.secure_write,
+                }
+                .check(self.security_level())?;
+                if prepare_authorize && !c.authorize {
+                    return Ok(());
+                }
+                match &write.method {
+                    CharacteristicWriteMethod::Fun(fun) => {
+                        let req = CharacteristicWriteRequest {
+                            adapter_name: String::new(),
+                            device_address: self.device_address,
+                            offset,
+                            op_type,
+                            mtu: self.mtu,
+                            link: Some(LinkType::Le),
+                            prepare_authorize,
+                        };
+                        fun(value, req).await.map_err(|err| req_error_code(err, true))
+                    }
+                    CharacteristicWriteMethod::Io if op_type == WriteOp::Reliable || offset != 0 => {
+                        Err(ErrorCode::RequestNotSupported)
+                    }
+                    CharacteristicWriteMethod::Io => self.write_io(*idx, value).await,
+                }
+            }
+            Value::Descriptor(idx) => {
+                let d = &db.descriptors[*idx];
+                let write = d.write.as_ref().ok_or(ErrorCode::WriteNotPermitted)?;
+                if op_type == WriteOp::Command
+                    || !(write.write
+                        || write.encrypt_write
+                        || write.encrypt_authenticated_write
+                        || write.secure_write)
+                {
+                    return Err(ErrorCode::WriteNotPermitted);
+                }
+                Security {
+                    encrypt: write.encrypt_write,
+                    authenticated: write.encrypt_authenticated_write,
+                    secure: write.secure_write,
+                }
+                .check(self.security_level())?;
+                if prepare_authorize && !d.authorize {
+                    return Ok(());
+                }
+                let req = DescriptorWriteRequest {
+                    adapter_name: String::new(),
+                    device_address: self.device_address,
+                    offset,
+                    link: Some(LinkType::Le),
+                    prepare_authorize,
+                };
+                (write.fun)(value, req).await.map_err(|err| req_error_code(err, true))
+            }
+        }
+    }
+
+    /// Writes a value to the reader of a characteristic using [CharacteristicWriteMethod::Io].
+    ///
+    /// The reader is requested from the application upon the first write.
+    async fn write_io(&mut self, idx: usize, value: Vec<u8>) -> AttResult<()> {
+        if let Some(socket) = self.write_io.get(&idx) {
+            if socket.send(&value).await.is_ok() {
+                return Ok(());
+            }
+            self.write_io.remove(&idx);
+        }
+
+        let c = &self.db.characteristics[idx].c;
+        let (tx, rx) = oneshot::channel();
+        let req = CharacteristicWriteIoRequest {
+            adapter_name: String::new(),
+            device_address: self.device_address,
+            mtu: self.mtu,
+            link: Some(LinkType::Le),
+            tx,
+        };
+        c.control_handle
+            .events_tx
+            .send(CharacteristicControlEvent::Write(req))
+            .await
+            .map_err(|_| ErrorCode::UnlikelyError)?;
+        let fd = rx.await.map_err(|_| ErrorCode::UnlikelyError)?.map_err(|err| req_error_code(err, true))?;
+        let socket = datagram_from_fd(fd).map_err(|_| ErrorCode::UnlikelyError)?;
+        socket.send(&value).await.map_err(|_| ErrorCode::UnlikelyError)?;
+        self.write_io.insert(idx, socket);
+        Ok(())
+    }
+
+    async fn prepare_write(
+        &mut self, handle: u16, offset: u16, value: Vec<u8>,
+    ) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        if self.prepared.len() >= MAX_PREPARED_WRITES {
+            return Err((handle, ErrorCode::PrepareQueueFull));
+        }
+        self.write(handle, value.clone(), offset, WriteOp::Reliable, true)
+            .await
+            .map_err(|error| (handle, error))?;
+        self.prepared.push((handle, offset, value.clone()));
+        Ok(Pdu::PrepareWriteRsp { handle, offset, value })
+    }
+
+    async fn execute_write(&mut self, execute: bool) -> std::result::Result<Pdu, (u16, ErrorCode)> {
+        let prepared = take(&mut self.prepared);
+        if !execute {
+            return Ok(Pdu::ExecuteWriteRsp);
+        }
+
+        // Merge contiguous parts of the same attribute.
+        let mut writes: Vec<(u16, u16, Vec<u8>)> = Vec::new();
+        for (handle, offset, value) in prepared {
+            match writes.last_mut() {
+                Some((last_handle, last_offset, last_value))
+                    if *last_handle == handle
+                        && usize::from(*last_offset) + last_value.len() == usize::from(offset) =>
+                {
+                    last_value.extend(value)
+                }
+                _ => writes.push((handle, offset, value)),
+            }
+        }
+
+        for (handle, offset, value) in writes {
+            if usize::from(offset) + value.len() > MAX_VALUE_LEN {
+                return Err((handle, ErrorCode::InvalidAttributeValueLength));
+            }
+            self.write(handle, value, offset, WriteOp::Reliable, false).await.map_err(|error| (handle, error))?;
+        }
+        Ok(Pdu::ExecuteWriteRsp)
+    }
+
+    /// Applies a client characteristic configuration and starts or stops the notification session.
+    async fn configure(&mut self, idx: usize, ccc: u16) -> AttResult<()> {
+        let entry = &self.db.characteristics[idx];
+        let props = properties(&entry.c);
+        if ccc & !(CCC_NOTIFY | CCC_INDICATE) != 0
+            || (ccc & CCC_NOTIFY != 0 && !props.notify)
+            || (ccc & CCC_INDICATE != 0 && !props.indicate)
+        {
+            return Err(ErrorCode::from(CCC_IMPROPERLY_CONFIGURED));
+        }
+
+        let prev = self.ccc.insert(idx, ccc).unwrap_or_default();
+        if prev == ccc {
+            return Ok(());
+        }
+        self.sessions.remove(&idx);
+        if self.indicating == Some(idx) {
+            self.indicating = None;
+        }
+        self.indications.retain(|(i, _)| *i != idx);
+        if ccc == 0 {
+            self.ccc.remove(&idx);
+            return Ok(());
+        }
+
+        let Some(notify) = &entry.c.notify else { return Ok(()) };
+        let confirming = ccc & CCC_NOTIFY == 0;
+        let session = match &notify.method {
+            CharacteristicNotifyMethod::Fun(fun) => {
+                let (stop_notify_tx, stop_notify_rx) = mpsc::channel(1);
+                let (confirm_tx, confirm_rx) = if confirming {
+                    let (tx, rx) = mpsc::channel(1);
+                    (Some(tx), Some(rx))
+                } else {
+                    (None, None)
+                };
+                let notifier = CharacteristicNotifier::att(
+                    entry.value_handle,
+                    self.notify_tx.clone(),
+                    stop_notify_tx,
+                    confirm_rx,
+                );
+                tokio::spawn(fun(notifier));
+                Session { confirm_tx, _stop_notify_rx: Some(stop_notify_rx), forwarder: None }
+            }
+            CharacteristicNotifyMethod::Io => {
+                let (fd, socket) = make_socket_pair(true).map_err(|_| ErrorCode::UnlikelyError)?;
+                let writer = CharacteristicWriter {
+                    adapter_name: String::new(),
+                    device_address: self.device_address,
+                    mtu: usize::from(self.mtu) - 3,
+                    socket,
+                };
+                entry
+                    .c
+                    .control_handle
+                    .events_tx
+                    .send(CharacteristicControlEvent::Notify(writer))
+                    .await
+                    .map_err(|_| ErrorCode::UnlikelyError)?;
+                let reader = datagram_from_fd(fd).map_err(|_| ErrorCode::UnlikelyError)?;
+                let forwarder = tokio::spawn(forward_notifications(
+                    reader,
+                    entry.value_handle,
+                    self.mtu,
+                    self.notify_tx.clone(),
+                ));
+                Session { confirm_tx: None, _stop_notify_rx: None, forwarder: Some(forwarder) }
+            }
+        };
+        self.sessions.insert(idx, session);
+        Ok(())
+    }
+
+    /// Sends a value from a notification session as notification or indication,
+    /// depending on the client characteristic configuration.
+    async fn notify(&mut self, handle: u16, mut value: Vec<u8>) -> Result<()> {
+        let Some(idx) = self.db.characteristic_by_value_handle(handle) else { return Ok(()) };
+        let ccc = self.ccc.get(&idx).copied().unwrap_or_default();
+        value.truncate(usize::from(self.mtu) - 3);
+        if ccc & CCC_NOTIFY != 0 {
+            self.send(Pdu::HandleValueNtf { handle, value }).await
+        } else if ccc & CCC_INDICATE != 0 {
+            self.indications.push_back((idx, value));
+            self.indicate_next().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sends the next queued indication, if none is awaiting confirmation.
+    async fn indicate_next(&mut self) -> Result<()> {
+        if self.indicating.is_some() {
+            return Ok(());
+        }
+        if let Some((idx, value)) = self.indications.pop_front() {
+            self.indicating = Some(idx);
+            let handle = self.db.characteristics[idx].value_handle;
+            self.send(Pdu::HandleValueInd { handle, value }).await?;
+        }
+        Ok(())
+    }
+
+    /// Handles the confirmation of an indication.
+    async fn confirm(&mut self) -> Result<()> {
+        if let Some(idx) = self.indicating.take() {
+            if let Some(Session { confirm_tx: Some(confirm_tx), .. }) = self.sessions.get(&idx) {
+                let _ = confirm_tx.try_send(());
+            }
+        }
+        self.indicate_next().await
+    }
+}
+
+/// Forwards values written to a [CharacteristicWriter] to the connection.
+async fn forward_notifications(
+    socket: UnixDatagram, handle: u16, mtu: u16, tx: mpsc::UnboundedSender<(u16, Vec<u8>)>,
+) {
+    let mut buf = vec![0; mtu.into()];
+    loop {
+        match socket.recv(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if tx.send((handle, buf[..n].to_vec())).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Converts a file descriptor of a UNIX socket pair into an asynchronous datagram socket.
+fn datagram_from_fd(fd: dbus::arg::OwnedFd) -> Result<UnixDatagram> {
+    let socket = unsafe { std::os::unix::net::UnixDatagram::from_raw_fd(fd.into_fd()) };
+    socket.set_nonblocking(true)?;
+    UnixDatagram::from_std(socket)
+}
+
+/// Checks the handle range of a request.
+fn check_range(start_handle: u16, end_handle: u16) -> std::result::Result<(), (u16, ErrorCode)> {
+    if start_handle == 0 || start_handle > end_handle {
+        Err((start_handle, ErrorCode::InvalidHandle))
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether the PDU is a request that requires a response.
+fn is_request(pdu: &Pdu) -> bool {
+    match pdu {
+        Pdu::Other { .. } => true,
+        pdu => !matches!(
+            pdu.opcode(),
+            opcode::ERROR_RSP
+                | opcode::EXCHANGE_MTU_RSP
+                | opcode::FIND_INFORMATION_RSP
+                | opcode::FIND_BY_TYPE_VALUE_RSP
+                | opcode::READ_BY_TYPE_RSP
+                | opcode::READ_RSP
+                | opcode::READ_BLOB_RSP
+                | opcode::READ_MULTIPLE_RSP
+                | opcode::READ_BY_GROUP_TYPE_RSP
+                | opcode::WRITE_RSP
+                | opcode::PREPARE_WRITE_RSP
+                | opcode::EXECUTE_WRITE_RSP
+                | opcode::HANDLE_VALUE_NTF
+                | opcode::HANDLE_VALUE_IND
+                | opcode::READ_MULTIPLE_VARIABLE_RSP
+                | opcode::MULTIPLE_HANDLE_VALUE_NTF
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::gatt::local::{
+        characteristic_control, descriptor_control, service_control, CharacteristicNotify, CharacteristicRead,
+        CharacteristicWrite, DescriptorRead, Service,
+    };
+
+    const SERVICE_UUID: Uuid = Uuid::from_u128(0x6e400001_b5a3_f393_e0a9_e50e24dcca9e);
+    const CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0x6e400002_b5a3_f393_e0a9_e50e24dcca9e);
+
+    fn handle(handle: u16) -> Option<NonZeroU16> {
+        NonZeroU16::new(handle)
+    }
+
+    /// Application with a battery service using automatic handles, a custom service using
+    /// specified handles and a secondary service.
+    fn application() -> Application {
+        Application {
+            services: vec![
+                Service {
+                    uuid: Uuid::from_u16(0x180f),
+                    primary: true,
+                    characteristics: vec![Characteristic {
+                        uuid: Uuid::from_u16(0x2a19),
+                        read: Some(CharacteristicRead {
+                            read: true,
+                            fun: Box::new(|_| async move { Ok(vec![0x64]) }.boxed()),
+                            ..Default::default()
+                        }),
+                        notify: Some(CharacteristicNotify { notify: true, ..Default::default() }),
+                        descriptors: vec![Descriptor {
+                            uuid: Uuid::from_u16(0x2901),
+                            read: Some(DescriptorRead {
+                                read: true,
+                                fun: Box::new(|_| async move { Err(ReqError::NotAuthorized) }.boxed()),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                Service {
+                    uuid: SERVICE_UUID,
+                    handle: handle(0x0010),
+                    primary: true,
+                    characteristics: vec![Characteristic {
+                        uuid: CHARACTERISTIC_UUID,
+                        handle: handle(0x0020),
+                        write: Some(CharacteristicWrite {
+                            write: true,
+                            reliable_write: true,
+                            ..Default::default()
+                        }),
+                        descriptors: vec![
+                            Descriptor {
+                                uuid: Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION),
+                                ..Default::default()
+                            },
+                            Descriptor {
+                                uuid: Uuid::from_u16(0x2901),
+                                handle: handle(0x0030),
+                                ..Default::default()
+                            },
+                        ],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                Service { uuid: Uuid::from_u16(0x1801), primary: false, ..Default::default() },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Connection serving the database over one end of a socket pair.
+    fn connection(db: Database, mtu: u16) -> (Connection, SeqPacket) {
+        let mut fds = [0; 2];
+        let ret = unsafe {
+            libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_NONBLOCK, 0, fds.as_mut_ptr())
+        };
+        assert_eq!(ret, 0);
+        let (socket, peer) =
+            unsafe { (SeqPacket::from_raw_fd(fds[0]).unwrap(), SeqPacket::from_raw_fd(fds[1]).unwrap()) };
+        let (notify_tx, _) = mpsc::unbounded_channel();
+        let conn = Connection {
+            db: Arc::new(db),
+            socket,
+            device_address: Address::any(),
+            mtu,
+            ccc: HashMap::new(),
+            sessions: HashMap::new(),
+            write_io: HashMap::new(),
+            prepared: Vec::new(),
+            notify_tx,
+            indicating: None,
+            indications: VecDeque::new(),
+        };
+        (conn, peer)
+    }
+
+    fn attributes(db: &Database) -> Vec<(u16, Uuid)> {
+        db.attributes.iter().map(|attr| (attr.handle, attr.attribute_type)).collect()
+    }
+
+    #[test]
+    fn handle_allocation() {
+        let (service_control, service_handle) = service_control();
+        let (char_control, char_handle) = characteristic_control();
+        let (desc_control, desc_handle) = descriptor_control();
+        let mut app = application();
+        app.services[1].control_handle = service_handle;
+        app.services[1].characteristics[0].control_handle = char_handle;
+        app.services[1].characteristics[0].descriptors[1].control_handle = desc_handle;
+
+        let db = Database::new(app).unwrap();
+        assert_eq!(
+            attributes(&db),
+            [
+                (0x0001, Uuid::from_u16(TYPE_PRIMARY_SERVICE)),
+                (0x0002, Uuid::from_u16(TYPE_CHARACTERISTIC)),
+                (0x0003, Uuid::from_u16(0x2a19)),
+                (0x0004, Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION)),
+                (0x0005, Uuid::from_u16(0x2901)),
+                (0x0010, Uuid::from_u16(TYPE_PRIMARY_SERVICE)),
+                (0x001f, Uuid::from_u16(TYPE_CHARACTERISTIC)),
+                (0x0020, CHARACTERISTIC_UUID),
+                (0x0021, Uuid::from_u16(TYPE_CHARACTERISTIC_EXTENDED_PROPERTIES)),
+                (0x0030, Uuid::from_u16(0x2901)),
+                (0x0031, Uuid::from_u16(TYPE_SECONDARY_SERVICE)),
+            ]
+        );
+        assert_eq!(service_control.handle().unwrap().get(), 0x0010);
+        assert_eq!(char_control.handle().unwrap().get(), 0x0020);
+        assert_eq!(desc_control.handle().unwrap().get(), 0x0030);
+
+        let group_ends: Vec<_> = db.attributes.iter().filter_map(|attr| attr.group_end).collect();
+        assert_eq!(group_ends, [0x0005, 0x0030, 0x0031]);
+        assert_eq!(db.characteristic_by_value_handle(0x0020), Some(1));
+        assert_eq!(db.characteristic_by_value_handle(0x001f), None);
+
+        // Reliable write is announced through the extended properties descriptor.
+        let Value::Static(ext) = &db.attribute(0x0021).unwrap().value else { panic!("invalid descriptor") };
+        assert_eq!(ext, &[0x01, 0x00]);
+    }
+
+    #[test]
+    fn handle_conflicts() {
+        let mut app = application();
+        app.services[1].handle = handle(0x0005);
+        assert_eq!(Database::new(app).err().unwrap().kind(), ErrorKind::InvalidInput);
+
+        let mut app = application();
+        app.services[1].characteristics[0].handle = handle(0x0010);
+        assert_eq!(Database::new(app).err().unwrap().kind(), ErrorKind::InvalidInput);
+
+        let mut app = application();
+        app.services[1].characteristics[0].descriptors[1].handle = handle(0x0021);
+        assert_eq!(Database::new(app).err().unwrap().kind(), ErrorKind::InvalidInput);
+
+        let mut app = application();
+        app.services[0].characteristics[0].handle = handle(0x0001);
+        assert_eq!(Database::new(app).err().unwrap().kind(), ErrorKind::InvalidInput);
+
+        let mut app = application();
+        app.services[2].handle = handle(u16::MAX);
+        app.services.push(Service { uuid: Uuid::from_u16(0x1800), ..Default::default() });
+        assert_eq!(Database::new(app).err().unwrap().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn find_information() {
+        let (conn, _peer) = connection(Database::new(application()).unwrap(), DEFAULT_MTU);
+
+        // 16-bit attribute types are limited by the MTU and must not be mixed with 128-bit types.
+        assert_eq!(
+            conn.find_information(0x0001, 0xffff).unwrap(),
+            Pdu::FindInformationRsp(vec![
+                (0x0001, Uuid::from_u16(TYPE_PRIMARY_SERVICE)),
+                (0x0002, Uuid::from_u16(TYPE_CHARACTERISTIC)),
+                (0x0003, Uuid::from_u16(0x2a19)),
+                (0x0004, Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION)),
+                (0x0005, Uuid::from_u16(0x2901)),
+            ])
+        );
+        assert_eq!(
+            conn.find_information(0x0006, 0xffff).unwrap(),
+            Pdu::FindInformationRsp(vec![
+                (0x0010, Uuid::from_u16(TYPE_PRIMARY_SERVICE)),
+                (0x001f, Uuid::from_u16(TYPE_CHARACTERISTIC)),
+            ])
+        );
+        assert_eq!(
+            conn.find_information(0x0020, 0xffff).unwrap(),
+            Pdu::FindInformationRsp(vec![(0x0020, CHARACTERISTIC_UUID)])
+        );
+
+        assert_eq!(conn.find_information(0x0032, 0xffff).unwrap_err(), (0x0032, ErrorCode::AttributeNotFound));
+        assert_eq!(conn.find_information(0x0000, 0xffff).unwrap_err(), (0x0000, ErrorCode::InvalidHandle));
+        assert_eq!(conn.find_information(0x0005, 0x0004).unwrap_err(), (0x0005, ErrorCode::InvalidHandle));
+    }
+
+    #[tokio::test]
+    async fn read_by_type() {
+        let (mut conn, _peer) = connection(Database::new(application()).unwrap(), DEFAULT_MTU);
+        let characteristic = Uuid::from_u16(TYPE_CHARACTERISTIC);
+
+        // Declarations of different length are returned in separate responses.
+        assert_eq!(
+            conn.read_by_type(0x0001, 0xffff, characteristic).await.unwrap(),
+            Pdu::ReadByTypeRsp(vec![(0x0002, vec![0x12, 0x03, 0x00, 0x19, 0x2a])])
+        );
+        let mut decl = vec![0x88, 0x20, 0x00];
+        decl.extend_from_slice(&CHARACTERISTIC_UUID.as_u128().to_le_bytes());
+        assert_eq!(
+            conn.read_by_type(0x0003, 0xffff, characteristic).await.unwrap(),
+            Pdu::ReadByTypeRsp(vec![(0x001f, decl)])
+        );
+
+        assert_eq!(
+            conn.read_by_type(0x0001, 0xffff, Uuid::from_u16(0x2a19)).await.unwrap(),
+            Pdu::ReadByTypeRsp(vec![(0x0003, vec![0x64])])
+        );
+        assert_eq!(
+            conn.read_by_type(0x0001, 0xffff, Uuid::from_u16(TYPE_CLIENT_CHARACTERISTIC_CONFIGURATION))
+                .await
+                .unwrap(),
+            Pdu::ReadByTypeRsp(vec![(0x0004, vec![0x00, 0x00])])
+        );
+
+        // Errors of the read callback are reported for the first attribute.
+        assert_eq!(
+            conn.read_by_type(0x0001, 0xffff, Uuid::from_u16(0x2901)).await.unwrap_err(),
+            (0x0005, ErrorCode::InsufficientAuthorization)
+        );
+        assert_eq!(
+            conn.read_by_type(0x0006, 0xffff, Uuid::from_u16(0x2901)).await.unwrap_err(),
+            (0x0030, ErrorCode::ReadNotPermitted)
+        );
+        assert_eq!(
+            conn.read_by_type(0x0022, 0x002f, characteristic).await.unwrap_err(),
+            (0x0022, ErrorCode::AttributeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn read_by_group_type() {
+        let (conn, _peer) = connection(Database::new(application()).unwrap(), DEFAULT_MTU);
+        let primary = Uuid::from_u16(TYPE_PRIMARY_SERVICE);
+
+        assert_eq!(
+            conn.read_by_group_type(0x0001, 0xffff, primary).unwrap(),
+            Pdu::ReadByGroupTypeRsp(vec![(0x0001, 0x0005, vec![0x0f, 0x18])])
+        );
+        assert_eq!(
+            conn.read_by_group_type(0x0006, 0xffff, primary).unwrap(),
+            Pdu::ReadByGroupTypeRsp(vec![(0x0010, 0x0030, SERVICE_UUID.as_u128().to_le_bytes().to_vec())])
+        );
+        assert_eq!(
+            conn.read_by_group_type(0x0001, 0xffff, Uuid::from_u16(TYPE_SECONDARY_SERVICE)).unwrap(),
+            Pdu::ReadByGroupTypeRsp(vec![(0x0031, 0x0031, vec![0x01, 0x18])])
+        );
+
+        assert_eq!(
+            conn.read_by_group_type(0x0011, 0xffff, primary).unwrap_err(),
+            (0x0011, ErrorCode::AttributeNotFound)
+        );
+        assert_eq!(
+            conn.read_by_group_type(0x0001, 0xffff, Uuid::from_u16(TYPE_CHARACTERISTIC)).unwrap_err(),
+            (0x0001, ErrorCode::UnsupportedGroupType)
+        );
+    }
+
+    #[tokio::test]
+    async fn read_by_group_type_mtu() {
+        let mut app = application();
+        app.services.truncate(1);
+        for uuid in [0x1800, 0x1801, 0x180a, 0x180d, 0x1810] {
+            app.services.push(Service { uuid: Uuid::from_u16(uuid), primary: true, ..Default::default() });
+        }
+        let (conn, _peer) = connection(Database::new(app).unwrap(), DEFAULT_MTU);
+
+        // Entries of 6 bytes each fit three times into the default MTU.
+        assert_eq!(
+            conn.read_by_group_type(0x0001, 0xffff, Uuid::from_u16(TYPE_PRIMARY_SERVICE)).unwrap(),
+            Pdu::ReadByGroupTypeRsp(vec![
+                (0x0001, 0x0005, vec![0x0f, 0x18]),
+                (0x0006, 0x0006, vec![0x00, 0x18]),
+                (0x0007, 0x0007, vec![0x01, 0x18]),
+            ])
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        for (err, read_code, write_code) in [
+            (ReqError::Failed, ErrorCode::UnlikelyError, ErrorCode::UnlikelyError),
+            (ReqError::InProgress, ErrorCode::from(ALREADY_IN_PROGRESS), ErrorCode::from(ALREADY_IN_PROGRESS)),
+            (ReqError::InvalidOffset, ErrorCode::InvalidOffset, ErrorCode::InvalidOffset),
+            (
+                ReqError::InvalidValueLength,
+                ErrorCode::InvalidAttributeValueLength,
+                ErrorCode::InvalidAttributeValueLength,
+            ),
+            (ReqError::NotPermitted, ErrorCode::ReadNotPermitted, ErrorCode::WriteNotPermitted),
+            (ReqError::NotAuthorized, ErrorCode::InsufficientAuthorization, ErrorCode::InsufficientAuthorization),
+            (ReqError::NotSupported, ErrorCode::RequestNotSupported, ErrorCode::RequestNotSupported),
+        ] {
+            assert_eq!(req_error_code(err, false), read_code, "{err:?}");
+            assert_eq!(req_error_code(err, true), write_code, "{err:?}");
+        }
+        assert_eq!(u8::from(ErrorCode::from(ALREADY_IN_PROGRESS)), 0xfe);
+    }
+}
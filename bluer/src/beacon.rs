@@ -0,0 +1,679 @@
+//! Bluetooth LE beacon formats.
+//!
+//! This module decodes and encodes the following beacon formats:
+//!
+//! * [iBeacon](IBeacon), transmitted as manufacturer specific data of Apple Inc.,
+//! * [Eddystone](Eddystone) UID, URL, TLM and EID frames, transmitted as service data
+//!   of the Eddystone service UUID,
+//! * [AltBeacon](AltBeacon), transmitted as manufacturer specific data.
+//!
+//! Beacons can be decoded from the manufacturer and service data of a discovered
+//! Bluetooth device using [Beacon::from_device] or [Beacon::from_device_property]
+//! and from [AD structures](crate::ad::AdStructure) using [Beacon::from_ad_structures].
+//! A beacon is broadcast by registering the advertisement returned by
+//! [Beacon::to_advertisement] using [Adapter::advertise](crate::Adapter::advertise).
+
+use std::{fmt, time::Duration};
+use uuid::Uuid;
+
+use crate::{ad::AdStructure, UuidExt};
+
+#[cfg(feature = "bluetoothd")]
+use crate::{adv::Advertisement, adv::Type, Device, DeviceProperty, Result};
+
+/// Apple Inc. company identifier used by iBeacon.
+pub const APPLE_COMPANY_ID: u16 = 0x004c;
+
+/// 16-bit Eddystone service UUID.
+pub const EDDYSTONE_UUID16: u16 = 0xfeaa;
+
+/// Eddystone service UUID.
+pub const EDDYSTONE_UUID: Uuid = Uuid::from_u128(0x0000feaa_0000_1000_8000_00805f9b34fb);
+
+const IBEACON_PREFIX: [u8; 2] = [0x02, 0x15];
+const ALTBEACON_PREFIX: [u8; 2] = [0xbe, 0xac];
+
+const EDDYSTONE_UID: u8 = 0x00;
+const EDDYSTONE_URL: u8 = 0x10;
+const EDDYSTONE_TLM: u8 = 0x20;
+const EDDYSTONE_EID: u8 = 0x30;
+
+const EDDYSTONE_URL_SCHEMES: [&str; 4] = ["http://www.", "https://www.", "http://", "https://"];
+const EDDYSTONE_URL_EXPANSIONS: [&str; 14] = [
+    ".com/", ".org/", ".edu/", ".net/", ".info/", ".biz/", ".gov/", ".com", ".org", ".edu", ".net", ".info",
+    ".biz", ".gov",
+];
+const EDDYSTONE_URL_MAX_LEN: usize = 17;
+
+/// Invalid beacon error.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvalidBeacon(pub String);
+
+impl fmt::Display for InvalidBeacon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid beacon: {}", &self.0)
+    }
+}
+
+impl std::error::Error for InvalidBeacon {}
+
+/// Apple iBeacon.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IBeacon {
+    /// Proximity UUID.
+    pub uuid: Uuid,
+    /// Major number.
+    pub major: u16,
+    /// Minor number.
+    pub minor: u16,
+    /// Measured power at 1 m distance in dBm.
+    pub measured_power: i8,
+}
+
+impl IBeacon {
+    /// Decodes an iBeacon from manufacturer specific data.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if company_id != APPLE_COMPANY_ID || data.len() != 23 || data[..2] != IBEACON_PREFIX {
+            return None;
+        }
+        Some(Self {
+            uuid: Uuid::from_slice(&data[2..18]).ok()?,
+            major: u16::from_be_bytes([data[18], data[19]]),
+            minor: u16::from_be_bytes([data[20], data[21]]),
+            measured_power: data[22] as i8,
+        })
+    }
+
+    /// Encodes the iBeacon into manufacturer specific data for [APPLE_COMPANY_ID].
+    pub fn to_manufacturer_data(&self) -> Vec<u8> {
+        let mut data = IBEACON_PREFIX.to_vec();
+        data.extend_from_slice(self.uuid.as_bytes());
+        data.extend_from_slice(&self.major.to_be_bytes());
+        data.extend_from_slice(&self.minor.to_be_bytes());
+        data.push(self.measured_power as u8);
+        data
+    }
+}
+
+/// AltBeacon.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AltBeacon {
+    /// Company identifier of the beacon manufacturer.
+    pub company_id: u16,
+    /// Beacon identifier.
+    ///
+    /// Usually the first 16 bytes are an organizational unit identifier and
+    /// the remaining 4 bytes are used for further subdivision.
+    pub beacon_id: [u8; 20],
+    /// Average received signal strength at 1 m distance in dBm.
+    pub reference_rssi: i8,
+    /// Reserved for use by the manufacturer.
+    pub manufacturer_reserved: u8,
+}
+
+impl AltBeacon {
+    /// Decodes an AltBeacon from manufacturer specific data.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        if data.len() != 24 || data[..2] != ALTBEACON_PREFIX {
+            return None;
+        }
+        Some(Self {
+            company_id,
+            beacon_id: data[2..22].try_into().unwrap(),
+            reference_rssi: data[22] as i8,
+            manufacturer_reserved: data[23],
+        })
+    }
+
+    /// Encodes the AltBeacon into manufacturer specific data for its [company id](Self::company_id).
+    pub fn to_manufacturer_data(&self) -> Vec<u8> {
+        let mut data = ALTBEACON_PREFIX.to_vec();
+        data.extend_from_slice(&self.beacon_id);
+        data.push(self.reference_rssi as u8);
+        data.push(self.manufacturer_reserved);
+        data
+    }
+}
+
+/// Eddystone telemetry data.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EddystoneTlm {
+    /// Unencrypted telemetry.
+    Plain {
+        /// Battery voltage in mV, zero if not supported.
+        battery_voltage: u16,
+        /// Beacon temperature in degrees Celsius, if supported.
+        temperature: Option<f32>,
+        /// Number of advertising frames sent since power-up or reboot.
+        advertising_count: u32,
+        /// Time since power-up or reboot with a resolution of 0.1 s.
+        uptime: Duration,
+    },
+    /// Encrypted telemetry.
+    Encrypted {
+        /// Encrypted telemetry data.
+        etlm: [u8; 12],
+        /// Salt.
+        salt: u16,
+        /// Message integrity check.
+        mic: u16,
+    },
+}
+
+/// Eddystone frame.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Eddystone {
+    /// Unique identifier.
+    Uid {
+        /// Calibrated TX power at 0 m in dBm.
+        tx_power: i8,
+        /// Namespace.
+        namespace: [u8; 10],
+        /// Instance.
+        instance: [u8; 6],
+    },
+    /// Compressed URL.
+    Url {
+        /// Calibrated TX power at 0 m in dBm.
+        tx_power: i8,
+        /// URL.
+        url: String,
+    },
+    /// Telemetry.
+    Tlm(EddystoneTlm),
+    /// Ephemeral identifier.
+    Eid {
+        /// Calibrated TX power at 0 m in dBm.
+        tx_power: i8,
+        /// Ephemeral identifier.
+        eid: [u8; 8],
+    },
+}
+
+impl Eddystone {
+    /// Decodes an Eddystone frame from service data of [EDDYSTONE_UUID].
+    pub fn from_service_data(data: &[u8]) -> Option<Self> {
+        let (&frame_type, data) = data.split_first()?;
+        match frame_type {
+            EDDYSTONE_UID if data.len() == 17 || data.len() == 19 => Some(Self::Uid {
+                tx_power: data[0] as i8,
+                namespace: data[1..11].try_into().unwrap(),
+                instance: data[11..17].try_into().unwrap(),
+            }),
+            EDDYSTONE_URL if data.len() >= 2 => {
+                let mut url = EDDYSTONE_URL_SCHEMES.get(data[1] as usize)?.to_string();
+                for &c in &data[2..] {
+                    match EDDYSTONE_URL_EXPANSIONS.get(c as usize) {
+                        Some(expansion) => url.push_str(expansion),
+                        None if (0x21..0x7f).contains(&c) => url.push(c as char),
+                        None => return None,
+                    }
+                }
+                Some(Self::Url { tx_power: data[0] as i8, url })
+            }
+            EDDYSTONE_TLM => match data {
+                [0x00, bv1, bv2, t1, t2, ac @ .., s1, s2, s3, s4] if ac.len() == 4 => {
+                    let temperature = i16::from_be_bytes([*t1, *t2]);
+                    Some(Self::Tlm(EddystoneTlm::Plain {
+                        battery_voltage: u16::from_be_bytes([*bv1, *bv2]),
+                        temperature: (temperature != i16::MIN).then(|| temperature as f32 / 256.0),
+                        advertising_count: u32::from_be_bytes(ac.try_into().unwrap()),
+                        uptime: Duration::from_millis(u32::from_be_bytes([*s1, *s2, *s3, *s4]) as u64 * 100),
+                    }))
+                }
+                [0x01, etlm @ .., s1, s2, m1, m2] if etlm.len() == 12 => {
+                    Some(Self::Tlm(EddystoneTlm::Encrypted {
+                        etlm: etlm.try_into().unwrap(),
+                        salt: u16::from_be_bytes([*s1, *s2]),
+                        mic: u16::from_be_bytes([*m1, *m2]),
+                    }))
+                }
+                _ => None,
+            },
+            EDDYSTONE_EID if data.len() == 9 => {
+                Some(Self::Eid { tx_power: data[0] as i8, eid: data[1..9].try_into().unwrap() })
+            }
+            _ => None,
+        }
+    }
+
+    /// Encodes the Eddystone frame into service data for [EDDYSTONE_UUID].
+    pub fn to_service_data(&self) -> std::result::Result<Vec<u8>, InvalidBeacon> {
+        let mut data = Vec::new();
+        match self {
+            Self::Uid { tx_power, namespace, instance } => {
+                data.push(EDDYSTONE_UID);
+                data.push(*tx_power as u8);
+                data.extend_from_slice(namespace);
+                data.extend_from_slice(instance);
+                data.extend_from_slice(&[0, 0]);
+            }
+            Self::Url { tx_power, url } => {
+                data.push(EDDYSTONE_URL);
+                data.push(*tx_power as u8);
+
+                // Longer schemes have precedence over their prefixes.
+                let (scheme, mut rest) = [1, 0, 3, 2]
+                    .into_iter()
+                    .find_map(|i| url.strip_prefix(EDDYSTONE_URL_SCHEMES[i]).map(|rest| (i as u8, rest)))
+                    .ok_or_else(|| InvalidBeacon(format!("URL {url} has unsupported scheme")))?;
+                data.push(scheme);
+
+                let start = data.len();
+                while !rest.is_empty() {
+                    match EDDYSTONE_URL_EXPANSIONS.iter().position(|expansion| rest.starts_with(expansion)) {
+                        Some(code) => {
+                            data.push(code as u8);
+                            rest = &rest[EDDYSTONE_URL_EXPANSIONS[code].len()..];
+                        }
+                        None => {
+                            let c = rest.as_bytes()[0];
+                            if !(0x21..0x7f).contains(&c) {
+                                return Err(InvalidBeacon(format!("URL {url} contains invalid characters")));
+                            }
+                            data.push(c);
+                            rest = &rest[1..];
+                        }
+                    }
+                }
+                if data.len() - start > EDDYSTONE_URL_MAX_LEN {
+                    return Err(InvalidBeacon(format!("URL {url} is too long")));
+                }
+            }
+            Self::Tlm(EddystoneTlm::Plain { battery_voltage, temperature, advertising_count, uptime }) => {
+                data.push(EDDYSTONE_TLM);
+                data.push(0x00);
+                data.extend_from_slice(&battery_voltage.to_be_bytes());
+                let temperature = match temperature {
+                    Some(temperature) => {
+                        (temperature * 256.0).clamp(i16::MIN as f32 + 1.0, i16::MAX as f32) as i16
+                    }
+                    None => i16::MIN,
+                };
+                data.extend_from_slice(&temperature.to_be_bytes());
+                data.extend_from_slice(&advertising_count.to_be_bytes());
+                let uptime = (uptime.as_millis() / 100).min(u32::MAX as u128) as u32;
+                data.extend_from_slice(&uptime.to_be_bytes());
+            }
+            Self::Tlm(EddystoneTlm::Encrypted { etlm, salt, mic }) => {
+                data.push(EDDYSTONE_TLM);
+                data.push(0x01);
+                data.extend_from_slice(etlm);
+                data.extend_from_slice(&salt.to_be_bytes());
+                data.extend_from_slice(&mic.to_be_bytes());
+            }
+            Self::Eid { tx_power, eid } => {
+                data.push(EDDYSTONE_EID);
+                data.push(*tx_power as u8);
+                data.extend_from_slice(eid);
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Bluetooth LE beacon.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Beacon {
+    /// Apple iBeacon.
+    IBeacon(IBeacon),
+    /// Eddystone.
+    Eddystone(Eddystone),
+    /// AltBeacon.
+    AltBeacon(AltBeacon),
+}
+
+impl Beacon {
+    /// Decodes a beacon from manufacturer specific data.
+    pub fn from_manufacturer_data(company_id: u16, data: &[u8]) -> Option<Self> {
+        IBeacon::from_manufacturer_data(company_id, data)
+            .map(Self::IBeacon)
+            .or_else(|| AltBeacon::from_manufacturer_data(company_id, data).map(Self::AltBeacon))
+    }
+
+    /// Decodes a beacon from service data.
+    pub fn from_service_data(uuid: &Uuid, data: &[u8]) -> Option<Self> {
+        if *uuid == EDDYSTONE_UUID {
+            Eddystone::from_service_data(data).map(Self::Eddystone)
+        } else {
+            None
+        }
+    }
+
+    /// Decodes all beacons contained in the specified manufacturer specific data and service data.
+    pub fn from_data<'a>(
+        manufacturer_data: impl IntoIterator<Item = (&'a u16, &'a Vec<u8>)>,
+        service_data: impl IntoIterator<Item = (&'a Uuid, &'a Vec<u8>)>,
+    ) -> Vec<Self> {
+        manufacturer_data
+            .into_iter()
+            .filter_map(|(company_id, data)| Self::from_manufacturer_data(*company_id, data))
+            .chain(service_data.into_iter().filter_map(|(uuid, data)| Self::from_service_data(uuid, data)))
+            .collect()
+    }
+
+    /// Decodes all beacons contained in the specified AD structures.
+    pub fn from_ad_structures(structures: &[AdStructure]) -> Vec<Self> {
+        structures
+            .iter()
+            .filter_map(|ad| match ad {
+                AdStructure::ManufacturerData { company_id, data } => {
+                    Self::from_manufacturer_data(*company_id, data)
+                }
+                AdStructure::ServiceData16 { uuid, data } => Self::from_service_data(uuid, data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Decodes all beacons contained in a changed device property.
+    ///
+    /// This is useful to decode beacons from the [device events](crate::Device::events)
+    /// of a discovered device.
+    #[cfg(feature = "bluetoothd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+    pub fn from_device_property(property: &DeviceProperty) -> Vec<Self> {
+        match property {
+            DeviceProperty::ManufacturerData(md) => Self::from_data(md, []),
+            DeviceProperty::ServiceData(sd) => Self::from_data([], sd),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Decodes all beacons broadcast by the specified device.
+    ///
+    /// This is useful to decode beacons of a device reported by an
+    /// [AdapterEvent::DeviceAdded](crate::AdapterEvent::DeviceAdded) event.
+    #[cfg(feature = "bluetoothd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+    pub async fn from_device(device: &Device) -> Result<Vec<Self>> {
+        let manufacturer_data = device.manufacturer_data().await?.unwrap_or_default();
+        let service_data = device.service_data().await?.unwrap_or_default();
+        Ok(Self::from_data(&manufacturer_data, &service_data))
+    }
+
+    /// AD structures that make up the beacon.
+    pub fn to_ad_structures(&self) -> std::result::Result<Vec<AdStructure>, InvalidBeacon> {
+        let structures = match self {
+            Self::IBeacon(ibeacon) => vec![AdStructure::ManufacturerData {
+                company_id: APPLE_COMPANY_ID,
+                data: ibeacon.to_manufacturer_data(),
+            }],
+            Self::Eddystone(eddystone) => vec![
+                AdStructure::ServiceUuids16 { uuids: vec![Uuid::from_u16(EDDYSTONE_UUID16)], complete: true },
+                AdStructure::ServiceData16 { uuid: EDDYSTONE_UUID, data: eddystone.to_service_data()? },
+            ],
+            Self::AltBeacon(altbeacon) => vec![AdStructure::ManufacturerData {
+                company_id: altbeacon.company_id,
+                data: altbeacon.to_manufacturer_data(),
+            }],
+        };
+        Ok(structures)
+    }
+
+    /// Builds a broadcast advertisement for the beacon.
+    ///
+    /// Further advertising parameters, such as the advertising interval, can be
+    /// set on the returned advertisement before registering it.
+    #[cfg(feature = "bluetoothd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+    pub fn to_advertisement(&self) -> std::result::Result<Advertisement, InvalidBeacon> {
+        let mut adv = Advertisement { advertisement_type: Type::Broadcast, ..Default::default() };
+        match self {
+            Self::IBeacon(ibeacon) => {
+                adv.manufacturer_data.insert(APPLE_COMPANY_ID, ibeacon.to_manufacturer_data());
+            }
+            Self::Eddystone(eddystone) => {
+                adv.service_uuids.insert(EDDYSTONE_UUID);
+                adv.service_data.insert(EDDYSTONE_UUID, eddystone.to_service_data()?);
+            }
+            Self::AltBeacon(altbeacon) => {
+                adv.manufacturer_data.insert(altbeacon.company_id, altbeacon.to_manufacturer_data());
+            }
+        }
+        Ok(adv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// iBeacon with the proximity UUID of Apple's AirLocate sample, major 1, minor 2
+    /// and measured power -59 dBm.
+    #[rustfmt::skip]
+    const IBEACON: [u8; 23] = [
+        0x02, 0x15,
+        0xe2, 0xc5, 0x6d, 0xb5, 0xdf, 0xfb, 0x48, 0xd2, 0xb0, 0x60, 0xd0, 0xf5, 0xa7, 0x10, 0x96, 0xe0,
+        0x00, 0x01, 0x00, 0x02, 0xc5,
+    ];
+
+    /// AltBeacon of Radius Networks (company id 0x0118) with reference RSSI -59 dBm.
+    #[rustfmt::skip]
+    const ALTBEACON: [u8; 24] = [
+        0xbe, 0xac,
+        0x2f, 0x23, 0x44, 0x54, 0xcf, 0x6d, 0x4a, 0x0f, 0xad, 0xf2, 0xf4, 0x91, 0x1b, 0xa9, 0xff, 0xa6,
+        0x00, 0x01, 0x00, 0x02,
+        0xc5, 0x00,
+    ];
+
+    /// Eddystone-UID frame with the namespace and instance used in the Eddystone documentation.
+    #[rustfmt::skip]
+    const EDDYSTONE_UID_FRAME: [u8; 20] = [
+        0x00, 0xee,
+        0xed, 0xd1, 0xeb, 0xea, 0xc0, 0x4e, 0x5d, 0xef, 0xa0, 0x17,
+        0x0b, 0xdb, 0x87, 0x53, 0x9b, 0x67,
+        0x00, 0x00,
+    ];
+
+    /// Eddystone-URL frame for https://www.google.com/.
+    const EDDYSTONE_URL_FRAME: [u8; 10] = [0x10, 0xee, 0x01, b'g', b'o', b'o', b'g', b'l', b'e', 0x00];
+
+    /// Eddystone-TLM frame with 3000 mV battery voltage, 25.5 °C, 1000 frames and 1 h uptime.
+    #[rustfmt::skip]
+    const EDDYSTONE_TLM_FRAME: [u8; 14] = [
+        0x20, 0x00,
+        0x0b, 0xb8,
+        0x19, 0x80,
+        0x00, 0x00, 0x03, 0xe8,
+        0x00, 0x00, 0x8c, 0xa0,
+    ];
+
+    /// Encrypted Eddystone-TLM frame.
+    #[rustfmt::skip]
+    const EDDYSTONE_ETLM_FRAME: [u8; 18] = [
+        0x20, 0x01,
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+        0x12, 0x34,
+        0x56, 0x78,
+    ];
+
+    /// Eddystone-EID frame.
+    const EDDYSTONE_EID_FRAME: [u8; 10] = [0x30, 0xf8, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+
+    fn eddystone_roundtrip(data: &[u8], expected: Eddystone) {
+        let eddystone = Eddystone::from_service_data(data).unwrap();
+        assert_eq!(eddystone, expected);
+        assert_eq!(eddystone.to_service_data().unwrap(), data);
+        assert_eq!(Beacon::from_service_data(&EDDYSTONE_UUID, data), Some(Beacon::Eddystone(expected)));
+    }
+
+    #[test]
+    fn ibeacon() {
+        let expected = IBeacon {
+            uuid: Uuid::parse_str("e2c56db5-dffb-48d2-b060-d0f5a71096e0").unwrap(),
+            major: 1,
+            minor: 2,
+            measured_power: -59,
+        };
+        let ibeacon = IBeacon::from_manufacturer_data(APPLE_COMPANY_ID, &IBEACON).unwrap();
+        assert_eq!(ibeacon, expected);
+        assert_eq!(ibeacon.to_manufacturer_data(), IBEACON);
+        assert_eq!(Beacon::from_manufacturer_data(APPLE_COMPANY_ID, &IBEACON), Some(Beacon::IBeacon(expected)));
+
+        assert_eq!(IBeacon::from_manufacturer_data(0x0118, &IBEACON), None);
+        assert_eq!(IBeacon::from_manufacturer_data(APPLE_COMPANY_ID, &IBEACON[..22]), None);
+        let mut data = IBEACON;
+        data[1] = 0x16;
+        assert_eq!(IBeacon::from_manufacturer_data(APPLE_COMPANY_ID, &data), None);
+    }
+
+    #[test]
+    fn altbeacon() {
+        let expected = AltBeacon {
+            company_id: 0x0118,
+            beacon_id: ALTBEACON[2..22].try_into().unwrap(),
+            reference_rssi: -59,
+            manufacturer_reserved: 0,
+        };
+        let altbeacon = AltBeacon::from_manufacturer_data(0x0118, &ALTBEACON).unwrap();
+        assert_eq!(altbeacon, expected);
+        assert_eq!(altbeacon.to_manufacturer_data(), ALTBEACON);
+        assert_eq!(Beacon::from_manufacturer_data(0x0118, &ALTBEACON), Some(Beacon::AltBeacon(expected)));
+
+        assert_eq!(AltBeacon::from_manufacturer_data(0x0118, &ALTBEACON[..23]), None);
+        assert_eq!(AltBeacon::from_manufacturer_data(0x0118, &IBEACON), None);
+    }
+
+    #[test]
+    fn eddystone_uid() {
+        let expected = Eddystone::Uid {
+            tx_power: -18,
+            namespace: [0xed, 0xd1, 0xeb, 0xea, 0xc0, 0x4e, 0x5d, 0xef, 0xa0, 0x17],
+            instance: [0x0b, 0xdb, 0x87, 0x53, 0x9b, 0x67],
+        };
+        eddystone_roundtrip(&EDDYSTONE_UID_FRAME, expected.clone());
+
+        // The reserved bytes are optional.
+        assert_eq!(Eddystone::from_service_data(&EDDYSTONE_UID_FRAME[..18]), Some(expected));
+        assert_eq!(Eddystone::from_service_data(&EDDYSTONE_UID_FRAME[..17]), None);
+    }
+
+    #[test]
+    fn eddystone_url() {
+        eddystone_roundtrip(
+            &EDDYSTONE_URL_FRAME,
+            Eddystone::Url { tx_power: -18, url: "https://www.google.com/".to_string() },
+        );
+        eddystone_roundtrip(
+            &[0x10, 0x00, 0x02, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x00, b'a'],
+            Eddystone::Url { tx_power: 0, url: "http://example.com/a".to_string() },
+        );
+
+        // Decoding accepts expansions that are not the longest possible.
+        let data = [0x10, 0x00, 0x02, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x07, b'/', b'a'];
+        let Some(Eddystone::Url { url, .. }) = Eddystone::from_service_data(&data) else {
+            panic!("invalid URL frame")
+        };
+        assert_eq!(url, "http://example.com/a");
+
+        // Unknown scheme and invalid character.
+        assert_eq!(Eddystone::from_service_data(&[0x10, 0xee, 0x04, b'a']), None);
+        assert_eq!(Eddystone::from_service_data(&[0x10, 0xee, 0x00, b' ']), None);
+
+        for url in ["ftp://example.com", "https://exa mple.com", "https://www.example-with-long-name.com"] {
+            let eddystone = Eddystone::Url { tx_power: 0, url: url.to_string() };
+            assert!(eddystone.to_service_data().is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn eddystone_tlm() {
+        eddystone_roundtrip(
+            &EDDYSTONE_TLM_FRAME,
+            Eddystone::Tlm(EddystoneTlm::Plain {
+                battery_voltage: 3000,
+                temperature: Some(25.5),
+                advertising_count: 1000,
+                uptime: Duration::from_secs(3600),
+            }),
+        );
+        eddystone_roundtrip(
+            &EDDYSTONE_ETLM_FRAME,
+            Eddystone::Tlm(EddystoneTlm::Encrypted {
+                etlm: [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c],
+                salt: 0x1234,
+                mic: 0x5678,
+            }),
+        );
+
+        // Temperature 0x8000 means not supported.
+        let mut data = EDDYSTONE_TLM_FRAME;
+        data[4..6].copy_from_slice(&[0x80, 0x00]);
+        let Some(Eddystone::Tlm(EddystoneTlm::Plain { temperature, .. })) = Eddystone::from_service_data(&data)
+        else {
+            panic!("invalid TLM frame");
+        };
+        assert_eq!(temperature, None);
+
+        let mut data = EDDYSTONE_TLM_FRAME;
+        data[1] = 0x02;
+        assert_eq!(Eddystone::from_service_data(&data), None);
+        assert_eq!(Eddystone::from_service_data(&EDDYSTONE_TLM_FRAME[..13]), None);
+    }
+
+    #[test]
+    fn eddystone_eid() {
+        eddystone_roundtrip(
+            &EDDYSTONE_EID_FRAME,
+            Eddystone::Eid { tx_power: -8, eid: [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88] },
+        );
+        assert_eq!(Eddystone::from_service_data(&EDDYSTONE_EID_FRAME[..9]), None);
+        assert_eq!(Eddystone::from_service_data(&[0x40, 0x00]), None);
+        assert_eq!(Eddystone::from_service_data(&[]), None);
+        assert_eq!(Beacon::from_service_data(&Uuid::from_u16(0xfeab), &EDDYSTONE_EID_FRAME), None);
+    }
+
+    #[test]
+    fn ad_structures() {
+        let beacon = Beacon::Eddystone(Eddystone::from_service_data(&EDDYSTONE_URL_FRAME).unwrap());
+        let structures = beacon.to_ad_structures().unwrap();
+        assert_eq!(
+            structures,
+            [
+                AdStructure::ServiceUuids16 { uuids: vec![EDDYSTONE_UUID], complete: true },
+                AdStructure::ServiceData16 { uuid: EDDYSTONE_UUID, data: EDDYSTONE_URL_FRAME.to_vec() },
+            ]
+        );
+
+        let mut structures = structures;
+        structures.push(AdStructure::ManufacturerData { company_id: APPLE_COMPANY_ID, data: IBEACON.to_vec() });
+        structures.push(AdStructure::ManufacturerData { company_id: 0x0118, data: vec![0x01] });
+        let beacons = Beacon::from_ad_structures(&structures);
+        assert_eq!(beacons.len(), 2);
+        assert_eq!(beacons[0], beacon);
+        assert!(matches!(beacons[1], Beacon::IBeacon(_)));
+    }
+
+    #[cfg(feature = "bluetoothd")]
+    #[test]
+    fn advertisement() {
+        let ibeacon = Beacon::from_manufacturer_data(APPLE_COMPANY_ID, &IBEACON).unwrap();
+        let adv = ibeacon.to_advertisement().unwrap();
+        assert_eq!(adv.advertisement_type, Type::Broadcast);
+        assert_eq!(adv.manufacturer_data, [(APPLE_COMPANY_ID, IBEACON.to_vec())].into());
+        assert!(adv.service_uuids.is_empty() && adv.service_data.is_empty());
+
+        let altbeacon = Beacon::from_manufacturer_data(0x0118, &ALTBEACON).unwrap();
+        let adv = altbeacon.to_advertisement().unwrap();
+        assert_eq!(adv.manufacturer_data, [(0x0118, ALTBEACON.to_vec())].into());
+
+        let eddystone = Beacon::from_service_data(&EDDYSTONE_UUID, &EDDYSTONE_UID_FRAME).unwrap();
+        let adv = eddystone.to_advertisement().unwrap();
+        assert_eq!(adv.service_uuids, [EDDYSTONE_UUID].into());
+        assert_eq!(adv.service_data, [(EDDYSTONE_UUID, EDDYSTONE_UID_FRAME.to_vec())].into());
+        assert!(adv.manufacturer_data.is_empty());
+
+        let invalid = Beacon::Eddystone(Eddystone::Url { tx_power: 0, url: "ftp://example.com".to_string() });
+        assert!(invalid.to_advertisement().is_err());
+    }
+}
@@ -0,0 +1,7 @@
+//! Audio codecs for Bluetooth audio streaming.
+//!
+//! The codecs are implemented in pure Rust and do not require any system libraries.
+//! They operate on encoded frames and interleaved PCM samples and are thus
+//! independent of the transport used for sending and receiving audio data.
+
+pub mod sbc;
@@ -0,0 +1,1203 @@
+//! Low complexity subband codec (SBC).
+//!
+//! SBC is the mandatory codec of the Advanced Audio Distribution Profile (A2DP).
+//! Its modified variant mSBC is used for wideband speech by the Hands-Free Profile (HFP).
+//!
+//! An [Encoder] turns interleaved 16-bit PCM samples into SBC frames and a
+//! [Decoder] turns SBC frames back into interleaved PCM samples.
+//! All sampling frequencies, block lengths, subband counts, channel modes,
+//! allocation methods and bitpool values of the A2DP specification are supported.
+//!
+//! Use [Config::msbc] for mSBC.
+//! The H2 synchronization header that prefixes mSBC frames on SCO links is not
+//! part of the frame and must be handled by the caller.
+
+use std::fmt;
+
+/// Syncword of an SBC frame.
+pub const SYNCWORD: u8 = 0x9c;
+
+/// Syncword of an mSBC frame.
+pub const MSBC_SYNCWORD: u8 = 0xad;
+
+/// Length of the frame header, including the CRC.
+const HEADER_LEN: usize = 4;
+
+/// Supported sampling frequencies in Hz, in the order of their header encoding.
+const SAMPLING_FREQUENCIES: [u32; 4] = [16000, 32000, 44100, 48000];
+
+/// Supported block lengths, in the order of their header encoding.
+const BLOCK_LENGTHS: [u8; 4] = [4, 8, 12, 16];
+
+/// Block length of mSBC frames.
+const MSBC_BLOCKS: u8 = 15;
+
+/// Bitpool value of mSBC frames.
+const MSBC_BITPOOL: u8 = 26;
+
+/// Loudness offsets for four subbands, indexed by sampling frequency.
+const OFFSET4: [[i32; 4]; 4] = [[-1, 0, 0, 0], [-2, 0, 0, 1], [-2, 0, 0, 1], [-2, 0, 0, 1]];
+
+/// Loudness offsets for eight subbands, indexed by sampling frequency.
+const OFFSET8: [[i32; 8]; 4] =
+    [[-2, 0, 0, 0, 0, 0, 0, 1], [-3, 0, 0, 0, 0, 0, 1, 2], [-4, 0, 0, 0, 0, 0, 1, 2], [-4, 0, 0, 0, 0, 0, 1, 2]];
+
+/// Analysis window coefficients for four subbands, as given by the A2DP specification.
+#[rustfmt::skip]
+#[allow(clippy::excessive_precision)]
+const PROTO_4: [f32; 40] = [
+    0.00000000E+00, 5.36548976E-04, 1.49188357E-03, 2.73370904E-03,
+    3.83720193E-03, 3.89205149E-03, 1.86581691E-03, -3.06012286E-03,
+    1.09137620E-02, 2.04385087E-02, 2.88757392E-02, 3.21939290E-02,
+    2.58767811E-02, 6.13245186E-03, -2.88217274E-02, -7.76463494E-02,
+    1.35593274E-01, 1.94987841E-01, 2.46636662E-01, 2.81828203E-01,
+    2.94315332E-01, 2.81828203E-01, 2.46636662E-01, 1.94987841E-01,
+    -1.35593274E-01, -7.76463494E-02, -2.88217274E-02, 6.13245186E-03,
+    2.58767811E-02, 3.21939290E-02, 2.88757392E-02, 2.04385087E-02,
+    -1.09137620E-02, -3.06012286E-03, 1.86581691E-03, 3.89205149E-03,
+    3.83720193E-03, 2.73370904E-03, 1.49188357E-03, 5.36548976E-04,
+];
+
+/// Analysis window coefficients for eight subbands, as given by the A2DP specification.
+#[rustfmt::skip]
+#[allow(clippy::excessive_precision)]
+const PROTO_8: [f32; 80] = [
+    0.00000000E+00, 1.56575398E-04, 3.43256425E-04, 5.54620202E-04,
+    8.23919506E-04, 1.13992507E-03, 1.47640169E-03, 1.78371725E-03,
+    2.01182542E-03, 2.10371989E-03, 1.99454554E-03, 1.61656283E-03,
+    9.02154502E-04, -1.78805361E-04, -1.64973098E-03, -3.49717454E-03,
+    5.65949473E-03, 8.02941163E-03, 1.04584443E-02, 1.27472335E-02,
+    1.46525263E-02, 1.59045603E-02, 1.62208471E-02, 1.53184106E-02,
+    1.29371806E-02, 8.85757540E-03, 2.92408442E-03, -4.91578024E-03,
+    -1.46404076E-02, -2.61098752E-02, -3.90751381E-02, -5.31873032E-02,
+    6.79989431E-02, 8.29847578E-02, 9.75753918E-02, 1.11196689E-01,
+    1.23264548E-01, 1.33264415E-01, 1.40753505E-01, 1.45389847E-01,
+    1.46955068E-01, 1.45389847E-01, 1.40753505E-01, 1.33264415E-01,
+    1.23264548E-01, 1.11196689E-01, 9.75753918E-02, 8.29847578E-02,
+    -6.79989431E-02, -5.31873032E-02, -3.90751381E-02, -2.61098752E-02,
+    -1.46404076E-02, -4.91578024E-03, 2.92408442E-03, 8.85757540E-03,
+    1.29371806E-02, 1.53184106E-02, 1.62208471E-02, 1.59045603E-02,
+    1.46525263E-02, 1.27472335E-02, 1.04584443E-02, 8.02941163E-03,
+    -5.65949473E-03, -3.49717454E-03, -1.64973098E-03, -1.78805361E-04,
+    9.02154502E-04, 1.61656283E-03, 1.99454554E-03, 2.10371989E-03,
+    2.01182542E-03, 1.78371725E-03, 1.47640169E-03, 1.13992507E-03,
+    8.23919506E-04, 5.54620202E-04, 3.43256425E-04, 1.56575398E-04,
+];
+
+/// SBC error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SbcError {
+    /// The configuration is not supported.
+    InvalidConfig(&'static str),
+    /// The frame does not start with a valid syncword.
+    InvalidSyncword(u8),
+    /// The data is shorter than the frame.
+    Truncated {
+        /// Length of data.
+        len: usize,
+        /// Length of the frame.
+        frame_len: usize,
+    },
+    /// The CRC of the frame does not match its contents.
+    CrcMismatch {
+        /// CRC contained in the frame.
+        received: u8,
+        /// CRC calculated from the frame contents.
+        calculated: u8,
+    },
+    /// The number of PCM samples does not match the frame size.
+    InvalidPcmLength {
+        /// Number of PCM samples provided.
+        len: usize,
+        /// Number of PCM samples required.
+        expected: usize,
+    },
+}
+
+impl fmt::Display for SbcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(msg) => write!(f, "invalid SBC configuration: {msg}"),
+            Self::InvalidSyncword(sync) => write!(f, "invalid SBC syncword 0x{sync:02x}"),
+            Self::Truncated { len, frame_len } => {
+                write!(f, "SBC frame of {frame_len} bytes is truncated to {len} bytes")
+            }
+            Self::CrcMismatch { received, calculated } => {
+                write!(f, "SBC frame CRC 0x{received:02x} does not match calculated CRC 0x{calculated:02x}")
+            }
+            Self::InvalidPcmLength { len, expected } => {
+                write!(f, "{len} PCM samples provided but SBC frame requires {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SbcError {}
+
+/// SBC channel mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelMode {
+    /// Mono.
+    Mono,
+    /// Two independently encoded channels.
+    DualChannel,
+    /// Stereo with bits shared between channels.
+    Stereo,
+    /// Stereo with optional mid/side coding of each subband.
+    JointStereo,
+}
+
+impl ChannelMode {
+    /// Number of audio channels.
+    pub fn channels(self) -> usize {
+        match self {
+            Self::Mono => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// SBC bit allocation method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AllocationMethod {
+    /// Loudness.
+    Loudness,
+    /// Signal to noise ratio.
+    Snr,
+}
+
+/// SBC stream configuration.
+///
+/// This is the information contained in the header of each SBC frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// Sampling frequency in Hz.
+    ///
+    /// Supported are 16000, 32000, 44100 and 48000 Hz.
+    pub sampling_frequency: u32,
+    /// Channel mode.
+    pub channel_mode: ChannelMode,
+    /// Number of blocks per frame.
+    ///
+    /// Supported are 4, 8, 12 and 16 blocks.
+    pub blocks: u8,
+    /// Number of subbands.
+    ///
+    /// Supported are 4 and 8 subbands.
+    pub subbands: u8,
+    /// Bit allocation method.
+    pub allocation_method: AllocationMethod,
+    /// Bitpool value.
+    ///
+    /// Higher values give better quality at a higher bit rate.
+    pub bitpool: u8,
+    /// Whether this is the mSBC configuration for wideband speech.
+    ///
+    /// Use [Config::msbc] to obtain the mSBC configuration.
+    pub msbc: bool,
+}
+
+impl Default for Config {
+    /// High quality joint stereo at 44.1 kHz, as recommended by the A2DP specification.
+    fn default() -> Self {
+        Self {
+            sampling_frequency: 44100,
+            channel_mode: ChannelMode::JointStereo,
+            blocks: 16,
+            subbands: 8,
+            allocation_method: AllocationMethod::Loudness,
+            bitpool: 53,
+            msbc: false,
+        }
+    }
+}
+
+impl Config {
+    /// Configuration of mSBC for wideband speech.
+    ///
+    /// mSBC frames are 57 bytes long and contain 120 samples of mono audio
+    /// sampled at 16 kHz.
+    pub const fn msbc() -> Self {
+        Self {
+            sampling_frequency: 16000,
+            channel_mode: ChannelMode::Mono,
+            blocks: MSBC_BLOCKS,
+            subbands: 8,
+            allocation_method: AllocationMethod::Loudness,
+            bitpool: MSBC_BITPOOL,
+            msbc: true,
+        }
+    }
+
+    /// Checks that the configuration is supported.
+    pub fn validate(&self) -> Result<(), SbcError> {
+        if self.msbc {
+            if *self != Self::msbc() {
+                return Err(SbcError::InvalidConfig("mSBC parameters are fixed"));
+            }
+            return Ok(());
+        }
+        if !SAMPLING_FREQUENCIES.contains(&self.sampling_frequency) {
+            return Err(SbcError::InvalidConfig("unsupported sampling frequency"));
+        }
+        if !BLOCK_LENGTHS.contains(&self.blocks) {
+            return Err(SbcError::InvalidConfig("unsupported block length"));
+        }
+        if self.subbands != 4 && self.subbands != 8 {
+            return Err(SbcError::InvalidConfig("unsupported number of subbands"));
+        }
+        let max_bitpool = match self.channel_mode {
+            ChannelMode::Mono | ChannelMode::DualChannel => 16 * self.subbands as u32,
+            ChannelMode::Stereo | ChannelMode::JointStereo => 32 * self.subbands as u32,
+        };
+        if self.bitpool < 2 || self.bitpool as u32 > max_bitpool {
+            return Err(SbcError::InvalidConfig("bitpool out of range"));
+        }
+        Ok(())
+    }
+
+    /// Number of audio channels.
+    pub fn channels(&self) -> usize {
+        self.channel_mode.channels()
+    }
+
+    /// Number of samples per channel contained in a frame.
+    pub fn frame_samples(&self) -> usize {
+        self.blocks as usize * self.subbands as usize
+    }
+
+    /// Length of an encoded frame in bytes.
+    pub fn frame_len(&self) -> usize {
+        let blocks = self.blocks as usize;
+        let subbands = self.subbands as usize;
+        let channels = self.channels();
+        let bitpool = self.bitpool as usize;
+        let data_bits = match self.channel_mode {
+            ChannelMode::Mono | ChannelMode::DualChannel => blocks * channels * bitpool,
+            ChannelMode::Stereo => blocks * bitpool,
+            ChannelMode::JointStereo => subbands + blocks * bitpool,
+        };
+        HEADER_LEN + (4 * subbands * channels) / 8 + data_bits.div_ceil(8)
+    }
+
+    /// Bit rate of the encoded stream in bits per second.
+    pub fn bit_rate(&self) -> u32 {
+        (8 * self.frame_len() as u64 * self.sampling_frequency as u64 / self.frame_samples() as u64) as u32
+    }
+
+    /// Parses the configuration from the header of an SBC or mSBC frame.
+    pub fn from_header(data: &[u8]) -> Result<Self, SbcError> {
+        if data.len() < HEADER_LEN {
+            return Err(SbcError::Truncated { len: data.len(), frame_len: HEADER_LEN });
+        }
+        match data[0] {
+            SYNCWORD => {
+                let b = data[1];
+                Ok(Self {
+                    sampling_frequency: SAMPLING_FREQUENCIES[(b >> 6) as usize],
+                    blocks: BLOCK_LENGTHS[((b >> 4) & 0x03) as usize],
+                    channel_mode: match (b >> 2) & 0x03 {
+                        0 => ChannelMode::Mono,
+                        1 => ChannelMode::DualChannel,
+                        2 => ChannelMode::Stereo,
+                        _ => ChannelMode::JointStereo,
+                    },
+                    allocation_method: if b & 0x02 != 0 {
+                        AllocationMethod::Snr
+                    } else {
+                        AllocationMethod::Loudness
+                    },
+                    subbands: if b & 0x01 != 0 { 8 } else { 4 },
+                    bitpool: data[2],
+                    msbc: false,
+                })
+            }
+            MSBC_SYNCWORD => Ok(Self::msbc()),
+            other => Err(SbcError::InvalidSyncword(other)),
+        }
+    }
+
+    /// Encodes the first three bytes of the frame header.
+    fn header(&self) -> [u8; 3] {
+        if self.msbc {
+            return [MSBC_SYNCWORD, 0, 0];
+        }
+        let freq =
+            SAMPLING_FREQUENCIES.iter().position(|f| *f == self.sampling_frequency).unwrap_or_default() as u8;
+        let blocks = BLOCK_LENGTHS.iter().position(|b| *b == self.blocks).unwrap_or_default() as u8;
+        let mode = match self.channel_mode {
+            ChannelMode::Mono => 0,
+            ChannelMode::DualChannel => 1,
+            ChannelMode::Stereo => 2,
+            ChannelMode::JointStereo => 3,
+        };
+        let alloc = match self.allocation_method {
+            AllocationMethod::Loudness => 0,
+            AllocationMethod::Snr => 1,
+        };
+        let subbands = if self.subbands == 8 { 1 } else { 0 };
+        [SYNCWORD, freq << 6 | blocks << 4 | mode << 2 | alloc << 1 | subbands, self.bitpool]
+    }
+
+    fn frequency_index(&self) -> usize {
+        SAMPLING_FREQUENCIES.iter().position(|f| *f == self.sampling_frequency).unwrap_or_default()
+    }
+
+    /// Calculates the number of bits allocated to each subband of each channel.
+    fn allocate_bits(&self, scale_factors: &[[u8; 8]; 2], bits: &mut [[u8; 8]; 2]) {
+        match self.channel_mode {
+            ChannelMode::Mono => self.allocate_channels(&scale_factors[..1], &mut bits[..1]),
+            ChannelMode::DualChannel => {
+                self.allocate_channels(&scale_factors[..1], &mut bits[..1]);
+                self.allocate_channels(&scale_factors[1..], &mut bits[1..]);
+            }
+            ChannelMode::Stereo | ChannelMode::JointStereo => self.allocate_channels(scale_factors, bits),
+        }
+    }
+
+    /// Distributes the bitpool over the subbands of the specified channels.
+    fn allocate_channels(&self, scale_factors: &[[u8; 8]], bits: &mut [[u8; 8]]) {
+        let subbands = self.subbands as usize;
+        let bitpool = self.bitpool as i32;
+        let offsets = match subbands {
+            4 => &OFFSET4[self.frequency_index()][..],
+            _ => &OFFSET8[self.frequency_index()][..],
+        };
+
+        let mut bitneed = [[0i32; 8]; 2];
+        for (ch, sfs) in scale_factors.iter().enumerate() {
+            for sb in 0..subbands {
+                let sf = sfs[sb] as i32;
+                bitneed[ch][sb] = match self.allocation_method {
+                    AllocationMethod::Snr => sf,
+                    AllocationMethod::Loudness if sf == 0 => -5,
+                    AllocationMethod::Loudness => {
+                        let loudness = sf - offsets[sb];
+                        if loudness > 0 {
+                            loudness / 2
+                        } else {
+                            loudness
+                        }
+                    }
+                };
+            }
+        }
+        let bitneed = &bitneed[..scale_factors.len()];
+        let max_bitneed = bitneed.iter().flat_map(|bn| bn[..subbands].iter()).copied().max().unwrap_or_default();
+
+        let mut bitcount = 0;
+        let mut slicecount = 0;
+        let mut bitslice = max_bitneed + 1;
+        loop {
+            bitslice -= 1;
+            bitcount += slicecount;
+            slicecount = 0;
+            for &bn in bitneed.iter().flat_map(|bn| bn[..subbands].iter()) {
+                if bn > bitslice + 1 && bn < bitslice + 16 {
+                    slicecount += 1;
+                } else if bn == bitslice + 1 {
+                    slicecount += 2;
+                }
+            }
+            if bitcount + slicecount >= bitpool {
+                break;
+            }
+        }
+        if bitcount + slicecount == bitpool {
+            bitcount += slicecount;
+            bitslice -= 1;
+        }
+
+        for (ch, bn) in bitneed.iter().enumerate() {
+            for sb in 0..subbands {
+                bits[ch][sb] = if bn[sb] < bitslice + 2 { 0 } else { (bn[sb] - bitslice).min(16) as u8 };
+            }
+        }
+
+        let channels = bitneed.len();
+        let (mut ch, mut sb) = (0, 0);
+        while bitcount < bitpool && sb < subbands {
+            if bits[ch][sb] >= 2 && bits[ch][sb] < 16 {
+                bits[ch][sb] += 1;
+                bitcount += 1;
+            } else if bitneed[ch][sb] == bitslice + 1 && bitpool > bitcount + 1 {
+                bits[ch][sb] = 2;
+                bitcount += 2;
+            }
+            ch += 1;
+            if ch == channels {
+                ch = 0;
+                sb += 1;
+            }
+        }
+
+        let (mut ch, mut sb) = (0, 0);
+        while bitcount < bitpool && sb < subbands {
+            if bits[ch][sb] < 16 {
+                bits[ch][sb] += 1;
+                bitcount += 1;
+            }
+            ch += 1;
+            if ch == channels {
+                ch = 0;
+                sb += 1;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bluetoothd")]
+impl TryFrom<&crate::media::a2dp::SbcCapabilities> for Config {
+    type Error = SbcError;
+
+    /// Converts an A2DP SBC configuration into a codec configuration.
+    ///
+    /// The configuration must contain exactly one value for each set.
+    /// The maximum bitpool value is used.
+    fn try_from(caps: &crate::media::a2dp::SbcCapabilities) -> Result<Self, SbcError> {
+        use crate::media::a2dp::{SbcAllocationMethod, SbcChannelMode};
+        use std::collections::BTreeSet;
+
+        fn single<T: Copy>(set: &BTreeSet<T>, what: &'static str) -> Result<T, SbcError> {
+            match set.len() {
+                1 => Ok(*set.iter().next().unwrap()),
+                _ => Err(SbcError::InvalidConfig(what)),
+            }
+        }
+
+        let config = Self {
+            sampling_frequency: single(&caps.sampling_frequencies, "ambiguous sampling frequency")?,
+            channel_mode: match single(&caps.channel_modes, "ambiguous channel mode")? {
+                SbcChannelMode::Mono => ChannelMode::Mono,
+                SbcChannelMode::DualChannel => ChannelMode::DualChannel,
+                SbcChannelMode::Stereo => ChannelMode::Stereo,
+                SbcChannelMode::JointStereo => ChannelMode::JointStereo,
+            },
+            blocks: single(&caps.block_lengths, "ambiguous block length")?,
+            subbands: single(&caps.subbands, "ambiguous number of subbands")?,
+            allocation_method: match single(&caps.allocation_methods, "ambiguous allocation method")? {
+                SbcAllocationMethod::Loudness => AllocationMethod::Loudness,
+                SbcAllocationMethod::Snr => AllocationMethod::Snr,
+            },
+            bitpool: caps.max_bitpool,
+            msbc: false,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Calculates the frame CRC over the first `bits` bits of `data`.
+fn crc8(data: &[u8], bits: usize) -> u8 {
+    let mut crc = 0x0fu8;
+    for i in 0..bits {
+        let bit = (data[i / 8] >> (7 - i % 8)) & 1;
+        let feedback = (crc >> 7) ^ bit;
+        crc <<= 1;
+        if feedback != 0 {
+            crc ^= 0x1d;
+        }
+    }
+    crc
+}
+
+/// Calculates the CRC of a frame whose header and scale factors have been written.
+fn frame_crc(frame: &[u8], sf_bits: usize) -> u8 {
+    let mut data = Vec::with_capacity(2 + sf_bits.div_ceil(8));
+    data.extend_from_slice(&frame[1..3]);
+    data.extend_from_slice(&frame[HEADER_LEN..HEADER_LEN + sf_bits.div_ceil(8)]);
+    crc8(&data, 16 + sf_bits)
+}
+
+/// Writes bits into a frame, most significant bit first.
+struct BitWriter<'a> {
+    data: &'a mut Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(data: &'a mut Vec<u8>) -> Self {
+        let pos = data.len() * 8;
+        Self { data, pos }
+    }
+
+    fn write(&mut self, value: u32, bits: u8) {
+        for i in (0..bits).rev() {
+            if self.pos % 8 == 0 {
+                self.data.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.data.last_mut().unwrap() |= bit << (7 - self.pos % 8);
+            self.pos += 1;
+        }
+    }
+}
+
+/// Reads bits from a frame, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    /// Reads the specified number of bits.
+    ///
+    /// The caller must ensure that sufficient data is available.
+    fn read(&mut self, bits: u8) -> u32 {
+        let mut value = 0;
+        for _ in 0..bits {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            value = value << 1 | bit as u32;
+            self.pos += 1;
+        }
+        value
+    }
+}
+
+/// Window coefficients and modulation matrices of the filter bank for one subband count.
+struct FilterBank {
+    subbands: usize,
+    /// Analysis window.
+    window: &'static [f32],
+    /// Analysis modulation matrix of size subbands × 2·subbands.
+    analysis: Vec<f32>,
+    /// Synthesis modulation matrix of size 2·subbands × subbands.
+    synthesis: Vec<f32>,
+}
+
+impl FilterBank {
+    fn new(subbands: usize) -> Self {
+        let m = subbands as f64;
+        let window: &'static [f32] = match subbands {
+            4 => &PROTO_4,
+            _ => &PROTO_8,
+        };
+        let mut analysis = Vec::with_capacity(2 * subbands * subbands);
+        for k in 0..subbands {
+            for i in 0..2 * subbands {
+                analysis.push(((k as f64 + 0.5) * (i as f64 - m / 2.0) * std::f64::consts::PI / m).cos() as f32);
+            }
+        }
+        let mut synthesis = Vec::with_capacity(2 * subbands * subbands);
+        for k in 0..2 * subbands {
+            for i in 0..subbands {
+                synthesis.push(((i as f64 + 0.5) * (k as f64 + m / 2.0) * std::f64::consts::PI / m).cos() as f32);
+            }
+        }
+        Self { subbands, window, analysis, synthesis }
+    }
+}
+
+/// Analysis filter state of one channel.
+#[derive(Clone)]
+struct AnalysisState {
+    x: Vec<f32>,
+}
+
+impl AnalysisState {
+    fn new(subbands: usize) -> Self {
+        Self { x: vec![0.0; 10 * subbands] }
+    }
+
+    /// Splits one block of input samples into subband samples.
+    fn analyze(&mut self, fb: &FilterBank, input: impl Iterator<Item = f32>, output: &mut [f32; 8]) {
+        let m = fb.subbands;
+        self.x.copy_within(0..9 * m, m);
+        for (i, sample) in input.take(m).enumerate() {
+            self.x[m - 1 - i] = sample;
+        }
+
+        let mut y = [0.0f32; 16];
+        for (i, y) in y[..2 * m].iter_mut().enumerate() {
+            *y = (0..5).map(|j| fb.window[i + 2 * m * j] * self.x[i + 2 * m * j]).sum();
+        }
+        for (k, out) in output[..m].iter_mut().enumerate() {
+            *out = fb.analysis[k * 2 * m..(k + 1) * 2 * m].iter().zip(&y).map(|(c, y)| c * y).sum();
+        }
+    }
+}
+
+/// Synthesis filter state of one channel.
+#[derive(Clone)]
+struct SynthesisState {
+    v: Vec<f32>,
+}
+
+impl SynthesisState {
+    fn new(subbands: usize) -> Self {
+        Self { v: vec![0.0; 20 * subbands] }
+    }
+
+    /// Combines the subband samples of one block into output samples.
+    fn synthesize(&mut self, fb: &FilterBank, input: &[f32; 8], output: &mut [f32; 8]) {
+        let m = fb.subbands;
+        self.v.copy_within(0..18 * m, 2 * m);
+        for k in 0..2 * m {
+            self.v[k] = fb.synthesis[k * m..(k + 1) * m].iter().zip(input).map(|(n, s)| n * s).sum();
+        }
+
+        let gain = -(m as f32);
+        for (j, out) in output[..m].iter_mut().enumerate() {
+            let mut sum = 0.0;
+            for i in 0..5 {
+                sum += self.v[i * 4 * m + j] * fb.window[i * 2 * m + j];
+                sum += self.v[i * 4 * m + 3 * m + j] * fb.window[i * 2 * m + m + j];
+            }
+            *out = sum * gain;
+        }
+    }
+}
+
+/// Audio samples and scale factors of one frame.
+struct FrameData {
+    /// Subband samples indexed by block, channel and subband.
+    samples: Vec<[[f32; 8]; 2]>,
+    /// Scale factors indexed by channel and subband.
+    scale_factors: [[u8; 8]; 2],
+    /// Bits allocated to each subband of each channel.
+    bits: [[u8; 8]; 2],
+    /// Joint stereo flags of each subband.
+    join: [bool; 8],
+}
+
+impl FrameData {
+    fn new(blocks: usize) -> Self {
+        Self {
+            samples: vec![[[0.0; 8]; 2]; blocks],
+            scale_factors: [[0; 8]; 2],
+            bits: [[0; 8]; 2],
+            join: [false; 8],
+        }
+    }
+}
+
+/// Smallest scale factor whose range covers the specified absolute sample value.
+fn scale_factor(max: f32) -> u8 {
+    let mut sf = 0;
+    while sf < 15 && max > (2u32 << sf) as f32 {
+        sf += 1;
+    }
+    sf
+}
+
+/// SBC encoder.
+///
+/// The encoder keeps the filter state between frames, so a separate encoder
+/// must be used for each stream.
+pub struct Encoder {
+    config: Config,
+    filter_bank: FilterBank,
+    state: Vec<AnalysisState>,
+}
+
+impl fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encoder").field("config", &self.config).finish()
+    }
+}
+
+impl Encoder {
+    /// Creates an encoder for the specified configuration.
+    pub fn new(config: Config) -> Result<Self, SbcError> {
+        config.validate()?;
+        let subbands = config.subbands as usize;
+        Ok(Self {
+            config,
+            filter_bank: FilterBank::new(subbands),
+            state: vec![AnalysisState::new(subbands); config.channels()],
+        })
+    }
+
+    /// Configuration of the encoder.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Number of interleaved PCM samples required to encode one frame.
+    pub fn pcm_len(&self) -> usize {
+        self.config.frame_samples() * self.config.channels()
+    }
+
+    /// Encodes one frame.
+    ///
+    /// `pcm` must contain exactly [Self::pcm_len] samples with the
+    /// channels interleaved.
+    /// The encoded frame of length [Config::frame_len] is appended to `frame`.
+    pub fn encode_frame(&mut self, pcm: &[i16], frame: &mut Vec<u8>) -> Result<(), SbcError> {
+        if pcm.len() != self.pcm_len() {
+            return Err(SbcError::InvalidPcmLength { len: pcm.len(), expected: self.pcm_len() });
+        }
+
+        let config = self.config;
+        let blocks = config.blocks as usize;
+        let subbands = config.subbands as usize;
+        let channels = config.channels();
+        let mut data = FrameData::new(blocks);
+
+        for (blk, block_pcm) in pcm.chunks(subbands * channels).enumerate() {
+            for ch in 0..channels {
+                let input = block_pcm.iter().skip(ch).step_by(channels).map(|s| *s as f32);
+                self.state[ch].analyze(&self.filter_bank, input, &mut data.samples[blk][ch]);
+            }
+        }
+
+        let max_abs = |samples: &[[[f32; 8]; 2]], ch: usize, sb: usize| {
+            samples.iter().map(|s| s[ch][sb].abs()).fold(0.0f32, f32::max)
+        };
+        for ch in 0..channels {
+            for sb in 0..subbands {
+                data.scale_factors[ch][sb] = scale_factor(max_abs(&data.samples, ch, sb));
+            }
+        }
+
+        if config.channel_mode == ChannelMode::JointStereo {
+            for sb in 0..subbands - 1 {
+                let (mut max_mid, mut max_side) = (0.0f32, 0.0f32);
+                for s in &data.samples {
+                    max_mid = max_mid.max(((s[0][sb] + s[1][sb]) / 2.0).abs());
+                    max_side = max_side.max(((s[0][sb] - s[1][sb]) / 2.0).abs());
+                }
+                let (sf_mid, sf_side) = (scale_factor(max_mid), scale_factor(max_side));
+                if sf_mid + sf_side < data.scale_factors[0][sb] + data.scale_factors[1][sb] {
+                    data.join[sb] = true;
+                    data.scale_factors[0][sb] = sf_mid;
+                    data.scale_factors[1][sb] = sf_side;
+                    for s in &mut data.samples {
+                        let (left, right) = (s[0][sb], s[1][sb]);
+                        s[0][sb] = (left + right) / 2.0;
+                        s[1][sb] = (left - right) / 2.0;
+                    }
+                }
+            }
+        }
+
+        config.allocate_bits(&data.scale_factors, &mut data.bits);
+
+        let start = frame.len();
+        frame.extend_from_slice(&config.header());
+        frame.push(0);
+        let mut w = BitWriter::new(frame);
+        let mut sf_bits = 0;
+        if config.channel_mode == ChannelMode::JointStereo {
+            for sb in 0..subbands {
+                w.write(data.join[sb] as u32, 1);
+            }
+            sf_bits += subbands;
+        }
+        for ch in 0..channels {
+            for sb in 0..subbands {
+                w.write(data.scale_factors[ch][sb] as u32, 4);
+            }
+        }
+        sf_bits += 4 * subbands * channels;
+
+        for s in &data.samples {
+            for (ch, samples) in s[..channels].iter().enumerate() {
+                for (sb, sample) in samples[..subbands].iter().enumerate() {
+                    let bits = data.bits[ch][sb];
+                    if bits == 0 {
+                        continue;
+                    }
+                    let levels = ((1u32 << bits) - 1) as f32;
+                    let scale = (2u32 << data.scale_factors[ch][sb]) as f32;
+                    let q = ((sample / scale + 1.0) * levels / 2.0).floor();
+                    w.write(q.clamp(0.0, levels - 1.0) as u32, bits);
+                }
+            }
+        }
+
+        frame.resize(start + config.frame_len(), 0);
+        frame[start + 3] = frame_crc(&frame[start..], sf_bits);
+        Ok(())
+    }
+}
+
+/// SBC decoder.
+///
+/// The decoder keeps the filter state between frames, so a separate decoder
+/// must be used for each stream.
+/// The configuration is obtained from the header of each frame.
+#[derive(Default)]
+pub struct Decoder {
+    config: Option<Config>,
+    filter_bank: Option<FilterBank>,
+    state: Vec<SynthesisState>,
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Decoder").field("config", &self.config).finish()
+    }
+}
+
+impl Decoder {
+    /// Creates a decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configuration of the most recently decoded frame.
+    pub fn config(&self) -> Option<&Config> {
+        self.config.as_ref()
+    }
+
+    /// Decodes one frame from the start of `data`.
+    ///
+    /// The decoded PCM samples with the channels interleaved are appended to `pcm`.
+    /// Returns the length of the decoded frame, which may be shorter than `data`.
+    pub fn decode_frame(&mut self, data: &[u8], pcm: &mut Vec<i16>) -> Result<usize, SbcError> {
+        let config = Config::from_header(data)?;
+        config.validate()?;
+        let frame_len = config.frame_len();
+        if data.len() < frame_len {
+            return Err(SbcError::Truncated { len: data.len(), frame_len });
+        }
+
+        let blocks = config.blocks as usize;
+        let subbands = config.subbands as usize;
+        let channels = config.channels();
+        let mut frame = FrameData::new(blocks);
+
+        let mut r = BitReader::new(data, 8 * HEADER_LEN);
+        if config.channel_mode == ChannelMode::JointStereo {
+            for sb in 0..subbands {
+                frame.join[sb] = r.read(1) != 0 && sb < subbands - 1;
+            }
+        }
+        for ch in 0..channels {
+            for sb in 0..subbands {
+                frame.scale_factors[ch][sb] = r.read(4) as u8;
+            }
+        }
+        let sf_bits = r.pos - 8 * HEADER_LEN;
+        let calculated = frame_crc(data, sf_bits);
+        if calculated != data[3] {
+            return Err(SbcError::CrcMismatch { received: data[3], calculated });
+        }
+
+        config.allocate_bits(&frame.scale_factors, &mut frame.bits);
+        for s in &mut frame.samples {
+            for (ch, samples) in s[..channels].iter_mut().enumerate() {
+                for (sb, sample) in samples[..subbands].iter_mut().enumerate() {
+                    let bits = frame.bits[ch][sb];
+                    if bits == 0 {
+                        continue;
+                    }
+                    let levels = ((1u32 << bits) - 1) as f32;
+                    let scale = (2u32 << frame.scale_factors[ch][sb]) as f32;
+                    let q = r.read(bits) as f32;
+                    *sample = scale * ((2.0 * q + 1.0) / levels - 1.0);
+                }
+            }
+        }
+
+        for sb in 0..subbands {
+            if frame.join[sb] {
+                for s in &mut frame.samples {
+                    let (mid, side) = (s[0][sb], s[1][sb]);
+                    s[0][sb] = mid + side;
+                    s[1][sb] = mid - side;
+                }
+            }
+        }
+
+        if self.config.map(|c| (c.subbands, c.channels())) != Some((config.subbands, channels)) {
+            self.filter_bank = Some(FilterBank::new(subbands));
+            self.state = vec![SynthesisState::new(subbands); channels];
+        }
+        self.config = Some(config);
+        let filter_bank = self.filter_bank.as_ref().unwrap();
+
+        let start = pcm.len();
+        pcm.resize(start + blocks * subbands * channels, 0);
+        let mut out = [0.0f32; 8];
+        for (blk, s) in frame.samples.iter().enumerate() {
+            for ch in 0..channels {
+                self.state[ch].synthesize(filter_bank, &s[ch], &mut out);
+                for (j, sample) in out[..subbands].iter().enumerate() {
+                    pcm[start + (blk * subbands + j) * channels + ch] =
+                        sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+                }
+            }
+        }
+
+        Ok(frame_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// mSBC frame of silence as produced by libsbc.
+    #[rustfmt::skip]
+    const MSBC_ZERO_FRAME: [u8; 57] = [
+        0xad, 0x00, 0x00, 0xc5, 0x00, 0x00, 0x00, 0x00, 0x77, 0x6d, 0xb6, 0xdd, 0xdb, 0x6d, 0xb7, 0x76,
+        0xdb, 0x6d, 0xdd, 0xb6, 0xdb, 0x77, 0x6d, 0xb6, 0xdd, 0xdb, 0x6d, 0xb7, 0x76, 0xdb, 0x6d, 0xdd,
+        0xb6, 0xdb, 0x77, 0x6d, 0xb6, 0xdd, 0xdb, 0x6d, 0xb7, 0x76, 0xdb, 0x6d, 0xdd, 0xb6, 0xdb, 0x77,
+        0x6d, 0xb6, 0xdd, 0xdb, 0x6d, 0xb7, 0x76, 0xdb, 0x6c,
+    ];
+
+    #[test]
+    fn msbc_zero_frame_decode() {
+        let mut decoder = Decoder::new();
+        let mut pcm = Vec::new();
+        assert_eq!(decoder.decode_frame(&MSBC_ZERO_FRAME, &mut pcm), Ok(57));
+        assert_eq!(decoder.config(), Some(&Config::msbc()));
+        assert_eq!(pcm.len(), 120);
+        assert!(pcm.iter().all(|s| s.abs() <= 1), "{pcm:?}");
+    }
+
+    #[test]
+    fn msbc_zero_frame_encode() {
+        let mut encoder = Encoder::new(Config::msbc()).unwrap();
+        let mut frame = Vec::new();
+        encoder.encode_frame(&[0; 120], &mut frame).unwrap();
+        assert_eq!(frame, MSBC_ZERO_FRAME);
+    }
+
+    #[test]
+    fn a2dp_recommended_configs() {
+        let high = Config::default();
+        assert_eq!(high.frame_len(), 119);
+        assert_eq!(high.bit_rate(), 327_993);
+        assert_eq!(high.header(), [SYNCWORD, 0xbd, 53]);
+
+        let high_48 = Config { sampling_frequency: 48000, bitpool: 51, ..Config::default() };
+        assert_eq!(high_48.frame_len(), 115);
+        assert_eq!(high_48.bit_rate(), 345_000);
+
+        let middle = Config { bitpool: 35, ..Config::default() };
+        assert_eq!(middle.frame_len(), 83);
+        assert_eq!(middle.bit_rate(), 228_768);
+
+        assert_eq!(Config::msbc().frame_len(), 57);
+        assert_eq!(Config::msbc().frame_samples(), 120);
+    }
+
+    #[test]
+    fn header_roundtrip() {
+        for config in test_configs() {
+            let mut header = config.header().to_vec();
+            header.push(0);
+            assert_eq!(Config::from_header(&header), Ok(config));
+        }
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!(Config { bitpool: 1, ..Config::default() }.validate().is_err());
+        assert!(Config { bitpool: 129, channel_mode: ChannelMode::Mono, ..Config::default() }
+            .validate()
+            .is_err());
+        assert!(Config { blocks: 15, ..Config::default() }.validate().is_err());
+        assert!(Config { bitpool: 30, ..Config::msbc() }.validate().is_err());
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut decoder = Decoder::new();
+        let mut pcm = Vec::new();
+        assert_eq!(
+            decoder.decode_frame(&MSBC_ZERO_FRAME[..56], &mut pcm),
+            Err(SbcError::Truncated { len: 56, frame_len: 57 })
+        );
+        let mut frame = MSBC_ZERO_FRAME;
+        frame[4] = 0x10;
+        assert!(matches!(
+            decoder.decode_frame(&frame, &mut pcm),
+            Err(SbcError::CrcMismatch { received: 0xc5, .. })
+        ));
+        frame[0] = 0x00;
+        assert_eq!(decoder.decode_frame(&frame, &mut pcm), Err(SbcError::InvalidSyncword(0x00)));
+        assert!(pcm.is_empty());
+    }
+
+    fn test_configs() -> Vec<Config> {
+        let mut configs = vec![Config::msbc()];
+        for channel_mode in
+            [ChannelMode::Mono, ChannelMode::DualChannel, ChannelMode::Stereo, ChannelMode::JointStereo]
+        {
+            for subbands in [4, 8] {
+                for allocation_method in [AllocationMethod::Loudness, AllocationMethod::Snr] {
+                    configs.push(Config {
+                        sampling_frequency: 48000,
+                        channel_mode,
+                        blocks: 16,
+                        subbands,
+                        allocation_method,
+                        bitpool: if channel_mode.channels() == 1 { 2 * subbands } else { 4 * subbands },
+                        msbc: false,
+                    });
+                }
+            }
+        }
+        configs
+    }
+
+    /// Signal to noise ratio in dB of the decoded output, compensating for the codec delay.
+    fn roundtrip_snr(config: Config) -> f64 {
+        let channels = config.channels();
+        let frames = 20;
+        let mut encoder = Encoder::new(config).unwrap();
+        let pcm: Vec<i16> = (0..frames * encoder.pcm_len())
+            .map(|i| {
+                let (t, ch) = ((i / channels) as f64, (i % channels) as f64);
+                let freq = 440.0 * (ch + 1.0) / config.sampling_frequency as f64;
+                (8000.0 * (2.0 * std::f64::consts::PI * freq * t).sin()) as i16
+            })
+            .collect();
+
+        let mut data = Vec::new();
+        for chunk in pcm.chunks(encoder.pcm_len()) {
+            encoder.encode_frame(chunk, &mut data).unwrap();
+        }
+        assert_eq!(data.len(), frames * config.frame_len());
+
+        let mut decoder = Decoder::new();
+        let mut decoded = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            pos += decoder.decode_frame(&data[pos..], &mut decoded).unwrap();
+        }
+        assert_eq!(decoded.len(), pcm.len());
+
+        let skip = 4 * config.frame_samples();
+        (0..200)
+            .map(|delay| {
+                let (mut signal, mut noise) = (0.0, 0.0);
+                for i in skip..(pcm.len() / channels - delay) {
+                    for ch in 0..channels {
+                        let s = pcm[i * channels + ch] as f64;
+                        let d = decoded[(i + delay) * channels + ch] as f64;
+                        signal += s * s;
+                        noise += (s - d) * (s - d);
+                    }
+                }
+                10.0 * (signal / noise).log10()
+            })
+            .fold(f64::MIN, f64::max)
+    }
+
+    #[test]
+    fn roundtrip() {
+        for config in test_configs() {
+            let snr = roundtrip_snr(config);
+            assert!(snr > 20.0, "SNR of {snr:.1} dB for {config:?}");
+        }
+    }
+
+    fn joint_stereo_config() -> Config {
+        Config {
+            sampling_frequency: 44100,
+            channel_mode: ChannelMode::JointStereo,
+            blocks: 16,
+            subbands: 4,
+            allocation_method: AllocationMethod::Snr,
+            bitpool: 16,
+            msbc: false,
+        }
+    }
+
+    /// Bit allocations worked out by hand following the SNR allocation procedure of the specification.
+    #[test]
+    fn snr_bit_allocation() {
+        let mono = Config { channel_mode: ChannelMode::Mono, bitpool: 10, ..joint_stereo_config() };
+        let mut bits = [[0; 8]; 2];
+        mono.allocate_bits(&[[4, 3, 2, 1, 0, 0, 0, 0], [0; 8]], &mut bits);
+        assert_eq!(bits[0][..4], [5, 3, 2, 0]);
+
+        let stereo = Config { bitpool: 6, ..joint_stereo_config() };
+        let mut bits = [[0; 8]; 2];
+        stereo.allocate_bits(&[[2, 0, 0, 0, 0, 0, 0, 0], [1, 0, 0, 0, 0, 0, 0, 0]], &mut bits);
+        assert_eq!(bits[0][..4], [4, 0, 0, 0]);
+        assert_eq!(bits[1][..4], [2, 0, 0, 0]);
+    }
+
+    /// Builds a joint stereo frame whose first subband contains the largest quantized value
+    /// in the channels selected by `peak` and zero everywhere else.
+    fn joint_stereo_frame(join: bool, peak: [bool; 2]) -> Vec<u8> {
+        let config = joint_stereo_config();
+        let scale_factors = [[8, 0, 0, 0, 0, 0, 0, 0]; 2];
+        let mut bits = [[0; 8]; 2];
+        config.allocate_bits(&scale_factors, &mut bits);
+
+        let mut frame = config.header().to_vec();
+        frame.push(0);
+        let mut w = BitWriter::new(&mut frame);
+        w.write(if join { 0b1000 } else { 0 }, 4);
+        for sfs in &scale_factors {
+            for sf in &sfs[..4] {
+                w.write(*sf as u32, 4);
+            }
+        }
+        for _ in 0..config.blocks {
+            for (ch, bits) in bits.iter().enumerate() {
+                for (sb, bits) in bits[..4].iter().enumerate() {
+                    let levels = (1u32 << bits) - 1;
+                    if levels == 0 {
+                        continue;
+                    }
+                    let q = if sb == 0 && peak[ch] { levels - 1 } else { levels / 2 };
+                    w.write(q, *bits);
+                }
+            }
+        }
+        frame.resize(config.frame_len(), 0);
+        frame[3] = frame_crc(&frame, 4 + 4 * 4 * 2);
+        frame
+    }
+
+    #[test]
+    fn joint_stereo_decode() {
+        let decode = |frame: Vec<u8>| {
+            let mut pcm = Vec::new();
+            Decoder::new().decode_frame(&frame, &mut pcm).unwrap();
+            let (left, right): (Vec<_>, Vec<_>) = pcm.chunks(2).map(|s| (s[0], s[1])).unzip();
+            assert!(left.iter().any(|s| s.abs() > 100), "{left:?}");
+            (left, right)
+        };
+
+        // Mid channel only: left and right are equal.
+        let (left, right) = decode(joint_stereo_frame(true, [true, false]));
+        assert_eq!(left, right);
+
+        // Side channel only: left and right are inverted.
+        let (left, right) = decode(joint_stereo_frame(true, [false, true]));
+        assert_eq!(left, right.iter().map(|s| -s).collect::<Vec<_>>());
+
+        // Without the join flag the channels are independent.
+        let (_, right) = decode(joint_stereo_frame(false, [true, false]));
+        assert!(right.iter().all(|s| *s == 0), "{right:?}");
+    }
+
+    #[test]
+    fn joint_stereo_encode() {
+        let config = joint_stereo_config();
+        let mut seed = 1u32;
+        let noise: Vec<i16> = (0..config.frame_samples())
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as i16 % 8000
+            })
+            .collect();
+
+        // The last subband is never joined.
+        for (right, join) in [(1, 0b1110), (-1, 0b1110), (0, 0b0000)] {
+            let pcm: Vec<i16> = noise.iter().flat_map(|s| [*s, right * s]).collect();
+            let mut frame = Vec::new();
+            Encoder::new(config).unwrap().encode_frame(&pcm, &mut frame).unwrap();
+            assert_eq!(frame[4] >> 4, join, "right channel factor {right}");
+            let mut decoded = Vec::new();
+            assert_eq!(Decoder::new().decode_frame(&frame, &mut decoded), Ok(config.frame_len()));
+        }
+    }
+}
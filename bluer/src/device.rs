@@ -17,10 +17,15 @@ use uuid::Uuid;
 use crate::{
     all_dbus_objects,
     gatt::{self, remote::Service, SERVICE_INTERFACE},
+    media::{self, player::Player},
+    network::{self, Network, NetworkProperty},
     Adapter, Address, AddressType, Error, ErrorKind, Event, InternalErrorKind, Modalias, Result, SessionInner,
     SERVICE_NAME, TIMEOUT,
 };
 
+#[cfg(feature = "sdp")]
+use crate::sdp::ServiceRecord;
+
 pub(crate) const INTERFACE: &str = "org.bluez.Device1";
 pub(crate) const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
 
@@ -90,10 +95,18 @@ impl Device {
 
     /// Streams device property changes.
     ///
+    /// This includes changes of the [network](Self::network) properties.
+    ///
     /// The stream ends when the device is removed.
     pub async fn events(&self) -> Result<impl Stream<Item = DeviceEvent>> {
         let events = self.inner.events(self.dbus_path.clone(), false).await?;
         let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { interface, changed, .. } if interface == network::INTERFACE => {
+                stream::iter(
+                    NetworkProperty::from_prop_map(changed).into_iter().map(DeviceEvent::NetworkPropertyChanged),
+                )
+                .boxed()
+            }
             Event::PropertiesChanged { changed, .. } => {
                 stream::iter(DeviceProperty::from_prop_map(changed).into_iter().map(DeviceEvent::PropertyChanged))
                     .boxed()
@@ -159,11 +172,55 @@ impl Device {
         Ok(services)
     }
 
+    /// Snapshot of the GATT database of the remote device.
+    ///
+    /// Walks all [services](Self::services), their characteristics and descriptors.
+    /// If `read_values` is true, the values of readable characteristics and of all descriptors
+    /// are read and included; values that cannot be read are omitted.
+    ///
+    /// The device must be connected for GATT services to be resolved.
+    pub async fn gatt_database(&self, read_values: bool) -> Result<gatt::database::Database> {
+        gatt::database::Database::from_device(self, read_values).await
+    }
+
     /// Remote GATT service with specified id.
     pub async fn service(&self, service_id: u16) -> Result<gatt::remote::Service> {
         gatt::remote::Service::new(self.inner.clone(), self.adapter_name.clone(), self.address, service_id)
     }
 
+    /// Media players of the remote device, controllable through AVRCP.
+    ///
+    /// Media players are only available while the AVRCP profile is connected.
+    pub async fn media_players(&self) -> Result<Vec<media::player::Player>> {
+        let mut players = Vec::new();
+        for (path, interfaces) in all_dbus_objects(&self.inner.connection).await? {
+            match Player::parse_dbus_path(&path) {
+                Some(((adapter, device_address), name))
+                    if adapter == *self.adapter_name
+                        && device_address == self.address
+                        && interfaces.contains_key(media::player::INTERFACE) =>
+                {
+                    players.push(self.media_player(name)?);
+                }
+                _ => (),
+            }
+        }
+
+        Ok(players)
+    }
+
+    /// Remote media player with specified name.
+    pub fn media_player(&self, name: &str) -> Result<media::player::Player> {
+        Player::new(self.inner.clone(), self.adapter_name.clone(), self.address, name)
+    }
+
+    /// Network service of the remote device for personal area networking (PAN).
+    ///
+    /// The remote device must provide a PAN service for the network interface to be available.
+    pub fn network(&self) -> Result<Network> {
+        Network::new(self.inner.clone(), self.adapter_name.clone(), self.address)
+    }
+
     dbus_interface!();
     dbus_default_interface!(INTERFACE);
 
@@ -237,6 +294,29 @@ impl Device {
         self.call_method("DisconnectProfile", (uuid.to_string(),)).await
     }
 
+    /// Retrieves the SDP service records of the device.
+    ///
+    /// The records are those found by the Bluetooth daemon during service discovery
+    /// of a classic Bluetooth (BR/EDR) device, for example when connecting or pairing.
+    /// Use [ServiceRecord::rfcomm_channel] or [ServiceRecord::l2cap_psm] to obtain the
+    /// channel or PSM of a service before connecting to it.
+    ///
+    /// This requires a recent version of the Bluetooth daemon.
+    #[cfg(feature = "sdp")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sdp")))]
+    pub async fn service_records(&self) -> Result<Vec<ServiceRecord>> {
+        let (records,): (Vec<Vec<u8>>,) = self.call_method("GetServiceRecords", ()).await?;
+        records
+            .iter()
+            .map(|record| {
+                ServiceRecord::decode(record).map_err(|err| Error {
+                    kind: ErrorKind::Internal(InternalErrorKind::InvalidValue),
+                    message: err.to_string(),
+                })
+            })
+            .collect()
+    }
+
     /// This method will connect to the remote device,
     /// initiate pairing and then retrieve all SDP records
     /// (or GATT primary services).
@@ -540,7 +620,10 @@ define_properties!(
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum DeviceEvent {
     /// Property changed.
     PropertyChanged(DeviceProperty),
+    /// Network property changed.
+    NetworkPropertyChanged(NetworkProperty),
 }
@@ -0,0 +1,777 @@
+//! Attribute Protocol (ATT) PDUs.
+
+use std::fmt;
+
+use uuid::Uuid;
+
+use super::{put_uuid, Reader};
+use crate::UuidExt;
+
+/// ATT opcodes.
+pub mod opcode {
+    /// Error response.
+    pub const ERROR_RSP: u8 = 0x01;
+    /// Exchange MTU request.
+    pub const EXCHANGE_MTU_REQ: u8 = 0x02;
+    /// Exchange MTU response.
+    pub const EXCHANGE_MTU_RSP: u8 = 0x03;
+    /// Find information request.
+    pub const FIND_INFORMATION_REQ: u8 = 0x04;
+    /// Find information response.
+    pub const FIND_INFORMATION_RSP: u8 = 0x05;
+    /// Find by type value request.
+    pub const FIND_BY_TYPE_VALUE_REQ: u8 = 0x06;
+    /// Find by type value response.
+    pub const FIND_BY_TYPE_VALUE_RSP: u8 = 0x07;
+    /// Read by type request.
+    pub const READ_BY_TYPE_REQ: u8 = 0x08;
+    /// Read by type response.
+    pub const READ_BY_TYPE_RSP: u8 = 0x09;
+    /// Read request.
+    pub const READ_REQ: u8 = 0x0a;
+    /// Read response.
+    pub const READ_RSP: u8 = 0x0b;
+    /// Read blob request.
+    pub const READ_BLOB_REQ: u8 = 0x0c;
+    /// Read blob response.
+    pub const READ_BLOB_RSP: u8 = 0x0d;
+    /// Read multiple request.
+    pub const READ_MULTIPLE_REQ: u8 = 0x0e;
+    /// Read multiple response.
+    pub const READ_MULTIPLE_RSP: u8 = 0x0f;
+    /// Read by group type request.
+    pub const READ_BY_GROUP_TYPE_REQ: u8 = 0x10;
+    /// Read by group type response.
+    pub const READ_BY_GROUP_TYPE_RSP: u8 = 0x11;
+    /// Write request.
+    pub const WRITE_REQ: u8 = 0x12;
+    /// Write response.
+    pub const WRITE_RSP: u8 = 0x13;
+    /// Prepare write request.
+    pub const PREPARE_WRITE_REQ: u8 = 0x16;
+    /// Prepare write response.
+    pub const PREPARE_WRITE_RSP: u8 = 0x17;
+    /// Execute write request.
+    pub const EXECUTE_WRITE_REQ: u8 = 0x18;
+    /// Execute write response.
+    pub const EXECUTE_WRITE_RSP: u8 = 0x19;
+    /// Handle value notification.
+    pub const HANDLE_VALUE_NTF: u8 = 0x1b;
+    /// Handle value indication.
+    pub const HANDLE_VALUE_IND: u8 = 0x1d;
+    /// Handle value confirmation.
+    pub const HANDLE_VALUE_CFM: u8 = 0x1e;
+    /// Read multiple variable length request.
+    pub const READ_MULTIPLE_VARIABLE_REQ: u8 = 0x20;
+    /// Read multiple variable length response.
+    pub const READ_MULTIPLE_VARIABLE_RSP: u8 = 0x21;
+    /// Multiple handle value notification.
+    pub const MULTIPLE_HANDLE_VALUE_NTF: u8 = 0x23;
+    /// Write command.
+    pub const WRITE_CMD: u8 = 0x52;
+    /// Signed write command.
+    pub const SIGNED_WRITE_CMD: u8 = 0xd2;
+}
+
+/// ATT error code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The attribute handle is invalid.
+    InvalidHandle,
+    /// The attribute cannot be read.
+    ReadNotPermitted,
+    /// The attribute cannot be written.
+    WriteNotPermitted,
+    /// The PDU is invalid.
+    InvalidPdu,
+    /// Authentication is required.
+    InsufficientAuthentication,
+    /// The request is not supported.
+    RequestNotSupported,
+    /// The offset is past the end of the attribute.
+    InvalidOffset,
+    /// Authorization is required.
+    InsufficientAuthorization,
+    /// Too many prepared writes are queued.
+    PrepareQueueFull,
+    /// No attribute was found within the handle range.
+    AttributeNotFound,
+    /// The attribute cannot be read using a read blob request.
+    AttributeNotLong,
+    /// The encryption key size is insufficient.
+    InsufficientEncryptionKeySize,
+    /// The attribute value length is invalid.
+    InvalidAttributeValueLength,
+    /// The request encountered an unlikely error.
+    UnlikelyError,
+    /// Encryption is required.
+    InsufficientEncryption,
+    /// The attribute type is not a supported grouping attribute.
+    UnsupportedGroupType,
+    /// Insufficient resources.
+    InsufficientResources,
+    /// The database is out of sync.
+    DatabaseOutOfSync,
+    /// The value is not allowed.
+    ValueNotAllowed,
+    /// Application or profile specific error.
+    Application(u8),
+    /// Other error code.
+    Other(u8),
+}
+
+impl From<u8> for ErrorCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => Self::InvalidHandle,
+            0x02 => Self::ReadNotPermitted,
+            0x03 => Self::WriteNotPermitted,
+            0x04 => Self::InvalidPdu,
+            0x05 => Self::InsufficientAuthentication,
+            0x06 => Self::RequestNotSupported,
+            0x07 => Self::InvalidOffset,
+            0x08 => Self::InsufficientAuthorization,
+            0x09 => Self::PrepareQueueFull,
+            0x0a => Self::AttributeNotFound,
+            0x0b => Self::AttributeNotLong,
+            0x0c => Self::InsufficientEncryptionKeySize,
+            0x0d => Self::InvalidAttributeValueLength,
+            0x0e => Self::UnlikelyError,
+            0x0f => Self::InsufficientEncryption,
+            0x10 => Self::UnsupportedGroupType,
+            0x11 => Self::InsufficientResources,
+            0x12 => Self::DatabaseOutOfSync,
+            0x13 => Self::ValueNotAllowed,
+            0x80..=0x9f => Self::Application(code),
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<ErrorCode> for u8 {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::InvalidHandle => 0x01,
+            ErrorCode::ReadNotPermitted => 0x02,
+            ErrorCode::WriteNotPermitted => 0x03,
+            ErrorCode::InvalidPdu => 0x04,
+            ErrorCode::InsufficientAuthentication => 0x05,
+            ErrorCode::RequestNotSupported => 0x06,
+            ErrorCode::InvalidOffset => 0x07,
+            ErrorCode::InsufficientAuthorization => 0x08,
+            ErrorCode::PrepareQueueFull => 0x09,
+            ErrorCode::AttributeNotFound => 0x0a,
+            ErrorCode::AttributeNotLong => 0x0b,
+            ErrorCode::InsufficientEncryptionKeySize => 0x0c,
+            ErrorCode::InvalidAttributeValueLength => 0x0d,
+            ErrorCode::UnlikelyError => 0x0e,
+            ErrorCode::InsufficientEncryption => 0x0f,
+            ErrorCode::UnsupportedGroupType => 0x10,
+            ErrorCode::InsufficientResources => 0x11,
+            ErrorCode::DatabaseOutOfSync => 0x12,
+            ErrorCode::ValueNotAllowed => 0x13,
+            ErrorCode::Application(code) | ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Application(code) => write!(f, "application error 0x{code:02x}"),
+            Self::Other(code) => write!(f, "error 0x{code:02x}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Attribute Protocol PDU.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Pdu {
+    /// Error response.
+    ErrorRsp {
+        /// Opcode of the failed request.
+        request_opcode: u8,
+        /// Attribute handle the error refers to.
+        handle: u16,
+        /// Error code.
+        error: ErrorCode,
+    },
+    /// Exchange MTU request with client receive MTU.
+    ExchangeMtuReq(u16),
+    /// Exchange MTU response with server receive MTU.
+    ExchangeMtuRsp(u16),
+    /// Find information request.
+    FindInformationReq {
+        /// First requested handle.
+        start_handle: u16,
+        /// Last requested handle.
+        end_handle: u16,
+    },
+    /// Find information response with handles and attribute types.
+    FindInformationRsp(Vec<(u16, Uuid)>),
+    /// Find by type value request.
+    FindByTypeValueReq {
+        /// First requested handle.
+        start_handle: u16,
+        /// Last requested handle.
+        end_handle: u16,
+        /// 16-bit attribute type.
+        attribute_type: u16,
+        /// Attribute value to find.
+        value: Vec<u8>,
+    },
+    /// Find by type value response with found handle and group end handle.
+    FindByTypeValueRsp(Vec<(u16, u16)>),
+    /// Read by type request.
+    ReadByTypeReq {
+        /// First requested handle.
+        start_handle: u16,
+        /// Last requested handle.
+        end_handle: u16,
+        /// Attribute type.
+        attribute_type: Uuid,
+    },
+    /// Read by type response with handles and values.
+    ///
+    /// All values must have the same length.
+    ReadByTypeRsp(Vec<(u16, Vec<u8>)>),
+    /// Read request.
+    ReadReq {
+        /// Attribute handle.
+        handle: u16,
+    },
+    /// Read response.
+    ReadRsp(Vec<u8>),
+    /// Read blob request.
+    ReadBlobReq {
+        /// Attribute handle.
+        handle: u16,
+        /// Value offset.
+        offset: u16,
+    },
+    /// Read blob response.
+    ReadBlobRsp(Vec<u8>),
+    /// Read multiple request with attribute handles.
+    ReadMultipleReq(Vec<u16>),
+    /// Read multiple response with concatenated values.
+    ReadMultipleRsp(Vec<u8>),
+    /// Read by group type request.
+    ReadByGroupTypeReq {
+        /// First requested handle.
+        start_handle: u16,
+        /// Last requested handle.
+        end_handle: u16,
+        /// Grouping attribute type.
+        group_type: Uuid,
+    },
+    /// Read by group type response with attribute handle, group end handle and value.
+    ///
+    /// All values must have the same length.
+    ReadByGroupTypeRsp(Vec<(u16, u16, Vec<u8>)>),
+    /// Write request.
+    WriteReq {
+        /// Attribute handle.
+        handle: u16,
+        /// Value.
+        value: Vec<u8>,
+    },
+    /// Write response.
+    WriteRsp,
+    /// Write command.
+    WriteCmd {
+        /// Attribute handle.
+        handle: u16,
+        /// Value.
+        value: Vec<u8>,
+    },
+    /// Signed write command.
+    SignedWriteCmd {
+        /// Attribute handle.
+        handle: u16,
+        /// Value.
+        value: Vec<u8>,
+        /// Authentication signature.
+        signature: [u8; 12],
+    },
+    /// Prepare write request.
+    PrepareWriteReq {
+        /// Attribute handle.
+        handle: u16,
+        /// Value offset.
+        offset: u16,
+        /// Part of the value.
+        value: Vec<u8>,
+    },
+    /// Prepare write response.
+    PrepareWriteRsp {
+        /// Attribute handle.
+        handle: u16,
+        /// Value offset.
+        offset: u16,
+        /// Part of the value.
+        value: Vec<u8>,
+    },
+    /// Execute write request.
+    ///
+    /// If `true` all prepared writes are executed, otherwise they are cancelled.
+    ExecuteWriteReq(bool),
+    /// Execute write response.
+    ExecuteWriteRsp,
+    /// Handle value notification.
+    HandleValueNtf {
+        /// Attribute handle.
+        handle: u16,
+        /// Value.
+        value: Vec<u8>,
+    },
+    /// Handle value indication.
+    HandleValueInd {
+        /// Attribute handle.
+        handle: u16,
+        /// Value.
+        value: Vec<u8>,
+    },
+    /// Handle value confirmation.
+    HandleValueCfm,
+    /// Read multiple variable length request with attribute handles.
+    ReadMultipleVariableReq(Vec<u16>),
+    /// Read multiple variable length response with values.
+    ReadMultipleVariableRsp(Vec<Vec<u8>>),
+    /// Multiple handle value notification with handles and values.
+    MultipleHandleValueNtf(Vec<(u16, Vec<u8>)>),
+    /// Other PDU.
+    Other {
+        /// Opcode.
+        opcode: u8,
+        /// Parameters.
+        data: Vec<u8>,
+    },
+}
+
+impl Pdu {
+    /// Parses an ATT PDU.
+    ///
+    /// Returns `None` if the PDU is malformed.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        use opcode::*;
+
+        let mut r = Reader::new(data);
+        let opcode = r.u8()?;
+        let handles = |r: &mut Reader| -> Option<Vec<u16>> {
+            let mut handles = Vec::new();
+            while !r.is_empty() {
+                handles.push(r.u16()?);
+            }
+            Some(handles)
+        };
+        let pdu = match opcode {
+            ERROR_RSP => Self::ErrorRsp { request_opcode: r.u8()?, handle: r.u16()?, error: r.u8()?.into() },
+            EXCHANGE_MTU_REQ => Self::ExchangeMtuReq(r.u16()?),
+            EXCHANGE_MTU_RSP => Self::ExchangeMtuRsp(r.u16()?),
+            FIND_INFORMATION_REQ => Self::FindInformationReq { start_handle: r.u16()?, end_handle: r.u16()? },
+            FIND_INFORMATION_RSP => {
+                let uuid_len = match r.u8()? {
+                    0x01 => 2,
+                    0x02 => 16,
+                    _ => return None,
+                };
+                let mut entries = Vec::new();
+                while !r.is_empty() {
+                    let handle = r.u16()?;
+                    entries.push((handle, Reader::new(r.bytes(uuid_len)?).uuid()?));
+                }
+                Self::FindInformationRsp(entries)
+            }
+            FIND_BY_TYPE_VALUE_REQ => Self::FindByTypeValueReq {
+                start_handle: r.u16()?,
+                end_handle: r.u16()?,
+                attribute_type: r.u16()?,
+                value: r.rest().to_vec(),
+            },
+            FIND_BY_TYPE_VALUE_RSP => {
+                let mut entries = Vec::new();
+                while !r.is_empty() {
+                    entries.push((r.u16()?, r.u16()?));
+                }
+                Self::FindByTypeValueRsp(entries)
+            }
+            READ_BY_TYPE_REQ => {
+                Self::ReadByTypeReq { start_handle: r.u16()?, end_handle: r.u16()?, attribute_type: r.uuid()? }
+            }
+            READ_BY_TYPE_RSP => {
+                let len = r.u8()? as usize;
+                if len < 2 {
+                    return None;
+                }
+                let mut entries = Vec::new();
+                while !r.is_empty() {
+                    let handle = r.u16()?;
+                    entries.push((handle, r.bytes(len - 2)?.to_vec()));
+                }
+                Self::ReadByTypeRsp(entries)
+            }
+            READ_REQ => Self::ReadReq { handle: r.u16()? },
+            READ_RSP => Self::ReadRsp(r.rest().to_vec()),
+            READ_BLOB_REQ => Self::ReadBlobReq { handle: r.u16()?, offset: r.u16()? },
+            READ_BLOB_RSP => Self::ReadBlobRsp(r.rest().to_vec()),
+            READ_MULTIPLE_REQ => Self::ReadMultipleReq(handles(&mut r)?),
+            READ_MULTIPLE_RSP => Self::ReadMultipleRsp(r.rest().to_vec()),
+            READ_BY_GROUP_TYPE_REQ => {
+                Self::ReadByGroupTypeReq { start_handle: r.u16()?, end_handle: r.u16()?, group_type: r.uuid()? }
+            }
+            READ_BY_GROUP_TYPE_RSP => {
+                let len = r.u8()? as usize;
+                if len < 4 {
+                    return None;
+                }
+                let mut entries = Vec::new();
+                while !r.is_empty() {
+                    let handle = r.u16()?;
+                    let end_handle = r.u16()?;
+                    entries.push((handle, end_handle, r.bytes(len - 4)?.to_vec()));
+                }
+                Self::ReadByGroupTypeRsp(entries)
+            }
+            WRITE_REQ => Self::WriteReq { handle: r.u16()?, value: r.rest().to_vec() },
+            WRITE_RSP => Self::WriteRsp,
+            WRITE_CMD => Self::WriteCmd { handle: r.u16()?, value: r.rest().to_vec() },
+            SIGNED_WRITE_CMD => {
+                let handle = r.u16()?;
+                let rest = r.rest();
+                let (value, signature) = rest.split_at(rest.len().checked_sub(12)?);
+                Self::SignedWriteCmd { handle, value: value.to_vec(), signature: signature.try_into().ok()? }
+            }
+            PREPARE_WRITE_REQ => {
+                Self::PrepareWriteReq { handle: r.u16()?, offset: r.u16()?, value: r.rest().to_vec() }
+            }
+            PREPARE_WRITE_RSP => {
+                Self::PrepareWriteRsp { handle: r.u16()?, offset: r.u16()?, value: r.rest().to_vec() }
+            }
+            EXECUTE_WRITE_REQ => Self::ExecuteWriteReq(r.u8()? != 0),
+            EXECUTE_WRITE_RSP => Self::ExecuteWriteRsp,
+            HANDLE_VALUE_NTF => Self::HandleValueNtf { handle: r.u16()?, value: r.rest().to_vec() },
+            HANDLE_VALUE_IND => Self::HandleValueInd { handle: r.u16()?, value: r.rest().to_vec() },
+            HANDLE_VALUE_CFM => Self::HandleValueCfm,
+            READ_MULTIPLE_VARIABLE_REQ => Self::ReadMultipleVariableReq(handles(&mut r)?),
+            READ_MULTIPLE_VARIABLE_RSP => {
+                let mut values = Vec::new();
+                while !r.is_empty() {
+                    let len = r.u16()? as usize;
+                    values.push(r.bytes(len)?.to_vec());
+                }
+                Self::ReadMultipleVariableRsp(values)
+            }
+            MULTIPLE_HANDLE_VALUE_NTF => {
+                let mut entries = Vec::new();
+                while !r.is_empty() {
+                    let handle = r.u16()?;
+                    let len = r.u16()? as usize;
+                    entries.push((handle, r.bytes(len)?.to_vec()));
+                }
+                Self::MultipleHandleValueNtf(entries)
+            }
+            _ => return Some(Self::Other { opcode, data: r.rest().to_vec() }),
+        };
+        r.is_empty().then_some(pdu)
+    }
+
+    /// Opcode of this PDU.
+    pub fn opcode(&self) -> u8 {
+        use opcode::*;
+        match self {
+            Self::ErrorRsp { .. } => ERROR_RSP,
+            Self::ExchangeMtuReq(_) => EXCHANGE_MTU_REQ,
+            Self::ExchangeMtuRsp(_) => EXCHANGE_MTU_RSP,
+            Self::FindInformationReq { .. } => FIND_INFORMATION_REQ,
+            Self::FindInformationRsp(_) => FIND_INFORMATION_RSP,
+            Self::FindByTypeValueReq { .. } => FIND_BY_TYPE_VALUE_REQ,
+            Self::FindByTypeValueRsp(_) => FIND_BY_TYPE_VALUE_RSP,
+            Self::ReadByTypeReq { .. } => READ_BY_TYPE_REQ,
+            Self::ReadByTypeRsp(_) => READ_BY_TYPE_RSP,
+            Self::ReadReq { .. } => READ_REQ,
+            Self::ReadRsp(_) => READ_RSP,
+            Self::ReadBlobReq { .. } => READ_BLOB_REQ,
+            Self::ReadBlobRsp(_) => READ_BLOB_RSP,
+            Self::ReadMultipleReq(_) => READ_MULTIPLE_REQ,
+            Self::ReadMultipleRsp(_) => READ_MULTIPLE_RSP,
+            Self::ReadByGroupTypeReq { .. } => READ_BY_GROUP_TYPE_REQ,
+            Self::ReadByGroupTypeRsp(_) => READ_BY_GROUP_TYPE_RSP,
+            Self::WriteReq { .. } => WRITE_REQ,
+            Self::WriteRsp => WRITE_RSP,
+            Self::WriteCmd { .. } => WRITE_CMD,
+            Self::SignedWriteCmd { .. } => SIGNED_WRITE_CMD,
+            Self::PrepareWriteReq { .. } => PREPARE_WRITE_REQ,
+            Self::PrepareWriteRsp { .. } => PREPARE_WRITE_RSP,
+            Self::ExecuteWriteReq(_) => EXECUTE_WRITE_REQ,
+            Self::ExecuteWriteRsp => EXECUTE_WRITE_RSP,
+            Self::HandleValueNtf { .. } => HANDLE_VALUE_NTF,
+            Self::HandleValueInd { .. } => HANDLE_VALUE_IND,
+            Self::HandleValueCfm => HANDLE_VALUE_CFM,
+            Self::ReadMultipleVariableReq(_) => READ_MULTIPLE_VARIABLE_REQ,
+            Self::ReadMultipleVariableRsp(_) => READ_MULTIPLE_VARIABLE_RSP,
+            Self::MultipleHandleValueNtf(_) => MULTIPLE_HANDLE_VALUE_NTF,
+            Self::Other { opcode, .. } => *opcode,
+        }
+    }
+
+    /// Encodes the PDU.
+    ///
+    /// Entries of list responses are encoded as given; the caller must ensure
+    /// that they have consistent lengths and UUID sizes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![self.opcode()];
+        let put_u16 = |buf: &mut Vec<u8>, v: u16| buf.extend_from_slice(&v.to_le_bytes());
+        match self {
+            Self::ErrorRsp { request_opcode, handle, error } => {
+                buf.push(*request_opcode);
+                put_u16(&mut buf, *handle);
+                buf.push((*error).into());
+            }
+            Self::ExchangeMtuReq(mtu) | Self::ExchangeMtuRsp(mtu) => put_u16(&mut buf, *mtu),
+            Self::FindInformationReq { start_handle, end_handle } => {
+                put_u16(&mut buf, *start_handle);
+                put_u16(&mut buf, *end_handle);
+            }
+            Self::FindInformationRsp(entries) => {
+                let short = entries.iter().all(|(_, uuid)| uuid.as_u16().is_some());
+                buf.push(if short { 0x01 } else { 0x02 });
+                for (handle, uuid) in entries {
+                    put_u16(&mut buf, *handle);
+                    match (short, uuid.as_u16()) {
+                        (true, Some(short)) => put_u16(&mut buf, short),
+                        _ => buf.extend_from_slice(&uuid.as_u128().to_le_bytes()),
+                    }
+                }
+            }
+            Self::FindByTypeValueReq { start_handle, end_handle, attribute_type, value } => {
+                put_u16(&mut buf, *start_handle);
+                put_u16(&mut buf, *end_handle);
+                put_u16(&mut buf, *attribute_type);
+                buf.extend_from_slice(value);
+            }
+            Self::FindByTypeValueRsp(entries) => {
+                for (handle, end_handle) in entries {
+                    put_u16(&mut buf, *handle);
+                    put_u16(&mut buf, *end_handle);
+                }
+            }
+            Self::ReadByTypeReq { start_handle, end_handle, attribute_type: uuid }
+            | Self::ReadByGroupTypeReq { start_handle, end_handle, group_type: uuid } => {
+                put_u16(&mut buf, *start_handle);
+                put_u16(&mut buf, *end_handle);
+                put_uuid(&mut buf, uuid);
+            }
+            Self::ReadByTypeRsp(entries) => {
+                buf.push(entries.first().map(|(_, value)| value.len() + 2).unwrap_or(2) as u8);
+                for (handle, value) in entries {
+                    put_u16(&mut buf, *handle);
+                    buf.extend_from_slice(value);
+                }
+            }
+            Self::ReadReq { handle } => put_u16(&mut buf, *handle),
+            Self::ReadRsp(value) | Self::ReadBlobRsp(value) | Self::ReadMultipleRsp(value) => {
+                buf.extend_from_slice(value)
+            }
+            Self::ReadBlobReq { handle, offset } => {
+                put_u16(&mut buf, *handle);
+                put_u16(&mut buf, *offset);
+            }
+            Self::ReadMultipleReq(handles) | Self::ReadMultipleVariableReq(handles) => {
+                for handle in handles {
+                    put_u16(&mut buf, *handle);
+                }
+            }
+            Self::ReadByGroupTypeRsp(entries) => {
+                buf.push(entries.first().map(|(_, _, value)| value.len() + 4).unwrap_or(4) as u8);
+                for (handle, end_handle, value) in entries {
+                    put_u16(&mut buf, *handle);
+                    put_u16(&mut buf, *end_handle);
+                    buf.extend_from_slice(value);
+                }
+            }
+            Self::WriteReq { handle, value }
+            | Self::WriteCmd { handle, value }
+            | Self::HandleValueNtf { handle, value }
+            | Self::HandleValueInd { handle, value } => {
+                put_u16(&mut buf, *handle);
+                buf.extend_from_slice(value);
+            }
+            Self::SignedWriteCmd { handle, value, signature } => {
+                put_u16(&mut buf, *handle);
+                buf.extend_from_slice(value);
+                buf.extend_from_slice(signature);
+            }
+            Self::PrepareWriteReq { handle, offset, value } | Self::PrepareWriteRsp { handle, offset, value } => {
+                put_u16(&mut buf, *handle);
+                put_u16(&mut buf, *offset);
+                buf.extend_from_slice(value);
+            }
+            Self::ExecuteWriteReq(execute) => buf.push((*execute).into()),
+            Self::WriteRsp | Self::ExecuteWriteRsp | Self::HandleValueCfm => (),
+            Self::ReadMultipleVariableRsp(values) => {
+                for value in values {
+                    put_u16(&mut buf, value.len() as u16);
+                    buf.extend_from_slice(value);
+                }
+            }
+            Self::MultipleHandleValueNtf(entries) => {
+                for (handle, value) in entries {
+                    put_u16(&mut buf, *handle);
+                    put_u16(&mut buf, value.len() as u16);
+                    buf.extend_from_slice(value);
+                }
+            }
+            Self::Other { data, .. } => buf.extend_from_slice(data),
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(data: &[u8], pdu: Pdu) {
+        assert_eq!(Pdu::parse(data).as_ref(), Some(&pdu));
+        assert_eq!(pdu.to_bytes(), data);
+    }
+
+    #[test]
+    fn discovery() {
+        roundtrip(&[0x02, 0x05, 0x02], Pdu::ExchangeMtuReq(517));
+        roundtrip(&[0x03, 0xf7, 0x00], Pdu::ExchangeMtuRsp(247));
+        roundtrip(
+            &[0x10, 0x01, 0x00, 0xff, 0xff, 0x00, 0x28],
+            Pdu::ReadByGroupTypeReq {
+                start_handle: 0x0001,
+                end_handle: 0xffff,
+                group_type: Uuid::from_u16(0x2800),
+            },
+        );
+        roundtrip(
+            &[0x11, 0x06, 0x01, 0x00, 0x05, 0x00, 0x00, 0x18, 0x06, 0x00, 0x09, 0x00, 0x01, 0x18],
+            Pdu::ReadByGroupTypeRsp(vec![(0x0001, 0x0005, vec![0x00, 0x18]), (0x0006, 0x0009, vec![0x01, 0x18])]),
+        );
+        roundtrip(
+            &[0x08, 0x01, 0x00, 0x05, 0x00, 0x03, 0x28],
+            Pdu::ReadByTypeReq {
+                start_handle: 0x0001,
+                end_handle: 0x0005,
+                attribute_type: Uuid::from_u16(0x2803),
+            },
+        );
+        roundtrip(
+            &[0x09, 0x07, 0x02, 0x00, 0x02, 0x03, 0x00, 0x00, 0x2a],
+            Pdu::ReadByTypeRsp(vec![(0x0002, vec![0x02, 0x03, 0x00, 0x00, 0x2a])]),
+        );
+        roundtrip(
+            &[0x06, 0x01, 0x00, 0xff, 0xff, 0x00, 0x28, 0x0d, 0x18],
+            Pdu::FindByTypeValueReq {
+                start_handle: 0x0001,
+                end_handle: 0xffff,
+                attribute_type: 0x2800,
+                value: vec![0x0d, 0x18],
+            },
+        );
+        roundtrip(&[0x07, 0x0a, 0x00, 0x10, 0x00], Pdu::FindByTypeValueRsp(vec![(0x000a, 0x0010)]));
+        roundtrip(
+            &[0x04, 0x0c, 0x00, 0x0d, 0x00],
+            Pdu::FindInformationReq { start_handle: 0x000c, end_handle: 0x000d },
+        );
+        roundtrip(
+            &[0x05, 0x01, 0x0c, 0x00, 0x37, 0x2a, 0x0d, 0x00, 0x02, 0x29],
+            Pdu::FindInformationRsp(vec![(0x000c, Uuid::from_u16(0x2a37)), (0x000d, Uuid::from_u16(0x2902))]),
+        );
+
+        let uuid = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        let mut data = vec![0x05, 0x02, 0x10, 0x00];
+        data.extend_from_slice(&uuid.as_u128().to_le_bytes());
+        roundtrip(&data, Pdu::FindInformationRsp(vec![(0x0010, uuid)]));
+    }
+
+    #[test]
+    fn errors() {
+        roundtrip(
+            &[0x01, 0x10, 0x0a, 0x00, 0x0a],
+            Pdu::ErrorRsp { request_opcode: 0x10, handle: 0x000a, error: ErrorCode::AttributeNotFound },
+        );
+        roundtrip(
+            &[0x01, 0x12, 0x03, 0x00, 0x80],
+            Pdu::ErrorRsp { request_opcode: 0x12, handle: 0x0003, error: ErrorCode::Application(0x80) },
+        );
+        for code in 0..=u8::MAX {
+            assert_eq!(u8::from(ErrorCode::from(code)), code);
+        }
+    }
+
+    #[test]
+    fn read_write() {
+        roundtrip(&[0x0a, 0x03, 0x00], Pdu::ReadReq { handle: 0x0003 });
+        roundtrip(&[0x0b, b'B', b'l', b'u', b'e', b'R'], Pdu::ReadRsp(b"BlueR".to_vec()));
+        roundtrip(&[0x0c, 0x03, 0x00, 0x16, 0x00], Pdu::ReadBlobReq { handle: 0x0003, offset: 22 });
+        roundtrip(&[0x0e, 0x03, 0x00, 0x05, 0x00], Pdu::ReadMultipleReq(vec![0x0003, 0x0005]));
+        roundtrip(
+            &[0x21, 0x01, 0x00, 0x01, 0x02, 0x00, 0x02, 0x03],
+            Pdu::ReadMultipleVariableRsp(vec![vec![0x01], vec![0x02, 0x03]]),
+        );
+        roundtrip(&[0x12, 0x0d, 0x00, 0x01, 0x00], Pdu::WriteReq { handle: 0x000d, value: vec![0x01, 0x00] });
+        roundtrip(&[0x13], Pdu::WriteRsp);
+        roundtrip(&[0x52, 0x0f, 0x00, 0xaa], Pdu::WriteCmd { handle: 0x000f, value: vec![0xaa] });
+        roundtrip(
+            &[0x16, 0x0f, 0x00, 0x02, 0x00, 0xaa, 0xbb],
+            Pdu::PrepareWriteReq { handle: 0x000f, offset: 2, value: vec![0xaa, 0xbb] },
+        );
+        roundtrip(&[0x18, 0x01], Pdu::ExecuteWriteReq(true));
+        roundtrip(&[0x19], Pdu::ExecuteWriteRsp);
+
+        let mut data = vec![0xd2, 0x0f, 0x00, 0xaa];
+        data.extend(1..=12);
+        let signature: [u8; 12] = data[4..].try_into().unwrap();
+        roundtrip(&data, Pdu::SignedWriteCmd { handle: 0x000f, value: vec![0xaa], signature });
+    }
+
+    #[test]
+    fn notifications() {
+        roundtrip(
+            &[0x1b, 0x0c, 0x00, 0x06, 0x48],
+            Pdu::HandleValueNtf { handle: 0x000c, value: vec![0x06, 0x48] },
+        );
+        roundtrip(
+            &[0x1d, 0x08, 0x00, 0x01, 0x00, 0xff, 0xff],
+            Pdu::HandleValueInd { handle: 0x0008, value: vec![0x01, 0x00, 0xff, 0xff] },
+        );
+        roundtrip(&[0x1e], Pdu::HandleValueCfm);
+        roundtrip(
+            &[0x23, 0x0c, 0x00, 0x01, 0x00, 0x48, 0x10, 0x00, 0x00, 0x00],
+            Pdu::MultipleHandleValueNtf(vec![(0x000c, vec![0x48]), (0x0010, vec![])]),
+        );
+    }
+
+    #[test]
+    fn other() {
+        roundtrip(&[0x30, 0x01, 0x02], Pdu::Other { opcode: 0x30, data: vec![0x01, 0x02] });
+    }
+
+    #[test]
+    fn malformed() {
+        for data in [
+            &[][..],
+            &[0x02, 0x05],
+            &[0x02, 0x05, 0x02, 0x00],
+            &[0x05, 0x03, 0x0c, 0x00, 0x37, 0x2a],
+            &[0x05, 0x01, 0x0c, 0x00, 0x37],
+            &[0x08, 0x01, 0x00, 0x05, 0x00, 0x03, 0x28, 0x00],
+            &[0x09, 0x01, 0x02, 0x00],
+            &[0x09, 0x04, 0x02, 0x00, 0x01, 0x03, 0x00],
+            &[0x11, 0x03, 0x01, 0x00, 0x05],
+            &[0x0e, 0x03, 0x00, 0x05],
+            &[0xd2, 0x0f, 0x00, 0x01, 0x02],
+            &[0x21, 0x02, 0x00, 0x01],
+        ] {
+            assert_eq!(Pdu::parse(data), None, "{data:02x?}");
+        }
+    }
+}
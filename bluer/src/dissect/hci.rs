@@ -0,0 +1,144 @@
+//! Host Controller Interface (HCI) packets.
+
+use super::Reader;
+
+/// H4 packet type of an HCI command packet.
+pub const H4_COMMAND: u8 = 0x01;
+/// H4 packet type of an HCI ACL data packet.
+pub const H4_ACL: u8 = 0x02;
+/// H4 packet type of an HCI SCO data packet.
+pub const H4_SCO: u8 = 0x03;
+/// H4 packet type of an HCI event packet.
+pub const H4_EVENT: u8 = 0x04;
+/// H4 packet type of an HCI ISO data packet.
+pub const H4_ISO: u8 = 0x05;
+
+/// Disconnection Complete event code.
+pub const EVT_DISCONN_COMPLETE: u8 = 0x05;
+/// Command Complete event code.
+pub const EVT_CMD_COMPLETE: u8 = 0x0e;
+/// Command Status event code.
+pub const EVT_CMD_STATUS: u8 = 0x0f;
+/// LE Meta event code.
+pub const EVT_LE_META: u8 = 0x3e;
+
+/// Packet boundary flag of a continuing ACL fragment.
+pub const ACL_CONT: u8 = 0x01;
+
+/// Direction of a packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// Sent from host to controller.
+    Tx,
+    /// Received by host from controller.
+    Rx,
+}
+
+impl Direction {
+    /// The opposite direction.
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Tx => Self::Rx,
+            Self::Rx => Self::Tx,
+        }
+    }
+}
+
+/// HCI ACL data packet.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AclPacket {
+    /// Connection handle.
+    pub handle: u16,
+    /// Packet boundary flag.
+    pub boundary: u8,
+    /// Broadcast flag.
+    pub broadcast: u8,
+    /// Data.
+    pub data: Vec<u8>,
+}
+
+impl AclPacket {
+    /// Parses an ACL data packet, starting with the handle.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let handle_flags = r.u16()?;
+        let len = r.u16()? as usize;
+        Some(Self {
+            handle: handle_flags & 0x0fff,
+            boundary: ((handle_flags >> 12) & 0x3) as u8,
+            broadcast: ((handle_flags >> 14) & 0x3) as u8,
+            data: r.bytes(len)?.to_vec(),
+        })
+    }
+
+    /// Whether this is a continuing fragment of an L2CAP frame.
+    pub fn is_continuation(&self) -> bool {
+        self.boundary == ACL_CONT
+    }
+}
+
+/// HCI packet.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HciPacket {
+    /// Command packet.
+    Command {
+        /// Opcode, consisting of opcode group field and opcode command field.
+        opcode: u16,
+        /// Parameters.
+        params: Vec<u8>,
+    },
+    /// Event packet.
+    Event {
+        /// Event code.
+        code: u8,
+        /// Parameters.
+        params: Vec<u8>,
+    },
+    /// ACL data packet.
+    Acl(Direction, AclPacket),
+    /// SCO data packet, starting with the handle.
+    Sco(Direction, Vec<u8>),
+    /// ISO data packet, starting with the handle.
+    Iso(Direction, Vec<u8>),
+}
+
+impl HciPacket {
+    /// Parses a command packet, starting with the opcode.
+    pub fn parse_command(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let opcode = r.u16()?;
+        let len = r.u8()? as usize;
+        Some(Self::Command { opcode, params: r.bytes(len)?.to_vec() })
+    }
+
+    /// Parses an event packet, starting with the event code.
+    pub fn parse_event(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let code = r.u8()?;
+        let len = r.u8()? as usize;
+        Some(Self::Event { code, params: r.bytes(len)?.to_vec() })
+    }
+
+    /// Parses a packet in H4 (UART) framing, i.e. starting with the packet type.
+    pub fn parse_h4(direction: Direction, data: &[u8]) -> Option<Self> {
+        let (&ty, data) = data.split_first()?;
+        match ty {
+            H4_COMMAND => Self::parse_command(data),
+            H4_EVENT => Self::parse_event(data),
+            H4_ACL => Some(Self::Acl(direction, AclPacket::parse(data)?)),
+            H4_SCO => Some(Self::Sco(direction, data.to_vec())),
+            H4_ISO => Some(Self::Iso(direction, data.to_vec())),
+            _ => None,
+        }
+    }
+
+    /// Direction of the packet.
+    pub fn direction(&self) -> Direction {
+        match self {
+            Self::Command { .. } => Direction::Tx,
+            Self::Event { .. } => Direction::Rx,
+            Self::Acl(dir, _) | Self::Sco(dir, _) | Self::Iso(dir, _) => *dir,
+        }
+    }
+}
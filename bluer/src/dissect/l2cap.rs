@@ -0,0 +1,172 @@
+//! L2CAP frames and their reassembly from ACL fragments.
+
+use std::collections::HashMap;
+
+use super::{
+    hci::{AclPacket, Direction},
+    Reader,
+};
+
+/// Classic Bluetooth (BR/EDR) signaling channel.
+pub const CID_SIGNALING: u16 = 0x0001;
+/// Connectionless channel.
+pub const CID_CONNECTIONLESS: u16 = 0x0002;
+/// Attribute Protocol (ATT) channel.
+pub const CID_ATT: u16 = 0x0004;
+/// LE signaling channel.
+pub const CID_LE_SIGNALING: u16 = 0x0005;
+/// Security Manager Protocol channel.
+pub const CID_SMP: u16 = 0x0006;
+/// Classic Bluetooth (BR/EDR) Security Manager Protocol channel.
+pub const CID_SMP_BR_EDR: u16 = 0x0007;
+/// First dynamically allocated channel id.
+pub const CID_DYNAMIC_START: u16 = 0x0040;
+
+/// Basic L2CAP frame.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Frame {
+    /// Connection handle.
+    pub handle: u16,
+    /// Destination channel id.
+    pub cid: u16,
+    /// Payload.
+    pub payload: Vec<u8>,
+}
+
+/// Partially received frame.
+#[derive(Debug)]
+struct Partial {
+    len: usize,
+    cid: u16,
+    payload: Vec<u8>,
+}
+
+/// Reassembles L2CAP frames from ACL fragments.
+///
+/// Fragments are tracked separately per controller, connection and direction.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    partial: HashMap<(u16, Direction, u16), Partial>,
+}
+
+impl Reassembler {
+    /// Creates a new reassembler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes an ACL fragment of the controller with the specified index.
+    ///
+    /// Returns the frame when it has been completed.
+    /// Malformed and unexpected fragments are dropped.
+    pub fn push(&mut self, index: u16, direction: Direction, acl: &AclPacket) -> Option<Frame> {
+        let key = (index, direction, acl.handle);
+
+        let partial = if acl.is_continuation() {
+            let mut partial = self.partial.remove(&key)?;
+            partial.payload.extend_from_slice(&acl.data);
+            partial
+        } else {
+            if self.partial.remove(&key).is_some() {
+                log::trace!("Dropping incomplete L2CAP frame on handle {}", acl.handle);
+            }
+            let mut r = Reader::new(&acl.data);
+            let len = r.u16()? as usize;
+            let cid = r.u16()?;
+            Partial { len, cid, payload: r.rest().to_vec() }
+        };
+
+        if partial.payload.len() < partial.len {
+            self.partial.insert(key, partial);
+            return None;
+        }
+
+        let Partial { len, cid, mut payload } = partial;
+        payload.truncate(len);
+        Some(Frame { handle: acl.handle, cid, payload })
+    }
+
+    /// Discards all state of the specified connection.
+    pub fn remove_connection(&mut self, index: u16, handle: u16) {
+        self.partial.retain(|(i, _, h), _| (*i, *h) != (index, handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acl(data: &[u8]) -> AclPacket {
+        AclPacket::parse(data).unwrap()
+    }
+
+    #[test]
+    fn single_fragment() {
+        // ATT Exchange MTU Request on handle 0x0040.
+        let mut r = Reassembler::new();
+        let frame =
+            r.push(0, Direction::Tx, &acl(&[0x40, 0x00, 0x07, 0x00, 0x03, 0x00, 0x04, 0x00, 0x02, 0x00, 0x02]));
+        assert_eq!(frame, Some(Frame { handle: 0x0040, cid: CID_ATT, payload: vec![0x02, 0x00, 0x02] }));
+    }
+
+    #[test]
+    fn fragmented() {
+        // ATT Read Response of 20 bytes, split into three ACL fragments.
+        let value: Vec<u8> = (0..20).collect();
+        let mut first = vec![0x40, 0x20, 0x08, 0x00, 0x15, 0x00, 0x04, 0x00, 0x0b];
+        first.extend_from_slice(&value[..3]);
+        let mut second = vec![0x40, 0x10, 0x0a, 0x00];
+        second.extend_from_slice(&value[3..13]);
+        let mut third = vec![0x40, 0x10, 0x07, 0x00];
+        third.extend_from_slice(&value[13..]);
+
+        let mut r = Reassembler::new();
+        assert_eq!(r.push(0, Direction::Rx, &acl(&first)), None);
+        assert_eq!(r.push(0, Direction::Rx, &acl(&second)), None);
+        let frame = r.push(0, Direction::Rx, &acl(&third)).unwrap();
+        assert_eq!(frame.handle, 0x0040);
+        assert_eq!(frame.cid, CID_ATT);
+        assert_eq!(frame.payload[0], 0x0b);
+        assert_eq!(frame.payload[1..], value);
+    }
+
+    #[test]
+    fn interleaved() {
+        let mut r = Reassembler::new();
+        let start = |handle: u8| acl(&[handle, 0x20, 0x05, 0x00, 0x03, 0x00, 0x04, 0x00, 0x1b]);
+        let cont = |handle: u8| acl(&[handle, 0x10, 0x02, 0x00, handle, 0x00]);
+
+        assert_eq!(r.push(0, Direction::Rx, &start(0x40)), None);
+        assert_eq!(r.push(0, Direction::Rx, &start(0x41)), None);
+        assert_eq!(r.push(1, Direction::Rx, &start(0x40)), None);
+        assert_eq!(r.push(0, Direction::Tx, &cont(0x40)), None);
+
+        let frame = r.push(0, Direction::Rx, &cont(0x41)).unwrap();
+        assert_eq!(frame, Frame { handle: 0x0041, cid: CID_ATT, payload: vec![0x1b, 0x41, 0x00] });
+        let frame = r.push(0, Direction::Rx, &cont(0x40)).unwrap();
+        assert_eq!(frame, Frame { handle: 0x0040, cid: CID_ATT, payload: vec![0x1b, 0x40, 0x00] });
+
+        r.remove_connection(1, 0x0040);
+        assert_eq!(r.push(1, Direction::Rx, &cont(0x40)), None);
+    }
+
+    #[test]
+    fn unexpected_fragments() {
+        let mut r = Reassembler::new();
+
+        // Continuation without start.
+        assert_eq!(r.push(0, Direction::Rx, &acl(&[0x40, 0x10, 0x02, 0x00, 0x01, 0x02])), None);
+
+        // New start replaces incomplete frame.
+        assert_eq!(r.push(0, Direction::Rx, &acl(&[0x40, 0x20, 0x05, 0x00, 0x08, 0x00, 0x04, 0x00, 0x1b])), None);
+        let frame = r.push(0, Direction::Rx, &acl(&[0x40, 0x20, 0x05, 0x00, 0x01, 0x00, 0x04, 0x00, 0x1e]));
+        assert_eq!(frame, Some(Frame { handle: 0x0040, cid: CID_ATT, payload: vec![0x1e] }));
+
+        // Excess data is truncated.
+        let frame = r.push(0, Direction::Rx, &acl(&[0x40, 0x20, 0x06, 0x00, 0x01, 0x00, 0x04, 0x00, 0x1e, 0xff]));
+        assert_eq!(frame, Some(Frame { handle: 0x0040, cid: CID_ATT, payload: vec![0x1e] }));
+
+        // Start fragment too short to contain the basic header.
+        assert_eq!(r.push(0, Direction::Rx, &acl(&[0x40, 0x20, 0x03, 0x00, 0x01, 0x00, 0x04])), None);
+    }
+}
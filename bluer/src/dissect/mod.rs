@@ -0,0 +1,420 @@
+//! Dissection of captured HCI traffic.
+//!
+//! This decodes HCI packets, reassembles ACL fragments into L2CAP frames and
+//! decodes the PDUs of the Attribute Protocol ([att]), the Security Manager
+//! Protocol ([smp]) and the L2CAP [signaling] channels.
+//! Data of dynamic L2CAP channels is attributed to the PSM it was
+//! connected on and LE credit-based SDUs are reassembled.
+//!
+//! Feed [HCI packets](hci::HciPacket) in capture order into a [Dissector]
+//! to obtain [dissected PDUs](Dissected).
+//! With the `monitor_capture` feature enabled, HCI packets can be obtained from
+//! live captures and btsnoop or pcap files.
+//!
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::UuidExt;
+
+pub mod att;
+pub mod hci;
+pub mod l2cap;
+pub mod signaling;
+pub mod smp;
+
+use hci::{Direction, HciPacket};
+
+/// Cursor for decoding little-endian protocol data.
+#[derive(Clone, Debug)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub(crate) fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() < n {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Some(head)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N)?.try_into().ok()
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Reads a 16-bit or 128-bit UUID, depending on the remaining length.
+    pub(crate) fn uuid(&mut self) -> Option<Uuid> {
+        match self.data.len() {
+            2 => Some(Uuid::from_u16(self.u16()?)),
+            16 => Some(Uuid::from_u128(u128::from_le_bytes(self.array()?))),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Appends a UUID in its shortest form used by ATT, i.e. 16 or 128 bits.
+pub(crate) fn put_uuid(buf: &mut Vec<u8>, uuid: &Uuid) {
+    match uuid.as_u16() {
+        Some(short) => buf.extend_from_slice(&short.to_le_bytes()),
+        None => buf.extend_from_slice(&uuid.as_u128().to_le_bytes()),
+    }
+}
+
+/// Decoded PDU of an L2CAP channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Pdu {
+    /// Attribute Protocol PDU.
+    Att(att::Pdu),
+    /// Security Manager Protocol PDU.
+    Smp(smp::Pdu),
+    /// Commands on the classic Bluetooth (BR/EDR) signaling channel.
+    Signaling(Vec<signaling::Command>),
+    /// Command on the LE signaling channel.
+    LeSignaling(signaling::Command),
+    /// Data of a connection-oriented channel.
+    ///
+    /// For LE credit-based channels this is a complete, reassembled SDU.
+    Data {
+        /// PSM the channel was connected on, if the connection has been captured.
+        psm: Option<u16>,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Data of a fixed channel that is not decoded or could not be decoded.
+    Raw(Vec<u8>),
+}
+
+/// Dissected PDU.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dissected {
+    /// Controller index.
+    pub index: u16,
+    /// Direction.
+    pub direction: Direction,
+    /// Connection handle.
+    pub handle: u16,
+    /// L2CAP channel id of the receiving side.
+    pub cid: u16,
+    /// Decoded PDU.
+    pub pdu: Pdu,
+}
+
+/// Key of an L2CAP channel endpoint.
+///
+/// Consists of controller index, connection handle, direction and destination channel id.
+type ChannelKey = (u16, u16, Direction, u16);
+
+/// Dynamic channel state.
+#[derive(Debug, Default)]
+struct Channel {
+    psm: u16,
+    le_credit_based: bool,
+    sdu: Option<(usize, Vec<u8>)>,
+}
+
+/// Channel connection request awaiting response.
+#[derive(Debug)]
+struct PendingConnect {
+    psm: u16,
+    scids: Vec<u16>,
+    le_credit_based: bool,
+}
+
+/// Stateful dissector of HCI traffic.
+///
+/// Feed all HCI packets in capture order using [push](Self::push).
+#[derive(Debug, Default)]
+pub struct Dissector {
+    reassembler: l2cap::Reassembler,
+    channels: HashMap<ChannelKey, Channel>,
+    pending: HashMap<(u16, u16, Direction, u8), PendingConnect>,
+}
+
+impl Dissector {
+    /// Creates a new dissector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Processes an HCI packet of the controller with the specified index.
+    ///
+    /// Returns a PDU when an L2CAP frame or SDU has been completed.
+    pub fn push(&mut self, index: u16, packet: &HciPacket) -> Option<Dissected> {
+        match packet {
+            HciPacket::Event { code: hci::EVT_DISCONN_COMPLETE, params } => {
+                if let Some(handle) = Reader::new(params.get(1..3)?).u16() {
+                    self.remove_connection(index, handle);
+                }
+                None
+            }
+            HciPacket::Acl(direction, acl) => {
+                let frame = self.reassembler.push(index, *direction, acl)?;
+                let pdu = self.decode(index, *direction, &frame)?;
+                Some(Dissected { index, direction: *direction, handle: frame.handle, cid: frame.cid, pdu })
+            }
+            _ => None,
+        }
+    }
+
+    /// PSM of the dynamic channel with the specified receiving channel id, if known.
+    pub fn channel_psm(&self, index: u16, handle: u16, direction: Direction, cid: u16) -> Option<u16> {
+        self.channels.get(&(index, handle, direction, cid)).map(|ch| ch.psm)
+    }
+
+    fn remove_connection(&mut self, index: u16, handle: u16) {
+        self.reassembler.remove_connection(index, handle);
+        self.channels.retain(|(i, h, _, _), _| (*i, *h) != (index, handle));
+        self.pending.retain(|(i, h, _, _), _| (*i, *h) != (index, handle));
+    }
+
+    fn decode(&mut self, index: u16, direction: Direction, frame: &l2cap::Frame) -> Option<Pdu> {
+        let raw = || Pdu::Raw(frame.payload.clone());
+        let pdu = match frame.cid {
+            l2cap::CID_ATT => att::Pdu::parse(&frame.payload).map(Pdu::Att).unwrap_or_else(raw),
+            l2cap::CID_SMP | l2cap::CID_SMP_BR_EDR => {
+                smp::Pdu::parse(&frame.payload).map(Pdu::Smp).unwrap_or_else(raw)
+            }
+            l2cap::CID_SIGNALING => match signaling::Command::parse_all(&frame.payload) {
+                Some(cmds) => {
+                    for cmd in &cmds {
+                        self.track(index, frame.handle, direction, cmd);
+                    }
+                    Pdu::Signaling(cmds)
+                }
+                None => raw(),
+            },
+            l2cap::CID_LE_SIGNALING => match signaling::Command::parse(&frame.payload) {
+                Some(cmd) => {
+                    self.track(index, frame.handle, direction, &cmd);
+                    Pdu::LeSignaling(cmd)
+                }
+                None => raw(),
+            },
+            cid if cid >= l2cap::CID_DYNAMIC_START => {
+                match self.channels.get_mut(&(index, frame.handle, direction, cid)) {
+                    Some(ch) if ch.le_credit_based => {
+                        // The first K-frame of an SDU starts with the SDU length.
+                        let (len, sdu) = match ch.sdu.take() {
+                            Some((len, mut sdu)) => {
+                                sdu.extend_from_slice(&frame.payload);
+                                (len, sdu)
+                            }
+                            None => {
+                                let mut r = Reader::new(&frame.payload);
+                                (r.u16()? as usize, r.rest().to_vec())
+                            }
+                        };
+                        if sdu.len() < len {
+                            ch.sdu = Some((len, sdu));
+                            return None;
+                        }
+                        Pdu::Data { psm: Some(ch.psm), data: sdu }
+                    }
+                    Some(ch) => Pdu::Data { psm: Some(ch.psm), data: frame.payload.clone() },
+                    None => Pdu::Data { psm: None, data: frame.payload.clone() },
+                }
+            }
+            _ => raw(),
+        };
+        Some(pdu)
+    }
+
+    /// Tracks channel connections to attribute data of dynamic channels.
+    fn track(&mut self, index: u16, handle: u16, direction: Direction, cmd: &signaling::Command) {
+        use signaling::CommandKind::*;
+        let id = cmd.identifier;
+        let (pending, dcids, success) = match &cmd.kind {
+            ConnectionRequest { psm, scid } => {
+                self.pending.insert(
+                    (index, handle, direction, id),
+                    PendingConnect { psm: *psm, scids: vec![*scid], le_credit_based: false },
+                );
+                return;
+            }
+            LeCreditBasedConnectionRequest { spsm, scid, .. } => {
+                self.pending.insert(
+                    (index, handle, direction, id),
+                    PendingConnect { psm: *spsm, scids: vec![*scid], le_credit_based: true },
+                );
+                return;
+            }
+            CreditBasedConnectionRequest { spsm, scids, .. } => {
+                self.pending.insert(
+                    (index, handle, direction, id),
+                    PendingConnect { psm: *spsm, scids: scids.clone(), le_credit_based: true },
+                );
+                return;
+            }
+            ConnectionResponse { dcid, result, .. } => {
+                let pending = self.pending.remove(&(index, handle, direction.opposite(), id));
+                (pending, vec![*dcid], *result == 0)
+            }
+            LeCreditBasedConnectionResponse { dcid, result, .. } => {
+                let pending = self.pending.remove(&(index, handle, direction.opposite(), id));
+                (pending, vec![*dcid], *result == 0)
+            }
+            CreditBasedConnectionResponse { dcids, result, .. } => {
+                let pending = self.pending.remove(&(index, handle, direction.opposite(), id));
+                (pending, dcids.clone(), *result == 0)
+            }
+            DisconnectionRequest { dcid, scid } => {
+                self.channels.remove(&(index, handle, direction, *dcid));
+                self.channels.remove(&(index, handle, direction.opposite(), *scid));
+                return;
+            }
+            _ => return,
+        };
+
+        let Some(pending) = pending else { return };
+        if !success && dcids.iter().all(|&dcid| dcid == 0) {
+            return;
+        }
+        // The requesting side sends with the destination channel ids allocated by the responder,
+        // the responding side sends with the source channel ids allocated by the requester.
+        for (&scid, &dcid) in pending.scids.iter().zip(dcids.iter()) {
+            if dcid == 0 {
+                continue;
+            }
+            for key in [(index, handle, direction.opposite(), dcid), (index, handle, direction, scid)] {
+                self.channels.insert(
+                    key,
+                    Channel { psm: pending.psm, le_credit_based: pending.le_credit_based, sdu: None },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(d: &mut Dissector, direction: Direction, h4: &[u8]) -> Option<Dissected> {
+        d.push(0, &HciPacket::parse_h4(direction, h4).unwrap())
+    }
+
+    #[test]
+    fn att() {
+        let mut d = Dissector::new();
+        let dissected = push(
+            &mut d,
+            Direction::Tx,
+            &[0x02, 0x40, 0x00, 0x07, 0x00, 0x03, 0x00, 0x04, 0x00, 0x0a, 0x03, 0x00],
+        )
+        .unwrap();
+        assert_eq!(
+            dissected,
+            Dissected {
+                index: 0,
+                direction: Direction::Tx,
+                handle: 0x0040,
+                cid: l2cap::CID_ATT,
+                pdu: Pdu::Att(att::Pdu::ReadReq { handle: 0x0003 }),
+            }
+        );
+
+        // Malformed PDUs are passed through raw.
+        let dissected =
+            push(&mut d, Direction::Rx, &[0x02, 0x40, 0x00, 0x05, 0x00, 0x01, 0x00, 0x04, 0x00, 0x02]);
+        assert_eq!(dissected.unwrap().pdu, Pdu::Raw(vec![0x02]));
+    }
+
+    #[test]
+    fn le_credit_based_channel() {
+        let mut d = Dissector::new();
+
+        // LE Credit Based Connection Request for SPSM 0x0080 with source channel 0x0040.
+        #[rustfmt::skip]
+        let req = [
+            0x02, 0x40, 0x00, 0x12, 0x00, 0x0e, 0x00, 0x05, 0x00,
+            0x14, 0x01, 0x0a, 0x00, 0x80, 0x00, 0x40, 0x00, 0x00, 0x02, 0x17, 0x00, 0x0a, 0x00,
+        ];
+        assert!(matches!(push(&mut d, Direction::Tx, &req).unwrap().pdu, Pdu::LeSignaling(_)));
+        assert_eq!(d.channel_psm(0, 0x0040, Direction::Rx, 0x0040), None);
+
+        // Response allocating destination channel 0x0041.
+        #[rustfmt::skip]
+        let rsp = [
+            0x02, 0x40, 0x00, 0x12, 0x00, 0x0e, 0x00, 0x05, 0x00,
+            0x15, 0x01, 0x0a, 0x00, 0x41, 0x00, 0x00, 0x02, 0x17, 0x00, 0x0a, 0x00, 0x00, 0x00,
+        ];
+        push(&mut d, Direction::Rx, &rsp).unwrap();
+        assert_eq!(d.channel_psm(0, 0x0040, Direction::Tx, 0x0041), Some(0x0080));
+        assert_eq!(d.channel_psm(0, 0x0040, Direction::Rx, 0x0040), Some(0x0080));
+
+        // SDU of 6 bytes sent in two K-frames.
+        let first = [0x02, 0x40, 0x00, 0x09, 0x00, 0x05, 0x00, 0x41, 0x00, 0x06, 0x00, 0x01, 0x02, 0x03];
+        assert_eq!(push(&mut d, Direction::Tx, &first), None);
+        let second = [0x02, 0x40, 0x00, 0x07, 0x00, 0x03, 0x00, 0x41, 0x00, 0x04, 0x05, 0x06];
+        let dissected = push(&mut d, Direction::Tx, &second).unwrap();
+        assert_eq!(dissected.cid, 0x0041);
+        assert_eq!(dissected.pdu, Pdu::Data { psm: Some(0x0080), data: vec![1, 2, 3, 4, 5, 6] });
+
+        // Disconnection Complete for handle 0x0040 discards the channels.
+        push(&mut d, Direction::Rx, &[0x04, 0x05, 0x04, 0x00, 0x40, 0x00, 0x13]);
+        assert_eq!(d.channel_psm(0, 0x0040, Direction::Tx, 0x0041), None);
+        let dissected = push(&mut d, Direction::Tx, &first).unwrap();
+        assert_eq!(dissected.pdu, Pdu::Data { psm: None, data: vec![0x06, 0x00, 0x01, 0x02, 0x03] });
+    }
+
+    #[test]
+    fn classic_channel() {
+        let mut d = Dissector::new();
+
+        // Connection Request for SDP and successful response.
+        let req = [
+            0x02, 0x0b, 0x20, 0x0c, 0x00, 0x08, 0x00, 0x01, 0x00, 0x02, 0x02, 0x04, 0x00, 0x01, 0x00, 0x40, 0x00,
+        ];
+        push(&mut d, Direction::Rx, &req).unwrap();
+        #[rustfmt::skip]
+        let rsp = [
+            0x02, 0x0b, 0x20, 0x10, 0x00, 0x0c, 0x00, 0x01, 0x00,
+            0x03, 0x02, 0x08, 0x00, 0x41, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let dissected = push(&mut d, Direction::Tx, &rsp).unwrap();
+        assert!(matches!(&dissected.pdu, Pdu::Signaling(cmds) if cmds.len() == 1));
+
+        let data = [0x02, 0x0b, 0x20, 0x06, 0x00, 0x02, 0x00, 0x41, 0x00, 0x06, 0x00];
+        let dissected = push(&mut d, Direction::Rx, &data).unwrap();
+        assert_eq!(dissected.pdu, Pdu::Data { psm: Some(0x0001), data: vec![0x06, 0x00] });
+
+        // Disconnection Request removes the channel.
+        #[rustfmt::skip]
+        let disc = [
+            0x02, 0x0b, 0x20, 0x0c, 0x00, 0x08, 0x00, 0x01, 0x00,
+            0x06, 0x03, 0x04, 0x00, 0x41, 0x00, 0x40, 0x00,
+        ];
+        push(&mut d, Direction::Rx, &disc).unwrap();
+        assert_eq!(d.channel_psm(0, 0x000b, Direction::Rx, 0x0041), None);
+        assert_eq!(d.channel_psm(0, 0x000b, Direction::Tx, 0x0040), None);
+    }
+}
@@ -0,0 +1,398 @@
+//! L2CAP signaling channel commands.
+
+use super::Reader;
+
+/// Signaling command.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Command {
+    /// Identifier matching requests and responses.
+    pub identifier: u8,
+    /// Command.
+    pub kind: CommandKind,
+}
+
+/// Signaling command kind.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CommandKind {
+    /// Command reject.
+    CommandReject {
+        /// Reason.
+        reason: u16,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Connection request.
+    ConnectionRequest {
+        /// Protocol/service multiplexer.
+        psm: u16,
+        /// Source channel id.
+        scid: u16,
+    },
+    /// Connection response.
+    ConnectionResponse {
+        /// Destination channel id.
+        dcid: u16,
+        /// Source channel id.
+        scid: u16,
+        /// Result.
+        result: u16,
+        /// Status.
+        status: u16,
+    },
+    /// Configuration request.
+    ConfigurationRequest {
+        /// Destination channel id.
+        dcid: u16,
+        /// Flags.
+        flags: u16,
+        /// Configuration options.
+        options: Vec<u8>,
+    },
+    /// Configuration response.
+    ConfigurationResponse {
+        /// Source channel id.
+        scid: u16,
+        /// Flags.
+        flags: u16,
+        /// Result.
+        result: u16,
+        /// Configuration options.
+        options: Vec<u8>,
+    },
+    /// Disconnection request.
+    DisconnectionRequest {
+        /// Destination channel id.
+        dcid: u16,
+        /// Source channel id.
+        scid: u16,
+    },
+    /// Disconnection response.
+    DisconnectionResponse {
+        /// Destination channel id.
+        dcid: u16,
+        /// Source channel id.
+        scid: u16,
+    },
+    /// Echo request.
+    EchoRequest(Vec<u8>),
+    /// Echo response.
+    EchoResponse(Vec<u8>),
+    /// Information request.
+    InformationRequest {
+        /// Information type.
+        info_type: u16,
+    },
+    /// Information response.
+    InformationResponse {
+        /// Information type.
+        info_type: u16,
+        /// Result.
+        result: u16,
+        /// Data.
+        data: Vec<u8>,
+    },
+    /// Connection parameter update request.
+    ConnectionParameterUpdateRequest {
+        /// Minimum connection interval in units of 1.25 ms.
+        min_interval: u16,
+        /// Maximum connection interval in units of 1.25 ms.
+        max_interval: u16,
+        /// Peripheral latency.
+        latency: u16,
+        /// Supervision timeout in units of 10 ms.
+        timeout: u16,
+    },
+    /// Connection parameter update response.
+    ConnectionParameterUpdateResponse {
+        /// Result.
+        result: u16,
+    },
+    /// LE credit-based connection request.
+    LeCreditBasedConnectionRequest {
+        /// Simplified protocol/service multiplexer.
+        spsm: u16,
+        /// Source channel id.
+        scid: u16,
+        /// Maximum transmission unit.
+        mtu: u16,
+        /// Maximum PDU payload size.
+        mps: u16,
+        /// Initial credits.
+        initial_credits: u16,
+    },
+    /// LE credit-based connection response.
+    LeCreditBasedConnectionResponse {
+        /// Destination channel id.
+        dcid: u16,
+        /// Maximum transmission unit.
+        mtu: u16,
+        /// Maximum PDU payload size.
+        mps: u16,
+        /// Initial credits.
+        initial_credits: u16,
+        /// Result.
+        result: u16,
+    },
+    /// Flow control credit indication.
+    FlowControlCredit {
+        /// Channel id.
+        cid: u16,
+        /// Credits.
+        credits: u16,
+    },
+    /// Enhanced credit-based connection request.
+    CreditBasedConnectionRequest {
+        /// Simplified protocol/service multiplexer.
+        spsm: u16,
+        /// Maximum transmission unit.
+        mtu: u16,
+        /// Maximum PDU payload size.
+        mps: u16,
+        /// Initial credits.
+        initial_credits: u16,
+        /// Source channel ids.
+        scids: Vec<u16>,
+    },
+    /// Enhanced credit-based connection response.
+    CreditBasedConnectionResponse {
+        /// Maximum transmission unit.
+        mtu: u16,
+        /// Maximum PDU payload size.
+        mps: u16,
+        /// Initial credits.
+        initial_credits: u16,
+        /// Result.
+        result: u16,
+        /// Destination channel ids.
+        dcids: Vec<u16>,
+    },
+    /// Enhanced credit-based reconfigure request.
+    CreditBasedReconfigureRequest {
+        /// Maximum transmission unit.
+        mtu: u16,
+        /// Maximum PDU payload size.
+        mps: u16,
+        /// Destination channel ids.
+        dcids: Vec<u16>,
+    },
+    /// Enhanced credit-based reconfigure response.
+    CreditBasedReconfigureResponse {
+        /// Result.
+        result: u16,
+    },
+    /// Other or malformed command.
+    Other {
+        /// Command code.
+        code: u8,
+        /// Data.
+        data: Vec<u8>,
+    },
+}
+
+impl Command {
+    /// Parses a single command, as used on the LE signaling channel.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let cmd = Self::parse_one(&mut r)?;
+        r.is_empty().then_some(cmd)
+    }
+
+    /// Parses all commands contained in a frame of the classic Bluetooth signaling channel.
+    pub fn parse_all(data: &[u8]) -> Option<Vec<Self>> {
+        let mut r = Reader::new(data);
+        let mut cmds = Vec::new();
+        while !r.is_empty() {
+            cmds.push(Self::parse_one(&mut r)?);
+        }
+        Some(cmds)
+    }
+
+    fn parse_one(r: &mut Reader) -> Option<Self> {
+        let code = r.u8()?;
+        let identifier = r.u8()?;
+        let len = r.u16()? as usize;
+        let data = r.bytes(len)?;
+        let kind =
+            CommandKind::parse(code, data).unwrap_or_else(|| CommandKind::Other { code, data: data.to_vec() });
+        Some(Self { identifier, kind })
+    }
+}
+
+impl CommandKind {
+    fn parse(code: u8, data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let cids = |r: &mut Reader| {
+            let rest = r.rest();
+            (rest.len() % 2 == 0).then(|| rest.chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect())
+        };
+        let kind = match code {
+            0x01 => Self::CommandReject { reason: r.u16()?, data: r.rest().to_vec() },
+            0x02 => Self::ConnectionRequest { psm: r.u16()?, scid: r.u16()? },
+            0x03 => {
+                Self::ConnectionResponse { dcid: r.u16()?, scid: r.u16()?, result: r.u16()?, status: r.u16()? }
+            }
+            0x04 => Self::ConfigurationRequest { dcid: r.u16()?, flags: r.u16()?, options: r.rest().to_vec() },
+            0x05 => Self::ConfigurationResponse {
+                scid: r.u16()?,
+                flags: r.u16()?,
+                result: r.u16()?,
+                options: r.rest().to_vec(),
+            },
+            0x06 => Self::DisconnectionRequest { dcid: r.u16()?, scid: r.u16()? },
+            0x07 => Self::DisconnectionResponse { dcid: r.u16()?, scid: r.u16()? },
+            0x08 => Self::EchoRequest(r.rest().to_vec()),
+            0x09 => Self::EchoResponse(r.rest().to_vec()),
+            0x0a => Self::InformationRequest { info_type: r.u16()? },
+            0x0b => Self::InformationResponse { info_type: r.u16()?, result: r.u16()?, data: r.rest().to_vec() },
+            0x12 => Self::ConnectionParameterUpdateRequest {
+                min_interval: r.u16()?,
+                max_interval: r.u16()?,
+                latency: r.u16()?,
+                timeout: r.u16()?,
+            },
+            0x13 => Self::ConnectionParameterUpdateResponse { result: r.u16()? },
+            0x14 => Self::LeCreditBasedConnectionRequest {
+                spsm: r.u16()?,
+                scid: r.u16()?,
+                mtu: r.u16()?,
+                mps: r.u16()?,
+                initial_credits: r.u16()?,
+            },
+            0x15 => Self::LeCreditBasedConnectionResponse {
+                dcid: r.u16()?,
+                mtu: r.u16()?,
+                mps: r.u16()?,
+                initial_credits: r.u16()?,
+                result: r.u16()?,
+            },
+            0x16 => Self::FlowControlCredit { cid: r.u16()?, credits: r.u16()? },
+            0x17 => Self::CreditBasedConnectionRequest {
+                spsm: r.u16()?,
+                mtu: r.u16()?,
+                mps: r.u16()?,
+                initial_credits: r.u16()?,
+                scids: cids(&mut r)?,
+            },
+            0x18 => Self::CreditBasedConnectionResponse {
+                mtu: r.u16()?,
+                mps: r.u16()?,
+                initial_credits: r.u16()?,
+                result: r.u16()?,
+                dcids: cids(&mut r)?,
+            },
+            0x19 => Self::CreditBasedReconfigureRequest { mtu: r.u16()?, mps: r.u16()?, dcids: cids(&mut r)? },
+            0x1a => Self::CreditBasedReconfigureResponse { result: r.u16()? },
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic() {
+        // Information request for the extended features mask and connection request for SDP.
+        let data = [0x0a, 0x01, 0x02, 0x00, 0x02, 0x00, 0x02, 0x02, 0x04, 0x00, 0x01, 0x00, 0x40, 0x00];
+        assert_eq!(
+            Command::parse_all(&data),
+            Some(vec![
+                Command { identifier: 1, kind: CommandKind::InformationRequest { info_type: 0x0002 } },
+                Command { identifier: 2, kind: CommandKind::ConnectionRequest { psm: 0x0001, scid: 0x0040 } },
+            ])
+        );
+        assert_eq!(Command::parse(&data), None);
+
+        let data = [0x03, 0x02, 0x08, 0x00, 0x41, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(
+            Command::parse(&data),
+            Some(Command {
+                identifier: 2,
+                kind: CommandKind::ConnectionResponse { dcid: 0x0041, scid: 0x0040, result: 0, status: 0 }
+            })
+        );
+
+        let data = [0x04, 0x03, 0x08, 0x00, 0x41, 0x00, 0x00, 0x00, 0x01, 0x02, 0xa0, 0x02];
+        assert_eq!(
+            Command::parse(&data),
+            Some(Command {
+                identifier: 3,
+                kind: CommandKind::ConfigurationRequest {
+                    dcid: 0x0041,
+                    flags: 0,
+                    options: vec![0x01, 0x02, 0xa0, 0x02]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn le() {
+        let data = [0x14, 0x01, 0x0a, 0x00, 0x80, 0x00, 0x40, 0x00, 0x00, 0x02, 0xf7, 0x00, 0x0a, 0x00];
+        assert_eq!(
+            Command::parse(&data),
+            Some(Command {
+                identifier: 1,
+                kind: CommandKind::LeCreditBasedConnectionRequest {
+                    spsm: 0x0080,
+                    scid: 0x0040,
+                    mtu: 512,
+                    mps: 247,
+                    initial_credits: 10,
+                }
+            })
+        );
+
+        let data = [0x12, 0x02, 0x08, 0x00, 0x18, 0x00, 0x28, 0x00, 0x00, 0x00, 0x2a, 0x00];
+        assert_eq!(
+            Command::parse(&data),
+            Some(Command {
+                identifier: 2,
+                kind: CommandKind::ConnectionParameterUpdateRequest {
+                    min_interval: 24,
+                    max_interval: 40,
+                    latency: 0,
+                    timeout: 42,
+                }
+            })
+        );
+
+        let data =
+            [0x17, 0x03, 0x0c, 0x00, 0x27, 0x00, 0x40, 0x00, 0x40, 0x00, 0x05, 0x00, 0x40, 0x00, 0x41, 0x00];
+        assert_eq!(
+            Command::parse(&data),
+            Some(Command {
+                identifier: 3,
+                kind: CommandKind::CreditBasedConnectionRequest {
+                    spsm: 0x0027,
+                    mtu: 64,
+                    mps: 64,
+                    initial_credits: 5,
+                    scids: vec![0x0040, 0x0041],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn other() {
+        // Unknown code and command with malformed parameters.
+        let data = [0x30, 0x01, 0x01, 0x00, 0xff, 0x02, 0x02, 0x01, 0x00, 0x01];
+        assert_eq!(
+            Command::parse_all(&data),
+            Some(vec![
+                Command { identifier: 1, kind: CommandKind::Other { code: 0x30, data: vec![0xff] } },
+                Command { identifier: 2, kind: CommandKind::Other { code: 0x02, data: vec![0x01] } },
+            ])
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(Command::parse_all(&[0x0a, 0x01, 0x02, 0x00, 0x02]), None);
+        assert_eq!(Command::parse(&[0x0a, 0x01, 0x02]), None);
+    }
+}
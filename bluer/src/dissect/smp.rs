@@ -0,0 +1,187 @@
+//! Security Manager Protocol (SMP) PDUs.
+
+use super::Reader;
+use crate::{Address, AddressType};
+
+/// Pairing features exchanged in pairing requests and responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PairingFeatures {
+    /// IO capability.
+    pub io_capability: u8,
+    /// Whether out-of-band authentication data is present.
+    pub oob_data: bool,
+    /// Authentication requirements.
+    pub auth_req: u8,
+    /// Maximum encryption key size.
+    pub max_key_size: u8,
+    /// Keys distributed by the initiator.
+    pub initiator_key_distribution: u8,
+    /// Keys distributed by the responder.
+    pub responder_key_distribution: u8,
+}
+
+impl PairingFeatures {
+    fn parse(r: &mut Reader) -> Option<Self> {
+        Some(Self {
+            io_capability: r.u8()?,
+            oob_data: r.u8()? != 0,
+            auth_req: r.u8()?,
+            max_key_size: r.u8()?,
+            initiator_key_distribution: r.u8()?,
+            responder_key_distribution: r.u8()?,
+        })
+    }
+}
+
+/// Security Manager Protocol PDU.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Pdu {
+    /// Pairing request.
+    PairingRequest(PairingFeatures),
+    /// Pairing response.
+    PairingResponse(PairingFeatures),
+    /// Pairing confirm value.
+    PairingConfirm([u8; 16]),
+    /// Pairing random value.
+    PairingRandom([u8; 16]),
+    /// Pairing failed with reason.
+    PairingFailed(u8),
+    /// Long term key.
+    EncryptionInformation([u8; 16]),
+    /// Central identification.
+    CentralIdentification {
+        /// Encrypted diversifier.
+        ediv: u16,
+        /// Random number.
+        rand: u64,
+    },
+    /// Identity resolving key.
+    IdentityInformation([u8; 16]),
+    /// Identity address.
+    IdentityAddressInformation {
+        /// Address.
+        address: Address,
+        /// Address type.
+        address_type: AddressType,
+    },
+    /// Connection signature resolving key.
+    SigningInformation([u8; 16]),
+    /// Security request with authentication requirements.
+    SecurityRequest(u8),
+    /// Public key for LE Secure Connections.
+    PairingPublicKey {
+        /// X coordinate.
+        x: [u8; 32],
+        /// Y coordinate.
+        y: [u8; 32],
+    },
+    /// DHKey check value.
+    PairingDhKeyCheck([u8; 16]),
+    /// Keypress notification.
+    KeypressNotification(u8),
+    /// Other PDU.
+    Other {
+        /// Code.
+        code: u8,
+        /// Data.
+        data: Vec<u8>,
+    },
+}
+
+impl Pdu {
+    /// Parses an SMP PDU.
+    ///
+    /// Returns `None` if the PDU is malformed.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(data);
+        let code = r.u8()?;
+        let pdu = match code {
+            0x01 => Self::PairingRequest(PairingFeatures::parse(&mut r)?),
+            0x02 => Self::PairingResponse(PairingFeatures::parse(&mut r)?),
+            0x03 => Self::PairingConfirm(r.array()?),
+            0x04 => Self::PairingRandom(r.array()?),
+            0x05 => Self::PairingFailed(r.u8()?),
+            0x06 => Self::EncryptionInformation(r.array()?),
+            0x07 => Self::CentralIdentification { ediv: r.u16()?, rand: r.u64()? },
+            0x08 => Self::IdentityInformation(r.array()?),
+            0x09 => {
+                let address_type = match r.u8()? {
+                    0 => AddressType::LePublic,
+                    1 => AddressType::LeRandom,
+                    _ => return None,
+                };
+                let mut addr: [u8; 6] = r.array()?;
+                addr.reverse();
+                Self::IdentityAddressInformation { address: Address(addr), address_type }
+            }
+            0x0a => Self::SigningInformation(r.array()?),
+            0x0b => Self::SecurityRequest(r.u8()?),
+            0x0c => Self::PairingPublicKey { x: r.array()?, y: r.array()? },
+            0x0d => Self::PairingDhKeyCheck(r.array()?),
+            0x0e => Self::KeypressNotification(r.u8()?),
+            _ => return Some(Self::Other { code, data: r.rest().to_vec() }),
+        };
+        r.is_empty().then_some(pdu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairing() {
+        assert_eq!(
+            Pdu::parse(&[0x01, 0x03, 0x00, 0x2d, 0x10, 0x0f, 0x0f]),
+            Some(Pdu::PairingRequest(PairingFeatures {
+                io_capability: 0x03,
+                oob_data: false,
+                auth_req: 0x2d,
+                max_key_size: 16,
+                initiator_key_distribution: 0x0f,
+                responder_key_distribution: 0x0f,
+            }))
+        );
+        assert_eq!(Pdu::parse(&[0x05, 0x08]), Some(Pdu::PairingFailed(0x08)));
+        assert_eq!(Pdu::parse(&[0x0b, 0x0d]), Some(Pdu::SecurityRequest(0x0d)));
+
+        let mut data = vec![0x03];
+        data.extend(0..16);
+        assert_eq!(Pdu::parse(&data), Some(Pdu::PairingConfirm(data[1..].try_into().unwrap())));
+
+        let mut data = vec![0x0c];
+        data.extend(0..64);
+        assert_eq!(
+            Pdu::parse(&data),
+            Some(Pdu::PairingPublicKey { x: data[1..33].try_into().unwrap(), y: data[33..].try_into().unwrap() })
+        );
+    }
+
+    #[test]
+    fn key_distribution() {
+        let data = [0x07, 0x34, 0x12, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01];
+        assert_eq!(
+            Pdu::parse(&data),
+            Some(Pdu::CentralIdentification { ediv: 0x1234, rand: 0x0102030405060708 })
+        );
+
+        let data = [0x09, 0x01, 0x13, 0x71, 0xda, 0x7d, 0x1a, 0xc0];
+        assert_eq!(
+            Pdu::parse(&data),
+            Some(Pdu::IdentityAddressInformation {
+                address: Address::new([0xc0, 0x1a, 0x7d, 0xda, 0x71, 0x13]),
+                address_type: AddressType::LeRandom,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(Pdu::parse(&[]), None);
+        assert_eq!(Pdu::parse(&[0x01, 0x03, 0x00, 0x2d, 0x10, 0x0f]), None);
+        assert_eq!(Pdu::parse(&[0x05, 0x08, 0x00]), None);
+        assert_eq!(Pdu::parse(&[0x09, 0x02, 0x13, 0x71, 0xda, 0x7d, 0x1a, 0xc0]), None);
+        assert_eq!(Pdu::parse(&[0x40, 0x01]), Some(Pdu::Other { code: 0x40, data: vec![0x01] }));
+    }
+}
@@ -0,0 +1,714 @@
+//! Snapshots of the GATT database of remote devices.
+//!
+//! A [Database] is obtained using [Device::gatt_database](crate::Device::gatt_database)
+//! and can be serialized when the `serde` feature is enabled.
+//! Two snapshots, for example taken before and after a firmware update, are compared
+//! using [Database::diff].
+
+use std::{fmt, iter};
+use uuid::Uuid;
+
+use super::CharacteristicFlags;
+use crate::{Device, Result};
+
+/// Snapshot of the GATT database of a remote device.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Database {
+    /// Services ordered by handle.
+    pub services: Vec<Service>,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+/// Snapshot of a GATT service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Service {
+    /// Service handle.
+    ///
+    /// This is the [service id](super::remote::Service::id), which the Bluetooth daemon
+    /// derives from the handle of the service declaration.
+    pub handle: u16,
+    /// 128-bit service UUID.
+    pub uuid: Uuid,
+    /// Assigned name of the service, if known.
+    ///
+    /// Only available when the `id` feature is enabled.
+    pub name: Option<String>,
+    /// Whether this is a primary service.
+    pub primary: bool,
+    /// Handles of included services.
+    pub includes: Vec<u16>,
+    /// Characteristics ordered by handle.
+    pub characteristics: Vec<Characteristic>,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+/// Snapshot of a GATT characteristic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Characteristic {
+    /// Characteristic handle.
+    ///
+    /// This is the [characteristic id](super::remote::Characteristic::id), which the Bluetooth
+    /// daemon derives from the handle of the characteristic declaration.
+    pub handle: u16,
+    /// 128-bit characteristic UUID.
+    pub uuid: Uuid,
+    /// Assigned name of the characteristic, if known.
+    ///
+    /// Only available when the `id` feature is enabled.
+    pub name: Option<String>,
+    /// Characteristic flags.
+    pub flags: CharacteristicFlags,
+    /// Value, if it was read.
+    pub value: Option<Vec<u8>>,
+    /// Descriptors ordered by handle.
+    pub descriptors: Vec<Descriptor>,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+/// Snapshot of a GATT characteristic descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Descriptor {
+    /// Descriptor handle.
+    ///
+    /// This is the [descriptor id](super::remote::Descriptor::id), which the Bluetooth
+    /// daemon derives from the handle of the descriptor.
+    pub handle: u16,
+    /// 128-bit descriptor UUID.
+    pub uuid: Uuid,
+    /// Assigned name of the descriptor, if known.
+    ///
+    /// Only available when the `id` feature is enabled.
+    pub name: Option<String>,
+    /// Value, if it was read.
+    pub value: Option<Vec<u8>>,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl Database {
+    pub(crate) async fn from_device(device: &Device, read_values: bool) -> Result<Self> {
+        let mut services = Vec::new();
+        for service in device.services().await? {
+            let uuid = service.uuid().await?;
+
+            let mut characteristics = Vec::new();
+            for characteristic in service.characteristics().await? {
+                let uuid = characteristic.uuid().await?;
+                let flags = characteristic.flags().await?;
+                let value = match read_values && flags.read {
+                    true => characteristic.read().await.ok(),
+                    false => None,
+                };
+
+                let mut descriptors = Vec::new();
+                for descriptor in characteristic.descriptors().await? {
+                    let uuid = descriptor.uuid().await?;
+                    let value = match read_values {
+                        true => descriptor.read().await.ok(),
+                        false => None,
+                    };
+                    descriptors.push(Descriptor {
+                        handle: descriptor.id(),
+                        uuid,
+                        name: descriptor_name(uuid),
+                        value,
+                        _non_exhaustive: (),
+                    });
+                }
+                descriptors.sort_by_key(|d| d.handle);
+
+                characteristics.push(Characteristic {
+                    handle: characteristic.id(),
+                    uuid,
+                    name: characteristic_name(uuid),
+                    flags,
+                    value,
+                    descriptors,
+                    _non_exhaustive: (),
+                });
+            }
+            characteristics.sort_by_key(|c| c.handle);
+
+            services.push(Service {
+                handle: service.id(),
+                uuid,
+                name: service_name(uuid),
+                primary: service.primary().await?,
+                includes: service.includes().await?,
+                characteristics,
+                _non_exhaustive: (),
+            });
+        }
+        services.sort_by_key(|s| s.handle);
+
+        Ok(Self { services, _non_exhaustive: () })
+    }
+
+    /// Changes from this snapshot to the `new` snapshot.
+    ///
+    /// Attributes are matched by their UUIDs and, if a UUID occurs multiple times within
+    /// the same parent, by their order.
+    /// Thus a moved attribute is reported as a [handle change](Change::HandleChanged),
+    /// not as removed and added.
+    /// Included services are compared by their UUIDs and values are only compared
+    /// if they were read in both snapshots.
+    pub fn diff(&self, new: &Database) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for (old_svc, new_svc) in pair(&self.services, &new.services, |s| s.uuid) {
+            let path = AttributePath { service: old_svc.or(new_svc).unwrap().uuid, ..Default::default() };
+            let (old_svc, new_svc) = match (old_svc, new_svc) {
+                (Some(old_svc), Some(new_svc)) => (old_svc, new_svc),
+                (Some(old_svc), None) => {
+                    changes.push(Change::Removed { path, handle: old_svc.handle });
+                    continue;
+                }
+                (None, Some(new_svc)) => {
+                    changes.push(Change::Added { path, handle: new_svc.handle });
+                    continue;
+                }
+                (None, None) => unreachable!(),
+            };
+
+            if old_svc.handle != new_svc.handle {
+                changes.push(Change::HandleChanged {
+                    path: path.clone(),
+                    old: old_svc.handle,
+                    new: new_svc.handle,
+                });
+            }
+            if old_svc.primary != new_svc.primary {
+                changes.push(Change::PrimaryChanged {
+                    path: path.clone(),
+                    old: old_svc.primary,
+                    new: new_svc.primary,
+                });
+            }
+            let old_includes = self.include_uuids(old_svc);
+            let new_includes = new.include_uuids(new_svc);
+            if old_includes != new_includes {
+                changes.push(Change::IncludesChanged {
+                    path: path.clone(),
+                    old: old_includes,
+                    new: new_includes,
+                });
+            }
+
+            for (old_char, new_char) in pair(&old_svc.characteristics, &new_svc.characteristics, |c| c.uuid) {
+                let path =
+                    AttributePath { characteristic: Some(old_char.or(new_char).unwrap().uuid), ..path.clone() };
+                let (old_char, new_char) = match (old_char, new_char) {
+                    (Some(old_char), Some(new_char)) => (old_char, new_char),
+                    (Some(old_char), None) => {
+                        changes.push(Change::Removed { path, handle: old_char.handle });
+                        continue;
+                    }
+                    (None, Some(new_char)) => {
+                        changes.push(Change::Added { path, handle: new_char.handle });
+                        continue;
+                    }
+                    (None, None) => unreachable!(),
+                };
+
+                if old_char.handle != new_char.handle {
+                    changes.push(Change::HandleChanged {
+                        path: path.clone(),
+                        old: old_char.handle,
+                        new: new_char.handle,
+                    });
+                }
+                if old_char.flags != new_char.flags {
+                    changes.push(Change::FlagsChanged {
+                        path: path.clone(),
+                        old: old_char.flags,
+                        new: new_char.flags,
+                    });
+                }
+                if let (Some(old_value), Some(new_value)) = (&old_char.value, &new_char.value) {
+                    if old_value != new_value {
+                        changes.push(Change::ValueChanged {
+                            path: path.clone(),
+                            old: old_value.clone(),
+                            new: new_value.clone(),
+                        });
+                    }
+                }
+
+                for (old_desc, new_desc) in pair(&old_char.descriptors, &new_char.descriptors, |d| d.uuid) {
+                    let path =
+                        AttributePath { descriptor: Some(old_desc.or(new_desc).unwrap().uuid), ..path.clone() };
+                    match (old_desc, new_desc) {
+                        (Some(old_desc), Some(new_desc)) => {
+                            if old_desc.handle != new_desc.handle {
+                                changes.push(Change::HandleChanged {
+                                    path: path.clone(),
+                                    old: old_desc.handle,
+                                    new: new_desc.handle,
+                                });
+                            }
+                            if let (Some(old_value), Some(new_value)) = (&old_desc.value, &new_desc.value) {
+                                if old_value != new_value {
+                                    changes.push(Change::ValueChanged {
+                                        path,
+                                        old: old_value.clone(),
+                                        new: new_value.clone(),
+                                    });
+                                }
+                            }
+                        }
+                        (Some(old_desc), None) => changes.push(Change::Removed { path, handle: old_desc.handle }),
+                        (None, Some(new_desc)) => changes.push(Change::Added { path, handle: new_desc.handle }),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// UUIDs of the services included by the specified service.
+    fn include_uuids(&self, service: &Service) -> Vec<Uuid> {
+        service
+            .includes
+            .iter()
+            .filter_map(|handle| self.services.iter().find(|s| s.handle == *handle))
+            .map(|s| s.uuid)
+            .collect()
+    }
+}
+
+/// Pairs the items of two lists by UUID, keeping their order for duplicate UUIDs.
+fn pair<'a, T>(old: &'a [T], new: &'a [T], uuid: impl Fn(&T) -> Uuid) -> Vec<(Option<&'a T>, Option<&'a T>)> {
+    let mut used = vec![false; new.len()];
+    let mut pairs = Vec::new();
+    for o in old {
+        match new.iter().enumerate().find(|(i, n)| !used[*i] && uuid(n) == uuid(o)) {
+            Some((i, n)) => {
+                used[i] = true;
+                pairs.push((Some(o), Some(n)));
+            }
+            None => pairs.push((Some(o), None)),
+        }
+    }
+    pairs.extend(new.iter().zip(used).filter(|(_, used)| !used).map(|(n, _)| (None, Some(n))));
+    pairs
+}
+
+#[cfg(feature = "id")]
+fn service_name(uuid: Uuid) -> Option<String> {
+    crate::id::Service::try_from(uuid).ok().map(|id| id.to_string())
+}
+
+#[cfg(feature = "id")]
+fn characteristic_name(uuid: Uuid) -> Option<String> {
+    crate::id::Characteristic::try_from(uuid).ok().map(|id| id.to_string())
+}
+
+#[cfg(feature = "id")]
+fn descriptor_name(uuid: Uuid) -> Option<String> {
+    crate::id::Descriptor::try_from(uuid).ok().map(|id| id.to_string())
+}
+
+#[cfg(not(feature = "id"))]
+fn service_name(_uuid: Uuid) -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "id"))]
+fn characteristic_name(_uuid: Uuid) -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "id"))]
+fn descriptor_name(_uuid: Uuid) -> Option<String> {
+    None
+}
+
+/// Location of an attribute within a GATT database.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributePath {
+    /// Service UUID.
+    pub service: Uuid,
+    /// Characteristic UUID, if the attribute is a characteristic or descriptor.
+    pub characteristic: Option<Uuid>,
+    /// Descriptor UUID, if the attribute is a descriptor.
+    pub descriptor: Option<Uuid>,
+}
+
+impl fmt::Display for AttributePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let uuids = iter::once(self.service).chain(self.characteristic).chain(self.descriptor);
+        for (n, uuid) in uuids.enumerate() {
+            if n > 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{uuid}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Change between two GATT database snapshots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Change {
+    /// Attribute was added.
+    Added {
+        /// Attribute.
+        path: AttributePath,
+        /// Handle of the added attribute.
+        handle: u16,
+    },
+    /// Attribute was removed.
+    Removed {
+        /// Attribute.
+        path: AttributePath,
+        /// Handle of the removed attribute.
+        handle: u16,
+    },
+    /// Handle of attribute changed.
+    HandleChanged {
+        /// Attribute.
+        path: AttributePath,
+        /// Old handle.
+        old: u16,
+        /// New handle.
+        new: u16,
+    },
+    /// Service changed between primary and secondary.
+    PrimaryChanged {
+        /// Service.
+        path: AttributePath,
+        /// Old primary flag.
+        old: bool,
+        /// New primary flag.
+        new: bool,
+    },
+    /// UUIDs of included services changed.
+    IncludesChanged {
+        /// Service.
+        path: AttributePath,
+        /// Old included service UUIDs.
+        old: Vec<Uuid>,
+        /// New included service UUIDs.
+        new: Vec<Uuid>,
+    },
+    /// Characteristic flags changed.
+    FlagsChanged {
+        /// Characteristic.
+        path: AttributePath,
+        /// Old flags.
+        old: CharacteristicFlags,
+        /// New flags.
+        new: CharacteristicFlags,
+    },
+    /// Value of characteristic or descriptor changed.
+    ValueChanged {
+        /// Characteristic or descriptor.
+        path: AttributePath,
+        /// Old value.
+        old: Vec<u8>,
+        /// New value.
+        new: Vec<u8>,
+    },
+}
+
+impl Change {
+    /// Attribute affected by the change.
+    pub fn path(&self) -> &AttributePath {
+        match self {
+            Self::Added { path, .. }
+            | Self::Removed { path, .. }
+            | Self::HandleChanged { path, .. }
+            | Self::PrimaryChanged { path, .. }
+            | Self::IncludesChanged { path, .. }
+            | Self::FlagsChanged { path, .. }
+            | Self::ValueChanged { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Added { path, handle } => write!(f, "added {path} at handle 0x{handle:04x}"),
+            Self::Removed { path, handle } => write!(f, "removed {path} at handle 0x{handle:04x}"),
+            Self::HandleChanged { path, old, new } => {
+                write!(f, "{path} moved from handle 0x{old:04x} to 0x{new:04x}")
+            }
+            Self::PrimaryChanged { path, new, .. } => {
+                write!(f, "{path} became {}", if *new { "primary" } else { "secondary" })
+            }
+            Self::IncludesChanged { path, old, new } => {
+                write!(f, "{path} includes changed from {old:?} to {new:?}")
+            }
+            Self::FlagsChanged { path, old, new } => {
+                write!(f, "{path} flags changed from {:?} to {:?}", old.as_vec(), new.as_vec())
+            }
+            Self::ValueChanged { path, old, new } => {
+                write!(f, "{path} value changed from {old:x?} to {new:x?}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UuidExt;
+
+    fn service(handle: u16, uuid: u16, characteristics: Vec<Characteristic>) -> Service {
+        Service {
+            handle,
+            uuid: Uuid::from_u16(uuid),
+            name: None,
+            primary: true,
+            includes: Vec::new(),
+            characteristics,
+            _non_exhaustive: (),
+        }
+    }
+
+    fn characteristic(
+        handle: u16, uuid: u16, value: Option<&[u8]>, descriptors: Vec<Descriptor>,
+    ) -> Characteristic {
+        Characteristic {
+            handle,
+            uuid: Uuid::from_u16(uuid),
+            name: None,
+            flags: CharacteristicFlags { read: true, ..Default::default() },
+            value: value.map(|v| v.to_vec()),
+            descriptors,
+            _non_exhaustive: (),
+        }
+    }
+
+    fn descriptor(handle: u16, uuid: u16, value: Option<&[u8]>) -> Descriptor {
+        Descriptor {
+            handle,
+            uuid: Uuid::from_u16(uuid),
+            name: None,
+            value: value.map(|v| v.to_vec()),
+            _non_exhaustive: (),
+        }
+    }
+
+    fn database(services: Vec<Service>) -> Database {
+        Database { services, _non_exhaustive: () }
+    }
+
+    fn path(service: u16, characteristic: Option<u16>, descriptor: Option<u16>) -> AttributePath {
+        AttributePath {
+            service: Uuid::from_u16(service),
+            characteristic: characteristic.map(Uuid::from_u16),
+            descriptor: descriptor.map(Uuid::from_u16),
+        }
+    }
+
+    /// Battery service and a device information service with two identical characteristics.
+    fn old() -> Database {
+        database(vec![
+            service(
+                0x0001,
+                0x180f,
+                vec![characteristic(
+                    0x0002,
+                    0x2a19,
+                    Some(&[0x64]),
+                    vec![descriptor(0x0004, 0x2902, Some(&[0, 0]))],
+                )],
+            ),
+            service(
+                0x0010,
+                0x180a,
+                vec![
+                    characteristic(0x0011, 0x2a26, Some(b"1.0"), Vec::new()),
+                    characteristic(0x0013, 0x2a26, Some(b"1.1"), Vec::new()),
+                ],
+            ),
+        ])
+    }
+
+    #[test]
+    fn unchanged() {
+        assert!(old().diff(&old()).is_empty());
+        assert!(Database::default().diff(&Database::default()).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let mut new = old();
+        new.services[0].characteristics[0].descriptors.clear();
+        new.services[1].characteristics.pop();
+        new.services.push(service(0x0020, 0x1805, Vec::new()));
+        new.services[0].characteristics.push(characteristic(0x0006, 0x2a1a, None, Vec::new()));
+
+        assert_eq!(
+            old().diff(&new),
+            [
+                Change::Removed { path: path(0x180f, Some(0x2a19), Some(0x2902)), handle: 0x0004 },
+                Change::Added { path: path(0x180f, Some(0x2a1a), None), handle: 0x0006 },
+                Change::Removed { path: path(0x180a, Some(0x2a26), None), handle: 0x0013 },
+                Change::Added { path: path(0x1805, None, None), handle: 0x0020 },
+            ]
+        );
+        assert_eq!(
+            new.diff(&old()),
+            [
+                Change::Added { path: path(0x180f, Some(0x2a19), Some(0x2902)), handle: 0x0004 },
+                Change::Removed { path: path(0x180f, Some(0x2a1a), None), handle: 0x0006 },
+                Change::Added { path: path(0x180a, Some(0x2a26), None), handle: 0x0013 },
+                Change::Removed { path: path(0x1805, None, None), handle: 0x0020 },
+            ]
+        );
+    }
+
+    #[test]
+    fn moved() {
+        // Moving the device information service in front of the battery service.
+        let mut new = old();
+        new.services.swap(0, 1);
+        new.services[0].handle = 0x0001;
+        new.services[0].characteristics[0].handle = 0x0002;
+        new.services[0].characteristics[1].handle = 0x0004;
+        new.services[1].handle = 0x0010;
+        new.services[1].characteristics[0].handle = 0x0011;
+        new.services[1].characteristics[0].descriptors[0].handle = 0x0013;
+
+        assert_eq!(
+            old().diff(&new),
+            [
+                Change::HandleChanged { path: path(0x180f, None, None), old: 0x0001, new: 0x0010 },
+                Change::HandleChanged { path: path(0x180f, Some(0x2a19), None), old: 0x0002, new: 0x0011 },
+                Change::HandleChanged {
+                    path: path(0x180f, Some(0x2a19), Some(0x2902)),
+                    old: 0x0004,
+                    new: 0x0013
+                },
+                Change::HandleChanged { path: path(0x180a, None, None), old: 0x0010, new: 0x0001 },
+                Change::HandleChanged { path: path(0x180a, Some(0x2a26), None), old: 0x0011, new: 0x0002 },
+                Change::HandleChanged { path: path(0x180a, Some(0x2a26), None), old: 0x0013, new: 0x0004 },
+            ]
+        );
+        assert_eq!(
+            old().diff(&new)[0].to_string(),
+            "0000180f-0000-1000-8000-00805f9b34fb moved from handle 0x0001 to 0x0010"
+        );
+    }
+
+    #[test]
+    fn duplicate_uuids() {
+        // Attributes with the same UUID are paired in order.
+        let mut new = old();
+        new.services[1].characteristics.insert(0, characteristic(0x0011, 0x2a26, Some(b"0.9"), Vec::new()));
+        new.services[1].characteristics[1].handle = 0x0013;
+        new.services[1].characteristics[2].handle = 0x0015;
+
+        let old_values: Vec<_> = old().services[1].characteristics.iter().map(|c| c.value.clone()).collect();
+        let new_values: Vec<_> = new.services[1].characteristics.iter().map(|c| c.value.clone()).collect();
+        let pairs = pair(&old_values, &new_values, |_| Uuid::nil());
+        assert_eq!(
+            pairs,
+            [
+                (Some(&old_values[0]), Some(&new_values[0])),
+                (Some(&old_values[1]), Some(&new_values[1])),
+                (None, Some(&new_values[2])),
+            ]
+        );
+
+        let path = path(0x180a, Some(0x2a26), None);
+        assert_eq!(
+            old().diff(&new),
+            [
+                Change::ValueChanged { path: path.clone(), old: b"1.0".to_vec(), new: b"0.9".to_vec() },
+                Change::ValueChanged { path: path.clone(), old: b"1.1".to_vec(), new: b"1.0".to_vec() },
+                Change::Added { path, handle: 0x0015 },
+            ]
+        );
+    }
+
+    #[test]
+    fn pair_by_uuid() {
+        let old = [1u16, 2, 1, 3];
+        let new = [3u16, 1, 4, 1, 1];
+        let pairs = pair(&old, &new, |&n| Uuid::from_u16(n));
+        let pairs: Vec<_> = pairs.into_iter().map(|(o, n)| (o.copied(), n.copied())).collect();
+        assert_eq!(
+            pairs,
+            [
+                (Some(1), Some(1)),
+                (Some(2), None),
+                (Some(1), Some(1)),
+                (Some(3), Some(3)),
+                (None, Some(4)),
+                (None, Some(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn values() {
+        let mut new = old();
+        new.services[0].characteristics[0].value = Some(vec![0x32]);
+        new.services[0].characteristics[0].descriptors[0].value = Some(vec![1, 0]);
+        // Values that were not read in both snapshots are not compared.
+        new.services[1].characteristics[0].value = None;
+
+        let changes = old().diff(&new);
+        assert_eq!(
+            changes,
+            [
+                Change::ValueChanged { path: path(0x180f, Some(0x2a19), None), old: vec![0x64], new: vec![0x32] },
+                Change::ValueChanged {
+                    path: path(0x180f, Some(0x2a19), Some(0x2902)),
+                    old: vec![0, 0],
+                    new: vec![1, 0]
+                },
+            ]
+        );
+        assert_eq!(changes[0].path(), &path(0x180f, Some(0x2a19), None));
+    }
+
+    #[test]
+    fn service_properties() {
+        let mut old = old();
+        old.services[0].includes.push(0x0010);
+        let mut new = old.clone();
+        new.services[1].primary = false;
+        new.services[0].includes.clear();
+        new.services[0].characteristics[0].flags.notify = true;
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            [
+                Change::IncludesChanged {
+                    path: path(0x180f, None, None),
+                    old: vec![Uuid::from_u16(0x180a)],
+                    new: Vec::new()
+                },
+                Change::FlagsChanged {
+                    path: path(0x180f, Some(0x2a19), None),
+                    old: CharacteristicFlags { read: true, ..Default::default() },
+                    new: CharacteristicFlags { read: true, notify: true, ..Default::default() },
+                },
+                Change::PrimaryChanged { path: path(0x180a, None, None), old: true, new: false },
+            ]
+        );
+        assert_eq!(changes[2].to_string(), "0000180a-0000-1000-8000-00805f9b34fb became secondary");
+    }
+}
@@ -0,0 +1,650 @@
+//! Emulation of peripherals from declarative GATT database descriptions.
+//!
+//! A [Peripheral] describes the services, characteristics and descriptors of a device
+//! together with their flags and values.
+//! It has the same shape as a [GATT database snapshot](super::database::Database),
+//! thus, when the `serde` feature is enabled, a serialized snapshot of a real device
+//! can be loaded as a [Peripheral] directly.
+//! Fields not needed for emulation, such as names, are ignored and optional fields may be omitted.
+//!
+//! [Peripheral::application] builds a [local GATT application](Application) that can be served
+//! using [Adapter::serve_gatt_application](crate::Adapter::serve_gatt_application) or,
+//! with the `att` feature, by [att::Server](crate::att::Server).
+//!
+//! Characteristic values are either static or follow a [Script].
+//! Writes permitted by the characteristic flags replace the current value,
+//! and clients that have enabled notifications or indications are informed about each change.
+
+use futures::{future, FutureExt};
+use std::{
+    num::NonZeroU16,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    select,
+    sync::watch,
+    time::{sleep_until, Instant},
+};
+use uuid::Uuid;
+
+use super::{
+    database::{self, Database},
+    local::{
+        self, Application, CharacteristicNotifier, CharacteristicNotify, CharacteristicNotifyMethod,
+        CharacteristicRead, CharacteristicWrite, CharacteristicWriteMethod, DescriptorRead, DescriptorWrite,
+        ReqError, ReqResult,
+    },
+    CharacteristicFlags,
+};
+use crate::UuidExt;
+
+/// UUID of the client characteristic configuration descriptor.
+const CLIENT_CHARACTERISTIC_CONFIGURATION: u16 = 0x2902;
+
+/// UUID of the characteristic extended properties descriptor.
+const CHARACTERISTIC_EXTENDED_PROPERTIES: u16 = 0x2900;
+
+/// UUID of the characteristic user description descriptor.
+const CHARACTERISTIC_USER_DESCRIPTION: u16 = 0x2901;
+
+/// Description of an emulated peripheral.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Peripheral {
+    /// Services.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub services: Vec<Service>,
+}
+
+/// Description of an emulated GATT service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Service {
+    /// Handle of the service declaration.
+    ///
+    /// Set to [None] to auto allocate an available handle.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub handle: Option<u16>,
+    /// 128-bit service UUID.
+    pub uuid: Uuid,
+    /// Whether this is a primary service.
+    #[cfg_attr(feature = "serde", serde(default = "default_primary"))]
+    pub primary: bool,
+    /// Characteristics.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub characteristics: Vec<Characteristic>,
+}
+
+#[cfg(feature = "serde")]
+fn default_primary() -> bool {
+    true
+}
+
+/// Description of an emulated GATT characteristic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Characteristic {
+    /// Handle of the characteristic declaration.
+    ///
+    /// The characteristic value is placed at the following handle.
+    /// Set to [None] to auto allocate an available handle.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub handle: Option<u16>,
+    /// 128-bit characteristic UUID.
+    pub uuid: Uuid,
+    /// Characteristic flags.
+    ///
+    /// These determine whether the characteristic can be read, written, notified and indicated.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flags: CharacteristicFlags,
+    /// Static value.
+    ///
+    /// An empty value is used when neither this nor [script](Self::script) is specified.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub value: Option<Vec<u8>>,
+    /// Scripted value, taking precedence over the [static value](Self::value).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub script: Option<Script>,
+    /// Descriptors.
+    ///
+    /// Client characteristic configuration and characteristic extended properties descriptors
+    /// are skipped, since they are provided by the GATT server according to the flags.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub descriptors: Vec<Descriptor>,
+}
+
+/// Description of an emulated GATT characteristic descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Descriptor {
+    /// Descriptor handle.
+    ///
+    /// Set to [None] to auto allocate an available handle.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub handle: Option<u16>,
+    /// 128-bit descriptor UUID.
+    pub uuid: Uuid,
+    /// Value.
+    ///
+    /// The descriptor is readable if a value is specified.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub value: Option<Vec<u8>>,
+    /// Whether the descriptor can be written, replacing its value.
+    ///
+    /// The characteristic user description is also writable if the characteristic
+    /// has the [writable auxiliaries](CharacteristicFlags::writable_auxiliaries) flag.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub writable: bool,
+}
+
+/// Sequence of values a characteristic takes over time.
+///
+/// Time is measured from the call to [Peripheral::application].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Script {
+    /// Values in order.
+    pub values: Vec<Vec<u8>>,
+    /// Time in milliseconds each value is held.
+    ///
+    /// If zero, the first value is held forever.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interval_ms: u64,
+    /// Whether to start over after the last value.
+    ///
+    /// Otherwise the last value is held forever.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub repeat: bool,
+}
+
+impl Peripheral {
+    /// Builds a local GATT application emulating the peripheral.
+    ///
+    /// Handles are requested as specified.
+    /// Included services are not supported by local GATT applications and thus not emulated.
+    pub fn application(&self) -> Application {
+        let start = Instant::now();
+        Application {
+            services: self
+                .services
+                .iter()
+                .map(|s| local::Service {
+                    uuid: s.uuid,
+                    handle: s.handle.and_then(NonZeroU16::new),
+                    primary: s.primary,
+                    characteristics: s.characteristics.iter().map(|c| c.local(start)).collect(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Database> for Peripheral {
+    /// Describes the peripheral the snapshot was taken from, using the read values as static values.
+    fn from(db: Database) -> Self {
+        Self {
+            services: db
+                .services
+                .into_iter()
+                .map(|s| Service {
+                    handle: Some(s.handle),
+                    uuid: s.uuid,
+                    primary: s.primary,
+                    characteristics: s.characteristics.into_iter().map(Characteristic::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<database::Characteristic> for Characteristic {
+    fn from(c: database::Characteristic) -> Self {
+        Self {
+            handle: Some(c.handle),
+            uuid: c.uuid,
+            flags: c.flags,
+            value: c.value,
+            script: None,
+            descriptors: c
+                .descriptors
+                .into_iter()
+                .map(|d| Descriptor { handle: Some(d.handle), uuid: d.uuid, value: d.value, writable: false })
+                .collect(),
+        }
+    }
+}
+
+impl Characteristic {
+    fn local(&self, start: Instant) -> local::Characteristic {
+        let f = &self.flags;
+        let state = Arc::new(ValueState::new(self, start));
+
+        let read = (f.read || f.encrypt_read || f.encrypt_authenticated_read || f.secure_read).then(|| {
+            let state = state.clone();
+            CharacteristicRead {
+                read: f.read,
+                encrypt_read: f.encrypt_read,
+                encrypt_authenticated_read: f.encrypt_authenticated_read,
+                secure_read: f.secure_read,
+                fun: Box::new(move |req| {
+                    let value = state.current().get(usize::from(req.offset)..).map(|v| v.to_vec());
+                    async move { value.ok_or(ReqError::InvalidOffset) }.boxed()
+                }),
+                ..Default::default()
+            }
+        });
+
+        let write = (f.write
+            || f.write_without_response
+            || f.reliable_write
+            || f.authenticated_signed_writes
+            || f.encrypt_write
+            || f.encrypt_authenticated_write
+            || f.secure_write)
+            .then(|| {
+                let state = state.clone();
+                CharacteristicWrite {
+                    write: f.write,
+                    write_without_response: f.write_without_response,
+                    reliable_write: f.reliable_write,
+                    authenticated_signed_writes: f.authenticated_signed_writes,
+                    encrypt_write: f.encrypt_write,
+                    encrypt_authenticated_write: f.encrypt_authenticated_write,
+                    secure_write: f.secure_write,
+                    method: CharacteristicWriteMethod::Fun(Box::new(move |value, req| {
+                        let res = state.write(req.offset, &value);
+                        async move { res }.boxed()
+                    })),
+                    ..Default::default()
+                }
+            });
+
+        let notify = (f.notify || f.indicate).then(|| CharacteristicNotify {
+            notify: f.notify,
+            indicate: f.indicate,
+            method: CharacteristicNotifyMethod::Fun(Box::new(move |notifier| {
+                tokio::spawn(state.clone().notify_session(notifier));
+                future::ready(()).boxed()
+            })),
+            ..Default::default()
+        });
+
+        local::Characteristic {
+            uuid: self.uuid,
+            handle: self.handle.and_then(|h| h.checked_add(1)).and_then(NonZeroU16::new),
+            broadcast: f.broadcast,
+            writable_auxiliaries: f.writable_auxiliaries,
+            descriptors: self
+                .descriptors
+                .iter()
+                .filter(|d| {
+                    d.uuid != Uuid::from_u16(CLIENT_CHARACTERISTIC_CONFIGURATION)
+                        && d.uuid != Uuid::from_u16(CHARACTERISTIC_EXTENDED_PROPERTIES)
+                })
+                .map(|d| d.local(f.writable_auxiliaries))
+                .collect(),
+            read,
+            write,
+            notify,
+            ..Default::default()
+        }
+    }
+}
+
+impl Descriptor {
+    fn local(&self, writable_auxiliaries: bool) -> local::Descriptor {
+        let value = Arc::new(Mutex::new(self.value.clone().unwrap_or_default()));
+        let writable = self.writable
+            || (writable_auxiliaries && self.uuid == Uuid::from_u16(CHARACTERISTIC_USER_DESCRIPTION));
+
+        let read = self.value.is_some().then(|| {
+            let value = value.clone();
+            DescriptorRead {
+                read: true,
+                fun: Box::new(move |req| {
+                    let value = value.lock().unwrap().get(usize::from(req.offset)..).map(|v| v.to_vec());
+                    async move { value.ok_or(ReqError::InvalidOffset) }.boxed()
+                }),
+                ..Default::default()
+            }
+        });
+
+        let write = writable.then(|| DescriptorWrite {
+            write: true,
+            fun: Box::new(move |data, req| {
+                let res = splice(&mut value.lock().unwrap(), req.offset, &data);
+                async move { res }.boxed()
+            }),
+            ..Default::default()
+        });
+
+        local::Descriptor {
+            uuid: self.uuid,
+            handle: self.handle.and_then(NonZeroU16::new),
+            read,
+            write,
+            ..Default::default()
+        }
+    }
+}
+
+/// Current value of an emulated characteristic.
+struct ValueState {
+    values: Vec<Vec<u8>>,
+    interval: Option<Duration>,
+    repeat: bool,
+    start: Instant,
+    /// Written value together with the script step it was written in.
+    written: Mutex<Option<(u64, Vec<u8>)>>,
+    /// Signalled when the value has been written.
+    changed: watch::Sender<()>,
+}
+
+impl ValueState {
+    fn new(c: &Characteristic, start: Instant) -> Self {
+        let (values, interval, repeat) = match &c.script {
+            Some(script) if !script.values.is_empty() => (
+                script.values.clone(),
+                Some(Duration::from_millis(script.interval_ms))
+                    .filter(|i| !i.is_zero() && script.values.len() > 1),
+                script.repeat,
+            ),
+            _ => (vec![c.value.clone().unwrap_or_default()], None, false),
+        };
+        Self { values, interval, repeat, start, written: Mutex::new(None), changed: watch::channel(()).0 }
+    }
+
+    /// Number of script steps that have elapsed, saturating at the last value if not repeating.
+    fn step(&self) -> u64 {
+        let Some(interval) = self.interval else { return 0 };
+        let step = (self.start.elapsed().as_nanos() / interval.as_nanos()) as u64;
+        match self.repeat {
+            true => step,
+            false => step.min(self.values.len() as u64 - 1),
+        }
+    }
+
+    /// Time of the next scripted change.
+    ///
+    /// There is no further change if its time is not representable.
+    fn next_change(&self) -> Option<Instant> {
+        let interval = self.interval?;
+        let step = self.step();
+        if !self.repeat && step == self.values.len() as u64 - 1 {
+            return None;
+        }
+        let steps = u32::try_from(step.checked_add(1)?).ok()?;
+        self.start.checked_add(interval.checked_mul(steps)?)
+    }
+
+    fn current(&self) -> Vec<u8> {
+        let step = self.step();
+        match &*self.written.lock().unwrap() {
+            Some((written_step, value)) if *written_step == step => value.clone(),
+            _ => self.values[(step % self.values.len() as u64) as usize].clone(),
+        }
+    }
+
+    /// Sends the value to the client on each change until the notification session is stopped.
+    async fn notify_session(self: Arc<Self>, mut notifier: CharacteristicNotifier) {
+        let mut changed = self.changed.subscribe();
+        loop {
+            let next_change = self.next_change();
+            select! {
+                () = async {
+                    match next_change {
+                        Some(at) => sleep_until(at).await,
+                        None => future::pending().await,
+                    }
+                } => (),
+                res = changed.changed() => {
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                () = notifier.stopped() => break,
+            }
+            if notifier.notify(self.current()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    fn write(&self, offset: u16, data: &[u8]) -> ReqResult<()> {
+        let step = self.step();
+        let mut value = self.current();
+        splice(&mut value, offset, data)?;
+        *self.written.lock().unwrap() = Some((step, value));
+        self.changed.send_replace(());
+        Ok(())
+    }
+}
+
+/// Replaces the end of `value` starting at `offset` by `data`.
+fn splice(value: &mut Vec<u8>, offset: u16, data: &[u8]) -> ReqResult<()> {
+    let offset = usize::from(offset);
+    if offset > value.len() {
+        return Err(ReqError::InvalidOffset);
+    }
+    value.truncate(offset);
+    value.extend_from_slice(data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::advance;
+
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn scripted(values: &[&[u8]], interval_ms: u64, repeat: bool) -> Characteristic {
+        Characteristic {
+            handle: None,
+            uuid: Uuid::from_u16(0x2a19),
+            flags: CharacteristicFlags { read: true, write: true, notify: true, ..Default::default() },
+            value: Some(vec![0xff]),
+            script: Some(Script { values: values.iter().map(|v| v.to_vec()).collect(), interval_ms, repeat }),
+            descriptors: Vec::new(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn step() {
+        let state = ValueState::new(&scripted(&[&[1], &[2], &[3]], 100, false), Instant::now());
+        assert_eq!((state.step(), state.current()), (0, vec![1]));
+        assert_eq!(state.next_change(), Some(state.start + INTERVAL));
+
+        advance(INTERVAL * 2 + INTERVAL / 2).await;
+        assert_eq!((state.step(), state.current()), (2, vec![3]));
+        assert_eq!(state.next_change(), None);
+
+        // Without repetition the last value is held.
+        advance(INTERVAL * 10).await;
+        assert_eq!((state.step(), state.current()), (2, vec![3]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn step_repeat() {
+        let state = ValueState::new(&scripted(&[&[1], &[2], &[3]], 100, true), Instant::now());
+        advance(INTERVAL * 4).await;
+        assert_eq!((state.step(), state.current()), (4, vec![2]));
+        assert_eq!(state.next_change(), Some(state.start + INTERVAL * 5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn static_value() {
+        // Without a script, with an empty script and with a zero interval the value never changes.
+        let mut c = scripted(&[], 100, true);
+        let state = ValueState::new(&c, Instant::now());
+        assert_eq!((state.current(), state.next_change()), (vec![0xff], None));
+
+        c.script = None;
+        c.value = None;
+        let state = ValueState::new(&c, Instant::now());
+        assert_eq!((state.current(), state.next_change()), (Vec::new(), None));
+
+        let state = ValueState::new(&scripted(&[&[1], &[2]], 0, true), Instant::now());
+        advance(INTERVAL).await;
+        assert_eq!((state.step(), state.current(), state.next_change()), (0, vec![1], None));
+
+        let state = ValueState::new(&scripted(&[&[1]], 100, true), Instant::now());
+        assert_eq!(state.next_change(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_change_overflow() {
+        let mut state = ValueState::new(&scripted(&[&[1], &[2], &[3]], 100, true), Instant::now());
+        state.interval = Some(Duration::MAX);
+        assert_eq!((state.step(), state.next_change()), (0, None));
+        state.interval = Some(Duration::from_secs(u64::MAX / 2));
+        assert_eq!((state.step(), state.next_change()), (0, None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn write() {
+        let state = ValueState::new(&scripted(&[&[1, 2, 3], &[4, 5, 6]], 100, false), Instant::now());
+        let changed = state.changed.subscribe();
+
+        state.write(1, &[7]).unwrap();
+        assert!(changed.has_changed().unwrap());
+        assert_eq!(state.current(), vec![1, 7]);
+        state.write(2, &[8, 9]).unwrap();
+        assert_eq!(state.current(), vec![1, 7, 8, 9]);
+        assert_eq!(state.write(5, &[0]), Err(ReqError::InvalidOffset));
+        assert_eq!(state.current(), vec![1, 7, 8, 9]);
+
+        // The written value is replaced by the next scripted value.
+        advance(INTERVAL).await;
+        assert_eq!(state.current(), vec![4, 5, 6]);
+        state.write(0, &[]).unwrap();
+        assert_eq!(state.current(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn splice() {
+        let mut value = vec![1, 2, 3];
+        super::splice(&mut value, 3, &[4]).unwrap();
+        assert_eq!(value, [1, 2, 3, 4]);
+        super::splice(&mut value, 1, &[5]).unwrap();
+        assert_eq!(value, [1, 5]);
+        super::splice(&mut value, 0, &[]).unwrap();
+        assert!(value.is_empty());
+        assert_eq!(super::splice(&mut value, 1, &[6]), Err(ReqError::InvalidOffset));
+        assert!(value.is_empty());
+    }
+
+    /// Snapshot of a device with a battery service and a writable user description.
+    fn snapshot() -> Database {
+        let descriptor = |handle, uuid, value: &[u8]| database::Descriptor {
+            handle,
+            uuid: Uuid::from_u16(uuid),
+            name: None,
+            value: Some(value.to_vec()),
+            _non_exhaustive: (),
+        };
+        Database {
+            services: vec![database::Service {
+                handle: 0x0010,
+                uuid: Uuid::from_u16(0x180f),
+                name: Some("Battery".to_string()),
+                primary: true,
+                includes: Vec::new(),
+                characteristics: vec![database::Characteristic {
+                    handle: 0x0011,
+                    uuid: Uuid::from_u16(0x2a19),
+                    name: None,
+                    flags: CharacteristicFlags {
+                        read: true,
+                        notify: true,
+                        writable_auxiliaries: true,
+                        ..Default::default()
+                    },
+                    value: Some(vec![0x64]),
+                    descriptors: vec![
+                        descriptor(0x0013, CLIENT_CHARACTERISTIC_CONFIGURATION, &[0x01, 0x00]),
+                        descriptor(0x0014, CHARACTERISTIC_USER_DESCRIPTION, b"Level"),
+                    ],
+                    _non_exhaustive: (),
+                }],
+                _non_exhaustive: (),
+            }],
+            _non_exhaustive: (),
+        }
+    }
+
+    fn expected() -> Peripheral {
+        Peripheral {
+            services: vec![Service {
+                handle: Some(0x0010),
+                uuid: Uuid::from_u16(0x180f),
+                primary: true,
+                characteristics: vec![Characteristic {
+                    handle: Some(0x0011),
+                    uuid: Uuid::from_u16(0x2a19),
+                    flags: CharacteristicFlags {
+                        read: true,
+                        notify: true,
+                        writable_auxiliaries: true,
+                        ..Default::default()
+                    },
+                    value: Some(vec![0x64]),
+                    script: None,
+                    descriptors: vec![
+                        Descriptor {
+                            handle: Some(0x0013),
+                            uuid: Uuid::from_u16(CLIENT_CHARACTERISTIC_CONFIGURATION),
+                            value: Some(vec![0x01, 0x00]),
+                            writable: false,
+                        },
+                        Descriptor {
+                            handle: Some(0x0014),
+                            uuid: Uuid::from_u16(CHARACTERISTIC_USER_DESCRIPTION),
+                            value: Some(b"Level".to_vec()),
+                            writable: false,
+                        },
+                    ],
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn from_database() {
+        assert_eq!(Peripheral::from(snapshot()), expected());
+    }
+
+    #[test]
+    fn application() {
+        let app = expected().application();
+        assert_eq!(app.services.len(), 1);
+        let service = &app.services[0];
+        assert_eq!(
+            (service.uuid, service.handle, service.primary),
+            (Uuid::from_u16(0x180f), NonZeroU16::new(0x10), true)
+        );
+
+        // The value handle follows the declaration and the configuration descriptor is left to the server.
+        let c = &service.characteristics[0];
+        assert_eq!(c.handle, NonZeroU16::new(0x12));
+        assert!(c.read.is_some() && c.write.is_none() && c.notify.is_some());
+        assert!(c.writable_auxiliaries);
+        assert_eq!(c.descriptors.len(), 1);
+        assert_eq!(c.descriptors[0].handle, NonZeroU16::new(0x14));
+        assert!(c.descriptors[0].read.is_some() && c.descriptors[0].write.is_some());
+
+        // Without the writable auxiliaries flag the user description is only writable if specified.
+        let mut peripheral = expected();
+        let c = &mut peripheral.services[0].characteristics[0];
+        c.flags.writable_auxiliaries = false;
+        let app = peripheral.application();
+        assert!(app.services[0].characteristics[0].descriptors[0].write.is_none());
+        peripheral.services[0].characteristics[0].descriptors[1].writable = true;
+        let app = peripheral.application();
+        assert!(app.services[0].characteristics[0].descriptors[0].write.is_some());
+    }
+}
@@ -33,6 +33,10 @@ use crate::{
     ERR_PREFIX, SERVICE_NAME, TIMEOUT,
 };
 
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use bluer_macros::{gatt_service_impl, GattService};
+
 pub(crate) const MANAGER_INTERFACE: &str = "org.bluez.GattManager1";
 
 /// Link type.
@@ -152,7 +156,7 @@ impl ServiceControl {
 ///
 /// Use [service_control] to obtain controller and associated handle.
 pub struct ServiceControlHandle {
-    handle_tx: watch::Sender<Option<NonZeroU16>>,
+    pub(crate) handle_tx: watch::Sender<Option<NonZeroU16>>,
 }
 
 impl Default for ServiceControlHandle {
@@ -511,13 +515,32 @@ impl CharacteristicWriteRequest {
 ///
 /// Use this to send notifications or indications.
 pub struct CharacteristicNotifier {
-    connection: Weak<SyncConnection>,
-    path: Path<'static>,
+    sink: NotifySink,
     stop_notify_tx: mpsc::Sender<()>,
     confirm_rx: Option<mpsc::Receiver<()>>,
 }
 
+/// Destination of notifications.
+enum NotifySink {
+    /// Characteristic value change signal to the Bluetooth daemon.
+    Dbus { connection: Weak<SyncConnection>, path: Path<'static> },
+    /// Handle value notification or indication sent by an ATT server connection.
+    #[cfg(feature = "att")]
+    Att { handle: u16, tx: mpsc::UnboundedSender<(u16, Vec<u8>)> },
+}
+
 impl CharacteristicNotifier {
+    /// Creates a notification session for a connection of [crate::att::Server].
+    ///
+    /// Values are sent over `tx` together with the characteristic value handle.
+    #[cfg(feature = "att")]
+    pub(crate) fn att(
+        handle: u16, tx: mpsc::UnboundedSender<(u16, Vec<u8>)>, stop_notify_tx: mpsc::Sender<()>,
+        confirm_rx: Option<mpsc::Receiver<()>>,
+    ) -> Self {
+        Self { sink: NotifySink::Att { handle, tx }, stop_notify_tx, confirm_rx }
+    }
+
     /// True, if each notification is confirmed by the receiving device.
     ///
     /// This is the case when the Indication mechanism is used.
@@ -543,8 +566,6 @@ impl CharacteristicNotifier {
     ///
     /// This fails when the notification session has been stopped by the receiving device.
     pub async fn notify(&mut self, value: Vec<u8>) -> Result<()> {
-        let connection =
-            self.connection.upgrade().ok_or_else(|| Error::new(ErrorKind::NotificationSessionStopped))?;
         if self.is_stopped() {
             return Err(Error::new(ErrorKind::NotificationSessionStopped));
         }
@@ -557,16 +578,25 @@ impl CharacteristicNotifier {
         }
 
         // Send notification.
-        let mut changed_properties = PropMap::new();
-        changed_properties.insert("Value".to_string(), Variant(Box::new(value)));
-        let ppc = PropertiesPropertiesChanged {
-            interface_name: CHARACTERISTIC_INTERFACE.to_string(),
-            changed_properties,
-            invalidated_properties: Vec::new(),
-        };
-        let msg = ppc.to_emit_message(&self.path);
-        connection.send(msg).map_err(|_| Error::new(ErrorKind::NotificationSessionStopped))?;
-        drop(connection);
+        match &self.sink {
+            NotifySink::Dbus { connection, path } => {
+                let connection =
+                    connection.upgrade().ok_or_else(|| Error::new(ErrorKind::NotificationSessionStopped))?;
+                let mut changed_properties = PropMap::new();
+                changed_properties.insert("Value".to_string(), Variant(Box::new(value)));
+                let ppc = PropertiesPropertiesChanged {
+                    interface_name: CHARACTERISTIC_INTERFACE.to_string(),
+                    changed_properties,
+                    invalidated_properties: Vec::new(),
+                };
+                let msg = ppc.to_emit_message(path);
+                connection.send(msg).map_err(|_| Error::new(ErrorKind::NotificationSessionStopped))?;
+            }
+            #[cfg(feature = "att")]
+            NotifySink::Att { handle, tx } => {
+                tx.send((*handle, value)).map_err(|_| Error::new(ErrorKind::NotificationSessionStopped))?;
+            }
+        }
 
         // Wait for confirmation if this is an indication session.
         // Note that we can be aborted before we receive the confirmation.
@@ -587,11 +617,11 @@ impl CharacteristicNotifier {
 
 /// A remote request to start writing to a characteristic via IO.
 pub struct CharacteristicWriteIoRequest {
-    adapter_name: String,
-    device_address: Address,
-    mtu: u16,
-    link: Option<LinkType>,
-    tx: oneshot::Sender<ReqResult<OwnedFd>>,
+    pub(crate) adapter_name: String,
+    pub(crate) device_address: Address,
+    pub(crate) mtu: u16,
+    pub(crate) link: Option<LinkType>,
+    pub(crate) tx: oneshot::Sender<ReqResult<OwnedFd>>,
 }
 
 impl CharacteristicWriteIoRequest {
@@ -696,8 +726,8 @@ impl Stream for CharacteristicControl {
 ///
 /// Use [characteristic_control] to obtain controller and associated handle.
 pub struct CharacteristicControlHandle {
-    handle_tx: watch::Sender<Option<NonZeroU16>>,
-    events_tx: mpsc::Sender<CharacteristicControlEvent>,
+    pub(crate) handle_tx: watch::Sender<Option<NonZeroU16>>,
+    pub(crate) events_tx: mpsc::Sender<CharacteristicControlEvent>,
 }
 
 impl Default for CharacteristicControlHandle {
@@ -871,8 +901,7 @@ impl RegisteredCharacteristic {
                                 });
                             }
                             let notifier = CharacteristicNotifier {
-                                connection: reg.connection.clone(),
-                                path,
+                                sink: NotifySink::Dbus { connection: reg.connection.clone(), path },
                                 stop_notify_tx,
                                 confirm_rx,
                             };
@@ -1181,7 +1210,7 @@ impl DescriptorControl {
 ///
 /// Use [descriptor_control] to obtain controller and associated handle.
 pub struct DescriptorControlHandle {
-    handle_tx: watch::Sender<Option<NonZeroU16>>,
+    pub(crate) handle_tx: watch::Sender<Option<NonZeroU16>>,
 }
 
 impl Default for DescriptorControlHandle {
@@ -1348,10 +1377,20 @@ impl Application {
         }
 
         log::trace!("Registering application at {}", &app_path);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        let () = proxy
-            .method_call(MANAGER_INTERFACE, "RegisterApplication", (app_path.clone(), PropMap::new()))
+        let manager_path = Adapter::dbus_path(&adapter_name)?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_app_path = app_path.clone();
+        inner
+            .register_with_daemon(&app_path, Some(&adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_app_path = reg_app_path.clone();
+                async move {
+                    let () = proxy
+                        .method_call(MANAGER_INTERFACE, "RegisterApplication", (reg_app_path, PropMap::new()))
+                        .await?;
+                    Ok(())
+                }
+            })
             .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
@@ -1360,6 +1399,7 @@ impl Application {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering application at {}", &app_path_unreg);
+            inner.unregister_with_daemon(&app_path_unreg).await;
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterApplication", (app_path_unreg,)).await;
 
@@ -1437,10 +1477,20 @@ impl Profile {
         }
 
         log::trace!("Registering profile at {}", &profile_path);
-        let proxy =
-            Proxy::new(SERVICE_NAME, Adapter::dbus_path(&adapter_name)?, TIMEOUT, inner.connection.clone());
-        let () = proxy
-            .method_call(MANAGER_INTERFACE, "RegisterApplication", (profile_path.clone(), PropMap::new()))
+        let manager_path = Adapter::dbus_path(&adapter_name)?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_profile_path = profile_path.clone();
+        inner
+            .register_with_daemon(&profile_path, Some(&adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_profile_path = reg_profile_path.clone();
+                async move {
+                    let () = proxy
+                        .method_call(MANAGER_INTERFACE, "RegisterApplication", (reg_profile_path, PropMap::new()))
+                        .await?;
+                    Ok(())
+                }
+            })
             .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
@@ -1449,6 +1499,7 @@ impl Profile {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering profile at {}", &profile_path_unreg);
+            inner.unregister_with_daemon(&profile_path_unreg).await;
             let _: std::result::Result<(), dbus::Error> = proxy
                 .method_call(MANAGER_INTERFACE, "UnregisterApplication", (profile_path_unreg.clone(),))
                 .await;
@@ -1482,3 +1533,466 @@ impl fmt::Debug for ProfileHandle {
         write!(f, "ProfileHandle {{ {} }}", &self.name)
     }
 }
+
+// ===========================================================================================
+// Derived GATT service
+// ===========================================================================================
+
+/// Value of a characteristic of a [GattService] that can be converted to and from bytes.
+///
+/// Integers and floating point numbers are encoded in little-endian byte order,
+/// booleans as a single byte and strings as UTF-8.
+pub trait GattValue: Sized {
+    /// Encodes the value.
+    fn to_gatt_value(&self) -> Vec<u8>;
+
+    /// Decodes the value.
+    fn from_gatt_value(value: &[u8]) -> ReqResult<Self>;
+}
+
+macro_rules! impl_gatt_value_for_num {
+    ($($t:ty),*) => {
+        $(
+            impl GattValue for $t {
+                fn to_gatt_value(&self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+
+                fn from_gatt_value(value: &[u8]) -> ReqResult<Self> {
+                    Ok(Self::from_le_bytes(value.try_into().map_err(|_| ReqError::InvalidValueLength)?))
+                }
+            }
+        )*
+    };
+}
+
+impl_gatt_value_for_num!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl GattValue for bool {
+    fn to_gatt_value(&self) -> Vec<u8> {
+        vec![u8::from(*self)]
+    }
+
+    fn from_gatt_value(value: &[u8]) -> ReqResult<Self> {
+        match value {
+            [v] => Ok(*v != 0),
+            _ => Err(ReqError::InvalidValueLength),
+        }
+    }
+}
+
+impl GattValue for String {
+    fn to_gatt_value(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_gatt_value(value: &[u8]) -> ReqResult<Self> {
+        String::from_utf8(value.to_vec()).map_err(|_| ReqError::Failed)
+    }
+}
+
+impl GattValue for Vec<u8> {
+    fn to_gatt_value(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_gatt_value(value: &[u8]) -> ReqResult<Self> {
+        Ok(value.to_vec())
+    }
+}
+
+impl<const N: usize> GattValue for [u8; N] {
+    fn to_gatt_value(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn from_gatt_value(value: &[u8]) -> ReqResult<Self> {
+        value.try_into().map_err(|_| ReqError::InvalidValueLength)
+    }
+}
+
+/// GATT service defined by a Rust type.
+///
+/// This is usually implemented using `#[derive(GattService)]`, which is available
+/// when the `macros` crate feature is enabled.
+/// The fields of the type hold the characteristic values and further state of the service.
+///
+/// Use [ServiceState] to obtain the [service definition](Service) for publishing and
+/// to access the state afterwards.
+pub trait GattService: Sized + Send + 'static {
+    /// Definition of the service accessing the specified state.
+    fn service(state: &ServiceState<Self>) -> Service;
+}
+
+/// Shared state of a [GattService].
+///
+/// Read and write requests of remote devices access the state.
+/// Notifications and indications are sent whenever a notifying characteristic value is changed,
+/// either by a write request or by a call to [update](Self::update).
+///
+/// The state is locked during each access and thus callbacks should not block.
+pub struct ServiceState<T> {
+    inner: Arc<ServiceStateInner<T>>,
+}
+
+struct ServiceStateInner<T> {
+    value: std::sync::Mutex<T>,
+    changed_tx: watch::Sender<()>,
+}
+
+impl<T> Clone for ServiceState<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<T> ServiceState<T> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.inner.value.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<T> fmt::Debug for ServiceState<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ServiceState").field(&*self.lock()).finish()
+    }
+}
+
+impl<T> ServiceState<T>
+where
+    T: GattService,
+{
+    /// Creates the shared state of a GATT service.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(ServiceStateInner {
+                value: std::sync::Mutex::new(value),
+                changed_tx: watch::channel(()).0,
+            }),
+        }
+    }
+
+    /// Definition of the GATT service for inclusion in an [Application].
+    pub fn service(&self) -> Service {
+        T::service(self)
+    }
+
+    /// Accesses the state.
+    pub fn get<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.lock())
+    }
+
+    /// Modifies the state.
+    ///
+    /// Subscribed devices are notified of all characteristic values that have changed.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let ret = f(&mut self.lock());
+        self.inner.changed_tx.send_replace(());
+        ret
+    }
+
+    /// Read function returning the characteristic value obtained by `get`.
+    #[doc(hidden)]
+    pub fn read_fun(&self, get: fn(&T) -> Vec<u8>) -> CharacteristicReadFun {
+        let state = self.clone();
+        Box::new(move |req| {
+            let value = state.get(get).get(usize::from(req.offset)..).map(|v| v.to_vec());
+            async move { value.ok_or(ReqError::InvalidOffset) }.boxed()
+        })
+    }
+
+    /// Write function decoding the value and passing it to `set`.
+    ///
+    /// Subscribed devices are only notified if `set` succeeds.
+    #[doc(hidden)]
+    pub fn write_fun<V>(&self, set: fn(&mut T, V) -> ReqResult<()>) -> CharacteristicWriteFun
+    where
+        V: GattValue + 'static,
+    {
+        let state = self.clone();
+        Box::new(move |value, req| {
+            let res = match req.offset {
+                0 => V::from_gatt_value(&value).and_then(|value| {
+                    let res = set(&mut state.lock(), value);
+                    if res.is_ok() {
+                        state.inner.changed_tx.send_replace(());
+                    }
+                    res
+                }),
+                _ => Err(ReqError::InvalidOffset),
+            };
+            async move { res }.boxed()
+        })
+    }
+
+    /// Notify function sending the characteristic value obtained by `get` whenever it changes.
+    #[doc(hidden)]
+    pub fn notify_fun(&self, get: fn(&T) -> Vec<u8>) -> CharacteristicNotifyFun {
+        let state = self.clone();
+        Box::new(move |mut notifier| {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let mut changed_rx = state.inner.changed_tx.subscribe();
+                let mut last = state.get(get);
+                loop {
+                    tokio::select! {
+                        res = changed_rx.changed() => {
+                            if res.is_err() {
+                                break;
+                            }
+                        }
+                        () = notifier.stopped() => break,
+                    }
+                    let value = state.get(get);
+                    if value != last {
+                        if notifier.notify(value.clone()).await.is_err() {
+                            break;
+                        }
+                        last = value;
+                    }
+                }
+            });
+            async {}.boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Counter {
+        count: u16,
+        limit: u16,
+    }
+
+    impl GattService for Counter {
+        fn service(_state: &ServiceState<Self>) -> Service {
+            Service::default()
+        }
+    }
+
+    fn read_req(offset: u16) -> CharacteristicReadRequest {
+        CharacteristicReadRequest {
+            adapter_name: "hci0".to_string(),
+            device_address: Address::any(),
+            offset,
+            mtu: 23,
+            link: None,
+        }
+    }
+
+    fn write_req(offset: u16) -> CharacteristicWriteRequest {
+        CharacteristicWriteRequest {
+            adapter_name: "hci0".to_string(),
+            device_address: Address::any(),
+            offset,
+            op_type: WriteOp::Request,
+            mtu: 23,
+            link: None,
+            prepare_authorize: false,
+        }
+    }
+
+    #[test]
+    fn gatt_values() {
+        assert_eq!(0x1234u16.to_gatt_value(), [0x34, 0x12]);
+        assert_eq!(u16::from_gatt_value(&[0x34, 0x12]), Ok(0x1234));
+        assert_eq!(u16::from_gatt_value(&[0x34]), Err(ReqError::InvalidValueLength));
+        assert_eq!((-2i8).to_gatt_value(), [0xfe]);
+        assert_eq!(1.5f32.to_gatt_value(), 1.5f32.to_le_bytes());
+        assert_eq!(true.to_gatt_value(), [1]);
+        assert_eq!(bool::from_gatt_value(&[2]), Ok(true));
+        assert_eq!(bool::from_gatt_value(&[]), Err(ReqError::InvalidValueLength));
+        assert_eq!(String::from_gatt_value(b"abc"), Ok("abc".to_string()));
+        assert_eq!(String::from_gatt_value(&[0xff]), Err(ReqError::Failed));
+        assert_eq!(<[u8; 2]>::from_gatt_value(&[1, 2]), Ok([1, 2]));
+        assert_eq!(<[u8; 2]>::from_gatt_value(&[1, 2, 3]), Err(ReqError::InvalidValueLength));
+    }
+
+    #[tokio::test]
+    async fn read_write_funs() {
+        let state = ServiceState::new(Counter { count: 0x0102, limit: 10 });
+        let mut changed_rx = state.inner.changed_tx.subscribe();
+
+        let read = state.read_fun(|s| s.count.to_gatt_value());
+        assert_eq!(read(read_req(0)).await, Ok(vec![0x02, 0x01]));
+        assert_eq!(read(read_req(1)).await, Ok(vec![0x01]));
+        assert_eq!(read(read_req(2)).await, Ok(vec![]));
+        assert_eq!(read(read_req(3)).await, Err(ReqError::InvalidOffset));
+
+        let write = state.write_fun(|s: &mut Counter, count: u16| {
+            if count > s.limit {
+                return Err(ReqError::NotPermitted);
+            }
+            s.count = count;
+            Ok(())
+        });
+        write(vec![5, 0], write_req(0)).await.unwrap();
+        assert_eq!(state.get(|s| s.count), 5);
+        assert!(changed_rx.has_changed().unwrap());
+        changed_rx.mark_unchanged();
+
+        // Failed writes leave the state unchanged and do not notify.
+        assert_eq!(write(vec![11, 0], write_req(0)).await, Err(ReqError::NotPermitted));
+        assert_eq!(write(vec![1], write_req(0)).await, Err(ReqError::InvalidValueLength));
+        assert_eq!(write(vec![1, 0], write_req(1)).await, Err(ReqError::InvalidOffset));
+        assert_eq!(state.get(|s| s.count), 5);
+        assert!(!changed_rx.has_changed().unwrap());
+
+        state.update(|s| s.limit = 20);
+        assert!(changed_rx.has_changed().unwrap());
+    }
+
+    #[cfg(feature = "macros")]
+    mod derived {
+        use super::*;
+        use crate::UuidExt;
+
+        #[derive(GattService)]
+        #[gatt_service(uuid = 0x180f, methods)]
+        struct Battery {
+            #[characteristic(uuid = 0x2a19, read, notify)]
+            level: u8,
+            #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b90", read, write)]
+            name: String,
+            #[characteristic(uuid = 0x12345678, write_without_response, indicate)]
+            mode: u32,
+            discharging: bool,
+        }
+
+        #[gatt_service_impl]
+        impl Battery {
+            #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b91", read)]
+            fn low(&self) -> bool {
+                self.level < 10
+            }
+
+            #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b92", write)]
+            fn set_discharging(&mut self, discharging: bool) -> ReqResult<()> {
+                if discharging && self.level == 0 {
+                    return Err(ReqError::NotPermitted);
+                }
+                self.discharging = discharging;
+                Ok(())
+            }
+
+            #[characteristic(uuid = "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b93", write)]
+            fn set_level(&mut self, level: u8) {
+                self.level = level;
+            }
+        }
+
+        const SECONDARY_UUID: Uuid = Uuid::from_u128(0x5d2c1e7a_0b3f_4e8d_9a6c_1f2e3d4c5b6a);
+
+        #[derive(GattService)]
+        #[gatt_service(uuid = SECONDARY_UUID, primary = false)]
+        struct Secondary {
+            #[characteristic(uuid = SECONDARY_UUID, read)]
+            value: [u8; 2],
+        }
+
+        fn battery() -> ServiceState<Battery> {
+            ServiceState::new(Battery { level: 50, name: "main".to_string(), mode: 0, discharging: false })
+        }
+
+        #[test]
+        fn service() {
+            let service = battery().service();
+            assert_eq!(service.uuid, Uuid::from_u16(0x180f));
+            assert!(service.primary);
+
+            let uuids: Vec<_> = service.characteristics.iter().map(|c| c.uuid).collect();
+            assert_eq!(
+                uuids,
+                [
+                    Uuid::from_u16(0x2a19),
+                    "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b90".parse().unwrap(),
+                    Uuid::from_u32(0x12345678),
+                    "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b91".parse().unwrap(),
+                    "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b92".parse().unwrap(),
+                    "a4d1f7b2-5c3e-4f0a-9b8d-2e6c7a1f3b93".parse().unwrap(),
+                ]
+            );
+
+            let flags: Vec<_> = service
+                .characteristics
+                .iter()
+                .map(|c| {
+                    (
+                        c.read.as_ref().map(|r| r.read),
+                        c.write.as_ref().map(|w| (w.write, w.write_without_response)),
+                        c.notify.as_ref().map(|n| (n.notify, n.indicate)),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                flags,
+                [
+                    (Some(true), None, Some((true, false))),
+                    (Some(true), Some((true, false)), None),
+                    (None, Some((false, true)), Some((false, true))),
+                    (Some(true), None, None),
+                    (None, Some((true, false)), None),
+                    (None, Some((true, false)), None),
+                ]
+            );
+
+            let state = ServiceState::new(Secondary { value: [1, 2] });
+            let service = state.service();
+            assert_eq!(service.uuid, SECONDARY_UUID);
+            assert!(!service.primary);
+            assert_eq!(service.characteristics.len(), 1);
+            assert_eq!(service.characteristics[0].uuid, SECONDARY_UUID);
+        }
+
+        fn read_fun(c: &Characteristic) -> &CharacteristicReadFun {
+            &c.read.as_ref().unwrap().fun
+        }
+
+        fn write_fun(c: &Characteristic) -> &CharacteristicWriteFun {
+            match &c.write.as_ref().unwrap().method {
+                CharacteristicWriteMethod::Fun(fun) => fun,
+                _ => panic!("write method is not a function"),
+            }
+        }
+
+        #[tokio::test]
+        async fn read_write() {
+            let state = battery();
+            let service = state.service();
+            let [level, name, mode, low, discharging, set_level] = &service.characteristics[..] else {
+                panic!("unexpected characteristics")
+            };
+
+            assert_eq!(read_fun(level)(read_req(0)).await, Ok(vec![50]));
+            assert_eq!(read_fun(name)(read_req(0)).await, Ok(b"main".to_vec()));
+            assert_eq!(read_fun(name)(read_req(2)).await, Ok(b"in".to_vec()));
+            assert_eq!(read_fun(low)(read_req(0)).await, Ok(vec![0]));
+
+            write_fun(name)(b"aux".to_vec(), write_req(0)).await.unwrap();
+            assert_eq!(read_fun(name)(read_req(0)).await, Ok(b"aux".to_vec()));
+            write_fun(mode)(vec![1, 2, 3, 4], write_req(0)).await.unwrap();
+            assert_eq!(state.get(|s| s.mode), 0x04030201);
+            assert_eq!(write_fun(mode)(vec![1], write_req(0)).await, Err(ReqError::InvalidValueLength));
+
+            write_fun(set_level)(vec![5], write_req(0)).await.unwrap();
+            assert_eq!(read_fun(level)(read_req(0)).await, Ok(vec![5]));
+            assert_eq!(read_fun(low)(read_req(0)).await, Ok(vec![1]));
+
+            write_fun(discharging)(vec![1], write_req(0)).await.unwrap();
+            assert!(state.get(|s| s.discharging));
+            write_fun(set_level)(vec![0], write_req(0)).await.unwrap();
+            write_fun(discharging)(vec![0], write_req(0)).await.unwrap();
+
+            let changed_rx = state.inner.changed_tx.subscribe();
+            assert_eq!(write_fun(discharging)(vec![1], write_req(0)).await, Err(ReqError::NotPermitted));
+            assert!(!state.get(|s| s.discharging));
+            assert!(!changed_rx.has_changed().unwrap());
+        }
+    }
+}
@@ -18,6 +18,8 @@ use tokio::{
 
 use crate::Address;
 
+pub mod database;
+pub mod emulate;
 pub mod local;
 pub mod remote;
 
@@ -222,11 +224,11 @@ impl IntoRawFd for CharacteristicReader {
 #[pin_project]
 #[derive(Debug)]
 pub struct CharacteristicWriter {
-    adapter_name: String,
-    device_address: Address,
-    mtu: usize,
+    pub(crate) adapter_name: String,
+    pub(crate) device_address: Address,
+    pub(crate) mtu: usize,
     #[pin]
-    socket: UnixDatagram,
+    pub(crate) socket: UnixDatagram,
 }
 
 impl CharacteristicWriter {
@@ -271,14 +273,7 @@ impl CharacteristicWriter {
     ///
     /// Does not wait for send space to become available.
     pub fn try_send(&self, buf: &[u8]) -> std::io::Result<()> {
-        if buf.len() > self.mtu {
-            return Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "data length exceeds MTU"));
-        }
-        match self.socket.try_send(buf) {
-            Ok(n) if n == buf.len() => Ok(()),
-            Ok(_) => Err(std::io::Error::new(std::io::ErrorKind::Other, "partial write occured")),
-            Err(err) => Err(err),
-        }
+        try_send_packet(&self.socket, buf, self.mtu)
     }
 
     /// Send the characteristic value using a single write or notify operation.
@@ -355,6 +350,18 @@ pub(crate) fn make_socket_pair(non_block: bool) -> std::io::Result<(OwnedFd, Uni
     Ok((fd1, us))
 }
 
+/// Tries to send a packet of at most `mtu` bytes over a sequential packet socket.
+pub(crate) fn try_send_packet(socket: &UnixDatagram, buf: &[u8], mtu: usize) -> std::io::Result<()> {
+    if buf.len() > mtu {
+        return Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "data length exceeds MTU"));
+    }
+    match socket.try_send(buf) {
+        Ok(n) if n == buf.len() => Ok(()),
+        Ok(_) => Err(std::io::Error::other("partial write occurred")),
+        Err(err) => Err(err),
+    }
+}
+
 /// Apply MTU workaround.
 ///
 /// BlueZ drops data at end of packet if full reported MTU is used, thus we
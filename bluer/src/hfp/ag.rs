@@ -0,0 +1,711 @@
+//! Audio gateway role.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{
+    at::Connection, number_type, AudioGatewayFeatures, CallHoldAction, Codec, Command, CurrentCall,
+    HandsFreeFeatures, HfpError, Indicator, Response, MAX_VOLUME,
+};
+use crate::rfcomm::Stream;
+
+/// Configuration of the audio gateway.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AudioGatewayConfig {
+    /// Supported features announced to the hands-free unit.
+    pub features: AudioGatewayFeatures,
+    /// Supported voice codecs used for codec negotiation.
+    ///
+    /// CVSD is always supported.
+    pub codecs: Vec<Codec>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for AudioGatewayConfig {
+    fn default() -> Self {
+        Self {
+            features: AudioGatewayFeatures {
+                reject_call: true,
+                enhanced_call_status: true,
+                extended_error_codes: true,
+                codec_negotiation: true,
+                ..Default::default()
+            },
+            codecs: vec![Codec::Cvsd, Codec::Msbc],
+            _non_exhaustive: (),
+        }
+    }
+}
+
+/// Event reported by the hands-free unit.
+///
+/// Requests must be answered using [AudioGateway::accept_request] or
+/// [AudioGateway::reject_request]; see [AudioGatewayEvent::is_request].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AudioGatewayEvent {
+    /// Request to answer the incoming call.
+    Answer,
+    /// Request to hang up the active call or reject the incoming call.
+    HangUp,
+    /// Request to call the phone number.
+    Dial(String),
+    /// Request to call the number stored at the memory location.
+    DialMemory(String),
+    /// Request to call the last dialed number.
+    Redial,
+    /// Request to send the DTMF code.
+    Dtmf(char),
+    /// Request to perform the call hold and multiparty action.
+    CallHold(CallHoldAction),
+    /// Request to activate or deactivate voice recognition.
+    VoiceRecognition(bool),
+    /// Request to enable or disable echo canceling and noise reduction.
+    EcNr(bool),
+    /// Request to perform codec negotiation using [AudioGateway::select_codec]
+    /// and establish the voice connection.
+    CodecConnection,
+    /// Request triggered by a button press of the headset.
+    ButtonPress,
+    /// Other request.
+    Other(Command),
+    /// Speaker gain of the hands-free unit changed.
+    SpeakerVolume(u8),
+    /// Microphone gain of the hands-free unit changed.
+    MicrophoneVolume(u8),
+    /// Voice codec has been selected by codec negotiation.
+    ///
+    /// The voice connection should be established using this codec.
+    CodecSelected(Codec),
+}
+
+impl AudioGatewayEvent {
+    /// Whether this is a request that must be answered.
+    pub fn is_request(&self) -> bool {
+        !matches!(self, Self::SpeakerVolume(_) | Self::MicrophoneVolume(_) | Self::CodecSelected(_))
+    }
+}
+
+/// Audio gateway connected to a hands-free unit.
+///
+/// The service level connection is established by [AudioGateway::accept].
+/// Requests and notifications of the hands-free unit are obtained using
+/// [AudioGateway::next_event]. Commands concerning the service level connection,
+/// indicators, the list of current calls and codec negotiation are answered automatically.
+///
+/// Each request must be answered before [AudioGateway::next_event] is called again;
+/// otherwise it is rejected.
+pub struct AudioGateway<S = Stream> {
+    conn: Connection<S>,
+    config: AudioGatewayConfig,
+    headset: bool,
+    slc: bool,
+    hf_features: HandsFreeFeatures,
+    hf_codecs: Vec<Codec>,
+    values: [u8; Indicator::ALL.len()],
+    event_reporting: bool,
+    cli: bool,
+    call_waiting: bool,
+    extended_errors: bool,
+    codec: Codec,
+    selecting: Option<Codec>,
+    calls: Vec<CurrentCall>,
+    operator: Option<String>,
+    subscriber_number: Option<String>,
+    request_pending: bool,
+    events: VecDeque<AudioGatewayEvent>,
+}
+
+impl<S> fmt::Debug for AudioGateway<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AudioGateway")
+            .field("headset", &self.headset)
+            .field("hf_features", &self.hf_features)
+            .field("indicators", &self.values)
+            .field("codec", &self.codec)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> AudioGateway<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(stream: S, config: AudioGatewayConfig, headset: bool) -> Self {
+        Self {
+            conn: Connection::new(stream),
+            config,
+            headset,
+            slc: headset,
+            hf_features: HandsFreeFeatures::default(),
+            hf_codecs: vec![Codec::Cvsd],
+            values: [0; Indicator::ALL.len()],
+            event_reporting: false,
+            cli: false,
+            call_waiting: false,
+            extended_errors: false,
+            codec: Codec::Cvsd,
+            selecting: None,
+            calls: Vec::new(),
+            operator: None,
+            subscriber_number: None,
+            request_pending: false,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Waits for the hands-free unit connected by the RFCOMM stream to establish
+    /// the service level connection of the hands-free profile.
+    ///
+    /// Requests received before the service level connection is established are rejected.
+    pub async fn accept(stream: S, config: AudioGatewayConfig) -> Result<Self> {
+        let mut this = Self::new(stream, config, false);
+        while !this.slc {
+            let cmd = this
+                .conn
+                .read_command()
+                .await?
+                .ok_or_else(|| Error::new(ErrorKind::ConnectionReset, "HFP connection closed"))?;
+            this.handle_command(cmd).await?;
+
+            let mut notifications = VecDeque::new();
+            while let Some(event) = this.events.pop_front() {
+                if event.is_request() {
+                    this.send_error(None).await?;
+                } else {
+                    notifications.push_back(event);
+                }
+            }
+            this.events = notifications;
+        }
+        Ok(this)
+    }
+
+    /// Uses the RFCOMM stream connected to a headset of the headset profile.
+    ///
+    /// The headset profile has no service level connection setup and no indicators.
+    pub fn headset(stream: S) -> Self {
+        Self::new(stream, AudioGatewayConfig::default(), true)
+    }
+
+    /// Supported features of the hands-free unit.
+    pub fn hf_features(&self) -> HandsFreeFeatures {
+        self.hf_features
+    }
+
+    /// Voice codecs supported by the hands-free unit.
+    pub fn hf_codecs(&self) -> &[Codec] {
+        &self.hf_codecs
+    }
+
+    /// Voice codec selected by the last codec negotiation.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Gets a reference to the underlying RFCOMM stream.
+    pub fn get_ref(&self) -> &S {
+        self.conn.get_ref()
+    }
+
+    /// Consumes this and returns the underlying RFCOMM stream.
+    pub fn into_inner(self) -> S {
+        self.conn.into_inner()
+    }
+
+    fn codec_negotiation(&self) -> bool {
+        !self.headset && self.config.features.codec_negotiation && self.hf_features.codec_negotiation
+    }
+
+    fn three_way_calling(&self) -> bool {
+        self.config.features.three_way_calling && self.hf_features.three_way_calling
+    }
+
+    fn supports_codec(&self, codec: Codec) -> bool {
+        codec == Codec::Cvsd || self.config.codecs.contains(&codec)
+    }
+
+    /// Waits for the next event reported by the hands-free unit.
+    ///
+    /// A request returned by the previous call that has not been answered
+    /// is rejected first.
+    ///
+    /// Returns [None] when the connection has been closed.
+    pub async fn next_event(&mut self) -> Result<Option<AudioGatewayEvent>> {
+        if self.request_pending {
+            log::warn!("Rejecting unanswered hands-free request");
+            self.reject_request(None).await?;
+        }
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                self.request_pending = event.is_request();
+                return Ok(Some(event));
+            }
+
+            match self.conn.read_command().await? {
+                Some(cmd) => self.handle_command(cmd).await?,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Answers the pending request with success.
+    pub async fn accept_request(&mut self) -> Result<()> {
+        self.take_request()?;
+        self.send(&Response::Ok).await
+    }
+
+    /// Answers the pending request with failure.
+    ///
+    /// The extended error code is sent if the hands-free unit enabled extended
+    /// error result codes.
+    pub async fn reject_request(&mut self, code: Option<u16>) -> Result<()> {
+        self.take_request()?;
+        self.send_error(code).await
+    }
+
+    fn take_request(&mut self) -> Result<()> {
+        if !self.request_pending {
+            return Err(Error::new(ErrorKind::InvalidInput, "no request pending"));
+        }
+        self.request_pending = false;
+        Ok(())
+    }
+
+    async fn send(&mut self, rsp: &Response) -> Result<()> {
+        self.conn.send_response(rsp).await
+    }
+
+    async fn send_error(&mut self, code: Option<u16>) -> Result<()> {
+        match code {
+            Some(code) if self.extended_errors => self.send(&Response::CmeError(code)).await,
+            _ => self.send(&Response::Error).await,
+        }
+    }
+
+    async fn handle_command(&mut self, cmd: std::result::Result<Command, HfpError>) -> Result<()> {
+        let cmd = match cmd {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                log::debug!("Rejecting invalid command: {err}");
+                return self.send_error(None).await;
+            }
+        };
+
+        let request = match cmd {
+            Command::SupportedFeatures(features) => {
+                self.hf_features = HandsFreeFeatures::from_bits(features);
+                self.send(&Response::SupportedFeatures(self.config.features.to_bits())).await?;
+                None
+            }
+            Command::AvailableCodecs(ids) => {
+                self.hf_codecs = ids.into_iter().filter_map(Codec::from_id).collect();
+                self.selecting = None;
+                None
+            }
+            Command::IndicatorsTest => {
+                let indicators =
+                    Indicator::ALL.iter().map(|indicator| (indicator.to_string(), indicator.range())).collect();
+                self.send(&Response::IndicatorList(indicators)).await?;
+                None
+            }
+            Command::IndicatorsRead => {
+                self.send(&Response::IndicatorValues(self.values.to_vec())).await?;
+                None
+            }
+            Command::EventReporting(enable) => {
+                self.event_reporting = enable;
+                if !self.three_way_calling() {
+                    self.slc = true;
+                }
+                None
+            }
+            Command::CallHoldTest if self.config.features.three_way_calling => {
+                let actions = if self.config.features.enhanced_call_control {
+                    ["0", "1", "1x", "2", "2x", "3", "4"].as_slice()
+                } else {
+                    ["0", "1", "2", "3"].as_slice()
+                };
+                self.send(&Response::CallHoldSupport(actions.iter().map(|a| a.to_string()).collect())).await?;
+                self.slc = true;
+                None
+            }
+            Command::CallingLineIdentification(enable) => {
+                self.cli = enable;
+                None
+            }
+            Command::CallWaitingNotification(enable) => {
+                self.call_waiting = enable;
+                None
+            }
+            Command::ExtendedErrors(enable) if self.config.features.extended_error_codes => {
+                self.extended_errors = enable;
+                None
+            }
+            Command::CodecSelection(id) => match self.selecting.take() {
+                Some(codec) if codec.id() == id => {
+                    self.codec = codec;
+                    self.events.push_back(AudioGatewayEvent::CodecSelected(codec));
+                    None
+                }
+                _ => return self.send_error(None).await,
+            },
+            Command::SpeakerVolume(gain) if gain <= MAX_VOLUME => {
+                self.events.push_back(AudioGatewayEvent::SpeakerVolume(gain));
+                None
+            }
+            Command::MicrophoneVolume(gain) if gain <= MAX_VOLUME => {
+                self.events.push_back(AudioGatewayEvent::MicrophoneVolume(gain));
+                None
+            }
+            Command::ListCurrentCalls => {
+                for call in self.calls.clone() {
+                    self.send(&Response::CurrentCall(call)).await?;
+                }
+                None
+            }
+            Command::NetworkOperatorFormat => None,
+            Command::NetworkOperator => {
+                self.send(&Response::NetworkOperator(self.operator.clone())).await?;
+                None
+            }
+            Command::SubscriberNumber => {
+                if let Some(number) = self.subscriber_number.clone() {
+                    let number_type = number_type(&number);
+                    self.send(&Response::SubscriberNumber { number, number_type }).await?;
+                }
+                None
+            }
+            Command::Answer => Some(AudioGatewayEvent::Answer),
+            Command::HangUp => Some(AudioGatewayEvent::HangUp),
+            Command::Dial(number) => Some(AudioGatewayEvent::Dial(number)),
+            Command::DialMemory(location) => Some(AudioGatewayEvent::DialMemory(location)),
+            Command::Redial => Some(AudioGatewayEvent::Redial),
+            Command::Dtmf(code) => Some(AudioGatewayEvent::Dtmf(code)),
+            Command::CallHold(action) if self.config.features.three_way_calling => {
+                Some(AudioGatewayEvent::CallHold(action))
+            }
+            Command::VoiceRecognition(active) if self.config.features.voice_recognition => {
+                Some(AudioGatewayEvent::VoiceRecognition(active))
+            }
+            Command::EcNr(enable) if self.config.features.ec_nr => Some(AudioGatewayEvent::EcNr(enable)),
+            Command::CodecConnection if self.codec_negotiation() => Some(AudioGatewayEvent::CodecConnection),
+            Command::KeyPress => Some(AudioGatewayEvent::ButtonPress),
+            Command::Other(cmd) => Some(AudioGatewayEvent::Other(Command::Other(cmd))),
+            cmd => {
+                log::debug!("Rejecting unsupported command {cmd}");
+                return self.send_error(None).await;
+            }
+        };
+
+        match request {
+            Some(event) => self.events.push_back(event),
+            None => self.send(&Response::Ok).await?,
+        }
+        Ok(())
+    }
+
+    /// Current value of the indicator.
+    pub fn indicator(&self, indicator: Indicator) -> u8 {
+        self.values[indicator_index(indicator)]
+    }
+
+    /// Sets the value of the indicator and notifies the hands-free unit if it changed.
+    pub async fn set_indicator(&mut self, indicator: Indicator, value: u8) -> Result<()> {
+        if !indicator.range().contains(&value) {
+            return Err(Error::new(ErrorKind::InvalidInput, "indicator value out of range"));
+        }
+        let index = indicator_index(indicator);
+        if self.values[index] == value {
+            return Ok(());
+        }
+        self.values[index] = value;
+        if self.slc && !self.headset && self.event_reporting {
+            self.send(&Response::IndicatorEvent { index: index as u8 + 1, value }).await?;
+        }
+        Ok(())
+    }
+
+    /// Sets the list of current calls reported to the hands-free unit.
+    pub fn set_current_calls(&mut self, calls: Vec<CurrentCall>) {
+        self.calls = calls;
+    }
+
+    /// Sets the network operator name reported to the hands-free unit.
+    pub fn set_network_operator(&mut self, name: Option<String>) {
+        self.operator = name;
+    }
+
+    /// Sets the phone number of the subscriber reported to the hands-free unit.
+    pub fn set_subscriber_number(&mut self, number: Option<String>) {
+        self.subscriber_number = number;
+    }
+
+    /// Sends an incoming call alert.
+    ///
+    /// The phone number is sent if the hands-free unit enabled calling line identification.
+    /// This should be repeated while the call is ringing.
+    pub async fn ring(&mut self, number: Option<&str>) -> Result<()> {
+        self.send(&Response::Ring).await?;
+        if let Some(number) = number.filter(|_| self.cli) {
+            let number_type = number_type(number);
+            self.send(&Response::CallingLineIdentification { number: number.to_string(), number_type }).await?;
+        }
+        Ok(())
+    }
+
+    /// Notifies the hands-free unit about a waiting call, if it enabled call waiting notifications.
+    pub async fn call_waiting(&mut self, number: &str) -> Result<()> {
+        if self.call_waiting {
+            let number_type = number_type(number);
+            self.send(&Response::CallWaiting { number: number.to_string(), number_type }).await?;
+        }
+        Ok(())
+    }
+
+    /// Sets the speaker gain of the hands-free unit, from 0 to [MAX_VOLUME].
+    pub async fn set_speaker_volume(&mut self, gain: u8) -> Result<()> {
+        if gain > MAX_VOLUME {
+            return Err(Error::new(ErrorKind::InvalidInput, "gain out of range"));
+        }
+        match self.headset {
+            true => self.send(&Response::Other(format!("+VGS={gain}"))).await,
+            false => self.send(&Response::SpeakerVolume(gain)).await,
+        }
+    }
+
+    /// Sets the microphone gain of the hands-free unit, from 0 to [MAX_VOLUME].
+    pub async fn set_microphone_volume(&mut self, gain: u8) -> Result<()> {
+        if gain > MAX_VOLUME {
+            return Err(Error::new(ErrorKind::InvalidInput, "gain out of range"));
+        }
+        match self.headset {
+            true => self.send(&Response::Other(format!("+VGM={gain}"))).await,
+            false => self.send(&Response::MicrophoneVolume(gain)).await,
+        }
+    }
+
+    /// Notifies the hands-free unit that voice recognition has been activated or deactivated.
+    pub async fn set_voice_recognition(&mut self, active: bool) -> Result<()> {
+        if !self.config.features.voice_recognition || !self.hf_features.voice_recognition {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.send(&Response::VoiceRecognition(active)).await
+    }
+
+    /// Notifies the hands-free unit whether the in-band ring tone is used.
+    pub async fn set_in_band_ring(&mut self, enabled: bool) -> Result<()> {
+        if !self.config.features.in_band_ring {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.send(&Response::InBandRing(enabled)).await
+    }
+
+    /// Starts codec negotiation by selecting the codec.
+    ///
+    /// The codec must be supported by both devices.
+    /// The confirmation of the hands-free unit is reported as [AudioGatewayEvent::CodecSelected].
+    pub async fn select_codec(&mut self, codec: Codec) -> Result<()> {
+        if !self.slc {
+            return Err(HfpError::NotConnected.into());
+        }
+        if !self.codec_negotiation() || !self.supports_codec(codec) || !self.hf_codecs.contains(&codec) {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.selecting = Some(codec);
+        self.send(&Response::CodecSelection(codec.id())).await
+    }
+}
+
+fn indicator_index(indicator: Indicator) -> usize {
+    Indicator::ALL.iter().position(|i| *i == indicator).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::DuplexStream;
+
+    use super::*;
+
+    async fn exchange(hf: &mut Connection<DuplexStream>, cmd: Command, rsps: &[Response]) {
+        hf.send_command(&cmd).await.unwrap();
+        for rsp in rsps {
+            assert_eq!(hf.read_response().await.unwrap().as_ref(), Some(rsp));
+        }
+    }
+
+    fn indicator_list() -> Response {
+        Response::IndicatorList(Indicator::ALL.iter().map(|i| (i.to_string(), i.range())).collect())
+    }
+
+    fn hf_features() -> HandsFreeFeatures {
+        HandsFreeFeatures { codec_negotiation: true, ..Default::default() }
+    }
+
+    /// Establishes the service level connection with a scripted hands-free unit.
+    async fn accept() -> (AudioGateway<DuplexStream>, Connection<DuplexStream>) {
+        let (local, remote) = tokio::io::duplex(4096);
+        let mut hf = Connection::new(remote);
+        let config = AudioGatewayConfig::default();
+        let ag_features = config.features.to_bits();
+
+        let script = async {
+            exchange(
+                &mut hf,
+                Command::SupportedFeatures(hf_features().to_bits()),
+                &[Response::SupportedFeatures(ag_features), Response::Ok],
+            )
+            .await;
+            exchange(&mut hf, Command::AvailableCodecs(vec![1, 2]), &[Response::Ok]).await;
+            exchange(&mut hf, Command::IndicatorsTest, &[indicator_list(), Response::Ok]).await;
+            exchange(&mut hf, Command::IndicatorsRead, &[Response::IndicatorValues(vec![0; 7]), Response::Ok])
+                .await;
+            exchange(&mut hf, Command::EventReporting(true), &[Response::Ok]).await;
+        };
+        let (ag, ()) = tokio::join!(AudioGateway::accept(local, config), script);
+        (ag.unwrap(), hf)
+    }
+
+    fn hfp_error(err: Error) -> HfpError {
+        err.get_ref().unwrap().downcast_ref::<HfpError>().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn requests_before_service_level_connection() {
+        let (local, remote) = tokio::io::duplex(4096);
+        let mut hf = Connection::new(remote);
+        let config = AudioGatewayConfig::default();
+        let ag_features = config.features.to_bits();
+
+        let script = async {
+            exchange(
+                &mut hf,
+                Command::SupportedFeatures(hf_features().to_bits()),
+                &[Response::SupportedFeatures(ag_features), Response::Ok],
+            )
+            .await;
+            exchange(&mut hf, Command::Answer, &[Response::Error]).await;
+            exchange(&mut hf, Command::AvailableCodecs(vec![1, 2]), &[Response::Ok]).await;
+            exchange(&mut hf, Command::CodecSelection(2), &[Response::Error]).await;
+            exchange(&mut hf, Command::CodecConnection, &[Response::Error]).await;
+            exchange(&mut hf, Command::IndicatorsTest, &[indicator_list(), Response::Ok]).await;
+            exchange(&mut hf, Command::Dial("123".into()), &[Response::Error]).await;
+            exchange(&mut hf, Command::IndicatorsRead, &[Response::IndicatorValues(vec![0; 7]), Response::Ok])
+                .await;
+            exchange(&mut hf, Command::SpeakerVolume(9), &[Response::Ok]).await;
+            exchange(&mut hf, Command::EventReporting(true), &[Response::Ok]).await;
+        };
+        let (ag, ()) = tokio::join!(AudioGateway::accept(local, config), script);
+        let mut ag = ag.unwrap();
+
+        assert_eq!(ag.hf_features(), hf_features());
+        assert_eq!(ag.hf_codecs(), &[Codec::Cvsd, Codec::Msbc]);
+        assert_eq!(ag.codec(), Codec::Cvsd);
+
+        // Notifications received during setup are kept.
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::SpeakerVolume(9)));
+        assert_eq!(ag.accept_request().await.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn closed_before_service_level_connection() {
+        let (local, remote) = tokio::io::duplex(4096);
+        let mut hf = Connection::new(remote);
+        let script = async {
+            exchange(&mut hf, Command::Answer, &[Response::Error]).await;
+            drop(hf);
+        };
+        let (ag, ()) = tokio::join!(AudioGateway::accept(local, AudioGatewayConfig::default()), script);
+        assert_eq!(ag.unwrap_err().kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn indicator_events() {
+        let (mut ag, mut hf) = accept().await;
+
+        ag.set_indicator(Indicator::Signal, 3).await.unwrap();
+        ag.set_indicator(Indicator::Signal, 3).await.unwrap();
+        assert_eq!(ag.set_indicator(Indicator::Call, 2).await.unwrap_err().kind(), ErrorKind::InvalidInput);
+        ag.set_indicator(Indicator::Call, 1).await.unwrap();
+        ag.set_indicator(Indicator::BatteryCharge, 5).await.unwrap();
+
+        for (index, value) in [(5, 3), (2, 1), (7, 5)] {
+            assert_eq!(hf.read_response().await.unwrap(), Some(Response::IndicatorEvent { index, value }));
+        }
+        assert_eq!(ag.indicator(Indicator::Signal), 3);
+        assert_eq!(ag.indicator(Indicator::Call), 1);
+
+        exchange(&mut hf, Command::IndicatorsRead, &[]).await;
+        exchange(&mut hf, Command::Answer, &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::Answer));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::IndicatorValues(vec![0, 1, 0, 0, 3, 0, 5])));
+    }
+
+    #[tokio::test]
+    async fn codec_negotiation() {
+        let (mut ag, mut hf) = accept().await;
+
+        ag.select_codec(Codec::Msbc).await.unwrap();
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::CodecSelection(2)));
+
+        // A confirmation of another codec is rejected.
+        exchange(&mut hf, Command::CodecSelection(1), &[]).await;
+        exchange(&mut hf, Command::SpeakerVolume(3), &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::SpeakerVolume(3)));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Error));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+        assert_eq!(ag.codec(), Codec::Cvsd);
+
+        ag.select_codec(Codec::Msbc).await.unwrap();
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::CodecSelection(2)));
+        exchange(&mut hf, Command::CodecSelection(2), &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::CodecSelected(Codec::Msbc)));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+        assert_eq!(ag.codec(), Codec::Msbc);
+
+        // Available codecs announced by the hands-free unit restrict the selection.
+        exchange(&mut hf, Command::AvailableCodecs(vec![1]), &[]).await;
+        exchange(&mut hf, Command::CodecConnection, &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::CodecConnection));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+        assert_eq!(ag.hf_codecs(), &[Codec::Cvsd]);
+        assert_eq!(hfp_error(ag.select_codec(Codec::Msbc).await.unwrap_err()), HfpError::NotSupported);
+        ag.select_codec(Codec::Cvsd).await.unwrap();
+        ag.accept_request().await.unwrap();
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::CodecSelection(1)));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+    }
+
+    #[tokio::test]
+    async fn requests() {
+        let (mut ag, mut hf) = accept().await;
+
+        exchange(&mut hf, Command::Answer, &[]).await;
+        exchange(&mut hf, Command::HangUp, &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::Answer));
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::HangUp));
+        ag.accept_request().await.unwrap();
+        assert_eq!(ag.accept_request().await.unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Error));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+
+        // Features not supported by the audio gateway are rejected.
+        exchange(&mut hf, Command::VoiceRecognition(true), &[]).await;
+        exchange(&mut hf, Command::ExtendedErrors(true), &[]).await;
+        exchange(&mut hf, Command::Redial, &[]).await;
+        assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::Redial));
+        ag.reject_request(Some(30)).await.unwrap();
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Error));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::Ok));
+        assert_eq!(hf.read_response().await.unwrap(), Some(Response::CmeError(30)));
+
+        drop(hf);
+        assert_eq!(ag.next_event().await.unwrap(), None);
+    }
+}
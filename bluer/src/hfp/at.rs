@@ -0,0 +1,785 @@
+//! AT commands and result codes.
+
+use std::{fmt, io, ops::RangeInclusive, str::FromStr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{number_type, CallHoldAction, CallState, CurrentCall, HfpError};
+
+/// Maximum length of a received line.
+const MAX_LINE_LEN: usize = 1024;
+
+/// AT command sent by the hands-free unit or headset.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Command {
+    /// `AT+BRSF`: supported features of the hands-free unit.
+    SupportedFeatures(u32),
+    /// `AT+BAC`: codec ids supported by the hands-free unit.
+    AvailableCodecs(Vec<u8>),
+    /// `AT+CIND=?`: query supported indicators.
+    IndicatorsTest,
+    /// `AT+CIND?`: query current indicator values.
+    IndicatorsRead,
+    /// `AT+CMER`: enable or disable indicator event reporting.
+    EventReporting(bool),
+    /// `AT+CHLD=?`: query supported call hold actions.
+    CallHoldTest,
+    /// `AT+CHLD`: call hold and multiparty action.
+    CallHold(CallHoldAction),
+    /// `ATA`: answer the incoming call.
+    Answer,
+    /// `AT+CHUP`: hang up or reject the call.
+    HangUp,
+    /// `ATD`: dial the phone number.
+    Dial(String),
+    /// `ATD>`: dial the number stored at the memory location.
+    DialMemory(String),
+    /// `AT+BLDN`: redial the last dialed number.
+    Redial,
+    /// `AT+VTS`: send the DTMF code.
+    Dtmf(char),
+    /// `AT+VGS`: speaker gain of the hands-free unit.
+    SpeakerVolume(u8),
+    /// `AT+VGM`: microphone gain of the hands-free unit.
+    MicrophoneVolume(u8),
+    /// `AT+CLIP`: enable or disable calling line identification notifications.
+    CallingLineIdentification(bool),
+    /// `AT+CCWA`: enable or disable call waiting notifications.
+    CallWaitingNotification(bool),
+    /// `AT+CMEE`: enable or disable extended error result codes.
+    ExtendedErrors(bool),
+    /// `AT+BVRA`: activate or deactivate voice recognition.
+    VoiceRecognition(bool),
+    /// `AT+BCC`: request the audio gateway to start codec connection setup.
+    CodecConnection,
+    /// `AT+BCS`: confirm the codec id selected by the audio gateway.
+    CodecSelection(u8),
+    /// `AT+CLCC`: query the list of current calls.
+    ListCurrentCalls,
+    /// `AT+COPS=3,0`: set the network operator name format to long alphanumeric.
+    NetworkOperatorFormat,
+    /// `AT+COPS?`: query the network operator name.
+    NetworkOperator,
+    /// `AT+CNUM`: query the subscriber number.
+    SubscriberNumber,
+    /// `AT+NREC`: enable or disable echo canceling and noise reduction of the audio gateway.
+    EcNr(bool),
+    /// `AT+CKPD=200`: headset button press.
+    KeyPress,
+    /// Other command, specified without the `AT` prefix.
+    Other(String),
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SupportedFeatures(features) => write!(f, "AT+BRSF={features}"),
+            Self::AvailableCodecs(ids) => write!(f, "AT+BAC={}", join(ids)),
+            Self::IndicatorsTest => write!(f, "AT+CIND=?"),
+            Self::IndicatorsRead => write!(f, "AT+CIND?"),
+            Self::EventReporting(enable) => write!(f, "AT+CMER=3,0,0,{}", *enable as u8),
+            Self::CallHoldTest => write!(f, "AT+CHLD=?"),
+            Self::CallHold(action) => write!(f, "AT+CHLD={action}"),
+            Self::Answer => write!(f, "ATA"),
+            Self::HangUp => write!(f, "AT+CHUP"),
+            Self::Dial(number) => write!(f, "ATD{number};"),
+            Self::DialMemory(location) => write!(f, "ATD>{location};"),
+            Self::Redial => write!(f, "AT+BLDN"),
+            Self::Dtmf(code) => write!(f, "AT+VTS={code}"),
+            Self::SpeakerVolume(gain) => write!(f, "AT+VGS={gain}"),
+            Self::MicrophoneVolume(gain) => write!(f, "AT+VGM={gain}"),
+            Self::CallingLineIdentification(enable) => write!(f, "AT+CLIP={}", *enable as u8),
+            Self::CallWaitingNotification(enable) => write!(f, "AT+CCWA={}", *enable as u8),
+            Self::ExtendedErrors(enable) => write!(f, "AT+CMEE={}", *enable as u8),
+            Self::VoiceRecognition(enable) => write!(f, "AT+BVRA={}", *enable as u8),
+            Self::CodecConnection => write!(f, "AT+BCC"),
+            Self::CodecSelection(id) => write!(f, "AT+BCS={id}"),
+            Self::ListCurrentCalls => write!(f, "AT+CLCC"),
+            Self::NetworkOperatorFormat => write!(f, "AT+COPS=3,0"),
+            Self::NetworkOperator => write!(f, "AT+COPS?"),
+            Self::SubscriberNumber => write!(f, "AT+CNUM"),
+            Self::EcNr(enable) => write!(f, "AT+NREC={}", *enable as u8),
+            Self::KeyPress => write!(f, "AT+CKPD=200"),
+            Self::Other(cmd) => write!(f, "AT{cmd}"),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = HfpError;
+
+    /// Parses an AT command line without the line terminator.
+    ///
+    /// Unknown commands are returned as [Command::Other].
+    fn from_str(line: &str) -> Result<Self, HfpError> {
+        let invalid = || HfpError::InvalidLine(line.to_string());
+        let line = line.trim();
+        if !line.get(..2).is_some_and(|at| at.eq_ignore_ascii_case("AT")) {
+            return Err(invalid());
+        }
+        let rest = &line[2..];
+        let upper = rest.to_ascii_uppercase();
+
+        if upper == "A" {
+            return Ok(Self::Answer);
+        }
+        if upper.starts_with('D') {
+            let number = rest[1..].trim_end_matches(';');
+            return Ok(match number.strip_prefix('>') {
+                Some(location) => Self::DialMemory(location.to_string()),
+                None => Self::Dial(number.to_string()),
+            });
+        }
+
+        let split = upper.find(['=', '?']).unwrap_or(upper.len());
+        let (name, suffix) = (&upper[..split], &rest[split..]);
+        let arg = suffix.strip_prefix('=');
+        let cmd = match (name, suffix, arg) {
+            ("+BRSF", _, Some(arg)) => Self::SupportedFeatures(arg.trim().parse().map_err(|_| invalid())?),
+            ("+BAC", _, Some(arg)) => Self::AvailableCodecs(
+                arg.split(',').map(|id| id.trim().parse()).collect::<Result<_, _>>().map_err(|_| invalid())?,
+            ),
+            ("+CIND", "=?", _) => Self::IndicatorsTest,
+            ("+CIND", "?", _) => Self::IndicatorsRead,
+            ("+CMER", _, Some(arg)) => Self::EventReporting(arg.split(',').nth(3).map(str::trim) == Some("1")),
+            ("+CHLD", "=?", _) => Self::CallHoldTest,
+            ("+CHLD", _, Some(arg)) => Self::CallHold(arg.trim().parse()?),
+            ("+CHUP", "", _) => Self::HangUp,
+            ("+BLDN", "", _) => Self::Redial,
+            ("+VTS", _, Some(arg)) => Self::Dtmf(arg.trim().chars().next().ok_or_else(invalid)?),
+            ("+VGS", _, Some(arg)) => Self::SpeakerVolume(arg.trim().parse().map_err(|_| invalid())?),
+            ("+VGM", _, Some(arg)) => Self::MicrophoneVolume(arg.trim().parse().map_err(|_| invalid())?),
+            ("+CLIP", _, Some(arg)) => Self::CallingLineIdentification(parse_bool(arg).ok_or_else(invalid)?),
+            ("+CCWA", _, Some(arg)) => Self::CallWaitingNotification(parse_bool(arg).ok_or_else(invalid)?),
+            ("+CMEE", _, Some(arg)) => Self::ExtendedErrors(parse_bool(arg).ok_or_else(invalid)?),
+            ("+BVRA", _, Some(arg)) => Self::VoiceRecognition(parse_bool(arg).ok_or_else(invalid)?),
+            ("+BCC", "", _) => Self::CodecConnection,
+            ("+BCS", _, Some(arg)) => Self::CodecSelection(arg.trim().parse().map_err(|_| invalid())?),
+            ("+CLCC", "", _) => Self::ListCurrentCalls,
+            ("+COPS", "=3,0", _) => Self::NetworkOperatorFormat,
+            ("+COPS", "?", _) => Self::NetworkOperator,
+            ("+CNUM", "", _) => Self::SubscriberNumber,
+            ("+NREC", _, Some(arg)) => Self::EcNr(parse_bool(arg).ok_or_else(invalid)?),
+            ("+CKPD", _, Some(_)) => Self::KeyPress,
+            _ => Self::Other(rest.to_string()),
+        };
+        Ok(cmd)
+    }
+}
+
+/// Result code sent by the audio gateway.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Response {
+    /// `OK`: command completed successfully.
+    Ok,
+    /// `ERROR`: command failed.
+    Error,
+    /// `+CME ERROR`: command failed with the extended error code.
+    CmeError(u16),
+    /// `RING`: incoming call alert.
+    Ring,
+    /// `NO CARRIER`: connection could not be established.
+    NoCarrier,
+    /// `BUSY`: remote party is busy.
+    Busy,
+    /// `NO ANSWER`: remote party did not answer.
+    NoAnswer,
+    /// `DELAYED`: call attempt delayed.
+    Delayed,
+    /// `BLACKLISTED`: call attempt refused.
+    Blacklisted,
+    /// `+BRSF`: supported features of the audio gateway.
+    SupportedFeatures(u32),
+    /// `+CIND`: names and value ranges of the supported indicators.
+    IndicatorList(Vec<(String, RangeInclusive<u8>)>),
+    /// `+CIND`: current indicator values.
+    IndicatorValues(Vec<u8>),
+    /// `+CIEV`: indicator value changed.
+    IndicatorEvent {
+        /// Index of the indicator, starting at 1.
+        index: u8,
+        /// New value.
+        value: u8,
+    },
+    /// `+CHLD`: supported call hold actions.
+    CallHoldSupport(Vec<String>),
+    /// `+CLIP`: calling line identification of an incoming call.
+    CallingLineIdentification {
+        /// Phone number.
+        number: String,
+        /// Type of the phone number.
+        number_type: u8,
+    },
+    /// `+CCWA`: call waiting notification.
+    CallWaiting {
+        /// Phone number.
+        number: String,
+        /// Type of the phone number.
+        number_type: u8,
+    },
+    /// `+VGS`: speaker gain of the hands-free unit.
+    SpeakerVolume(u8),
+    /// `+VGM`: microphone gain of the hands-free unit.
+    MicrophoneVolume(u8),
+    /// `+BCS`: codec id selected by the audio gateway.
+    CodecSelection(u8),
+    /// `+BVRA`: voice recognition activated or deactivated.
+    VoiceRecognition(bool),
+    /// `+BSIR`: in-band ring tone enabled or disabled.
+    InBandRing(bool),
+    /// `+CLCC`: entry of the list of current calls.
+    CurrentCall(CurrentCall),
+    /// `+COPS`: network operator name.
+    NetworkOperator(Option<String>),
+    /// `+CNUM`: subscriber number.
+    SubscriberNumber {
+        /// Phone number.
+        number: String,
+        /// Type of the phone number.
+        number_type: u8,
+    },
+    /// Other result code.
+    Other(String),
+}
+
+impl Response {
+    /// Whether this is an unsolicited result code, i.e. not sent in response to a command.
+    pub fn is_unsolicited(&self) -> bool {
+        matches!(
+            self,
+            Self::Ring
+                | Self::IndicatorEvent { .. }
+                | Self::CallingLineIdentification { .. }
+                | Self::CallWaiting { .. }
+                | Self::SpeakerVolume(_)
+                | Self::MicrophoneVolume(_)
+                | Self::CodecSelection(_)
+                | Self::VoiceRecognition(_)
+                | Self::InBandRing(_)
+        )
+    }
+
+    /// Whether this is a final result code, terminating the response to a command.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Ok
+                | Self::Error
+                | Self::CmeError(_)
+                | Self::NoCarrier
+                | Self::Busy
+                | Self::NoAnswer
+                | Self::Delayed
+                | Self::Blacklisted
+        )
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "OK"),
+            Self::Error => write!(f, "ERROR"),
+            Self::CmeError(code) => write!(f, "+CME ERROR: {code}"),
+            Self::Ring => write!(f, "RING"),
+            Self::NoCarrier => write!(f, "NO CARRIER"),
+            Self::Busy => write!(f, "BUSY"),
+            Self::NoAnswer => write!(f, "NO ANSWER"),
+            Self::Delayed => write!(f, "DELAYED"),
+            Self::Blacklisted => write!(f, "BLACKLISTED"),
+            Self::SupportedFeatures(features) => write!(f, "+BRSF: {features}"),
+            Self::IndicatorList(indicators) => {
+                let indicators: Vec<_> = indicators
+                    .iter()
+                    .map(|(name, range)| {
+                        if range.end().saturating_sub(*range.start()) == 1 {
+                            format!("(\"{name}\",({},{}))", range.start(), range.end())
+                        } else {
+                            format!("(\"{name}\",({}-{}))", range.start(), range.end())
+                        }
+                    })
+                    .collect();
+                write!(f, "+CIND: {}", indicators.join(","))
+            }
+            Self::IndicatorValues(values) => write!(f, "+CIND: {}", join(values)),
+            Self::IndicatorEvent { index, value } => write!(f, "+CIEV: {index},{value}"),
+            Self::CallHoldSupport(actions) => write!(f, "+CHLD: ({})", actions.join(",")),
+            Self::CallingLineIdentification { number, number_type } => {
+                write!(f, "+CLIP: \"{number}\",{number_type}")
+            }
+            Self::CallWaiting { number, number_type } => write!(f, "+CCWA: \"{number}\",{number_type},1"),
+            Self::SpeakerVolume(gain) => write!(f, "+VGS: {gain}"),
+            Self::MicrophoneVolume(gain) => write!(f, "+VGM: {gain}"),
+            Self::CodecSelection(id) => write!(f, "+BCS: {id}"),
+            Self::VoiceRecognition(active) => write!(f, "+BVRA: {}", *active as u8),
+            Self::InBandRing(enabled) => write!(f, "+BSIR: {}", *enabled as u8),
+            Self::CurrentCall(call) => {
+                write!(
+                    f,
+                    "+CLCC: {},{},{},{},{}",
+                    call.index,
+                    call.outgoing as u8,
+                    call.state.value(),
+                    call.mode,
+                    call.multiparty as u8
+                )?;
+                if let Some(number) = &call.number {
+                    write!(f, ",\"{number}\",{}", number_type(number))?;
+                }
+                Ok(())
+            }
+            Self::NetworkOperator(Some(name)) => write!(f, "+COPS: 0,0,\"{name}\""),
+            Self::NetworkOperator(None) => write!(f, "+COPS: 0"),
+            Self::SubscriberNumber { number, number_type } => write!(f, "+CNUM: ,\"{number}\",{number_type},,4"),
+            Self::Other(line) => write!(f, "{line}"),
+        }
+    }
+}
+
+impl FromStr for Response {
+    type Err = HfpError;
+
+    /// Parses a result code line without the line terminators.
+    ///
+    /// Unknown result codes are returned as [Response::Other].
+    fn from_str(line: &str) -> Result<Self, HfpError> {
+        let invalid = || HfpError::InvalidLine(line.to_string());
+        let line = line.trim();
+        let rsp = match line {
+            "OK" => Self::Ok,
+            "ERROR" => Self::Error,
+            "RING" => Self::Ring,
+            "NO CARRIER" => Self::NoCarrier,
+            "BUSY" => Self::Busy,
+            "NO ANSWER" => Self::NoAnswer,
+            "DELAYED" => Self::Delayed,
+            "BLACKLISTED" => Self::Blacklisted,
+            _ => {
+                let Some(split) = line.find([':', '=']).filter(|_| line.starts_with('+')) else {
+                    return Ok(Self::Other(line.to_string()));
+                };
+                let (name, arg) = (&line[..split], line[split + 1..].trim());
+                let args = split_args(arg);
+                let num = |n: usize| args.get(n).and_then(|v| v.parse::<u8>().ok()).ok_or_else(invalid);
+                let number_type = |n: usize| args.get(n).and_then(|v| v.parse().ok()).unwrap_or(129);
+                match name {
+                    "+CME ERROR" => Self::CmeError(arg.parse().map_err(|_| invalid())?),
+                    "+BRSF" => Self::SupportedFeatures(arg.parse().map_err(|_| invalid())?),
+                    "+CIND" if arg.starts_with('(') => {
+                        Self::IndicatorList(parse_indicator_list(arg).ok_or_else(invalid)?)
+                    }
+                    "+CIND" => Self::IndicatorValues(
+                        args.iter().map(|v| v.parse()).collect::<Result<_, _>>().map_err(|_| invalid())?,
+                    ),
+                    "+CIEV" => Self::IndicatorEvent { index: num(0)?, value: num(1)? },
+                    "+CHLD" => Self::CallHoldSupport(
+                        split_args(arg.trim_start_matches('(').trim_end_matches(')'))
+                            .into_iter()
+                            .filter(|action| !action.is_empty())
+                            .collect(),
+                    ),
+                    "+CLIP" => Self::CallingLineIdentification {
+                        number: args.first().cloned().unwrap_or_default(),
+                        number_type: number_type(1),
+                    },
+                    "+CCWA" => Self::CallWaiting {
+                        number: args.first().cloned().unwrap_or_default(),
+                        number_type: number_type(1),
+                    },
+                    "+VGS" => Self::SpeakerVolume(num(0)?),
+                    "+VGM" => Self::MicrophoneVolume(num(0)?),
+                    "+BCS" => Self::CodecSelection(num(0)?),
+                    "+BVRA" => Self::VoiceRecognition(num(0)? != 0),
+                    "+BSIR" => Self::InBandRing(num(0)? != 0),
+                    "+CLCC" => Self::CurrentCall(CurrentCall {
+                        index: num(0)?,
+                        outgoing: num(1)? != 0,
+                        state: CallState::from_value(num(2)?).ok_or_else(invalid)?,
+                        mode: num(3)?,
+                        multiparty: num(4)? != 0,
+                        number: args.get(5).filter(|number| !number.is_empty()).cloned(),
+                    }),
+                    "+COPS" => Self::NetworkOperator(args.get(2).cloned()),
+                    "+CNUM" => Self::SubscriberNumber {
+                        number: args.get(1).cloned().ok_or_else(invalid)?,
+                        number_type: number_type(2),
+                    },
+                    _ => Self::Other(line.to_string()),
+                }
+            }
+        };
+        Ok(rsp)
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn parse_bool(arg: &str) -> Option<bool> {
+    match arg.split(',').next()?.trim() {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+/// Splits comma-separated arguments, removing quotes.
+fn split_args(arg: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in arg.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => args.push(std::mem::take(&mut current).trim().to_string()),
+            c => current.push(c),
+        }
+    }
+    args.push(current.trim().to_string());
+    args
+}
+
+/// Splits at top-level commas, i.e. those outside of quotes and parentheses.
+fn split_top_level(arg: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in arg.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                parts.push(arg[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(arg[start..].trim());
+    parts
+}
+
+/// Parses an indicator list of the form `("service",(0,1)),("callsetup",(0-3))`.
+fn parse_indicator_list(arg: &str) -> Option<Vec<(String, RangeInclusive<u8>)>> {
+    split_top_level(arg)
+        .into_iter()
+        .map(|item| {
+            let item = item.strip_prefix('(')?.strip_suffix(')')?;
+            let (name, range) = match split_top_level(item)[..] {
+                [name, range] => (name, range),
+                _ => return None,
+            };
+            let name = name.trim_matches('"').to_string();
+            let values: Vec<u8> = range
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .split([',', '-'])
+                .map(|v| v.trim().parse().ok())
+                .collect::<Option<_>>()?;
+            Some((name, *values.iter().min()?..=*values.iter().max()?))
+        })
+        .collect()
+}
+
+/// Line-based AT connection.
+pub(crate) struct Connection<S> {
+    stream: S,
+    buf: Vec<u8>,
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(stream: S) -> Self {
+        Self { stream, buf: Vec::new() }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Reads the next non-empty line.
+    ///
+    /// Returns [None] when the connection has been closed.
+    async fn read_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(pos) = self.buf.iter().position(|b| *b == b'\r' || *b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line[..pos]);
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                log::trace!("AT recv: {line}");
+                return Ok(Some(line.to_string()));
+            }
+            if self.buf.len() > MAX_LINE_LEN {
+                return Err(HfpError::LineTooLong.into());
+            }
+
+            let mut chunk = [0; 256];
+            let n = self.stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    async fn write_line(&mut self, line: &str) -> io::Result<()> {
+        log::trace!("AT send: {}", line.trim());
+        self.stream.write_all(line.as_bytes()).await?;
+        self.stream.flush().await
+    }
+
+    /// Reads the next command.
+    ///
+    /// Lines that are not AT commands are returned as errors.
+    pub async fn read_command(&mut self) -> io::Result<Option<Result<Command, HfpError>>> {
+        Ok(self.read_line().await?.map(|line| line.parse()))
+    }
+
+    /// Reads the next result code, skipping malformed lines.
+    pub async fn read_response(&mut self) -> io::Result<Option<Response>> {
+        loop {
+            let Some(line) = self.read_line().await? else { return Ok(None) };
+            match line.parse() {
+                Ok(rsp) => return Ok(Some(rsp)),
+                Err(err) => log::debug!("Ignoring malformed result code: {err}"),
+            }
+        }
+    }
+
+    pub async fn send_command(&mut self, cmd: &Command) -> io::Result<()> {
+        self.write_line(&format!("{cmd}\r")).await
+    }
+
+    pub async fn send_response(&mut self, rsp: &Response) -> io::Result<()> {
+        self.write_line(&format!("\r\n{rsp}\r\n")).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(line: &str, cmd: Command) {
+        assert_eq!(line.parse::<Command>().as_ref(), Ok(&cmd), "{line}");
+        assert_eq!(cmd.to_string(), line);
+    }
+
+    fn response(line: &str, rsp: Response) {
+        assert_eq!(line.parse::<Response>().as_ref(), Ok(&rsp), "{line}");
+        assert_eq!(rsp.to_string(), line);
+    }
+
+    #[test]
+    fn service_level_connection_commands() {
+        command("AT+BRSF=1023", Command::SupportedFeatures(1023));
+        command("AT+BAC=1,2", Command::AvailableCodecs(vec![1, 2]));
+        command("AT+CIND=?", Command::IndicatorsTest);
+        command("AT+CIND?", Command::IndicatorsRead);
+        command("AT+CMER=3,0,0,1", Command::EventReporting(true));
+        command("AT+CMER=3,0,0,0", Command::EventReporting(false));
+        command("AT+CHLD=?", Command::CallHoldTest);
+        command("AT+CMEE=1", Command::ExtendedErrors(true));
+        command("AT+CLIP=1", Command::CallingLineIdentification(true));
+        command("AT+CCWA=0", Command::CallWaitingNotification(false));
+        command("AT+NREC=0", Command::EcNr(false));
+        command("AT+COPS=3,0", Command::NetworkOperatorFormat);
+        command("AT+COPS?", Command::NetworkOperator);
+        command("AT+CNUM", Command::SubscriberNumber);
+        command("AT+CLCC", Command::ListCurrentCalls);
+    }
+
+    #[test]
+    fn call_commands() {
+        command("ATA", Command::Answer);
+        command("AT+CHUP", Command::HangUp);
+        command("ATD+4915112345678;", Command::Dial("+4915112345678".to_string()));
+        command("ATD>1;", Command::DialMemory("1".to_string()));
+        command("AT+BLDN", Command::Redial);
+        command("AT+VTS=#", Command::Dtmf('#'));
+        command("AT+CHLD=1", Command::CallHold(CallHoldAction::ReleaseActiveAcceptOther));
+        command("AT+CHLD=12", Command::CallHold(CallHoldAction::ReleaseCall(2)));
+        command("AT+CHLD=21", Command::CallHold(CallHoldAction::PrivateConsultation(1)));
+        command("AT+VGS=15", Command::SpeakerVolume(15));
+        command("AT+VGM=8", Command::MicrophoneVolume(8));
+        command("AT+BVRA=1", Command::VoiceRecognition(true));
+        command("AT+BCC", Command::CodecConnection);
+        command("AT+BCS=2", Command::CodecSelection(2));
+        command("AT+CKPD=200", Command::KeyPress);
+        command("AT+XAPL=ABCD-1234-0100,10", Command::Other("+XAPL=ABCD-1234-0100,10".to_string()));
+    }
+
+    #[test]
+    fn lenient_commands() {
+        assert_eq!("at+brsf=63".parse(), Ok(Command::SupportedFeatures(63)));
+        assert_eq!("AT+VGS= 7".parse(), Ok(Command::SpeakerVolume(7)));
+        assert_eq!("ATD123".parse(), Ok(Command::Dial("123".to_string())));
+        assert_eq!("AT+CKPD=\"e\"".parse(), Ok(Command::KeyPress));
+        assert_eq!("AT+CLIP=1,0".parse(), Ok(Command::CallingLineIdentification(true)));
+    }
+
+    #[test]
+    fn invalid_commands() {
+        for line in [
+            "",
+            "A",
+            "BRSF=1",
+            "+BRSF=1",
+            "AT+BRSF=x",
+            "AT+BAC=1,",
+            "AT+VGS=",
+            "AT+VTS=",
+            "AT+CLIP=2",
+            "AT+CHLD=5",
+        ] {
+            assert!(line.parse::<Command>().is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn final_result_codes() {
+        response("OK", Response::Ok);
+        response("ERROR", Response::Error);
+        response("+CME ERROR: 30", Response::CmeError(30));
+        response("NO CARRIER", Response::NoCarrier);
+        response("BUSY", Response::Busy);
+        response("NO ANSWER", Response::NoAnswer);
+        response("DELAYED", Response::Delayed);
+        response("BLACKLISTED", Response::Blacklisted);
+        assert!(Response::CmeError(30).is_final());
+        assert!(!Response::Ring.is_final());
+    }
+
+    #[test]
+    fn indicators() {
+        let line = "+CIND: (\"service\",(0,1)),(\"call\",(0,1)),(\"callsetup\",(0-3)),(\"callheld\",(0-2)),\
+                    (\"signal\",(0-5)),(\"roam\",(0,1)),(\"battchg\",(0-5))";
+        response(
+            line,
+            Response::IndicatorList(vec![
+                ("service".to_string(), 0..=1),
+                ("call".to_string(), 0..=1),
+                ("callsetup".to_string(), 0..=3),
+                ("callheld".to_string(), 0..=2),
+                ("signal".to_string(), 0..=5),
+                ("roam".to_string(), 0..=1),
+                ("battchg".to_string(), 0..=5),
+            ]),
+        );
+        response("+CIND: 1,0,0,0,4,0,3", Response::IndicatorValues(vec![1, 0, 0, 0, 4, 0, 3]));
+        response("+CIEV: 3,1", Response::IndicatorEvent { index: 3, value: 1 });
+        assert!(Response::IndicatorEvent { index: 3, value: 1 }.is_unsolicited());
+
+        // Ranges given as lists of values.
+        assert_eq!(
+            "+CIND: (\"call\",(0,1)),(\"signal\",(0,1,2,3,4,5))".parse(),
+            Ok(Response::IndicatorList(vec![("call".to_string(), 0..=1), ("signal".to_string(), 0..=5)]))
+        );
+    }
+
+    #[test]
+    fn call_responses() {
+        response("+BRSF: 871", Response::SupportedFeatures(871));
+        response(
+            "+CHLD: (0,1,1x,2,2x,3,4)",
+            Response::CallHoldSupport(["0", "1", "1x", "2", "2x", "3", "4"].map(String::from).to_vec()),
+        );
+        response("RING", Response::Ring);
+        response(
+            "+CLIP: \"+4915112345678\",145",
+            Response::CallingLineIdentification { number: "+4915112345678".to_string(), number_type: 145 },
+        );
+        response(
+            "+CCWA: \"0301234\",129,1",
+            Response::CallWaiting { number: "0301234".to_string(), number_type: 129 },
+        );
+        response(
+            "+CLCC: 1,0,0,0,0,\"+4915112345678\",145",
+            Response::CurrentCall(CurrentCall {
+                index: 1,
+                outgoing: false,
+                state: CallState::Active,
+                mode: 0,
+                multiparty: false,
+                number: Some("+4915112345678".to_string()),
+            }),
+        );
+        response(
+            "+CLCC: 2,1,1,0,1",
+            Response::CurrentCall(CurrentCall {
+                index: 2,
+                outgoing: true,
+                state: CallState::Held,
+                mode: 0,
+                multiparty: true,
+                number: None,
+            }),
+        );
+        response("+COPS: 0,0,\"Operator\"", Response::NetworkOperator(Some("Operator".to_string())));
+        response("+COPS: 0", Response::NetworkOperator(None));
+        response(
+            "+CNUM: ,\"+4930123456\",145,,4",
+            Response::SubscriberNumber { number: "+4930123456".to_string(), number_type: 145 },
+        );
+    }
+
+    #[test]
+    fn audio_responses() {
+        response("+VGS: 12", Response::SpeakerVolume(12));
+        response("+VGM: 9", Response::MicrophoneVolume(9));
+        response("+BCS: 2", Response::CodecSelection(2));
+        response("+BVRA: 1", Response::VoiceRecognition(true));
+        response("+BSIR: 0", Response::InBandRing(false));
+        assert_eq!("+VGS=12".parse(), Ok(Response::SpeakerVolume(12)));
+        assert!(Response::CodecSelection(2).is_unsolicited());
+    }
+
+    #[test]
+    fn other_responses() {
+        response("+XAPL=iPhone,6", Response::Other("+XAPL=iPhone,6".to_string()));
+        response("+BIND: 1,1", Response::Other("+BIND: 1,1".to_string()));
+        response("CONNECT", Response::Other("CONNECT".to_string()));
+        for line in ["+CIEV: x,1", "+VGS: 256", "+BRSF: -1", "+CLCC: 1,0,9,0,0", "+CNUM: ", "+CIND: (\"call\")"] {
+            assert!(line.parse::<Response>().is_err(), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn connection() {
+        let (local, mut remote) = tokio::io::duplex(4096);
+        let mut conn = Connection::new(local);
+
+        remote.write_all(b"\r\n+BRSF: 871\r\n\r\nOK\r\nAT+VGS=5\r\n+CIEV: x\r\n\r\nRING\r\n").await.unwrap();
+        assert_eq!(conn.read_response().await.unwrap(), Some(Response::SupportedFeatures(871)));
+        assert_eq!(conn.read_response().await.unwrap(), Some(Response::Ok));
+        assert_eq!(conn.read_command().await.unwrap(), Some(Ok(Command::SpeakerVolume(5))));
+        assert_eq!(conn.read_response().await.unwrap(), Some(Response::Ring));
+
+        conn.send_command(&Command::HangUp).await.unwrap();
+        conn.send_response(&Response::Ok).await.unwrap();
+        let mut buf = [0; 14];
+        remote.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"AT+CHUP\r\r\nOK\r\n");
+
+        remote.write_all(&[b'x'; MAX_LINE_LEN + 1]).await.unwrap();
+        let err = conn.read_response().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let (local, remote) = tokio::io::duplex(64);
+        let mut conn = Connection::new(local);
+        drop(remote);
+        assert_eq!(conn.read_response().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,661 @@
+//! Hands-free unit role.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{
+    at::Connection, AudioGatewayFeatures, CallHoldAction, Codec, Command, CurrentCall, HandsFreeFeatures,
+    HfpError, Indicator, Response, MAX_VOLUME,
+};
+use crate::rfcomm::Stream;
+
+/// Configuration of the hands-free unit.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HandsFreeConfig {
+    /// Supported features announced to the audio gateway.
+    pub features: HandsFreeFeatures,
+    /// Supported voice codecs used for codec negotiation.
+    ///
+    /// CVSD is always supported.
+    pub codecs: Vec<Codec>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for HandsFreeConfig {
+    fn default() -> Self {
+        Self {
+            features: HandsFreeFeatures {
+                cli_presentation: true,
+                remote_volume_control: true,
+                enhanced_call_status: true,
+                codec_negotiation: true,
+                ..Default::default()
+            },
+            codecs: vec![Codec::Cvsd, Codec::Msbc],
+            _non_exhaustive: (),
+        }
+    }
+}
+
+/// Event reported by the audio gateway.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HandsFreeEvent {
+    /// Value of an indicator changed.
+    Indicator {
+        /// Indicator.
+        indicator: Indicator,
+        /// New value.
+        value: u8,
+    },
+    /// Incoming call alert.
+    Ring,
+    /// Phone number of the incoming call.
+    CallingLineIdentification {
+        /// Phone number.
+        number: String,
+    },
+    /// Another call is waiting.
+    CallWaiting {
+        /// Phone number of the waiting call.
+        number: String,
+    },
+    /// Speaker gain set by the audio gateway.
+    SpeakerVolume(u8),
+    /// Microphone gain set by the audio gateway.
+    MicrophoneVolume(u8),
+    /// Voice codec has been selected by codec negotiation.
+    ///
+    /// The voice connection should be established using this codec.
+    CodecSelected(Codec),
+    /// Voice recognition has been activated or deactivated.
+    VoiceRecognition(bool),
+    /// In-band ring tone has been enabled or disabled.
+    InBandRing(bool),
+    /// Other unsolicited result code.
+    Other(Response),
+}
+
+/// Hands-free unit connected to an audio gateway.
+///
+/// The service level connection is established by [HandsFree::connect].
+/// Unsolicited result codes sent by the audio gateway are obtained using
+/// [HandsFree::next_event]; codec selections are confirmed automatically.
+///
+/// Commands are executed one after another, as required by the protocol.
+/// Unsolicited result codes received while waiting for the response to a
+/// command are kept and returned by the next call to [HandsFree::next_event].
+///
+/// Errors returned by the audio gateway and protocol violations are returned
+/// as the inner [HfpError] of the [std::io::Error].
+pub struct HandsFree<S = Stream> {
+    conn: Connection<S>,
+    config: HandsFreeConfig,
+    headset: bool,
+    ag_features: AudioGatewayFeatures,
+    indicators: Vec<Option<Indicator>>,
+    values: BTreeMap<Indicator, u8>,
+    call_hold_actions: Vec<String>,
+    codec: Codec,
+    pending: VecDeque<Command>,
+    events: VecDeque<HandsFreeEvent>,
+}
+
+impl<S> fmt::Debug for HandsFree<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandsFree")
+            .field("headset", &self.headset)
+            .field("ag_features", &self.ag_features)
+            .field("indicators", &self.values)
+            .field("codec", &self.codec)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> HandsFree<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(stream: S, config: HandsFreeConfig, headset: bool) -> Self {
+        Self {
+            conn: Connection::new(stream),
+            config,
+            headset,
+            ag_features: AudioGatewayFeatures::default(),
+            indicators: Vec::new(),
+            values: BTreeMap::new(),
+            call_hold_actions: Vec::new(),
+            codec: Codec::Cvsd,
+            pending: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Establishes the service level connection of the hands-free profile
+    /// with the audio gateway connected by the RFCOMM stream.
+    ///
+    /// This exchanges the supported features and codecs, retrieves the
+    /// indicators and enables indicator event reporting.
+    /// Afterwards calling line identification, call waiting notifications
+    /// and extended error result codes are enabled if supported.
+    pub async fn connect(stream: S, config: HandsFreeConfig) -> Result<Self> {
+        let mut this = Self::new(stream, config, false);
+
+        let rsps = this.exchange(&Command::SupportedFeatures(this.config.features.to_bits())).await?;
+        let features = rsps
+            .iter()
+            .find_map(|rsp| match rsp {
+                Response::SupportedFeatures(features) => Some(*features),
+                _ => None,
+            })
+            .ok_or_else(|| unexpected(&rsps))?;
+        this.ag_features = AudioGatewayFeatures::from_bits(features);
+
+        if this.codec_negotiation() {
+            this.exchange(&Command::AvailableCodecs(this.codec_ids())).await?;
+        }
+
+        let rsps = this.exchange(&Command::IndicatorsTest).await?;
+        let Some(Response::IndicatorList(indicators)) = rsps.first() else { return Err(unexpected(&rsps)) };
+        this.indicators = indicators.iter().map(|(name, _)| name.parse().ok()).collect();
+
+        let rsps = this.exchange(&Command::IndicatorsRead).await?;
+        let Some(Response::IndicatorValues(values)) = rsps.first() else { return Err(unexpected(&rsps)) };
+        this.values = this
+            .indicators
+            .iter()
+            .zip(values)
+            .filter_map(|(indicator, value)| indicator.map(|indicator| (indicator, *value)))
+            .collect();
+
+        this.exchange(&Command::EventReporting(true)).await?;
+
+        let three_way = this.config.features.three_way_calling && this.ag_features.three_way_calling;
+        if three_way {
+            let rsps = this.exchange(&Command::CallHoldTest).await?;
+            let Some(Response::CallHoldSupport(actions)) = rsps.first() else { return Err(unexpected(&rsps)) };
+            this.call_hold_actions = actions.clone();
+        }
+
+        if this.config.features.cli_presentation {
+            this.exchange(&Command::CallingLineIdentification(true)).await?;
+        }
+        if three_way {
+            this.exchange(&Command::CallWaitingNotification(true)).await?;
+        }
+        if this.ag_features.extended_error_codes {
+            this.exchange(&Command::ExtendedErrors(true)).await?;
+        }
+
+        this.flush_pending().await?;
+        Ok(this)
+    }
+
+    /// Uses the RFCOMM stream connected to an audio gateway of the headset profile.
+    ///
+    /// The headset profile has no service level connection setup and supports
+    /// only [button presses](Self::button_press), ring alerts and volume control.
+    pub fn headset(stream: S) -> Self {
+        Self::new(stream, HandsFreeConfig::default(), true)
+    }
+
+    /// Supported features of the audio gateway.
+    pub fn ag_features(&self) -> AudioGatewayFeatures {
+        self.ag_features
+    }
+
+    /// Current value of the indicator.
+    ///
+    /// Returns [None] if the audio gateway does not provide the indicator.
+    pub fn indicator(&self, indicator: Indicator) -> Option<u8> {
+        self.values.get(&indicator).copied()
+    }
+
+    /// Call hold and multiparty actions supported by the audio gateway, for example `1x`.
+    pub fn call_hold_actions(&self) -> &[String] {
+        &self.call_hold_actions
+    }
+
+    /// Voice codec selected by the last codec negotiation.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Gets a reference to the underlying RFCOMM stream.
+    pub fn get_ref(&self) -> &S {
+        self.conn.get_ref()
+    }
+
+    /// Consumes this and returns the underlying RFCOMM stream.
+    pub fn into_inner(self) -> S {
+        self.conn.into_inner()
+    }
+
+    fn codec_negotiation(&self) -> bool {
+        !self.headset && self.config.features.codec_negotiation && self.ag_features.codec_negotiation
+    }
+
+    fn codec_ids(&self) -> Vec<u8> {
+        let mut ids: Vec<_> = self.config.codecs.iter().map(|codec| codec.id()).collect();
+        ids.push(Codec::Cvsd.id());
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Waits for the next event reported by the audio gateway.
+    ///
+    /// Returns [None] when the connection has been closed.
+    pub async fn next_event(&mut self) -> Result<Option<HandsFreeEvent>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            self.flush_pending().await?;
+            if !self.events.is_empty() {
+                continue;
+            }
+
+            match self.conn.read_response().await? {
+                Some(rsp) if rsp.is_unsolicited() => self.handle_unsolicited(rsp),
+                Some(rsp) => self.events.push_back(HandsFreeEvent::Other(rsp)),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Sends the command and returns the information responses on success.
+    ///
+    /// Unsolicited result codes received in the meantime are reported by
+    /// [HandsFree::next_event].
+    pub async fn command(&mut self, cmd: &Command) -> Result<Vec<Response>> {
+        let result = self.exchange(cmd).await;
+        self.flush_pending().await?;
+        result
+    }
+
+    /// Sends the command and waits for its final result code.
+    async fn exchange(&mut self, cmd: &Command) -> Result<Vec<Response>> {
+        self.conn.send_command(cmd).await?;
+
+        let mut rsps = Vec::new();
+        loop {
+            let rsp = self
+                .conn
+                .read_response()
+                .await?
+                .ok_or_else(|| Error::new(ErrorKind::ConnectionReset, "HFP connection closed"))?;
+            match rsp {
+                Response::Ok => return Ok(rsps),
+                Response::Error => return Err(HfpError::Error.into()),
+                Response::CmeError(code) => return Err(HfpError::CmeError(code).into()),
+                rsp if rsp.is_final() => return Err(HfpError::UnexpectedResponse(rsp.to_string()).into()),
+                rsp if rsp.is_unsolicited() => self.handle_unsolicited(rsp),
+                rsp => rsps.push(rsp),
+            }
+        }
+    }
+
+    /// Sends commands required by received unsolicited result codes.
+    async fn flush_pending(&mut self) -> Result<()> {
+        while let Some(cmd) = self.pending.pop_front() {
+            match self.exchange(&cmd).await {
+                Ok(_) => {
+                    if let Command::CodecSelection(id) = cmd {
+                        if let Some(codec) = Codec::from_id(id) {
+                            self.codec = codec;
+                            self.events.push_back(HandsFreeEvent::CodecSelected(codec));
+                        }
+                    }
+                }
+                Err(err) if err.get_ref().is_some_and(|inner| inner.is::<HfpError>()) => {
+                    log::warn!("Audio gateway rejected {cmd}: {err}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_unsolicited(&mut self, rsp: Response) {
+        let event = match rsp {
+            Response::IndicatorEvent { index, value } => {
+                let indicator =
+                    index.checked_sub(1).and_then(|idx| self.indicators.get(idx as usize).copied().flatten());
+                match indicator {
+                    Some(indicator) => {
+                        self.values.insert(indicator, value);
+                        HandsFreeEvent::Indicator { indicator, value }
+                    }
+                    None => {
+                        log::debug!("Ignoring event of unknown indicator {index}");
+                        return;
+                    }
+                }
+            }
+            Response::Ring => HandsFreeEvent::Ring,
+            Response::CallingLineIdentification { number, .. } => {
+                HandsFreeEvent::CallingLineIdentification { number }
+            }
+            Response::CallWaiting { number, .. } => HandsFreeEvent::CallWaiting { number },
+            Response::SpeakerVolume(gain) => HandsFreeEvent::SpeakerVolume(gain),
+            Response::MicrophoneVolume(gain) => HandsFreeEvent::MicrophoneVolume(gain),
+            Response::VoiceRecognition(active) => HandsFreeEvent::VoiceRecognition(active),
+            Response::InBandRing(enabled) => HandsFreeEvent::InBandRing(enabled),
+            Response::CodecSelection(id) => {
+                if self.codec_ids().contains(&id) {
+                    self.pending.push_back(Command::CodecSelection(id));
+                } else {
+                    self.pending.push_back(Command::AvailableCodecs(self.codec_ids()));
+                }
+                return;
+            }
+            rsp => HandsFreeEvent::Other(rsp),
+        };
+        self.events.push_back(event);
+    }
+
+    /// Answers the incoming call.
+    pub async fn answer(&mut self) -> Result<()> {
+        self.command(&Command::Answer).await?;
+        Ok(())
+    }
+
+    /// Hangs up the active call or rejects the incoming call.
+    pub async fn hang_up(&mut self) -> Result<()> {
+        self.command(&Command::HangUp).await?;
+        Ok(())
+    }
+
+    /// Places a call to the phone number.
+    pub async fn dial(&mut self, number: &str) -> Result<()> {
+        self.command(&Command::Dial(number.to_string())).await?;
+        Ok(())
+    }
+
+    /// Places a call to the number stored at the memory location of the audio gateway.
+    pub async fn dial_memory(&mut self, location: &str) -> Result<()> {
+        self.command(&Command::DialMemory(location.to_string())).await?;
+        Ok(())
+    }
+
+    /// Places a call to the last dialed number.
+    pub async fn redial(&mut self) -> Result<()> {
+        self.command(&Command::Redial).await?;
+        Ok(())
+    }
+
+    /// Sends the DTMF code, which must be one of `0-9`, `*`, `#` and `A-D`, during a call.
+    pub async fn send_dtmf(&mut self, code: char) -> Result<()> {
+        if !matches!(code, '0'..='9' | '*' | '#' | 'A'..='D') {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid DTMF code"));
+        }
+        self.command(&Command::Dtmf(code)).await?;
+        Ok(())
+    }
+
+    /// Informs the audio gateway about the speaker gain, from 0 to [MAX_VOLUME].
+    pub async fn set_speaker_volume(&mut self, gain: u8) -> Result<()> {
+        if gain > MAX_VOLUME {
+            return Err(Error::new(ErrorKind::InvalidInput, "gain out of range"));
+        }
+        self.command(&Command::SpeakerVolume(gain)).await?;
+        Ok(())
+    }
+
+    /// Informs the audio gateway about the microphone gain, from 0 to [MAX_VOLUME].
+    pub async fn set_microphone_volume(&mut self, gain: u8) -> Result<()> {
+        if gain > MAX_VOLUME {
+            return Err(Error::new(ErrorKind::InvalidInput, "gain out of range"));
+        }
+        self.command(&Command::MicrophoneVolume(gain)).await?;
+        Ok(())
+    }
+
+    /// Activates or deactivates voice recognition of the audio gateway.
+    pub async fn set_voice_recognition(&mut self, active: bool) -> Result<()> {
+        if !self.ag_features.voice_recognition {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.command(&Command::VoiceRecognition(active)).await?;
+        Ok(())
+    }
+
+    /// Performs the call hold and multiparty action.
+    pub async fn call_hold(&mut self, action: CallHoldAction) -> Result<()> {
+        if self.call_hold_actions.is_empty() {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.command(&Command::CallHold(action)).await?;
+        Ok(())
+    }
+
+    /// Requests the audio gateway to perform codec negotiation and establish
+    /// the voice connection.
+    ///
+    /// The selected codec is reported as [HandsFreeEvent::CodecSelected].
+    pub async fn request_codec_connection(&mut self) -> Result<()> {
+        if !self.codec_negotiation() {
+            return Err(HfpError::NotSupported.into());
+        }
+        self.command(&Command::CodecConnection).await?;
+        Ok(())
+    }
+
+    /// Queries the list of current calls.
+    pub async fn current_calls(&mut self) -> Result<Vec<CurrentCall>> {
+        let rsps = self.command(&Command::ListCurrentCalls).await?;
+        Ok(rsps
+            .into_iter()
+            .filter_map(|rsp| match rsp {
+                Response::CurrentCall(call) => Some(call),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Queries the name of the network operator.
+    pub async fn network_operator(&mut self) -> Result<Option<String>> {
+        self.command(&Command::NetworkOperatorFormat).await?;
+        let rsps = self.command(&Command::NetworkOperator).await?;
+        Ok(rsps.into_iter().find_map(|rsp| match rsp {
+            Response::NetworkOperator(name) => name,
+            _ => None,
+        }))
+    }
+
+    /// Queries the phone numbers of the subscriber.
+    pub async fn subscriber_numbers(&mut self) -> Result<Vec<String>> {
+        let rsps = self.command(&Command::SubscriberNumber).await?;
+        Ok(rsps
+            .into_iter()
+            .filter_map(|rsp| match rsp {
+                Response::SubscriberNumber { number, .. } => Some(number),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Sends a button press of the headset profile.
+    pub async fn button_press(&mut self) -> Result<()> {
+        self.command(&Command::KeyPress).await?;
+        Ok(())
+    }
+}
+
+fn unexpected(rsps: &[Response]) -> Error {
+    let rsps: Vec<_> = rsps.iter().map(|rsp| rsp.to_string()).collect();
+    HfpError::UnexpectedResponse(rsps.join("; ")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::DuplexStream;
+
+    use super::*;
+    use crate::hfp::{AudioGateway, AudioGatewayConfig, AudioGatewayEvent};
+
+    async fn expect(ag: &mut Connection<DuplexStream>, cmd: Command, rsps: &[Response]) {
+        assert_eq!(ag.read_command().await.unwrap(), Some(Ok(cmd)));
+        for rsp in rsps {
+            ag.send_response(rsp).await.unwrap();
+        }
+    }
+
+    /// Establishes the service level connection with a scripted audio gateway
+    /// that reports indicators in a different order and an unknown indicator.
+    async fn connect() -> (HandsFree<DuplexStream>, Connection<DuplexStream>) {
+        let (local, remote) = tokio::io::duplex(4096);
+        let mut ag = Connection::new(remote);
+        let config = HandsFreeConfig::default();
+        let hf_features = config.features.to_bits();
+        let ag_features = AudioGatewayFeatures { codec_negotiation: true, ..Default::default() };
+
+        let script = async {
+            expect(
+                &mut ag,
+                Command::SupportedFeatures(hf_features),
+                &[Response::SupportedFeatures(ag_features.to_bits()), Response::Ok],
+            )
+            .await;
+            expect(&mut ag, Command::AvailableCodecs(vec![1, 2]), &[Response::Ok]).await;
+            let indicators = vec![
+                ("call".to_string(), 0..=1),
+                ("service".to_string(), 0..=1),
+                ("foo".to_string(), 0..=3),
+                ("signal".to_string(), 0..=5),
+            ];
+            expect(&mut ag, Command::IndicatorsTest, &[Response::IndicatorList(indicators), Response::Ok]).await;
+            expect(
+                &mut ag,
+                Command::IndicatorsRead,
+                &[Response::IndicatorValues(vec![0, 1, 2, 4]), Response::Ok],
+            )
+            .await;
+            expect(&mut ag, Command::EventReporting(true), &[Response::Ok]).await;
+            expect(&mut ag, Command::CallingLineIdentification(true), &[Response::Ok]).await;
+        };
+        let (hf, ()) = tokio::join!(HandsFree::connect(local, config), script);
+        let hf = hf.unwrap();
+        assert_eq!(hf.ag_features(), ag_features);
+        (hf, ag)
+    }
+
+    #[tokio::test]
+    async fn service_level_connection() {
+        let (local, remote) = tokio::io::duplex(4096);
+        let hf = async {
+            let mut hf = HandsFree::connect(local, HandsFreeConfig::default()).await.unwrap();
+            hf.set_speaker_volume(7).await.unwrap();
+            hf
+        };
+        let ag = async {
+            let mut ag = AudioGateway::accept(remote, AudioGatewayConfig::default()).await.unwrap();
+            assert_eq!(ag.next_event().await.unwrap(), Some(AudioGatewayEvent::SpeakerVolume(7)));
+            ag
+        };
+        let (mut hf, mut ag) = tokio::join!(hf, ag);
+
+        assert_eq!(hf.ag_features(), AudioGatewayConfig::default().features);
+        assert_eq!(ag.hf_features(), HandsFreeConfig::default().features);
+        assert_eq!(ag.hf_codecs(), &[Codec::Cvsd, Codec::Msbc]);
+        assert!(hf.call_hold_actions().is_empty());
+        for indicator in Indicator::ALL {
+            assert_eq!(hf.indicator(indicator), Some(0));
+        }
+
+        ag.set_indicator(Indicator::Signal, 4).await.unwrap();
+        assert_eq!(
+            hf.next_event().await.unwrap(),
+            Some(HandsFreeEvent::Indicator { indicator: Indicator::Signal, value: 4 })
+        );
+        assert_eq!(hf.indicator(Indicator::Signal), Some(4));
+
+        ag.select_codec(Codec::Msbc).await.unwrap();
+        let (hf_event, ag_event) = tokio::join!(hf.next_event(), ag.next_event());
+        assert_eq!(hf_event.unwrap(), Some(HandsFreeEvent::CodecSelected(Codec::Msbc)));
+        assert_eq!(ag_event.unwrap(), Some(AudioGatewayEvent::CodecSelected(Codec::Msbc)));
+        assert_eq!(hf.codec(), Codec::Msbc);
+        assert_eq!(ag.codec(), Codec::Msbc);
+
+        drop(ag);
+        assert_eq!(hf.next_event().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn indicator_events() {
+        let (mut hf, mut ag) = connect().await;
+        assert_eq!(hf.indicator(Indicator::Call), Some(0));
+        assert_eq!(hf.indicator(Indicator::Service), Some(1));
+        assert_eq!(hf.indicator(Indicator::Signal), Some(4));
+        assert_eq!(hf.indicator(Indicator::CallSetup), None);
+
+        for (index, value) in [(0, 1), (3, 1), (5, 1), (1, 1), (4, 2)] {
+            ag.send_response(&Response::IndicatorEvent { index, value }).await.unwrap();
+        }
+        ag.send_response(&Response::Ring).await.unwrap();
+
+        assert_eq!(
+            hf.next_event().await.unwrap(),
+            Some(HandsFreeEvent::Indicator { indicator: Indicator::Call, value: 1 })
+        );
+        assert_eq!(
+            hf.next_event().await.unwrap(),
+            Some(HandsFreeEvent::Indicator { indicator: Indicator::Signal, value: 2 })
+        );
+        assert_eq!(hf.next_event().await.unwrap(), Some(HandsFreeEvent::Ring));
+        assert_eq!(hf.indicator(Indicator::Call), Some(1));
+        assert_eq!(hf.indicator(Indicator::Signal), Some(2));
+    }
+
+    #[tokio::test]
+    async fn codec_negotiation() {
+        let (mut hf, mut ag) = connect().await;
+        assert_eq!(hf.codec(), Codec::Cvsd);
+
+        ag.send_response(&Response::CodecSelection(2)).await.unwrap();
+        let (event, ()) =
+            tokio::join!(hf.next_event(), expect(&mut ag, Command::CodecSelection(2), &[Response::Ok]));
+        assert_eq!(event.unwrap(), Some(HandsFreeEvent::CodecSelected(Codec::Msbc)));
+        assert_eq!(hf.codec(), Codec::Msbc);
+
+        // Unsupported codecs are answered with the available codecs.
+        ag.send_response(&Response::CodecSelection(9)).await.unwrap();
+        ag.send_response(&Response::Ring).await.unwrap();
+        let (event, ()) =
+            tokio::join!(hf.next_event(), expect(&mut ag, Command::AvailableCodecs(vec![1, 2]), &[Response::Ok]));
+        assert_eq!(event.unwrap(), Some(HandsFreeEvent::Ring));
+        assert_eq!(hf.codec(), Codec::Msbc);
+
+        // A rejected confirmation does not change the codec.
+        ag.send_response(&Response::CodecSelection(1)).await.unwrap();
+        ag.send_response(&Response::Ring).await.unwrap();
+        let (event, ()) =
+            tokio::join!(hf.next_event(), expect(&mut ag, Command::CodecSelection(1), &[Response::Error]));
+        assert_eq!(event.unwrap(), Some(HandsFreeEvent::Ring));
+        assert_eq!(hf.codec(), Codec::Msbc);
+
+        let (result, ()) = tokio::join!(
+            hf.request_codec_connection(),
+            expect(&mut ag, Command::CodecConnection, &[Response::Ok])
+        );
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejected_connection() {
+        let (local, remote) = tokio::io::duplex(4096);
+        let mut ag = Connection::new(remote);
+        let config = HandsFreeConfig::default();
+        let script = expect(&mut ag, Command::SupportedFeatures(config.features.to_bits()), &[Response::Error]);
+        let (hf, ()) = tokio::join!(HandsFree::connect(local, config), script);
+        let err = hf.unwrap_err();
+        assert_eq!(err.get_ref().unwrap().downcast_ref::<HfpError>(), Some(&HfpError::Error));
+    }
+}
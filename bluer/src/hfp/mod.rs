@@ -0,0 +1,527 @@
+//! Hands-free profile (HFP) and headset profile (HSP).
+//!
+//! Both profiles exchange AT commands and result codes over an RFCOMM connection.
+//! The [Command] and [Response] types parse and format the AT commands sent by the
+//! hands-free unit and the result codes sent by the audio gateway.
+//!
+//! [HandsFree] implements the hands-free role, i.e. a headset or car kit,
+//! and [AudioGateway] implements the audio gateway role, i.e. a phone.
+//! Both establish the service level connection (SLC), track the indicators
+//! of the audio gateway, negotiate the voice codec and report unsolicited
+//! result codes and requests of the remote device as typed events.
+//!
+//! To obtain the RFCOMM connection, register a [Profile](crate::rfcomm::Profile)
+//! for the hands-free or audio gateway service class and accept the
+//! [connect request](crate::rfcomm::ConnectRequest).
+//! The voice connection is established separately using a [SCO socket](crate::sco),
+//! using the codec selected during codec negotiation.
+//!
+//! Refer to the Hands-Free Profile specification for details.
+
+use std::{fmt, io, ops::RangeInclusive};
+use strum::{Display, EnumString};
+
+mod ag;
+mod at;
+mod hf;
+
+pub use ag::*;
+pub use at::*;
+pub use hf::*;
+
+/// Service class UUID of the hands-free unit.
+pub const HANDS_FREE_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x0000111e_0000_1000_8000_00805f9b34fb);
+
+/// Service class UUID of the hands-free audio gateway.
+pub const HANDS_FREE_AUDIO_GATEWAY_UUID: uuid::Uuid =
+    uuid::Uuid::from_u128(0x0000111f_0000_1000_8000_00805f9b34fb);
+
+/// Service class UUID of the headset.
+pub const HEADSET_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x00001108_0000_1000_8000_00805f9b34fb);
+
+/// Service class UUID of the headset audio gateway.
+pub const HEADSET_AUDIO_GATEWAY_UUID: uuid::Uuid = uuid::Uuid::from_u128(0x00001112_0000_1000_8000_00805f9b34fb);
+
+/// Maximum speaker and microphone gain.
+pub const MAX_VOLUME: u8 = 15;
+
+/// HFP error.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HfpError {
+    /// Line received from the remote device exceeds the maximum length.
+    LineTooLong,
+    /// Result code or command could not be parsed.
+    InvalidLine(String),
+    /// Remote device responded with `ERROR`.
+    Error,
+    /// Remote device responded with an extended error code.
+    CmeError(u16),
+    /// Response from the remote device is missing or not the expected one.
+    UnexpectedResponse(String),
+    /// Feature is not supported by the local or the remote device.
+    NotSupported,
+    /// Service level connection is not established.
+    NotConnected,
+}
+
+impl fmt::Display for HfpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LineTooLong => write!(f, "AT line too long"),
+            Self::InvalidLine(line) => write!(f, "invalid AT line {line:?}"),
+            Self::Error => write!(f, "remote device responded with ERROR"),
+            Self::CmeError(code) => write!(f, "remote device responded with CME error {code}"),
+            Self::UnexpectedResponse(line) => write!(f, "unexpected response {line:?}"),
+            Self::NotSupported => write!(f, "feature not supported"),
+            Self::NotConnected => write!(f, "service level connection not established"),
+        }
+    }
+}
+
+impl std::error::Error for HfpError {}
+
+impl From<HfpError> for io::Error {
+    fn from(err: HfpError) -> Self {
+        let kind = match err {
+            HfpError::Error | HfpError::CmeError(_) => io::ErrorKind::Other,
+            HfpError::NotSupported => io::ErrorKind::Unsupported,
+            HfpError::NotConnected => io::ErrorKind::NotConnected,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Supported features of the hands-free unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandsFreeFeatures {
+    /// Echo canceling and noise reduction.
+    pub ec_nr: bool,
+    /// Three-way calling.
+    pub three_way_calling: bool,
+    /// Calling line identification presentation.
+    pub cli_presentation: bool,
+    /// Voice recognition activation.
+    pub voice_recognition: bool,
+    /// Remote volume control.
+    pub remote_volume_control: bool,
+    /// Enhanced call status.
+    pub enhanced_call_status: bool,
+    /// Enhanced call control.
+    pub enhanced_call_control: bool,
+    /// Codec negotiation.
+    pub codec_negotiation: bool,
+    /// HF indicators.
+    pub hf_indicators: bool,
+    /// eSCO S4 settings.
+    pub esco_s4: bool,
+    /// Enhanced voice recognition status.
+    pub enhanced_voice_recognition: bool,
+    /// Voice recognition text.
+    pub voice_recognition_text: bool,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl HandsFreeFeatures {
+    /// Decodes the features from their bit representation.
+    pub fn from_bits(bits: u32) -> Self {
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Self {
+            ec_nr: bit(0),
+            three_way_calling: bit(1),
+            cli_presentation: bit(2),
+            voice_recognition: bit(3),
+            remote_volume_control: bit(4),
+            enhanced_call_status: bit(5),
+            enhanced_call_control: bit(6),
+            codec_negotiation: bit(7),
+            hf_indicators: bit(8),
+            esco_s4: bit(9),
+            enhanced_voice_recognition: bit(10),
+            voice_recognition_text: bit(11),
+            _non_exhaustive: (),
+        }
+    }
+
+    /// Encodes the features into their bit representation.
+    pub fn to_bits(&self) -> u32 {
+        [
+            self.ec_nr,
+            self.three_way_calling,
+            self.cli_presentation,
+            self.voice_recognition,
+            self.remote_volume_control,
+            self.enhanced_call_status,
+            self.enhanced_call_control,
+            self.codec_negotiation,
+            self.hf_indicators,
+            self.esco_s4,
+            self.enhanced_voice_recognition,
+            self.voice_recognition_text,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (n, set)| if *set { bits | 1 << n } else { bits })
+    }
+}
+
+/// Supported features of the audio gateway.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioGatewayFeatures {
+    /// Three-way calling.
+    pub three_way_calling: bool,
+    /// Echo canceling and noise reduction.
+    pub ec_nr: bool,
+    /// Voice recognition.
+    pub voice_recognition: bool,
+    /// In-band ring tone.
+    pub in_band_ring: bool,
+    /// Attach a number to a voice tag.
+    pub voice_tag: bool,
+    /// Ability to reject a call.
+    pub reject_call: bool,
+    /// Enhanced call status.
+    pub enhanced_call_status: bool,
+    /// Enhanced call control.
+    pub enhanced_call_control: bool,
+    /// Extended error result codes.
+    pub extended_error_codes: bool,
+    /// Codec negotiation.
+    pub codec_negotiation: bool,
+    /// HF indicators.
+    pub hf_indicators: bool,
+    /// eSCO S4 settings.
+    pub esco_s4: bool,
+    /// Enhanced voice recognition status.
+    pub enhanced_voice_recognition: bool,
+    /// Voice recognition text.
+    pub voice_recognition_text: bool,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl AudioGatewayFeatures {
+    /// Decodes the features from their bit representation.
+    pub fn from_bits(bits: u32) -> Self {
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Self {
+            three_way_calling: bit(0),
+            ec_nr: bit(1),
+            voice_recognition: bit(2),
+            in_band_ring: bit(3),
+            voice_tag: bit(4),
+            reject_call: bit(5),
+            enhanced_call_status: bit(6),
+            enhanced_call_control: bit(7),
+            extended_error_codes: bit(8),
+            codec_negotiation: bit(9),
+            hf_indicators: bit(10),
+            esco_s4: bit(11),
+            enhanced_voice_recognition: bit(12),
+            voice_recognition_text: bit(13),
+            _non_exhaustive: (),
+        }
+    }
+
+    /// Encodes the features into their bit representation.
+    pub fn to_bits(&self) -> u32 {
+        [
+            self.three_way_calling,
+            self.ec_nr,
+            self.voice_recognition,
+            self.in_band_ring,
+            self.voice_tag,
+            self.reject_call,
+            self.enhanced_call_status,
+            self.enhanced_call_control,
+            self.extended_error_codes,
+            self.codec_negotiation,
+            self.hf_indicators,
+            self.esco_s4,
+            self.enhanced_voice_recognition,
+            self.voice_recognition_text,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (n, set)| if *set { bits | 1 << n } else { bits })
+    }
+}
+
+/// Voice codec.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Codec {
+    /// CVSD for narrowband speech.
+    ///
+    /// This is used when codec negotiation is not supported.
+    #[default]
+    Cvsd,
+    /// mSBC for wideband speech.
+    Msbc,
+    /// LC3-SWB for super-wideband speech.
+    Lc3Swb,
+}
+
+impl Codec {
+    /// Codec id used during codec negotiation.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Cvsd => 1,
+            Self::Msbc => 2,
+            Self::Lc3Swb => 3,
+        }
+    }
+
+    /// Codec with the specified codec id.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Cvsd),
+            2 => Some(Self::Msbc),
+            3 => Some(Self::Lc3Swb),
+            _ => None,
+        }
+    }
+}
+
+/// Indicator of the audio gateway.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Indicator {
+    /// Cellular network is available (1) or not (0).
+    #[strum(serialize = "service")]
+    Service,
+    /// At least one call is in progress (1) or not (0).
+    #[strum(serialize = "call")]
+    Call,
+    /// Call setup status, see [CallSetup].
+    #[strum(serialize = "callsetup")]
+    CallSetup,
+    /// Call hold status, see [CallHeld].
+    #[strum(serialize = "callheld")]
+    CallHeld,
+    /// Signal strength from 0 to 5.
+    #[strum(serialize = "signal")]
+    Signal,
+    /// Roaming is active (1) or not (0).
+    #[strum(serialize = "roam")]
+    Roam,
+    /// Battery charge level from 0 to 5.
+    #[strum(serialize = "battchg")]
+    BatteryCharge,
+}
+
+impl Indicator {
+    /// All indicators in the order they are reported by [AudioGateway].
+    pub const ALL: [Self; 7] = [
+        Self::Service,
+        Self::Call,
+        Self::CallSetup,
+        Self::CallHeld,
+        Self::Signal,
+        Self::Roam,
+        Self::BatteryCharge,
+    ];
+
+    /// Range of valid values.
+    pub fn range(&self) -> RangeInclusive<u8> {
+        match self {
+            Self::Service | Self::Call | Self::Roam => 0..=1,
+            Self::CallSetup => 0..=3,
+            Self::CallHeld => 0..=2,
+            Self::Signal | Self::BatteryCharge => 0..=5,
+        }
+    }
+}
+
+/// Value of the [call setup indicator](Indicator::CallSetup).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallSetup {
+    /// No call setup in progress.
+    #[default]
+    None = 0,
+    /// Incoming call setup in progress.
+    Incoming = 1,
+    /// Outgoing call setup in dialing state.
+    Outgoing = 2,
+    /// Outgoing call setup in alerting state.
+    Alerting = 3,
+}
+
+impl CallSetup {
+    /// Call setup status from the indicator value.
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Incoming),
+            2 => Some(Self::Outgoing),
+            3 => Some(Self::Alerting),
+            _ => None,
+        }
+    }
+}
+
+/// Value of the [call held indicator](Indicator::CallHeld).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallHeld {
+    /// No calls held.
+    #[default]
+    None = 0,
+    /// A call is held and another call is active or was swapped.
+    HeldAndActive = 1,
+    /// A call is held and no call is active.
+    Held = 2,
+}
+
+impl CallHeld {
+    /// Call hold status from the indicator value.
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::HeldAndActive),
+            2 => Some(Self::Held),
+            _ => None,
+        }
+    }
+}
+
+/// Action of the call hold and multiparty command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CallHoldAction {
+    /// Release all held calls or reject the waiting call.
+    ReleaseAllHeld,
+    /// Release all active calls and accept the held or waiting call.
+    ReleaseActiveAcceptOther,
+    /// Release the call with the specified index.
+    ReleaseCall(u8),
+    /// Hold all active calls and accept the held or waiting call.
+    HoldActiveAcceptOther,
+    /// Hold all active calls except the call with the specified index.
+    PrivateConsultation(u8),
+    /// Add a held call to the conversation.
+    AddHeldCall,
+    /// Connect the two calls and disconnect from both.
+    ExplicitCallTransfer,
+}
+
+impl fmt::Display for CallHoldAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ReleaseAllHeld => write!(f, "0"),
+            Self::ReleaseActiveAcceptOther => write!(f, "1"),
+            Self::ReleaseCall(idx) => write!(f, "1{idx}"),
+            Self::HoldActiveAcceptOther => write!(f, "2"),
+            Self::PrivateConsultation(idx) => write!(f, "2{idx}"),
+            Self::AddHeldCall => write!(f, "3"),
+            Self::ExplicitCallTransfer => write!(f, "4"),
+        }
+    }
+}
+
+impl std::str::FromStr for CallHoldAction {
+    type Err = HfpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || HfpError::InvalidLine(s.to_string());
+        let (action, idx) = s.split_at(s.len().min(1));
+        let idx = if idx.is_empty() { None } else { Some(idx.parse().map_err(|_| invalid())?) };
+        match (action, idx) {
+            ("0", None) => Ok(Self::ReleaseAllHeld),
+            ("1", None) => Ok(Self::ReleaseActiveAcceptOther),
+            ("1", Some(idx)) => Ok(Self::ReleaseCall(idx)),
+            ("2", None) => Ok(Self::HoldActiveAcceptOther),
+            ("2", Some(idx)) => Ok(Self::PrivateConsultation(idx)),
+            ("3", None) => Ok(Self::AddHeldCall),
+            ("4", None) => Ok(Self::ExplicitCallTransfer),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// State of a call in the list of current calls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum CallState {
+    /// Active.
+    Active,
+    /// Held.
+    Held,
+    /// Outgoing call being dialed.
+    Dialing,
+    /// Outgoing call alerting the remote party.
+    Alerting,
+    /// Incoming call.
+    Incoming,
+    /// Incoming call waiting while another call is active.
+    Waiting,
+    /// Call held by response and hold.
+    ResponseAndHold,
+}
+
+impl CallState {
+    fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Active),
+            1 => Some(Self::Held),
+            2 => Some(Self::Dialing),
+            3 => Some(Self::Alerting),
+            4 => Some(Self::Incoming),
+            5 => Some(Self::Waiting),
+            6 => Some(Self::ResponseAndHold),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> u8 {
+        match self {
+            Self::Active => 0,
+            Self::Held => 1,
+            Self::Dialing => 2,
+            Self::Alerting => 3,
+            Self::Incoming => 4,
+            Self::Waiting => 5,
+            Self::ResponseAndHold => 6,
+        }
+    }
+}
+
+/// Entry of the list of current calls.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CurrentCall {
+    /// Call index, starting at 1.
+    pub index: u8,
+    /// Whether the call is outgoing.
+    pub outgoing: bool,
+    /// Call state.
+    pub state: CallState,
+    /// Call mode; 0 for voice calls.
+    pub mode: u8,
+    /// Whether the call is part of a multiparty conference.
+    pub multiparty: bool,
+    /// Phone number.
+    pub number: Option<String>,
+}
+
+/// Type of a phone number as used in AT commands.
+///
+/// Numbers starting with `+` are international numbers.
+fn number_type(number: &str) -> u8 {
+    if number.starts_with('+') {
+        145
+    } else {
+        129
+    }
+}
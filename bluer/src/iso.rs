@@ -0,0 +1,801 @@
+//! Isochronous channel (ISO) sockets for LE Audio.
+//!
+//! ISO sockets transfer service data units (SDUs) over Connected Isochronous
+//! Streams (CIS) and Broadcast Isochronous Streams (BIS) of Bluetooth Low Energy.
+//! The timing and size of SDUs is configured using [UnicastQos] or [BroadcastQos]
+//! before connecting or listening.
+//!
+//! ## Unicast
+//! Use [SeqPacket::connect] to establish a CIS with a connected device or
+//! [Listener::bind] with [SocketAddr::any_le] to accept CIS requests from peers.
+//!
+//! ## Broadcast source
+//! Configure the [broadcast QoS](Socket::set_broadcast_qos) of a [Socket] and
+//! [connect](Socket::connect) it to a [broadcast address](SocketAddr::broadcast) with
+//! [Address::any] as device address.
+//! The BIS indices of the broadcast address specify the streams to create.
+//!
+//! ## Broadcast sink
+//! Bind a [Socket] to a [broadcast address](SocketAddr::broadcast) specifying the
+//! address and advertising SID of the broadcast source together with the indices of the
+//! BIS to receive and [listen](Socket::listen) on it.
+//! The kernel synchronizes to the periodic advertising (PA) of the source and then to
+//! its broadcast isochronous group (BIG).
+//! The synchronized BIS are returned by [Listener::accept].
+//!
+//! With [deferred setup](Socket::set_defer_setup) enabled, the listener first returns
+//! a connection representing the PA sync.
+//! The BIS indices can then be changed by [binding](Socket::bind) this connection to a new
+//! broadcast address before calling [SeqPacket::accept_deferred] to synchronize to the BIG.
+//!
+
+use crate::{
+    sock::{self, OwnedFd},
+    sys::{
+        bt_iso_bcast_qos, bt_iso_io_qos, bt_iso_qos, bt_iso_ucast_qos, sockaddr_iso, sockaddr_iso_bc,
+        BTPROTO_ISO, BT_DEFER_SETUP, BT_ISO_PHY_2M, BT_ISO_QOS, ISO_MAX_NUM_BIS, SOCKADDR_ISO_UCAST_LEN,
+    },
+    Address, AddressType,
+};
+use futures::ready;
+use libc::{
+    AF_BLUETOOTH, EAGAIN, EINPROGRESS, SHUT_RD, SHUT_RDWR, SHUT_WR, SOCK_SEQPACKET, SOL_BLUETOOTH, SOL_SOCKET,
+    SO_ERROR, SO_RCVBUF, TIOCINQ, TIOCOUTQ,
+};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    net::Shutdown,
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    task::{Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, ReadBuf};
+
+pub use crate::sys::{
+    BT_ISO_QOS_BIG_UNSET as BIG_UNSET, BT_ISO_QOS_BIS_UNSET as BIS_UNSET, BT_ISO_QOS_CIG_UNSET as CIG_UNSET,
+    BT_ISO_QOS_CIS_UNSET as CIS_UNSET,
+};
+
+/// Possible bit values for the [PHY field](IoQos::phy) of the QoS.
+pub mod phy {
+    pub use crate::sys::{
+        BT_ISO_PHY_1M as LE_1M, BT_ISO_PHY_2M as LE_2M, BT_ISO_PHY_ANY as ANY, BT_ISO_PHY_CODED as LE_CODED,
+    };
+}
+
+/// Highest broadcast isochronous stream (BIS) index.
+pub const BIS_MAX: u8 = ISO_MAX_NUM_BIS as _;
+
+/// An ISO socket address.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketAddr {
+    /// Device address.
+    ///
+    /// When listening or binding, specify [Address::any] for any local adapter address.
+    /// When connecting a broadcast source, specify [Address::any].
+    pub addr: Address,
+    /// Device address type.
+    pub addr_type: AddressType,
+    /// Broadcast address.
+    ///
+    /// This is `None` for unicast.
+    pub broadcast: Option<BroadcastAddr>,
+}
+
+impl SocketAddr {
+    /// Creates a new unicast ISO socket address.
+    pub const fn new(addr: Address, addr_type: AddressType) -> Self {
+        Self { addr, addr_type, broadcast: None }
+    }
+
+    /// Creates a new broadcast ISO socket address.
+    pub const fn broadcast(addr: Address, addr_type: AddressType, broadcast: BroadcastAddr) -> Self {
+        Self { addr, addr_type, broadcast: Some(broadcast) }
+    }
+
+    /// When specified to [Socket::bind] binds to any public, local adapter address.
+    pub const fn any_le() -> Self {
+        Self { addr: Address::any(), addr_type: AddressType::LePublic, broadcast: None }
+    }
+}
+
+impl Default for SocketAddr {
+    fn default() -> Self {
+        Self::any_le()
+    }
+}
+
+/// Broadcast part of an ISO socket address.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastAddr {
+    /// Address of the broadcast source.
+    ///
+    /// Specify [Address::any] for a local broadcast source.
+    pub addr: Address,
+    /// Address type of the broadcast source.
+    pub addr_type: AddressType,
+    /// Advertising set identifier (SID) of the periodic advertising.
+    pub sid: u8,
+    /// Indices of the broadcast isochronous streams (BIS), starting from 1.
+    ///
+    /// At most [BIS_MAX] indices can be specified.
+    pub bis: Vec<u8>,
+}
+
+impl sock::SysSockAddr for SocketAddr {
+    type SysSockAddr = sockaddr_iso;
+
+    const MIN_SYS_SOCK_ADDR_LEN: usize = SOCKADDR_ISO_UCAST_LEN;
+
+    fn into_sys_sock_addr(self) -> Self::SysSockAddr {
+        let iso_bc = match self.broadcast {
+            Some(bc) => {
+                let mut bc_bis = [0; ISO_MAX_NUM_BIS];
+                let num_bis = bc.bis.len().min(ISO_MAX_NUM_BIS);
+                bc_bis[..num_bis].copy_from_slice(&bc.bis[..num_bis]);
+                sockaddr_iso_bc {
+                    bc_bdaddr: bc.addr.into(),
+                    bc_bdaddr_type: bc.addr_type as _,
+                    bc_sid: bc.sid,
+                    bc_num_bis: num_bis as _,
+                    bc_bis,
+                }
+            }
+            None => Default::default(),
+        };
+        sockaddr_iso {
+            iso_family: AF_BLUETOOTH as _,
+            iso_bdaddr: self.addr.into(),
+            iso_bdaddr_type: self.addr_type as _,
+            iso_bc,
+        }
+    }
+
+    fn sys_sock_addr_len(&self) -> usize {
+        match self.broadcast {
+            Some(_) => std::mem::size_of::<sockaddr_iso>(),
+            None => SOCKADDR_ISO_UCAST_LEN,
+        }
+    }
+
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.iso_family != AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_iso::iso_family is not AF_BLUETOOTH"));
+        }
+        let addr_type = |ty| {
+            AddressType::from_u8(ty)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid sockaddr_iso address type"))
+        };
+        let bc = &saddr.iso_bc;
+        let broadcast = match bc.bc_num_bis {
+            0 => None,
+            num_bis => Some(BroadcastAddr {
+                addr: Address::from(bc.bc_bdaddr.clone()),
+                addr_type: addr_type(bc.bc_bdaddr_type)?,
+                sid: bc.bc_sid,
+                bis: bc.bc_bis[..(num_bis as usize).min(ISO_MAX_NUM_BIS)].to_vec(),
+            }),
+        };
+        Ok(Self {
+            addr: Address::from(saddr.iso_bdaddr),
+            addr_type: addr_type(saddr.iso_bdaddr_type)?,
+            broadcast,
+        })
+    }
+}
+
+/// Framing of service data units (SDUs).
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Framing {
+    /// Unframed; each SDU is carried in one or more whole isochronous PDUs.
+    #[default]
+    Unframed = 0x00,
+    /// Framed; SDUs may be segmented across isochronous PDUs.
+    Framed = 0x01,
+}
+
+/// Arrangement of subevents of multiple streams within a group.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Packing {
+    /// Sequential.
+    #[default]
+    Sequential = 0x00,
+    /// Interleaved.
+    Interleaved = 0x01,
+}
+
+/// Quality of service (QoS) of one direction of an isochronous stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoQos {
+    /// SDU interval in microseconds.
+    ///
+    /// Set to zero if this direction is unused.
+    pub interval: u32,
+    /// Maximum transport latency in milliseconds.
+    pub latency: u16,
+    /// Maximum SDU size in bytes.
+    pub sdu: u16,
+    /// PHY bit field.
+    ///
+    /// Possible values are defined in the [phy] module.
+    pub phy: u8,
+    /// Number of retransmissions.
+    pub rtn: u8,
+}
+
+impl IoQos {
+    /// QoS of an unused direction.
+    pub const fn unused() -> Self {
+        Self { interval: 0, latency: 0, sdu: 0, phy: 0, rtn: 0 }
+    }
+}
+
+impl Default for IoQos {
+    /// The kernel default of a 10 ms SDU interval carrying 40 byte SDUs.
+    fn default() -> Self {
+        Self { interval: 10_000, latency: 10, sdu: 40, phy: BT_ISO_PHY_2M, rtn: 2 }
+    }
+}
+
+impl From<IoQos> for bt_iso_io_qos {
+    fn from(q: IoQos) -> Self {
+        bt_iso_io_qos { interval: q.interval, latency: q.latency, sdu: q.sdu, phy: q.phy, rtn: q.rtn }
+    }
+}
+
+impl From<bt_iso_io_qos> for IoQos {
+    fn from(q: bt_iso_io_qos) -> Self {
+        Self { interval: q.interval, latency: q.latency, sdu: q.sdu, phy: q.phy, rtn: q.rtn }
+    }
+}
+
+fn framing_from_sys(value: u8) -> Result<Framing> {
+    Framing::from_u8(value).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid ISO framing"))
+}
+
+fn packing_from_sys(value: u8) -> Result<Packing> {
+    Packing::from_u8(value).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid ISO packing"))
+}
+
+/// Quality of service (QoS) of a connected isochronous stream (CIS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnicastQos {
+    /// Connected isochronous group (CIG) id or [CIG_UNSET] for automatic allocation.
+    pub cig: u8,
+    /// Connected isochronous stream (CIS) id or [CIS_UNSET] for automatic allocation.
+    pub cis: u8,
+    /// Worst case sleep clock accuracy.
+    pub sca: u8,
+    /// Packing.
+    pub packing: Packing,
+    /// Framing.
+    pub framing: Framing,
+    /// QoS of received data.
+    pub input: IoQos,
+    /// QoS of sent data.
+    pub output: IoQos,
+}
+
+impl Default for UnicastQos {
+    fn default() -> Self {
+        Self {
+            cig: CIG_UNSET,
+            cis: CIS_UNSET,
+            sca: 0,
+            packing: Packing::default(),
+            framing: Framing::default(),
+            input: IoQos::default(),
+            output: IoQos::default(),
+        }
+    }
+}
+
+impl From<UnicastQos> for bt_iso_ucast_qos {
+    fn from(q: UnicastQos) -> Self {
+        bt_iso_ucast_qos {
+            cig: q.cig,
+            cis: q.cis,
+            sca: q.sca,
+            packing: q.packing as _,
+            framing: q.framing as _,
+            in_: q.input.into(),
+            out: q.output.into(),
+        }
+    }
+}
+
+impl TryFrom<bt_iso_ucast_qos> for UnicastQos {
+    type Error = Error;
+    fn try_from(q: bt_iso_ucast_qos) -> Result<Self> {
+        Ok(Self {
+            cig: q.cig,
+            cis: q.cis,
+            sca: q.sca,
+            packing: packing_from_sys(q.packing)?,
+            framing: framing_from_sys(q.framing)?,
+            input: q.in_.into(),
+            output: q.out.into(),
+        })
+    }
+}
+
+/// Quality of service (QoS) of a broadcast isochronous stream (BIS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BroadcastQos {
+    /// Broadcast isochronous group (BIG) handle or [BIG_UNSET] for automatic allocation.
+    pub big: u8,
+    /// Broadcast isochronous stream (BIS) index or [BIS_UNSET] for automatic allocation.
+    pub bis: u8,
+    /// Factor of the periodic advertising interval relative to the SDU interval.
+    pub sync_factor: u8,
+    /// Packing.
+    pub packing: Packing,
+    /// Framing.
+    pub framing: Framing,
+    /// QoS of received data, used by a broadcast sink.
+    pub input: IoQos,
+    /// QoS of sent data, used by a broadcast source.
+    pub output: IoQos,
+    /// Whether the broadcast is encrypted.
+    pub encryption: bool,
+    /// Broadcast code used for encryption.
+    pub broadcast_code: [u8; 16],
+    /// Periodic advertising create sync options.
+    pub options: u8,
+    /// Number of periodic advertising events that can be skipped.
+    pub skip: u16,
+    /// Synchronization timeout for periodic advertising in units of 10 ms.
+    pub sync_timeout: u16,
+    /// Constant tone extension types excluded from synchronization.
+    pub sync_cte_type: u8,
+    /// Maximum number of subevents used to receive PDUs per BIS event.
+    pub mse: u8,
+    /// Synchronization timeout for the BIG in units of 10 ms.
+    pub timeout: u16,
+}
+
+impl Default for BroadcastQos {
+    fn default() -> Self {
+        Self {
+            big: BIG_UNSET,
+            bis: BIS_UNSET,
+            sync_factor: 0x01,
+            packing: Packing::default(),
+            framing: Framing::default(),
+            input: IoQos::default(),
+            output: IoQos::default(),
+            encryption: false,
+            broadcast_code: [0; 16],
+            options: 0,
+            skip: 0,
+            sync_timeout: 0x07d0,
+            sync_cte_type: 0,
+            mse: 0,
+            timeout: 0x07d0,
+        }
+    }
+}
+
+impl From<BroadcastQos> for bt_iso_bcast_qos {
+    fn from(q: BroadcastQos) -> Self {
+        bt_iso_bcast_qos {
+            big: q.big,
+            bis: q.bis,
+            sync_factor: q.sync_factor,
+            packing: q.packing as _,
+            framing: q.framing as _,
+            in_: q.input.into(),
+            out: q.output.into(),
+            encryption: q.encryption.into(),
+            bcode: q.broadcast_code,
+            options: q.options,
+            skip: q.skip,
+            sync_timeout: q.sync_timeout,
+            sync_cte_type: q.sync_cte_type,
+            mse: q.mse,
+            timeout: q.timeout,
+        }
+    }
+}
+
+impl TryFrom<bt_iso_bcast_qos> for BroadcastQos {
+    type Error = Error;
+    fn try_from(q: bt_iso_bcast_qos) -> Result<Self> {
+        Ok(Self {
+            big: q.big,
+            bis: q.bis,
+            sync_factor: q.sync_factor,
+            packing: packing_from_sys(q.packing)?,
+            framing: framing_from_sys(q.framing)?,
+            input: q.in_.into(),
+            output: q.out.into(),
+            encryption: q.encryption != 0,
+            broadcast_code: q.bcode,
+            options: q.options,
+            skip: q.skip,
+            sync_timeout: q.sync_timeout,
+            sync_cte_type: q.sync_cte_type,
+            mse: q.mse,
+            timeout: q.timeout,
+        })
+    }
+}
+
+/// An ISO socket that has not yet been converted to a [Listener] or [SeqPacket].
+///
+/// The primary use of this is to configure the socket before connecting or listening.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socket").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
+
+impl Socket {
+    /// Creates a new socket of sequential packet type.
+    pub fn new() -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(sock::socket(AF_BLUETOOTH, SOCK_SEQPACKET, BTPROTO_ISO)?)? })
+    }
+
+    /// Convert the socket into a [Listener].
+    ///
+    /// `backlog` defines the maximum number of pending connections are queued by the operating system
+    /// at any given time.
+    pub fn listen(self, backlog: u32) -> Result<Listener> {
+        sock::listen(
+            self.fd.get_ref(),
+            backlog.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid backlog"))?,
+        )?;
+        Ok(Listener { socket: self })
+    }
+
+    /// Establish an isochronous stream with a peer at the specified socket address.
+    ///
+    /// For a broadcast source, specify a broadcast address with [Address::any] as device address.
+    pub async fn connect(self, sa: SocketAddr) -> Result<SeqPacket> {
+        self.connect_priv(sa).await?;
+        Ok(SeqPacket { socket: self })
+    }
+
+    /// Bind the socket to the given address.
+    pub fn bind(&self, sa: SocketAddr) -> Result<()> {
+        sock::bind(self.fd.get_ref(), sa)
+    }
+
+    /// Get the local address of this socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        sock::getsockname(self.fd.get_ref())
+    }
+
+    /// Get the peer address of this socket.
+    fn peer_addr_priv(&self) -> Result<SocketAddr> {
+        sock::getpeername(self.fd.get_ref())
+    }
+
+    /// Gets the QoS of a connected isochronous stream.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn unicast_qos(&self) -> Result<UnicastQos> {
+        let qos: bt_iso_qos = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS)?;
+        UnicastQos::try_from(unsafe { qos.ucast })
+    }
+
+    /// Sets the QoS of a connected isochronous stream.
+    ///
+    /// This must be set before connecting or listening.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn set_unicast_qos(&self, qos: &UnicastQos) -> Result<()> {
+        let mut value: bt_iso_qos = bt_iso_qos { bcast: Default::default() };
+        value.ucast = (*qos).into();
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS, &value)
+    }
+
+    /// Gets the QoS of a broadcast isochronous stream.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn broadcast_qos(&self) -> Result<BroadcastQos> {
+        let qos: bt_iso_qos = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS)?;
+        BroadcastQos::try_from(unsafe { qos.bcast })
+    }
+
+    /// Sets the QoS of a broadcast isochronous stream.
+    ///
+    /// This must be set before connecting or listening.
+    ///
+    /// This corresponds to the `BT_ISO_QOS` socket option.
+    pub fn set_broadcast_qos(&self, qos: &BroadcastQos) -> Result<()> {
+        let value = bt_iso_qos { bcast: (*qos).into() };
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_ISO_QOS, &value)
+    }
+
+    /// Gets whether deferred connection setup is enabled.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn is_defer_setup(&self) -> Result<bool> {
+        let value: u32 = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP)?;
+        Ok(value != 0)
+    }
+
+    /// Sets whether deferred connection setup is enabled.
+    ///
+    /// When enabled on a listening socket, incoming connections are returned by
+    /// [Listener::accept] before they are accepted and must be accepted using
+    /// [SeqPacket::accept_deferred].
+    /// Dropping the connection rejects it.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn set_defer_setup(&self, defer_setup: bool) -> Result<()> {
+        let value: u32 = defer_setup.into();
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP, &value)
+    }
+
+    /// Gets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn recv_buffer(&self) -> Result<i32> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF)
+    }
+
+    /// Sets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn set_recv_buffer(&self, recv_buffer: i32) -> Result<()> {
+        sock::setsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF, &recv_buffer)
+    }
+
+    /// Get the number of bytes in the input buffer.
+    ///
+    /// This corresponds to the `TIOCINQ` IOCTL.
+    pub fn input_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCINQ)?;
+        Ok(value as _)
+    }
+
+    /// Get the number of bytes in the output buffer.
+    ///
+    /// This corresponds to the `TIOCOUTQ` IOCTL.
+    pub fn output_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCOUTQ)?;
+        Ok(value as _)
+    }
+
+    /// Constructs a new [Socket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(OwnedFd::new(fd))? })
+    }
+
+    fn from_owned_fd(fd: OwnedFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(fd)? })
+    }
+
+    sock_priv!();
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_inner().into_raw_fd()
+    }
+}
+
+impl FromRawFd for Socket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Socket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An ISO socket server, listening for [SeqPacket] connections.
+///
+/// This is used for accepting connected isochronous streams and for receiving
+/// broadcast isochronous streams.
+#[derive(Debug)]
+pub struct Listener {
+    socket: Socket,
+}
+
+impl Listener {
+    /// Creates a new Listener, which will be bound to the specified socket address,
+    /// using the default QoS.
+    ///
+    /// Specify [SocketAddr::any_le] for accepting connected isochronous streams on
+    /// any local adapter.
+    pub async fn bind(sa: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(sa)?;
+        socket.listen(1)
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    pub async fn accept(&self) -> Result<(SeqPacket, SocketAddr)> {
+        let (socket, sa) = self.socket.accept_priv().await?;
+        Ok((SeqPacket { socket }, sa))
+    }
+
+    /// Polls to accept a new incoming connection to this listener.
+    pub fn poll_accept(&self, cx: &mut Context) -> Poll<Result<(SeqPacket, SocketAddr)>> {
+        let (socket, sa) = ready!(self.socket.poll_accept_priv(cx))?;
+        Poll::Ready(Ok((SeqPacket { socket }, sa)))
+    }
+
+    /// Constructs a new [Listener] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for Listener {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Listener {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Listener::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// An isochronous stream, transferring one service data unit (SDU) per packet.
+#[derive(Debug)]
+pub struct SeqPacket {
+    socket: Socket,
+}
+
+impl SeqPacket {
+    /// Establish a connected isochronous stream with a peer at the specified socket address
+    /// using the default QoS.
+    ///
+    /// Uses any local Bluetooth adapter.
+    pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::any_le())?;
+        socket.connect(addr).await
+    }
+
+    /// Gets the peer address of this stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.socket.peer_addr_priv()
+    }
+
+    /// Accepts a connection that was received with
+    /// [deferred setup](Socket::set_defer_setup) enabled.
+    pub fn accept_deferred(&self) -> Result<()> {
+        let mut buf = ReadBuf::new(&mut []);
+        sock::recv(self.socket.fd.get_ref(), &mut buf, 0)?;
+        Ok(())
+    }
+
+    /// Sends an SDU.
+    ///
+    /// The SDU length must not exceed the SDU size of the output QoS.
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.socket.send_priv(buf).await
+    }
+
+    /// Attempts to send an SDU.
+    ///
+    /// The SDU length must not exceed the SDU size of the output QoS.
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.socket.poll_send_priv(cx, buf)
+    }
+
+    /// Receives an SDU.
+    ///
+    /// The provided buffer must be at least of the SDU size of the input QoS, otherwise
+    /// the SDU may be truncated.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.socket.recv_priv(buf).await
+    }
+
+    /// Attempts to receive an SDU.
+    ///
+    /// The provided buffer must be at least of the SDU size of the input QoS, otherwise
+    /// the SDU may be truncated.
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        self.socket.poll_recv_priv(cx, buf)
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.socket.shutdown_priv(how)
+    }
+
+    /// Constructs a new [SeqPacket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for SeqPacket {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for SeqPacket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for SeqPacket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [SeqPacket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
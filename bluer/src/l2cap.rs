@@ -77,6 +77,8 @@ pub const PSM_LE_DYN_START: u16 = 0x80;
 /// The highest protocol service multiplexor (PSM) for Bluetooth Low Energy.
 pub const PSM_LE_MAX: u16 = 0xff;
 
+pub use crate::dissect::l2cap::CID_ATT;
+
 /// An L2CAP socket address.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -100,9 +102,9 @@ pub struct SocketAddr {
     ///
     /// Set to 0 for listening to assign an available PSM.
     pub psm: u16,
-    /// Connection identifier (CID).
+    /// Connection identifier (CID) of a fixed channel, for example [CID_ATT].
     ///
-    /// Should be set to 0.
+    /// Should be set to 0 for connection-oriented channels, which are identified by the PSM.
     pub cid: u16,
 }
 
@@ -112,6 +114,13 @@ impl SocketAddr {
         Self { addr, addr_type, psm, cid: 0 }
     }
 
+    /// Creates a new L2CAP socket address of a fixed channel.
+    ///
+    /// The PSM is set to 0.
+    pub const fn new_fixed(addr: Address, addr_type: AddressType, cid: u16) -> Self {
+        Self { addr, addr_type, psm: 0, cid }
+    }
+
     /// When specified to [Socket::bind] binds to any local adapter address
     /// using classic Bluetooth (BR/EDR) and a dynamically allocated PSM.
     pub const fn any_br_edr() -> Self {
@@ -153,11 +162,14 @@ impl sock::SysSockAddr for SocketAddr {
 }
 
 /// Any bind address for connecting to specified address.
+///
+/// Fixed channels must be bound to the same CID they connect to.
 fn any_bind_addr(addr: &SocketAddr) -> SocketAddr {
-    match addr.addr_type {
+    let any = match addr.addr_type {
         AddressType::BrEdr => SocketAddr::any_br_edr(),
         AddressType::LePublic | AddressType::LeRandom => SocketAddr::any_le(),
-    }
+    };
+    SocketAddr { cid: addr.cid, ..any }
 }
 
 /// L2CAP socket security level.
@@ -436,7 +448,7 @@ impl<Type> Socket<Type> {
         Ok(Self { fd: AsyncFd::new(fd)?, _type: PhantomData })
     }
 
-    sock_priv!();
+    sock_priv!(stream);
 }
 
 impl<Type> AsRawFd for Socket<Type> {
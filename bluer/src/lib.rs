@@ -30,14 +30,33 @@
 //!     * read, write and notify operations on characteristics
 //!     * read and write operations on characteristic descriptors
 //!     * optional use of low-overhead [AsyncRead] and [AsyncWrite] streams for notify and write operations
+//!     * [serializable snapshots](Device::gatt_database) of the GATT database that can be compared
 //! * [publishing local GATT services](Adapter::serve_gatt_application)
 //!     * read, write and notify operations on characteristics
 //!     * read and write operations on characteristic descriptors
 //!     * two programming models supported
 //!         * callback-based interface
 //!         * low-overhead [AsyncRead] and [AsyncWrite] streams
+//!     * [emulation of peripherals](gatt::emulate) from declarative descriptions or database snapshots
+//!     * [derivation of services](gatt::local::GattService) from Rust types using `#[derive(GattService)]`
 //! * [sending Bluetooth Low Energy advertisements](Adapter::advertise)
+//! * [parsing and encoding of advertising data](ad)
+//!     * [iBeacon, Eddystone and AltBeacon](beacon) formats
 //! * [Bluetooth authorization agent](agent::Agent)
+//! * [audio streaming](media)
+//!     * [registration of media endpoints](Adapter::register_media_endpoint) with SBC, AAC and vendor codecs
+//!     * acquisition of media transports for sending and receiving audio packets
+//!     * [remote control](Device::media_players) of media players through AVRCP, including browsing
+//!     * [registration of local media players](Adapter::register_media_player)
+//! * [SBC audio codec](codec::sbc) implemented in pure Rust
+//!     * encoding and decoding of all A2DP configurations
+//!     * mSBC for wideband speech
+//! * [personal area networking (PAN)](network)
+//!     * [connecting](Device::network) to network access points and group networks
+//!     * [serving](Adapter::network_server) network access to remote devices
+//! * [OBEX client](obex) for the OBEX daemon
+//!     * object push, file transfer, phonebook access and message access
+//!     * transfer progress as a stream of status updates
 //! * efficient event dispatching
 //!     * not affected by D-Bus match rule count
 //!     * O(1) in number of subscriptions
@@ -47,13 +66,46 @@
 //!     * sequential packet oriented
 //!     * datagram oriented
 //!     * async IO interface with [AsyncRead] and [AsyncWrite] support
+//! * [GATT client over the ATT channel](att) without the Bluetooth daemon
+//!     * service, characteristic and descriptor discovery by attribute handle
+//!     * long reads and prepared writes
+//!     * notifications and indications as streams
+//! * [GATT server over the ATT channel](att::Server) publishing a [local application](gatt::local::Application)
+//!   without the Bluetooth daemon
 //! * [RFCOMM sockets](rfcomm)
 //!     * support for classic Bluetooth (BR/EDR)
 //!     * stream oriented
 //!     * async IO interface with [AsyncRead] and [AsyncWrite] support
+//! * [hands-free and headset profiles](hfp)
+//!     * AT command and result code parsing and formatting
+//!     * hands-free unit and audio gateway roles with service level connection setup
+//!     * indicators, call status and codec negotiation as typed events
+//! * [SDP service records](sdp)
+//!     * typed attributes and data elements
+//!     * conversion to and from the XML format of the Bluetooth daemon
+//!     * [querying](sdp::Client) the SDP server of a remote device
+//!     * [retrieving](Device::service_records) the records of a remote device found by the Bluetooth daemon
+//! * [SCO sockets](sco)
+//!     * voice links for hands-free and headset profiles
+//!     * CVSD and transparent voice settings
+//!     * async IO interface with [AsyncRead] and [AsyncWrite] support
+//! * [ISO sockets](iso) for LE Audio
+//!     * connected isochronous streams (CIS)
+//!     * broadcast isochronous streams (BIS) as source and sink
+//!     * typed QoS configuration
+//! * [Bluetooth Management API](mgmt) of the kernel
+//!     * controller configuration without a running Bluetooth daemon
+//!     * index added and removed notifications
+//! * [HCI traffic capture](monitor_capture) through the kernel monitor channel
+//!     * btsnoop file writing compatible with Wireshark and btmon
+//!     * btsnoop and pcap file reading
+//! * [dissection of HCI traffic](dissect)
+//!     * reassembly of L2CAP frames from ACL fragments
+//!     * decoding of ATT, SMP and L2CAP signaling PDUs
 //! * [Bluetooth Mesh](mesh)
 //!     * provision and join networks
 //!     * send and receive messages
+//! * [fake Bluetooth daemon](mock) for testing without Bluetooth hardware
 //! * [database of assigned numbers](id)
 //!     * manufacturer ids
 //!     * services classes, GATT services, characteristics and descriptors
@@ -64,22 +116,40 @@
 //! ## Crate features
 //! The following crate features are available.
 //!
+//! * `ad`: Enables parsing and encoding of advertising data.
+//! * `att`: Enables the native GATT client over the ATT channel of L2CAP.
+//!   Together with the `bluetoothd` feature it also enables the native GATT server `att::Server`.
+//! * `beacon`: Enables the iBeacon, Eddystone and AltBeacon formats.
 //! * `bluetoothd`: Enables all functions requiring a running Bluetooth daemon.
+//! * `dissect`: Enables dissection of HCI traffic.
+//! * `hfp`: Enables the hands-free and headset profiles over RFCOMM.
 //! * `id`: Enables database of assigned numbers.
 //! * `l2cap`: Enables L2CAP sockets.
+//! * `macros`: Enables the derive macro for defining local GATT services.
 //! * `rfcomm`: Enables RFCOMM sockets.
+//! * `sco`: Enables SCO sockets.
+//! * `iso`: Enables ISO sockets.
 //! * `mesh`: Enables Bluetooth mesh functionality.
+//! * `mgmt`: Enables the Bluetooth Management API of the kernel.
+//! * `monitor_capture`: Enables capturing of HCI traffic.
+//! * `mock`: Enables a scriptable fake Bluetooth daemon for testing.
+//!   It is meant for dev-dependencies and thus not included in `full`.
+//! * `obex`: Enables the OBEX client for file transfer, object push, phonebook and message access.
+//! * `sbc`: Enables the SBC audio codec.
+//! * `sdp`: Enables SDP service records.
+//!   Together with the `l2cap` feature it also enables the SDP client `sdp::Client`
+//!   and together with the `bluetoothd` feature `Device::service_records`.
 //! * `serde`: Enables serialization and deserialization of some data types.
 //!
-//! To enable all crate features specify the `full` crate feature.
+//! To enable all crate features except `mock` specify the `full` crate feature.
 //!
 //! ## Basic usage
 //! Create a [Session] using [Session::new]; this establishes a connection to the Bluetooth daemon.
 //! Then obtain a Bluetooth adapter using [Session::adapter].
 //! From there on you can access most of the functionality using the methods provided by [Adapter].
 //!
-//! ## L2CAP and RFCOMM sockets
-//! Refer to the [l2cap] and [rfcomm] modules.
+//! ## L2CAP, RFCOMM, SCO and ISO sockets
+//! Refer to the [l2cap], [rfcomm], [sco] and [iso] modules.
 //! No [Session] and therefore no running Bluetooth daemon is required.
 //!
 //! [Linux Bluetooth protocol stack (BlueZ)]: http://www.bluez.org/
@@ -123,6 +193,8 @@ pub(crate) const SERVICE_NAME: &str = "org.bluez";
 #[cfg(feature = "bluetoothd")]
 pub(crate) const ERR_PREFIX: &str = "org.bluez.Error.";
 #[cfg(feature = "bluetoothd")]
+pub(crate) const OBEX_ERR_PREFIX: &str = "org.bluez.obex.Error.";
+#[cfg(feature = "bluetoothd")]
 pub(crate) const TIMEOUT: Duration = Duration::from_secs(120);
 
 #[cfg(feature = "bluetoothd")]
@@ -141,6 +213,10 @@ macro_rules! dbus_interface {
             R: for<'b> dbus::arg::Get<'b> + std::fmt::Debug + 'static,
         {
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
+            if let Some(Some(value)) = self.inner.cached_property::<R>(&self.proxy().path, interface, name) {
+                log::trace!("{}: {}.{} = {:?} (cached)", &self.proxy().path, &interface, &name, &value);
+                return Ok(value);
+            }
             let value = self.proxy().get(interface, name).await?;
             log::trace!("{}: {}.{} = {:?}", &self.proxy().path, &interface, &name, &value);
             Ok(value)
@@ -154,6 +230,10 @@ macro_rules! dbus_interface {
             R: for<'b> dbus::arg::Get<'b> + std::fmt::Debug + 'static,
         {
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
+            if let Some(value) = self.inner.cached_property::<R>(&self.proxy().path, interface, name) {
+                log::trace!("{}: {}.{} = {:?} (cached)", &self.proxy().path, &interface, &name, &value);
+                return Ok(value);
+            }
             match self.proxy().get(interface, name).await {
                 Ok(value) => {
                     log::trace!("{}: {}.{} = {:?}", &self.proxy().path, &interface, &name, &value);
@@ -175,6 +255,7 @@ macro_rules! dbus_interface {
             use dbus::nonblock::stdintf::org_freedesktop_dbus::Properties;
             log::trace!("{}: {}.{} := {:?}", &self.proxy().path, &interface, &name, &value);
             self.proxy().set(interface, name, value).await?;
+            self.inner.invalidate_cached_property(&self.proxy().path, interface, name);
             Ok(())
         }
 
@@ -379,7 +460,7 @@ macro_rules! define_properties {
             }
 
             #[allow(dead_code)]
-            fn from_prop_map(prop_map: dbus::arg::PropMap) -> Vec<Self> {
+            pub(crate) fn from_prop_map(prop_map: dbus::arg::PropMap) -> Vec<Self> {
                 prop_map.into_iter().filter_map(|(name, value)|
                     Self::from_variant_property(&name, value).ok().flatten()
                 ).collect()
@@ -465,6 +546,7 @@ macro_rules! define_flags {
     }) => {
         #[derive(Clone, Copy, Default, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
         #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(default))]
         #[doc=$doc]
         $vis struct $name {
             $(
@@ -514,10 +596,24 @@ macro_rules! read_opt_prop {
     };
 }
 
-#[cfg(any(feature = "l2cap", feature = "rfcomm"))]
+#[cfg(any(
+    feature = "l2cap",
+    feature = "rfcomm",
+    feature = "sco",
+    feature = "iso",
+    feature = "mgmt",
+    feature = "monitor_capture"
+))]
 #[macro_use]
 mod sock;
 
+// Allows the derive macros to refer to this crate in unit tests.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as bluer;
+
+#[cfg(feature = "ad")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ad")))]
+pub mod ad;
 #[cfg(feature = "bluetoothd")]
 mod adapter;
 #[cfg(feature = "bluetoothd")]
@@ -526,23 +622,65 @@ pub mod adv;
 #[cfg(feature = "bluetoothd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 pub mod agent;
+#[cfg(feature = "att")]
+#[cfg_attr(docsrs, doc(cfg(feature = "att")))]
+pub mod att;
+#[cfg(feature = "beacon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "beacon")))]
+pub mod beacon;
+#[cfg(feature = "sbc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sbc")))]
+pub mod codec;
 #[cfg(feature = "bluetoothd")]
 mod device;
+#[cfg(feature = "dissect")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dissect")))]
+pub mod dissect;
 #[cfg(feature = "bluetoothd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 pub mod gatt;
+#[cfg(feature = "hfp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hfp")))]
+pub mod hfp;
+#[cfg(feature = "iso")]
+#[cfg_attr(docsrs, doc(cfg(feature = "iso")))]
+pub mod iso;
 #[cfg(feature = "l2cap")]
 #[cfg_attr(docsrs, doc(cfg(feature = "l2cap")))]
 pub mod l2cap;
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod media;
 #[cfg(feature = "mesh")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mesh")))]
 pub mod mesh;
+#[cfg(feature = "mgmt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mgmt")))]
+pub mod mgmt;
+#[cfg(feature = "mock")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+pub mod mock;
 #[cfg(feature = "bluetoothd")]
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 pub mod monitor;
+#[cfg(feature = "monitor_capture")]
+#[cfg_attr(docsrs, doc(cfg(feature = "monitor_capture")))]
+pub mod monitor_capture;
+#[cfg(feature = "bluetoothd")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+pub mod network;
+#[cfg(feature = "obex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "obex")))]
+pub mod obex;
 #[cfg(feature = "rfcomm")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rfcomm")))]
 pub mod rfcomm;
+#[cfg(feature = "sco")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sco")))]
+pub mod sco;
+#[cfg(feature = "sdp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sdp")))]
+pub mod sdp;
 #[cfg(feature = "bluetoothd")]
 mod session;
 mod sys;
@@ -722,7 +860,7 @@ impl From<dbus::Error> for Error {
         }
         let kind = match err
             .name()
-            .and_then(|name| name.strip_prefix(ERR_PREFIX))
+            .and_then(|name| name.strip_prefix(ERR_PREFIX).or_else(|| name.strip_prefix(OBEX_ERR_PREFIX)))
             .and_then(|s| ErrorKind::from_str(s).ok())
         {
             Some(kind) => kind,
@@ -0,0 +1,611 @@
+//! A2DP codec capabilities and configurations.
+//!
+//! Capabilities and configurations share the same encoding.
+//! A configuration contains exactly one value for each of the sets in the capabilities.
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::collections::BTreeSet;
+
+/// A2DP codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum Codec {
+    /// Low complexity subband codec (SBC).
+    Sbc = 0x00,
+    /// MPEG-1,2 audio.
+    Mpeg12 = 0x01,
+    /// MPEG-2,4 advanced audio coding (AAC).
+    Aac = 0x02,
+    /// MPEG-D unified speech and audio coding (USAC).
+    Usac = 0x03,
+    /// ATRAC family.
+    Atrac = 0x04,
+    /// Vendor specific codec.
+    Vendor = 0xff,
+}
+
+/// SBC channel mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SbcChannelMode {
+    /// Mono.
+    Mono,
+    /// Dual channel.
+    DualChannel,
+    /// Stereo.
+    Stereo,
+    /// Joint stereo.
+    JointStereo,
+}
+
+/// SBC bit allocation method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SbcAllocationMethod {
+    /// Signal to noise ratio.
+    Snr,
+    /// Loudness.
+    Loudness,
+}
+
+const SBC_FREQUENCIES: [(u32, u8); 4] = [(16000, 0x80), (32000, 0x40), (44100, 0x20), (48000, 0x10)];
+const SBC_CHANNEL_MODES: [(SbcChannelMode, u8); 4] = [
+    (SbcChannelMode::Mono, 0x08),
+    (SbcChannelMode::DualChannel, 0x04),
+    (SbcChannelMode::Stereo, 0x02),
+    (SbcChannelMode::JointStereo, 0x01),
+];
+const SBC_BLOCK_LENGTHS: [(u8, u8); 4] = [(4, 0x80), (8, 0x40), (12, 0x20), (16, 0x10)];
+const SBC_SUBBANDS: [(u8, u8); 2] = [(4, 0x08), (8, 0x04)];
+const SBC_ALLOCATION_METHODS: [(SbcAllocationMethod, u8); 2] =
+    [(SbcAllocationMethod::Snr, 0x02), (SbcAllocationMethod::Loudness, 0x01)];
+
+/// SBC capabilities or configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SbcCapabilities {
+    /// Sampling frequencies in Hz.
+    ///
+    /// Supported are 16000, 32000, 44100 and 48000 Hz.
+    pub sampling_frequencies: BTreeSet<u32>,
+    /// Channel modes.
+    pub channel_modes: BTreeSet<SbcChannelMode>,
+    /// Block lengths.
+    ///
+    /// Supported are 4, 8, 12 and 16 blocks.
+    pub block_lengths: BTreeSet<u8>,
+    /// Number of subbands.
+    ///
+    /// Supported are 4 and 8 subbands.
+    pub subbands: BTreeSet<u8>,
+    /// Bit allocation methods.
+    pub allocation_methods: BTreeSet<SbcAllocationMethod>,
+    /// Minimum bitpool value.
+    pub min_bitpool: u8,
+    /// Maximum bitpool value.
+    pub max_bitpool: u8,
+}
+
+impl Default for SbcCapabilities {
+    /// All features with bitpool values from 2 to 53, as recommended for high quality stereo.
+    fn default() -> Self {
+        Self {
+            sampling_frequencies: SBC_FREQUENCIES.iter().map(|(v, _)| *v).collect(),
+            channel_modes: SBC_CHANNEL_MODES.iter().map(|(v, _)| *v).collect(),
+            block_lengths: SBC_BLOCK_LENGTHS.iter().map(|(v, _)| *v).collect(),
+            subbands: SBC_SUBBANDS.iter().map(|(v, _)| *v).collect(),
+            allocation_methods: SBC_ALLOCATION_METHODS.iter().map(|(v, _)| *v).collect(),
+            min_bitpool: 2,
+            max_bitpool: 53,
+        }
+    }
+}
+
+impl SbcCapabilities {
+    /// Length of encoded SBC capabilities.
+    pub const LEN: usize = 4;
+
+    /// Decodes SBC capabilities.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let [b0, b1, min_bitpool, max_bitpool] = data.try_into().ok()?;
+        Some(Self {
+            sampling_frequencies: from_bits(b0, &SBC_FREQUENCIES),
+            channel_modes: from_bits(b0, &SBC_CHANNEL_MODES),
+            block_lengths: from_bits(b1, &SBC_BLOCK_LENGTHS),
+            subbands: from_bits(b1, &SBC_SUBBANDS),
+            allocation_methods: from_bits(b1, &SBC_ALLOCATION_METHODS),
+            min_bitpool,
+            max_bitpool,
+        })
+    }
+
+    /// Encodes SBC capabilities.
+    ///
+    /// Unsupported values are ignored.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        vec![
+            to_bits(&self.sampling_frequencies, &SBC_FREQUENCIES)
+                | to_bits(&self.channel_modes, &SBC_CHANNEL_MODES),
+            to_bits(&self.block_lengths, &SBC_BLOCK_LENGTHS)
+                | to_bits(&self.subbands, &SBC_SUBBANDS)
+                | to_bits(&self.allocation_methods, &SBC_ALLOCATION_METHODS),
+            self.min_bitpool,
+            self.max_bitpool,
+        ]
+    }
+
+    /// Selects the highest quality configuration supported by both these
+    /// and the remote capabilities.
+    ///
+    /// Returns [None] if there is no common configuration.
+    pub fn select(&self, remote: &Self) -> Option<Self> {
+        let frequency = [44100, 48000, 32000, 16000]
+            .into_iter()
+            .find(|f| self.sampling_frequencies.contains(f) && remote.sampling_frequencies.contains(f))?;
+        let channel_mode = self.channel_modes.intersection(&remote.channel_modes).max()?;
+        let block_length = self.block_lengths.intersection(&remote.block_lengths).max()?;
+        let subbands = self.subbands.intersection(&remote.subbands).max()?;
+        let allocation_method = self.allocation_methods.intersection(&remote.allocation_methods).max()?;
+        let min_bitpool = self.min_bitpool.max(remote.min_bitpool);
+        let max_bitpool = self.max_bitpool.min(remote.max_bitpool);
+        if min_bitpool > max_bitpool {
+            return None;
+        }
+
+        Some(Self {
+            sampling_frequencies: [frequency].into(),
+            channel_modes: [*channel_mode].into(),
+            block_lengths: [*block_length].into(),
+            subbands: [*subbands].into(),
+            allocation_methods: [*allocation_method].into(),
+            min_bitpool,
+            max_bitpool,
+        })
+    }
+}
+
+/// AAC object type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum AacObjectType {
+    /// MPEG-2 AAC LC.
+    Mpeg2AacLc,
+    /// MPEG-4 AAC LC.
+    Mpeg4AacLc,
+    /// MPEG-4 AAC LTP.
+    Mpeg4AacLtp,
+    /// MPEG-4 AAC scalable.
+    Mpeg4AacScalable,
+    /// MPEG-4 HE-AAC.
+    Mpeg4HeAac,
+    /// MPEG-4 HE-AACv2.
+    Mpeg4HeAacV2,
+    /// MPEG-4 AAC-ELDv2.
+    Mpeg4AacEldV2,
+}
+
+const AAC_OBJECT_TYPES: [(AacObjectType, u8); 7] = [
+    (AacObjectType::Mpeg2AacLc, 0x80),
+    (AacObjectType::Mpeg4AacLc, 0x40),
+    (AacObjectType::Mpeg4AacLtp, 0x20),
+    (AacObjectType::Mpeg4AacScalable, 0x10),
+    (AacObjectType::Mpeg4HeAac, 0x08),
+    (AacObjectType::Mpeg4HeAacV2, 0x04),
+    (AacObjectType::Mpeg4AacEldV2, 0x02),
+];
+const AAC_FREQUENCIES: [(u32, u16); 12] = [
+    (8000, 0x800),
+    (11025, 0x400),
+    (12000, 0x200),
+    (16000, 0x100),
+    (22050, 0x080),
+    (24000, 0x040),
+    (32000, 0x020),
+    (44100, 0x010),
+    (48000, 0x008),
+    (64000, 0x004),
+    (88200, 0x002),
+    (96000, 0x001),
+];
+const AAC_CHANNELS: [(u8, u8); 2] = [(1, 0x08), (2, 0x04)];
+
+/// AAC capabilities or configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AacCapabilities {
+    /// Object types.
+    pub object_types: BTreeSet<AacObjectType>,
+    /// Sampling frequencies in Hz.
+    ///
+    /// Supported are 8000, 11025, 12000, 16000, 22050, 24000, 32000,
+    /// 44100, 48000, 64000, 88200 and 96000 Hz.
+    pub sampling_frequencies: BTreeSet<u32>,
+    /// Numbers of channels.
+    ///
+    /// Supported are 1 and 2 channels.
+    pub channels: BTreeSet<u8>,
+    /// Variable bit rate support.
+    pub vbr: bool,
+    /// Maximum bit rate in bits per second or zero, if unknown.
+    ///
+    /// Only the lower 23 bits are used.
+    pub bitrate: u32,
+}
+
+impl Default for AacCapabilities {
+    /// MPEG-2 and MPEG-4 AAC LC with common sampling frequencies, one or two channels and
+    /// variable bit rate of up to 320 kbit/s.
+    fn default() -> Self {
+        Self {
+            object_types: [AacObjectType::Mpeg2AacLc, AacObjectType::Mpeg4AacLc].into(),
+            sampling_frequencies: [44100, 48000].into(),
+            channels: [1, 2].into(),
+            vbr: true,
+            bitrate: 320_000,
+        }
+    }
+}
+
+impl AacCapabilities {
+    /// Length of encoded AAC capabilities.
+    pub const LEN: usize = 6;
+
+    /// Decodes AAC capabilities.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let [b0, b1, b2, b3, b4, b5] = data.try_into().ok()?;
+        let frequencies = (u16::from(b1) << 4) | (u16::from(b2) >> 4);
+        Some(Self {
+            object_types: from_bits(b0, &AAC_OBJECT_TYPES),
+            sampling_frequencies: from_bits(frequencies, &AAC_FREQUENCIES),
+            channels: from_bits(b2 & 0x0f, &AAC_CHANNELS),
+            vbr: b3 & 0x80 != 0,
+            bitrate: u32::from_be_bytes([0, b3 & 0x7f, b4, b5]),
+        })
+    }
+
+    /// Encodes AAC capabilities.
+    ///
+    /// Unsupported values are ignored.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let frequencies = to_bits(&self.sampling_frequencies, &AAC_FREQUENCIES);
+        let [_, b3, b4, b5] = (self.bitrate & 0x7f_ffff).to_be_bytes();
+        vec![
+            to_bits(&self.object_types, &AAC_OBJECT_TYPES),
+            (frequencies >> 4) as u8,
+            ((frequencies & 0x0f) as u8) << 4 | to_bits(&self.channels, &AAC_CHANNELS),
+            if self.vbr { 0x80 } else { 0 } | b3,
+            b4,
+            b5,
+        ]
+    }
+
+    /// Selects the highest quality configuration supported by both these
+    /// and the remote capabilities.
+    ///
+    /// Returns [None] if there is no common configuration.
+    pub fn select(&self, remote: &Self) -> Option<Self> {
+        let object_type = self.object_types.intersection(&remote.object_types).min()?;
+        let frequency = self.sampling_frequencies.intersection(&remote.sampling_frequencies).max()?;
+        let channels = self.channels.intersection(&remote.channels).max()?;
+        let bitrate = match (self.bitrate, remote.bitrate) {
+            (0, b) | (b, 0) => b,
+            (a, b) => a.min(b),
+        };
+
+        Some(Self {
+            object_types: [*object_type].into(),
+            sampling_frequencies: [*frequency].into(),
+            channels: [*channels].into(),
+            vbr: self.vbr && remote.vbr,
+            bitrate,
+        })
+    }
+}
+
+/// Vendor specific codec capabilities or configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VendorCapabilities {
+    /// Vendor id assigned by the Bluetooth SIG.
+    pub vendor_id: u32,
+    /// Vendor specific codec id.
+    pub codec_id: u16,
+    /// Codec specific capabilities.
+    pub data: Vec<u8>,
+}
+
+impl VendorCapabilities {
+    /// Decodes vendor specific codec capabilities.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 6 {
+            return None;
+        }
+        Some(Self {
+            vendor_id: u32::from_le_bytes(data[0..4].try_into().unwrap()),
+            codec_id: u16::from_le_bytes(data[4..6].try_into().unwrap()),
+            data: data[6..].to_vec(),
+        })
+    }
+
+    /// Encodes vendor specific codec capabilities.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(6 + self.data.len());
+        buf.extend_from_slice(&self.vendor_id.to_le_bytes());
+        buf.extend_from_slice(&self.codec_id.to_le_bytes());
+        buf.extend_from_slice(&self.data);
+        buf
+    }
+}
+
+/// Codec capabilities or configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Capabilities {
+    /// SBC.
+    Sbc(SbcCapabilities),
+    /// AAC.
+    Aac(AacCapabilities),
+    /// Vendor specific codec.
+    Vendor(VendorCapabilities),
+    /// Other codec with raw capabilities.
+    Other {
+        /// Codec.
+        codec: Codec,
+        /// Raw capabilities.
+        data: Vec<u8>,
+    },
+}
+
+impl Capabilities {
+    /// Codec.
+    pub fn codec(&self) -> Codec {
+        match self {
+            Self::Sbc(_) => Codec::Sbc,
+            Self::Aac(_) => Codec::Aac,
+            Self::Vendor(_) => Codec::Vendor,
+            Self::Other { codec, .. } => *codec,
+        }
+    }
+
+    /// Decodes the capabilities of the specified codec.
+    ///
+    /// Capabilities of SBC, AAC and vendor specific codecs must be well-formed.
+    /// Other codecs are returned with raw capabilities.
+    pub fn from_bytes(codec: u8, data: &[u8]) -> Option<Self> {
+        match Codec::from_u8(codec)? {
+            Codec::Sbc => SbcCapabilities::from_bytes(data).map(Self::Sbc),
+            Codec::Aac => AacCapabilities::from_bytes(data).map(Self::Aac),
+            Codec::Vendor => VendorCapabilities::from_bytes(data).map(Self::Vendor),
+            codec => Some(Self::Other { codec, data: data.to_vec() }),
+        }
+    }
+
+    /// Encodes the capabilities.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Sbc(caps) => caps.to_bytes(),
+            Self::Aac(caps) => caps.to_bytes(),
+            Self::Vendor(caps) => caps.to_bytes(),
+            Self::Other { data, .. } => data.clone(),
+        }
+    }
+
+    /// Selects the highest quality configuration supported by both these
+    /// and the remote capabilities.
+    ///
+    /// This is only supported for SBC and AAC.
+    /// Returns [None] if there is no common configuration or the codec is unsupported.
+    pub fn select(&self, remote: &Self) -> Option<Self> {
+        match (self, remote) {
+            (Self::Sbc(local), Self::Sbc(remote)) => local.select(remote).map(Self::Sbc),
+            (Self::Aac(local), Self::Aac(remote)) => local.select(remote).map(Self::Aac),
+            _ => None,
+        }
+    }
+}
+
+impl From<SbcCapabilities> for Capabilities {
+    fn from(caps: SbcCapabilities) -> Self {
+        Self::Sbc(caps)
+    }
+}
+
+impl From<AacCapabilities> for Capabilities {
+    fn from(caps: AacCapabilities) -> Self {
+        Self::Aac(caps)
+    }
+}
+
+impl From<VendorCapabilities> for Capabilities {
+    fn from(caps: VendorCapabilities) -> Self {
+        Self::Vendor(caps)
+    }
+}
+
+fn from_bits<T, B>(bits: B, table: &[(T, B)]) -> BTreeSet<T>
+where
+    T: Copy + Ord,
+    B: Copy + std::ops::BitAnd<Output = B> + PartialEq + Default,
+{
+    table.iter().filter(|(_, bit)| bits & *bit != B::default()).map(|(v, _)| *v).collect()
+}
+
+fn to_bits<T, B>(set: &BTreeSet<T>, table: &[(T, B)]) -> B
+where
+    T: Copy + Ord,
+    B: Copy + std::ops::BitOr<Output = B> + Default,
+{
+    table.iter().filter(|(v, _)| set.contains(v)).fold(B::default(), |acc, (_, bit)| acc | *bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sbc() {
+        assert_eq!(SbcCapabilities::default().to_bytes(), [0xff, 0xff, 2, 53]);
+        assert_eq!(SbcCapabilities::from_bytes(&[0xff, 0xff, 2, 53]), Some(SbcCapabilities::default()));
+
+        let caps = SbcCapabilities::from_bytes(&[0x48, 0x8a, 10, 30]).unwrap();
+        assert_eq!(
+            caps,
+            SbcCapabilities {
+                sampling_frequencies: [32000].into(),
+                channel_modes: [SbcChannelMode::Mono].into(),
+                block_lengths: [4].into(),
+                subbands: [4].into(),
+                allocation_methods: [SbcAllocationMethod::Snr].into(),
+                min_bitpool: 10,
+                max_bitpool: 30,
+            }
+        );
+        assert_eq!(caps.to_bytes(), [0x48, 0x8a, 10, 30]);
+
+        let unsupported = SbcCapabilities { sampling_frequencies: [22050, 48000].into(), ..caps };
+        assert_eq!(unsupported.to_bytes(), [0x18, 0x8a, 10, 30]);
+
+        assert_eq!(SbcCapabilities::from_bytes(&[0xff, 0xff, 2]), None);
+        assert_eq!(SbcCapabilities::from_bytes(&[0xff, 0xff, 2, 53, 0]), None);
+    }
+
+    #[test]
+    fn sbc_select() {
+        let local = SbcCapabilities::default();
+        assert_eq!(local.select(&local).unwrap().to_bytes(), [0x21, 0x15, 2, 53]);
+
+        let remote = SbcCapabilities::from_bytes(&[0x48, 0x8a, 10, 30]).unwrap();
+        assert_eq!(local.select(&remote).unwrap().to_bytes(), [0x48, 0x8a, 10, 30]);
+
+        let remote = SbcCapabilities::from_bytes(&[0x3f, 0xff, 2, 53]).unwrap();
+        assert_eq!(local.select(&remote).unwrap().to_bytes(), [0x21, 0x15, 2, 53]);
+
+        let local = SbcCapabilities::from_bytes(&[0x1f, 0xff, 2, 53]).unwrap();
+        let remote = SbcCapabilities::from_bytes(&[0x8f, 0xff, 2, 53]).unwrap();
+        assert_eq!(local.select(&remote), None);
+
+        let local = SbcCapabilities::from_bytes(&[0xff, 0xff, 2, 20]).unwrap();
+        let remote = SbcCapabilities::from_bytes(&[0xff, 0xff, 30, 53]).unwrap();
+        assert_eq!(local.select(&remote), None);
+    }
+
+    #[test]
+    fn aac() {
+        assert_eq!(AacCapabilities::default().to_bytes(), [0xc0, 0x01, 0x8c, 0x84, 0xe2, 0x00]);
+        assert_eq!(
+            AacCapabilities::from_bytes(&[0xc0, 0x01, 0x8c, 0x84, 0xe2, 0x00]),
+            Some(AacCapabilities::default())
+        );
+
+        let caps = AacCapabilities::from_bytes(&[0x40, 0x01, 0x04, 0x03, 0xe8, 0x00]).unwrap();
+        assert_eq!(
+            caps,
+            AacCapabilities {
+                object_types: [AacObjectType::Mpeg4AacLc].into(),
+                sampling_frequencies: [44100].into(),
+                channels: [2].into(),
+                vbr: false,
+                bitrate: 256_000,
+            }
+        );
+
+        let caps = AacCapabilities { bitrate: u32::MAX, ..caps };
+        assert_eq!(caps.to_bytes(), [0x40, 0x01, 0x04, 0x7f, 0xff, 0xff]);
+
+        assert_eq!(AacCapabilities::from_bytes(&[0xc0, 0x01, 0x8c, 0x84, 0xe2]), None);
+    }
+
+    #[test]
+    fn aac_sampling_frequencies() {
+        // The twelve frequency bits span the second octet and the upper half of the third octet.
+        let frequencies = [
+            (8000, [0x80, 0x00]),
+            (11025, [0x40, 0x00]),
+            (12000, [0x20, 0x00]),
+            (16000, [0x10, 0x00]),
+            (22050, [0x08, 0x00]),
+            (24000, [0x04, 0x00]),
+            (32000, [0x02, 0x00]),
+            (44100, [0x01, 0x00]),
+            (48000, [0x00, 0x80]),
+            (64000, [0x00, 0x40]),
+            (88200, [0x00, 0x20]),
+            (96000, [0x00, 0x10]),
+        ];
+        for (frequency, [b1, b2]) in frequencies {
+            let caps = AacCapabilities {
+                sampling_frequencies: [frequency].into(),
+                channels: [1].into(),
+                vbr: false,
+                bitrate: 0,
+                ..Default::default()
+            };
+            let bytes = [0xc0, b1, b2 | 0x08, 0, 0, 0];
+            assert_eq!(caps.to_bytes(), bytes, "{frequency} Hz");
+            assert_eq!(AacCapabilities::from_bytes(&bytes), Some(caps), "{frequency} Hz");
+        }
+
+        let all = AacCapabilities::from_bytes(&[0x80, 0xff, 0xf0, 0, 0, 0]).unwrap();
+        assert_eq!(all.sampling_frequencies.len(), 12);
+        assert!(all.channels.is_empty());
+    }
+
+    #[test]
+    fn aac_select() {
+        let local = AacCapabilities::default();
+        assert_eq!(local.select(&local).unwrap().to_bytes(), [0x80, 0x00, 0x84, 0x84, 0xe2, 0x00]);
+
+        let remote = AacCapabilities::from_bytes(&[0x40, 0x01, 0x04, 0x03, 0xe8, 0x00]).unwrap();
+        assert_eq!(local.select(&remote).unwrap().to_bytes(), [0x40, 0x01, 0x04, 0x03, 0xe8, 0x00]);
+
+        // Unknown bit rate of the remote device.
+        let remote = AacCapabilities::from_bytes(&[0xc0, 0x01, 0x8c, 0x80, 0x00, 0x00]).unwrap();
+        assert_eq!(local.select(&remote).unwrap().bitrate, 320_000);
+
+        let remote = AacCapabilities::from_bytes(&[0x20, 0x01, 0x8c, 0x84, 0xe2, 0x00]).unwrap();
+        assert_eq!(local.select(&remote), None);
+    }
+
+    #[test]
+    fn vendor() {
+        let bytes = [0x4f, 0x00, 0x00, 0x00, 0x01, 0x00, 0xff];
+        let caps = VendorCapabilities::from_bytes(&bytes).unwrap();
+        assert_eq!(caps, VendorCapabilities { vendor_id: 0x4f, codec_id: 0x0001, data: vec![0xff] });
+        assert_eq!(caps.to_bytes(), bytes);
+
+        let caps = VendorCapabilities::from_bytes(&bytes[..6]).unwrap();
+        assert!(caps.data.is_empty());
+        assert_eq!(VendorCapabilities::from_bytes(&bytes[..5]), None);
+    }
+
+    #[test]
+    fn capabilities() {
+        let sbc = Capabilities::from_bytes(0x00, &[0xff, 0xff, 2, 53]).unwrap();
+        assert_eq!(sbc, Capabilities::Sbc(SbcCapabilities::default()));
+        assert_eq!(sbc.codec(), Codec::Sbc);
+
+        let aac = Capabilities::from_bytes(0x02, &[0xc0, 0x01, 0x8c, 0x84, 0xe2, 0x00]).unwrap();
+        assert_eq!(aac, Capabilities::Aac(AacCapabilities::default()));
+        assert_eq!(aac.to_bytes(), [0xc0, 0x01, 0x8c, 0x84, 0xe2, 0x00]);
+
+        let vendor = Capabilities::from_bytes(0xff, &[0x4f, 0x00, 0x00, 0x00, 0x01, 0x00, 0xff]).unwrap();
+        assert_eq!(vendor.codec(), Codec::Vendor);
+
+        let mpeg = Capabilities::from_bytes(0x01, &[0x3c, 0x3f, 0xff, 0xfe]).unwrap();
+        assert_eq!(mpeg, Capabilities::Other { codec: Codec::Mpeg12, data: vec![0x3c, 0x3f, 0xff, 0xfe] });
+        assert_eq!(mpeg.to_bytes(), [0x3c, 0x3f, 0xff, 0xfe]);
+
+        assert_eq!(Capabilities::from_bytes(0x00, &[0xff, 0xff]), None);
+        assert_eq!(Capabilities::from_bytes(0x10, &[]), None);
+
+        assert_eq!(sbc.select(&sbc).unwrap().to_bytes(), [0x21, 0x15, 2, 53]);
+        assert_eq!(aac.select(&aac).unwrap().codec(), Codec::Aac);
+        assert_eq!(sbc.select(&aac), None);
+        assert_eq!(vendor.select(&vendor), None);
+    }
+}
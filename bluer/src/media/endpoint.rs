@@ -0,0 +1,304 @@
+//! Local media endpoints.
+
+use dbus::{
+    arg::{prop_cast, PropMap, Variant},
+    nonblock::Proxy,
+    Path,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+use futures::Future;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Arc, Weak},
+};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::{
+    a2dp::{Capabilities, Codec},
+    transport::Transport,
+    ReqError, ReqResult, INTERFACE as MEDIA_INTERFACE,
+};
+use crate::{method_call, Adapter, Address, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.MediaEndpoint1";
+pub(crate) const ENDPOINT_PREFIX: &str = publish_path!("media_endpoint/");
+
+/// Arguments for a select configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SelectConfiguration {
+    /// Adapter making the request.
+    pub adapter: String,
+    /// Capabilities of the remote endpoint.
+    pub capabilities: Capabilities,
+}
+
+/// Function handling a select configuration request.
+pub type SelectConfigurationFn = Box<
+    dyn (Fn(SelectConfiguration) -> Pin<Box<dyn Future<Output = ReqResult<Capabilities>> + Send>>) + Send + Sync,
+>;
+
+/// Arguments for a set configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SetConfiguration {
+    /// Adapter making the request.
+    pub adapter: String,
+    /// Address of remote device.
+    pub device: Address,
+    /// Media transport that has been configured.
+    pub transport: Transport,
+    /// Configuration of the media transport.
+    pub configuration: Capabilities,
+}
+
+/// Function handling a set configuration request.
+pub type SetConfigurationFn =
+    Box<dyn (Fn(SetConfiguration) -> Pin<Box<dyn Future<Output = ReqResult<()>> + Send>>) + Send + Sync>;
+
+/// Arguments for a clear configuration request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ClearConfiguration {
+    /// Adapter making the request.
+    pub adapter: String,
+    /// Address of remote device.
+    pub device: Address,
+    /// Media transport that is being removed.
+    pub transport: Transport,
+}
+
+/// Function handling a clear configuration request.
+pub type ClearConfigurationFn =
+    Box<dyn (Fn(ClearConfiguration) -> Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync>;
+
+/// Local media endpoint.
+///
+/// Use [Adapter::register_media_endpoint](crate::Adapter::register_media_endpoint) to register
+/// the endpoint.
+pub struct Endpoint {
+    /// Service class UUID of the profile the endpoint is for.
+    ///
+    /// Use [A2DP_SINK_UUID](super::A2DP_SINK_UUID) to receive audio and
+    /// [A2DP_SOURCE_UUID](super::A2DP_SOURCE_UUID) to send audio.
+    pub uuid: Uuid,
+    /// Supported codec and its capabilities.
+    pub capabilities: Capabilities,
+    /// Enables delay reporting.
+    ///
+    /// If enabled the delay of the media transport can be set.
+    pub delay_reporting: bool,
+    /// This method gets called when the service daemon
+    /// needs to select a configuration based on the
+    /// capabilities of a remote endpoint.
+    ///
+    /// If [None], the highest quality configuration supported by both
+    /// endpoints is [selected](Capabilities::select).
+    pub select_configuration: Option<SelectConfigurationFn>,
+    /// This method gets called when the service daemon
+    /// has configured a media transport for this endpoint.
+    ///
+    /// If [None], the configuration is accepted.
+    pub set_configuration: Option<SetConfigurationFn>,
+    /// This method gets called when the service daemon
+    /// removes a media transport configured for this endpoint.
+    pub clear_configuration: Option<ClearConfigurationFn>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl fmt::Debug for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("uuid", &self.uuid)
+            .field("capabilities", &self.capabilities)
+            .field("delay_reporting", &self.delay_reporting)
+            .finish()
+    }
+}
+
+impl Endpoint {
+    /// Creates a media endpoint for the specified profile UUID and codec capabilities
+    /// with default handlers.
+    pub fn new(uuid: Uuid, capabilities: impl Into<Capabilities>) -> Self {
+        Self {
+            uuid,
+            capabilities: capabilities.into(),
+            delay_reporting: false,
+            select_configuration: None,
+            set_configuration: None,
+            clear_configuration: None,
+            _non_exhaustive: (),
+        }
+    }
+
+    pub(crate) async fn register(
+        self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
+    ) -> Result<EndpointHandle> {
+        let name = Path::new(format!("{}{}", ENDPOINT_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        let uuid = self.uuid;
+        let codec = self.capabilities.codec() as u8;
+        let capabilities = self.capabilities.to_bytes();
+        let delay_reporting = self.delay_reporting;
+        log::trace!("Publishing media endpoint at {}", &name);
+
+        {
+            let mut cr = inner.crossroads.lock().await;
+            let reg =
+                RegisteredEndpoint { e: self, inner: Arc::downgrade(&inner), adapter_name: adapter_name.clone() };
+            cr.insert(name.clone(), &[inner.media_endpoint_token], Arc::new(reg));
+        }
+
+        log::trace!("Registering media endpoint at {}", &name);
+        let manager_path = Adapter::dbus_path(&adapter_name)?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_name = name.clone();
+        inner
+            .register_with_daemon(&name, Some(&adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_name = reg_name.clone();
+                let mut props = PropMap::new();
+                props.insert("UUID".to_string(), Variant(Box::new(uuid.to_string())));
+                props.insert("Codec".to_string(), Variant(Box::new(codec)));
+                props.insert("Capabilities".to_string(), Variant(Box::new(capabilities.clone())));
+                props.insert("DelayReporting".to_string(), Variant(Box::new(delay_reporting)));
+                async move {
+                    let () = proxy.method_call(MEDIA_INTERFACE, "RegisterEndpoint", (reg_name, props)).await?;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let unreg_name = name.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+
+            log::trace!("Unregistering media endpoint at {}", &unreg_name);
+            inner.unregister_with_daemon(&unreg_name).await;
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(MEDIA_INTERFACE, "UnregisterEndpoint", (unreg_name.clone(),)).await;
+
+            log::trace!("Unpublishing media endpoint at {}", &unreg_name);
+            let mut cr = inner.crossroads.lock().await;
+            let _: Option<Arc<RegisteredEndpoint>> = cr.remove(&unreg_name);
+        });
+
+        Ok(EndpointHandle { name, _drop_tx: drop_tx })
+    }
+}
+
+pub(crate) struct RegisteredEndpoint {
+    e: Endpoint,
+    inner: Weak<SessionInner>,
+    adapter_name: Arc<String>,
+}
+
+impl RegisteredEndpoint {
+    fn codec(&self) -> Codec {
+        self.e.capabilities.codec()
+    }
+
+    fn parse_capabilities(&self, data: &[u8]) -> ReqResult<Capabilities> {
+        match Capabilities::from_bytes(self.codec() as u8, data) {
+            Some(caps) => Ok(caps),
+            None => {
+                log::error!("Invalid {:?} capabilities: {:x?}", self.codec(), data);
+                Err(ReqError::InvalidArguments)
+            }
+        }
+    }
+
+    fn transport(&self, path: Path<'static>) -> ReqResult<(String, Address, Transport)> {
+        let inner = self.inner.upgrade().ok_or(ReqError::Rejected)?;
+        match Transport::new(inner, path.clone()) {
+            Ok(transport) => Ok((transport.adapter_name().to_string(), transport.device_address(), transport)),
+            Err(_) => {
+                log::error!("Cannot parse media transport path {}", &path);
+                Err(ReqError::InvalidArguments)
+            }
+        }
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            ib.method_with_cr_async(
+                "SelectConfiguration",
+                ("capabilities",),
+                ("configuration",),
+                |ctx, cr, (capabilities,): (Vec<u8>,)| {
+                    method_call(ctx, cr, move |reg: Arc<Self>| async move {
+                        let adapter = reg.adapter_name.to_string();
+                        let capabilities = reg.parse_capabilities(&capabilities)?;
+                        let configuration = match &reg.e.select_configuration {
+                            Some(f) => f(SelectConfiguration { adapter, capabilities }).await?,
+                            None => reg.e.capabilities.select(&capabilities).ok_or(ReqError::NotSupported)?,
+                        };
+                        if configuration.codec() != reg.codec() {
+                            log::error!("Selected configuration has codec {:?}", configuration.codec());
+                            return Err(ReqError::InvalidArguments.into());
+                        }
+                        Ok((configuration.to_bytes(),))
+                    })
+                },
+            );
+            ib.method_with_cr_async(
+                "SetConfiguration",
+                ("transport", "properties"),
+                (),
+                |ctx, cr, (transport, properties): (Path<'static>, PropMap)| {
+                    method_call(ctx, cr, move |reg: Arc<Self>| async move {
+                        let (adapter, device, transport) = reg.transport(transport)?;
+                        let configuration = prop_cast::<Vec<u8>>(&properties, "Configuration")
+                            .ok_or(ReqError::InvalidArguments)?;
+                        let configuration = reg.parse_capabilities(configuration)?;
+                        if let Some(f) = &reg.e.set_configuration {
+                            f(SetConfiguration { adapter, device, transport, configuration }).await?;
+                        }
+                        Ok(())
+                    })
+                },
+            );
+            ib.method_with_cr_async(
+                "ClearConfiguration",
+                ("transport",),
+                (),
+                |ctx, cr, (transport,): (Path<'static>,)| {
+                    method_call(ctx, cr, move |reg: Arc<Self>| async move {
+                        let (adapter, device, transport) = reg.transport(transport)?;
+                        if let Some(f) = &reg.e.clear_configuration {
+                            f(ClearConfiguration { adapter, device, transport }).await;
+                        }
+                        Ok(())
+                    })
+                },
+            );
+            ib.method_with_cr_async("Release", (), (), |ctx, cr, ()| {
+                method_call(ctx, cr, move |_reg: Arc<Self>| async move { Ok(()) })
+            });
+        })
+    }
+}
+
+/// Handle to registered media endpoint.
+///
+/// Drop to unregister media endpoint.
+#[must_use = "EndpointHandle must be held for media endpoint to be registered"]
+pub struct EndpointHandle {
+    name: Path<'static>,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl Drop for EndpointHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for EndpointHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EndpointHandle {{ {} }}", &self.name)
+    }
+}
@@ -0,0 +1,524 @@
+//! Local media players controllable by remote devices using AVRCP.
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    channel::Sender,
+    message::SignalArgs,
+    nonblock::{stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged, Proxy, SyncConnection},
+    Path,
+};
+use dbus_crossroads::{Crossroads, IfaceBuilder, IfaceToken};
+use futures::Future;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::{
+    player::{Repeat, Shuffle, Status, Track},
+    ReqError, ReqResult, INTERFACE as MEDIA_INTERFACE,
+};
+use crate::{method_call, Adapter, Error, ErrorKind, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+pub(crate) const PLAYER_PREFIX: &str = publish_path!("media_player/");
+
+/// Command sent by a remote controller to a local media player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PlayerCommand {
+    /// Start or resume playback.
+    Play,
+    /// Pause playback.
+    Pause,
+    /// Toggle between playing and paused.
+    PlayPause,
+    /// Stop playback.
+    Stop,
+    /// Skip to next track.
+    Next,
+    /// Skip to previous track.
+    Previous,
+    /// Change repeat setting.
+    SetRepeat(Repeat),
+    /// Change shuffle setting.
+    SetShuffle(Shuffle),
+}
+
+/// Arguments for a player command request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PlayerRequest {
+    /// Adapter the player is registered on.
+    pub adapter: String,
+    /// Command.
+    pub command: PlayerCommand,
+}
+
+/// Function handling a player command request.
+pub type PlayerCommandFn =
+    Box<dyn (Fn(PlayerRequest) -> Pin<Box<dyn Future<Output = ReqResult<()>> + Send>>) + Send + Sync>;
+
+/// Local media player.
+///
+/// The player is exposed to remote devices through AVRCP.
+/// Use [Adapter::register_media_player](crate::Adapter::register_media_player) to register
+/// the player and the returned [PlayerHandle] to update its state.
+pub struct Player {
+    /// Initial playback status.
+    pub status: Status,
+    /// Initial playback position.
+    pub position: Duration,
+    /// Initial track metadata.
+    pub track: Track,
+    /// Initial repeat setting.
+    pub repeat: Repeat,
+    /// Initial shuffle setting.
+    pub shuffle: Shuffle,
+    /// This method gets called when a remote controller sends a command.
+    ///
+    /// The repeat and shuffle settings are updated if the function succeeds.
+    /// If [None], all commands are rejected and the player reports that it
+    /// cannot be controlled.
+    pub command: Option<PlayerCommandFn>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            status: Status::Stopped,
+            position: Duration::ZERO,
+            track: Track::default(),
+            repeat: Repeat::Off,
+            shuffle: Shuffle::Off,
+            command: None,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl fmt::Debug for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Player")
+            .field("status", &self.status)
+            .field("position", &self.position)
+            .field("track", &self.track)
+            .field("repeat", &self.repeat)
+            .field("shuffle", &self.shuffle)
+            .finish()
+    }
+}
+
+impl Player {
+    pub(crate) async fn register(
+        self, inner: Arc<SessionInner>, adapter_name: Arc<String>,
+    ) -> Result<PlayerHandle> {
+        let name = Path::new(format!("{}{}", PLAYER_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        log::trace!("Publishing media player at {}", &name);
+
+        let state = PlayerState {
+            status: self.status,
+            position: self.position,
+            track: self.track.clone(),
+            repeat: self.repeat,
+            shuffle: self.shuffle,
+        };
+        let reg = Arc::new(RegisteredPlayer {
+            p: self,
+            adapter_name: adapter_name.clone(),
+            state: Mutex::new(state),
+            name: name.clone(),
+            connection: Arc::downgrade(&inner.connection),
+        });
+
+        {
+            let mut cr = inner.crossroads.lock().await;
+            cr.insert(name.clone(), &[inner.media_player_token], reg.clone());
+        }
+
+        log::trace!("Registering media player at {}", &name);
+        let manager_path = Adapter::dbus_path(&adapter_name)?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_name = name.clone();
+        let reg_player = Arc::downgrade(&reg);
+        inner
+            .register_with_daemon(&name, Some(&adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_name = reg_name.clone();
+                let props = reg_player.upgrade().map(|reg| reg.props()).unwrap_or_default();
+                async move {
+                    let () = proxy.method_call(MEDIA_INTERFACE, "RegisterPlayer", (reg_name, props)).await?;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let unreg_name = name.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+
+            log::trace!("Unregistering media player at {}", &unreg_name);
+            inner.unregister_with_daemon(&unreg_name).await;
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(MEDIA_INTERFACE, "UnregisterPlayer", (unreg_name.clone(),)).await;
+
+            log::trace!("Unpublishing media player at {}", &unreg_name);
+            let mut cr = inner.crossroads.lock().await;
+            let _: Option<Arc<RegisteredPlayer>> = cr.remove(&unreg_name);
+        });
+
+        Ok(PlayerHandle { reg, _drop_tx: drop_tx })
+    }
+}
+
+/// Current state of a registered player.
+struct PlayerState {
+    status: Status,
+    position: Duration,
+    track: Track,
+    repeat: Repeat,
+    shuffle: Shuffle,
+}
+
+fn variant<T: RefArg + 'static>(value: T) -> Variant<Box<dyn RefArg + 'static>> {
+    Variant(Box::new(value))
+}
+
+impl PlayerState {
+    fn playback_status(&self) -> &'static str {
+        match self.status {
+            Status::Playing | Status::ForwardSeek | Status::ReverseSeek => "Playing",
+            Status::Paused => "Paused",
+            _ => "Stopped",
+        }
+    }
+
+    fn loop_status(&self) -> &'static str {
+        match self.repeat {
+            Repeat::SingleTrack => "Track",
+            Repeat::AllTracks | Repeat::Group => "Playlist",
+            _ => "None",
+        }
+    }
+
+    fn position(&self) -> i64 {
+        self.position.as_micros().try_into().unwrap_or(i64::MAX)
+    }
+
+    fn metadata(&self) -> PropMap {
+        let track = &self.track;
+        let mut dict = PropMap::new();
+        dict.insert(
+            "mpris:trackid".to_string(),
+            variant(Path::from("/org/mpris/MediaPlayer2/TrackList/NoTrack")),
+        );
+        if let Some(title) = &track.title {
+            dict.insert("xesam:title".to_string(), variant(title.clone()));
+        }
+        if let Some(artist) = &track.artist {
+            dict.insert("xesam:artist".to_string(), variant(vec![artist.clone()]));
+        }
+        if let Some(album) = &track.album {
+            dict.insert("xesam:album".to_string(), variant(album.clone()));
+        }
+        if let Some(genre) = &track.genre {
+            dict.insert("xesam:genre".to_string(), variant(vec![genre.clone()]));
+        }
+        if let Some(number_of_tracks) = track.number_of_tracks {
+            dict.insert("xesam:totalTracks".to_string(), variant(number_of_tracks as i32));
+        }
+        if let Some(track_number) = track.track_number {
+            dict.insert("xesam:trackNumber".to_string(), variant(track_number as i32));
+        }
+        if let Some(duration) = track.duration {
+            dict.insert(
+                "mpris:length".to_string(),
+                variant(i64::try_from(duration.as_micros()).unwrap_or(i64::MAX)),
+            );
+        }
+        dict
+    }
+}
+
+pub(crate) struct RegisteredPlayer {
+    p: Player,
+    adapter_name: Arc<String>,
+    state: Mutex<PlayerState>,
+    name: Path<'static>,
+    connection: Weak<SyncConnection>,
+}
+
+impl RegisteredPlayer {
+    /// Player properties passed on registration.
+    fn props(&self) -> PropMap {
+        let state = self.state.lock().unwrap();
+        let mut props = PropMap::new();
+        props.insert("PlaybackStatus".to_string(), variant(state.playback_status().to_string()));
+        props.insert("LoopStatus".to_string(), variant(state.loop_status().to_string()));
+        props.insert("Shuffle".to_string(), variant(state.shuffle != Shuffle::Off));
+        props.insert("Position".to_string(), variant(state.position()));
+        props.insert("Metadata".to_string(), variant(state.metadata()));
+        props.insert("Rate".to_string(), variant(1.0f64));
+        for name in ["CanGoNext", "CanGoPrevious", "CanPlay", "CanPause", "CanControl"] {
+            props.insert(name.to_string(), variant(self.can_control()));
+        }
+        props.insert("CanSeek".to_string(), variant(false));
+        props
+    }
+
+    /// Whether remote controllers can send commands to the player.
+    fn can_control(&self) -> bool {
+        self.p.command.is_some()
+    }
+
+    /// Updates the state and notifies the Bluetooth daemon about the changed properties.
+    fn update(&self, f: impl FnOnce(&mut PlayerState) -> PropMap) -> Result<()> {
+        let changed_properties = {
+            let mut state = self.state.lock().unwrap();
+            f(&mut state)
+        };
+        let connection = self.connection.upgrade().ok_or_else(|| Error::new(ErrorKind::NotRegistered))?;
+        let ppc = PropertiesPropertiesChanged {
+            interface_name: INTERFACE.to_string(),
+            changed_properties,
+            invalidated_properties: Vec::new(),
+        };
+        connection.send(ppc.to_emit_message(&self.name)).map_err(|_| Error::new(ErrorKind::NotRegistered))?;
+        Ok(())
+    }
+
+    async fn command(&self, command: PlayerCommand) -> ReqResult<()> {
+        match &self.p.command {
+            Some(f) => f(PlayerRequest { adapter: self.adapter_name.to_string(), command }).await,
+            None => Err(ReqError::NotSupported),
+        }
+    }
+
+    pub(crate) fn register_interface(cr: &mut Crossroads) -> IfaceToken<Arc<Self>> {
+        cr.register(INTERFACE, |ib: &mut IfaceBuilder<Arc<Self>>| {
+            for (method, command) in [
+                ("Play", PlayerCommand::Play),
+                ("Pause", PlayerCommand::Pause),
+                ("PlayPause", PlayerCommand::PlayPause),
+                ("Stop", PlayerCommand::Stop),
+                ("Next", PlayerCommand::Next),
+                ("Previous", PlayerCommand::Previous),
+            ] {
+                ib.method_with_cr_async(method, (), (), move |ctx, cr, ()| {
+                    method_call(ctx, cr, move |reg: Arc<Self>| async move {
+                        reg.command(command).await?;
+                        Ok(())
+                    })
+                });
+            }
+            ib.method_with_cr_async("Seek", ("Offset",), (), |ctx, cr, (_offset,): (i64,)| {
+                method_call(ctx, cr, |_reg: Arc<Self>| async move { Err::<(), _>(ReqError::NotSupported.into()) })
+            });
+            ib.method_with_cr_async(
+                "SetPosition",
+                ("TrackId", "Position"),
+                (),
+                |ctx, cr, (_track_id, _position): (Path<'static>, i64)| {
+                    method_call(
+                        ctx,
+                        cr,
+                        |_reg: Arc<Self>| async move { Err::<(), _>(ReqError::NotSupported.into()) },
+                    )
+                },
+            );
+            ib.method_with_cr_async("OpenUri", ("Uri",), (), |ctx, cr, (_uri,): (String,)| {
+                method_call(ctx, cr, |_reg: Arc<Self>| async move { Err::<(), _>(ReqError::NotSupported.into()) })
+            });
+
+            cr_property!(ib, "PlaybackStatus", reg => {
+                Some(reg.state.lock().unwrap().playback_status().to_string())
+            });
+            ib.property("LoopStatus")
+                .get(|_ctx, reg| Ok(reg.state.lock().unwrap().loop_status().to_string()))
+                .set(|ctx, reg, value: String| {
+                    log::trace!("{}: {}.LoopStatus <- {}", ctx.path(), INTERFACE, &value);
+                    let repeat = match value.as_str() {
+                        "None" => Repeat::Off,
+                        "Track" => Repeat::SingleTrack,
+                        "Playlist" => Repeat::AllTracks,
+                        _ => return Err(ReqError::InvalidArguments.into()),
+                    };
+                    Self::spawn_setting(reg.clone(), PlayerCommand::SetRepeat(repeat));
+                    Ok(None)
+                });
+            ib.property("Shuffle").get(|_ctx, reg| Ok(reg.state.lock().unwrap().shuffle != Shuffle::Off)).set(
+                |ctx, reg, value: bool| {
+                    log::trace!("{}: {}.Shuffle <- {}", ctx.path(), INTERFACE, value);
+                    let shuffle = if value { Shuffle::AllTracks } else { Shuffle::Off };
+                    Self::spawn_setting(reg.clone(), PlayerCommand::SetShuffle(shuffle));
+                    Ok(None)
+                },
+            );
+            cr_property!(ib, "Position", reg => {
+                Some(reg.state.lock().unwrap().position())
+            });
+            cr_property!(ib, "Metadata", reg => {
+                Some(reg.state.lock().unwrap().metadata())
+            });
+            cr_property!(ib, "Rate", _reg => { Some(1.0f64) });
+            cr_property!(ib, "MinimumRate", _reg => { Some(1.0f64) });
+            cr_property!(ib, "MaximumRate", _reg => { Some(1.0f64) });
+            cr_property!(ib, "CanGoNext", reg => { Some(reg.can_control()) });
+            cr_property!(ib, "CanGoPrevious", reg => { Some(reg.can_control()) });
+            cr_property!(ib, "CanPlay", reg => { Some(reg.can_control()) });
+            cr_property!(ib, "CanPause", reg => { Some(reg.can_control()) });
+            cr_property!(ib, "CanSeek", _reg => { Some(false) });
+            cr_property!(ib, "CanControl", reg => { Some(reg.can_control()) });
+        })
+    }
+
+    /// Passes a setting change to the command function and applies it on success.
+    fn spawn_setting(reg: Arc<Self>, command: PlayerCommand) {
+        tokio::spawn(async move {
+            if let Err(err) = reg.command(command).await {
+                log::debug!("Media player setting {:?} rejected: {}", command, err);
+                return;
+            }
+            let _ = reg.update(|state| {
+                let mut changed = PropMap::new();
+                match command {
+                    PlayerCommand::SetRepeat(repeat) => {
+                        state.repeat = repeat;
+                        changed.insert("LoopStatus".to_string(), variant(state.loop_status().to_string()));
+                    }
+                    PlayerCommand::SetShuffle(shuffle) => {
+                        state.shuffle = shuffle;
+                        changed.insert("Shuffle".to_string(), variant(shuffle != Shuffle::Off));
+                    }
+                    _ => (),
+                }
+                changed
+            });
+        });
+    }
+}
+
+/// Handle to registered local media player.
+///
+/// Use it to update the state of the player.
+/// Drop to unregister the media player.
+#[must_use = "PlayerHandle must be held for media player to be registered"]
+pub struct PlayerHandle {
+    reg: Arc<RegisteredPlayer>,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl PlayerHandle {
+    /// Updates the playback status.
+    pub fn set_status(&self, status: Status) -> Result<()> {
+        self.reg.update(|state| {
+            state.status = status;
+            let mut changed = PropMap::new();
+            changed.insert("PlaybackStatus".to_string(), variant(state.playback_status().to_string()));
+            changed
+        })
+    }
+
+    /// Updates the playback position.
+    pub fn set_position(&self, position: Duration) -> Result<()> {
+        self.reg.update(|state| {
+            state.position = position;
+            let mut changed = PropMap::new();
+            changed.insert("Position".to_string(), variant(state.position()));
+            changed
+        })
+    }
+
+    /// Updates the metadata of the current track.
+    ///
+    /// The playback position is reset to zero.
+    pub fn set_track(&self, track: Track) -> Result<()> {
+        self.reg.update(|state| {
+            state.track = track;
+            state.position = Duration::ZERO;
+            let mut changed = PropMap::new();
+            changed.insert("Metadata".to_string(), variant(state.metadata()));
+            changed.insert("Position".to_string(), variant(state.position()));
+            changed
+        })
+    }
+
+    /// Updates the repeat setting.
+    pub fn set_repeat(&self, repeat: Repeat) -> Result<()> {
+        self.reg.update(|state| {
+            state.repeat = repeat;
+            let mut changed = PropMap::new();
+            changed.insert("LoopStatus".to_string(), variant(state.loop_status().to_string()));
+            changed
+        })
+    }
+
+    /// Updates the shuffle setting.
+    pub fn set_shuffle(&self, shuffle: Shuffle) -> Result<()> {
+        self.reg.update(|state| {
+            state.shuffle = shuffle;
+            let mut changed = PropMap::new();
+            changed.insert("Shuffle".to_string(), variant(shuffle != Shuffle::Off));
+            changed
+        })
+    }
+}
+
+impl Drop for PlayerHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for PlayerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PlayerHandle {{ {} }}", &self.reg.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered(p: Player) -> RegisteredPlayer {
+        let state = PlayerState {
+            status: p.status,
+            position: p.position,
+            track: p.track.clone(),
+            repeat: p.repeat,
+            shuffle: p.shuffle,
+        };
+        RegisteredPlayer {
+            p,
+            adapter_name: Arc::new("hci0".to_string()),
+            state: Mutex::new(state),
+            name: Path::new(format!("{PLAYER_PREFIX}test")).unwrap(),
+            connection: Weak::new(),
+        }
+    }
+
+    #[test]
+    fn controllable() {
+        let can = |reg: &RegisteredPlayer| {
+            let props = reg.props();
+            ["CanGoNext", "CanGoPrevious", "CanPlay", "CanPause", "CanControl"]
+                .map(|name| props[name].0.as_u64().unwrap() != 0)
+        };
+
+        let reg = registered(Player::default());
+        assert_eq!(can(&reg), [false; 5]);
+
+        let command: PlayerCommandFn = Box::new(|_| Box::pin(async { Ok(()) }));
+        let reg = registered(Player { command: Some(command), ..Default::default() });
+        assert_eq!(can(&reg), [true; 5]);
+        assert_eq!(reg.props()["CanSeek"].0.as_u64(), Some(0));
+    }
+}
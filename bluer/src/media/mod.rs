@@ -0,0 +1,64 @@
+//! Media endpoints and transports for audio streaming.
+//!
+//! A [local media endpoint](endpoint::Endpoint) is registered with the Bluetooth daemon
+//! using [Adapter::register_media_endpoint](crate::Adapter::register_media_endpoint).
+//! The Bluetooth daemon then negotiates the codec configuration with remote devices
+//! through the endpoint and, once a stream is configured, provides a
+//! [media transport](transport::Transport).
+//! The transport is acquired to obtain a socket for sending or receiving
+//! encoded audio packets.
+//!
+//! Media players of remote devices are controlled through AVRCP using
+//! [Device::media_players](crate::Device::media_players).
+//! A [local media player](local::Player) is made controllable by remote devices
+//! using [Adapter::register_media_player](crate::Adapter::register_media_player).
+
+use strum::IntoStaticStr;
+use uuid::Uuid;
+
+use crate::ERR_PREFIX;
+
+pub mod a2dp;
+pub mod endpoint;
+pub mod local;
+pub mod player;
+pub mod transport;
+
+pub(crate) const INTERFACE: &str = "org.bluez.Media1";
+
+/// A2DP audio source service class UUID.
+pub const A2DP_SOURCE_UUID: Uuid = Uuid::from_u128(0x0000110a_0000_1000_8000_00805f9b34fb);
+
+/// A2DP audio sink service class UUID.
+pub const A2DP_SINK_UUID: Uuid = Uuid::from_u128(0x0000110b_0000_1000_8000_00805f9b34fb);
+
+/// Error response from us to a Bluetooth media request.
+#[derive(Clone, Copy, Debug, displaydoc::Display, Eq, PartialEq, Ord, PartialOrd, Hash, IntoStaticStr)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ReqError {
+    /// Bluetooth media request rejected
+    Rejected,
+    /// Invalid arguments for Bluetooth media request
+    InvalidArguments,
+    /// Bluetooth media request not supported
+    NotSupported,
+}
+
+impl std::error::Error for ReqError {}
+
+impl Default for ReqError {
+    fn default() -> Self {
+        Self::Rejected
+    }
+}
+
+impl From<ReqError> for dbus::MethodErr {
+    fn from(err: ReqError) -> Self {
+        let name: &'static str = err.into();
+        Self::from((ERR_PREFIX.to_string() + name, &err.to_string()))
+    }
+}
+
+/// Result of a Bluetooth media request to us.
+pub type ReqResult<T> = std::result::Result<T, ReqError>;
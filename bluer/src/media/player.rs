@@ -0,0 +1,554 @@
+//! Media players of remote devices controlled using AVRCP.
+
+use dbus::{
+    arg::{prop_cast, PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use futures::{stream, Stream, StreamExt};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+use strum::{Display, EnumString};
+
+use crate::{Address, Device, Error, ErrorKind, Event, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.MediaPlayer1";
+pub(crate) const FOLDER_INTERFACE: &str = "org.bluez.MediaFolder1";
+pub(crate) const ITEM_INTERFACE: &str = "org.bluez.MediaItem1";
+
+/// Playback status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Status {
+    /// Playing.
+    #[strum(serialize = "playing")]
+    Playing,
+    /// Stopped.
+    #[strum(serialize = "stopped")]
+    Stopped,
+    /// Paused.
+    #[strum(serialize = "paused")]
+    Paused,
+    /// Seeking forward.
+    #[strum(serialize = "forward-seek")]
+    ForwardSeek,
+    /// Seeking backward.
+    #[strum(serialize = "reverse-seek")]
+    ReverseSeek,
+    /// Error.
+    #[strum(serialize = "error")]
+    Error,
+}
+
+/// Repeat setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Repeat {
+    /// Repeat disabled.
+    #[strum(serialize = "off")]
+    Off,
+    /// Repeat current track.
+    #[strum(serialize = "singletrack")]
+    SingleTrack,
+    /// Repeat all tracks.
+    #[strum(serialize = "alltracks")]
+    AllTracks,
+    /// Repeat current group.
+    #[strum(serialize = "group")]
+    Group,
+}
+
+/// Shuffle setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Shuffle {
+    /// Shuffle disabled.
+    #[strum(serialize = "off")]
+    Off,
+    /// Shuffle all tracks.
+    #[strum(serialize = "alltracks")]
+    AllTracks,
+    /// Shuffle current group.
+    #[strum(serialize = "group")]
+    Group,
+}
+
+/// Track metadata.
+///
+/// Each field is [None] if the information is not provided.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    /// Track title.
+    pub title: Option<String>,
+    /// Track artist.
+    pub artist: Option<String>,
+    /// Track album.
+    pub album: Option<String>,
+    /// Track genre.
+    pub genre: Option<String>,
+    /// Number of tracks in total.
+    pub number_of_tracks: Option<u32>,
+    /// Track number.
+    pub track_number: Option<u32>,
+    /// Track duration.
+    pub duration: Option<Duration>,
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub _non_exhaustive: (),
+}
+
+impl Track {
+    pub(crate) fn from_dict(dict: &PropMap) -> Self {
+        let string = |key: &str| prop_cast::<String>(dict, key).cloned();
+        let number = |key: &str| prop_cast::<u32>(dict, key).cloned();
+        Self {
+            title: string("Title"),
+            artist: string("Artist"),
+            album: string("Album"),
+            genre: string("Genre"),
+            number_of_tracks: number("NumberOfTracks"),
+            track_number: number("TrackNumber").or_else(|| number("Number")),
+            duration: number("Duration").map(|ms| Duration::from_millis(ms.into())),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+/// Media item type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ItemType {
+    /// Video.
+    #[strum(serialize = "video")]
+    Video,
+    /// Audio.
+    #[strum(serialize = "audio")]
+    Audio,
+    /// Folder.
+    #[strum(serialize = "folder")]
+    Folder,
+}
+
+/// Media folder type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FolderType {
+    /// Mixed content.
+    #[strum(serialize = "mixed")]
+    Mixed,
+    /// Titles.
+    #[strum(serialize = "titles")]
+    Titles,
+    /// Albums.
+    #[strum(serialize = "albums")]
+    Albums,
+    /// Artists.
+    #[strum(serialize = "artists")]
+    Artists,
+}
+
+/// Interface to a media player of a remote device.
+///
+/// Use [Device::media_players](crate::Device::media_players) to obtain the players
+/// of a connected device.
+#[derive(Clone)]
+pub struct Player {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    adapter_name: Arc<String>,
+    device_address: Address,
+    name: String,
+}
+
+impl fmt::Debug for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Player {{ adapter_name: {}, device_address: {}, name: {} }}",
+            self.adapter_name(),
+            self.device_address(),
+            self.name()
+        )
+    }
+}
+
+impl Player {
+    pub(crate) fn new(
+        inner: Arc<SessionInner>, adapter_name: Arc<String>, device_address: Address, name: &str,
+    ) -> Result<Self> {
+        let device_path = Device::dbus_path(&adapter_name, device_address)?;
+        let dbus_path = Path::new(format!("{device_path}/{name}"))
+            .map_err(|_| Error::new(ErrorKind::InvalidName(name.to_string())))?;
+        Ok(Self { inner, dbus_path, adapter_name, device_address, name: name.to_string() })
+    }
+
+    pub(crate) fn parse_dbus_path<'a>(path: &'a Path) -> Option<((&'a str, Address), &'a str)> {
+        match Device::parse_dbus_path_prefix(path) {
+            Some((device, p)) => match p.strip_prefix('/') {
+                Some(name) if name.starts_with("player") && !name.contains('/') => Some((device, name)),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// Name of adapter.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Address of remote device.
+    pub fn device_address(&self) -> Address {
+        self.device_address
+    }
+
+    /// Name of the D-Bus object of the player, for example `player0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Streams player property changes.
+    ///
+    /// This includes changes of playback status, position and track.
+    ///
+    /// The stream ends when the player is removed.
+    pub async fn events(&self) -> Result<impl Stream<Item = PlayerEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { interface, changed, .. } if interface == INTERFACE => {
+                stream::iter(PlayerProperty::from_prop_map(changed).into_iter().map(PlayerEvent::PropertyChanged))
+                    .boxed()
+            }
+            _ => stream::empty().boxed(),
+        });
+
+        Ok(stream)
+    }
+
+    /// Root folder of the media library of the player.
+    ///
+    /// The player must be [browsable](Self::is_browsable).
+    pub fn folder(&self) -> Folder {
+        Folder { inner: self.inner.clone(), dbus_path: self.dbus_path.clone() }
+    }
+
+    /// Resume playback.
+    pub async fn play(&self) -> Result<()> {
+        self.call_method("Play", ()).await
+    }
+
+    /// Pause playback.
+    pub async fn pause(&self) -> Result<()> {
+        self.call_method("Pause", ()).await
+    }
+
+    /// Stop playback.
+    pub async fn stop(&self) -> Result<()> {
+        self.call_method("Stop", ()).await
+    }
+
+    /// Next item.
+    pub async fn next(&self) -> Result<()> {
+        self.call_method("Next", ()).await
+    }
+
+    /// Previous item.
+    pub async fn previous(&self) -> Result<()> {
+        self.call_method("Previous", ()).await
+    }
+
+    /// Fast forward playback, this action is only stopped
+    /// when another method in this interface is called.
+    pub async fn fast_forward(&self) -> Result<()> {
+        self.call_method("FastForward", ()).await
+    }
+
+    /// Rewind playback, this action is only stopped
+    /// when another method in this interface is called.
+    pub async fn rewind(&self) -> Result<()> {
+        self.call_method("Rewind", ()).await
+    }
+
+    /// Press a specific key to send as passthrough command.
+    ///
+    /// The key will be released automatically. Use [hold](Self::hold)
+    /// instead if the intention is to hold down the key.
+    pub async fn press(&self, avc_key: u8) -> Result<()> {
+        self.call_method("Press", (avc_key,)).await
+    }
+
+    /// Press and hold a specific key to send as passthrough command.
+    ///
+    /// It is the caller's responsibility to make sure that [release](Self::release)
+    /// is called after calling this method.
+    pub async fn hold(&self, avc_key: u8) -> Result<()> {
+        self.call_method("Hold", (avc_key,)).await
+    }
+
+    /// Release the previously held key invoked using [hold](Self::hold).
+    pub async fn release(&self) -> Result<()> {
+        self.call_method("Release", ()).await
+    }
+}
+
+define_properties!(
+    Player,
+    /// Media player property.
+    pub PlayerProperty => {
+        /// Player name.
+        property(
+            Name, String,
+            dbus: (INTERFACE, "Name", String, OPTIONAL),
+            get: (player_name, v => {v.to_owned()}),
+        );
+
+        /// Player type, for example `Audio`.
+        property(
+            Type, String,
+            dbus: (INTERFACE, "Type", String, OPTIONAL),
+            get: (player_type, v => {v.to_owned()}),
+        );
+
+        /// Player subtype, for example `Podcast`.
+        property(
+            Subtype, String,
+            dbus: (INTERFACE, "Subtype", String, OPTIONAL),
+            get: (player_subtype, v => {v.to_owned()}),
+        );
+
+        /// Playback status.
+        property(
+            Status, Status,
+            dbus: (INTERFACE, "Status", String, MANDATORY),
+            get: (status, v => {v.parse()?}),
+        );
+
+        /// Playback position.
+        ///
+        /// Changes are not signalled continuously during playback,
+        /// but only when the position is changed explicitly or the
+        /// playback status changes.
+        property(
+            Position, Duration,
+            dbus: (INTERFACE, "Position", u32, MANDATORY),
+            get: (position, v => {Duration::from_millis((*v).into())}),
+        );
+
+        /// Metadata of the current track.
+        property(
+            Track, Track,
+            dbus: (INTERFACE, "Track", HashMap<String, Variant<Box<dyn RefArg + 'static>>>, OPTIONAL),
+            get: (track, v => {Track::from_dict(v)}),
+        );
+
+        /// Repeat setting.
+        property(
+            Repeat, Repeat,
+            dbus: (INTERFACE, "Repeat", String, OPTIONAL),
+            get: (repeat, v => {v.parse()?}),
+            set: (set_repeat, v => {v.to_string()}),
+        );
+
+        /// Shuffle setting.
+        property(
+            Shuffle, Shuffle,
+            dbus: (INTERFACE, "Shuffle", String, OPTIONAL),
+            get: (shuffle, v => {v.parse()?}),
+            set: (set_shuffle, v => {v.to_string()}),
+        );
+
+        /// Indicates if the media library of the player can be browsed using
+        /// [Player::folder].
+        property(
+            Browsable, bool,
+            dbus: (INTERFACE, "Browsable", bool, OPTIONAL),
+            get: (is_browsable, v => {v.to_owned()}),
+        );
+
+        /// Indicates if the media library of the player can be searched using
+        /// [Folder::search].
+        property(
+            Searchable, bool,
+            dbus: (INTERFACE, "Searchable", bool, OPTIONAL),
+            get: (is_searchable, v => {v.to_owned()}),
+        );
+    }
+);
+
+/// Media player event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PlayerEvent {
+    /// Property changed.
+    PropertyChanged(PlayerProperty),
+}
+
+/// Range of media items to list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ItemFilter {
+    /// Offset of the first item.
+    pub start: Option<u32>,
+    /// Offset of the last item.
+    pub end: Option<u32>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+/// Interface to a folder of the media library of a remote player.
+#[derive(Clone)]
+pub struct Folder {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Folder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Folder {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Folder {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(FOLDER_INTERFACE);
+
+    /// Return a folder containing the search result.
+    ///
+    /// The player must be [searchable](Player::is_searchable).
+    pub async fn search(&self, value: &str) -> Result<Folder> {
+        let (path,): (Path<'static>,) = self.call_method("Search", (value, PropMap::new())).await?;
+        Ok(Folder { inner: self.inner.clone(), dbus_path: path })
+    }
+
+    /// Return a list of items found in the current folder.
+    pub async fn list_items(&self, filter: ItemFilter) -> Result<Vec<Item>> {
+        let mut dict = PropMap::new();
+        if let Some(start) = filter.start {
+            dict.insert("Start".to_string(), Variant(Box::new(start)));
+        }
+        if let Some(end) = filter.end {
+            dict.insert("End".to_string(), Variant(Box::new(end)));
+        }
+        let (items,): (HashMap<Path<'static>, PropMap>,) = self.call_method("ListItems", (dict,)).await?;
+        let mut items: Vec<_> =
+            items.into_keys().map(|path| Item { inner: self.inner.clone(), dbus_path: path }).collect();
+        items.sort_by(|a, b| a.dbus_path.cmp(&b.dbus_path));
+        Ok(items)
+    }
+
+    /// Change current folder to the specified folder item.
+    ///
+    /// Subsequent [list_items](Self::list_items) calls return the contents of that folder.
+    pub async fn change_folder(&self, folder: &Item) -> Result<()> {
+        self.call_method("ChangeFolder", (folder.dbus_path.clone(),)).await
+    }
+}
+
+define_properties!(
+    Folder,
+    /// Media folder property.
+    pub FolderProperty => {
+        /// Number of items in the folder.
+        property(
+            NumberOfItems, u32,
+            dbus: (FOLDER_INTERFACE, "NumberOfItems", u32, MANDATORY),
+            get: (number_of_items, v => {v.to_owned()}),
+        );
+
+        /// Folder name, for example `/Filesystem/Music`.
+        property(
+            Name, String,
+            dbus: (FOLDER_INTERFACE, "Name", String, MANDATORY),
+            get: (name, v => {v.to_owned()}),
+        );
+    }
+);
+
+/// Interface to an item of the media library of a remote player.
+#[derive(Clone)]
+pub struct Item {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Item {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Item {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(ITEM_INTERFACE);
+
+    /// Play the item.
+    pub async fn play(&self) -> Result<()> {
+        self.call_method("Play", ()).await
+    }
+
+    /// Add the item to the now playing list.
+    pub async fn add_to_now_playing(&self) -> Result<()> {
+        self.call_method("AddtoNowPlaying", ()).await
+    }
+}
+
+define_properties!(
+    Item,
+    /// Media item property.
+    pub ItemProperty => {
+        /// Item displayable name.
+        property(
+            Name, String,
+            dbus: (ITEM_INTERFACE, "Name", String, MANDATORY),
+            get: (name, v => {v.to_owned()}),
+        );
+
+        /// Item type.
+        property(
+            Type, ItemType,
+            dbus: (ITEM_INTERFACE, "Type", String, MANDATORY),
+            get: (item_type, v => {v.parse()?}),
+        );
+
+        /// Folder type, if the item is a folder.
+        property(
+            FolderType, FolderType,
+            dbus: (ITEM_INTERFACE, "FolderType", String, OPTIONAL),
+            get: (folder_type, v => {v.parse()?}),
+        );
+
+        /// Indicates if the item can be played.
+        property(
+            Playable, bool,
+            dbus: (ITEM_INTERFACE, "Playable", bool, OPTIONAL),
+            get: (is_playable, v => {v.to_owned()}),
+        );
+
+        /// Metadata of the item.
+        property(
+            Metadata, Track,
+            dbus: (ITEM_INTERFACE, "Metadata", HashMap<String, Variant<Box<dyn RefArg + 'static>>>, OPTIONAL),
+            get: (metadata, v => {Track::from_dict(v)}),
+        );
+    }
+);
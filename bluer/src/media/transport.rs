@@ -0,0 +1,323 @@
+//! Media transports of remote devices.
+
+use dbus::{
+    arg::OwnedFd,
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use futures::{stream, Stream, StreamExt};
+use num_traits::FromPrimitive;
+use std::{
+    fmt,
+    os::unix::io::{FromRawFd, IntoRawFd, RawFd},
+    sync::Arc,
+};
+use strum::{Display, EnumString};
+use tokio::net::UnixDatagram;
+use uuid::Uuid;
+
+use super::a2dp::{Capabilities, Codec};
+use crate::{
+    gatt::try_send_packet, Address, Device, Error, ErrorKind, Event, InternalErrorKind, Result, SessionInner,
+    SERVICE_NAME, TIMEOUT,
+};
+
+pub(crate) const INTERFACE: &str = "org.bluez.MediaTransport1";
+
+/// Media transport state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransportState {
+    /// Not streaming.
+    #[strum(serialize = "idle")]
+    Idle,
+    /// Streaming but not acquired.
+    #[strum(serialize = "pending")]
+    Pending,
+    /// Streaming and acquired.
+    #[strum(serialize = "active")]
+    Active,
+    /// Broadcasting but not acquired.
+    #[strum(serialize = "broadcasting")]
+    Broadcasting,
+}
+
+/// Interface to a media transport of a remote device.
+///
+/// A media transport is provided by the Bluetooth daemon to a
+/// [local media endpoint](super::endpoint::Endpoint) once a stream has been configured.
+#[derive(Clone)]
+pub struct Transport {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    adapter_name: String,
+    device_address: Address,
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transport {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Transport {
+    pub(crate) fn new(inner: Arc<SessionInner>, dbus_path: Path<'static>) -> Result<Self> {
+        let ((adapter_name, device_address), _) = Device::parse_dbus_path_prefix(&dbus_path)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidName(dbus_path.to_string())))?;
+        let adapter_name = adapter_name.to_string();
+        Ok(Self { inner, dbus_path, adapter_name, device_address })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// Name of adapter.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Address of remote device.
+    pub fn device_address(&self) -> Address {
+        self.device_address
+    }
+
+    /// D-Bus object path of the media transport.
+    pub fn path(&self) -> &str {
+        &self.dbus_path
+    }
+
+    /// Streams media transport property changes.
+    ///
+    /// The stream ends when the media transport is removed.
+    pub async fn events(&self) -> Result<impl Stream<Item = TransportEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { changed, .. } => stream::iter(
+                TransportProperty::from_prop_map(changed).into_iter().map(TransportEvent::PropertyChanged),
+            )
+            .boxed(),
+            _ => stream::empty().boxed(),
+        });
+
+        Ok(stream)
+    }
+
+    /// Decoded codec configuration of the media transport.
+    pub async fn codec_configuration(&self) -> Result<Capabilities> {
+        let codec: u8 = self.get_property("Codec").await?;
+        let configuration: Vec<u8> = self.get_property("Configuration").await?;
+        Capabilities::from_bytes(codec, &configuration)
+            .ok_or_else(|| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))
+    }
+
+    /// Acquire the media transport, waiting for the stream to become ready.
+    ///
+    /// The media transport must be released using [release](Self::release)
+    /// when it is not needed anymore.
+    pub async fn acquire(&self) -> Result<TransportIo> {
+        let (fd, read_mtu, write_mtu): (OwnedFd, u16, u16) = self.call_method("Acquire", ()).await?;
+        self.make_io(fd, read_mtu, write_mtu)
+    }
+
+    /// Acquire the media transport only if it is in [pending](TransportState::Pending) or
+    /// [broadcasting](TransportState::Broadcasting) state.
+    ///
+    /// Fails with [ErrorKind::NotAvailable] if the media transport is idle.
+    ///
+    /// The media transport must be released using [release](Self::release)
+    /// when it is not needed anymore.
+    pub async fn try_acquire(&self) -> Result<TransportIo> {
+        let (fd, read_mtu, write_mtu): (OwnedFd, u16, u16) = self.call_method("TryAcquire", ()).await?;
+        self.make_io(fd, read_mtu, write_mtu)
+    }
+
+    /// Release the media transport.
+    pub async fn release(&self) -> Result<()> {
+        self.call_method("Release", ()).await
+    }
+
+    fn make_io(&self, fd: OwnedFd, read_mtu: u16, write_mtu: u16) -> Result<TransportIo> {
+        let socket = unsafe { std::os::unix::net::UnixDatagram::from_raw_fd(fd.into_fd()) };
+        socket.set_nonblocking(true)?;
+        let socket = UnixDatagram::from_std(socket)?;
+        Ok(TransportIo {
+            adapter_name: self.adapter_name.clone(),
+            device_address: self.device_address,
+            read_mtu: read_mtu.into(),
+            write_mtu: write_mtu.into(),
+            socket,
+        })
+    }
+}
+
+define_properties!(
+    Transport,
+    /// Media transport property.
+    pub TransportProperty => {
+        /// Service class UUID of the profile the transport is for.
+        property(
+            Uuid, Uuid,
+            dbus: (INTERFACE, "UUID", String, MANDATORY),
+            get: (uuid, v => {v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidUuid(v.to_string()))))?}),
+        );
+
+        /// Codec of the transport.
+        property(
+            Codec, Codec,
+            dbus: (INTERFACE, "Codec", u8, MANDATORY),
+            get: (codec, v => {Codec::from_u8(*v).ok_or_else(|| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidValue)))?}),
+        );
+
+        /// Encoded codec configuration.
+        ///
+        /// Use [Transport::codec_configuration] to obtain the decoded configuration.
+        property(
+            Configuration, Vec<u8>,
+            dbus: (INTERFACE, "Configuration", Vec<u8>, MANDATORY),
+            get: (configuration, v => {v.to_owned()}),
+        );
+
+        /// State of the transport.
+        property(
+            State, TransportState,
+            dbus: (INTERFACE, "State", String, MANDATORY),
+            get: (state, v => {v.parse()?}),
+        );
+
+        /// Transport delay in 1/10 of millisecond.
+        ///
+        /// This property is only present if the delay reporting
+        /// feature is enabled on the endpoint.
+        property(
+            Delay, u16,
+            dbus: (INTERFACE, "Delay", u16, OPTIONAL),
+            get: (delay, v => {v.to_owned()}),
+            set: (set_delay, v => {v}),
+        );
+
+        /// Transport volume from 0 to 127.
+        ///
+        /// This property is only present if the remote device supports
+        /// absolute volume control.
+        property(
+            Volume, u16,
+            dbus: (INTERFACE, "Volume", u16, OPTIONAL),
+            get: (volume, v => {v.to_owned()}),
+            set: (set_volume, v => {v}),
+        );
+    }
+);
+
+/// Media transport event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransportEvent {
+    /// Property changed.
+    PropertyChanged(TransportProperty),
+}
+
+/// Socket of an acquired media transport.
+///
+/// Each packet sent or received contains encoded audio data, for A2DP
+/// prefixed by an RTP header.
+pub struct TransportIo {
+    adapter_name: String,
+    device_address: Address,
+    read_mtu: usize,
+    write_mtu: usize,
+    socket: UnixDatagram,
+}
+
+impl fmt::Debug for TransportIo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TransportIo")
+            .field("adapter_name", &self.adapter_name)
+            .field("device_address", &self.device_address)
+            .field("read_mtu", &self.read_mtu)
+            .field("write_mtu", &self.write_mtu)
+            .finish()
+    }
+}
+
+impl TransportIo {
+    /// Name of adapter.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Address of remote device.
+    pub fn device_address(&self) -> Address {
+        self.device_address
+    }
+
+    /// Maximum size of a received packet.
+    pub fn read_mtu(&self) -> usize {
+        self.read_mtu
+    }
+
+    /// Maximum size of a sent packet.
+    pub fn write_mtu(&self) -> usize {
+        self.write_mtu
+    }
+
+    /// Waits for a packet to become available for receiving.
+    pub async fn recvable(&self) -> std::io::Result<()> {
+        self.socket.readable().await
+    }
+
+    /// Tries to receive a packet.
+    ///
+    /// The buffer should be at least [Self::read_mtu] bytes long.
+    ///
+    /// Does not wait for a packet to become available.
+    pub fn try_recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.socket.try_recv(buf)
+    }
+
+    /// Receives a packet.
+    ///
+    /// The buffer should be at least [Self::read_mtu] bytes long.
+    pub async fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.socket.recv(buf).await
+    }
+
+    /// Waits for send space to become available.
+    pub async fn sendable(&self) -> std::io::Result<()> {
+        self.socket.writable().await
+    }
+
+    /// Tries to send a packet.
+    ///
+    /// The length of `buf` must not exceed [Self::write_mtu].
+    ///
+    /// Does not wait for send space to become available.
+    pub fn try_send(&self, buf: &[u8]) -> std::io::Result<()> {
+        try_send_packet(&self.socket, buf, self.write_mtu)
+    }
+
+    /// Sends a packet.
+    ///
+    /// The length of `buf` must not exceed [Self::write_mtu].
+    ///
+    /// Waits for send space to become available.
+    pub async fn send(&self, buf: &[u8]) -> std::io::Result<()> {
+        loop {
+            self.sendable().await?;
+            match self.try_send(buf) {
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => continue,
+                res => return res,
+            }
+        }
+    }
+
+    /// Consumes this object, returning the raw underlying file descriptor.
+    pub fn into_raw_fd(self) -> std::io::Result<RawFd> {
+        Ok(self.socket.into_std()?.into_raw_fd())
+    }
+}
@@ -0,0 +1,1459 @@
+//! Bluetooth Management (mgmt) interface of the Linux kernel.
+//!
+//! The management interface provides controller-level configuration, such as
+//! power, enabling classic Bluetooth (BR/EDR) and Bluetooth Low Energy (LE),
+//! Secure Simple Pairing, Secure Connections, privacy, addresses, names,
+//! the device accept list and LE connection parameters.
+//! It talks directly to the kernel and thus does not require a running Bluetooth daemon.
+//!
+//! Open the interface using [Management::new], enumerate controllers using
+//! [Management::index_list] and then configure a controller
+//! through the [Controller] obtained by [Management::controller].
+//!
+//! Access to the management interface requires the `CAP_NET_ADMIN` capability.
+//! If the Bluetooth daemon is running concurrently, it will observe and possibly
+//! react to changes made through this interface.
+//!
+
+use futures::{
+    channel::{mpsc, oneshot},
+    Stream, StreamExt,
+};
+use libc::{AF_BLUETOOTH, SOCK_RAW};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::io::{unix::AsyncFd, ReadBuf};
+
+use crate::{
+    sock::{self, HciAddr, OwnedFd},
+    sys::{BTPROTO_HCI, HCI_CHANNEL_CONTROL, HCI_DEV_NONE},
+    Address, AddressType,
+};
+
+/// Controller index used for commands and events that do not refer to a specific controller.
+pub const INDEX_NONE: u16 = HCI_DEV_NONE;
+
+/// Length of the packet header.
+const HEADER_LEN: usize = 6;
+
+/// Maximum length of a local name, excluding terminating zero.
+pub const MAX_NAME_LEN: usize = 248;
+
+/// Maximum length of a short local name, excluding terminating zero.
+pub const MAX_SHORT_NAME_LEN: usize = 10;
+
+/// Command opcodes.
+pub mod opcode {
+    /// Read Management Version Information.
+    pub const READ_VERSION: u16 = 0x0001;
+    /// Read Management Supported Commands.
+    pub const READ_COMMANDS: u16 = 0x0002;
+    /// Read Controller Index List.
+    pub const READ_INDEX_LIST: u16 = 0x0003;
+    /// Read Controller Information.
+    pub const READ_INFO: u16 = 0x0004;
+    /// Set Powered.
+    pub const SET_POWERED: u16 = 0x0005;
+    /// Set Discoverable.
+    pub const SET_DISCOVERABLE: u16 = 0x0006;
+    /// Set Connectable.
+    pub const SET_CONNECTABLE: u16 = 0x0007;
+    /// Set Fast Connectable.
+    pub const SET_FAST_CONNECTABLE: u16 = 0x0008;
+    /// Set Bondable.
+    pub const SET_BONDABLE: u16 = 0x0009;
+    /// Set Link Security.
+    pub const SET_LINK_SECURITY: u16 = 0x000a;
+    /// Set Secure Simple Pairing.
+    pub const SET_SSP: u16 = 0x000b;
+    /// Set High Speed.
+    pub const SET_HS: u16 = 0x000c;
+    /// Set Low Energy.
+    pub const SET_LE: u16 = 0x000d;
+    /// Set Device Class.
+    pub const SET_DEV_CLASS: u16 = 0x000e;
+    /// Set Local Name.
+    pub const SET_LOCAL_NAME: u16 = 0x000f;
+    /// Disconnect.
+    pub const DISCONNECT: u16 = 0x0014;
+    /// Start Discovery.
+    pub const START_DISCOVERY: u16 = 0x0023;
+    /// Stop Discovery.
+    pub const STOP_DISCOVERY: u16 = 0x0024;
+    /// Set Advertising.
+    pub const SET_ADVERTISING: u16 = 0x0029;
+    /// Set BR/EDR.
+    pub const SET_BREDR: u16 = 0x002a;
+    /// Set Static Address.
+    pub const SET_STATIC_ADDRESS: u16 = 0x002b;
+    /// Set Secure Connections.
+    pub const SET_SECURE_CONN: u16 = 0x002d;
+    /// Set Privacy.
+    pub const SET_PRIVACY: u16 = 0x002f;
+    /// Add Device.
+    pub const ADD_DEVICE: u16 = 0x0033;
+    /// Remove Device.
+    pub const REMOVE_DEVICE: u16 = 0x0034;
+    /// Load Connection Parameters.
+    pub const LOAD_CONN_PARAM: u16 = 0x0035;
+    /// Read Unconfigured Controller Index List.
+    pub const READ_UNCONF_INDEX_LIST: u16 = 0x0036;
+    /// Set Appearance.
+    pub const SET_APPEARANCE: u16 = 0x0043;
+}
+
+/// Event codes.
+pub mod event_code {
+    /// Command Complete.
+    pub const CMD_COMPLETE: u16 = 0x0001;
+    /// Command Status.
+    pub const CMD_STATUS: u16 = 0x0002;
+    /// Controller Error.
+    pub const CONTROLLER_ERROR: u16 = 0x0003;
+    /// Index Added.
+    pub const INDEX_ADDED: u16 = 0x0004;
+    /// Index Removed.
+    pub const INDEX_REMOVED: u16 = 0x0005;
+    /// New Settings.
+    pub const NEW_SETTINGS: u16 = 0x0006;
+    /// Class Of Device Changed.
+    pub const CLASS_OF_DEV_CHANGED: u16 = 0x0007;
+    /// Local Name Changed.
+    pub const LOCAL_NAME_CHANGED: u16 = 0x0008;
+    /// Device Connected.
+    pub const DEVICE_CONNECTED: u16 = 0x000b;
+    /// Device Disconnected.
+    pub const DEVICE_DISCONNECTED: u16 = 0x000c;
+    /// Connect Failed.
+    pub const CONNECT_FAILED: u16 = 0x000d;
+    /// Authentication Failed.
+    pub const AUTH_FAILED: u16 = 0x0011;
+    /// Device Found.
+    pub const DEVICE_FOUND: u16 = 0x0012;
+    /// Discovering.
+    pub const DISCOVERING: u16 = 0x0013;
+    /// Device Unpaired.
+    pub const DEVICE_UNPAIRED: u16 = 0x0016;
+    /// Device Added.
+    pub const DEVICE_ADDED: u16 = 0x001a;
+    /// Device Removed.
+    pub const DEVICE_REMOVED: u16 = 0x001b;
+    /// New Connection Parameter.
+    pub const NEW_CONN_PARAM: u16 = 0x001c;
+    /// Unconfigured Index Added.
+    pub const UNCONF_INDEX_ADDED: u16 = 0x001d;
+    /// Unconfigured Index Removed.
+    pub const UNCONF_INDEX_REMOVED: u16 = 0x001e;
+    /// Advertising Added.
+    pub const ADVERTISING_ADDED: u16 = 0x0023;
+    /// Advertising Removed.
+    pub const ADVERTISING_REMOVED: u16 = 0x0024;
+}
+
+/// Status code returned by the kernel for a management command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Status {
+    /// Success.
+    Success,
+    /// Unknown command.
+    UnknownCommand,
+    /// Not connected.
+    NotConnected,
+    /// Failed.
+    Failed,
+    /// Connect failed.
+    ConnectFailed,
+    /// Authentication failed.
+    AuthenticationFailed,
+    /// Not paired.
+    NotPaired,
+    /// No resources.
+    NoResources,
+    /// Timeout.
+    Timeout,
+    /// Already connected.
+    AlreadyConnected,
+    /// Busy.
+    Busy,
+    /// Rejected.
+    Rejected,
+    /// Not supported.
+    NotSupported,
+    /// Invalid parameters.
+    InvalidParameters,
+    /// Disconnected.
+    Disconnected,
+    /// Not powered.
+    NotPowered,
+    /// Cancelled.
+    Cancelled,
+    /// Invalid index.
+    InvalidIndex,
+    /// Blocked through rfkill.
+    RfKilled,
+    /// Already paired.
+    AlreadyPaired,
+    /// Permission denied.
+    PermissionDenied,
+    /// Unknown status code.
+    Unknown(u8),
+}
+
+impl From<u8> for Status {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Success,
+            0x01 => Self::UnknownCommand,
+            0x02 => Self::NotConnected,
+            0x03 => Self::Failed,
+            0x04 => Self::ConnectFailed,
+            0x05 => Self::AuthenticationFailed,
+            0x06 => Self::NotPaired,
+            0x07 => Self::NoResources,
+            0x08 => Self::Timeout,
+            0x09 => Self::AlreadyConnected,
+            0x0a => Self::Busy,
+            0x0b => Self::Rejected,
+            0x0c => Self::NotSupported,
+            0x0d => Self::InvalidParameters,
+            0x0e => Self::Disconnected,
+            0x0f => Self::NotPowered,
+            0x10 => Self::Cancelled,
+            0x11 => Self::InvalidIndex,
+            0x12 => Self::RfKilled,
+            0x13 => Self::AlreadyPaired,
+            0x14 => Self::PermissionDenied,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Success => 0x00,
+            Status::UnknownCommand => 0x01,
+            Status::NotConnected => 0x02,
+            Status::Failed => 0x03,
+            Status::ConnectFailed => 0x04,
+            Status::AuthenticationFailed => 0x05,
+            Status::NotPaired => 0x06,
+            Status::NoResources => 0x07,
+            Status::Timeout => 0x08,
+            Status::AlreadyConnected => 0x09,
+            Status::Busy => 0x0a,
+            Status::Rejected => 0x0b,
+            Status::NotSupported => 0x0c,
+            Status::InvalidParameters => 0x0d,
+            Status::Disconnected => 0x0e,
+            Status::NotPowered => 0x0f,
+            Status::Cancelled => 0x10,
+            Status::InvalidIndex => 0x11,
+            Status::RfKilled => 0x12,
+            Status::AlreadyPaired => 0x13,
+            Status::PermissionDenied => 0x14,
+            Status::Unknown(other) => other,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::Success => "success",
+            Self::UnknownCommand => "unknown command",
+            Self::NotConnected => "not connected",
+            Self::Failed => "failed",
+            Self::ConnectFailed => "connect failed",
+            Self::AuthenticationFailed => "authentication failed",
+            Self::NotPaired => "not paired",
+            Self::NoResources => "no resources",
+            Self::Timeout => "timeout",
+            Self::AlreadyConnected => "already connected",
+            Self::Busy => "busy",
+            Self::Rejected => "rejected",
+            Self::NotSupported => "not supported",
+            Self::InvalidParameters => "invalid parameters",
+            Self::Disconnected => "disconnected",
+            Self::NotPowered => "not powered",
+            Self::Cancelled => "cancelled",
+            Self::InvalidIndex => "invalid index",
+            Self::RfKilled => "blocked through rfkill",
+            Self::AlreadyPaired => "already paired",
+            Self::PermissionDenied => "permission denied",
+            Self::Unknown(other) => return write!(f, "unknown status 0x{other:02x}"),
+        };
+        write!(f, "{msg}")
+    }
+}
+
+/// A management command failed.
+///
+/// This is returned as the inner error of the [std::io::Error] returned by
+/// management commands.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CommandError {
+    /// Command opcode.
+    pub opcode: u16,
+    /// Status returned by the kernel.
+    pub status: Status,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "management command 0x{:04x} failed: {}", self.opcode, self.status)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<CommandError> for Error {
+    fn from(err: CommandError) -> Self {
+        let kind = match err.status {
+            Status::NotConnected | Status::Disconnected => ErrorKind::NotConnected,
+            Status::Timeout => ErrorKind::TimedOut,
+            Status::NotSupported | Status::UnknownCommand => ErrorKind::Unsupported,
+            Status::InvalidParameters => ErrorKind::InvalidInput,
+            Status::InvalidIndex => ErrorKind::NotFound,
+            Status::PermissionDenied => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Other,
+        };
+        Error::new(kind, err)
+    }
+}
+
+/// Controller settings.
+///
+/// Used for both supported and current settings of a controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Settings {
+    /// Powered.
+    pub powered: bool,
+    /// Connectable.
+    pub connectable: bool,
+    /// Fast connectable.
+    pub fast_connectable: bool,
+    /// Discoverable.
+    pub discoverable: bool,
+    /// Bondable.
+    pub bondable: bool,
+    /// Link level security.
+    pub link_security: bool,
+    /// Secure Simple Pairing.
+    pub ssp: bool,
+    /// Classic Bluetooth (BR/EDR).
+    pub br_edr: bool,
+    /// High speed.
+    pub high_speed: bool,
+    /// Bluetooth Low Energy.
+    pub le: bool,
+    /// Advertising.
+    pub advertising: bool,
+    /// Secure Connections.
+    pub secure_connections: bool,
+    /// Debug keys.
+    pub debug_keys: bool,
+    /// Privacy.
+    pub privacy: bool,
+    /// Controller configuration.
+    pub configuration: bool,
+    /// Static address.
+    pub static_address: bool,
+    /// PHY configuration.
+    pub phy_configuration: bool,
+    /// Wideband speech.
+    pub wideband_speech: bool,
+    /// Connected isochronous stream central.
+    pub cis_central: bool,
+    /// Connected isochronous stream peripheral.
+    pub cis_peripheral: bool,
+    /// Isochronous broadcaster.
+    pub iso_broadcaster: bool,
+    /// Synchronized receiver.
+    pub iso_sync_receiver: bool,
+    /// Link layer privacy.
+    pub ll_privacy: bool,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Settings {
+    /// Decodes settings from their bit representation.
+    pub fn from_bits(bits: u32) -> Self {
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Self {
+            powered: bit(0),
+            connectable: bit(1),
+            fast_connectable: bit(2),
+            discoverable: bit(3),
+            bondable: bit(4),
+            link_security: bit(5),
+            ssp: bit(6),
+            br_edr: bit(7),
+            high_speed: bit(8),
+            le: bit(9),
+            advertising: bit(10),
+            secure_connections: bit(11),
+            debug_keys: bit(12),
+            privacy: bit(13),
+            configuration: bit(14),
+            static_address: bit(15),
+            phy_configuration: bit(16),
+            wideband_speech: bit(17),
+            cis_central: bit(18),
+            cis_peripheral: bit(19),
+            iso_broadcaster: bit(20),
+            iso_sync_receiver: bit(21),
+            ll_privacy: bit(22),
+            _non_exhaustive: (),
+        }
+    }
+
+    /// Encodes settings into their bit representation.
+    pub fn to_bits(&self) -> u32 {
+        [
+            self.powered,
+            self.connectable,
+            self.fast_connectable,
+            self.discoverable,
+            self.bondable,
+            self.link_security,
+            self.ssp,
+            self.br_edr,
+            self.high_speed,
+            self.le,
+            self.advertising,
+            self.secure_connections,
+            self.debug_keys,
+            self.privacy,
+            self.configuration,
+            self.static_address,
+            self.phy_configuration,
+            self.wideband_speech,
+            self.cis_central,
+            self.cis_peripheral,
+            self.iso_broadcaster,
+            self.iso_sync_receiver,
+            self.ll_privacy,
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(0, |bits, (n, set)| if set { bits | (1 << n) } else { bits })
+    }
+}
+
+/// Management interface version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version {
+    /// Version.
+    pub version: u8,
+    /// Revision.
+    pub revision: u16,
+}
+
+/// Commands and events supported by the management interface.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SupportedCommands {
+    /// Supported command opcodes.
+    pub commands: Vec<u16>,
+    /// Supported event codes.
+    pub events: Vec<u16>,
+}
+
+/// Controller information.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ControllerInfo {
+    /// Public address.
+    pub address: Address,
+    /// Bluetooth core specification version.
+    pub bluetooth_version: u8,
+    /// Manufacturer id.
+    pub manufacturer: u16,
+    /// Settings supported by the controller.
+    pub supported_settings: Settings,
+    /// Currently active settings.
+    pub current_settings: Settings,
+    /// Class of device.
+    pub class_of_device: u32,
+    /// Local name.
+    pub name: String,
+    /// Short local name.
+    pub short_name: String,
+}
+
+/// Privacy mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Privacy {
+    /// Privacy disabled.
+    Disabled = 0x00,
+    /// Privacy enabled.
+    Enabled = 0x01,
+    /// Limited privacy.
+    ///
+    /// The identity address is used when the controller is discoverable.
+    Limited = 0x02,
+}
+
+/// Secure Connections mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SecureConnections {
+    /// Secure Connections disabled.
+    Disabled = 0x00,
+    /// Secure Connections enabled.
+    Enabled = 0x01,
+    /// Secure Connections Only mode.
+    Only = 0x02,
+}
+
+/// Action taken for a device on the accept list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddDeviceAction {
+    /// Scan in the background and report the device.
+    BackgroundScan = 0x00,
+    /// Allow incoming connections.
+    AllowIncoming = 0x01,
+    /// Automatically connect when the device is seen.
+    AutoConnect = 0x02,
+}
+
+/// LE connection parameters for a device.
+///
+/// Intervals are specified in units of 1.25 ms and the supervision timeout
+/// is specified in units of 10 ms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConnParams {
+    /// Device address.
+    pub address: Address,
+    /// Device address type.
+    pub address_type: AddressType,
+    /// Minimum connection interval.
+    pub min_interval: u16,
+    /// Maximum connection interval.
+    pub max_interval: u16,
+    /// Peripheral latency.
+    pub latency: u16,
+    /// Supervision timeout.
+    pub timeout: u16,
+}
+
+impl ConnParams {
+    const LEN: usize = 15;
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        put_address(buf, self.address, self.address_type);
+        buf.extend_from_slice(&self.min_interval.to_le_bytes());
+        buf.extend_from_slice(&self.max_interval.to_le_bytes());
+        buf.extend_from_slice(&self.latency.to_le_bytes());
+        buf.extend_from_slice(&self.timeout.to_le_bytes());
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let (address, address_type) = get_address(data)?;
+        Some(Self {
+            address,
+            address_type,
+            min_interval: get_u16(data, 7)?,
+            max_interval: get_u16(data, 9)?,
+            latency: get_u16(data, 11)?,
+            timeout: get_u16(data, 13)?,
+        })
+    }
+}
+
+/// Management event.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Event {
+    /// A controller has been added.
+    IndexAdded,
+    /// A controller has been removed.
+    IndexRemoved,
+    /// An unconfigured controller has been added.
+    UnconfiguredIndexAdded,
+    /// An unconfigured controller has been removed.
+    UnconfiguredIndexRemoved,
+    /// A controller error occurred.
+    ControllerError(u8),
+    /// Current settings changed.
+    NewSettings(Settings),
+    /// Class of device changed.
+    ClassOfDeviceChanged(u32),
+    /// Local name changed.
+    LocalNameChanged {
+        /// Local name.
+        name: String,
+        /// Short local name.
+        short_name: String,
+    },
+    /// A device connected.
+    DeviceConnected {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Connection flags.
+        flags: u32,
+        /// Extended inquiry response or advertising data.
+        eir: Vec<u8>,
+    },
+    /// A device disconnected.
+    DeviceDisconnected {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Disconnection reason.
+        reason: u8,
+    },
+    /// Connecting to a device failed.
+    ConnectFailed {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Failure status.
+        status: Status,
+    },
+    /// Authentication with a device failed.
+    AuthenticationFailed {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Failure status.
+        status: Status,
+    },
+    /// A device was found during discovery.
+    DeviceFound {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Received signal strength in dBm.
+        rssi: i8,
+        /// Flags.
+        flags: u32,
+        /// Extended inquiry response or advertising data.
+        eir: Vec<u8>,
+    },
+    /// Discovery state changed.
+    Discovering {
+        /// Bit mask of address types being discovered.
+        address_types: u8,
+        /// Whether discovery is active.
+        discovering: bool,
+    },
+    /// A device has been unpaired.
+    DeviceUnpaired {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+    },
+    /// A device has been added to the accept list.
+    DeviceAdded {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+        /// Action.
+        action: u8,
+    },
+    /// A device has been removed from the accept list.
+    DeviceRemoved {
+        /// Device address.
+        address: Address,
+        /// Device address type.
+        address_type: AddressType,
+    },
+    /// New LE connection parameters are in use.
+    NewConnParams {
+        /// Whether the parameters should be stored persistently.
+        store_hint: bool,
+        /// Connection parameters.
+        params: ConnParams,
+    },
+    /// An advertising instance has been added.
+    AdvertisingAdded(u8),
+    /// An advertising instance has been removed.
+    AdvertisingRemoved(u8),
+    /// Other or malformed event.
+    Other {
+        /// Event code.
+        code: u16,
+        /// Event parameters.
+        data: Vec<u8>,
+    },
+}
+
+impl Event {
+    /// Parses an event from its code and parameters.
+    pub fn parse(code: u16, data: &[u8]) -> Self {
+        Self::parse_known(code, data).unwrap_or_else(|| Self::Other { code, data: data.to_vec() })
+    }
+
+    fn parse_known(code: u16, data: &[u8]) -> Option<Self> {
+        use event_code::*;
+        let event = match code {
+            INDEX_ADDED => Self::IndexAdded,
+            INDEX_REMOVED => Self::IndexRemoved,
+            UNCONF_INDEX_ADDED => Self::UnconfiguredIndexAdded,
+            UNCONF_INDEX_REMOVED => Self::UnconfiguredIndexRemoved,
+            CONTROLLER_ERROR => Self::ControllerError(*data.first()?),
+            NEW_SETTINGS => Self::NewSettings(Settings::from_bits(get_u32(data, 0)?)),
+            CLASS_OF_DEV_CHANGED => Self::ClassOfDeviceChanged(get_u24(data, 0)?),
+            LOCAL_NAME_CHANGED => {
+                let (name, short_name) = get_names(data)?;
+                Self::LocalNameChanged { name, short_name }
+            }
+            DEVICE_CONNECTED => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceConnected { address, address_type, flags: get_u32(data, 7)?, eir: get_eir(data, 11)? }
+            }
+            DEVICE_DISCONNECTED => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceDisconnected { address, address_type, reason: *data.get(7)? }
+            }
+            CONNECT_FAILED => {
+                let (address, address_type) = get_address(data)?;
+                Self::ConnectFailed { address, address_type, status: (*data.get(7)?).into() }
+            }
+            AUTH_FAILED => {
+                let (address, address_type) = get_address(data)?;
+                Self::AuthenticationFailed { address, address_type, status: (*data.get(7)?).into() }
+            }
+            DEVICE_FOUND => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceFound {
+                    address,
+                    address_type,
+                    rssi: *data.get(7)? as i8,
+                    flags: get_u32(data, 8)?,
+                    eir: get_eir(data, 12)?,
+                }
+            }
+            DISCOVERING => Self::Discovering { address_types: *data.first()?, discovering: *data.get(1)? != 0 },
+            DEVICE_UNPAIRED => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceUnpaired { address, address_type }
+            }
+            DEVICE_ADDED => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceAdded { address, address_type, action: *data.get(7)? }
+            }
+            DEVICE_REMOVED => {
+                let (address, address_type) = get_address(data)?;
+                Self::DeviceRemoved { address, address_type }
+            }
+            NEW_CONN_PARAM => {
+                let store_hint = *data.get(7)? != 0;
+                let mut params = data[..7].to_vec();
+                params.extend_from_slice(data.get(8..16)?);
+                Self::NewConnParams { store_hint, params: ConnParams::decode(&params)? }
+            }
+            ADVERTISING_ADDED => Self::AdvertisingAdded(*data.first()?),
+            ADVERTISING_REMOVED => Self::AdvertisingRemoved(*data.first()?),
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+fn get_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn get_u24(data: &[u8], pos: usize) -> Option<u32> {
+    let b = data.get(pos..pos + 3)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+fn get_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn get_address(data: &[u8]) -> Option<(Address, AddressType)> {
+    let mut addr: [u8; 6] = data.get(..6)?.try_into().ok()?;
+    addr.reverse();
+    let addr_type = match *data.get(6)? {
+        0 => AddressType::BrEdr,
+        1 => AddressType::LePublic,
+        2 => AddressType::LeRandom,
+        _ => return None,
+    };
+    Some((Address(addr), addr_type))
+}
+
+fn put_address(buf: &mut Vec<u8>, address: Address, address_type: AddressType) {
+    let mut addr = address.0;
+    addr.reverse();
+    buf.extend_from_slice(&addr);
+    buf.push(address_type as u8);
+}
+
+fn get_eir(data: &[u8], pos: usize) -> Option<Vec<u8>> {
+    let len = get_u16(data, pos)? as usize;
+    Some(data.get(pos + 2..pos + 2 + len)?.to_vec())
+}
+
+fn get_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+fn get_names(data: &[u8]) -> Option<(String, String)> {
+    let name = data.get(..MAX_NAME_LEN + 1)?;
+    let short_name = data.get(MAX_NAME_LEN + 1..MAX_NAME_LEN + 1 + MAX_SHORT_NAME_LEN + 1)?;
+    Some((get_string(name), get_string(short_name)))
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &str, max_len: usize) -> Result<()> {
+    if s.len() > max_len {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("name exceeds {max_len} bytes")));
+    }
+    buf.extend_from_slice(s.as_bytes());
+    buf.resize(buf.len() + max_len + 1 - s.len(), 0);
+    Ok(())
+}
+
+fn invalid_reply(opcode: u16) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid reply to management command 0x{opcode:04x}"))
+}
+
+type ReplySender = oneshot::Sender<std::result::Result<Vec<u8>, Status>>;
+
+/// Pending replies by opcode and controller index, in order of sending.
+type PendingReplies = HashMap<(u16, u16), VecDeque<(u64, ReplySender)>>;
+
+/// State shared between management handles and the receive task.
+struct Shared {
+    fd: AsyncFd<OwnedFd>,
+    next_id: AtomicU64,
+    pending: Mutex<PendingReplies>,
+    event_txs: Mutex<Vec<mpsc::UnboundedSender<(u16, Event)>>>,
+}
+
+impl Shared {
+    async fn send(&self, buf: &[u8]) -> Result<()> {
+        loop {
+            let mut guard = self.fd.writable().await?;
+            match guard.try_io(|inner| sock::send(inner.get_ref(), buf, 0)) {
+                Ok(result) => {
+                    if result? != buf.len() {
+                        return Err(Error::new(ErrorKind::WriteZero, "incomplete management command sent"));
+                    }
+                    return Ok(());
+                }
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let mut buf = ReadBuf::new(buf);
+        loop {
+            let mut guard = self.fd.readable().await?;
+            match guard.try_io(|inner| sock::recv(inner.get_ref(), &mut buf, 0)) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn handle_packet(&self, pkt: &[u8]) {
+        if pkt.len() < HEADER_LEN {
+            log::warn!("Received truncated management packet");
+            return;
+        }
+        let code = u16::from_le_bytes([pkt[0], pkt[1]]);
+        let index = u16::from_le_bytes([pkt[2], pkt[3]]);
+        let len = u16::from_le_bytes([pkt[4], pkt[5]]) as usize;
+        let Some(data) = pkt.get(HEADER_LEN..HEADER_LEN + len) else {
+            log::warn!("Received management packet with invalid length");
+            return;
+        };
+
+        match code {
+            event_code::CMD_COMPLETE | event_code::CMD_STATUS if data.len() >= 3 => {
+                let opcode = u16::from_le_bytes([data[0], data[1]]);
+                let status = Status::from(data[2]);
+                let reply = match status {
+                    Status::Success => Ok(data[3..].to_vec()),
+                    status => Err(status),
+                };
+                let tx = {
+                    let mut pending = self.pending.lock().unwrap();
+                    let tx = pending.get_mut(&(opcode, index)).and_then(|queue| queue.pop_front());
+                    if pending.get(&(opcode, index)).is_some_and(|queue| queue.is_empty()) {
+                        pending.remove(&(opcode, index));
+                    }
+                    tx
+                };
+                match tx {
+                    Some((_, tx)) => {
+                        let _ = tx.send(reply);
+                    }
+                    None => log::trace!("Unsolicited reply to management command 0x{opcode:04x}"),
+                }
+            }
+            _ => {
+                let event = Event::parse(code, data);
+                log::trace!("Management event on index {index}: {event:?}");
+                let mut event_txs = self.event_txs.lock().unwrap();
+                event_txs.retain(|tx| tx.unbounded_send((index, event.clone())).is_ok());
+            }
+        }
+    }
+}
+
+/// Interface to the Bluetooth Management (mgmt) API of the Linux kernel.
+///
+/// This can be cloned cheaply.
+/// The underlying socket is closed when all clones and [controllers](Controller)
+/// have been dropped.
+#[derive(Clone)]
+pub struct Management {
+    shared: Arc<Shared>,
+    _drop_tx: Arc<oneshot::Sender<()>>,
+}
+
+impl fmt::Debug for Management {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Management {{ fd: {:?} }}", self.shared.fd.get_ref())
+    }
+}
+
+impl Management {
+    /// Opens the management interface.
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    pub async fn new() -> Result<Self> {
+        let fd = sock::socket(AF_BLUETOOTH, SOCK_RAW, BTPROTO_HCI)?;
+        sock::bind(&fd, HciAddr { dev: HCI_DEV_NONE, channel: HCI_CHANNEL_CONTROL })?;
+
+        let shared = Arc::new(Shared {
+            fd: AsyncFd::new(fd)?,
+            next_id: AtomicU64::new(0),
+            pending: Mutex::new(HashMap::new()),
+            event_txs: Mutex::new(Vec::new()),
+        });
+
+        let (drop_tx, mut drop_rx) = oneshot::channel();
+        let task_shared = shared.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0; HEADER_LEN + u16::MAX as usize];
+            loop {
+                tokio::select! {
+                    res = task_shared.recv(&mut buf) => match res {
+                        Ok(n) => task_shared.handle_packet(&buf[..n]),
+                        Err(err) => {
+                            log::warn!("Receiving from management socket failed: {err}");
+                            break;
+                        }
+                    },
+                    _ = &mut drop_rx => break,
+                }
+            }
+            task_shared.pending.lock().unwrap().clear();
+            task_shared.event_txs.lock().unwrap().clear();
+        });
+
+        Ok(Self { shared, _drop_tx: Arc::new(drop_tx) })
+    }
+
+    /// Sends a raw management command and waits for its reply.
+    ///
+    /// Returns the return parameters of the command.
+    /// Use [INDEX_NONE] as controller index for commands that do not refer to a controller.
+    pub async fn command(&self, opcode: u16, index: u16, params: &[u8]) -> Result<Vec<u8>> {
+        let len: u16 = params
+            .len()
+            .try_into()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "management command parameters too long"))?;
+        let mut pkt = Vec::with_capacity(HEADER_LEN + params.len());
+        pkt.extend_from_slice(&opcode.to_le_bytes());
+        pkt.extend_from_slice(&index.to_le_bytes());
+        pkt.extend_from_slice(&len.to_le_bytes());
+        pkt.extend_from_slice(params);
+
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending.lock().unwrap().entry((opcode, index)).or_default().push_back((id, tx));
+
+        if let Err(err) = self.shared.send(&pkt).await {
+            let mut pending = self.shared.pending.lock().unwrap();
+            if let Some(queue) = pending.get_mut(&(opcode, index)) {
+                queue.retain(|(i, _)| *i != id);
+            }
+            return Err(err);
+        }
+
+        match rx.await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(status)) => Err(CommandError { opcode, status }.into()),
+            Err(_) => Err(Error::new(ErrorKind::BrokenPipe, "management socket closed")),
+        }
+    }
+
+    /// Reads the version of the management interface.
+    pub async fn version(&self) -> Result<Version> {
+        let reply = self.command(opcode::READ_VERSION, INDEX_NONE, &[]).await?;
+        Ok(Version {
+            version: *reply.first().ok_or_else(|| invalid_reply(opcode::READ_VERSION))?,
+            revision: get_u16(&reply, 1).ok_or_else(|| invalid_reply(opcode::READ_VERSION))?,
+        })
+    }
+
+    /// Reads the commands and events supported by the management interface.
+    pub async fn supported_commands(&self) -> Result<SupportedCommands> {
+        let reply = self.command(opcode::READ_COMMANDS, INDEX_NONE, &[]).await?;
+        let parse = || {
+            let n_commands = get_u16(&reply, 0)? as usize;
+            let n_events = get_u16(&reply, 2)? as usize;
+            let list =
+                |pos: usize, n: usize| (0..n).map(|i| get_u16(&reply, pos + 2 * i)).collect::<Option<Vec<_>>>();
+            Some(SupportedCommands {
+                commands: list(4, n_commands)?,
+                events: list(4 + 2 * n_commands, n_events)?,
+            })
+        };
+        parse().ok_or_else(|| invalid_reply(opcode::READ_COMMANDS))
+    }
+
+    async fn read_index_list(&self, opcode: u16) -> Result<Vec<u16>> {
+        let reply = self.command(opcode, INDEX_NONE, &[]).await?;
+        let n = get_u16(&reply, 0).ok_or_else(|| invalid_reply(opcode))? as usize;
+        (0..n)
+            .map(|i| get_u16(&reply, 2 + 2 * i))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid_reply(opcode))
+    }
+
+    /// Indices of all configured controllers.
+    pub async fn index_list(&self) -> Result<Vec<u16>> {
+        self.read_index_list(opcode::READ_INDEX_LIST).await
+    }
+
+    /// Indices of all unconfigured controllers.
+    pub async fn unconfigured_index_list(&self) -> Result<Vec<u16>> {
+        self.read_index_list(opcode::READ_UNCONF_INDEX_LIST).await
+    }
+
+    /// Interface to the controller with the specified index.
+    ///
+    /// The index corresponds to the number in the adapter name, i.e. 0 for `hci0`.
+    /// The existence of the controller is not checked.
+    pub fn controller(&self, index: u16) -> Controller {
+        Controller { mgmt: self.clone(), index }
+    }
+
+    /// Streams management events of all controllers.
+    ///
+    /// Each event is accompanied by the index of the controller it refers to,
+    /// or [INDEX_NONE] if it does not refer to a specific controller.
+    /// This includes [Event::IndexAdded] and [Event::IndexRemoved] notifications.
+    pub async fn events(&self) -> Result<impl Stream<Item = (u16, Event)>> {
+        let (tx, rx) = mpsc::unbounded();
+        self.shared.event_txs.lock().unwrap().push(tx);
+
+        // The kernel only sends index notifications to sockets that have read the index list.
+        self.index_list().await?;
+        self.unconfigured_index_list().await?;
+
+        Ok(rx)
+    }
+}
+
+/// Interface to a Bluetooth controller through the management API.
+#[derive(Clone, Debug)]
+pub struct Controller {
+    mgmt: Management,
+    index: u16,
+}
+
+impl Controller {
+    /// Controller index.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Sends a raw management command to this controller and waits for its reply.
+    pub async fn command(&self, opcode: u16, params: &[u8]) -> Result<Vec<u8>> {
+        self.mgmt.command(opcode, self.index, params).await
+    }
+
+    async fn settings_command(&self, opcode: u16, params: &[u8]) -> Result<Settings> {
+        let reply = self.command(opcode, params).await?;
+        Ok(Settings::from_bits(get_u32(&reply, 0).ok_or_else(|| invalid_reply(opcode))?))
+    }
+
+    /// Reads information about the controller.
+    pub async fn info(&self) -> Result<ControllerInfo> {
+        let reply = self.command(opcode::READ_INFO, &[]).await?;
+        let parse = || {
+            let mut addr: [u8; 6] = reply.get(..6)?.try_into().ok()?;
+            addr.reverse();
+            let (name, short_name) = get_names(reply.get(20..)?)?;
+            Some(ControllerInfo {
+                address: Address(addr),
+                bluetooth_version: *reply.get(6)?,
+                manufacturer: get_u16(&reply, 7)?,
+                supported_settings: Settings::from_bits(get_u32(&reply, 9)?),
+                current_settings: Settings::from_bits(get_u32(&reply, 13)?),
+                class_of_device: get_u24(&reply, 17)?,
+                name,
+                short_name,
+            })
+        };
+        parse().ok_or_else(|| invalid_reply(opcode::READ_INFO))
+    }
+
+    /// Switches the controller on or off.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_powered(&self, powered: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_POWERED, &[powered.into()]).await
+    }
+
+    /// Sets whether the controller accepts incoming connections.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_connectable(&self, connectable: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_CONNECTABLE, &[connectable.into()]).await
+    }
+
+    /// Sets whether the controller is discoverable.
+    ///
+    /// The timeout is specified in seconds, with zero meaning no timeout.
+    /// A controller must be connectable to become discoverable.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_discoverable(&self, discoverable: bool, timeout: u16) -> Result<Settings> {
+        let mut params = vec![discoverable.into()];
+        params.extend_from_slice(&timeout.to_le_bytes());
+        self.settings_command(opcode::SET_DISCOVERABLE, &params).await
+    }
+
+    /// Sets whether the controller accepts pairing requests.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_bondable(&self, bondable: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_BONDABLE, &[bondable.into()]).await
+    }
+
+    /// Enables or disables Secure Simple Pairing.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_ssp(&self, ssp: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_SSP, &[ssp.into()]).await
+    }
+
+    /// Enables or disables Bluetooth Low Energy.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_le(&self, le: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_LE, &[le.into()]).await
+    }
+
+    /// Enables or disables classic Bluetooth (BR/EDR).
+    ///
+    /// Returns the new current settings.
+    pub async fn set_br_edr(&self, br_edr: bool) -> Result<Settings> {
+        self.settings_command(opcode::SET_BREDR, &[br_edr.into()]).await
+    }
+
+    /// Sets the Secure Connections mode.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_secure_connections(&self, mode: SecureConnections) -> Result<Settings> {
+        self.settings_command(opcode::SET_SECURE_CONN, &[mode as u8]).await
+    }
+
+    /// Sets the privacy mode and local identity resolving key (IRK).
+    ///
+    /// The controller must be powered off.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_privacy(&self, privacy: Privacy, irk: [u8; 16]) -> Result<Settings> {
+        let mut params = vec![privacy as u8];
+        params.extend_from_slice(&irk);
+        self.settings_command(opcode::SET_PRIVACY, &params).await
+    }
+
+    /// Sets the static random address used for Bluetooth Low Energy.
+    ///
+    /// Specify [Address::any] to use the public address.
+    /// The controller must be powered off.
+    ///
+    /// Returns the new current settings.
+    pub async fn set_static_address(&self, address: Address) -> Result<Settings> {
+        let mut addr = address.0;
+        addr.reverse();
+        self.settings_command(opcode::SET_STATIC_ADDRESS, &addr).await
+    }
+
+    /// Sets the local name and short local name.
+    ///
+    /// The name can be at most [MAX_NAME_LEN] bytes long and the short name
+    /// can be at most [MAX_SHORT_NAME_LEN] bytes long.
+    ///
+    /// Returns the new local name and short local name.
+    pub async fn set_local_name(&self, name: &str, short_name: &str) -> Result<(String, String)> {
+        let mut params = Vec::new();
+        put_string(&mut params, name, MAX_NAME_LEN)?;
+        put_string(&mut params, short_name, MAX_SHORT_NAME_LEN)?;
+        let reply = self.command(opcode::SET_LOCAL_NAME, &params).await?;
+        get_names(&reply).ok_or_else(|| invalid_reply(opcode::SET_LOCAL_NAME))
+    }
+
+    /// Adds a device to the accept list of the controller.
+    pub async fn add_device(
+        &self, address: Address, address_type: AddressType, action: AddDeviceAction,
+    ) -> Result<()> {
+        let mut params = Vec::new();
+        put_address(&mut params, address, address_type);
+        params.push(action as u8);
+        self.command(opcode::ADD_DEVICE, &params).await?;
+        Ok(())
+    }
+
+    /// Removes a device from the accept list of the controller.
+    ///
+    /// Specify [Address::any] to remove all devices.
+    pub async fn remove_device(&self, address: Address, address_type: AddressType) -> Result<()> {
+        let mut params = Vec::new();
+        put_address(&mut params, address, address_type);
+        self.command(opcode::REMOVE_DEVICE, &params).await?;
+        Ok(())
+    }
+
+    /// Loads LE connection parameters to use for the specified devices.
+    ///
+    /// This replaces all previously loaded connection parameters.
+    pub async fn load_conn_params(&self, params: &[ConnParams]) -> Result<()> {
+        let count: u16 = params
+            .len()
+            .try_into()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "too many connection parameters"))?;
+        let mut buf = Vec::with_capacity(2 + params.len() * ConnParams::LEN);
+        buf.extend_from_slice(&count.to_le_bytes());
+        for p in params {
+            p.encode_into(&mut buf);
+        }
+        self.command(opcode::LOAD_CONN_PARAM, &buf).await?;
+        Ok(())
+    }
+
+    /// Streams management events of this controller.
+    pub async fn events(&self) -> Result<impl Stream<Item = Event>> {
+        let index = self.index;
+        let events = self.mgmt.events().await?;
+        Ok(events.filter_map(move |(idx, event)| async move { (idx == index).then_some(event) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: Address = Address([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+    const ADDRESS_LE: [u8; 6] = [0x66, 0x55, 0x44, 0x33, 0x22, 0x11];
+
+    fn with_address(address_type: u8, rest: &[u8]) -> Vec<u8> {
+        let mut data = ADDRESS_LE.to_vec();
+        data.push(address_type);
+        data.extend_from_slice(rest);
+        data
+    }
+
+    #[test]
+    fn settings() {
+        // Powered, bondable, SSP, BR/EDR, LE and Secure Connections.
+        let settings = Settings::from_bits(0x0ad1);
+        assert_eq!(
+            settings,
+            Settings {
+                powered: true,
+                bondable: true,
+                ssp: true,
+                br_edr: true,
+                le: true,
+                secure_connections: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(settings.to_bits(), 0x0ad1);
+
+        for n in 0..23 {
+            assert_eq!(Settings::from_bits(1 << n).to_bits(), 1 << n, "bit {n}");
+        }
+        assert_eq!(Settings::from_bits(0x007f_ffff).to_bits(), 0x007f_ffff);
+        assert_eq!(Settings::from_bits(0xff80_0000), Settings::default());
+        assert!(Settings::from_bits(1 << 22).ll_privacy);
+        assert!(Settings::from_bits(1 << 17).wideband_speech);
+    }
+
+    #[test]
+    fn names() {
+        let mut buf = Vec::new();
+        put_string(&mut buf, "bluer", MAX_NAME_LEN).unwrap();
+        put_string(&mut buf, "br", MAX_SHORT_NAME_LEN).unwrap();
+        assert_eq!(buf.len(), MAX_NAME_LEN + 1 + MAX_SHORT_NAME_LEN + 1);
+        assert_eq!(&buf[..6], b"bluer\0");
+        assert_eq!(get_names(&buf), Some(("bluer".to_string(), "br".to_string())));
+
+        // Names of maximum length are terminated by the following byte.
+        let mut buf = Vec::new();
+        put_string(&mut buf, &"n".repeat(MAX_NAME_LEN), MAX_NAME_LEN).unwrap();
+        put_string(&mut buf, &"s".repeat(MAX_SHORT_NAME_LEN), MAX_SHORT_NAME_LEN).unwrap();
+        assert_eq!(get_names(&buf), Some(("n".repeat(MAX_NAME_LEN), "s".repeat(MAX_SHORT_NAME_LEN))));
+
+        assert_eq!(
+            put_string(&mut Vec::new(), &"s".repeat(MAX_SHORT_NAME_LEN + 1), MAX_SHORT_NAME_LEN)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(get_names(&buf[..buf.len() - 1]), None);
+        assert_eq!(get_string(b"ab\xffc\0d"), "ab\u{fffd}c");
+        assert_eq!(get_string(b"abc"), "abc");
+    }
+
+    #[test]
+    fn controller_events() {
+        use event_code::*;
+
+        assert_eq!(Event::parse(INDEX_ADDED, &[]), Event::IndexAdded);
+        assert_eq!(Event::parse(UNCONF_INDEX_REMOVED, &[]), Event::UnconfiguredIndexRemoved);
+        assert_eq!(Event::parse(CONTROLLER_ERROR, &[0x05]), Event::ControllerError(0x05));
+        assert_eq!(
+            Event::parse(NEW_SETTINGS, &[0xd1, 0x0a, 0x00, 0x00]),
+            Event::NewSettings(Settings::from_bits(0x0ad1))
+        );
+        assert_eq!(
+            Event::parse(CLASS_OF_DEV_CHANGED, &[0x0c, 0x01, 0x5a]),
+            Event::ClassOfDeviceChanged(0x5a010c)
+        );
+
+        let mut data = vec![0; MAX_NAME_LEN + 1 + MAX_SHORT_NAME_LEN + 1];
+        data[..5].copy_from_slice(b"bluer");
+        data[MAX_NAME_LEN + 1..MAX_NAME_LEN + 3].copy_from_slice(b"br");
+        assert_eq!(
+            Event::parse(LOCAL_NAME_CHANGED, &data),
+            Event::LocalNameChanged { name: "bluer".to_string(), short_name: "br".to_string() }
+        );
+
+        assert_eq!(
+            Event::parse(DISCOVERING, &[0x07, 0x01]),
+            Event::Discovering { address_types: 7, discovering: true }
+        );
+        assert_eq!(Event::parse(ADVERTISING_ADDED, &[0x02]), Event::AdvertisingAdded(2));
+        assert_eq!(Event::parse(ADVERTISING_REMOVED, &[0x02]), Event::AdvertisingRemoved(2));
+    }
+
+    #[test]
+    fn device_events() {
+        use event_code::*;
+
+        assert_eq!(
+            Event::parse(DEVICE_CONNECTED, &with_address(1, &[0x01, 0, 0, 0, 0x03, 0x00, 0x02, 0x01, 0x06])),
+            Event::DeviceConnected {
+                address: ADDRESS,
+                address_type: AddressType::LePublic,
+                flags: 1,
+                eir: vec![0x02, 0x01, 0x06],
+            }
+        );
+        assert_eq!(
+            Event::parse(DEVICE_DISCONNECTED, &with_address(0, &[0x02])),
+            Event::DeviceDisconnected { address: ADDRESS, address_type: AddressType::BrEdr, reason: 2 }
+        );
+        assert_eq!(
+            Event::parse(CONNECT_FAILED, &with_address(2, &[0x04])),
+            Event::ConnectFailed {
+                address: ADDRESS,
+                address_type: AddressType::LeRandom,
+                status: Status::ConnectFailed
+            }
+        );
+        assert_eq!(
+            Event::parse(AUTH_FAILED, &with_address(0, &[0x42])),
+            Event::AuthenticationFailed {
+                address: ADDRESS,
+                address_type: AddressType::BrEdr,
+                status: Status::Unknown(0x42),
+            }
+        );
+        assert_eq!(
+            Event::parse(DEVICE_FOUND, &with_address(1, &[0xc4, 0x04, 0, 0, 0, 0x00, 0x00])),
+            Event::DeviceFound {
+                address: ADDRESS,
+                address_type: AddressType::LePublic,
+                rssi: -60,
+                flags: 4,
+                eir: Vec::new(),
+            }
+        );
+        assert_eq!(
+            Event::parse(DEVICE_UNPAIRED, &with_address(1, &[])),
+            Event::DeviceUnpaired { address: ADDRESS, address_type: AddressType::LePublic }
+        );
+        assert_eq!(
+            Event::parse(DEVICE_ADDED, &with_address(1, &[0x02])),
+            Event::DeviceAdded { address: ADDRESS, address_type: AddressType::LePublic, action: 2 }
+        );
+        assert_eq!(
+            Event::parse(DEVICE_REMOVED, &with_address(2, &[])),
+            Event::DeviceRemoved { address: ADDRESS, address_type: AddressType::LeRandom }
+        );
+        assert_eq!(
+            Event::parse(
+                NEW_CONN_PARAM,
+                &with_address(1, &[0x01, 0x18, 0x00, 0x28, 0x00, 0x00, 0x00, 0x2a, 0x00])
+            ),
+            Event::NewConnParams {
+                store_hint: true,
+                params: ConnParams {
+                    address: ADDRESS,
+                    address_type: AddressType::LePublic,
+                    min_interval: 0x18,
+                    max_interval: 0x28,
+                    latency: 0,
+                    timeout: 0x2a,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_events() {
+        use event_code::*;
+
+        let other = |code: u16, data: &[u8]| {
+            assert_eq!(Event::parse(code, data), Event::Other { code, data: data.to_vec() }, "{code:#06x}");
+        };
+        other(0x7fff, &[1, 2, 3]);
+        other(CONTROLLER_ERROR, &[]);
+        other(NEW_SETTINGS, &[0xd1, 0x0a, 0x00]);
+        other(CLASS_OF_DEV_CHANGED, &[0x0c, 0x01]);
+        other(LOCAL_NAME_CHANGED, &[0; MAX_NAME_LEN + 1]);
+        other(DISCOVERING, &[0x07]);
+
+        // Invalid address type.
+        other(DEVICE_DISCONNECTED, &with_address(3, &[0x02]));
+        other(DEVICE_UNPAIRED, &ADDRESS_LE);
+        // EIR data shorter than its length.
+        other(DEVICE_CONNECTED, &with_address(1, &[0x01, 0, 0, 0, 0x04, 0x00, 0x02, 0x01, 0x06]));
+        other(DEVICE_FOUND, &with_address(1, &[0xc4, 0x04, 0, 0, 0]));
+        other(NEW_CONN_PARAM, &with_address(1, &[0x01, 0x18, 0x00, 0x28, 0x00, 0x00, 0x00, 0x2a]));
+        other(NEW_CONN_PARAM, &with_address(1, &[]));
+    }
+}
@@ -0,0 +1,1369 @@
+//! Scriptable fake Bluetooth daemon for testing.
+//!
+//! [MockBluez] starts a private D-Bus message bus, claims the `org.bluez` name on it
+//! and serves a fake object tree of
+//! [adapters](MockAdapter), [devices](MockDevice), [GATT services](MockService),
+//! [characteristics](MockCharacteristic) and [descriptors](MockDescriptor)
+//! together with an LE advertising manager.
+//!
+//! A [Session] obtained using [MockBluez::session] talks to this fake exactly like
+//! it would talk to the system Bluetooth daemon.
+//! Thus code built on BlueR can be exercised deterministically without
+//! Bluetooth hardware, for example in continuous integration.
+//!
+//! The following behavior of the Bluetooth daemon is emulated:
+//!
+//!   * adapter properties can be read and set,
+//!   * starting discovery publishes the [discoverable devices](MockBluez::add_discoverable_device),
+//!   * connecting a device publishes its GATT services and marks services as resolved,
+//!   * GATT characteristics and descriptors can be read and written,
+//!   * notification sessions can be started and values [pushed](MockBluez::notify) to them,
+//!   * advertisements can be registered and [inspected](MockBluez::advertisements),
+//!   * devices advertising a PAN service UUID accept network connections and
+//!     network servers can be registered on adapters.
+//!
+//! All method calls received by the fake can be observed using [MockBluez::calls]
+//! and each method can be scripted to fail using [MockBluez::set_method_error].
+//!
+//! The `dbus-daemon` executable must be available in the search path.
+//!
+//! # Example
+//! ```no_run
+//! use bluer::{mock::{MockAdapter, MockBluez, MockDevice}, Address};
+//!
+//! # async fn example() -> bluer::Result<()> {
+//! let mock = MockBluez::new().await?;
+//! mock.add_adapter(MockAdapter::new("hci0", Address::new([0, 1, 2, 3, 4, 5]))).await?;
+//!
+//! let mut device = MockDevice::new(Address::new([0x10, 0x20, 0x30, 0x40, 0x50, 0x60]));
+//! device.name = Some("Thermometer".to_string());
+//! device.rssi = Some(-60);
+//! mock.add_device("hci0", device).await?;
+//!
+//! let session = mock.session().await?;
+//! let adapter = session.default_adapter().await?;
+//! assert_eq!(adapter.device_addresses().await?.len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use dbus::{
+    arg::{prop_cast, PropMap, RefArg, Variant},
+    channel::Sender,
+    message::{MatchRule, SignalArgs},
+    nonblock::{
+        stdintf::org_freedesktop_dbus::{
+            ObjectManagerInterfacesAdded, ObjectManagerInterfacesRemoved, Properties, PropertiesPropertiesChanged,
+        },
+        Proxy, SyncConnection,
+    },
+    strings::BusName,
+    Message, MethodErr, Path,
+};
+use dbus_tokio::connection;
+use futures::{channel::mpsc, Stream, StreamExt};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::{spawn_blocking, JoinHandle};
+use uuid::Uuid;
+
+use crate::{
+    ad, adapter,
+    adv::{self, Advertisement},
+    device,
+    gatt::{self, CharacteristicFlags},
+    network::{self, Role},
+    sdp::ServiceRecord,
+    session, Adapter, Address, AddressType, Device, DeviceProperty, Error, ErrorKind, Result, Session,
+    ERR_PREFIX, SERVICE_NAME, TIMEOUT,
+};
+
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const UNKNOWN_OBJECT: &str = "org.freedesktop.DBus.Error.UnknownObject";
+const UNKNOWN_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+
+/// D-Bus property value.
+type PropValue = Variant<Box<dyn RefArg + 'static>>;
+
+const DISCOVERY_FILTERS: &[&str] =
+    &["UUIDs", "RSSI", "Pathloss", "Transport", "DuplicateData", "Discoverable", "Pattern"];
+
+/// Fake Bluetooth adapter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockAdapter {
+    /// Adapter name, for example `hci0`.
+    pub name: String,
+    /// Adapter address.
+    pub address: Address,
+    /// Adapter address type.
+    pub address_type: AddressType,
+    /// System name and alias of the adapter.
+    pub alias: String,
+    /// Class of device.
+    pub class: u32,
+    /// Power state.
+    pub powered: bool,
+    /// Discoverable state.
+    pub discoverable: bool,
+    /// Discoverable timeout in seconds.
+    pub discoverable_timeout: u32,
+    /// Pairable state.
+    pub pairable: bool,
+    /// Pairable timeout in seconds.
+    pub pairable_timeout: u32,
+    /// Available local services.
+    pub uuids: HashSet<Uuid>,
+    /// Maximum number of simultaneously registered advertisements.
+    pub supported_advertisement_instances: u8,
+    /// Maximum advertising data length supported by the controller.
+    pub max_advertisement_length: u8,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MockAdapter {
+    /// Creates a powered fake adapter with the specified name and address.
+    pub fn new(name: &str, address: Address) -> Self {
+        Self {
+            name: name.to_string(),
+            address,
+            address_type: AddressType::LePublic,
+            alias: "BlueR mock".to_string(),
+            class: 0,
+            powered: true,
+            discoverable: false,
+            discoverable_timeout: 180,
+            pairable: true,
+            pairable_timeout: 0,
+            uuids: HashSet::new(),
+            supported_advertisement_instances: 5,
+            max_advertisement_length: ad::MAX_LEGACY_LEN as u8,
+            _non_exhaustive: (),
+        }
+    }
+
+    fn to_interfaces(&self) -> HashMap<String, PropMap> {
+        let mut props = PropMap::new();
+        insert_prop(&mut props, "Address", self.address.to_string());
+        insert_prop(&mut props, "AddressType", self.address_type.to_string());
+        insert_prop(&mut props, "Name", self.alias.clone());
+        insert_prop(&mut props, "Alias", self.alias.clone());
+        insert_prop(&mut props, "Class", self.class);
+        insert_prop(&mut props, "Powered", self.powered);
+        insert_prop(&mut props, "Discoverable", self.discoverable);
+        insert_prop(&mut props, "DiscoverableTimeout", self.discoverable_timeout);
+        insert_prop(&mut props, "Pairable", self.pairable);
+        insert_prop(&mut props, "PairableTimeout", self.pairable_timeout);
+        insert_prop(&mut props, "Discovering", false);
+        insert_prop(&mut props, "UUIDs", self.uuids.iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>());
+
+        let mut adv_props = PropMap::new();
+        insert_prop(&mut adv_props, "ActiveInstances", 0u8);
+        insert_prop(&mut adv_props, "SupportedInstances", self.supported_advertisement_instances);
+        insert_prop(
+            &mut adv_props,
+            "SupportedIncludes",
+            vec![
+                adv::Feature::TxPower.to_string(),
+                adv::Feature::Appearance.to_string(),
+                adv::Feature::LocalName.to_string(),
+            ],
+        );
+        let mut capabilities = PropMap::new();
+        insert_prop(&mut capabilities, "MaxAdvLen", self.max_advertisement_length);
+        insert_prop(&mut capabilities, "MaxScnRspLen", self.max_advertisement_length);
+        insert_prop(&mut capabilities, "MinTxPower", -34i16);
+        insert_prop(&mut capabilities, "MaxTxPower", 7i16);
+        insert_prop(&mut adv_props, "SupportedCapabilities", capabilities);
+
+        let mut interfaces = HashMap::new();
+        interfaces.insert(adapter::INTERFACE.to_string(), props);
+        interfaces.insert(adv::MANAGER_INTERFACE.to_string(), adv_props);
+        interfaces.insert(network::SERVER_INTERFACE.to_string(), PropMap::new());
+        interfaces
+    }
+}
+
+/// Fake remote Bluetooth device.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockDevice {
+    /// Device address.
+    pub address: Address,
+    /// Device address type.
+    pub address_type: AddressType,
+    /// Remote name.
+    pub name: Option<String>,
+    /// Class of device.
+    pub class: Option<u32>,
+    /// External appearance.
+    pub appearance: Option<u16>,
+    /// Advertised service UUIDs.
+    pub uuids: HashSet<Uuid>,
+    /// Pairing state.
+    pub paired: bool,
+    /// Trusted state.
+    pub trusted: bool,
+    /// Blocked state.
+    pub blocked: bool,
+    /// Received signal strength indicator.
+    pub rssi: Option<i16>,
+    /// Advertised transmitted power level.
+    pub tx_power: Option<i16>,
+    /// Manufacturer specific advertisement data.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// Service advertisement data.
+    pub service_data: HashMap<Uuid, Vec<u8>>,
+    /// Raw advertising data.
+    pub advertising_data: HashMap<u8, Vec<u8>>,
+    /// Battery percentage.
+    pub battery_percentage: Option<u8>,
+    /// GATT services published once the device is connected.
+    pub services: Vec<MockService>,
+    /// ATT MTU reported for GATT characteristics.
+    pub mtu: u16,
+    /// SDP service records returned by [Device::service_records].
+    pub service_records: Vec<ServiceRecord>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MockDevice {
+    /// Creates a fake LE device with the specified address.
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            address_type: AddressType::LePublic,
+            name: None,
+            class: None,
+            appearance: None,
+            uuids: HashSet::new(),
+            paired: false,
+            trusted: false,
+            blocked: false,
+            rssi: None,
+            tx_power: None,
+            manufacturer_data: HashMap::new(),
+            service_data: HashMap::new(),
+            advertising_data: HashMap::new(),
+            battery_percentage: None,
+            services: Vec::new(),
+            mtu: 23,
+            service_records: Vec::new(),
+            _non_exhaustive: (),
+        }
+    }
+
+    fn to_interfaces(&self, adapter_path: &Path<'static>) -> HashMap<String, PropMap> {
+        let mut props = PropMap::new();
+        insert_prop(&mut props, "Adapter", adapter_path.clone());
+        insert_prop(&mut props, "LegacyPairing", false);
+
+        let mut properties = vec![
+            DeviceProperty::RemoteAddress(self.address),
+            DeviceProperty::AddressType(self.address_type),
+            DeviceProperty::Alias(
+                self.name.clone().unwrap_or_else(|| self.address.to_string().replace(':', "-")),
+            ),
+            DeviceProperty::Uuids(self.uuids.clone()),
+            DeviceProperty::Paired(self.paired),
+            DeviceProperty::Connected(false),
+            DeviceProperty::Trusted(self.trusted),
+            DeviceProperty::Blocked(self.blocked),
+            DeviceProperty::ServicesResolved(false),
+        ];
+        if let Some(name) = &self.name {
+            properties.push(DeviceProperty::Name(name.clone()));
+        }
+        if let Some(class) = self.class {
+            properties.push(DeviceProperty::Class(class));
+        }
+        if let Some(appearance) = self.appearance {
+            properties.push(DeviceProperty::Appearance(appearance));
+        }
+        if let Some(rssi) = self.rssi {
+            properties.push(DeviceProperty::Rssi(rssi));
+        }
+        if let Some(tx_power) = self.tx_power {
+            properties.push(DeviceProperty::TxPower(tx_power));
+        }
+        if !self.manufacturer_data.is_empty() {
+            properties.push(DeviceProperty::ManufacturerData(self.manufacturer_data.clone()));
+        }
+        if !self.service_data.is_empty() {
+            properties.push(DeviceProperty::ServiceData(self.service_data.clone()));
+        }
+        if !self.advertising_data.is_empty() {
+            properties.push(DeviceProperty::AdvertisingData(self.advertising_data.clone()));
+        }
+
+        let mut battery_props = PropMap::new();
+        for property in properties {
+            let (interface, name, value) = device_property_to_dbus(property);
+            match interface {
+                device::INTERFACE => props.insert(name.to_string(), value),
+                _ => battery_props.insert(name.to_string(), value),
+            };
+        }
+        if let Some(percentage) = self.battery_percentage {
+            insert_prop(&mut battery_props, "Percentage", percentage);
+        }
+
+        let mut interfaces = HashMap::new();
+        interfaces.insert(device::INTERFACE.to_string(), props);
+        if !battery_props.is_empty() {
+            interfaces.insert(device::BATTERY_INTERFACE.to_string(), battery_props);
+        }
+        if self.uuids.iter().any(|uuid| Role::from_uuid(*uuid).is_some()) {
+            let mut network_props = PropMap::new();
+            insert_prop(&mut network_props, "Connected", false);
+            interfaces.insert(network::INTERFACE.to_string(), network_props);
+        }
+        interfaces
+    }
+}
+
+/// Fake remote GATT service.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockService {
+    /// Service id, used to build the D-Bus object path.
+    pub id: u16,
+    /// Service UUID.
+    pub uuid: Uuid,
+    /// Whether this is a primary service.
+    pub primary: bool,
+    /// Characteristics.
+    pub characteristics: Vec<MockCharacteristic>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MockService {
+    /// Creates a fake primary GATT service.
+    pub fn new(id: u16, uuid: Uuid) -> Self {
+        Self { id, uuid, primary: true, characteristics: Vec::new(), _non_exhaustive: () }
+    }
+}
+
+/// Fake remote GATT characteristic.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockCharacteristic {
+    /// Characteristic id, used to build the D-Bus object path.
+    pub id: u16,
+    /// Characteristic UUID.
+    pub uuid: Uuid,
+    /// Characteristic flags.
+    ///
+    /// Reads, writes and notification sessions are rejected
+    /// if the corresponding flag is not set.
+    pub flags: CharacteristicFlags,
+    /// Initial value.
+    pub value: Vec<u8>,
+    /// Descriptors.
+    pub descriptors: Vec<MockDescriptor>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MockCharacteristic {
+    /// Creates a fake GATT characteristic.
+    pub fn new(id: u16, uuid: Uuid, flags: CharacteristicFlags) -> Self {
+        Self { id, uuid, flags, value: Vec::new(), descriptors: Vec::new(), _non_exhaustive: () }
+    }
+}
+
+/// Fake remote GATT characteristic descriptor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockDescriptor {
+    /// Descriptor id, used to build the D-Bus object path.
+    pub id: u16,
+    /// Descriptor UUID.
+    pub uuid: Uuid,
+    /// Initial value.
+    pub value: Vec<u8>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MockDescriptor {
+    /// Creates a fake GATT characteristic descriptor.
+    pub fn new(id: u16, uuid: Uuid) -> Self {
+        Self { id, uuid, value: Vec::new(), _non_exhaustive: () }
+    }
+}
+
+/// Method call received by the fake Bluetooth daemon.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MockCall {
+    /// D-Bus object path.
+    pub path: String,
+    /// D-Bus interface.
+    pub interface: String,
+    /// Method name.
+    pub method: String,
+}
+
+/// State of the fake object tree.
+#[derive(Default)]
+struct MockState {
+    objects: BTreeMap<Path<'static>, HashMap<String, PropMap>>,
+    services: HashMap<Path<'static>, (Vec<MockService>, u16)>,
+    service_records: HashMap<Path<'static>, Vec<ServiceRecord>>,
+    discoverable: HashMap<String, Vec<MockDevice>>,
+    advertisements: HashMap<String, Vec<(Path<'static>, Advertisement)>>,
+    network_servers: HashMap<Path<'static>, HashMap<Role, String>>,
+    network_interfaces: u32,
+    errors: HashMap<(String, String), ErrorKind>,
+    call_txs: Vec<mpsc::UnboundedSender<MockCall>>,
+}
+
+struct MockInner {
+    connection: Arc<SyncConnection>,
+    state: Mutex<MockState>,
+}
+
+/// Private D-Bus message bus daemon.
+struct BusDaemon {
+    child: Child,
+    dir: PathBuf,
+    address: String,
+}
+
+impl BusDaemon {
+    fn start() -> std::io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!("bluer-mock-{}", Uuid::new_v4().as_simple()));
+        std::fs::create_dir_all(&dir)?;
+
+        let config = dir.join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:dir={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#,
+                dir.display()
+            ),
+        )?;
+
+        let mut child = Command::new("dbus-daemon")
+            .arg("--nofork")
+            .arg("--nopidfile")
+            .arg("--print-address")
+            .arg(format!("--config-file={}", config.display()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let mut address = String::new();
+        let stdout = child.stdout.take().unwrap();
+        if let Err(err) = BufReader::new(stdout).read_line(&mut address) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::other("dbus-daemon did not report its address"));
+        }
+
+        Ok(Self { child, dir, address })
+    }
+}
+
+impl Drop for BusDaemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Scriptable fake Bluetooth daemon serving on a private D-Bus message bus.
+///
+/// The message bus and all connections to it are shut down when this is dropped.
+pub struct MockBluez {
+    inner: Arc<MockInner>,
+    dbus_task: JoinHandle<connection::IOResourceError>,
+    mc_task: JoinHandle<()>,
+    daemon: BusDaemon,
+}
+
+impl fmt::Debug for MockBluez {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MockBluez {{ {} }}", &self.daemon.address)
+    }
+}
+
+impl Drop for MockBluez {
+    fn drop(&mut self) {
+        self.mc_task.abort();
+        self.dbus_task.abort();
+    }
+}
+
+impl MockBluez {
+    /// Starts a private D-Bus message bus and serves the fake Bluetooth daemon on it.
+    ///
+    /// Initially the fake has no adapters.
+    pub async fn new() -> Result<Self> {
+        let daemon = spawn_blocking(BusDaemon::start).await??;
+        let (connection, dbus_task) = session::connect(&daemon.address).await?;
+        log::trace!("Mock Bluetooth daemon connected to {} as {}", &daemon.address, &connection.unique_name());
+
+        connection.request_name(SERVICE_NAME, false, true, true).await?;
+
+        let inner =
+            Arc::new(MockInner { connection: connection.clone(), state: Mutex::new(MockState::default()) });
+        inner.insert_object(Path::new(adapter::PATH).unwrap(), HashMap::new());
+
+        let mc_callback = connection.add_match(MatchRule::new_method_call()).await?;
+        let mc_inner = Arc::downgrade(&inner);
+        let mc_task = tokio::spawn(async move {
+            let (_mc_callback, mut mc_stream) = mc_callback.msg_stream();
+            while let Some(msg) = mc_stream.next().await {
+                let mc_inner = match mc_inner.upgrade() {
+                    Some(inner) => inner,
+                    None => return,
+                };
+                tokio::spawn(async move { mc_inner.handle_message(msg).await });
+            }
+        });
+
+        Ok(Self { inner, dbus_task, mc_task, daemon })
+    }
+
+    /// Address of the private D-Bus message bus.
+    pub fn bus_address(&self) -> &str {
+        &self.daemon.address
+    }
+
+    /// Creates a new Bluetooth session connected to the fake Bluetooth daemon.
+    ///
+    /// This is equivalent to calling [Session::with_address] with the [bus address](Self::bus_address).
+    pub async fn session(&self) -> Result<Session> {
+        Session::with_address(&self.daemon.address).await
+    }
+
+    /// Adds a fake adapter.
+    pub async fn add_adapter(&self, adapter: MockAdapter) -> Result<()> {
+        let path = Adapter::dbus_path(&adapter.name)?;
+        if self.inner.has_object(&path) {
+            return Err(Error::new(ErrorKind::AlreadyExists));
+        }
+        self.inner.insert_object(path, adapter.to_interfaces());
+        Ok(())
+    }
+
+    /// Removes a fake adapter together with all its devices.
+    pub async fn remove_adapter(&self, adapter_name: &str) -> Result<()> {
+        let path = Adapter::dbus_path(adapter_name)?;
+        if !self.inner.remove_objects(&path) {
+            return Err(Error::new(ErrorKind::NotFound));
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        state.discoverable.remove(adapter_name);
+        state.advertisements.remove(adapter_name);
+        Ok(())
+    }
+
+    /// Adds a fake device that is known to the adapter.
+    pub async fn add_device(&self, adapter_name: &str, device: MockDevice) -> Result<()> {
+        self.inner.add_device(adapter_name, device)
+    }
+
+    /// Adds a fake device that will be found once discovery is started on the adapter.
+    ///
+    /// If discovery is already active, the device is added immediately.
+    pub async fn add_discoverable_device(&self, adapter_name: &str, device: MockDevice) -> Result<()> {
+        let path = Adapter::dbus_path(adapter_name)?;
+        let discovering: Option<bool> = self.inner.prop(&path, adapter::INTERFACE, "Discovering");
+        match discovering {
+            Some(true) => self.inner.add_device(adapter_name, device),
+            Some(false) => {
+                let mut state = self.inner.state.lock().unwrap();
+                state.discoverable.entry(adapter_name.to_string()).or_default().push(device);
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::NotFound)),
+        }
+    }
+
+    /// Removes a fake device.
+    pub async fn remove_device(&self, adapter_name: &str, address: Address) -> Result<()> {
+        let path = Device::dbus_path(adapter_name, address)?;
+        if !self.inner.remove_objects(&path) {
+            return Err(Error::new(ErrorKind::NotFound));
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        state.services.remove(&path);
+        state.service_records.remove(&path);
+        Ok(())
+    }
+
+    /// Changes a property of a fake device and emits the corresponding change notification.
+    pub async fn set_device_property(
+        &self, adapter_name: &str, address: Address, property: DeviceProperty,
+    ) -> Result<()> {
+        let path = Device::dbus_path(adapter_name, address)?;
+        if !self.inner.has_object(&path) {
+            return Err(Error::new(ErrorKind::NotFound));
+        }
+        let (interface, name, value) = device_property_to_dbus(property);
+        self.inner.set_props(&path, interface, vec![(name.to_string(), value)]);
+        Ok(())
+    }
+
+    /// Sends a notification or indication from a characteristic of a connected fake device.
+    ///
+    /// This changes the value of the characteristic.
+    pub async fn notify(
+        &self, adapter_name: &str, address: Address, service_id: u16, characteristic_id: u16, value: Vec<u8>,
+    ) -> Result<()> {
+        let path = gatt::remote::Characteristic::dbus_path(adapter_name, address, service_id, characteristic_id)?;
+        if !self.inner.has_object(&path) {
+            return Err(Error::new(ErrorKind::NotFound));
+        }
+        self.inner.set_props(&path, gatt::CHARACTERISTIC_INTERFACE, vec![("Value".to_string(), variant(value))]);
+        Ok(())
+    }
+
+    /// Current value of a characteristic of a connected fake device.
+    pub async fn characteristic_value(
+        &self, adapter_name: &str, address: Address, service_id: u16, characteristic_id: u16,
+    ) -> Result<Vec<u8>> {
+        let path = gatt::remote::Characteristic::dbus_path(adapter_name, address, service_id, characteristic_id)?;
+        self.inner
+            .prop(&path, gatt::CHARACTERISTIC_INTERFACE, "Value")
+            .ok_or_else(|| Error::new(ErrorKind::NotFound))
+    }
+
+    /// Current value of a characteristic descriptor of a connected fake device.
+    pub async fn descriptor_value(
+        &self, adapter_name: &str, address: Address, service_id: u16, characteristic_id: u16, descriptor_id: u16,
+    ) -> Result<Vec<u8>> {
+        let path = gatt::remote::Descriptor::dbus_path(
+            adapter_name,
+            address,
+            service_id,
+            characteristic_id,
+            descriptor_id,
+        )?;
+        self.inner.prop(&path, gatt::DESCRIPTOR_INTERFACE, "Value").ok_or_else(|| Error::new(ErrorKind::NotFound))
+    }
+
+    /// Advertisements currently registered on the fake adapter.
+    pub async fn advertisements(&self, adapter_name: &str) -> Vec<Advertisement> {
+        let state = self.inner.state.lock().unwrap();
+        state
+            .advertisements
+            .get(adapter_name)
+            .map(|advs| advs.iter().map(|(_, adv)| adv.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Network servers currently registered on the fake adapter together with their bridge interfaces.
+    pub async fn network_servers(&self, adapter_name: &str) -> HashMap<Role, String> {
+        let Ok(path) = Adapter::dbus_path(adapter_name) else { return HashMap::new() };
+        let state = self.inner.state.lock().unwrap();
+        state.network_servers.get(&path).cloned().unwrap_or_default()
+    }
+
+    /// Makes calls of the specified method on the object with the specified D-Bus path fail.
+    ///
+    /// The object path is formed as by the Bluetooth daemon, for example
+    /// `/org/bluez/hci0/dev_01_02_03_04_05_06`.
+    /// Only error kinds that are reported by the Bluetooth daemon, i.e. those without
+    /// associated data, are supported.
+    ///
+    /// Specify `None` to make calls succeed again.
+    pub async fn set_method_error(&self, path: &str, method: &str, error: Option<ErrorKind>) {
+        let mut state = self.inner.state.lock().unwrap();
+        let key = (path.to_string(), method.to_string());
+        match error {
+            Some(kind) => state.errors.insert(key, kind),
+            None => state.errors.remove(&key),
+        };
+    }
+
+    /// Streams all method calls received by the fake Bluetooth daemon from now on.
+    pub fn calls(&self) -> impl Stream<Item = MockCall> {
+        let (tx, rx) = mpsc::unbounded();
+        self.inner.state.lock().unwrap().call_txs.push(tx);
+        rx
+    }
+}
+
+impl MockInner {
+    fn has_object(&self, path: &Path<'static>) -> bool {
+        self.state.lock().unwrap().objects.contains_key(path)
+    }
+
+    fn prop<T: Clone + 'static>(&self, path: &Path<'static>, interface: &str, name: &str) -> Option<T> {
+        let state = self.state.lock().unwrap();
+        let props = state.objects.get(path)?.get(interface)?;
+        prop_cast::<T>(props, name).cloned()
+    }
+
+    fn emit(&self, msg: Message) {
+        if self.connection.send(msg).is_err() {
+            log::warn!("Mock Bluetooth daemon failed to emit signal");
+        }
+    }
+
+    /// Inserts an object and announces it.
+    fn insert_object(&self, path: Path<'static>, interfaces: HashMap<String, PropMap>) {
+        let announced =
+            interfaces.iter().map(|(interface, props)| (interface.clone(), clone_props(props))).collect();
+        self.state.lock().unwrap().objects.insert(path.clone(), interfaces);
+
+        let signal = ObjectManagerInterfacesAdded { object: path, interfaces: announced };
+        self.emit(signal.to_emit_message(&Path::new("/").unwrap()));
+    }
+
+    /// Removes an object and all its children, announcing their removal.
+    ///
+    /// Returns whether the object existed.
+    fn remove_objects(&self, path: &Path<'static>) -> bool {
+        let prefix = format!("{path}/");
+        let removed: Vec<_> = {
+            let mut state = self.state.lock().unwrap();
+            let paths: Vec<_> =
+                state.objects.keys().filter(|p| *p == path || p.starts_with(&prefix)).cloned().collect();
+            paths.into_iter().rev().filter_map(|p| state.objects.remove(&p).map(|ifaces| (p, ifaces))).collect()
+        };
+
+        for (object, interfaces) in &removed {
+            let signal = ObjectManagerInterfacesRemoved {
+                object: object.clone(),
+                interfaces: interfaces.keys().cloned().collect(),
+            };
+            self.emit(signal.to_emit_message(&Path::new("/").unwrap()));
+        }
+
+        !removed.is_empty()
+    }
+
+    /// Changes properties of an object and announces the changes.
+    fn set_props(&self, path: &Path<'static>, interface: &str, changes: Vec<(String, PropValue)>) {
+        let mut changed_properties = PropMap::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let Some(props) = state.objects.get_mut(path).and_then(|ifaces| ifaces.get_mut(interface)) else {
+                return;
+            };
+            for (name, value) in changes {
+                changed_properties.insert(name.clone(), Variant(value.0.box_clone()));
+                props.insert(name, value);
+            }
+        }
+
+        let signal = PropertiesPropertiesChanged {
+            interface_name: interface.to_string(),
+            changed_properties,
+            invalidated_properties: Vec::new(),
+        };
+        self.emit(signal.to_emit_message(path));
+    }
+
+    fn add_device(&self, adapter_name: &str, device: MockDevice) -> Result<()> {
+        let adapter_path = Adapter::dbus_path(adapter_name)?;
+        if !self.has_object(&adapter_path) {
+            return Err(Error::new(ErrorKind::NotFound));
+        }
+        let path = Device::dbus_path(adapter_name, device.address)?;
+        if self.has_object(&path) {
+            return Err(Error::new(ErrorKind::AlreadyExists));
+        }
+
+        let interfaces = device.to_interfaces(&adapter_path);
+        {
+            let mut state = self.state.lock().unwrap();
+            state.services.insert(path.clone(), (device.services, device.mtu));
+            state.service_records.insert(path.clone(), device.service_records);
+        }
+        self.insert_object(path, interfaces);
+        Ok(())
+    }
+
+    /// Publishes the GATT services of a device.
+    fn resolve_services(&self, device_path: &Path<'static>) {
+        let Some((services, mtu)) = self.state.lock().unwrap().services.get(device_path).cloned() else {
+            return;
+        };
+
+        for service in services {
+            let service_path = Path::new(format!("{device_path}/service{:04x}", service.id)).unwrap();
+            let mut props = PropMap::new();
+            insert_prop(&mut props, "UUID", service.uuid.to_string());
+            insert_prop(&mut props, "Primary", service.primary);
+            insert_prop(&mut props, "Device", device_path.clone());
+            insert_prop(&mut props, "Includes", Vec::<Path<'static>>::new());
+            self.insert_object(
+                service_path.clone(),
+                HashMap::from([(gatt::SERVICE_INTERFACE.to_string(), props)]),
+            );
+
+            for characteristic in service.characteristics {
+                let char_path = Path::new(format!("{service_path}/char{:04x}", characteristic.id)).unwrap();
+                let mut props = PropMap::new();
+                insert_prop(&mut props, "UUID", characteristic.uuid.to_string());
+                insert_prop(&mut props, "Service", service_path.clone());
+                insert_prop(&mut props, "Value", characteristic.value);
+                insert_prop(&mut props, "Notifying", false);
+                insert_prop(&mut props, "Flags", characteristic.flags.as_vec());
+                insert_prop(&mut props, "MTU", mtu);
+                self.insert_object(
+                    char_path.clone(),
+                    HashMap::from([(gatt::CHARACTERISTIC_INTERFACE.to_string(), props)]),
+                );
+
+                for descriptor in characteristic.descriptors {
+                    let desc_path = Path::new(format!("{char_path}/desc{:04x}", descriptor.id)).unwrap();
+                    let mut props = PropMap::new();
+                    insert_prop(&mut props, "UUID", descriptor.uuid.to_string());
+                    insert_prop(&mut props, "Characteristic", char_path.clone());
+                    insert_prop(&mut props, "Value", descriptor.value);
+                    self.insert_object(
+                        desc_path,
+                        HashMap::from([(gatt::DESCRIPTOR_INTERFACE.to_string(), props)]),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Removes the published GATT services of a device.
+    fn unresolve_services(&self, device_path: &Path<'static>) {
+        let children: Vec<_> = {
+            let state = self.state.lock().unwrap();
+            let prefix = format!("{device_path}/service");
+            state.objects.keys().filter(|p| p.starts_with(&prefix) && !p.contains("/char")).cloned().collect()
+        };
+        for child in children {
+            self.remove_objects(&child);
+        }
+    }
+
+    async fn handle_message(self: Arc<Self>, msg: Message) {
+        // The properties of an advertisement must be queried from the registering application.
+        let adv_props = match Self::advertisement_to_register(&msg) {
+            Some((sender, adv_path)) => {
+                let proxy = Proxy::new(sender, adv_path, TIMEOUT, &*self.connection);
+                proxy.get_all(adv::ADVERTISEMENT_INTERFACE).await.ok()
+            }
+            None => None,
+        };
+
+        let reply = match self.dispatch(&msg, adv_props) {
+            Ok(reply) => reply,
+            Err(err) => err.to_message(&msg),
+        };
+        if !msg.get_no_reply() && self.connection.send(reply).is_err() {
+            log::warn!("Mock Bluetooth daemon failed to send reply");
+        }
+    }
+
+    fn advertisement_to_register(msg: &Message) -> Option<(BusName<'static>, Path<'static>)> {
+        if msg.interface()?.as_bytes() != adv::MANAGER_INTERFACE.as_bytes()
+            || msg.member()?.as_bytes() != b"RegisterAdvertisement"
+        {
+            return None;
+        }
+        let adv_path: Path = msg.get1()?;
+        Some((msg.sender()?.into_static(), adv_path.into_static()))
+    }
+
+    fn dispatch(&self, msg: &Message, adv_props: Option<PropMap>) -> std::result::Result<Message, MethodErr> {
+        let path = msg.path().ok_or_else(MethodErr::no_arg)?.into_static();
+        let interface = msg.interface().map(|i| i.to_string()).unwrap_or_default();
+        let method = msg.member().map(|m| m.to_string()).unwrap_or_default();
+        log::trace!("Mock Bluetooth daemon: {}: {}.{}", &path, &interface, &method);
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let call = MockCall { path: path.to_string(), interface: interface.clone(), method: method.clone() };
+            state.call_txs.retain(|tx| tx.unbounded_send(call.clone()).is_ok());
+
+            if let Some(kind) = state.errors.get(&(path.to_string(), method.clone())) {
+                return Err(bluez_error(kind.clone()));
+            }
+
+            let known = match interface.as_str() {
+                OBJECT_MANAGER_INTERFACE => path.as_bytes() == b"/",
+                PROPERTIES_INTERFACE => state.objects.contains_key(&path),
+                _ => state.objects.get(&path).map(|ifaces| ifaces.contains_key(&interface)).unwrap_or_default(),
+            };
+            if !known {
+                return Err(MethodErr::from((UNKNOWN_OBJECT, format!("{path} does not exist").as_str())));
+            }
+        }
+
+        let reply = msg.method_return();
+        match (interface.as_str(), method.as_str()) {
+            (OBJECT_MANAGER_INTERFACE, "GetManagedObjects") => {
+                let state = self.state.lock().unwrap();
+                let objects: HashMap<_, HashMap<_, _>> = state
+                    .objects
+                    .iter()
+                    .filter(|(_, ifaces)| !ifaces.is_empty())
+                    .map(|(path, ifaces)| {
+                        (path.clone(), ifaces.iter().map(|(i, props)| (i.clone(), clone_props(props))).collect())
+                    })
+                    .collect();
+                Ok(reply.append1(objects))
+            }
+            (PROPERTIES_INTERFACE, "Get") => {
+                let (iface, name): (String, String) = msg.read2()?;
+                let state = self.state.lock().unwrap();
+                match state
+                    .objects
+                    .get(&path)
+                    .and_then(|ifaces| ifaces.get(&iface))
+                    .and_then(|props| props.get(&name))
+                {
+                    Some(value) => Ok(reply.append1(Variant(value.0.box_clone()))),
+                    None => Err(MethodErr::from((INVALID_ARGS, "No such property"))),
+                }
+            }
+            (PROPERTIES_INTERFACE, "GetAll") => {
+                let iface: String = msg.read1()?;
+                let state = self.state.lock().unwrap();
+                let props = state.objects.get(&path).and_then(|ifaces| ifaces.get(&iface)).map(clone_props);
+                Ok(reply.append1(props.unwrap_or_default()))
+            }
+            (PROPERTIES_INTERFACE, "Set") => {
+                let (iface, name, value): (String, String, PropValue) = msg.read3()?;
+                let exists = self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .objects
+                    .get(&path)
+                    .and_then(|ifaces| ifaces.get(&iface))
+                    .map(|props| props.contains_key(&name))
+                    .unwrap_or_default();
+                if !exists {
+                    return Err(MethodErr::from((INVALID_ARGS, "No such property")));
+                }
+                self.set_props(&path, &iface, vec![(name, value)]);
+                Ok(reply)
+            }
+
+            (adapter::INTERFACE, "SetDiscoveryFilter") => {
+                let _filter: PropMap = msg.read1()?;
+                Ok(reply)
+            }
+            (adapter::INTERFACE, "GetDiscoveryFilters") => {
+                Ok(reply.append1(DISCOVERY_FILTERS.iter().map(|f| f.to_string()).collect::<Vec<_>>()))
+            }
+            (adapter::INTERFACE, "StartDiscovery") => {
+                let adapter_name = Adapter::parse_dbus_path(&path).unwrap_or_default().to_string();
+                self.set_props(&path, adapter::INTERFACE, vec![("Discovering".to_string(), variant(true))]);
+                let devices = self.state.lock().unwrap().discoverable.remove(&adapter_name).unwrap_or_default();
+                for device in devices {
+                    let _ = self.add_device(&adapter_name, device);
+                }
+                Ok(reply)
+            }
+            (adapter::INTERFACE, "StopDiscovery") => {
+                self.set_props(&path, adapter::INTERFACE, vec![("Discovering".to_string(), variant(false))]);
+                Ok(reply)
+            }
+            (adapter::INTERFACE, "RemoveDevice") => {
+                let device_path: Path<'static> = msg.read1()?;
+                if Device::parse_dbus_path(&device_path).is_none() || !self.remove_objects(&device_path) {
+                    return Err(bluez_error(ErrorKind::DoesNotExist));
+                }
+                let mut state = self.state.lock().unwrap();
+                state.services.remove(&device_path);
+                state.service_records.remove(&device_path);
+                Ok(reply)
+            }
+            (adapter::INTERFACE, "ConnectDevice") => {
+                let props: PropMap = msg.read1()?;
+                let address: Address = prop_cast::<String>(&props, "Address")
+                    .and_then(|addr| addr.parse().ok())
+                    .ok_or_else(|| MethodErr::invalid_arg("Address"))?;
+                let adapter_name = Adapter::parse_dbus_path(&path).unwrap_or_default().to_string();
+                let device_path =
+                    Device::dbus_path(&adapter_name, address).map_err(|err| MethodErr::failed(&err))?;
+                if self.has_object(&device_path) {
+                    return Err(bluez_error(ErrorKind::AlreadyExists));
+                }
+                let device = {
+                    let mut state = self.state.lock().unwrap();
+                    let discoverable = state.discoverable.entry(adapter_name.clone()).or_default();
+                    match discoverable.iter().position(|dev| dev.address == address) {
+                        Some(pos) => discoverable.remove(pos),
+                        None => MockDevice::new(address),
+                    }
+                };
+                self.add_device(&adapter_name, device).map_err(|err| MethodErr::failed(&err))?;
+                self.connect_device(&device_path);
+                Ok(reply.append1(device_path))
+            }
+
+            (device::INTERFACE, "Connect") => {
+                self.connect_device(&path);
+                Ok(reply)
+            }
+            (device::INTERFACE, "Disconnect") => {
+                self.unresolve_services(&path);
+                self.set_props(
+                    &path,
+                    device::INTERFACE,
+                    vec![
+                        ("ServicesResolved".to_string(), variant(false)),
+                        ("Connected".to_string(), variant(false)),
+                    ],
+                );
+                Ok(reply)
+            }
+            (device::INTERFACE, "ConnectProfile") | (device::INTERFACE, "DisconnectProfile") => {
+                let _uuid: String = msg.read1()?;
+                Ok(reply)
+            }
+            (device::INTERFACE, "Pair") => {
+                self.set_props(&path, device::INTERFACE, vec![("Paired".to_string(), variant(true))]);
+                Ok(reply)
+            }
+            (device::INTERFACE, "CancelPairing") => Ok(reply),
+            (device::INTERFACE, "GetServiceRecords") => {
+                let records = self.state.lock().unwrap().service_records.get(&path).cloned().unwrap_or_default();
+                let records: Vec<Vec<u8>> = records.iter().map(|record| record.encode()).collect();
+                Ok(reply.append1(records))
+            }
+
+            (network::INTERFACE, "Connect") => {
+                let role: String = msg.read1()?;
+                let role: Role = role.parse().map_err(|_| bluez_error(ErrorKind::InvalidArguments))?;
+                let uuids: Vec<String> = self.prop(&path, device::INTERFACE, "UUIDs").unwrap_or_default();
+                if !uuids.iter().any(|uuid| uuid.parse() == Ok(role.uuid())) {
+                    return Err(bluez_error(ErrorKind::NotSupported));
+                }
+                if self.prop::<bool>(&path, network::INTERFACE, "Connected").unwrap_or_default() {
+                    return Err(bluez_error(ErrorKind::AlreadyConnected));
+                }
+                let interface = {
+                    let mut state = self.state.lock().unwrap();
+                    let interface = format!("bnep{}", state.network_interfaces);
+                    state.network_interfaces += 1;
+                    interface
+                };
+                self.set_props(
+                    &path,
+                    network::INTERFACE,
+                    vec![
+                        ("Connected".to_string(), variant(true)),
+                        ("Interface".to_string(), variant(interface.clone())),
+                        ("UUID".to_string(), variant(role.uuid().to_string())),
+                    ],
+                );
+                Ok(reply.append1(interface))
+            }
+            (network::INTERFACE, "Disconnect") => {
+                if !self.prop::<bool>(&path, network::INTERFACE, "Connected").unwrap_or_default() {
+                    return Err(bluez_error(ErrorKind::Failed));
+                }
+                self.set_props(&path, network::INTERFACE, vec![("Connected".to_string(), variant(false))]);
+                Ok(reply)
+            }
+
+            (network::SERVER_INTERFACE, "Register") => {
+                let (role, bridge): (String, String) = msg.read2()?;
+                let role: Role = role.parse().map_err(|_| bluez_error(ErrorKind::InvalidArguments))?;
+                let mut state = self.state.lock().unwrap();
+                let servers = state.network_servers.entry(path.clone()).or_default();
+                if servers.contains_key(&role) {
+                    return Err(bluez_error(ErrorKind::AlreadyExists));
+                }
+                servers.insert(role, bridge);
+                Ok(reply)
+            }
+            (network::SERVER_INTERFACE, "Unregister") => {
+                let role: String = msg.read1()?;
+                let role: Role = role.parse().map_err(|_| bluez_error(ErrorKind::InvalidArguments))?;
+                let mut state = self.state.lock().unwrap();
+                match state.network_servers.get_mut(&path).and_then(|servers| servers.remove(&role)) {
+                    Some(_) => Ok(reply),
+                    None => Err(bluez_error(ErrorKind::DoesNotExist)),
+                }
+            }
+
+            (gatt::CHARACTERISTIC_INTERFACE, "ReadValue") => {
+                let options: PropMap = msg.read1()?;
+                self.require_flag(&path, |flags| flags.read)?;
+                let value = self.read_value(&path, gatt::CHARACTERISTIC_INTERFACE, &options)?;
+                Ok(reply.append1(value))
+            }
+            (gatt::CHARACTERISTIC_INTERFACE, "WriteValue") => {
+                let (value, options): (Vec<u8>, PropMap) = msg.read2()?;
+                self.require_flag(&path, |flags| flags.write || flags.write_without_response)?;
+                self.write_value(&path, gatt::CHARACTERISTIC_INTERFACE, value, &options)?;
+                Ok(reply)
+            }
+            (gatt::CHARACTERISTIC_INTERFACE, "StartNotify") => {
+                self.require_flag(&path, |flags| flags.notify || flags.indicate)?;
+                self.set_props(
+                    &path,
+                    gatt::CHARACTERISTIC_INTERFACE,
+                    vec![("Notifying".to_string(), variant(true))],
+                );
+                Ok(reply)
+            }
+            (gatt::CHARACTERISTIC_INTERFACE, "StopNotify") => {
+                self.set_props(
+                    &path,
+                    gatt::CHARACTERISTIC_INTERFACE,
+                    vec![("Notifying".to_string(), variant(false))],
+                );
+                Ok(reply)
+            }
+            (gatt::CHARACTERISTIC_INTERFACE, "AcquireWrite")
+            | (gatt::CHARACTERISTIC_INTERFACE, "AcquireNotify") => Err(bluez_error(ErrorKind::NotSupported)),
+
+            (gatt::DESCRIPTOR_INTERFACE, "ReadValue") => {
+                let options: PropMap = msg.read1()?;
+                let value = self.read_value(&path, gatt::DESCRIPTOR_INTERFACE, &options)?;
+                Ok(reply.append1(value))
+            }
+            (gatt::DESCRIPTOR_INTERFACE, "WriteValue") => {
+                let (value, options): (Vec<u8>, PropMap) = msg.read2()?;
+                self.write_value(&path, gatt::DESCRIPTOR_INTERFACE, value, &options)?;
+                Ok(reply)
+            }
+
+            (adv::MANAGER_INTERFACE, "RegisterAdvertisement") => {
+                let (adv_path, _options): (Path<'static>, PropMap) = msg.read2()?;
+                let adapter_name = Adapter::parse_dbus_path(&path).unwrap_or_default().to_string();
+                let props = adv_props.ok_or_else(|| bluez_error(ErrorKind::InvalidArguments))?;
+                let adv =
+                    advertisement_from_dict(&props).ok_or_else(|| bluez_error(ErrorKind::InvalidArguments))?;
+
+                let active = {
+                    let supported: u8 =
+                        self.prop(&path, adv::MANAGER_INTERFACE, "SupportedInstances").unwrap_or(0);
+                    let mut state = self.state.lock().unwrap();
+                    let advs = state.advertisements.entry(adapter_name).or_default();
+                    if advs.iter().any(|(p, _)| *p == adv_path) {
+                        return Err(bluez_error(ErrorKind::AlreadyExists));
+                    }
+                    if advs.len() >= supported as usize {
+                        return Err(MethodErr::from((
+                            format!("{ERR_PREFIX}Failed"),
+                            "Maximum advertisements reached",
+                        )));
+                    }
+                    advs.push((adv_path, adv));
+                    advs.len() as u8
+                };
+                self.set_props(
+                    &path,
+                    adv::MANAGER_INTERFACE,
+                    vec![("ActiveInstances".to_string(), variant(active))],
+                );
+                Ok(reply)
+            }
+            (adv::MANAGER_INTERFACE, "UnregisterAdvertisement") => {
+                let adv_path: Path<'static> = msg.read1()?;
+                let adapter_name = Adapter::parse_dbus_path(&path).unwrap_or_default().to_string();
+                let active = {
+                    let mut state = self.state.lock().unwrap();
+                    let advs = state.advertisements.entry(adapter_name).or_default();
+                    let Some(pos) = advs.iter().position(|(p, _)| *p == adv_path) else {
+                        return Err(bluez_error(ErrorKind::DoesNotExist));
+                    };
+                    advs.remove(pos);
+                    advs.len() as u8
+                };
+                self.set_props(
+                    &path,
+                    adv::MANAGER_INTERFACE,
+                    vec![("ActiveInstances".to_string(), variant(active))],
+                );
+                Ok(reply)
+            }
+
+            _ => {
+                Err(MethodErr::from((UNKNOWN_METHOD, format!("{interface}.{method} is not supported").as_str())))
+            }
+        }
+    }
+
+    fn connect_device(&self, path: &Path<'static>) {
+        self.set_props(path, device::INTERFACE, vec![("Connected".to_string(), variant(true))]);
+        let resolved: bool = self.prop(path, device::INTERFACE, "ServicesResolved").unwrap_or_default();
+        if !resolved {
+            self.resolve_services(path);
+            self.set_props(path, device::INTERFACE, vec![("ServicesResolved".to_string(), variant(true))]);
+        }
+    }
+
+    fn require_flag(
+        &self, path: &Path<'static>, f: impl FnOnce(&CharacteristicFlags) -> bool,
+    ) -> std::result::Result<(), MethodErr> {
+        let flags: Vec<String> = self.prop(path, gatt::CHARACTERISTIC_INTERFACE, "Flags").unwrap_or_default();
+        match f(&CharacteristicFlags::from_slice(&flags)) {
+            true => Ok(()),
+            false => Err(bluez_error(ErrorKind::NotPermitted)),
+        }
+    }
+
+    fn read_value(
+        &self, path: &Path<'static>, interface: &str, options: &PropMap,
+    ) -> std::result::Result<Vec<u8>, MethodErr> {
+        let offset = prop_cast::<u16>(options, "offset").copied().unwrap_or_default() as usize;
+        let value: Vec<u8> = self.prop(path, interface, "Value").unwrap_or_default();
+        match value.get(offset..) {
+            Some(value) => Ok(value.to_vec()),
+            None => Err(bluez_error(ErrorKind::InvalidOffset)),
+        }
+    }
+
+    fn write_value(
+        &self, path: &Path<'static>, interface: &str, value: Vec<u8>, options: &PropMap,
+    ) -> std::result::Result<(), MethodErr> {
+        let offset = prop_cast::<u16>(options, "offset").copied().unwrap_or_default() as usize;
+        let mut stored: Vec<u8> = self.prop(path, interface, "Value").unwrap_or_default();
+        if offset > stored.len() {
+            return Err(bluez_error(ErrorKind::InvalidOffset));
+        }
+        stored.truncate(offset);
+        stored.extend(value);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(props) = state.objects.get_mut(path).and_then(|ifaces| ifaces.get_mut(interface)) {
+            props.insert("Value".to_string(), variant(stored));
+        }
+        Ok(())
+    }
+}
+
+fn variant<T: RefArg + 'static>(value: T) -> PropValue {
+    Variant(Box::new(value))
+}
+
+fn insert_prop<T: RefArg + 'static>(props: &mut PropMap, name: &str, value: T) {
+    props.insert(name.to_string(), variant(value));
+}
+
+fn clone_props(props: &PropMap) -> PropMap {
+    props.iter().map(|(name, value)| (name.clone(), Variant(value.0.box_clone()))).collect()
+}
+
+fn bluez_error(kind: ErrorKind) -> MethodErr {
+    MethodErr::from((format!("{ERR_PREFIX}{kind:?}"), kind.to_string()))
+}
+
+fn variant_map<K, V>(map: impl IntoIterator<Item = (K, V)>) -> HashMap<K, PropValue>
+where
+    K: std::hash::Hash + Eq,
+    V: RefArg + 'static,
+{
+    map.into_iter().map(|(k, v)| (k, variant(v))).collect()
+}
+
+/// Converts a device property into its D-Bus interface, name and value.
+fn device_property_to_dbus(property: DeviceProperty) -> (&'static str, &'static str, PropValue) {
+    use DeviceProperty::*;
+    let (name, value) = match property {
+        Name(v) => ("Name", variant(v)),
+        RemoteAddress(v) => ("Address", variant(v.to_string())),
+        AddressType(v) => ("AddressType", variant(v.to_string())),
+        Icon(v) => ("Icon", variant(v)),
+        Class(v) => ("Class", variant(v)),
+        Appearance(v) => ("Appearance", variant(v)),
+        Uuids(v) => ("UUIDs", variant(v.iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>())),
+        Paired(v) => ("Paired", variant(v)),
+        Connected(v) => ("Connected", variant(v)),
+        Trusted(v) => ("Trusted", variant(v)),
+        Blocked(v) => ("Blocked", variant(v)),
+        WakeAllowed(v) => ("WakeAllowed", variant(v)),
+        Alias(v) => ("Alias", variant(v)),
+        LegacyPairing(v) => ("LegacyPairing", variant(v)),
+        Modalias(v) => {
+            ("Modalias", variant(format!("{}:v{:04X}p{:04X}d{:04X}", v.source, v.vendor, v.product, v.device)))
+        }
+        Rssi(v) => ("RSSI", variant(v)),
+        TxPower(v) => ("TxPower", variant(v)),
+        ManufacturerData(v) => ("ManufacturerData", variant(variant_map(v))),
+        ServiceData(v) => ("ServiceData", variant(variant_map(v.into_iter().map(|(k, v)| (k.to_string(), v))))),
+        ServicesResolved(v) => ("ServicesResolved", variant(v)),
+        AdvertisingFlags(v) => ("AdvertisingFlags", variant(v)),
+        AdvertisingData(v) => ("AdvertisingData", variant(variant_map(v))),
+        BatteryPercentage(v) => return (device::BATTERY_INTERFACE, "Percentage", variant(v)),
+    };
+    (device::INTERFACE, name, value)
+}
+
+/// Reads the properties of a registered advertisement.
+fn advertisement_from_dict(dict: &PropMap) -> Option<Advertisement> {
+    fn uuids(dict: &PropMap, name: &str) -> BTreeSet<Uuid> {
+        prop_cast::<Vec<String>>(dict, name)
+            .map(|v| v.iter().filter_map(|s| s.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn data_map<K: Clone + Ord + std::hash::Hash + 'static>(dict: &PropMap, name: &str) -> BTreeMap<K, Vec<u8>> {
+        let Some(value) = dict.get(name) else { return BTreeMap::new() };
+        crate::with_variant_property_cast(&value.0, |map: Option<&HashMap<K, PropValue>>| {
+            map.map(|map| {
+                map.iter()
+                    .filter_map(|(k, v)| dbus::arg::cast::<Vec<u8>>(&v.0).map(|v| (k.clone(), v.clone())))
+                    .collect()
+            })
+            .unwrap_or_default()
+        })
+    }
+
+    let secs = |name| prop_cast::<u16>(dict, name).map(|v| Duration::from_secs((*v).into()));
+    let millis = |name| prop_cast::<u32>(dict, name).map(|v| Duration::from_millis((*v).into()));
+
+    Some(Advertisement {
+        advertisement_type: prop_cast::<String>(dict, "Type")?.parse().ok()?,
+        service_uuids: uuids(dict, "ServiceUUIDs"),
+        manufacturer_data: data_map(dict, "ManufacturerData"),
+        solicit_uuids: uuids(dict, "SolicitUUIDs"),
+        service_data: data_map::<String>(dict, "ServiceData")
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.parse().ok()?, v)))
+            .collect(),
+        advertising_data: data_map(dict, "Data"),
+        discoverable: prop_cast::<bool>(dict, "Discoverable").copied(),
+        discoverable_timeout: secs("DiscoverableTimeout"),
+        system_includes: prop_cast::<Vec<String>>(dict, "Includes")
+            .map(|v| v.iter().filter_map(|s| s.parse().ok()).collect())
+            .unwrap_or_default(),
+        local_name: prop_cast::<String>(dict, "LocalName").cloned(),
+        appearance: prop_cast::<u16>(dict, "Appearance").copied(),
+        duration: secs("Duration"),
+        timeout: secs("Timeout"),
+        secondary_channel: prop_cast::<String>(dict, "SecondaryChannel").and_then(|s| s.parse().ok()),
+        min_interval: millis("MinInterval"),
+        max_interval: millis("MaxInterval"),
+        tx_power: prop_cast::<i16>(dict, "TxPower").copied(),
+        _non_exhaustive: (),
+    })
+}
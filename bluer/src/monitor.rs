@@ -401,8 +401,18 @@ impl MonitorManager {
         }
 
         log::trace!("Registering advertisement monitor root at {}", &root);
-        let proxy = Proxy::new(SERVICE_NAME, manager_path, TIMEOUT, inner.connection.clone());
-        let () = proxy.method_call(MANAGER_INTERFACE, "RegisterMonitor", (root.clone(),)).await?;
+        let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, inner.connection.clone());
+        let reg_root = root.clone();
+        inner
+            .register_with_daemon(&root, Some(adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, manager_path.clone(), TIMEOUT, connection);
+                let reg_root = reg_root.clone();
+                async move {
+                    let () = proxy.method_call(MANAGER_INTERFACE, "RegisterMonitor", (reg_root,)).await?;
+                    Ok(())
+                }
+            })
+            .await?;
 
         let (_drop_tx, drop_rx) = oneshot::channel();
         let unreg_root = root.clone();
@@ -411,6 +421,7 @@ impl MonitorManager {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering advertisement monitor root at {}", &unreg_root);
+            unreg_inner.unregister_with_daemon(&unreg_root).await;
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterMonitor", (unreg_root.clone(),)).await;
 
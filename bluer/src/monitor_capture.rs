@@ -0,0 +1,913 @@
+//! Capture of HCI traffic through the kernel monitor channel.
+//!
+//! The monitor channel provides a copy of all HCI traffic of all Bluetooth
+//! controllers, including commands, events, ACL, SCO and ISO data, as well as
+//! index and logging information. This is the same information displayed by `btmon`.
+//!
+//! Open a [Capture] to obtain a stream of [records](Record) and use a
+//! [BtSnoopWriter] to save them into a btsnoop file, which can be read by
+//! Wireshark and `btmon -r`.
+//!
+//! Capturing requires the `CAP_NET_RAW` capability.
+//! No running Bluetooth daemon is required.
+//!
+
+use futures::{ready, stream, Stream};
+use libc::{AF_BLUETOOTH, SOCK_RAW};
+use std::{
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::io::{unix::AsyncFd, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::{
+    dissect::hci::{self, AclPacket, HciPacket},
+    sock::{self, HciAddr, OwnedFd},
+    sys::{BTPROTO_HCI, HCI_CHANNEL_MONITOR, HCI_DEV_NONE},
+    Address,
+};
+
+/// Controller index used for records that do not refer to a specific controller.
+pub const INDEX_NONE: u16 = HCI_DEV_NONE;
+
+/// Length of the monitor packet header.
+const HEADER_LEN: usize = 6;
+
+/// Length of the name in a new index record.
+const NEW_INDEX_NAME_LEN: usize = 8;
+
+/// Maximum length of a record in a capture file.
+///
+/// This is the maximum HCI packet payload length plus room for the
+/// HCI packet header, the H4 packet type and the pcap pseudo header.
+const MAX_RECORD_LEN: usize = 16 + u16::MAX as usize;
+
+/// Monitor channel opcodes.
+pub mod opcode {
+    /// New controller index.
+    pub const NEW_INDEX: u16 = 0;
+    /// Deleted controller index.
+    pub const DEL_INDEX: u16 = 1;
+    /// HCI command packet.
+    pub const COMMAND_PKT: u16 = 2;
+    /// HCI event packet.
+    pub const EVENT_PKT: u16 = 3;
+    /// Outgoing ACL data packet.
+    pub const ACL_TX_PKT: u16 = 4;
+    /// Incoming ACL data packet.
+    pub const ACL_RX_PKT: u16 = 5;
+    /// Outgoing SCO data packet.
+    pub const SCO_TX_PKT: u16 = 6;
+    /// Incoming SCO data packet.
+    pub const SCO_RX_PKT: u16 = 7;
+    /// Controller opened.
+    pub const OPEN_INDEX: u16 = 8;
+    /// Controller closed.
+    pub const CLOSE_INDEX: u16 = 9;
+    /// Controller information.
+    pub const INDEX_INFO: u16 = 10;
+    /// Vendor diagnostic data.
+    pub const VENDOR_DIAG: u16 = 11;
+    /// System note.
+    pub const SYSTEM_NOTE: u16 = 12;
+    /// User logging message.
+    pub const USER_LOGGING: u16 = 13;
+    /// Control channel opened.
+    pub const CTRL_OPEN: u16 = 14;
+    /// Control channel closed.
+    pub const CTRL_CLOSE: u16 = 15;
+    /// Control channel command.
+    pub const CTRL_COMMAND: u16 = 16;
+    /// Control channel event.
+    pub const CTRL_EVENT: u16 = 17;
+    /// Outgoing ISO data packet.
+    pub const ISO_TX_PKT: u16 = 18;
+    /// Incoming ISO data packet.
+    pub const ISO_RX_PKT: u16 = 19;
+}
+
+pub use crate::dissect::hci::Direction;
+
+/// Content of a monitor record.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Packet {
+    /// A controller has been added.
+    NewIndex {
+        /// Controller type.
+        controller_type: u8,
+        /// Bus the controller is attached to.
+        bus: u8,
+        /// Controller address.
+        address: Address,
+        /// Controller name, e.g. `hci0`.
+        name: String,
+    },
+    /// A controller has been removed.
+    DeleteIndex,
+    /// HCI command packet, starting with the opcode.
+    Command(Vec<u8>),
+    /// HCI event packet, starting with the event code.
+    Event(Vec<u8>),
+    /// ACL data packet, starting with the handle.
+    Acl(Direction, Vec<u8>),
+    /// SCO data packet, starting with the handle.
+    Sco(Direction, Vec<u8>),
+    /// ISO data packet, starting with the handle.
+    Iso(Direction, Vec<u8>),
+    /// A controller has been opened.
+    OpenIndex,
+    /// A controller has been closed.
+    CloseIndex,
+    /// Controller information.
+    IndexInfo {
+        /// Controller address.
+        address: Address,
+        /// Manufacturer id.
+        manufacturer: u16,
+    },
+    /// Vendor diagnostic data.
+    VendorDiag(Vec<u8>),
+    /// Note from the kernel.
+    SystemNote(String),
+    /// Logging message from a user space program.
+    UserLogging {
+        /// Syslog priority.
+        priority: u8,
+        /// Identifier of the program.
+        ident: String,
+        /// Message.
+        message: String,
+    },
+    /// Other packet, including management control channel traffic.
+    Other {
+        /// Monitor opcode.
+        opcode: u16,
+        /// Packet data.
+        data: Vec<u8>,
+    },
+}
+
+impl Packet {
+    /// Parses a packet from its monitor opcode and data.
+    pub fn parse(opcode: u16, data: &[u8]) -> Self {
+        Self::parse_known(opcode, data).unwrap_or_else(|| Self::Other { opcode, data: data.to_vec() })
+    }
+
+    fn parse_known(opcode: u16, data: &[u8]) -> Option<Self> {
+        use opcode::*;
+        let packet = match opcode {
+            NEW_INDEX => {
+                let mut addr: [u8; 6] = data.get(2..8)?.try_into().ok()?;
+                addr.reverse();
+                Self::NewIndex {
+                    controller_type: *data.first()?,
+                    bus: *data.get(1)?,
+                    address: Address(addr),
+                    name: get_string(data.get(8..8 + NEW_INDEX_NAME_LEN)?),
+                }
+            }
+            DEL_INDEX => Self::DeleteIndex,
+            COMMAND_PKT => Self::Command(data.to_vec()),
+            EVENT_PKT => Self::Event(data.to_vec()),
+            ACL_TX_PKT => Self::Acl(Direction::Tx, data.to_vec()),
+            ACL_RX_PKT => Self::Acl(Direction::Rx, data.to_vec()),
+            SCO_TX_PKT => Self::Sco(Direction::Tx, data.to_vec()),
+            SCO_RX_PKT => Self::Sco(Direction::Rx, data.to_vec()),
+            ISO_TX_PKT => Self::Iso(Direction::Tx, data.to_vec()),
+            ISO_RX_PKT => Self::Iso(Direction::Rx, data.to_vec()),
+            OPEN_INDEX => Self::OpenIndex,
+            CLOSE_INDEX => Self::CloseIndex,
+            INDEX_INFO => {
+                let mut addr: [u8; 6] = data.get(..6)?.try_into().ok()?;
+                addr.reverse();
+                Self::IndexInfo {
+                    address: Address(addr),
+                    manufacturer: u16::from_le_bytes(data.get(6..8)?.try_into().ok()?),
+                }
+            }
+            VENDOR_DIAG => Self::VendorDiag(data.to_vec()),
+            SYSTEM_NOTE => Self::SystemNote(get_string(data)),
+            USER_LOGGING => {
+                let priority = *data.first()?;
+                let ident_len = *data.get(1)? as usize;
+                let ident = get_string(data.get(2..2 + ident_len)?);
+                let message = get_string(data.get(2 + ident_len..)?);
+                Self::UserLogging { priority, ident, message }
+            }
+            _ => return None,
+        };
+        Some(packet)
+    }
+
+    /// Monitor opcode of this packet.
+    pub fn opcode(&self) -> u16 {
+        use opcode::*;
+        match self {
+            Self::NewIndex { .. } => NEW_INDEX,
+            Self::DeleteIndex => DEL_INDEX,
+            Self::Command(_) => COMMAND_PKT,
+            Self::Event(_) => EVENT_PKT,
+            Self::Acl(Direction::Tx, _) => ACL_TX_PKT,
+            Self::Acl(Direction::Rx, _) => ACL_RX_PKT,
+            Self::Sco(Direction::Tx, _) => SCO_TX_PKT,
+            Self::Sco(Direction::Rx, _) => SCO_RX_PKT,
+            Self::Iso(Direction::Tx, _) => ISO_TX_PKT,
+            Self::Iso(Direction::Rx, _) => ISO_RX_PKT,
+            Self::OpenIndex => OPEN_INDEX,
+            Self::CloseIndex => CLOSE_INDEX,
+            Self::IndexInfo { .. } => INDEX_INFO,
+            Self::VendorDiag(_) => VENDOR_DIAG,
+            Self::SystemNote(_) => SYSTEM_NOTE,
+            Self::UserLogging { .. } => USER_LOGGING,
+            Self::Other { opcode, .. } => *opcode,
+        }
+    }
+
+    /// Parses a packet in H4 (UART) framing, i.e. starting with the HCI packet type.
+    pub fn parse_h4(direction: Direction, data: &[u8]) -> Option<Self> {
+        let (&ty, data) = data.split_first()?;
+        let data = data.to_vec();
+        let packet = match ty {
+            hci::H4_COMMAND => Self::Command(data),
+            hci::H4_EVENT => Self::Event(data),
+            hci::H4_ACL => Self::Acl(direction, data),
+            hci::H4_SCO => Self::Sco(direction, data),
+            hci::H4_ISO => Self::Iso(direction, data),
+            _ => return None,
+        };
+        Some(packet)
+    }
+
+    /// Decodes the contained HCI packet for [dissection](crate::dissect).
+    ///
+    /// Returns `None` if this is not an HCI packet or it is malformed.
+    pub fn hci_packet(&self) -> Option<HciPacket> {
+        match self {
+            Self::Command(data) => HciPacket::parse_command(data),
+            Self::Event(data) => HciPacket::parse_event(data),
+            Self::Acl(dir, data) => Some(HciPacket::Acl(*dir, AclPacket::parse(data)?)),
+            Self::Sco(dir, data) => Some(HciPacket::Sco(*dir, data.clone())),
+            Self::Iso(dir, data) => Some(HciPacket::Iso(*dir, data.clone())),
+            _ => None,
+        }
+    }
+
+    /// Encodes the packet data, excluding the monitor header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::NewIndex { controller_type, bus, address, name } => {
+                let mut addr = address.0;
+                addr.reverse();
+                let mut buf = vec![*controller_type, *bus];
+                buf.extend_from_slice(&addr);
+                let name = name.as_bytes();
+                let len = name.len().min(NEW_INDEX_NAME_LEN - 1);
+                buf.extend_from_slice(&name[..len]);
+                buf.resize(2 + 6 + NEW_INDEX_NAME_LEN, 0);
+                buf
+            }
+            Self::DeleteIndex | Self::OpenIndex | Self::CloseIndex => Vec::new(),
+            Self::Command(data)
+            | Self::Event(data)
+            | Self::Acl(_, data)
+            | Self::Sco(_, data)
+            | Self::Iso(_, data)
+            | Self::VendorDiag(data)
+            | Self::Other { data, .. } => data.clone(),
+            Self::IndexInfo { address, manufacturer } => {
+                let mut buf = address.0.to_vec();
+                buf.reverse();
+                buf.extend_from_slice(&manufacturer.to_le_bytes());
+                buf
+            }
+            Self::SystemNote(note) => {
+                let mut buf = note.as_bytes().to_vec();
+                buf.push(0);
+                buf
+            }
+            Self::UserLogging { priority, ident, message } => {
+                let ident = &ident.as_bytes()[..ident.len().min(u8::MAX as usize - 1)];
+                let mut buf = vec![*priority, ident.len() as u8 + 1];
+                buf.extend_from_slice(ident);
+                buf.push(0);
+                buf.extend_from_slice(message.as_bytes());
+                buf.push(0);
+                buf
+            }
+        }
+    }
+}
+
+fn get_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Captured monitor record.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// Index of the controller this record refers to or [INDEX_NONE].
+    pub index: u16,
+    /// Time of reception.
+    pub timestamp: SystemTime,
+    /// Packet.
+    pub packet: Packet,
+}
+
+impl Record {
+    /// Decodes a record from a monitor channel packet including its header.
+    pub fn decode(buf: &[u8], timestamp: SystemTime) -> Result<Self> {
+        if buf.len() < HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "monitor packet too short"));
+        }
+        let opcode = u16::from_le_bytes([buf[0], buf[1]]);
+        let index = u16::from_le_bytes([buf[2], buf[3]]);
+        let len = u16::from_le_bytes([buf[4], buf[5]]) as usize;
+        let data = buf
+            .get(HEADER_LEN..HEADER_LEN + len)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "monitor packet length mismatch"))?;
+        Ok(Self { index, timestamp, packet: Packet::parse(opcode, data) })
+    }
+}
+
+/// Capture of all HCI traffic through the kernel monitor channel.
+///
+/// Use the [Stream] implementation to receive captured records.
+#[derive(Debug)]
+pub struct Capture {
+    fd: AsyncFd<OwnedFd>,
+    buf: Vec<u8>,
+}
+
+impl Capture {
+    /// Starts capturing HCI traffic of all controllers.
+    ///
+    /// This requires the `CAP_NET_RAW` capability.
+    /// The kernel first sends records describing all existing controllers.
+    pub async fn new() -> Result<Self> {
+        let fd = sock::socket(AF_BLUETOOTH, SOCK_RAW, BTPROTO_HCI)?;
+        sock::bind(&fd, HciAddr { dev: HCI_DEV_NONE, channel: HCI_CHANNEL_MONITOR })?;
+        Ok(Self { fd: AsyncFd::new(fd)?, buf: vec![0; HEADER_LEN + u16::MAX as usize] })
+    }
+}
+
+impl Stream for Capture {
+    type Item = Result<Record>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = ready!(this.fd.poll_read_ready(cx))?;
+            let mut buf = ReadBuf::new(&mut this.buf);
+            match guard.try_io(|inner| sock::recv(inner.get_ref(), &mut buf, 0)) {
+                Ok(Ok(n)) => return Poll::Ready(Some(Record::decode(&this.buf[..n], SystemTime::now()))),
+                Ok(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// btsnoop datalink type for unencapsulated HCI packets.
+pub const BTSNOOP_FORMAT_HCI: u32 = 1001;
+
+/// btsnoop datalink type for HCI packets in H4 (UART) framing.
+pub const BTSNOOP_FORMAT_UART: u32 = 1002;
+
+/// btsnoop datalink type for Linux monitor channel records.
+pub const BTSNOOP_FORMAT_MONITOR: u32 = 2001;
+
+/// pcap link type for HCI packets in H4 framing with direction header.
+pub const LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR: u32 = 201;
+
+/// pcap link type for Linux monitor channel records.
+pub const LINKTYPE_BLUETOOTH_LINUX_MONITOR: u32 = 254;
+
+/// Identification pattern at the start of a btsnoop file.
+const BTSNOOP_MAGIC: &[u8; 8] = b"btsnoop\0";
+
+/// btsnoop file format version.
+const BTSNOOP_VERSION: u32 = 1;
+
+/// Offset of the Unix epoch from the btsnoop epoch (year 0) in microseconds.
+const BTSNOOP_EPOCH_OFFSET: i64 = 0x00dc_ddb3_0f2f_8000;
+
+/// Writes monitor records into a btsnoop file.
+///
+/// The file uses the Linux monitor datalink type and can be read by
+/// Wireshark and `btmon -r`.
+#[derive(Debug)]
+pub struct BtSnoopWriter<W> {
+    writer: W,
+}
+
+impl<W> BtSnoopWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Creates a new btsnoop writer and writes the file header.
+    pub async fn new(mut writer: W) -> Result<Self> {
+        let mut header = BTSNOOP_MAGIC.to_vec();
+        header.extend_from_slice(&BTSNOOP_VERSION.to_be_bytes());
+        header.extend_from_slice(&BTSNOOP_FORMAT_MONITOR.to_be_bytes());
+        writer.write_all(&header).await?;
+        Ok(Self { writer })
+    }
+
+    /// Writes a record.
+    pub async fn write_record(&mut self, record: &Record) -> Result<()> {
+        let data = record.packet.to_bytes();
+        let len: u32 =
+            data.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "record too long"))?;
+        let flags = (record.index as u32) << 16 | record.packet.opcode() as u32;
+        let micros = match record.timestamp.duration_since(UNIX_EPOCH) {
+            Ok(dur) => dur.as_micros() as i64,
+            Err(err) => -(err.duration().as_micros() as i64),
+        };
+
+        let mut buf = Vec::with_capacity(24 + data.len());
+        buf.extend_from_slice(&len.to_be_bytes());
+        buf.extend_from_slice(&len.to_be_bytes());
+        buf.extend_from_slice(&flags.to_be_bytes());
+        buf.extend_from_slice(&0u32.to_be_bytes());
+        let ts = micros
+            .checked_add(BTSNOOP_EPOCH_OFFSET)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "record timestamp out of range"))?;
+        buf.extend_from_slice(&ts.to_be_bytes());
+        buf.extend_from_slice(&data);
+        self.writer.write_all(&buf).await
+    }
+
+    /// Flushes the underlying writer.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Converts a btsnoop timestamp into a system time.
+///
+/// Returns `None` if the timestamp cannot be represented.
+fn btsnoop_timestamp(ts: i64) -> Option<SystemTime> {
+    let micros = ts.checked_sub(BTSNOOP_EPOCH_OFFSET)?;
+    if micros >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_micros(micros as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_micros(micros.unsigned_abs()))
+    }
+}
+
+/// Format of a capture file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FileFormat {
+    /// btsnoop file.
+    BtSnoop {
+        /// Datalink type, for example [BTSNOOP_FORMAT_MONITOR].
+        datalink: u32,
+    },
+    /// pcap file.
+    Pcap {
+        /// Link type, for example [LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR].
+        linktype: u32,
+        /// Whether header fields are big endian.
+        big_endian: bool,
+        /// Whether timestamps have nanosecond resolution.
+        nanos: bool,
+    },
+}
+
+/// Reads monitor records from a btsnoop or pcap capture file.
+///
+/// Supported are btsnoop files with monitor, H4 (UART) or unencapsulated HCI
+/// datalink types and pcap files with the `LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR`
+/// or `LINKTYPE_BLUETOOTH_LINUX_MONITOR` link types.
+/// Records of files that do not contain a controller index are attributed to controller 0.
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    reader: R,
+    format: FileFormat,
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+impl<R> CaptureReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Creates a new capture reader, detecting the file format from its header.
+    pub async fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).await?;
+
+        let format = match magic {
+            [b'b', b't', b's', b'n'] => {
+                let mut header = [0; 12];
+                reader.read_exact(&mut header).await?;
+                if &header[..4] != b"oop\0" {
+                    return Err(invalid_data("invalid btsnoop header"));
+                }
+                let version = u32::from_be_bytes(header[4..8].try_into().unwrap());
+                if version != BTSNOOP_VERSION {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("unsupported btsnoop version {version}"),
+                    ));
+                }
+                let datalink = u32::from_be_bytes(header[8..12].try_into().unwrap());
+                if !matches!(datalink, BTSNOOP_FORMAT_HCI | BTSNOOP_FORMAT_UART | BTSNOOP_FORMAT_MONITOR) {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("unsupported btsnoop datalink type {datalink}"),
+                    ));
+                }
+                FileFormat::BtSnoop { datalink }
+            }
+            _ => {
+                let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                    (0xa1b2c3d4, _) => (false, false),
+                    (0xa1b23c4d, _) => (false, true),
+                    (_, 0xa1b2c3d4) => (true, false),
+                    (_, 0xa1b23c4d) => (true, true),
+                    _ => return Err(invalid_data("unknown capture file format")),
+                };
+                let mut header = [0; 20];
+                reader.read_exact(&mut header).await?;
+                let linktype = header[16..20].try_into().unwrap();
+                let linktype =
+                    if big_endian { u32::from_be_bytes(linktype) } else { u32::from_le_bytes(linktype) };
+                if !matches!(linktype, LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR | LINKTYPE_BLUETOOTH_LINUX_MONITOR) {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("unsupported pcap link type {linktype}"),
+                    ));
+                }
+                FileFormat::Pcap { linktype, big_endian, nanos }
+            }
+        };
+
+        Ok(Self { reader, format })
+    }
+
+    /// Format of the capture file.
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+
+    /// Reads exactly the specified number of bytes or returns `None` at the end of the file.
+    async fn read_or_eof<const N: usize>(&mut self) -> Result<Option<[u8; N]>> {
+        let mut buf = [0; N];
+        let mut filled = 0;
+        while filled < N {
+            match self.reader.read(&mut buf[filled..]).await? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof, "truncated capture record")),
+                n => filled += n,
+            }
+        }
+        Ok(Some(buf))
+    }
+
+    /// Reads the data of a record with the length specified in its header.
+    async fn read_data(&mut self, len: u32) -> Result<Vec<u8>> {
+        let len = len as usize;
+        if len > MAX_RECORD_LEN {
+            return Err(invalid_data("capture record too long"));
+        }
+        let mut data = vec![0; len];
+        self.reader.read_exact(&mut data).await?;
+        Ok(data)
+    }
+
+    /// Reads the next record.
+    ///
+    /// Returns `None` at the end of the file.
+    /// Records that cannot be represented are skipped.
+    pub async fn read_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            let record = match self.format {
+                FileFormat::BtSnoop { datalink } => {
+                    let Some(header) = self.read_or_eof::<24>().await? else { return Ok(None) };
+                    let be32 = |pos: usize| u32::from_be_bytes(header[pos..pos + 4].try_into().unwrap());
+                    let flags = be32(8);
+                    let timestamp = btsnoop_timestamp(i64::from_be_bytes(header[16..24].try_into().unwrap()))
+                        .ok_or_else(|| invalid_data("capture record timestamp out of range"))?;
+                    let data = self.read_data(be32(4)).await?;
+
+                    let direction = if flags & 0x01 == 0 { Direction::Tx } else { Direction::Rx };
+                    match datalink {
+                        BTSNOOP_FORMAT_MONITOR => Some(Record {
+                            index: (flags >> 16) as u16,
+                            timestamp,
+                            packet: Packet::parse(flags as u16, &data),
+                        }),
+                        BTSNOOP_FORMAT_UART => Packet::parse_h4(direction, &data).map(|packet| Record {
+                            index: 0,
+                            timestamp,
+                            packet,
+                        }),
+                        _ => {
+                            let packet = match (flags & 0x02 != 0, direction) {
+                                (true, Direction::Tx) => Packet::Command(data),
+                                (true, Direction::Rx) => Packet::Event(data),
+                                (false, direction) => Packet::Acl(direction, data),
+                            };
+                            Some(Record { index: 0, timestamp, packet })
+                        }
+                    }
+                }
+                FileFormat::Pcap { linktype, big_endian, nanos } => {
+                    let Some(header) = self.read_or_eof::<16>().await? else { return Ok(None) };
+                    let u32_at = |pos: usize| {
+                        let bytes = header[pos..pos + 4].try_into().unwrap();
+                        if big_endian {
+                            u32::from_be_bytes(bytes)
+                        } else {
+                            u32::from_le_bytes(bytes)
+                        }
+                    };
+                    let sub_sec = if nanos {
+                        Duration::from_nanos(u32_at(4) as _)
+                    } else {
+                        Duration::from_micros(u32_at(4) as _)
+                    };
+                    let timestamp = UNIX_EPOCH + Duration::from_secs(u32_at(0) as _) + sub_sec;
+                    let data = self.read_data(u32_at(8)).await?;
+
+                    match linktype {
+                        LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR if data.len() >= 4 => {
+                            let direction = if u32::from_be_bytes(data[..4].try_into().unwrap()) & 0x01 == 0 {
+                                Direction::Tx
+                            } else {
+                                Direction::Rx
+                            };
+                            Packet::parse_h4(direction, &data[4..]).map(|packet| Record {
+                                index: 0,
+                                timestamp,
+                                packet,
+                            })
+                        }
+                        LINKTYPE_BLUETOOTH_LINUX_MONITOR if data.len() >= 4 => Some(Record {
+                            index: u16::from_be_bytes([data[0], data[1]]),
+                            timestamp,
+                            packet: Packet::parse(u16::from_be_bytes([data[2], data[3]]), &data[4..]),
+                        }),
+                        _ => None,
+                    }
+                }
+            };
+
+            match record {
+                Some(record) => return Ok(Some(record)),
+                None => log::trace!("Skipping unsupported capture record"),
+            }
+        }
+    }
+
+    /// Converts the reader into a stream of records.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Record>> {
+        stream::try_unfold(self, |mut reader| async move {
+            Ok(reader.read_record().await?.map(|record| (record, reader)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// btsnoop file with H4 (UART) datalink containing an HCI_Reset command
+    /// and its Command Complete event, 1.5 seconds after the Unix epoch.
+    const BTSNOOP_UART: &[u8] = &[
+        b'b', b't', b's', b'n', b'o', b'o', b'p', 0, // identification pattern
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x03, 0xea, // datalink type 1002
+        0x00, 0x00, 0x00, 0x04, // original length
+        0x00, 0x00, 0x00, 0x04, // included length
+        0x00, 0x00, 0x00, 0x02, // flags: sent command
+        0x00, 0x00, 0x00, 0x00, // cumulative drops
+        0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x46, 0x63, 0x60, // timestamp
+        0x01, 0x03, 0x0c, 0x00, // HCI_Reset
+        0x00, 0x00, 0x00, 0x07, // original length
+        0x00, 0x00, 0x00, 0x07, // included length
+        0x00, 0x00, 0x00, 0x03, // flags: received event
+        0x00, 0x00, 0x00, 0x00, // cumulative drops
+        0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x46, 0x63, 0x60, // timestamp
+        0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00, // Command Complete (HCI_Reset, success)
+    ];
+
+    /// btsnoop file with monitor datalink containing a new index record for hci1
+    /// and an incoming ACL packet on it.
+    const BTSNOOP_MONITOR: &[u8] = &[
+        b'b', b't', b's', b'n', b'o', b'o', b'p', 0, // identification pattern
+        0x00, 0x00, 0x00, 0x01, // version
+        0x00, 0x00, 0x07, 0xd1, // datalink type 2001
+        0x00, 0x00, 0x00, 0x10, // original length
+        0x00, 0x00, 0x00, 0x10, // included length
+        0x00, 0x01, 0x00, 0x00, // flags: index 1, opcode NEW_INDEX
+        0x00, 0x00, 0x00, 0x00, // cumulative drops
+        0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x46, 0x63, 0x60, // timestamp
+        0x00, 0x01, // primary controller on USB
+        0x66, 0x55, 0x44, 0x33, 0x22, 0x11, // address
+        b'h', b'c', b'i', b'1', 0, 0, 0, 0, // name
+        0x00, 0x00, 0x00, 0x09, // original length
+        0x00, 0x00, 0x00, 0x09, // included length
+        0x00, 0x01, 0x00, 0x05, // flags: index 1, opcode ACL_RX_PKT
+        0x00, 0x00, 0x00, 0x00, // cumulative drops
+        0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x46, 0x63, 0x60, // timestamp
+        0x40, 0x20, 0x05, 0x00, // handle 0x040, first automatically flushable, length 5
+        0x01, 0x00, 0x04, 0x00, 0x0a, // L2CAP ATT: ATT_READ_REQ opcode, truncated
+    ];
+
+    /// Little-endian pcap file with microsecond timestamps and link type
+    /// `LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR` containing a received Command Complete event.
+    const PCAP_H4: &[u8] = &[
+        0xd4, 0xc3, 0xb2, 0xa1, // magic
+        0x02, 0x00, 0x04, 0x00, // version 2.4
+        0x00, 0x00, 0x00, 0x00, // time zone
+        0x00, 0x00, 0x00, 0x00, // timestamp accuracy
+        0xff, 0xff, 0x00, 0x00, // snapshot length
+        0xc9, 0x00, 0x00, 0x00, // link type 201
+        0x01, 0x00, 0x00, 0x00, // seconds
+        0x20, 0xa1, 0x07, 0x00, // microseconds
+        0x0b, 0x00, 0x00, 0x00, // included length
+        0x0b, 0x00, 0x00, 0x00, // original length
+        0x00, 0x00, 0x00, 0x01, // pseudo header: received
+        0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00, // Command Complete (HCI_Reset, success)
+    ];
+
+    fn timestamp() -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(1500)
+    }
+
+    async fn read_all(data: &[u8]) -> Result<Vec<Record>> {
+        let mut reader = CaptureReader::new(data).await?;
+        let mut records = Vec::new();
+        while let Some(record) = reader.read_record().await? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    #[tokio::test]
+    async fn read_btsnoop_uart() {
+        let reader = CaptureReader::new(BTSNOOP_UART).await.unwrap();
+        assert_eq!(reader.format(), FileFormat::BtSnoop { datalink: BTSNOOP_FORMAT_UART });
+
+        let records = read_all(BTSNOOP_UART).await.unwrap();
+        assert_eq!(
+            records,
+            vec![
+                Record { index: 0, timestamp: timestamp(), packet: Packet::Command(vec![0x03, 0x0c, 0x00]) },
+                Record {
+                    index: 0,
+                    timestamp: timestamp(),
+                    packet: Packet::Event(vec![0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00])
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_btsnoop_monitor() {
+        let records = read_all(BTSNOOP_MONITOR).await.unwrap();
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    index: 1,
+                    timestamp: timestamp(),
+                    packet: Packet::NewIndex {
+                        controller_type: 0,
+                        bus: 1,
+                        address: Address::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+                        name: "hci1".to_string(),
+                    },
+                },
+                Record {
+                    index: 1,
+                    timestamp: timestamp(),
+                    packet: Packet::Acl(
+                        Direction::Rx,
+                        vec![0x40, 0x20, 0x05, 0x00, 0x01, 0x00, 0x04, 0x00, 0x0a]
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_pcap_h4() {
+        let reader = CaptureReader::new(PCAP_H4).await.unwrap();
+        assert_eq!(
+            reader.format(),
+            FileFormat::Pcap { linktype: LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR, big_endian: false, nanos: false }
+        );
+
+        let records = read_all(PCAP_H4).await.unwrap();
+        assert_eq!(
+            records,
+            vec![Record {
+                index: 0,
+                timestamp: timestamp(),
+                packet: Packet::Event(vec![0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00])
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn btsnoop_roundtrip() {
+        let records = vec![
+            Record {
+                index: INDEX_NONE,
+                timestamp: timestamp(),
+                packet: Packet::SystemNote("Bluetooth subsystem version 2.22".to_string()),
+            },
+            Record {
+                index: 0,
+                timestamp: timestamp() + Duration::from_micros(1),
+                packet: Packet::UserLogging {
+                    priority: 6,
+                    ident: "bluetoothd".to_string(),
+                    message: "Starting".to_string(),
+                },
+            },
+            Record { index: 0, timestamp: timestamp(), packet: Packet::Command(vec![0x03, 0x0c, 0x00]) },
+            Record { index: 0, timestamp: timestamp(), packet: Packet::Iso(Direction::Tx, vec![0x01, 0x00]) },
+        ];
+
+        let mut writer = BtSnoopWriter::new(Vec::new()).await.unwrap();
+        for record in &records {
+            writer.write_record(record).await.unwrap();
+        }
+        let data = writer.into_inner();
+        assert_eq!(&data[..16], &BTSNOOP_MONITOR[..16]);
+
+        assert_eq!(read_all(&data).await.unwrap(), records);
+    }
+
+    #[tokio::test]
+    async fn truncated() {
+        for len in [BTSNOOP_UART.len() - 1, BTSNOOP_UART.len() - 8, 16 + 10] {
+            let err = read_all(&BTSNOOP_UART[..len]).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof, "length {len}");
+        }
+        let err = read_all(&PCAP_H4[..PCAP_H4.len() - 1]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let err = CaptureReader::new(&BTSNOOP_UART[..10]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        // A file without records is valid.
+        assert!(read_all(&BTSNOOP_UART[..16]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn oversized_length() {
+        let mut btsnoop = BTSNOOP_UART.to_vec();
+        btsnoop[20..24].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = read_all(&btsnoop).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut pcap = PCAP_H4.to_vec();
+        pcap[32..36].copy_from_slice(&(MAX_RECORD_LEN as u32 + 1).to_le_bytes());
+        let err = read_all(&pcap).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn invalid_timestamp() {
+        for ts in [i64::MIN, i64::MIN + BTSNOOP_EPOCH_OFFSET - 1] {
+            let mut btsnoop = BTSNOOP_UART.to_vec();
+            btsnoop[32..40].copy_from_slice(&ts.to_be_bytes());
+            let err = read_all(&btsnoop).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "timestamp {ts}");
+        }
+
+        // Timestamps before the Unix epoch are valid.
+        let mut btsnoop = BTSNOOP_UART.to_vec();
+        btsnoop[32..40].copy_from_slice(&(BTSNOOP_EPOCH_OFFSET - 1_000_000).to_be_bytes());
+        let records = read_all(&btsnoop).await.unwrap();
+        assert_eq!(records[0].timestamp, UNIX_EPOCH - Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn invalid_header() {
+        let err = CaptureReader::new(&b"not a capture file"[..]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut btsnoop = BTSNOOP_UART.to_vec();
+        btsnoop[15] = 0xff;
+        let err = CaptureReader::new(&btsnoop[..]).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
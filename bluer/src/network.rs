@@ -0,0 +1,257 @@
+//! Bluetooth personal area networking (PAN).
+//!
+//! Use [Device::network](crate::Device::network) to connect to a network service
+//! provided by a remote device and [Adapter::network_server](crate::Adapter::network_server)
+//! to offer a network service to remote devices.
+//!
+//! Networking is performed by the kernel using the BNEP protocol.
+//! Each connection is represented by a network interface, usually called `bnepX`,
+//! which must be configured by the application or the operating system.
+
+use dbus::{
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{fmt, sync::Arc};
+use strum::{Display, EnumString};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::{Adapter, Address, Device, Result, SessionInner, SERVICE_NAME, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.Network1";
+pub(crate) const SERVER_INTERFACE: &str = "org.bluez.NetworkServer1";
+pub(crate) const SERVER_PREFIX: &str = publish_path!("network_server/");
+
+/// PAN role.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[strum(ascii_case_insensitive)]
+#[non_exhaustive]
+pub enum Role {
+    /// PAN user.
+    #[strum(to_string = "panu", serialize = "00001115-0000-1000-8000-00805f9b34fb")]
+    Panu,
+    /// Network access point.
+    #[strum(to_string = "nap", serialize = "00001116-0000-1000-8000-00805f9b34fb")]
+    Nap,
+    /// Group ad-hoc network.
+    #[strum(to_string = "gn", serialize = "00001117-0000-1000-8000-00805f9b34fb")]
+    Gn,
+}
+
+impl Role {
+    /// Service class UUID of the role.
+    pub const fn uuid(&self) -> Uuid {
+        match self {
+            Self::Panu => Uuid::from_u128(0x00001115_0000_1000_8000_00805f9b34fb),
+            Self::Nap => Uuid::from_u128(0x00001116_0000_1000_8000_00805f9b34fb),
+            Self::Gn => Uuid::from_u128(0x00001117_0000_1000_8000_00805f9b34fb),
+        }
+    }
+
+    /// Role for the specified service class UUID.
+    pub fn from_uuid(uuid: Uuid) -> Option<Self> {
+        [Self::Panu, Self::Nap, Self::Gn].into_iter().find(|role| role.uuid() == uuid)
+    }
+}
+
+/// Interface to the network service of a remote Bluetooth device.
+///
+/// Network property changes are delivered as
+/// [DeviceEvent::NetworkPropertyChanged](crate::DeviceEvent::NetworkPropertyChanged)
+/// by [Device::events](crate::Device::events).
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Clone)]
+pub struct Network {
+    inner: Arc<SessionInner>,
+    dbus_path: Path<'static>,
+    adapter_name: Arc<String>,
+    address: Address,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Network {{ adapter_name: {}, address: {} }}", self.adapter_name(), self.address())
+    }
+}
+
+impl Network {
+    pub(crate) fn new(inner: Arc<SessionInner>, adapter_name: Arc<String>, address: Address) -> Result<Self> {
+        Ok(Self { inner, dbus_path: Device::dbus_path(&adapter_name, address)?, adapter_name, address })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// The Bluetooth adapter name.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// The Bluetooth device address of the remote device.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Connect to the network service of the remote device using the specified role
+    /// of the remote device.
+    ///
+    /// After a successful connection the name of the created network interface,
+    /// for example `bnep0`, is returned.
+    ///
+    /// Possible errors: [ErrorKind::AlreadyConnected](crate::ErrorKind::AlreadyConnected),
+    /// [ErrorKind::ConnectionAttemptFailed](crate::ErrorKind::ConnectionAttemptFailed),
+    /// [ErrorKind::NotSupported](crate::ErrorKind::NotSupported),
+    /// [ErrorKind::InProgress](crate::ErrorKind::InProgress),
+    /// [ErrorKind::Failed](crate::ErrorKind::Failed).
+    pub async fn connect(&self, role: Role) -> Result<String> {
+        let (interface,): (String,) = self.call_method("Connect", (role.to_string(),)).await?;
+        Ok(interface)
+    }
+
+    /// Disconnect from the network service.
+    ///
+    /// The network interface is removed by the kernel.
+    ///
+    /// Possible errors: [ErrorKind::Failed](crate::ErrorKind::Failed).
+    pub async fn disconnect(&self) -> Result<()> {
+        self.call_method("Disconnect", ()).await
+    }
+}
+
+define_properties!(
+    Network,
+    /// Bluetooth network property.
+    pub NetworkProperty => {
+        /// Indicates if the device is connected.
+        property(
+            Connected, bool,
+            dbus: (INTERFACE, "Connected", bool, OPTIONAL),
+            get: (is_connected, v => {v.to_owned()}),
+        );
+
+        /// Indicates the network interface name when available.
+        property(
+            Interface, String,
+            dbus: (INTERFACE, "Interface", String, OPTIONAL),
+            get: (interface, v => {v.to_owned()}),
+        );
+
+        /// Indicates the connection role when available.
+        property(
+            Role, Role,
+            dbus: (INTERFACE, "UUID", String, OPTIONAL),
+            get: (role, v => {v.parse()?}),
+        );
+    }
+);
+
+/// Interface to the network server of a local Bluetooth adapter.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[derive(Clone)]
+pub struct NetworkServer {
+    inner: Arc<SessionInner>,
+    adapter_name: Arc<String>,
+}
+
+impl fmt::Debug for NetworkServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkServer {{ adapter_name: {} }}", self.adapter_name())
+    }
+}
+
+impl NetworkServer {
+    pub(crate) fn new(inner: Arc<SessionInner>, adapter_name: Arc<String>) -> Self {
+        Self { inner, adapter_name }
+    }
+
+    /// The Bluetooth adapter name.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Register a network server for the specified role.
+    ///
+    /// All new BNEP connections for the role are added to the specified
+    /// bridge network interface, which must already exist.
+    /// Only one server can be registered for each role.
+    ///
+    /// Drop the returned [NetworkServerHandle] to unregister the server.
+    ///
+    /// Possible errors: [ErrorKind::AlreadyExists](crate::ErrorKind::AlreadyExists),
+    /// [ErrorKind::Failed](crate::ErrorKind::Failed).
+    pub async fn register(&self, role: Role, bridge: &str) -> Result<NetworkServerHandle> {
+        let name = Path::new(format!("{}{}", SERVER_PREFIX, Uuid::new_v4().as_simple())).unwrap();
+        let adapter_path = Adapter::dbus_path(&self.adapter_name)?;
+        log::trace!("Registering network server for role {} with bridge {} on {}", role, bridge, &adapter_path);
+
+        let reg_path = adapter_path.clone();
+        let reg_bridge = bridge.to_string();
+        self.inner
+            .register_with_daemon(&name, Some(&self.adapter_name), move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, reg_path.clone(), TIMEOUT, connection);
+                let bridge = reg_bridge.clone();
+                async move {
+                    let () = proxy.method_call(SERVER_INTERFACE, "Register", (role.to_string(), bridge)).await?;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let (drop_tx, drop_rx) = oneshot::channel();
+        let inner = self.inner.clone();
+        let unreg_name = name.clone();
+        tokio::spawn(async move {
+            let _ = drop_rx.await;
+
+            log::trace!("Unregistering network server for role {} on {}", role, &adapter_path);
+            inner.unregister_with_daemon(&unreg_name).await;
+            let proxy = Proxy::new(SERVICE_NAME, adapter_path, TIMEOUT, inner.connection.clone());
+            let _: std::result::Result<(), dbus::Error> =
+                proxy.method_call(SERVER_INTERFACE, "Unregister", (role.to_string(),)).await;
+        });
+
+        Ok(NetworkServerHandle { role, bridge: bridge.to_string(), _drop_tx: drop_tx })
+    }
+}
+
+/// Handle to registered network server.
+///
+/// Drop to unregister network server.
+#[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
+#[must_use = "NetworkServerHandle must be held for network server to be registered"]
+pub struct NetworkServerHandle {
+    role: Role,
+    bridge: String,
+    _drop_tx: oneshot::Sender<()>,
+}
+
+impl NetworkServerHandle {
+    /// Role of the network server.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Name of the bridge network interface.
+    pub fn bridge(&self) -> &str {
+        &self.bridge
+    }
+}
+
+impl Drop for NetworkServerHandle {
+    fn drop(&mut self) {
+        // required for drop order
+    }
+}
+
+impl fmt::Debug for NetworkServerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NetworkServerHandle {{ role: {}, bridge: {} }}", self.role, &self.bridge)
+    }
+}
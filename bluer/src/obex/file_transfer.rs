@@ -0,0 +1,142 @@
+//! File transfer profile (FTP).
+
+use dbus::{
+    arg::{prop_cast, PropMap},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{fmt, path::Path as FsPath, sync::Arc};
+use strum::{Display, EnumString};
+
+use super::{path_arg, ObexInner, Transfer, SERVICE_NAME};
+use crate::{read_dict, Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.FileTransfer1";
+
+/// Kind of folder entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FolderEntryKind {
+    /// File.
+    #[strum(serialize = "file")]
+    File,
+    /// Folder.
+    #[strum(serialize = "folder")]
+    Folder,
+}
+
+/// Entry of a folder on the remote device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct FolderEntry {
+    /// Name.
+    pub name: String,
+    /// Kind of entry.
+    pub kind: FolderEntryKind,
+    /// Size in bytes.
+    pub size: Option<u64>,
+    /// Modification time, for example `20230102T030405Z`.
+    pub modified: Option<String>,
+    /// Creation time.
+    pub created: Option<String>,
+    /// Last access time.
+    pub accessed: Option<String>,
+}
+
+impl FolderEntry {
+    fn from_dict(dict: &PropMap) -> Result<Self> {
+        let string = |key: &str| prop_cast::<String>(dict, key).cloned();
+        Ok(Self {
+            name: read_dict::<String>(dict, "Name")?.clone(),
+            kind: read_dict::<String>(dict, "Type")?.parse()?,
+            size: prop_cast::<u64>(dict, "Size").cloned(),
+            modified: string("Modified"),
+            created: string("Created"),
+            accessed: string("Accessed"),
+        })
+    }
+}
+
+/// File transfer interface of an OBEX session.
+///
+/// Use [Session::file_transfer](super::Session::file_transfer) to obtain this.
+///
+/// Paths on the remote device are relative to the current folder.
+#[derive(Clone)]
+pub struct FileTransfer {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for FileTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileTransfer {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl FileTransfer {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    fn transfer(&self, (path, _props): (Path<'static>, PropMap)) -> Transfer {
+        Transfer::new(self.inner.clone(), path)
+    }
+
+    /// Changes the current folder of the remote device.
+    ///
+    /// Use `..` to change to the parent folder.
+    pub async fn change_folder(&self, folder: &str) -> Result<()> {
+        self.call_method("ChangeFolder", (folder,)).await
+    }
+
+    /// Creates a new folder on the remote device and changes into it.
+    pub async fn create_folder(&self, folder: &str) -> Result<()> {
+        self.call_method("CreateFolder", (folder,)).await
+    }
+
+    /// Lists the contents of the current folder.
+    pub async fn list_folder(&self) -> Result<Vec<FolderEntry>> {
+        let (entries,): (Vec<PropMap>,) = self.call_method("ListFolder", ()).await?;
+        entries.iter().map(FolderEntry::from_dict).collect()
+    }
+
+    /// Copies the remote file `source` to the local file `target`.
+    ///
+    /// If `target` is empty a temporary file is created by the OBEX daemon,
+    /// whose name is available from [Transfer::filename].
+    pub async fn get_file(&self, target: &FsPath, source: &str) -> Result<Transfer> {
+        let reply = self.call_method("GetFile", (path_arg(target)?, source)).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Copies the local file `source` to the remote file `target`.
+    pub async fn put_file(&self, source: &FsPath, target: &str) -> Result<Transfer> {
+        let reply = self.call_method("PutFile", (path_arg(source)?, target)).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Copies a file within the remote device.
+    pub async fn copy_file(&self, source: &str, target: &str) -> Result<()> {
+        self.call_method("CopyFile", (source, target)).await
+    }
+
+    /// Moves a file within the remote device.
+    pub async fn move_file(&self, source: &str, target: &str) -> Result<()> {
+        self.call_method("MoveFile", (source, target)).await
+    }
+
+    /// Deletes the specified file or folder on the remote device.
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        self.call_method("Delete", (name,)).await
+    }
+}
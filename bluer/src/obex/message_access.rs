@@ -0,0 +1,358 @@
+//! Message access profile (MAP).
+
+use dbus::{
+    arg::{prop_cast, PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{collections::HashMap, fmt, path::Path as FsPath, sync::Arc};
+
+use super::{path_arg, ObexInner, Transfer, SERVICE_NAME};
+use crate::{Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.MessageAccess1";
+pub(crate) const MESSAGE_INTERFACE: &str = "org.bluez.obex.Message1";
+
+/// Filter for listing folders.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FolderFilter {
+    /// Index of the first folder.
+    pub offset: Option<u16>,
+    /// Maximum number of folders.
+    pub max_count: Option<u16>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl FolderFilter {
+    fn to_dict(&self) -> PropMap {
+        let mut pm = PropMap::new();
+        if let Some(offset) = &self.offset {
+            pm.insert("Offset".to_string(), Variant(offset.box_clone()));
+        }
+        if let Some(max_count) = &self.max_count {
+            pm.insert("MaxCount".to_string(), Variant(max_count.box_clone()));
+        }
+        pm
+    }
+}
+
+/// Filter for listing messages.
+///
+/// Fields set to [None] do not restrict the listing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MessageFilter {
+    /// Index of the first message.
+    pub offset: Option<u16>,
+    /// Maximum number of messages.
+    pub max_count: Option<u16>,
+    /// Maximum length of the subject.
+    pub subject_length: Option<u8>,
+    /// Message fields to include.
+    ///
+    /// Use [MessageAccess::filter_fields] to obtain the supported fields.
+    pub fields: Option<Vec<String>>,
+    /// Message types to include, for example `sms` or `email`.
+    pub types: Option<Vec<String>>,
+    /// Only include messages after this time, for example `20230102T030405`.
+    pub period_begin: Option<String>,
+    /// Only include messages before this time.
+    pub period_end: Option<String>,
+    /// Only include messages that have or have not been read.
+    pub read: Option<bool>,
+    /// Only include messages with matching recipient.
+    pub recipient: Option<String>,
+    /// Only include messages with matching sender.
+    pub sender: Option<String>,
+    /// Only include messages with or without high priority.
+    pub priority: Option<bool>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MessageFilter {
+    fn to_dict(&self) -> PropMap {
+        let mut pm = PropMap::new();
+        let mut put = |key: &str, value: Box<dyn RefArg>| {
+            pm.insert(key.to_string(), Variant(value));
+        };
+        if let Some(offset) = &self.offset {
+            put("Offset", offset.box_clone());
+        }
+        if let Some(max_count) = &self.max_count {
+            put("MaxCount", max_count.box_clone());
+        }
+        if let Some(subject_length) = &self.subject_length {
+            put("SubjectLength", subject_length.box_clone());
+        }
+        if let Some(fields) = &self.fields {
+            put("Fields", fields.box_clone());
+        }
+        if let Some(types) = &self.types {
+            put("Types", types.box_clone());
+        }
+        if let Some(period_begin) = &self.period_begin {
+            put("PeriodBegin", period_begin.box_clone());
+        }
+        if let Some(period_end) = &self.period_end {
+            put("PeriodEnd", period_end.box_clone());
+        }
+        if let Some(read) = &self.read {
+            put("Read", read.box_clone());
+        }
+        if let Some(recipient) = &self.recipient {
+            put("Recipient", recipient.box_clone());
+        }
+        if let Some(sender) = &self.sender {
+            put("Sender", sender.box_clone());
+        }
+        if let Some(priority) = &self.priority {
+            put("Priority", priority.box_clone());
+        }
+        pm
+    }
+}
+
+/// Message access interface of an OBEX session.
+///
+/// Use [Session::message_access](super::Session::message_access) to obtain this.
+#[derive(Clone)]
+pub struct MessageAccess {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for MessageAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MessageAccess {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl MessageAccess {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// Changes the current folder.
+    ///
+    /// Use `..` to change to the parent folder and an empty string for the root folder.
+    pub async fn set_folder(&self, name: &str) -> Result<()> {
+        self.call_method("SetFolder", (name,)).await
+    }
+
+    /// Lists the names of the subfolders of the current folder.
+    pub async fn list_folders(&self, filter: &FolderFilter) -> Result<Vec<String>> {
+        let (folders,): (Vec<PropMap>,) = self.call_method("ListFolders", (filter.to_dict(),)).await?;
+        Ok(folders.iter().filter_map(|folder| prop_cast::<String>(folder, "Name").cloned()).collect())
+    }
+
+    /// Message fields supported by the [fields filter](MessageFilter::fields).
+    pub async fn filter_fields(&self) -> Result<Vec<String>> {
+        let (fields,): (Vec<String>,) = self.call_method("ListFilterFields", ()).await?;
+        Ok(fields)
+    }
+
+    /// Lists the messages in the specified subfolder of the current folder.
+    ///
+    /// Use an empty string to list the messages of the current folder.
+    pub async fn list_messages(&self, folder: &str, filter: &MessageFilter) -> Result<Vec<Message>> {
+        let (messages,): (HashMap<Path<'static>, PropMap>,) =
+            self.call_method("ListMessages", (folder, filter.to_dict())).await?;
+        let mut messages: Vec<_> =
+            messages.into_keys().map(|path| Message { inner: self.inner.clone(), dbus_path: path }).collect();
+        messages.sort_by(|a, b| a.dbus_path.cmp(&b.dbus_path));
+        Ok(messages)
+    }
+
+    /// Requests the remote device to check for new messages.
+    pub async fn update_inbox(&self) -> Result<()> {
+        self.call_method("UpdateInbox", ()).await
+    }
+
+    /// Sends the message in the local file `source` using the specified folder
+    /// of the remote device.
+    ///
+    /// The file must be in bMessage format.
+    pub async fn push_message(&self, source: &FsPath, folder: &str) -> Result<Transfer> {
+        let (path, _props): (Path<'static>, PropMap) =
+            self.call_method("PushMessage", (path_arg(source)?, folder, PropMap::new())).await?;
+        Ok(Transfer::new(self.inner.clone(), path))
+    }
+}
+
+define_properties!(
+    MessageAccess,
+    /// Message access property.
+    pub MessageAccessProperty => {
+        /// Message types supported by the remote device.
+        property(
+            SupportedTypes, Vec<String>,
+            dbus: (INTERFACE, "SupportedTypes", Vec<String>, OPTIONAL),
+            get: (supported_types, v => {v.to_owned()}),
+        );
+    }
+);
+
+/// Message stored on the remote device.
+///
+/// Use [MessageAccess::list_messages] to obtain messages.
+#[derive(Clone)]
+pub struct Message {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Message {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Message {
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(MESSAGE_INTERFACE);
+
+    /// D-Bus object path of the message.
+    pub fn path(&self) -> &str {
+        &self.dbus_path
+    }
+
+    /// Downloads the message into the local file `target`.
+    ///
+    /// If `attachment` is true, attachments are included.
+    /// If `target` is empty a temporary file is created by the OBEX daemon,
+    /// whose name is available from [Transfer::filename].
+    pub async fn get(&self, target: &FsPath, attachment: bool) -> Result<Transfer> {
+        let (path, _props): (Path<'static>, PropMap) =
+            self.call_method("Get", (path_arg(target)?, attachment)).await?;
+        Ok(Transfer::new(self.inner.clone(), path))
+    }
+
+    /// Sets the deleted status of the message.
+    pub async fn set_deleted(&self, deleted: bool) -> Result<()> {
+        self.set_property("Deleted", deleted).await
+    }
+}
+
+define_properties!(
+    Message,
+    /// Message property.
+    pub MessageProperty => {
+        /// Folder which the message belongs to.
+        property(
+            Folder, String,
+            dbus: (MESSAGE_INTERFACE, "Folder", String, MANDATORY),
+            get: (folder, v => {v.to_owned()}),
+        );
+
+        /// Subject.
+        property(
+            Subject, String,
+            dbus: (MESSAGE_INTERFACE, "Subject", String, OPTIONAL),
+            get: (subject, v => {v.to_owned()}),
+        );
+
+        /// Time, for example `20230102T030405`.
+        property(
+            Timestamp, String,
+            dbus: (MESSAGE_INTERFACE, "Timestamp", String, OPTIONAL),
+            get: (timestamp, v => {v.to_owned()}),
+        );
+
+        /// Name of the sender.
+        property(
+            Sender, String,
+            dbus: (MESSAGE_INTERFACE, "Sender", String, OPTIONAL),
+            get: (sender, v => {v.to_owned()}),
+        );
+
+        /// Address of the sender, for example a phone number or e-mail address.
+        property(
+            SenderAddress, String,
+            dbus: (MESSAGE_INTERFACE, "SenderAddress", String, OPTIONAL),
+            get: (sender_address, v => {v.to_owned()}),
+        );
+
+        /// Reply-to address.
+        property(
+            ReplyTo, String,
+            dbus: (MESSAGE_INTERFACE, "ReplyTo", String, OPTIONAL),
+            get: (reply_to, v => {v.to_owned()}),
+        );
+
+        /// Name of the recipient.
+        property(
+            Recipient, String,
+            dbus: (MESSAGE_INTERFACE, "Recipient", String, OPTIONAL),
+            get: (recipient, v => {v.to_owned()}),
+        );
+
+        /// Address of the recipient.
+        property(
+            RecipientAddress, String,
+            dbus: (MESSAGE_INTERFACE, "RecipientAddress", String, OPTIONAL),
+            get: (recipient_address, v => {v.to_owned()}),
+        );
+
+        /// Message type, for example `sms-gsm` or `email`.
+        property(
+            Type, String,
+            dbus: (MESSAGE_INTERFACE, "Type", String, OPTIONAL),
+            get: (message_type, v => {v.to_owned()}),
+        );
+
+        /// Size in bytes.
+        property(
+            Size, u64,
+            dbus: (MESSAGE_INTERFACE, "Size", u64, OPTIONAL),
+            get: (size, v => {*v}),
+        );
+
+        /// Reception status, for example `complete`.
+        property(
+            Status, String,
+            dbus: (MESSAGE_INTERFACE, "Status", String, OPTIONAL),
+            get: (status, v => {v.to_owned()}),
+        );
+
+        /// Whether the message has high priority.
+        property(
+            Priority, bool,
+            dbus: (MESSAGE_INTERFACE, "Priority", bool, OPTIONAL),
+            get: (is_priority, v => {*v}),
+        );
+
+        /// Whether the message has been read.
+        property(
+            Read, bool,
+            dbus: (MESSAGE_INTERFACE, "Read", bool, OPTIONAL),
+            get: (is_read, v => {*v}),
+            set: (set_read, v => {v}),
+        );
+
+        /// Whether the message has been sent.
+        property(
+            Sent, bool,
+            dbus: (MESSAGE_INTERFACE, "Sent", bool, OPTIONAL),
+            get: (is_sent, v => {*v}),
+        );
+
+        /// Whether the message is DRM protected.
+        property(
+            Protected, bool,
+            dbus: (MESSAGE_INTERFACE, "Protected", bool, OPTIONAL),
+            get: (is_protected, v => {*v}),
+        );
+    }
+);
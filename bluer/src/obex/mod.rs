@@ -0,0 +1,170 @@
+//! Object exchange (OBEX) client.
+//!
+//! This talks to the OBEX daemon `obexd` that is part of BlueZ.
+//! Unlike the Bluetooth daemon, `obexd` is reachable on the D-Bus session bus
+//! of the logged in user, thus a separate [Client] is used instead of a
+//! [Session](crate::Session).
+//!
+//! Use [Client::create_session] to connect to a remote device for a specific
+//! [target profile](Target).
+//! The [session](Session) then provides access to
+//!
+//!   * [object push (OPP)](object_push::ObjectPush),
+//!   * [file transfer (FTP)](file_transfer::FileTransfer),
+//!   * [phonebook access (PBAP)](phonebook_access::PhonebookAccess) and
+//!   * [message access (MAP)](message_access::MessageAccess).
+//!
+//! Sending and receiving objects is performed in the background by `obexd`.
+//! Each operation returns a [Transfer] that provides the transfer status
+//! as a stream of [progress updates](Transfer::progress).
+
+use dbus::nonblock::{Proxy, SyncConnection};
+use dbus_tokio::connection;
+use futures::channel::mpsc;
+use std::{fmt, sync::Arc};
+use tokio::task::{spawn_blocking, JoinHandle};
+
+use crate::{Error, ErrorKind, Event, Result, SubscriptionReq, TIMEOUT};
+
+pub mod file_transfer;
+pub mod message_access;
+pub mod object_push;
+pub mod phonebook_access;
+mod session;
+mod transfer;
+
+pub use session::*;
+pub use transfer::*;
+
+pub(crate) const SERVICE_NAME: &str = "org.bluez.obex";
+pub(crate) const PATH: &str = "/org/bluez/obex";
+pub(crate) const CLIENT_INTERFACE: &str = "org.bluez.obex.Client1";
+
+/// Shared state of all objects of an OBEX client.
+pub(crate) struct ObexInner {
+    pub connection: Arc<SyncConnection>,
+    pub event_sub_tx: mpsc::Sender<SubscriptionReq>,
+    dbus_task: Option<JoinHandle<connection::IOResourceError>>,
+}
+
+impl ObexInner {
+    pub async fn events(
+        &self, path: dbus::Path<'static>, child_objects: bool,
+    ) -> Result<mpsc::UnboundedReceiver<Event>> {
+        Event::subscribe(&mut self.event_sub_tx.clone(), path, child_objects).await
+    }
+
+    /// Objects of the OBEX daemon are not cached, thus this always returns [None].
+    pub fn cached_property<R>(&self, _path: &dbus::Path, _interface: &str, _name: &str) -> Option<Option<R>> {
+        None
+    }
+
+    /// Objects of the OBEX daemon are not cached, thus this does nothing.
+    pub fn invalidate_cached_property(&self, _path: &dbus::Path, _interface: &str, _name: &str) {}
+}
+
+impl Drop for ObexInner {
+    fn drop(&mut self) {
+        // documentation for dbus_tokio::connection::IOResource indicates it is abortable
+        if let Some(dbus_task) = &self.dbus_task {
+            dbus_task.abort();
+        }
+    }
+}
+
+/// OBEX client.
+///
+/// Encapsulates a connection to the OBEX daemon `obexd` on the D-Bus session bus.
+///
+/// Sessions created by a client are closed by the OBEX daemon when the client is dropped.
+#[derive(Clone)]
+pub struct Client {
+    inner: Arc<ObexInner>,
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Client {{ {} }}", self.inner.connection.unique_name())
+    }
+}
+
+impl Client {
+    /// Create a new OBEX client.
+    ///
+    /// This establishes a connection to the OBEX daemon over the D-Bus session bus.
+    pub async fn new() -> Result<Self> {
+        let (resource, connection) = spawn_blocking(connection::new_session_sync).await??;
+        let dbus_task = tokio::spawn(resource);
+        log::trace!("Connected to D-Bus session bus with unique name {}", &connection.unique_name());
+        Self::from_connection(connection, Some(dbus_task)).await
+    }
+
+    /// Create a new OBEX client using the specified D-Bus connection.
+    ///
+    /// The caller is responsible for driving the connection, usually by spawning the
+    /// `IOResource` returned by [dbus_tokio::connection] when the connection was established.
+    ///
+    /// Signal match mode is enabled on the connection, so that signals are delivered
+    /// to all matching handlers.
+    pub async fn with_connection(connection: Arc<SyncConnection>) -> Result<Self> {
+        connection.set_signal_match_mode(true);
+        Self::from_connection(connection, None).await
+    }
+
+    /// Create a new OBEX client connected to the D-Bus message bus at the specified address.
+    ///
+    /// The address is specified in D-Bus format, for example `unix:path=/run/user/1000/bus`.
+    /// This is useful when the session bus of another user or a private message bus is used.
+    pub async fn with_address(address: &str) -> Result<Self> {
+        let (connection, dbus_task) = crate::session::connect(address).await?;
+        log::trace!("Connected to D-Bus at {} with unique name {}", address, &connection.unique_name());
+        Self::from_connection(connection, Some(dbus_task)).await
+    }
+
+    async fn from_connection(
+        connection: Arc<SyncConnection>, dbus_task: Option<JoinHandle<connection::IOResourceError>>,
+    ) -> Result<Self> {
+        let (event_sub_tx, event_sub_rx) = mpsc::channel(1);
+        Event::handle_connection(connection.clone(), SERVICE_NAME, event_sub_rx, Default::default()).await?;
+        Ok(Self { inner: Arc::new(ObexInner { connection, event_sub_tx, dbus_task }) })
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, PATH, TIMEOUT, &*self.inner.connection)
+    }
+
+    /// Creates a new OBEX session to the specified remote device for the target profile.
+    ///
+    /// The Bluetooth connection is established by the OBEX daemon.
+    pub async fn create_session(&self, options: SessionOptions) -> Result<Session> {
+        let args = options.to_dict();
+        log::trace!("{}: {}.CreateSession ({}, {:?})", PATH, CLIENT_INTERFACE, options.destination, &args);
+        let (path,): (dbus::Path<'static>,) = self
+            .proxy()
+            .method_call(CLIENT_INTERFACE, "CreateSession", (options.destination.to_string(), args))
+            .await?;
+        log::trace!("{}: {}.CreateSession (...) -> {}", PATH, CLIENT_INTERFACE, &path);
+        Ok(Session::new(self.inner.clone(), path))
+    }
+
+    /// Closes the OBEX session.
+    ///
+    /// Transfers that are still in progress are aborted.
+    pub async fn remove_session(&self, session: &Session) -> Result<()> {
+        log::trace!("{}: {}.RemoveSession ({})", PATH, CLIENT_INTERFACE, session.path());
+        let () =
+            self.proxy().method_call(CLIENT_INTERFACE, "RemoveSession", (session.dbus_path.clone(),)).await?;
+        Ok(())
+    }
+}
+
+/// Converts a local file path into a D-Bus argument.
+pub(crate) fn path_arg(path: &std::path::Path) -> Result<String> {
+    match path.to_str() {
+        Some(path) => Ok(path.to_string()),
+        None => Err(Error {
+            kind: ErrorKind::InvalidArguments,
+            message: format!("path {} is not valid UTF-8", path.display()),
+        }),
+    }
+}
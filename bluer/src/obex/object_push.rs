@@ -0,0 +1,69 @@
+//! Object push profile (OPP).
+
+use dbus::{
+    arg::PropMap,
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{fmt, path::Path as FsPath, sync::Arc};
+
+use super::{path_arg, ObexInner, Transfer, SERVICE_NAME};
+use crate::{Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.ObjectPush1";
+
+/// Object push interface of an OBEX session.
+///
+/// Use [Session::object_push](super::Session::object_push) to obtain this.
+#[derive(Clone)]
+pub struct ObjectPush {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for ObjectPush {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObjectPush {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl ObjectPush {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    fn transfer(&self, (path, _props): (Path<'static>, PropMap)) -> Transfer {
+        Transfer::new(self.inner.clone(), path)
+    }
+
+    /// Sends the local file to the remote device.
+    ///
+    /// The returned transfer is used to monitor the progress.
+    pub async fn send_file(&self, source: &FsPath) -> Result<Transfer> {
+        let reply = self.call_method("SendFile", (path_arg(source)?,)).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Requests the business card of the remote device and stores it in the local file.
+    ///
+    /// If `target` is empty a temporary file is created by the OBEX daemon,
+    /// whose name is available from [Transfer::filename].
+    pub async fn pull_business_card(&self, target: &FsPath) -> Result<Transfer> {
+        let reply = self.call_method("PullBusinessCard", (path_arg(target)?,)).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Pushes the local business card `client` to the remote device and
+    /// stores the business card of the remote device in the local file `target`.
+    pub async fn exchange_business_cards(&self, client: &FsPath, target: &FsPath) -> Result<Transfer> {
+        let reply = self.call_method("ExchangeBusinessCards", (path_arg(client)?, path_arg(target)?)).await?;
+        Ok(self.transfer(reply))
+    }
+}
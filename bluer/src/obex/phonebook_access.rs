@@ -0,0 +1,287 @@
+//! Phonebook access profile (PBAP).
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{fmt, path::Path as FsPath, sync::Arc};
+use strum::{Display, EnumString};
+
+use super::{path_arg, ObexInner, Transfer, SERVICE_NAME};
+use crate::{Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.PhonebookAccess1";
+
+/// Storage location of a phonebook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Location {
+    /// Internal phone memory.
+    Internal,
+    /// SIM card with the specified number, starting at 1.
+    Sim(u8),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Internal => write!(f, "int"),
+            Self::Sim(n) => write!(f, "sim{n}"),
+        }
+    }
+}
+
+/// Phonebook object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Phonebook {
+    /// Main phonebook.
+    #[strum(serialize = "pb")]
+    Main,
+    /// Incoming call history.
+    #[strum(serialize = "ich")]
+    IncomingCalls,
+    /// Outgoing call history.
+    #[strum(serialize = "och")]
+    OutgoingCalls,
+    /// Missed call history.
+    #[strum(serialize = "mch")]
+    MissedCalls,
+    /// Combined call history.
+    #[strum(serialize = "cch")]
+    CombinedCalls,
+    /// Speed dial.
+    #[strum(serialize = "spd")]
+    SpeedDial,
+    /// Favorites.
+    #[strum(serialize = "fav")]
+    Favorites,
+}
+
+/// vCard format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum VcardFormat {
+    /// vCard 2.1.
+    #[strum(serialize = "vcard21")]
+    Vcard21,
+    /// vCard 3.0.
+    #[strum(serialize = "vcard30")]
+    Vcard30,
+}
+
+/// Sort order of vCard listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Order {
+    /// By index.
+    #[strum(serialize = "indexed")]
+    Indexed,
+    /// Alphabetically by name.
+    #[strum(serialize = "alphanumeric")]
+    Alphanumeric,
+    /// By sound of name.
+    #[strum(serialize = "phonetic")]
+    Phonetic,
+}
+
+/// Field used for searching a phonebook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum SearchField {
+    /// Name.
+    #[strum(serialize = "name")]
+    Name,
+    /// Phone number.
+    #[strum(serialize = "number")]
+    Number,
+    /// Sound of name.
+    #[strum(serialize = "sound")]
+    Sound,
+}
+
+/// Filter for phonebook operations.
+///
+/// Fields set to [None] use the default of the remote device.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PhonebookFilter {
+    /// vCard format.
+    pub format: Option<VcardFormat>,
+    /// Sort order.
+    pub order: Option<Order>,
+    /// Index of the first entry.
+    pub offset: Option<u16>,
+    /// Maximum number of entries.
+    pub max_count: Option<u16>,
+    /// vCard fields to include.
+    ///
+    /// Use [PhonebookAccess::filter_fields] to obtain the supported fields.
+    pub fields: Option<Vec<String>>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl PhonebookFilter {
+    fn to_dict(&self) -> PropMap {
+        let mut pm = PropMap::new();
+        if let Some(format) = &self.format {
+            pm.insert("Format".to_string(), Variant(format.to_string().box_clone()));
+        }
+        if let Some(order) = &self.order {
+            pm.insert("Order".to_string(), Variant(order.to_string().box_clone()));
+        }
+        if let Some(offset) = &self.offset {
+            pm.insert("Offset".to_string(), Variant(offset.box_clone()));
+        }
+        if let Some(max_count) = &self.max_count {
+            pm.insert("MaxCount".to_string(), Variant(max_count.box_clone()));
+        }
+        if let Some(fields) = &self.fields {
+            pm.insert("Fields".to_string(), Variant(fields.box_clone()));
+        }
+        pm
+    }
+}
+
+/// Entry of a vCard listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VcardEntry {
+    /// vCard handle, for example `1.vcf`.
+    pub handle: String,
+    /// Name of the contact.
+    pub name: String,
+}
+
+/// Phonebook access interface of an OBEX session.
+///
+/// Use [Session::phonebook_access](super::Session::phonebook_access) to obtain this.
+///
+/// A phonebook must be [selected](Self::select) before any other operation.
+#[derive(Clone)]
+pub struct PhonebookAccess {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for PhonebookAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PhonebookAccess {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl PhonebookAccess {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    fn transfer(&self, (path, _props): (Path<'static>, PropMap)) -> Transfer {
+        Transfer::new(self.inner.clone(), path)
+    }
+
+    /// Selects the phonebook object for other operations.
+    pub async fn select(&self, location: Location, phonebook: Phonebook) -> Result<()> {
+        self.call_method("Select", (location.to_string(), phonebook.to_string())).await
+    }
+
+    /// Downloads the entire selected phonebook into the local file `target`.
+    ///
+    /// If `target` is empty a temporary file is created by the OBEX daemon,
+    /// whose name is available from [Transfer::filename].
+    pub async fn pull_all(&self, target: &FsPath, filter: &PhonebookFilter) -> Result<Transfer> {
+        let reply = self.call_method("PullAll", (path_arg(target)?, filter.to_dict())).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Lists the entries of the selected phonebook.
+    pub async fn list(&self, filter: &PhonebookFilter) -> Result<Vec<VcardEntry>> {
+        let (entries,): (Vec<(String, String)>,) = self.call_method("List", (filter.to_dict(),)).await?;
+        Ok(entries.into_iter().map(|(handle, name)| VcardEntry { handle, name }).collect())
+    }
+
+    /// Downloads the vCard with the specified handle into the local file `target`.
+    pub async fn pull(&self, handle: &str, target: &FsPath, filter: &PhonebookFilter) -> Result<Transfer> {
+        let reply = self.call_method("Pull", (handle, path_arg(target)?, filter.to_dict())).await?;
+        Ok(self.transfer(reply))
+    }
+
+    /// Searches the selected phonebook for entries whose field matches the value.
+    pub async fn search(
+        &self, field: SearchField, value: &str, filter: &PhonebookFilter,
+    ) -> Result<Vec<VcardEntry>> {
+        let (entries,): (Vec<(String, String)>,) =
+            self.call_method("Search", (field.to_string(), value, filter.to_dict())).await?;
+        Ok(entries.into_iter().map(|(handle, name)| VcardEntry { handle, name }).collect())
+    }
+
+    /// Number of entries in the selected phonebook.
+    pub async fn size(&self) -> Result<u16> {
+        let (size,): (u16,) = self.call_method("GetSize", ()).await?;
+        Ok(size)
+    }
+
+    /// Requests the remote device to update the version counters of the phonebook.
+    pub async fn update_version(&self) -> Result<()> {
+        self.call_method("UpdateVersion", ()).await
+    }
+
+    /// vCard fields supported by the [fields filter](PhonebookFilter::fields).
+    pub async fn filter_fields(&self) -> Result<Vec<String>> {
+        let (fields,): (Vec<String>,) = self.call_method("ListFilterFields", ()).await?;
+        Ok(fields)
+    }
+}
+
+define_properties!(
+    PhonebookAccess,
+    /// Phonebook access property.
+    pub PhonebookAccessProperty => {
+        /// Current folder.
+        property(
+            Folder, String,
+            dbus: (INTERFACE, "Folder", String, MANDATORY),
+            get: (folder, v => {v.to_owned()}),
+        );
+
+        /// Identifier of the phonebook database.
+        property(
+            DatabaseIdentifier, String,
+            dbus: (INTERFACE, "DatabaseIdentifier", String, OPTIONAL),
+            get: (database_identifier, v => {v.to_owned()}),
+        );
+
+        /// Primary version counter of the selected phonebook.
+        property(
+            PrimaryCounter, String,
+            dbus: (INTERFACE, "PrimaryCounter", String, OPTIONAL),
+            get: (primary_counter, v => {v.to_owned()}),
+        );
+
+        /// Secondary version counter of the selected phonebook.
+        property(
+            SecondaryCounter, String,
+            dbus: (INTERFACE, "SecondaryCounter", String, OPTIONAL),
+            get: (secondary_counter, v => {v.to_owned()}),
+        );
+
+        /// Whether the remote device only supports images of fixed size.
+        property(
+            FixedImageSize, bool,
+            dbus: (INTERFACE, "FixedImageSize", bool, OPTIONAL),
+            get: (fixed_image_size, v => {*v}),
+        );
+    }
+);
@@ -0,0 +1,202 @@
+//! OBEX sessions.
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use std::{fmt, sync::Arc};
+use strum::{Display, EnumString};
+use uuid::Uuid;
+
+use super::{
+    file_transfer::FileTransfer, message_access::MessageAccess, object_push::ObjectPush,
+    phonebook_access::PhonebookAccess, ObexInner, SERVICE_NAME,
+};
+use crate::{Address, Error, ErrorKind, InternalErrorKind, Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.Session1";
+
+/// Target profile of an OBEX session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Target {
+    /// File transfer profile (FTP).
+    #[strum(serialize = "ftp")]
+    FileTransfer,
+    /// Message access profile (MAP).
+    #[strum(serialize = "map")]
+    MessageAccess,
+    /// Object push profile (OPP).
+    #[strum(serialize = "opp")]
+    ObjectPush,
+    /// Phonebook access profile (PBAP).
+    #[strum(serialize = "pbap")]
+    PhonebookAccess,
+    /// Synchronization profile (SYNC).
+    #[strum(serialize = "sync")]
+    Synchronization,
+}
+
+/// Options for creating an OBEX session.
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    /// Address of the remote device.
+    pub destination: Address,
+    /// Target profile.
+    pub target: Target,
+    /// Address of the local adapter.
+    ///
+    /// If [None] the default adapter is used.
+    pub source: Option<Address>,
+    /// RFCOMM channel of the remote service.
+    ///
+    /// If [None] the channel is discovered using SDP.
+    pub channel: Option<u8>,
+    /// L2CAP PSM of the remote service.
+    ///
+    /// If [None] the PSM is discovered using SDP.
+    pub psm: Option<u16>,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl SessionOptions {
+    /// Session options for connecting to the target profile of the specified remote device.
+    pub fn new(destination: Address, target: Target) -> Self {
+        Self { destination, target, source: None, channel: None, psm: None, _non_exhaustive: () }
+    }
+
+    pub(crate) fn to_dict(&self) -> PropMap {
+        let mut pm: PropMap = PropMap::new();
+        pm.insert("Target".to_string(), Variant(self.target.to_string().box_clone()));
+        if let Some(source) = &self.source {
+            pm.insert("Source".to_string(), Variant(source.to_string().box_clone()));
+        }
+        if let Some(channel) = &self.channel {
+            pm.insert("Channel".to_string(), Variant(channel.box_clone()));
+        }
+        if let Some(psm) = &self.psm {
+            pm.insert("PSM".to_string(), Variant(psm.box_clone()));
+        }
+        pm
+    }
+}
+
+/// OBEX session to a remote device.
+///
+/// Use [Client::create_session](super::Client::create_session) to obtain a session.
+#[derive(Clone)]
+pub struct Session {
+    inner: Arc<ObexInner>,
+    pub(crate) dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Session {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Session {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// D-Bus object path of the session.
+    pub fn path(&self) -> &str {
+        &self.dbus_path
+    }
+
+    /// Gets the capabilities object of the remote device.
+    ///
+    /// The capabilities are returned in XML format.
+    pub async fn capabilities(&self) -> Result<String> {
+        let (caps,): (String,) = self.call_method("GetCapabilities", ()).await?;
+        Ok(caps)
+    }
+
+    /// Object push interface of the session.
+    ///
+    /// The session must have been created for the [object push target](Target::ObjectPush).
+    pub fn object_push(&self) -> ObjectPush {
+        ObjectPush::new(self.inner.clone(), self.dbus_path.clone())
+    }
+
+    /// File transfer interface of the session.
+    ///
+    /// The session must have been created for the [file transfer target](Target::FileTransfer).
+    pub fn file_transfer(&self) -> FileTransfer {
+        FileTransfer::new(self.inner.clone(), self.dbus_path.clone())
+    }
+
+    /// Phonebook access interface of the session.
+    ///
+    /// The session must have been created for the [phonebook access target](Target::PhonebookAccess).
+    pub fn phonebook_access(&self) -> PhonebookAccess {
+        PhonebookAccess::new(self.inner.clone(), self.dbus_path.clone())
+    }
+
+    /// Message access interface of the session.
+    ///
+    /// The session must have been created for the [message access target](Target::MessageAccess).
+    pub fn message_access(&self) -> MessageAccess {
+        MessageAccess::new(self.inner.clone(), self.dbus_path.clone())
+    }
+}
+
+define_properties!(
+    Session,
+    /// OBEX session property.
+    pub SessionProperty => {
+        /// Address of the local adapter.
+        property(
+            Source, Address,
+            dbus: (INTERFACE, "Source", String, MANDATORY),
+            get: (source, v => {v.parse()?}),
+        );
+
+        /// Address of the remote device.
+        property(
+            Destination, Address,
+            dbus: (INTERFACE, "Destination", String, MANDATORY),
+            get: (destination, v => {v.parse()?}),
+        );
+
+        /// RFCOMM channel used by the session.
+        property(
+            Channel, u8,
+            dbus: (INTERFACE, "Channel", u8, OPTIONAL),
+            get: (channel, v => {*v}),
+        );
+
+        /// L2CAP PSM used by the session.
+        property(
+            Psm, u16,
+            dbus: (INTERFACE, "PSM", u16, OPTIONAL),
+            get: (psm, v => {*v}),
+        );
+
+        /// Target UUID of the session.
+        property(
+            Target, Uuid,
+            dbus: (INTERFACE, "Target", String, MANDATORY),
+            get: (target, v => {v.parse().map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::InvalidUuid(v.to_string()))))?}),
+        );
+
+        /// Root path of the session.
+        property(
+            Root, String,
+            dbus: (INTERFACE, "Root", String, OPTIONAL),
+            get: (root, v => {v.to_owned()}),
+        );
+    }
+);
@@ -0,0 +1,260 @@
+//! OBEX transfers.
+
+use dbus::{
+    nonblock::{Proxy, SyncConnection},
+    Path,
+};
+use futures::{future, stream, Stream, StreamExt};
+use std::{fmt, sync::Arc};
+use strum::{Display, EnumString};
+
+use super::{ObexInner, SERVICE_NAME};
+use crate::{Error, ErrorKind, Event, Result, TIMEOUT};
+
+pub(crate) const INTERFACE: &str = "org.bluez.obex.Transfer1";
+
+/// Status of an OBEX transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransferStatus {
+    /// Transfer is waiting to be started.
+    #[strum(serialize = "queued")]
+    Queued,
+    /// Transfer is in progress.
+    #[strum(serialize = "active")]
+    Active,
+    /// Transfer has been suspended.
+    #[strum(serialize = "suspended")]
+    Suspended,
+    /// Transfer has completed successfully.
+    #[strum(serialize = "complete")]
+    Complete,
+    /// Transfer has failed.
+    #[strum(serialize = "error")]
+    Error,
+}
+
+impl TransferStatus {
+    /// Whether the transfer has finished, either successfully or with an error.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Complete | Self::Error)
+    }
+}
+
+/// Progress of an OBEX transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransferProgress {
+    /// Transfer status.
+    pub status: TransferStatus,
+    /// Number of bytes transferred so far.
+    pub transferred: u64,
+    /// Total size of the object in bytes, if known.
+    pub size: Option<u64>,
+}
+
+/// OBEX transfer event.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TransferEvent {
+    /// Property changed.
+    PropertyChanged(TransferProperty),
+}
+
+/// OBEX transfer performed in the background by the OBEX daemon.
+///
+/// The OBEX daemon removes the transfer shortly after it has finished.
+#[derive(Clone)]
+pub struct Transfer {
+    inner: Arc<ObexInner>,
+    dbus_path: Path<'static>,
+}
+
+impl fmt::Debug for Transfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transfer {{ {} }}", &self.dbus_path)
+    }
+}
+
+impl Transfer {
+    pub(crate) fn new(inner: Arc<ObexInner>, dbus_path: Path<'static>) -> Self {
+        Self { inner, dbus_path }
+    }
+
+    fn proxy(&self) -> Proxy<'_, &SyncConnection> {
+        Proxy::new(SERVICE_NAME, &self.dbus_path, TIMEOUT, &*self.inner.connection)
+    }
+
+    dbus_interface!();
+    dbus_default_interface!(INTERFACE);
+
+    /// D-Bus object path of the transfer.
+    pub fn path(&self) -> &str {
+        &self.dbus_path
+    }
+
+    /// Streams transfer property changes.
+    pub async fn events(&self) -> Result<impl Stream<Item = TransferEvent>> {
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let stream = events.flat_map(move |event| match event {
+            Event::PropertiesChanged { interface, changed, .. } if interface == INTERFACE => stream::iter(
+                TransferProperty::from_prop_map(changed).into_iter().map(TransferEvent::PropertyChanged),
+            )
+            .boxed(),
+            _ => stream::empty().boxed(),
+        });
+
+        Ok(stream)
+    }
+
+    /// Streams the progress of the transfer.
+    ///
+    /// The current progress is returned as the first item, followed by an item for
+    /// each change.
+    /// The stream ends after the transfer has finished or has been removed.
+    ///
+    /// Fails with [ErrorKind::NotFound] if the transfer has already been removed
+    /// by the OBEX daemon.
+    pub async fn progress(&self) -> Result<impl Stream<Item = TransferProgress>> {
+        // Subscribe before querying the initial state so that no change is missed.
+        let events = self.inner.events(self.dbus_path.clone(), false).await?;
+        let initial = TransferProgress {
+            status: self.status().await?,
+            transferred: self.transferred().await?.unwrap_or_default(),
+            size: self.size().await?,
+        };
+
+        let stream = stream::unfold(Some((events, initial.clone())), |state| async move {
+            let (mut events, mut progress) = state?;
+            if progress.status.is_finished() {
+                return None;
+            }
+            loop {
+                match events.next().await? {
+                    Event::PropertiesChanged { interface, changed, .. } if interface == INTERFACE => {
+                        for prop in TransferProperty::from_prop_map(changed) {
+                            match prop {
+                                TransferProperty::Status(status) => progress.status = status,
+                                TransferProperty::Transferred(transferred) => progress.transferred = transferred,
+                                TransferProperty::Size(size) => progress.size = Some(size),
+                                _ => (),
+                            }
+                        }
+                        return Some((progress.clone(), Some((events, progress))));
+                    }
+                    Event::ObjectRemoved { .. } => return None,
+                    _ => (),
+                }
+            }
+        });
+
+        Ok(stream::once(future::ready(initial)).chain(stream).boxed())
+    }
+
+    /// Waits for the transfer to finish.
+    ///
+    /// Returns an error if the transfer fails or is removed before it has completed.
+    /// Since the OBEX daemon removes a finished transfer, this should be called
+    /// right after the transfer has been started.
+    pub async fn wait(&self) -> Result<()> {
+        let mut progress = self.progress().await?;
+        let mut status = None;
+        while let Some(p) = progress.next().await {
+            status = Some(p.status);
+        }
+
+        match status {
+            Some(TransferStatus::Complete) => Ok(()),
+            Some(TransferStatus::Error) => {
+                Err(Error { kind: ErrorKind::Failed, message: "transfer failed".to_string() })
+            }
+            _ => Err(Error { kind: ErrorKind::Failed, message: "transfer was removed".to_string() }),
+        }
+    }
+
+    /// Stops the transfer.
+    pub async fn cancel(&self) -> Result<()> {
+        self.call_method("Cancel", ()).await
+    }
+
+    /// Suspends the transfer.
+    ///
+    /// Only transfers that are [active](TransferStatus::Active) or
+    /// [queued](TransferStatus::Queued) can be suspended.
+    pub async fn suspend(&self) -> Result<()> {
+        self.call_method("Suspend", ()).await
+    }
+
+    /// Resumes a [suspended](TransferStatus::Suspended) transfer.
+    pub async fn resume(&self) -> Result<()> {
+        self.call_method("Resume", ()).await
+    }
+}
+
+define_properties!(
+    Transfer,
+    /// OBEX transfer property.
+    pub TransferProperty => {
+        /// Transfer status.
+        property(
+            Status, TransferStatus,
+            dbus: (INTERFACE, "Status", String, MANDATORY),
+            get: (status, v => {v.parse()?}),
+        );
+
+        /// D-Bus object path of the session the transfer belongs to.
+        property(
+            Session, String,
+            dbus: (INTERFACE, "Session", Path<'static>, MANDATORY),
+            get: (session_path, v => {v.to_string()}),
+        );
+
+        /// Name of the transferred object.
+        property(
+            Name, String,
+            dbus: (INTERFACE, "Name", String, OPTIONAL),
+            get: (name, v => {v.to_owned()}),
+        );
+
+        /// MIME type of the transferred object.
+        property(
+            Type, String,
+            dbus: (INTERFACE, "Type", String, OPTIONAL),
+            get: (mime_type, v => {v.to_owned()}),
+        );
+
+        /// Time of the transferred object, in seconds since the UNIX epoch.
+        property(
+            Time, u64,
+            dbus: (INTERFACE, "Time", u64, OPTIONAL),
+            get: (time, v => {*v}),
+        );
+
+        /// Size of the transferred object in bytes.
+        ///
+        /// If the size is unknown, this is not available.
+        property(
+            Size, u64,
+            dbus: (INTERFACE, "Size", u64, OPTIONAL),
+            get: (size, v => {*v}),
+        );
+
+        /// Number of bytes transferred so far.
+        ///
+        /// This is only available for active transfers.
+        property(
+            Transferred, u64,
+            dbus: (INTERFACE, "Transferred", u64, OPTIONAL),
+            get: (transferred, v => {*v}),
+        );
+
+        /// Complete name of the local file.
+        property(
+            Filename, String,
+            dbus: (INTERFACE, "Filename", String, OPTIONAL),
+            get: (filename, v => {v.to_owned()}),
+        );
+    }
+);
@@ -348,7 +348,7 @@ impl Socket {
         Ok(Self { fd: AsyncFd::new(fd)? })
     }
 
-    sock_priv!();
+    sock_priv!(stream);
 }
 
 impl AsRawFd for Socket {
@@ -128,7 +128,9 @@ pub struct Profile {
     /// In case of a client UUID this will force connection of the RFCOMM or L2CAP
     /// channels when a remote device is connected.
     pub auto_connect: Option<bool>,
-    /// Provide a manual SDP record.
+    /// Provide a manual SDP record in XML format.
+    ///
+    /// Use [ServiceRecord::to_xml](crate::sdp::ServiceRecord::to_xml) to build it from a typed record.
     pub service_record: Option<String>,
     /// Profile version (for SDP record).
     pub version: Option<u16>,
@@ -343,12 +345,16 @@ impl RegisteredProfile {
 
         log::trace!("Registering profile at {}", &name);
         let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, inner.connection.clone());
-        let () = proxy
-            .method_call(
-                MANAGER_INTERFACE,
-                "RegisterProfile",
-                (name.clone(), profile.uuid.to_string(), profile.to_dict()),
-            )
+        let reg_name = name.clone();
+        inner
+            .register_with_daemon(&name, None, move |connection| {
+                let proxy = Proxy::new(SERVICE_NAME, MANAGER_PATH, TIMEOUT, connection);
+                let args = (reg_name.clone(), profile.uuid.to_string(), profile.to_dict());
+                async move {
+                    let () = proxy.method_call(MANAGER_INTERFACE, "RegisterProfile", args).await?;
+                    Ok(())
+                }
+            })
             .await?;
 
         let (drop_tx, drop_rx) = oneshot::channel();
@@ -357,6 +363,7 @@ impl RegisteredProfile {
             let _ = drop_rx.await;
 
             log::trace!("Unregistering profile at {}", &unreg_name);
+            inner.unregister_with_daemon(&unreg_name).await;
             let _: std::result::Result<(), dbus::Error> =
                 proxy.method_call(MANAGER_INTERFACE, "UnregisterProfile", (unreg_name.clone(),)).await;
 
@@ -0,0 +1,636 @@
+//! Synchronous connection-oriented (SCO) sockets.
+//!
+//! SCO sockets carry voice data over classic Bluetooth (BR/EDR) links.
+//! They are used by the audio connection of the Hands-Free Profile (HFP) and the
+//! Headset Profile (HSP).
+//!
+//! A SCO connection is always established towards a device that is already
+//! connected using an ACL link, for example by an RFCOMM connection carrying
+//! the profile's control channel.
+//!
+//! Each send and receive operation transfers exactly one SCO packet.
+//!
+
+use crate::{
+    sock::{self, OwnedFd},
+    sys::{
+        bt_voice, sco_options, sockaddr_sco, BTPROTO_SCO, BT_DEFER_SETUP, BT_PKT_STATUS, BT_SCM_PKT_STATUS,
+        BT_VOICE, BT_VOICE_CVSD_16BIT, BT_VOICE_TRANSPARENT, SCO_CONNINFO, SCO_OPTIONS, SOL_SCO,
+    },
+    Address,
+};
+use futures::ready;
+use libc::{
+    AF_BLUETOOTH, EAGAIN, EINPROGRESS, MSG_PEEK, SHUT_RD, SHUT_RDWR, SHUT_WR, SOCK_SEQPACKET, SOL_BLUETOOTH,
+    SOL_SOCKET, SO_ERROR, SO_RCVBUF, TIOCINQ, TIOCOUTQ,
+};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{Error, ErrorKind, Result},
+    mem::ManuallyDrop,
+    net::Shutdown,
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::io::{unix::AsyncFd, AsyncRead, AsyncWrite, ReadBuf};
+
+pub use crate::sys::sco_conninfo as ConnInfo;
+
+/// A SCO socket address.
+///
+/// ## String representation
+/// The string representation is the Bluetooth address, i.e.
+/// of the form `01:23:45:67:89:0a`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketAddr {
+    /// Device address.
+    ///
+    /// When listening or binding, specify [Address::any] for any local adapter address.
+    pub addr: Address,
+}
+
+impl SocketAddr {
+    /// Creates a new SCO socket address.
+    pub const fn new(addr: Address) -> Self {
+        Self { addr }
+    }
+
+    /// When specified to [Socket::bind] binds to any local adapter address.
+    pub const fn any() -> Self {
+        Self { addr: Address::any() }
+    }
+}
+
+impl fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.addr)
+    }
+}
+
+/// Invalid SCO socket address error.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvalidSocketAddr(pub String);
+
+impl fmt::Display for InvalidSocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid SCO socket address: {}", &self.0)
+    }
+}
+
+impl std::error::Error for InvalidSocketAddr {}
+
+impl FromStr for SocketAddr {
+    type Err = InvalidSocketAddr;
+    fn from_str(s: &str) -> std::result::Result<Self, InvalidSocketAddr> {
+        Ok(Self { addr: s.parse().map_err(|_| InvalidSocketAddr(s.to_string()))? })
+    }
+}
+
+impl sock::SysSockAddr for SocketAddr {
+    type SysSockAddr = sockaddr_sco;
+
+    fn into_sys_sock_addr(self) -> Self::SysSockAddr {
+        sockaddr_sco { sco_family: AF_BLUETOOTH as _, sco_bdaddr: self.addr.into() }
+    }
+
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.sco_family != AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_sco::sco_family is not AF_BLUETOOTH"));
+        }
+        Ok(Self { addr: Address::from(saddr.sco_bdaddr) })
+    }
+}
+
+/// SCO voice setting.
+///
+/// This determines how the controller processes the audio data.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Voice {
+    /// 16-bit linear PCM samples that are CVSD encoded by the controller.
+    ///
+    /// This is the default and used for narrowband speech.
+    #[default]
+    Cvsd16Bit = BT_VOICE_CVSD_16BIT as _,
+    /// Transparent data that is passed through by the controller unmodified.
+    ///
+    /// This is used for codecs encoded by the host, for example mSBC for wideband speech.
+    Transparent = BT_VOICE_TRANSPARENT as _,
+}
+
+/// Status of a received SCO packet as reported by the controller.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketStatus {
+    /// Correctly received data.
+    Correct = 0x00,
+    /// Possibly invalid data.
+    PossiblyInvalid = 0x01,
+    /// No data received; the packet contents should be ignored.
+    NoData = 0x02,
+    /// Data partially lost.
+    PartiallyLost = 0x03,
+}
+
+/// A SCO socket that has not yet been converted to a [Listener] or [Stream].
+///
+/// The primary use of this is to configure the socket before connecting or listening.
+pub struct Socket {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socket").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
+
+impl Socket {
+    /// Creates a new socket.
+    pub fn new() -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(sock::socket(AF_BLUETOOTH, SOCK_SEQPACKET, BTPROTO_SCO)?)? })
+    }
+
+    /// Convert the socket into a [Listener].
+    ///
+    /// `backlog` defines the maximum number of pending connections are queued by the operating system
+    /// at any given time.
+    pub fn listen(self, backlog: u32) -> Result<Listener> {
+        sock::listen(
+            self.fd.get_ref(),
+            backlog.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid backlog"))?,
+        )?;
+        Ok(Listener { socket: self })
+    }
+
+    /// Establish a SCO connection with a peer at the specified socket address.
+    ///
+    /// The [voice setting](Self::set_voice) must be configured before connecting.
+    pub async fn connect(self, sa: SocketAddr) -> Result<Stream> {
+        self.connect_priv(sa).await?;
+        Stream::from_socket(self)
+    }
+
+    /// Bind the socket to the given address.
+    pub fn bind(&self, sa: SocketAddr) -> Result<()> {
+        sock::bind(self.fd.get_ref(), sa)
+    }
+
+    /// Get the local address of this socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        sock::getsockname(self.fd.get_ref())
+    }
+
+    /// Get the peer address of this socket.
+    fn peer_addr_priv(&self) -> Result<SocketAddr> {
+        sock::getpeername(self.fd.get_ref())
+    }
+
+    /// Gets the voice setting.
+    ///
+    /// This corresponds to the `BT_VOICE` socket option.
+    pub fn voice(&self) -> Result<Voice> {
+        let value: bt_voice = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_VOICE)?;
+        Voice::from_u16(value.setting).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid voice setting"))
+    }
+
+    /// Sets the voice setting.
+    ///
+    /// When connecting this must be set before [connect](Self::connect) is called.
+    /// When listening with [deferred setup](Self::set_defer_setup) this must be set on
+    /// the accepted stream before calling [Stream::accept_deferred].
+    ///
+    /// This corresponds to the `BT_VOICE` socket option.
+    pub fn set_voice(&self, voice: Voice) -> Result<()> {
+        let value = bt_voice { setting: voice as _ };
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_VOICE, &value)
+    }
+
+    /// Gets whether deferred connection setup is enabled.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn is_defer_setup(&self) -> Result<bool> {
+        let value: u32 = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP)?;
+        Ok(value != 0)
+    }
+
+    /// Sets whether deferred connection setup is enabled.
+    ///
+    /// When enabled on a listening socket, incoming connections are returned by
+    /// [Listener::accept] before they are accepted by the local controller.
+    /// This allows to inspect the peer address and configure the
+    /// [voice setting](Self::set_voice) before accepting the connection using
+    /// [Stream::accept_deferred].
+    /// Dropping the stream rejects the connection.
+    ///
+    /// This corresponds to the `BT_DEFER_SETUP` socket option.
+    pub fn set_defer_setup(&self, defer_setup: bool) -> Result<()> {
+        let value: u32 = defer_setup.into();
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_DEFER_SETUP, &value)
+    }
+
+    /// Gets whether the status of received packets is reported.
+    ///
+    /// This corresponds to the `BT_PKT_STATUS` socket option.
+    pub fn is_pkt_status(&self) -> Result<bool> {
+        let value: c_int = sock::getsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_PKT_STATUS)?;
+        Ok(value != 0)
+    }
+
+    /// Sets whether the status of received packets is reported.
+    ///
+    /// When enabled, the status is returned by [Stream::recv_with_status].
+    ///
+    /// This corresponds to the `BT_PKT_STATUS` socket option.
+    pub fn set_pkt_status(&self, pkt_status: bool) -> Result<()> {
+        let value: u32 = pkt_status.into();
+        sock::setsockopt(self.fd.get_ref(), SOL_BLUETOOTH, BT_PKT_STATUS, &value)
+    }
+
+    /// Gets the maximum transmission unit (MTU) of the SCO link.
+    ///
+    /// This is only available once the connection has been established.
+    ///
+    /// This corresponds to the `SCO_OPTIONS` socket option.
+    pub fn mtu(&self) -> Result<u16> {
+        let opts: sco_options = sock::getsockopt(self.fd.get_ref(), SOL_SCO, SCO_OPTIONS)?;
+        Ok(opts.mtu)
+    }
+
+    /// Gets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn recv_buffer(&self) -> Result<i32> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF)
+    }
+
+    /// Sets the maximum socket receive buffer in bytes.
+    ///
+    /// This corresponds to the `SO_RCVBUF` socket option.
+    pub fn set_recv_buffer(&self, recv_buffer: i32) -> Result<()> {
+        sock::setsockopt(self.fd.get_ref(), SOL_SOCKET, SO_RCVBUF, &recv_buffer)
+    }
+
+    /// Gets the SCO socket connection information.
+    ///
+    /// This corresponds to the `SCO_CONNINFO` socket option.
+    pub fn conn_info(&self) -> Result<ConnInfo> {
+        sock::getsockopt(self.fd.get_ref(), SOL_SCO, SCO_CONNINFO)
+    }
+
+    /// Get the number of bytes in the input buffer.
+    ///
+    /// This corresponds to the `TIOCINQ` IOCTL.
+    pub fn input_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCINQ)?;
+        Ok(value as _)
+    }
+
+    /// Get the number of bytes in the output buffer.
+    ///
+    /// This corresponds to the `TIOCOUTQ` IOCTL.
+    pub fn output_buffer(&self) -> Result<u32> {
+        let value: c_int = sock::ioctl_read(self.fd.get_ref(), TIOCOUTQ)?;
+        Ok(value as _)
+    }
+
+    /// Constructs a new [Socket] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(OwnedFd::new(fd))? })
+    }
+
+    fn from_owned_fd(fd: OwnedFd) -> Result<Self> {
+        Ok(Self { fd: AsyncFd::new(fd)? })
+    }
+
+    fn recv_with_status_priv(&self, buf: &mut ReadBuf) -> Result<Option<PacketStatus>> {
+        let (_, cmsgs) = sock::recvmsg(self.fd.get_ref(), buf, 0)?;
+        Ok(cmsgs
+            .into_iter()
+            .find(|cmsg| cmsg.level == SOL_BLUETOOTH && cmsg.ty == BT_SCM_PKT_STATUS)
+            .and_then(|cmsg| cmsg.data.first().copied())
+            .and_then(PacketStatus::from_u8))
+    }
+
+    sock_priv!(stream);
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        self.fd.into_inner().into_raw_fd()
+    }
+}
+
+impl FromRawFd for Socket {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Socket::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// A SCO socket server, listening for [Stream] connections.
+#[derive(Debug)]
+pub struct Listener {
+    socket: Socket,
+}
+
+impl Listener {
+    /// Creates a new Listener, which will be bound to the specified socket address.
+    ///
+    /// Specify [SocketAddr::any] for any local adapter address.
+    pub async fn bind(sa: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(sa)?;
+        socket.listen(1)
+    }
+
+    /// Accepts a new incoming connection from this listener.
+    pub async fn accept(&self) -> Result<(Stream, SocketAddr)> {
+        let (socket, sa) = self.socket.accept_priv().await?;
+        Ok((Stream::from_socket(socket)?, sa))
+    }
+
+    /// Polls to accept a new incoming connection to this listener.
+    pub fn poll_accept(&self, cx: &mut Context) -> Poll<Result<(Stream, SocketAddr)>> {
+        let (socket, sa) = ready!(self.socket.poll_accept_priv(cx))?;
+        Poll::Ready(Ok((Stream::from_socket(socket)?, sa)))
+    }
+
+    /// Constructs a new [Listener] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Ok(Self { socket: Socket::from_raw_fd(fd)? })
+    }
+}
+
+impl AsRef<Socket> for Listener {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Listener {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Listener::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+/// A SCO connection between a local and remote device.
+///
+/// Data is transferred in packets of at most [Self::mtu] bytes.
+/// When using the [AsyncRead] and [AsyncWrite] interfaces, each read returns
+/// exactly one packet and each write sends exactly one packet.
+#[derive(Debug)]
+pub struct Stream {
+    socket: Socket,
+}
+
+impl Stream {
+    /// Create Stream from Socket.
+    fn from_socket(socket: Socket) -> Result<Self> {
+        Ok(Self { socket })
+    }
+
+    /// Establish a SCO connection with a peer at the specified socket address
+    /// using the default voice setting.
+    ///
+    /// Uses any local Bluetooth adapter.
+    pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        let socket = Socket::new()?;
+        socket.bind(SocketAddr::any())?;
+        socket.connect(addr).await
+    }
+
+    /// Gets the peer address of this stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.socket.peer_addr_priv()
+    }
+
+    /// Accepts a connection that was received with
+    /// [deferred setup](Socket::set_defer_setup) enabled.
+    ///
+    /// The [voice setting](Socket::set_voice) of the connection must be configured
+    /// before calling this.
+    pub fn accept_deferred(&self) -> Result<()> {
+        let mut buf = ReadBuf::new(&mut []);
+        sock::recv(self.socket.fd.get_ref(), &mut buf, 0)?;
+        Ok(())
+    }
+
+    /// Sends a packet.
+    ///
+    /// The packet length must not exceed the [Self::mtu].
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.socket.send_priv(buf).await
+    }
+
+    /// Attempts to send a packet.
+    ///
+    /// The packet length must not exceed the [Self::mtu].
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.socket.poll_send_priv(cx, buf)
+    }
+
+    /// Receives a packet.
+    ///
+    /// The provided buffer must be of length [Self::mtu], otherwise
+    /// the packet may be truncated.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.socket.recv_priv(buf).await
+    }
+
+    /// Attempts to receive a packet.
+    ///
+    /// The provided buffer must be of length [Self::mtu], otherwise
+    /// the packet may be truncated.
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        self.socket.poll_recv_priv(cx, buf)
+    }
+
+    /// Receives a packet together with its status.
+    ///
+    /// The status is only available if [packet status reporting](Socket::set_pkt_status)
+    /// has been enabled; otherwise `None` is returned.
+    ///
+    /// The provided buffer must be of length [Self::mtu], otherwise
+    /// the packet may be truncated.
+    pub async fn recv_with_status(&self, buf: &mut [u8]) -> Result<(usize, Option<PacketStatus>)> {
+        let mut buf = ReadBuf::new(buf);
+        loop {
+            let mut guard = self.socket.fd.readable().await?;
+            match guard.try_io(|_| self.socket.recv_with_status_priv(&mut buf)) {
+                Ok(result) => return result.map(|status| (buf.filled().len(), status)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Attempts to receive a packet together with its status.
+    ///
+    /// The status is only available if [packet status reporting](Socket::set_pkt_status)
+    /// has been enabled; otherwise `None` is returned.
+    ///
+    /// The provided buffer must be of length [Self::mtu], otherwise
+    /// the packet may be truncated.
+    pub fn poll_recv_with_status(
+        &self, cx: &mut Context, buf: &mut ReadBuf,
+    ) -> Poll<Result<Option<PacketStatus>>> {
+        loop {
+            let mut guard = ready!(self.socket.fd.poll_read_ready(cx))?;
+            match guard.try_io(|_| self.socket.recv_with_status_priv(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.socket.shutdown_priv(how)
+    }
+
+    /// Maximum transmission unit (MTU) of the SCO link.
+    pub fn mtu(&self) -> Result<usize> {
+        self.socket.mtu().map(|v| v.into())
+    }
+
+    /// Splits the stream into a borrowed read half and a borrowed write half, which can be used
+    /// to read and write the stream concurrently.
+    #[allow(clippy::needless_lifetimes)]
+    pub fn split<'a>(&'a mut self) -> (stream::ReadHalf<'a>, stream::WriteHalf<'a>) {
+        (stream::ReadHalf(self), stream::WriteHalf(self))
+    }
+
+    /// Splits the into an owned read half and an owned write half, which can be used to read
+    /// and write the stream concurrently.
+    pub fn into_split(self) -> (stream::OwnedReadHalf, stream::OwnedWriteHalf) {
+        let stream = Arc::new(self);
+        let r = stream::OwnedReadHalf {
+            stream: ManuallyDrop::new(stream.clone()),
+            shutdown_on_drop: true,
+            drop: true,
+        };
+        let w = stream::OwnedWriteHalf { stream, shutdown_on_drop: true };
+        (r, w)
+    }
+
+    fn poll_write_priv(&self, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.socket.poll_send_priv(cx, buf)
+    }
+
+    /// Constructs a new [Stream] from the given raw file descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// This function *consumes ownership* of the specified file descriptor.
+    /// The returned object will take responsibility for closing it when the object goes out of scope.
+    ///
+    /// # Safety
+    /// If the passed file descriptor is invalid, undefined behavior may occur.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<Self> {
+        Self::from_socket(Socket::from_raw_fd(fd)?)
+    }
+}
+
+impl AsRef<Socket> for Stream {
+    fn as_ref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl AsRawFd for Stream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Stream {
+    /// Constructs a new instance of `Self` from the given raw file
+    /// descriptor.
+    ///
+    /// The file descriptor must have been set to non-blocking mode.
+    ///
+    /// # Panics
+    /// Panics when the conversion fails.
+    /// Use [Stream::from_raw_fd] for a non-panicking variant.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_raw_fd(fd).expect("from_raw_fd failed")
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<Result<()>> {
+        self.socket.poll_recv_priv(cx, buf)
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize>> {
+        self.poll_write_priv(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        self.socket.poll_flush_priv(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        self.socket.poll_shutdown_priv(cx, Shutdown::Write)
+    }
+}
+
+#[allow(clippy::duplicate_mod)]
+#[path = "stream_util.rs"]
+pub mod stream;
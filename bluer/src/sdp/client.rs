@@ -0,0 +1,390 @@
+//! SDP client over L2CAP.
+
+use std::{
+    io::{Error, ErrorKind, Result},
+    ops::RangeInclusive,
+};
+use uuid::Uuid;
+
+use super::{DataElement, SdpError, ServiceRecord, ATTR_PROTOCOL_DESCRIPTOR_LIST};
+use crate::{
+    l2cap::{SeqPacket, SocketAddr},
+    Address, AddressType,
+};
+
+/// Protocol service multiplexor (PSM) of the SDP server.
+pub const PSM: u16 = 0x0001;
+
+/// Range of all attribute ids.
+pub const ALL_ATTRIBUTES: RangeInclusive<u16> = 0x0000..=0xffff;
+
+const ERROR_RSP: u8 = 0x01;
+const SERVICE_SEARCH_REQ: u8 = 0x02;
+const SERVICE_SEARCH_RSP: u8 = 0x03;
+const SERVICE_ATTRIBUTE_REQ: u8 = 0x04;
+const SERVICE_ATTRIBUTE_RSP: u8 = 0x05;
+const SERVICE_SEARCH_ATTRIBUTE_REQ: u8 = 0x06;
+const SERVICE_SEARCH_ATTRIBUTE_RSP: u8 = 0x07;
+
+const HEADER_LEN: usize = 5;
+const MAX_CONTINUATION_LEN: usize = 16;
+const MAX_PATTERN_LEN: usize = 12;
+/// Maximum total length of a response reassembled from partial responses.
+const MAX_RESPONSE_LEN: usize = 1 << 20;
+
+/// SDP client for querying the service records of a remote device.
+///
+/// Requests are performed sequentially, as required by the protocol.
+/// Partial responses are reassembled using the continuation state.
+///
+/// Errors returned by the SDP server and malformed responses are returned
+/// as the inner [SdpError] of the [std::io::Error].
+#[derive(Debug)]
+pub struct Client {
+    socket: SeqPacket,
+    transaction_id: u16,
+}
+
+impl Client {
+    /// Connects to the SDP server of the specified remote device using classic Bluetooth (BR/EDR).
+    pub async fn connect(addr: Address) -> Result<Self> {
+        let socket = SeqPacket::connect(SocketAddr::new(addr, AddressType::BrEdr, PSM)).await?;
+        Ok(Self::from_socket(socket))
+    }
+
+    /// Uses an established L2CAP connection to an SDP server.
+    pub fn from_socket(socket: SeqPacket) -> Self {
+        Self { socket, transaction_id: 0 }
+    }
+
+    /// Sends a request and returns the parameters of the response.
+    async fn request(&mut self, pdu_id: u8, rsp_pdu_id: u8, params: &[u8]) -> Result<Vec<u8>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let tid = self.transaction_id;
+        let len: u16 =
+            params.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "SDP request too long"))?;
+
+        let mut pdu = Vec::with_capacity(HEADER_LEN + params.len());
+        pdu.push(pdu_id);
+        pdu.extend_from_slice(&tid.to_be_bytes());
+        pdu.extend_from_slice(&len.to_be_bytes());
+        pdu.extend_from_slice(params);
+        if self.socket.send(&pdu).await? != pdu.len() {
+            return Err(Error::new(ErrorKind::WriteZero, "incomplete SDP request sent"));
+        }
+
+        let mut buf = vec![0; self.socket.recv_mtu()?];
+        loop {
+            let n = self.socket.recv(&mut buf).await?;
+            if n == 0 {
+                return Err(Error::new(ErrorKind::ConnectionReset, "SDP connection closed"));
+            }
+            let rsp = &buf[..n];
+            if rsp.len() < HEADER_LEN {
+                return Err(SdpError::InvalidResponse.into());
+            }
+            let rsp_tid = u16::from_be_bytes([rsp[1], rsp[2]]);
+            if rsp_tid != tid {
+                log::debug!("Ignoring SDP response with unexpected transaction id {rsp_tid}");
+                continue;
+            }
+            let len = u16::from_be_bytes([rsp[3], rsp[4]]) as usize;
+            let rsp_params = rsp.get(HEADER_LEN..HEADER_LEN + len).ok_or(SdpError::InvalidResponse)?;
+
+            return match rsp[0] {
+                ERROR_RSP => match rsp_params {
+                    [a, b, ..] => Err(SdpError::ErrorResponse(u16::from_be_bytes([*a, *b])).into()),
+                    _ => Err(SdpError::InvalidResponse.into()),
+                },
+                id if id == rsp_pdu_id => Ok(rsp_params.to_vec()),
+                _ => Err(SdpError::InvalidResponse.into()),
+            };
+        }
+    }
+
+    /// Maximum number of attribute bytes that fit into a single response.
+    fn max_attribute_byte_count(&self) -> Result<u16> {
+        let mtu = self.socket.recv_mtu()?;
+        let max = mtu.saturating_sub(HEADER_LEN + 2 + 1 + MAX_CONTINUATION_LEN).clamp(7, u16::MAX as usize);
+        Ok(max as u16)
+    }
+
+    /// Returns the handles of the service records that contain all UUIDs of the pattern.
+    ///
+    /// The pattern must contain between 1 and 12 UUIDs.
+    pub async fn service_search(&mut self, pattern: &[Uuid]) -> Result<Vec<u32>> {
+        let pattern = encode_pattern(pattern)?;
+        let mut handles = Vec::new();
+        let mut cont = Vec::new();
+
+        loop {
+            let mut params = pattern.clone();
+            params.extend_from_slice(&u16::MAX.to_be_bytes());
+            push_continuation(&mut params, &cont);
+            let rsp = self.request(SERVICE_SEARCH_REQ, SERVICE_SEARCH_RSP, &params).await?;
+
+            let (total, list, next) = parse_service_search_rsp(&rsp)?;
+            handles.extend(list);
+            cont = next;
+
+            if cont.is_empty() || handles.len() >= total {
+                break;
+            }
+        }
+
+        Ok(handles)
+    }
+
+    /// Requests the attributes of the service record with the specified handle.
+    ///
+    /// Only attributes within the specified ranges of attribute ids are returned.
+    /// Use [ALL_ATTRIBUTES] to request all attributes.
+    pub async fn service_attributes(
+        &mut self, handle: u32, attributes: &[RangeInclusive<u16>],
+    ) -> Result<ServiceRecord> {
+        let elem = self
+            .attribute_request(SERVICE_ATTRIBUTE_REQ, SERVICE_ATTRIBUTE_RSP, &handle.to_be_bytes(), attributes)
+            .await?;
+        Ok(ServiceRecord::from_attribute_list(&elem)?)
+    }
+
+    /// Requests the attributes of all service records that contain all UUIDs of the pattern.
+    ///
+    /// The pattern must contain between 1 and 12 UUIDs.
+    /// Only attributes within the specified ranges of attribute ids are returned.
+    /// Use [ALL_ATTRIBUTES] to request all attributes.
+    pub async fn service_search_attributes(
+        &mut self, pattern: &[Uuid], attributes: &[RangeInclusive<u16>],
+    ) -> Result<Vec<ServiceRecord>> {
+        let pattern = encode_pattern(pattern)?;
+        let elem = self
+            .attribute_request(SERVICE_SEARCH_ATTRIBUTE_REQ, SERVICE_SEARCH_ATTRIBUTE_RSP, &pattern, attributes)
+            .await?;
+        match elem {
+            DataElement::Sequence(lists) => {
+                Ok(lists.iter().map(ServiceRecord::from_attribute_list).collect::<std::result::Result<_, _>>()?)
+            }
+            _ => Err(SdpError::InvalidResponse.into()),
+        }
+    }
+
+    /// Returns the RFCOMM channel of the first service record that contains the specified
+    /// service class UUID.
+    ///
+    /// Returns [None] if no such service record exists or it does not use RFCOMM.
+    pub async fn rfcomm_channel(&mut self, service: Uuid) -> Result<Option<u8>> {
+        let records = self
+            .service_search_attributes(
+                &[service],
+                &[ATTR_PROTOCOL_DESCRIPTOR_LIST..=ATTR_PROTOCOL_DESCRIPTOR_LIST],
+            )
+            .await?;
+        Ok(records.iter().find_map(|record| record.rfcomm_channel()))
+    }
+
+    /// Performs an attribute request, reassembling partial responses, and
+    /// returns the decoded attribute list.
+    async fn attribute_request(
+        &mut self, pdu_id: u8, rsp_pdu_id: u8, prefix: &[u8], attributes: &[RangeInclusive<u16>],
+    ) -> Result<DataElement> {
+        let max_bytes = self.max_attribute_byte_count()?;
+        let attributes = encode_attribute_ids(attributes)?;
+        let mut data = Vec::new();
+        let mut cont = Vec::new();
+
+        loop {
+            let mut params = prefix.to_vec();
+            params.extend_from_slice(&max_bytes.to_be_bytes());
+            params.extend_from_slice(&attributes);
+            push_continuation(&mut params, &cont);
+            let rsp = self.request(pdu_id, rsp_pdu_id, &params).await?;
+
+            let (list, next) = parse_attribute_rsp(&rsp)?;
+            data.extend_from_slice(list);
+            cont = next;
+
+            if cont.is_empty() {
+                break;
+            }
+            if data.len() > MAX_RESPONSE_LEN {
+                return Err(SdpError::InvalidResponse.into());
+            }
+        }
+
+        let (elem, len) = DataElement::decode(&data)?;
+        if len != data.len() {
+            return Err(SdpError::InvalidResponse.into());
+        }
+        Ok(elem)
+    }
+}
+
+/// Parses a service search response into the total record count, the handles contained
+/// in this response and the continuation state.
+///
+/// A partial response without handles is rejected, since the request would be repeated forever.
+fn parse_service_search_rsp(rsp: &[u8]) -> Result<(usize, Vec<u32>, Vec<u8>)> {
+    let (total, current) = match rsp.get(..4) {
+        Some(&[t0, t1, c0, c1]) => (u16::from_be_bytes([t0, t1]) as usize, u16::from_be_bytes([c0, c1]) as usize),
+        _ => return Err(SdpError::InvalidResponse.into()),
+    };
+    let list = rsp.get(4..4 + 4 * current).ok_or(SdpError::InvalidResponse)?;
+    let handles = list.chunks(4).map(|h| u32::from_be_bytes(h.try_into().unwrap())).collect();
+    let cont = parse_continuation(&rsp[4 + 4 * current..])?;
+    if !cont.is_empty() && current == 0 {
+        return Err(SdpError::InvalidResponse.into());
+    }
+    Ok((total, handles, cont))
+}
+
+/// Parses an attribute response into the attribute list bytes contained in this response
+/// and the continuation state.
+///
+/// A partial response without data is rejected, since the request would be repeated forever.
+fn parse_attribute_rsp(rsp: &[u8]) -> Result<(&[u8], Vec<u8>)> {
+    let count = match rsp.get(..2) {
+        Some(&[c0, c1]) => u16::from_be_bytes([c0, c1]) as usize,
+        _ => return Err(SdpError::InvalidResponse.into()),
+    };
+    let list = rsp.get(2..2 + count).ok_or(SdpError::InvalidResponse)?;
+    let cont = parse_continuation(&rsp[2 + count..])?;
+    if !cont.is_empty() && count == 0 {
+        return Err(SdpError::InvalidResponse.into());
+    }
+    Ok((list, cont))
+}
+
+fn encode_pattern(pattern: &[Uuid]) -> Result<Vec<u8>> {
+    if pattern.is_empty() || pattern.len() > MAX_PATTERN_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("service search pattern must contain between 1 and {MAX_PATTERN_LEN} UUIDs"),
+        ));
+    }
+    Ok(DataElement::Sequence(pattern.iter().cloned().map(DataElement::Uuid).collect()).encode())
+}
+
+fn encode_attribute_ids(attributes: &[RangeInclusive<u16>]) -> Result<Vec<u8>> {
+    let ids: Vec<_> = attributes
+        .iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            if range.start() == range.end() {
+                DataElement::U16(*range.start())
+            } else {
+                DataElement::U32((*range.start() as u32) << 16 | *range.end() as u32)
+            }
+        })
+        .collect();
+    if ids.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "no attribute ids specified"));
+    }
+    Ok(DataElement::Sequence(ids).encode())
+}
+
+fn push_continuation(params: &mut Vec<u8>, cont: &[u8]) {
+    params.push(cont.len() as u8);
+    params.extend_from_slice(cont);
+}
+
+fn parse_continuation(data: &[u8]) -> Result<Vec<u8>> {
+    match data.split_first() {
+        Some((&len, state)) if len as usize <= MAX_CONTINUATION_LEN && state.len() >= len as usize => {
+            Ok(state[..len as usize].to_vec())
+        }
+        _ => Err(SdpError::InvalidResponse.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UuidExt;
+
+    fn sdp_error(err: Error) -> Option<SdpError> {
+        err.into_inner()?.downcast::<SdpError>().ok().map(|err| *err)
+    }
+
+    #[test]
+    fn pattern() {
+        assert_eq!(
+            encode_pattern(&[Uuid::from_u16(0x1101), Uuid::from_u16(0x0100)]).unwrap(),
+            [0x35, 0x06, 0x19, 0x11, 0x01, 0x19, 0x01, 0x00]
+        );
+
+        let uuid = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        let mut expected = vec![0x35, 0x11, 0x1c];
+        expected.extend_from_slice(uuid.as_bytes());
+        assert_eq!(encode_pattern(&[uuid]).unwrap(), expected);
+
+        assert_eq!(encode_pattern(&[]).unwrap_err().kind(), ErrorKind::InvalidInput);
+        let too_long = vec![Uuid::from_u16(0x1101); MAX_PATTERN_LEN + 1];
+        assert_eq!(encode_pattern(&too_long).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(encode_pattern(&too_long[1..]).is_ok());
+    }
+
+    #[test]
+    fn attribute_ids() {
+        assert_eq!(encode_attribute_ids(&[ALL_ATTRIBUTES]).unwrap(), [0x35, 0x05, 0x0a, 0x00, 0x00, 0xff, 0xff]);
+        assert_eq!(
+            encode_attribute_ids(&[0x0004..=0x0004, 0x0100..=0x0102]).unwrap(),
+            [0x35, 0x08, 0x09, 0x00, 0x04, 0x0a, 0x01, 0x00, 0x01, 0x02]
+        );
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 0x0002..=0x0001;
+        assert_eq!(
+            encode_attribute_ids(&[empty.clone(), 0x0001..=0x0001]).unwrap(),
+            [0x35, 0x03, 0x09, 0x00, 0x01]
+        );
+        assert_eq!(encode_attribute_ids(&[]).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(encode_attribute_ids(&[empty]).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn continuation() {
+        assert_eq!(parse_continuation(&[0x00]).unwrap(), Vec::<u8>::new());
+        assert_eq!(parse_continuation(&[0x02, 0xab, 0xcd]).unwrap(), [0xab, 0xcd]);
+        assert_eq!(parse_continuation(&[0x01, 0xab, 0xcd]).unwrap(), [0xab]);
+
+        let mut max = vec![MAX_CONTINUATION_LEN as u8];
+        max.extend(0..MAX_CONTINUATION_LEN as u8);
+        assert_eq!(parse_continuation(&max).unwrap(), max[1..]);
+
+        for data in [&[][..], &[0x02, 0xab], &[0x11; 18]] {
+            assert_eq!(sdp_error(parse_continuation(data).unwrap_err()), Some(SdpError::InvalidResponse));
+        }
+
+        let mut params = vec![0xff];
+        push_continuation(&mut params, &[0x01, 0x02]);
+        assert_eq!(params, [0xff, 0x02, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn service_search_rsp() {
+        let rsp = [0x00, 0x03, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x01, 0x07];
+        assert_eq!(parse_service_search_rsp(&rsp).unwrap(), (3, vec![0x00010000, 0x00010001], vec![0x07]));
+
+        let rsp = [0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x02, 0x00];
+        assert_eq!(parse_service_search_rsp(&rsp).unwrap(), (1, vec![0x00010002], vec![]));
+
+        // Truncated handle list, and partial response without progress.
+        for rsp in [&[0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00][..], &[0x00, 0x03, 0x00, 0x00, 0x01, 0x07]] {
+            assert_eq!(sdp_error(parse_service_search_rsp(rsp).unwrap_err()), Some(SdpError::InvalidResponse));
+        }
+    }
+
+    #[test]
+    fn attribute_rsp() {
+        let rsp = [0x00, 0x03, 0x35, 0x05, 0x09, 0x02, 0xaa, 0xbb];
+        let (list, cont) = parse_attribute_rsp(&rsp).unwrap();
+        assert_eq!((list, cont), (&[0x35, 0x05, 0x09][..], vec![0xaa, 0xbb]));
+
+        let (list, cont) = parse_attribute_rsp(&[0x00, 0x00, 0x00]).unwrap();
+        assert_eq!((list, cont), (&[][..], vec![]));
+
+        for rsp in [&[0x00][..], &[0x00, 0x04, 0x35, 0x02, 0x00], &[0x00, 0x00, 0x01, 0x07]] {
+            assert_eq!(sdp_error(parse_attribute_rsp(rsp).unwrap_err()), Some(SdpError::InvalidResponse));
+        }
+    }
+}
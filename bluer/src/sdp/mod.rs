@@ -0,0 +1,924 @@
+//! Service Discovery Protocol (SDP) records.
+//!
+//! A [service record](ServiceRecord) consists of attributes, each identified by a
+//! 16-bit attribute id and holding a [data element](DataElement).
+//! The universal attributes are defined in the Bluetooth Core Specification, Vol 3, Part B.
+//!
+//! Use the typed accessors of [ServiceRecord] to build a record and [ServiceRecord::to_xml]
+//! to convert it into the XML format used by the Bluetooth daemon, for example for
+//! [Profile::service_record](crate::rfcomm::Profile::service_record).
+//! [ServiceRecord::from_xml] parses a record given in that format.
+//!
+//! [ServiceRecord::decode] and [ServiceRecord::encode] convert a record from and into
+//! the binary attribute list format used by the Service Discovery Protocol.
+//! The [Client] queries the SDP server of a remote device.
+
+use std::{collections::BTreeMap, fmt, io};
+use uuid::Uuid;
+
+use crate::UuidExt;
+
+#[cfg(feature = "l2cap")]
+mod client;
+mod xml;
+
+#[cfg(feature = "l2cap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "l2cap")))]
+pub use client::*;
+
+/// Service record handle attribute.
+pub const ATTR_SERVICE_RECORD_HANDLE: u16 = 0x0000;
+/// Service class id list attribute.
+pub const ATTR_SERVICE_CLASS_ID_LIST: u16 = 0x0001;
+/// Service record state attribute.
+pub const ATTR_SERVICE_RECORD_STATE: u16 = 0x0002;
+/// Service id attribute.
+pub const ATTR_SERVICE_ID: u16 = 0x0003;
+/// Protocol descriptor list attribute.
+pub const ATTR_PROTOCOL_DESCRIPTOR_LIST: u16 = 0x0004;
+/// Browse group list attribute.
+pub const ATTR_BROWSE_GROUP_LIST: u16 = 0x0005;
+/// Language base attribute id list attribute.
+pub const ATTR_LANGUAGE_BASE_ATTRIBUTE_ID_LIST: u16 = 0x0006;
+/// Service info time to live attribute.
+pub const ATTR_SERVICE_INFO_TIME_TO_LIVE: u16 = 0x0007;
+/// Service availability attribute.
+pub const ATTR_SERVICE_AVAILABILITY: u16 = 0x0008;
+/// Bluetooth profile descriptor list attribute.
+pub const ATTR_PROFILE_DESCRIPTOR_LIST: u16 = 0x0009;
+/// Documentation URL attribute.
+pub const ATTR_DOCUMENTATION_URL: u16 = 0x000a;
+/// Client executable URL attribute.
+pub const ATTR_CLIENT_EXECUTABLE_URL: u16 = 0x000b;
+/// Icon URL attribute.
+pub const ATTR_ICON_URL: u16 = 0x000c;
+/// Additional protocol descriptor lists attribute.
+pub const ATTR_ADDITIONAL_PROTOCOL_DESCRIPTOR_LISTS: u16 = 0x000d;
+/// Service name attribute in the primary language.
+pub const ATTR_SERVICE_NAME: u16 = 0x0100;
+/// Service description attribute in the primary language.
+pub const ATTR_SERVICE_DESCRIPTION: u16 = 0x0101;
+/// Provider name attribute in the primary language.
+pub const ATTR_PROVIDER_NAME: u16 = 0x0102;
+/// GOEP L2CAP PSM attribute of OBEX based profiles.
+pub const ATTR_GOEP_L2CAP_PSM: u16 = 0x0200;
+/// Supported features attribute of many profiles.
+pub const ATTR_SUPPORTED_FEATURES: u16 = 0x0311;
+
+/// SDP protocol UUID.
+pub const PROTOCOL_SDP: u16 = 0x0001;
+/// RFCOMM protocol UUID.
+pub const PROTOCOL_RFCOMM: u16 = 0x0003;
+/// OBEX protocol UUID.
+pub const PROTOCOL_OBEX: u16 = 0x0008;
+/// BNEP protocol UUID.
+pub const PROTOCOL_BNEP: u16 = 0x000f;
+/// AVCTP protocol UUID.
+pub const PROTOCOL_AVCTP: u16 = 0x0017;
+/// AVDTP protocol UUID.
+pub const PROTOCOL_AVDTP: u16 = 0x0019;
+/// L2CAP protocol UUID.
+pub const PROTOCOL_L2CAP: u16 = 0x0100;
+
+/// Public browse root group UUID.
+pub const PUBLIC_BROWSE_GROUP: u16 = 0x1002;
+
+/// SDP error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SdpError {
+    /// XML document is malformed.
+    InvalidXml {
+        /// Byte offset of the error within the document.
+        offset: usize,
+        /// Description of the error.
+        reason: String,
+    },
+    /// XML element does not describe a data element.
+    UnknownElement(String),
+    /// Value of an XML element is invalid for its type.
+    InvalidValue {
+        /// XML element name.
+        element: String,
+        /// Value.
+        value: String,
+    },
+    /// Attribute is specified more than once.
+    DuplicateAttribute(u16),
+    /// Required attribute is missing.
+    MissingAttribute(u16),
+    /// Attribute value does not have the structure required by the attribute id.
+    InvalidAttribute(u16),
+    /// Binary data element at the specified offset exceeds the end of the data.
+    Truncated {
+        /// Offset of the data element.
+        offset: usize,
+    },
+    /// Binary data element descriptor is invalid.
+    InvalidDescriptor {
+        /// Offset of the data element.
+        offset: usize,
+        /// Data element descriptor.
+        descriptor: u8,
+    },
+    /// Binary data elements are nested too deeply.
+    NestingTooDeep {
+        /// Offset of the data element.
+        offset: usize,
+    },
+    /// Attribute list is not a sequence of attribute id and value pairs.
+    InvalidAttributeList,
+    /// Error response from the SDP server with the specified error code.
+    ErrorResponse(u16),
+    /// Response from the SDP server is malformed.
+    InvalidResponse,
+}
+
+impl fmt::Display for SdpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidXml { offset, reason } => write!(f, "invalid XML at offset {offset}: {reason}"),
+            Self::UnknownElement(element) => write!(f, "unknown data element <{element}>"),
+            Self::InvalidValue { element, value } => write!(f, "invalid value {value:?} for <{element}>"),
+            Self::DuplicateAttribute(id) => write!(f, "attribute 0x{id:04x} is specified more than once"),
+            Self::MissingAttribute(id) => write!(f, "attribute 0x{id:04x} is missing"),
+            Self::InvalidAttribute(id) => write!(f, "attribute 0x{id:04x} has invalid structure"),
+            Self::Truncated { offset } => write!(f, "data element at offset {offset} is truncated"),
+            Self::InvalidDescriptor { offset, descriptor } => {
+                write!(f, "data element at offset {offset} has invalid descriptor 0x{descriptor:02x}")
+            }
+            Self::NestingTooDeep { offset } => write!(f, "data element at offset {offset} is nested too deeply"),
+            Self::InvalidAttributeList => write!(f, "invalid attribute list"),
+            Self::ErrorResponse(code) => write!(f, "SDP server returned error 0x{code:04x}"),
+            Self::InvalidResponse => write!(f, "invalid response from SDP server"),
+        }
+    }
+}
+
+impl std::error::Error for SdpError {}
+
+impl From<SdpError> for io::Error {
+    fn from(err: SdpError) -> Self {
+        let kind = match err {
+            SdpError::ErrorResponse(_) => io::ErrorKind::Other,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Maximum nesting depth of binary data elements.
+const MAX_DEPTH: usize = 32;
+
+/// SDP data element.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataElement {
+    /// Null.
+    Nil,
+    /// Unsigned 8-bit integer.
+    U8(u8),
+    /// Unsigned 16-bit integer.
+    U16(u16),
+    /// Unsigned 32-bit integer.
+    U32(u32),
+    /// Unsigned 64-bit integer.
+    U64(u64),
+    /// Unsigned 128-bit integer.
+    U128(u128),
+    /// Signed 8-bit integer.
+    I8(i8),
+    /// Signed 16-bit integer.
+    I16(i16),
+    /// Signed 32-bit integer.
+    I32(i32),
+    /// Signed 64-bit integer.
+    I64(i64),
+    /// Signed 128-bit integer.
+    I128(i128),
+    /// UUID.
+    ///
+    /// It is encoded in the shortest form possible.
+    Uuid(Uuid),
+    /// Text string.
+    ///
+    /// The encoding is not specified, but usually UTF-8.
+    Text(Vec<u8>),
+    /// Boolean.
+    Bool(bool),
+    /// Sequence of data elements.
+    Sequence(Vec<DataElement>),
+    /// Alternative of data elements, from which one is to be selected.
+    Alternative(Vec<DataElement>),
+    /// URL.
+    Url(String),
+}
+
+impl DataElement {
+    /// Text string data element.
+    pub fn text(text: impl AsRef<str>) -> Self {
+        Self::Text(text.as_ref().as_bytes().to_vec())
+    }
+
+    /// UUID data element from a 16-bit short UUID.
+    pub fn uuid16(uuid: u16) -> Self {
+        Self::Uuid(Uuid::from_u16(uuid))
+    }
+
+    /// The value, if this is an unsigned integer of any size that fits into 32 bits.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Self::U8(v) => Some(*v as u32),
+            Self::U16(v) => Some(*v as u32),
+            Self::U32(v) => Some(*v),
+            Self::U64(v) => u32::try_from(*v).ok(),
+            Self::U128(v) => u32::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    /// The UUID, if this is a UUID.
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        match self {
+            Self::Uuid(uuid) => Some(*uuid),
+            _ => None,
+        }
+    }
+
+    /// The text, if this is a text string.
+    ///
+    /// Trailing zero bytes are removed and invalid UTF-8 is replaced.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Self::Text(text) => {
+                let len = text.iter().rposition(|&b| b != 0).map(|p| p + 1).unwrap_or_default();
+                Some(String::from_utf8_lossy(&text[..len]).into_owned())
+            }
+            _ => None,
+        }
+    }
+
+    /// The contained data elements, if this is a sequence or an alternative.
+    pub fn as_sequence(&self) -> Option<&[DataElement]> {
+        match self {
+            Self::Sequence(elems) | Self::Alternative(elems) => Some(elems),
+            _ => None,
+        }
+    }
+
+    /// Decodes a data element from its binary representation.
+    ///
+    /// Returns the data element and the number of bytes consumed.
+    pub fn decode(data: &[u8]) -> Result<(Self, usize), SdpError> {
+        Self::decode_at(data, 0, 0)
+    }
+
+    /// Decodes the data element at the specified offset and returns it together with its end offset.
+    fn decode_at(data: &[u8], offset: usize, depth: usize) -> Result<(Self, usize), SdpError> {
+        let truncated = SdpError::Truncated { offset };
+        let descriptor = *data.get(offset).ok_or(truncated.clone())?;
+        let (ty, size_index) = (descriptor >> 3, descriptor & 0x07);
+        if !matches!((ty, size_index), (0, 0) | (1 | 2, 0..=4) | (3, 1 | 2 | 4) | (4 | 6 | 7 | 8, 5..=7) | (5, 0))
+        {
+            return Err(SdpError::InvalidDescriptor { offset, descriptor });
+        }
+
+        let mut pos = offset + 1;
+        let len = match size_index {
+            0..=4 => 1 << size_index,
+            _ => {
+                let n = 1 << (size_index - 5);
+                let bytes = data.get(pos..pos + n).ok_or(truncated.clone())?;
+                pos += n;
+                bytes.iter().fold(0usize, |len, &b| len << 8 | b as usize)
+            }
+        };
+        let len = if ty == 0 { 0 } else { len };
+        let end = pos.checked_add(len).ok_or(truncated.clone())?;
+        let value = data.get(pos..end).ok_or(truncated)?;
+
+        let elem = match (ty, size_index) {
+            (0, 0) => Self::Nil,
+            (1, 0) => Self::U8(value[0]),
+            (1, 1) => Self::U16(u16::from_be_bytes(value.try_into().unwrap())),
+            (1, 2) => Self::U32(u32::from_be_bytes(value.try_into().unwrap())),
+            (1, 3) => Self::U64(u64::from_be_bytes(value.try_into().unwrap())),
+            (1, 4) => Self::U128(u128::from_be_bytes(value.try_into().unwrap())),
+            (2, 0) => Self::I8(value[0] as i8),
+            (2, 1) => Self::I16(i16::from_be_bytes(value.try_into().unwrap())),
+            (2, 2) => Self::I32(i32::from_be_bytes(value.try_into().unwrap())),
+            (2, 3) => Self::I64(i64::from_be_bytes(value.try_into().unwrap())),
+            (2, 4) => Self::I128(i128::from_be_bytes(value.try_into().unwrap())),
+            (3, 1) => Self::Uuid(Uuid::from_u16(u16::from_be_bytes(value.try_into().unwrap()))),
+            (3, 2) => Self::Uuid(Uuid::from_u32(u32::from_be_bytes(value.try_into().unwrap()))),
+            (3, 4) => Self::Uuid(Uuid::from_bytes(value.try_into().unwrap())),
+            (4, 5..=7) => Self::Text(value.to_vec()),
+            (5, 0) => Self::Bool(value[0] != 0),
+            (6 | 7, 5..=7) => {
+                if depth >= MAX_DEPTH {
+                    return Err(SdpError::NestingTooDeep { offset });
+                }
+                let mut elems = Vec::new();
+                while pos < end {
+                    let (elem, elem_end) = Self::decode_at(&data[..end], pos, depth + 1)?;
+                    elems.push(elem);
+                    pos = elem_end;
+                }
+                if ty == 6 {
+                    Self::Sequence(elems)
+                } else {
+                    Self::Alternative(elems)
+                }
+            }
+            (8, 5..=7) => Self::Url(String::from_utf8_lossy(value).into_owned()),
+            _ => unreachable!(),
+        };
+
+        Ok((elem, end))
+    }
+
+    /// Encodes the data element into its binary representation.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        fn var(buf: &mut Vec<u8>, ty: u8, data: &[u8]) {
+            let len = data.len();
+            if len <= 0xff {
+                buf.push(ty << 3 | 5);
+                buf.push(len as u8);
+            } else if len <= 0xffff {
+                buf.push(ty << 3 | 6);
+                buf.extend_from_slice(&(len as u16).to_be_bytes());
+            } else {
+                buf.push(ty << 3 | 7);
+                buf.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+            buf.extend_from_slice(data);
+        }
+
+        match self {
+            Self::Nil => buf.push(0x00),
+            Self::U8(v) => buf.extend_from_slice(&[0x08, *v]),
+            Self::U16(v) => {
+                buf.push(0x09);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::U32(v) => {
+                buf.push(0x0a);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::U64(v) => {
+                buf.push(0x0b);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::U128(v) => {
+                buf.push(0x0c);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::I8(v) => buf.extend_from_slice(&[0x10, *v as u8]),
+            Self::I16(v) => {
+                buf.push(0x11);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::I32(v) => {
+                buf.push(0x12);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::I64(v) => {
+                buf.push(0x13);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::I128(v) => {
+                buf.push(0x14);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Self::Uuid(uuid) => match (uuid.as_u16(), uuid.as_u32()) {
+                (Some(short), _) => {
+                    buf.push(0x19);
+                    buf.extend_from_slice(&short.to_be_bytes());
+                }
+                (None, Some(short)) => {
+                    buf.push(0x1a);
+                    buf.extend_from_slice(&short.to_be_bytes());
+                }
+                (None, None) => {
+                    buf.push(0x1c);
+                    buf.extend_from_slice(uuid.as_bytes());
+                }
+            },
+            Self::Text(text) => var(buf, 4, text),
+            Self::Bool(v) => buf.extend_from_slice(&[0x28, *v as u8]),
+            Self::Sequence(elems) | Self::Alternative(elems) => {
+                let mut data = Vec::new();
+                for elem in elems {
+                    elem.encode_into(&mut data);
+                }
+                var(buf, if matches!(self, Self::Sequence(_)) { 6 } else { 7 }, &data);
+            }
+            Self::Url(url) => var(buf, 8, url.as_bytes()),
+        }
+    }
+}
+
+/// Protocol layer of a protocol descriptor list.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolDescriptor {
+    /// Protocol UUID.
+    pub protocol: Uuid,
+    /// Protocol specific parameters.
+    pub params: Vec<DataElement>,
+}
+
+impl ProtocolDescriptor {
+    /// L2CAP protocol layer with optional PSM.
+    pub fn l2cap(psm: Option<u16>) -> Self {
+        Self { protocol: Uuid::from_u16(PROTOCOL_L2CAP), params: psm.into_iter().map(DataElement::U16).collect() }
+    }
+
+    /// RFCOMM protocol layer with the specified server channel.
+    pub fn rfcomm(channel: u8) -> Self {
+        Self { protocol: Uuid::from_u16(PROTOCOL_RFCOMM), params: vec![DataElement::U8(channel)] }
+    }
+
+    /// OBEX protocol layer.
+    pub fn obex() -> Self {
+        Self { protocol: Uuid::from_u16(PROTOCOL_OBEX), params: Vec::new() }
+    }
+
+    fn from_element(elem: &DataElement) -> Option<Self> {
+        let (protocol, params) = elem.as_sequence()?.split_first()?;
+        Some(Self { protocol: protocol.as_uuid()?, params: params.to_vec() })
+    }
+
+    fn to_element(&self) -> DataElement {
+        let mut elems = vec![DataElement::Uuid(self.protocol)];
+        elems.extend(self.params.iter().cloned());
+        DataElement::Sequence(elems)
+    }
+}
+
+/// Bluetooth profile descriptor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProfileDescriptor {
+    /// Profile UUID.
+    pub profile: Uuid,
+    /// Profile version with major version in the high byte and minor version in the low byte.
+    pub version: u16,
+}
+
+impl ProfileDescriptor {
+    fn from_element(elem: &DataElement) -> Option<Self> {
+        match elem.as_sequence()? {
+            [DataElement::Uuid(profile), DataElement::U16(version)] => {
+                Some(Self { profile: *profile, version: *version })
+            }
+            _ => None,
+        }
+    }
+
+    fn to_element(self) -> DataElement {
+        DataElement::Sequence(vec![DataElement::Uuid(self.profile), DataElement::U16(self.version)])
+    }
+}
+
+/// SDP service record.
+///
+/// The typed accessors return [None] or an empty list if the attribute is absent
+/// or does not have the expected structure.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServiceRecord {
+    /// Attributes by attribute id.
+    pub attributes: BTreeMap<u16, DataElement>,
+}
+
+impl ServiceRecord {
+    /// Creates an empty service record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a service record from the XML format used by the Bluetooth daemon.
+    pub fn from_xml(xml: &str) -> Result<Self, SdpError> {
+        xml::parse(xml)
+    }
+
+    /// Converts the service record into the XML format used by the Bluetooth daemon.
+    pub fn to_xml(&self) -> String {
+        xml::write(self)
+    }
+
+    /// Decodes a service record from its binary attribute list.
+    ///
+    /// The attribute list is a sequence of attribute id and value pairs.
+    pub fn decode(data: &[u8]) -> Result<Self, SdpError> {
+        let (elem, len) = DataElement::decode(data)?;
+        if len != data.len() {
+            return Err(SdpError::InvalidAttributeList);
+        }
+        Self::from_attribute_list(&elem)
+    }
+
+    /// Builds a service record from an attribute list data element.
+    pub fn from_attribute_list(elem: &DataElement) -> Result<Self, SdpError> {
+        let DataElement::Sequence(elems) = elem else {
+            return Err(SdpError::InvalidAttributeList);
+        };
+        if elems.len() % 2 != 0 {
+            return Err(SdpError::InvalidAttributeList);
+        }
+
+        let mut record = Self::new();
+        for pair in elems.chunks(2) {
+            let DataElement::U16(id) = &pair[0] else {
+                return Err(SdpError::InvalidAttributeList);
+            };
+            if record.attributes.insert(*id, pair[1].clone()).is_some() {
+                return Err(SdpError::DuplicateAttribute(*id));
+            }
+        }
+        Ok(record)
+    }
+
+    /// Encodes the service record into its binary attribute list.
+    pub fn encode(&self) -> Vec<u8> {
+        let elems =
+            self.attributes.iter().flat_map(|(id, value)| [DataElement::U16(*id), value.clone()]).collect();
+        DataElement::Sequence(elems).encode()
+    }
+
+    /// Attribute value.
+    pub fn attribute(&self, id: u16) -> Option<&DataElement> {
+        self.attributes.get(&id)
+    }
+
+    /// Sets the attribute value.
+    pub fn set_attribute(&mut self, id: u16, value: DataElement) {
+        self.attributes.insert(id, value);
+    }
+
+    fn uuid_list(&self, id: u16) -> Vec<Uuid> {
+        match self.attribute(id).and_then(|elem| elem.as_sequence()) {
+            Some(elems) => elems.iter().filter_map(|elem| elem.as_uuid()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn set_uuid_list(&mut self, id: u16, uuids: &[Uuid]) {
+        self.set_attribute(id, DataElement::Sequence(uuids.iter().cloned().map(DataElement::Uuid).collect()));
+    }
+
+    /// Service record handle assigned by the SDP server.
+    pub fn handle(&self) -> Option<u32> {
+        match self.attribute(ATTR_SERVICE_RECORD_HANDLE) {
+            Some(DataElement::U32(handle)) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    /// Service classes the service is an instance of, most specific first.
+    pub fn service_class_ids(&self) -> Vec<Uuid> {
+        self.uuid_list(ATTR_SERVICE_CLASS_ID_LIST)
+    }
+
+    /// Sets the service classes the service is an instance of, most specific first.
+    pub fn set_service_class_ids(&mut self, uuids: &[Uuid]) {
+        self.set_uuid_list(ATTR_SERVICE_CLASS_ID_LIST, uuids)
+    }
+
+    /// Protocol stack used to access the service, lowest layer first.
+    pub fn protocol_descriptors(&self) -> Vec<ProtocolDescriptor> {
+        match self.attribute(ATTR_PROTOCOL_DESCRIPTOR_LIST) {
+            Some(DataElement::Sequence(elems)) => {
+                elems.iter().filter_map(ProtocolDescriptor::from_element).collect()
+            }
+            // Use first alternative.
+            Some(DataElement::Alternative(alts)) => match alts.first() {
+                Some(DataElement::Sequence(elems)) => {
+                    elems.iter().filter_map(ProtocolDescriptor::from_element).collect()
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets the protocol stack used to access the service, lowest layer first.
+    pub fn set_protocol_descriptors(&mut self, protocols: &[ProtocolDescriptor]) {
+        self.set_attribute(
+            ATTR_PROTOCOL_DESCRIPTOR_LIST,
+            DataElement::Sequence(protocols.iter().map(|p| p.to_element()).collect()),
+        );
+    }
+
+    /// RFCOMM server channel from the protocol descriptor list.
+    pub fn rfcomm_channel(&self) -> Option<u8> {
+        self.protocol_descriptors().into_iter().find(|p| p.protocol == Uuid::from_u16(PROTOCOL_RFCOMM)).and_then(
+            |p| match p.params.first() {
+                Some(DataElement::U8(channel)) => Some(*channel),
+                _ => None,
+            },
+        )
+    }
+
+    /// L2CAP PSM from the protocol descriptor list.
+    ///
+    /// If the protocol descriptor list does not specify a PSM, the
+    /// [GOEP L2CAP PSM](ATTR_GOEP_L2CAP_PSM) is returned, if present.
+    pub fn l2cap_psm(&self) -> Option<u16> {
+        let psm = self
+            .protocol_descriptors()
+            .into_iter()
+            .find(|p| p.protocol == Uuid::from_u16(PROTOCOL_L2CAP))
+            .and_then(|p| match p.params.first() {
+                Some(DataElement::U16(psm)) => Some(*psm),
+                _ => None,
+            });
+        psm.or(match self.attribute(ATTR_GOEP_L2CAP_PSM) {
+            Some(DataElement::U16(psm)) => Some(*psm),
+            _ => None,
+        })
+    }
+
+    /// Browse groups the service belongs to.
+    pub fn browse_groups(&self) -> Vec<Uuid> {
+        self.uuid_list(ATTR_BROWSE_GROUP_LIST)
+    }
+
+    /// Sets the browse groups the service belongs to.
+    ///
+    /// Services must be in the [public browse group](PUBLIC_BROWSE_GROUP) to be found by browsing.
+    pub fn set_browse_groups(&mut self, uuids: &[Uuid]) {
+        self.set_uuid_list(ATTR_BROWSE_GROUP_LIST, uuids)
+    }
+
+    /// Bluetooth profiles the service conforms to.
+    pub fn profile_descriptors(&self) -> Vec<ProfileDescriptor> {
+        match self.attribute(ATTR_PROFILE_DESCRIPTOR_LIST).and_then(|elem| elem.as_sequence()) {
+            Some(elems) => elems.iter().filter_map(ProfileDescriptor::from_element).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Sets the Bluetooth profiles the service conforms to.
+    pub fn set_profile_descriptors(&mut self, profiles: &[ProfileDescriptor]) {
+        self.set_attribute(
+            ATTR_PROFILE_DESCRIPTOR_LIST,
+            DataElement::Sequence(profiles.iter().map(|p| p.to_element()).collect()),
+        );
+    }
+
+    /// Service name in the primary language.
+    pub fn service_name(&self) -> Option<String> {
+        self.attribute(ATTR_SERVICE_NAME).and_then(|elem| elem.as_text())
+    }
+
+    /// Sets the service name in the primary language.
+    pub fn set_service_name(&mut self, name: &str) {
+        self.set_attribute(ATTR_SERVICE_NAME, DataElement::text(name))
+    }
+
+    /// Profile specific supported features.
+    pub fn supported_features(&self) -> Option<u16> {
+        match self.attribute(ATTR_SUPPORTED_FEATURES) {
+            Some(DataElement::U16(features)) => Some(*features),
+            _ => None,
+        }
+    }
+
+    /// Sets the profile specific supported features.
+    pub fn set_supported_features(&mut self, features: u16) {
+        self.set_attribute(ATTR_SUPPORTED_FEATURES, DataElement::U16(features))
+    }
+
+    /// Checks that the service class id list is present and that all
+    /// universal attributes have the structure required by the specification.
+    pub fn validate(&self) -> Result<(), SdpError> {
+        let is_uuid_list = |elem: &DataElement| match elem {
+            DataElement::Sequence(elems) => elems.iter().all(|elem| elem.as_uuid().is_some()),
+            _ => false,
+        };
+        let is_protocol_list = |elem: &DataElement| match elem {
+            DataElement::Sequence(elems) => {
+                elems.iter().all(|elem| ProtocolDescriptor::from_element(elem).is_some())
+            }
+            _ => false,
+        };
+
+        match self.attribute(ATTR_SERVICE_CLASS_ID_LIST) {
+            None => return Err(SdpError::MissingAttribute(ATTR_SERVICE_CLASS_ID_LIST)),
+            Some(elem) if !is_uuid_list(elem) || self.service_class_ids().is_empty() => {
+                return Err(SdpError::InvalidAttribute(ATTR_SERVICE_CLASS_ID_LIST))
+            }
+            Some(_) => (),
+        }
+
+        for (&id, elem) in &self.attributes {
+            let valid = match id {
+                ATTR_SERVICE_RECORD_HANDLE | ATTR_SERVICE_RECORD_STATE | ATTR_SERVICE_INFO_TIME_TO_LIVE => {
+                    matches!(elem, DataElement::U32(_))
+                }
+                ATTR_SERVICE_ID => matches!(elem, DataElement::Uuid(_)),
+                ATTR_BROWSE_GROUP_LIST => is_uuid_list(elem),
+                ATTR_PROTOCOL_DESCRIPTOR_LIST => match elem {
+                    DataElement::Alternative(alts) => alts.iter().all(is_protocol_list),
+                    elem => is_protocol_list(elem),
+                },
+                ATTR_ADDITIONAL_PROTOCOL_DESCRIPTOR_LISTS => match elem {
+                    DataElement::Sequence(elems) => elems.iter().all(is_protocol_list),
+                    _ => false,
+                },
+                ATTR_LANGUAGE_BASE_ATTRIBUTE_ID_LIST => match elem {
+                    DataElement::Sequence(elems) => {
+                        elems.len() % 3 == 0 && elems.iter().all(|elem| matches!(elem, DataElement::U16(_)))
+                    }
+                    _ => false,
+                },
+                ATTR_SERVICE_AVAILABILITY => matches!(elem, DataElement::U8(_)),
+                ATTR_PROFILE_DESCRIPTOR_LIST => match elem {
+                    DataElement::Sequence(elems) => {
+                        elems.iter().all(|elem| ProfileDescriptor::from_element(elem).is_some())
+                    }
+                    _ => false,
+                },
+                ATTR_DOCUMENTATION_URL | ATTR_CLIENT_EXECUTABLE_URL | ATTR_ICON_URL => {
+                    matches!(elem, DataElement::Url(_))
+                }
+                _ => true,
+            };
+            if !valid {
+                return Err(SdpError::InvalidAttribute(id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attribute list of a Serial Port service record on RFCOMM channel 1.
+    #[rustfmt::skip]
+    const SERIAL_PORT: [u8; 43] = [
+        0x35, 0x29,
+        0x09, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x01,
+        0x09, 0x00, 0x01, 0x35, 0x03, 0x19, 0x11, 0x01,
+        0x09, 0x00, 0x04, 0x35, 0x0c, 0x35, 0x03, 0x19, 0x01, 0x00, 0x35, 0x05, 0x19, 0x00, 0x03, 0x08, 0x01,
+        0x09, 0x01, 0x00, 0x25, 0x03, b'S', b'P', b'P',
+    ];
+
+    fn roundtrip(data: &[u8], elem: DataElement) {
+        assert_eq!(DataElement::decode(data), Ok((elem.clone(), data.len())));
+        assert_eq!(elem.encode(), data);
+    }
+
+    #[test]
+    fn integers() {
+        roundtrip(&[0x00], DataElement::Nil);
+        roundtrip(&[0x08, 0xab], DataElement::U8(0xab));
+        roundtrip(&[0x09, 0x12, 0x34], DataElement::U16(0x1234));
+        roundtrip(&[0x0a, 0x12, 0x34, 0x56, 0x78], DataElement::U32(0x12345678));
+        roundtrip(&[0x0b, 0, 0, 0, 0, 0, 0, 0x01, 0x00], DataElement::U64(0x100));
+        roundtrip(&[0x0c, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01], DataElement::U128(1));
+        roundtrip(&[0x10, 0xff], DataElement::I8(-1));
+        roundtrip(&[0x11, 0xff, 0xfe], DataElement::I16(-2));
+        roundtrip(&[0x12, 0x80, 0x00, 0x00, 0x00], DataElement::I32(i32::MIN));
+        roundtrip(&[0x13, 0, 0, 0, 0, 0, 0, 0, 0x7f], DataElement::I64(127));
+        roundtrip(
+            &[
+                0x14, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xfd,
+            ],
+            DataElement::I128(-3),
+        );
+        roundtrip(&[0x28, 0x01], DataElement::Bool(true));
+    }
+
+    #[test]
+    fn uuids() {
+        roundtrip(&[0x19, 0x11, 0x01], DataElement::uuid16(0x1101));
+        roundtrip(&[0x1a, 0x12, 0x34, 0x56, 0x78], DataElement::Uuid(Uuid::from_u32(0x12345678)));
+
+        let uuid = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        let mut data = vec![0x1c];
+        data.extend_from_slice(uuid.as_bytes());
+        roundtrip(&data, DataElement::Uuid(uuid));
+
+        // Base UUID given in 128-bit form is encoded in its short form.
+        let mut data = vec![0x1c];
+        data.extend_from_slice(Uuid::from_u16(0x1101).as_bytes());
+        assert_eq!(DataElement::decode(&data).unwrap().0.encode(), [0x19, 0x11, 0x01]);
+    }
+
+    #[test]
+    fn strings() {
+        roundtrip(&[0x25, 0x03, b'S', b'P', b'P'], DataElement::text("SPP"));
+        roundtrip(&[0x45, 0x03, b'a', b':', b'b'], DataElement::Url("a:b".to_string()));
+
+        let text = vec![b'x'; 0x100];
+        let mut data = vec![0x26, 0x01, 0x00];
+        data.extend_from_slice(&text);
+        roundtrip(&data, DataElement::Text(text));
+
+        // 32-bit length is decoded, but the shortest length is used for encoding.
+        let (elem, len) = DataElement::decode(&[0x27, 0x00, 0x00, 0x00, 0x02, b'h', b'i']).unwrap();
+        assert_eq!((elem.clone(), len), (DataElement::text("hi"), 7));
+        assert_eq!(elem.encode(), [0x25, 0x02, b'h', b'i']);
+
+        assert_eq!(DataElement::Text(b"name\0\0".to_vec()).as_text(), Some("name".to_string()));
+    }
+
+    #[test]
+    fn sequences() {
+        roundtrip(
+            &[0x35, 0x06, 0x19, 0x01, 0x00, 0x09, 0x00, 0x19],
+            DataElement::Sequence(vec![DataElement::uuid16(PROTOCOL_L2CAP), DataElement::U16(0x0019)]),
+        );
+        roundtrip(
+            &[0x3d, 0x04, 0x35, 0x00, 0x28, 0x00],
+            DataElement::Alternative(vec![DataElement::Sequence(vec![]), DataElement::Bool(false)]),
+        );
+
+        // Trailing data is not consumed.
+        assert_eq!(DataElement::decode(&[0x08, 0x01, 0x08]), Ok((DataElement::U8(1), 2)));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(DataElement::decode(&[]), Err(SdpError::Truncated { offset: 0 }));
+        assert_eq!(DataElement::decode(&[0x09, 0x01]), Err(SdpError::Truncated { offset: 0 }));
+        assert_eq!(DataElement::decode(&[0x26, 0x01]), Err(SdpError::Truncated { offset: 0 }));
+        assert_eq!(DataElement::decode(&[0x35, 0x03, 0x08, 0x01, 0x09]), Err(SdpError::Truncated { offset: 4 }));
+        assert_eq!(
+            DataElement::decode(&[0x35, 0x02, 0x1b, 0x00]),
+            Err(SdpError::InvalidDescriptor { offset: 2, descriptor: 0x1b })
+        );
+        assert_eq!(
+            DataElement::decode(&[0x0d]),
+            Err(SdpError::InvalidDescriptor { offset: 0, descriptor: 0x0d })
+        );
+        assert_eq!(
+            DataElement::decode(&[0x48]),
+            Err(SdpError::InvalidDescriptor { offset: 0, descriptor: 0x48 })
+        );
+
+        let depth = MAX_DEPTH + 1;
+        let data: Vec<u8> = (0..depth).flat_map(|i| [0x35, (2 * (depth - i - 1)) as u8]).collect();
+        assert_eq!(DataElement::decode(&data), Err(SdpError::NestingTooDeep { offset: 2 * MAX_DEPTH }));
+        assert!(DataElement::decode(&data[2..]).is_ok());
+    }
+
+    #[test]
+    fn serial_port_record() {
+        let record = ServiceRecord::decode(&SERIAL_PORT).unwrap();
+        assert_eq!(record.handle(), Some(0x00010001));
+        assert_eq!(record.service_class_ids(), [Uuid::from_u16(0x1101)]);
+        assert_eq!(
+            record.protocol_descriptors(),
+            [ProtocolDescriptor::l2cap(None), ProtocolDescriptor::rfcomm(1)]
+        );
+        assert_eq!(record.rfcomm_channel(), Some(1));
+        assert_eq!(record.l2cap_psm(), None);
+        assert_eq!(record.service_name(), Some("SPP".to_string()));
+        assert_eq!(record.validate(), Ok(()));
+        assert_eq!(record.encode(), SERIAL_PORT);
+
+        let mut built = ServiceRecord::new();
+        built.set_attribute(ATTR_SERVICE_RECORD_HANDLE, DataElement::U32(0x00010001));
+        built.set_service_class_ids(&[Uuid::from_u16(0x1101)]);
+        built.set_protocol_descriptors(&[ProtocolDescriptor::l2cap(None), ProtocolDescriptor::rfcomm(1)]);
+        built.set_service_name("SPP");
+        assert_eq!(built, record);
+    }
+
+    #[test]
+    fn record_errors() {
+        assert_eq!(ServiceRecord::decode(&[0x08, 0x01]), Err(SdpError::InvalidAttributeList));
+        assert_eq!(ServiceRecord::decode(&[0x35, 0x03, 0x09, 0x00, 0x01]), Err(SdpError::InvalidAttributeList));
+        assert_eq!(ServiceRecord::decode(&[0x35, 0x00, 0x00]), Err(SdpError::InvalidAttributeList));
+        assert_eq!(
+            ServiceRecord::decode(&[0x35, 0x04, 0x08, 0x01, 0x08, 0x02]),
+            Err(SdpError::InvalidAttributeList)
+        );
+        assert_eq!(
+            ServiceRecord::decode(&[0x35, 0x0a, 0x09, 0x01, 0x00, 0x08, 0x01, 0x09, 0x01, 0x00, 0x08, 0x02]),
+            Err(SdpError::DuplicateAttribute(0x0100))
+        );
+
+        let mut record = ServiceRecord::new();
+        assert_eq!(record.validate(), Err(SdpError::MissingAttribute(ATTR_SERVICE_CLASS_ID_LIST)));
+        record.set_attribute(ATTR_SERVICE_CLASS_ID_LIST, DataElement::Sequence(vec![DataElement::U8(1)]));
+        assert_eq!(record.validate(), Err(SdpError::InvalidAttribute(ATTR_SERVICE_CLASS_ID_LIST)));
+        record.set_service_class_ids(&[Uuid::from_u16(0x1101)]);
+        record.set_attribute(ATTR_SERVICE_RECORD_HANDLE, DataElement::U16(1));
+        assert_eq!(record.validate(), Err(SdpError::InvalidAttribute(ATTR_SERVICE_RECORD_HANDLE)));
+    }
+}
@@ -0,0 +1,578 @@
+//! XML format of service records used by the Bluetooth daemon.
+
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::{DataElement, SdpError, ServiceRecord};
+use crate::UuidExt;
+
+/// Converts a service record into XML.
+pub(super) fn write(record: &ServiceRecord) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n\n<record>\n");
+    for (id, elem) in &record.attributes {
+        let _ = writeln!(out, "\t<attribute id=\"0x{id:04x}\">");
+        write_element(&mut out, elem, 2);
+        out.push_str("\t</attribute>\n");
+    }
+    out.push_str("</record>\n");
+    out
+}
+
+fn write_element(out: &mut String, elem: &DataElement, depth: usize) {
+    let indent = "\t".repeat(depth);
+    let mut value = |name: &str, value: &str| {
+        let _ = writeln!(out, "{indent}<{name} value=\"{}\" />", escape(value));
+    };
+    match elem {
+        DataElement::Nil => {
+            let _ = writeln!(out, "{indent}<nil />");
+        }
+        DataElement::U8(v) => value("uint8", &format!("0x{v:02x}")),
+        DataElement::U16(v) => value("uint16", &format!("0x{v:04x}")),
+        DataElement::U32(v) => value("uint32", &format!("0x{v:08x}")),
+        DataElement::U64(v) => value("uint64", &format!("0x{v:016x}")),
+        DataElement::U128(v) => value("uint128", &format!("0x{v:032x}")),
+        DataElement::I8(v) => value("int8", &v.to_string()),
+        DataElement::I16(v) => value("int16", &v.to_string()),
+        DataElement::I32(v) => value("int32", &v.to_string()),
+        DataElement::I64(v) => value("int64", &v.to_string()),
+        DataElement::I128(v) => value("int128", &format!("0x{:032x}", *v as u128)),
+        DataElement::Uuid(uuid) => match (uuid.as_u16(), uuid.as_u32()) {
+            (Some(short), _) => value("uuid", &format!("0x{short:04x}")),
+            (None, Some(short)) => value("uuid", &format!("0x{short:08x}")),
+            (None, None) => value("uuid", &uuid.to_string()),
+        },
+        DataElement::Text(text) => {
+            if text.iter().all(|b| (0x20..0x7f).contains(b)) {
+                value("text", &String::from_utf8_lossy(text))
+            } else {
+                let hex: String = text.iter().map(|b| format!("{b:02x}")).collect();
+                let _ = writeln!(out, "{indent}<text encoding=\"hex\" value=\"{hex}\" />");
+            }
+        }
+        DataElement::Bool(v) => value("boolean", if *v { "true" } else { "false" }),
+        DataElement::Url(url) => value("url", url),
+        DataElement::Sequence(elems) | DataElement::Alternative(elems) => {
+            let name = if matches!(elem, DataElement::Sequence(_)) { "sequence" } else { "alternate" };
+            let _ = writeln!(out, "{indent}<{name}>");
+            for elem in elems {
+                write_element(out, elem, depth + 1);
+            }
+            let _ = writeln!(out, "{indent}</{name}>");
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// XML element.
+struct Node {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+    offset: usize,
+}
+
+impl Node {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Minimal XML parser sufficient for service records.
+struct Parser<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn err(&self, reason: impl Into<String>) -> SdpError {
+        SdpError::InvalidXml { offset: self.pos, reason: reason.into() }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_until(&mut self, end: &str) -> Result<(), SdpError> {
+        match self.rest().find(end) {
+            Some(p) => {
+                self.pos += p + end.len();
+                Ok(())
+            }
+            None => Err(self.err(format!("missing {end}"))),
+        }
+    }
+
+    /// Skips whitespace, character data, comments, processing instructions and declarations.
+    fn skip_misc(&mut self) -> Result<(), SdpError> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if rest.starts_with("<!") {
+                self.skip_until(">")?;
+            } else if rest.starts_with('<') || rest.is_empty() {
+                return Ok(());
+            } else {
+                self.pos += rest.find('<').unwrap_or(rest.len());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, SdpError> {
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.err("expected name"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), SdpError> {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            Ok(())
+        } else {
+            Err(self.err(format!("expected {s}")))
+        }
+    }
+
+    fn attr_value(&mut self) -> Result<String, SdpError> {
+        let quote = match self.rest().chars().next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Err(self.err("expected quoted attribute value")),
+        };
+        self.pos += 1;
+        let len = self.rest().find(quote).ok_or_else(|| self.err("unterminated attribute value"))?;
+        let value = unescape(&self.rest()[..len]).ok_or_else(|| self.err("invalid entity reference"))?;
+        self.pos += len + 1;
+        Ok(value)
+    }
+
+    fn element(&mut self) -> Result<Node, SdpError> {
+        let offset = self.pos;
+        self.expect("<")?;
+        let name = self.name()?;
+        let mut node = Node { name, attrs: Vec::new(), children: Vec::new(), offset };
+
+        loop {
+            self.skip_ws();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(node);
+            } else if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let attr_name = self.name()?;
+            self.skip_ws();
+            self.expect("=")?;
+            self.skip_ws();
+            let value = self.attr_value()?;
+            node.attrs.push((attr_name, value));
+        }
+
+        loop {
+            self.skip_misc()?;
+            if self.rest().is_empty() {
+                return Err(self.err(format!("missing </{}>", &node.name)));
+            } else if self.rest().starts_with("</") {
+                self.pos += 2;
+                let end_name = self.name()?;
+                if end_name != node.name {
+                    return Err(self.err(format!("expected </{}>", &node.name)));
+                }
+                self.skip_ws();
+                self.expect(">")?;
+                return Ok(node);
+            }
+            node.children.push(self.element()?);
+        }
+    }
+
+    fn document(&mut self) -> Result<Node, SdpError> {
+        self.skip_misc()?;
+        if self.rest().is_empty() {
+            return Err(self.err("missing root element"));
+        }
+        let root = self.element()?;
+        loop {
+            self.skip_ws();
+            if self.rest().starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_until("?>")?;
+            } else {
+                break;
+            }
+        }
+        if !self.rest().is_empty() {
+            return Err(self.err("content after root element"));
+        }
+        Ok(root)
+    }
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(p) = rest.find('&') {
+        out.push_str(&rest[..p]);
+        rest = &rest[p + 1..];
+        let end = rest.find(';')?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+fn strip_hex_prefix(s: &str) -> Option<&str> {
+    s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))
+}
+
+fn parse_uint(s: &str) -> Option<u128> {
+    match strip_hex_prefix(s) {
+        Some(hex) => u128::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parses a signed integer of the specified bit width.
+///
+/// Hexadecimal values specify the two's complement representation.
+fn parse_int(s: &str, bits: u32) -> Option<i128> {
+    match strip_hex_prefix(s) {
+        Some(hex) => {
+            let v = u128::from_str_radix(hex, 16).ok()?;
+            if bits < 128 && v >> bits != 0 {
+                return None;
+            }
+            let shift = 128 - bits;
+            Some(((v << shift) as i128) >> shift)
+        }
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_uuid(s: &str) -> Option<Uuid> {
+    match strip_hex_prefix(s) {
+        Some(hex) if hex.len() <= 4 => Some(Uuid::from_u16(u16::from_str_radix(hex, 16).ok()?)),
+        Some(hex) if hex.len() <= 8 => Some(Uuid::from_u32(u32::from_str_radix(hex, 16).ok()?)),
+        Some(hex) => Some(Uuid::from_u128(u128::from_str_radix(hex, 16).ok()?)),
+        None => Uuid::parse_str(s).ok(),
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+fn element(node: &Node) -> Result<DataElement, SdpError> {
+    let value = || {
+        node.attr("value").map(|v| v.trim()).ok_or_else(|| SdpError::InvalidXml {
+            offset: node.offset,
+            reason: format!("<{}> has no value attribute", &node.name),
+        })
+    };
+    let invalid = |value: &str| SdpError::InvalidValue { element: node.name.clone(), value: value.to_string() };
+    let uint = |value: &str| parse_uint(value).ok_or_else(|| invalid(value));
+    let int = |value: &str, bits| parse_int(value, bits).ok_or_else(|| invalid(value));
+
+    let elem = match node.name.as_str() {
+        "nil" => DataElement::Nil,
+        "boolean" => {
+            let v = value()?;
+            match v.to_ascii_lowercase().as_str() {
+                "true" => DataElement::Bool(true),
+                "false" => DataElement::Bool(false),
+                _ => return Err(invalid(v)),
+            }
+        }
+        "uint8" => {
+            let v = value()?;
+            DataElement::U8(uint(v)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "uint16" => {
+            let v = value()?;
+            DataElement::U16(uint(v)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "uint32" => {
+            let v = value()?;
+            DataElement::U32(uint(v)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "uint64" => {
+            let v = value()?;
+            DataElement::U64(uint(v)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "uint128" => DataElement::U128(uint(value()?)?),
+        "int8" => {
+            let v = value()?;
+            DataElement::I8(int(v, 8)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "int16" => {
+            let v = value()?;
+            DataElement::I16(int(v, 16)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "int32" => {
+            let v = value()?;
+            DataElement::I32(int(v, 32)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "int64" => {
+            let v = value()?;
+            DataElement::I64(int(v, 64)?.try_into().map_err(|_| invalid(v))?)
+        }
+        "int128" => DataElement::I128(int(value()?, 128)?),
+        "uuid" => {
+            let v = value()?;
+            DataElement::Uuid(parse_uuid(v).ok_or_else(|| invalid(v))?)
+        }
+        "text" => {
+            let v = node.attr("value").ok_or_else(|| invalid(""))?;
+            match node.attr("encoding") {
+                Some("hex") => DataElement::Text(parse_hex(v.trim()).ok_or_else(|| invalid(v))?),
+                Some(encoding) => return Err(invalid(encoding)),
+                None => DataElement::text(v),
+            }
+        }
+        "url" => DataElement::Url(value()?.to_string()),
+        "sequence" => DataElement::Sequence(node.children.iter().map(element).collect::<Result<_, _>>()?),
+        "alternate" => DataElement::Alternative(node.children.iter().map(element).collect::<Result<_, _>>()?),
+        other => return Err(SdpError::UnknownElement(other.to_string())),
+    };
+    Ok(elem)
+}
+
+/// Parses a service record from XML.
+pub(super) fn parse(xml: &str) -> Result<ServiceRecord, SdpError> {
+    let root = Parser { xml, pos: 0 }.document()?;
+    if root.name != "record" {
+        return Err(SdpError::InvalidXml { offset: root.offset, reason: "root element must be <record>".into() });
+    }
+
+    let mut record = ServiceRecord::new();
+    for attr in &root.children {
+        if attr.name != "attribute" {
+            return Err(SdpError::InvalidXml {
+                offset: attr.offset,
+                reason: format!("expected <attribute> instead of <{}>", &attr.name),
+            });
+        }
+        let id = attr.attr("id").unwrap_or_default();
+        let id: u16 = parse_uint(id.trim())
+            .and_then(|id| id.try_into().ok())
+            .ok_or_else(|| SdpError::InvalidValue { element: attr.name.clone(), value: id.to_string() })?;
+        let value = match attr.children.as_slice() {
+            [value] => element(value)?,
+            _ => {
+                return Err(SdpError::InvalidXml {
+                    offset: attr.offset,
+                    reason: format!("attribute 0x{id:04x} must contain exactly one data element"),
+                })
+            }
+        };
+        if record.attributes.insert(id, value).is_some() {
+            return Err(SdpError::DuplicateAttribute(id));
+        }
+    }
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdp::{ProfileDescriptor, ProtocolDescriptor};
+
+    /// Hands-free unit record in the format written by the Bluetooth daemon.
+    const HANDSFREE: &str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+
+<record>
+	<attribute id="0x0001">
+		<sequence>
+			<uuid value="0x111e" />
+			<uuid value="0x1203" />
+		</sequence>
+	</attribute>
+	<attribute id="0x0004">
+		<sequence>
+			<sequence>
+				<uuid value="0x0100" />
+			</sequence>
+			<sequence>
+				<uuid value="0x0003" />
+				<uint8 value="0x07" />
+			</sequence>
+		</sequence>
+	</attribute>
+	<attribute id="0x0009">
+		<sequence>
+			<sequence>
+				<uuid value="0x111e" />
+				<uint16 value="0x0108" />
+			</sequence>
+		</sequence>
+	</attribute>
+	<attribute id="0x0100">
+		<text value="Hands-Free unit" />
+	</attribute>
+	<attribute id="0x0311">
+		<uint16 value="0x003f" />
+	</attribute>
+</record>
+"#;
+
+    #[test]
+    fn handsfree_record() {
+        let record = ServiceRecord::from_xml(HANDSFREE).unwrap();
+        assert_eq!(record.service_class_ids(), [Uuid::from_u16(0x111e), Uuid::from_u16(0x1203)]);
+        assert_eq!(
+            record.protocol_descriptors(),
+            [ProtocolDescriptor::l2cap(None), ProtocolDescriptor::rfcomm(7)]
+        );
+        assert_eq!(
+            record.profile_descriptors(),
+            [ProfileDescriptor { profile: Uuid::from_u16(0x111e), version: 0x0108 }]
+        );
+        assert_eq!(record.service_name(), Some("Hands-Free unit".to_string()));
+        assert_eq!(record.supported_features(), Some(0x003f));
+        assert_eq!(record.to_xml(), HANDSFREE);
+    }
+
+    #[test]
+    fn all_types() {
+        let uuid = Uuid::parse_str("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        let elems = vec![
+            DataElement::Nil,
+            DataElement::U8(0xff),
+            DataElement::U32(0x00010001),
+            DataElement::U64(u64::MAX),
+            DataElement::U128(1),
+            DataElement::I8(-128),
+            DataElement::I16(-2),
+            DataElement::I32(100),
+            DataElement::I64(i64::MIN),
+            DataElement::I128(-1),
+            DataElement::Uuid(Uuid::from_u32(0x12345678)),
+            DataElement::Uuid(uuid),
+            DataElement::text("<a & \"b\">"),
+            DataElement::Text(vec![0x00, 0xff, b'a']),
+            DataElement::Bool(false),
+            DataElement::Url("http://example.com/?a=1&b=2".to_string()),
+            DataElement::Alternative(vec![DataElement::Sequence(vec![])]),
+        ];
+        let mut record = ServiceRecord::new();
+        for (id, elem) in elems.into_iter().enumerate() {
+            record.set_attribute(0x0200 + id as u16, elem);
+        }
+
+        let xml = record.to_xml();
+        assert!(xml.contains(r#"<text value="&lt;a &amp; &quot;b&quot;&gt;" />"#), "{xml}");
+        assert!(xml.contains(r#"<text encoding="hex" value="00ff61" />"#), "{xml}");
+        assert!(xml.contains(r#"<int128 value="0xffffffffffffffffffffffffffffffff" />"#), "{xml}");
+        assert!(xml.contains(r#"<uuid value="6e400001-b5a3-f393-e0a9-e50e24dcca9e" />"#), "{xml}");
+        assert_eq!(ServiceRecord::from_xml(&xml), Ok(record));
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let xml = r#"<!-- comment --><record>
+            <attribute id='256'><text value=" padded &#x41;&#66;&apos; " /></attribute>
+            <attribute id="0x0200" ><int8 value="0xff"/></attribute>
+            <attribute id="0x0201"><boolean value="TRUE" /></attribute>
+            <attribute id="0x0202"><uuid value="0x1101" /></attribute>
+        </record >
+        <!-- trailing -->"#;
+        let record = ServiceRecord::from_xml(xml).unwrap();
+        assert_eq!(record.attribute(0x0100), Some(&DataElement::text(" padded AB' ")));
+        assert_eq!(record.attribute(0x0200), Some(&DataElement::I8(-1)));
+        assert_eq!(record.attribute(0x0201), Some(&DataElement::Bool(true)));
+        assert_eq!(record.attribute(0x0202), Some(&DataElement::uuid16(0x1101)));
+    }
+
+    #[test]
+    fn errors() {
+        let attr = |elem: &str| format!(r#"<record><attribute id="0x0200">{elem}</attribute></record>"#);
+        let invalid = |element: &str, value: &str| {
+            Err(SdpError::InvalidValue { element: element.to_string(), value: value.to_string() })
+        };
+
+        assert_eq!(ServiceRecord::from_xml(&attr(r#"<uint8 value="0x100" />"#)), invalid("uint8", "0x100"));
+        assert_eq!(ServiceRecord::from_xml(&attr(r#"<int8 value="0x1ff" />"#)), invalid("int8", "0x1ff"));
+        assert_eq!(ServiceRecord::from_xml(&attr(r#"<int16 value="40000" />"#)), invalid("int16", "40000"));
+        assert_eq!(ServiceRecord::from_xml(&attr(r#"<boolean value="yes" />"#)), invalid("boolean", "yes"));
+        assert_eq!(ServiceRecord::from_xml(&attr(r#"<uuid value="xyz" />"#)), invalid("uuid", "xyz"));
+        assert_eq!(
+            ServiceRecord::from_xml(&attr(r#"<text encoding="hex" value="abc" />"#)),
+            invalid("text", "abc")
+        );
+        assert_eq!(
+            ServiceRecord::from_xml(&attr(r#"<float value="1.0" />"#)),
+            Err(SdpError::UnknownElement("float".to_string()))
+        );
+        assert_eq!(
+            ServiceRecord::from_xml(r#"<record><attribute id="0x70000"><nil /></attribute></record>"#),
+            invalid("attribute", "0x70000")
+        );
+        assert_eq!(
+            ServiceRecord::from_xml(
+                r#"<record><attribute id="1"><nil /></attribute><attribute id="0x0001"><nil /></attribute></record>"#
+            ),
+            Err(SdpError::DuplicateAttribute(0x0001))
+        );
+
+        for xml in [
+            "",
+            "<records></records>",
+            "<record><attr id=\"1\"><nil /></attr></record>",
+            "<record><attribute id=\"1\"><nil /><nil /></attribute></record>",
+            "<record><attribute id=\"1\"><uint8 /></attribute></record>",
+            "<record><attribute id=\"1\"><nil /></attribute>",
+            "<record></recor>",
+            "<record x=1></record>",
+            "<record><attribute id=\"1\"><text value=\"&bogus;\" /></attribute></record>",
+            "<record></record><record></record>",
+        ] {
+            assert!(matches!(ServiceRecord::from_xml(xml), Err(SdpError::InvalidXml { .. })), "{xml}");
+        }
+    }
+}
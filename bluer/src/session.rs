@@ -1,7 +1,8 @@
 //! Bluetooth session.
 
 use dbus::{
-    arg::Variant,
+    arg::{IterAppend, PropMap, Variant},
+    channel::Channel,
     message::MatchRule,
     nonblock::{
         stdintf::org_freedesktop_dbus::{
@@ -16,14 +17,17 @@ use dbus_crossroads::{Crossroads, IfaceToken};
 use dbus_tokio::connection;
 use futures::{
     channel::{mpsc, oneshot},
+    future::BoxFuture,
     lock::Mutex,
-    Future, SinkExt, Stream, StreamExt,
+    stream, Future, FutureExt, SinkExt, Stream, StreamExt,
 };
-use lazy_static::lazy_static;
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Debug, Formatter},
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
 };
 use tokio::{
     select,
@@ -34,7 +38,7 @@ use crate::{
     adapter,
     adv::Advertisement,
     agent::{Agent, AgentHandle, RegisteredAgent},
-    all_dbus_objects, gatt,
+    all_dbus_objects, gatt, media,
     monitor::RegisteredMonitor,
     parent_path, Adapter, DiscoveryFilter, Error, ErrorKind, InternalErrorKind, Result, SERVICE_NAME,
 };
@@ -48,9 +52,22 @@ use crate::mesh::{
 #[cfg(feature = "rfcomm")]
 use crate::rfcomm::{profile::RegisteredProfile, Profile, ProfileHandle};
 
+/// D-Bus path namespace of all objects published by BlueR.
+const PUBLISH_NAMESPACE: &str = concat!("/org/bluez/", env!("CARGO_PKG_NAME"));
+
 /// Terminate TX and terminated RX for single session.
 type SingleSessionTerm = (Weak<oneshot::Sender<()>>, oneshot::Receiver<()>);
 
+/// Function registering a published object with the Bluetooth daemon.
+type RegisterFn = Box<dyn Fn(Arc<SyncConnection>) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// Registration of a published object with the Bluetooth daemon.
+struct DaemonRegistration {
+    /// Adapter the object is registered with, if any.
+    adapter_name: Option<String>,
+    register_fn: RegisterFn,
+}
+
 /// Shared state of all objects in a Bluetooth session.
 pub(crate) struct SessionInner {
     pub connection: Arc<SyncConnection>,
@@ -61,6 +78,8 @@ pub(crate) struct SessionInner {
     pub gatt_reg_characteristic_descriptor_token: IfaceToken<Arc<gatt::local::RegisteredDescriptor>>,
     pub gatt_profile_token: IfaceToken<gatt::local::Profile>,
     pub agent_token: IfaceToken<Arc<RegisteredAgent>>,
+    pub media_endpoint_token: IfaceToken<Arc<media::endpoint::RegisteredEndpoint>>,
+    pub media_player_token: IfaceToken<Arc<media::local::RegisteredPlayer>>,
     #[cfg(feature = "mesh")]
     pub application_token: IfaceToken<Arc<RegisteredApplication>>,
     #[cfg(feature = "mesh")]
@@ -74,8 +93,12 @@ pub(crate) struct SessionInner {
     pub profile_token: IfaceToken<Arc<RegisteredProfile>>,
     pub single_sessions: Mutex<HashMap<dbus::Path<'static>, SingleSessionTerm>>,
     pub event_sub_tx: mpsc::Sender<SubscriptionReq>,
-    dbus_task: JoinHandle<connection::IOResourceError>,
+    pub object_cache: Arc<std::sync::Mutex<Option<ObjectCache>>>,
+    dbus_task: Option<JoinHandle<connection::IOResourceError>>,
     pub adapter_discovery_filter: Mutex<HashMap<String, DiscoveryFilter>>,
+    daemon_event_txs: Mutex<Vec<mpsc::UnboundedSender<SessionEvent>>>,
+    registrations: Mutex<HashMap<dbus::Path<'static>, DaemonRegistration>>,
+    auto_reregister: AtomicBool,
 }
 
 impl SessionInner {
@@ -142,12 +165,114 @@ impl SessionInner {
     ) -> Result<mpsc::UnboundedReceiver<Event>> {
         Event::subscribe(&mut self.event_sub_tx.clone(), path, child_objects).await
     }
+
+    /// Returns the value of a property from the object cache.
+    ///
+    /// Returns [None] if the object cache is disabled or cannot answer the query.
+    /// Returns `Some(None)` if the object interface is cached but does not have the property.
+    pub fn cached_property<R>(&self, path: &dbus::Path, interface: &str, name: &str) -> Option<Option<R>>
+    where
+        R: for<'b> dbus::arg::Get<'b> + 'static,
+    {
+        let object_cache = self.object_cache.lock().unwrap();
+        object_cache.as_ref()?.property(path, interface, name)
+    }
+
+    /// Forces reading of a property from the Bluetooth daemon until its change has been signalled.
+    pub fn invalidate_cached_property(&self, path: &dbus::Path, interface: &str, name: &str) {
+        let mut object_cache = self.object_cache.lock().unwrap();
+        if let Some(object_cache) = object_cache.as_mut() {
+            object_cache.stale.insert((path.clone().into_static(), interface.to_string(), name.to_string()));
+        }
+    }
+
+    /// Registers the published object `name` with the Bluetooth daemon by calling `register_fn`.
+    ///
+    /// The registration is remembered, so that it can be repeated when the Bluetooth daemon
+    /// restarts or, if `adapter_name` is specified, the adapter is removed and added again.
+    pub async fn register_with_daemon<F, Fut>(
+        &self, name: &dbus::Path<'static>, adapter_name: Option<&str>, register_fn: F,
+    ) -> Result<()>
+    where
+        F: Fn(Arc<SyncConnection>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        register_fn(self.connection.clone()).await?;
+
+        let mut registrations = self.registrations.lock().await;
+        registrations.insert(
+            name.clone(),
+            DaemonRegistration {
+                adapter_name: adapter_name.map(|name| name.to_string()),
+                register_fn: Box::new(move |connection| register_fn(connection).boxed()),
+            },
+        );
+        Ok(())
+    }
+
+    /// Forgets the registration of the published object `name` with the Bluetooth daemon.
+    pub async fn unregister_with_daemon(&self, name: &dbus::Path<'static>) {
+        let mut registrations = self.registrations.lock().await;
+        registrations.remove(name);
+    }
+
+    /// Repeats the registrations with the specified adapter or, if [None], with the
+    /// Bluetooth daemon itself.
+    async fn reregister(&self, adapter_name: Option<&str>) {
+        let regs: Vec<_> = {
+            let registrations = self.registrations.lock().await;
+            registrations
+                .iter()
+                .filter(|(_, reg)| reg.adapter_name.as_deref() == adapter_name)
+                .map(|(name, reg)| (name.clone(), (reg.register_fn)(self.connection.clone())))
+                .collect()
+        };
+
+        for (name, register) in regs {
+            log::trace!("Re-registering {}", &name);
+            if let Err(err) = register.await {
+                log::warn!("Re-registering {} failed: {}", &name, &err);
+            }
+        }
+    }
+
+    /// Sends the event to all subscribers of session events.
+    async fn send_daemon_event(&self, event: SessionEvent) {
+        let mut txs = self.daemon_event_txs.lock().await;
+        txs.retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// Handles an ownership change of the Bluetooth daemon D-Bus name.
+    async fn handle_daemon_owner_changed(&self, new_owner: &str) {
+        if new_owner.is_empty() {
+            log::trace!("Bluetooth daemon lost");
+            if let Some(object_cache) = self.object_cache.lock().unwrap().as_mut() {
+                object_cache.clear();
+            }
+            self.send_daemon_event(SessionEvent::DaemonLost).await;
+        } else {
+            log::trace!("Bluetooth daemon started as {}", new_owner);
+            self.send_daemon_event(SessionEvent::DaemonRestarted).await;
+            if self.auto_reregister.load(Ordering::SeqCst) {
+                self.reregister(None).await;
+            }
+        }
+    }
+
+    /// Handles an adapter being added.
+    async fn handle_adapter_added(&self, adapter_name: &str) {
+        if self.auto_reregister.load(Ordering::SeqCst) {
+            self.reregister(Some(adapter_name)).await;
+        }
+    }
 }
 
 impl Drop for SessionInner {
     fn drop(&mut self) {
         // documentation for dbus_tokio::connection::IOResource indicates it is abortable
-        self.dbus_task.abort();
+        if let Some(dbus_task) = &self.dbus_task {
+            dbus_task.abort();
+        }
     }
 }
 
@@ -161,6 +286,21 @@ impl Drop for SingleSessionToken {
     }
 }
 
+/// Opens a connection to the D-Bus message bus at the specified address.
+pub(crate) async fn connect(
+    address: &str,
+) -> Result<(Arc<SyncConnection>, JoinHandle<connection::IOResourceError>)> {
+    let address = address.to_string();
+    let (resource, connection) = spawn_blocking(move || -> std::result::Result<_, dbus::Error> {
+        let mut channel = Channel::open_private(&address)?;
+        channel.register()?;
+        connection::from_channel::<SyncConnection>(channel)
+    })
+    .await??;
+    let dbus_task = tokio::spawn(resource);
+    Ok((connection, dbus_task))
+}
+
 /// Bluetooth session.
 ///
 /// Encapsulates a connection to the system Bluetooth daemon.
@@ -180,11 +320,19 @@ impl Debug for Session {
 #[cfg_attr(docsrs, doc(cfg(feature = "bluetoothd")))]
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum SessionEvent {
     /// Adapter added.
     AdapterAdded(String),
     /// Adapter removed.
     AdapterRemoved(String),
+    /// Bluetooth daemon has been started or restarted.
+    ///
+    /// All objects registered before are unknown to the new daemon instance,
+    /// unless [automatic re-registration](Session::set_auto_reregister) is enabled.
+    DaemonRestarted,
+    /// Bluetooth daemon has exited or crashed.
+    DaemonLost,
 }
 
 impl Session {
@@ -195,7 +343,40 @@ impl Session {
         let (resource, connection) = spawn_blocking(connection::new_system_sync).await??;
         let dbus_task = tokio::spawn(resource);
         log::trace!("Connected to D-Bus with unique name {}", &connection.unique_name());
+        Self::from_connection(connection, Some(dbus_task)).await
+    }
+
+    /// Create a new Bluetooth session using the specified D-Bus connection.
+    ///
+    /// This allows sharing a D-Bus connection with the rest of the application.
+    /// The caller is responsible for driving the connection, usually by spawning the
+    /// `IOResource` returned by [dbus_tokio::connection] when the connection was established.
+    ///
+    /// Signal match mode is enabled on the connection, so that signals are delivered
+    /// to all matching handlers.
+    /// Method calls are only handled for objects published by BlueR.
+    pub async fn with_connection(connection: Arc<SyncConnection>) -> Result<Self> {
+        connection.set_signal_match_mode(true);
+        Self::from_connection(connection, None).await
+    }
+
+    /// Create a new Bluetooth session connected to the D-Bus message bus at the specified address.
+    ///
+    /// The address is specified in D-Bus format, for example `unix:path=/run/dbus/system_bus_socket`.
+    /// This is useful to talk to a Bluetooth daemon on a private message bus or through a
+    /// D-Bus proxy.
+    pub async fn with_address(address: &str) -> Result<Self> {
+        let (connection, dbus_task) = connect(address).await?;
+        log::trace!("Connected to D-Bus at {} with unique name {}", address, &connection.unique_name());
+        Self::from_connection(connection, Some(dbus_task)).await
+    }
 
+    /// Create a new Bluetooth session on the specified D-Bus connection.
+    ///
+    /// If specified, the task driving the connection is aborted when the session is dropped.
+    async fn from_connection(
+        connection: Arc<SyncConnection>, dbus_task: Option<JoinHandle<connection::IOResourceError>>,
+    ) -> Result<Self> {
         let mut crossroads = Crossroads::new();
         crossroads.set_async_support(Some((
             connection.clone(),
@@ -214,6 +395,8 @@ impl Session {
             gatt::local::RegisteredDescriptor::register_interface(&mut crossroads);
         let gatt_profile_token = gatt::local::Profile::register_interface(&mut crossroads);
         let agent_token = RegisteredAgent::register_interface(&mut crossroads);
+        let media_endpoint_token = media::endpoint::RegisteredEndpoint::register_interface(&mut crossroads);
+        let media_player_token = media::local::RegisteredPlayer::register_interface(&mut crossroads);
         let monitor_token = RegisteredMonitor::register_interface(&mut crossroads);
         #[cfg(feature = "rfcomm")]
         let profile_token = RegisteredProfile::register_interface(&mut crossroads);
@@ -227,7 +410,8 @@ impl Session {
         let provision_agent_token = RegisteredProvisionAgent::register_interface(&mut crossroads);
 
         let (event_sub_tx, event_sub_rx) = mpsc::channel(1);
-        Event::handle_connection(connection.clone(), event_sub_rx).await?;
+        let object_cache = Arc::new(std::sync::Mutex::new(None));
+        Event::handle_connection(connection.clone(), SERVICE_NAME, event_sub_rx, object_cache.clone()).await?;
 
         let inner = Arc::new(SessionInner {
             connection: connection.clone(),
@@ -238,6 +422,8 @@ impl Session {
             gatt_reg_characteristic_descriptor_token,
             gatt_profile_token,
             agent_token,
+            media_endpoint_token,
+            media_player_token,
             #[cfg(feature = "mesh")]
             application_token,
             #[cfg(feature = "mesh")]
@@ -251,11 +437,16 @@ impl Session {
             profile_token,
             single_sessions: Mutex::new(HashMap::new()),
             event_sub_tx,
+            object_cache,
             dbus_task,
             adapter_discovery_filter: Mutex::new(HashMap::new()),
+            daemon_event_txs: Mutex::new(Vec::new()),
+            registrations: Mutex::new(HashMap::new()),
+            auto_reregister: AtomicBool::new(false),
         });
 
-        let mc_callback = connection.add_match(MatchRule::new_method_call()).await?;
+        let mc_rule = MatchRule::new_method_call().with_namespaced_path(PUBLISH_NAMESPACE);
+        let mc_callback = connection.add_match(mc_rule).await?;
         let mc_inner = Arc::downgrade(&inner);
         tokio::spawn(async move {
             let (_mc_callback, mut mc_stream) = mc_callback.msg_stream();
@@ -269,6 +460,44 @@ impl Session {
             }
         });
 
+        // Only adapters being added and removed are delivered, since they are the direct
+        // child objects of the adapter path. A separate match rule for InterfacesAdded
+        // cannot be used, because the event handler already consumes these signals.
+        let mut adapter_events = inner.events(adapter::PATH.into(), true).await?;
+        let noc_rule =
+            MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged").with_sender("org.freedesktop.DBus");
+        let noc_callback = connection.add_match(noc_rule).await?;
+        let daemon_inner = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let (_noc_callback, mut noc_stream) = noc_callback.msg_stream();
+            loop {
+                select! {
+                    msg_opt = noc_stream.next() => {
+                        let Some(msg) = msg_opt else { break };
+                        let Ok((name, _old_owner, new_owner)) = msg.read3::<String, String, String>() else { continue };
+                        if name != SERVICE_NAME {
+                            continue;
+                        }
+                        let Some(inner) = daemon_inner.upgrade() else { break };
+                        inner.handle_daemon_owner_changed(&new_owner).await;
+                    }
+                    evt_opt = adapter_events.next() => {
+                        match evt_opt {
+                            Some(Event::ObjectAdded { object, interfaces })
+                                if interfaces.iter().any(|i| i == adapter::INTERFACE) =>
+                            {
+                                let Some(name) = Adapter::parse_dbus_path(&object) else { continue };
+                                let Some(inner) = daemon_inner.upgrade() else { break };
+                                inner.handle_adapter_added(name).await;
+                            }
+                            Some(_) => (),
+                            None => break,
+                        }
+                    }
+                }
+            }
+        });
+
         Ok(Self { inner })
     }
 
@@ -353,8 +582,49 @@ impl Session {
         reg_profile.register(self.inner.clone(), profile, req_rx).await
     }
 
-    /// Stream adapter added and removed events.
+    /// Sets whether objects registered through this session are registered again automatically
+    /// when the Bluetooth daemon restarts.
+    ///
+    /// This covers advertisements, GATT applications and profiles, agents, advertisement monitors
+    /// and RFCOMM profiles.
+    /// Objects registered with an adapter are registered again once that adapter reappears.
+    /// The handles returned at registration stay valid.
+    ///
+    /// Disabled by default.
+    pub fn set_auto_reregister(&self, auto_reregister: bool) {
+        self.inner.auto_reregister.store(auto_reregister, Ordering::SeqCst);
+    }
+
+    /// Enables or disables the client-side cache of Bluetooth daemon objects.
+    ///
+    /// When enabled, property getters, for example [Device::rssi](crate::Device::rssi) or
+    /// [Device::name](crate::Device::name), are answered locally from a mirror of the object tree of
+    /// the Bluetooth daemon instead of performing a D-Bus round trip for each call.
+    /// The mirror is populated by calling `GetManagedObjects` once and is then kept up to date
+    /// from the same D-Bus signals that drive the event streams.
+    /// Thus, when an event reports a property change, a getter called afterwards returns the
+    /// changed value.
+    ///
+    /// Properties that have been set through BlueR are read from the Bluetooth daemon until
+    /// their change has been signalled.
+    ///
+    /// Disabled by default.
+    pub async fn set_object_cache(&self, enabled: bool) -> Result<()> {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        self.inner
+            .event_sub_tx
+            .clone()
+            .send(SubscriptionReq::ObjectCache { enabled, ready_tx })
+            .await
+            .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
+        ready_rx.await.map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?
+    }
+
+    /// Stream adapter added and removed events as well as Bluetooth daemon events.
     pub async fn events(&self) -> Result<impl Stream<Item = SessionEvent>> {
+        let (daemon_tx, daemon_rx) = mpsc::unbounded();
+        self.inner.daemon_event_txs.lock().await.push(daemon_tx);
+
         let obj_events = self.inner.events(adapter::PATH.into(), true).await?;
         let events = obj_events.filter_map(|evt| async move {
             match evt {
@@ -371,7 +641,7 @@ impl Session {
                 _ => None,
             }
         });
-        Ok(events)
+        Ok(stream::select(events, daemon_rx))
     }
 }
 
@@ -405,37 +675,162 @@ impl Clone for Event {
 }
 
 /// D-Bus events subscription request.
-pub(crate) struct SubscriptionReq {
-    path: dbus::Path<'static>,
-    child_objects: bool,
-    tx: mpsc::UnboundedSender<Event>,
-    ready_tx: oneshot::Sender<()>,
+pub(crate) enum SubscriptionReq {
+    /// Subscribe to events of an object.
+    Events {
+        path: dbus::Path<'static>,
+        child_objects: bool,
+        tx: mpsc::UnboundedSender<Event>,
+        ready_tx: oneshot::Sender<()>,
+    },
+    /// Enable or disable the object cache.
+    ObjectCache { enabled: bool, ready_tx: oneshot::Sender<Result<()>> },
+}
+
+/// Mirror of the D-Bus objects of the Bluetooth daemon.
+pub(crate) struct ObjectCache {
+    objects: HashMap<dbus::Path<'static>, HashMap<String, PropMap>>,
+    /// Properties that must be read from the Bluetooth daemon until their change has been signalled.
+    stale: HashSet<(dbus::Path<'static>, String, String)>,
+    /// Signals received while waiting for the initial object tree.
+    pending: Option<Vec<Message>>,
+}
+
+impl ObjectCache {
+    /// Creates an empty cache that queues signals until it is populated.
+    fn new() -> Self {
+        Self { objects: HashMap::new(), stale: HashSet::new(), pending: Some(Vec::new()) }
+    }
+
+    /// Populates the cache with the initial object tree and applies queued signals.
+    ///
+    /// Signals that were emitted before the object tree was obtained are already reflected
+    /// in it and applying them again in order leads to the same state.
+    fn populate(&mut self, objects: HashMap<dbus::Path<'static>, HashMap<String, PropMap>>) {
+        self.objects = objects;
+        for msg in self.pending.take().unwrap_or_default() {
+            self.update(&msg);
+        }
+    }
+
+    /// Removes all objects.
+    fn clear(&mut self) {
+        self.objects.clear();
+        self.stale.clear();
+        if let Some(pending) = &mut self.pending {
+            pending.clear();
+        }
+    }
+
+    /// Updates the cache from an object manager or properties signal.
+    fn update(&mut self, msg: &Message) {
+        use dbus::message::SignalArgs;
+
+        if let Some(pending) = &mut self.pending {
+            if let Ok(msg) = msg.duplicate() {
+                pending.push(msg);
+            }
+            return;
+        }
+
+        if let (
+            Some(object),
+            Some(PropertiesPropertiesChanged { interface_name, changed_properties, invalidated_properties }),
+        ) = (msg.path(), PropertiesPropertiesChanged::from_message(msg))
+        {
+            let object = object.into_static();
+            if let Some(props) = self.objects.get_mut(&object).and_then(|ifaces| ifaces.get_mut(&interface_name))
+            {
+                for name in invalidated_properties {
+                    self.stale.remove(&(object.clone(), interface_name.clone(), name.clone()));
+                    props.remove(&name);
+                }
+                for (name, value) in changed_properties {
+                    self.stale.remove(&(object.clone(), interface_name.clone(), name.clone()));
+                    props.insert(name, value);
+                }
+            }
+        }
+
+        if let Some(ObjectManagerInterfacesAdded { object, interfaces }) =
+            ObjectManagerInterfacesAdded::from_message(msg)
+        {
+            let ifaces = self.objects.entry(object).or_default();
+            for (interface, props) in interfaces {
+                ifaces.insert(interface, props);
+            }
+        }
+
+        if let Some(ObjectManagerInterfacesRemoved { object, interfaces }) =
+            ObjectManagerInterfacesRemoved::from_message(msg)
+        {
+            if let Some(ifaces) = self.objects.get_mut(&object) {
+                for interface in &interfaces {
+                    ifaces.remove(interface);
+                }
+                if ifaces.is_empty() {
+                    self.objects.remove(&object);
+                }
+            }
+            self.stale.retain(|(path, interface, _)| *path != object || !interfaces.contains(interface));
+        }
+    }
+
+    /// Returns the value of a property.
+    ///
+    /// Returns [None] if the property must be read from the Bluetooth daemon.
+    fn property<R>(&self, path: &dbus::Path, interface: &str, name: &str) -> Option<Option<R>>
+    where
+        R: for<'b> dbus::arg::Get<'b> + 'static,
+    {
+        let props = self.objects.get(path)?.get(interface)?;
+        if self.stale.contains(&(path.clone().into_static(), interface.to_string(), name.to_string())) {
+            return None;
+        }
+        match props.get(name) {
+            Some(value) => {
+                // Marshal the value so that it can be read with its D-Bus type.
+                let mut msg = Message::new_signal("/", "org.bluez.ObjectCache", "Value").ok()?;
+                value.0.append(&mut IterAppend::new(&mut msg));
+                msg.get1().map(Some)
+            }
+            None => Some(None),
+        }
+    }
 }
 
 impl Event {
     /// Spawns a task that handles events for the specified connection.
+    ///
+    /// Only signals sent by the D-Bus service `service_name` are handled.
     pub(crate) async fn handle_connection(
-        connection: Arc<SyncConnection>, mut sub_rx: mpsc::Receiver<SubscriptionReq>,
+        connection: Arc<SyncConnection>, service_name: &'static str, mut sub_rx: mpsc::Receiver<SubscriptionReq>,
+        object_cache: Arc<std::sync::Mutex<Option<ObjectCache>>>,
     ) -> Result<()> {
         use dbus::message::SignalArgs;
-        lazy_static! {
-            static ref SERVICE_NAME_BUS: BusName<'static> = BusName::new(SERVICE_NAME).unwrap();
-            static ref SERVICE_NAME_REF: Option<&'static BusName<'static>> = Some(&SERVICE_NAME_BUS);
-        }
+        let service_name_bus =
+            BusName::new(service_name).map_err(|err| Error::new(ErrorKind::InvalidName(err)))?;
+        let service_name_ref = Some(&service_name_bus);
 
         let (msg_tx, mut msg_rx) = mpsc::unbounded();
+        let msg_object_cache = object_cache.clone();
         let handle_msg = move |msg: Message| {
+            // Object cache is updated in message dispatch order, so that it is coherent with
+            // method replies and events.
+            if let Some(object_cache) = msg_object_cache.lock().unwrap().as_mut() {
+                object_cache.update(&msg);
+            }
             let _ = msg_tx.unbounded_send(msg);
             true
         };
 
-        let rule_add = ObjectManagerInterfacesAdded::match_rule(*SERVICE_NAME_REF, None);
+        let rule_add = ObjectManagerInterfacesAdded::match_rule(service_name_ref, None).static_clone();
         let msg_match_add = connection.add_match(rule_add).await?.msg_cb(handle_msg.clone());
 
-        let rule_removed = ObjectManagerInterfacesRemoved::match_rule(*SERVICE_NAME_REF, None);
+        let rule_removed = ObjectManagerInterfacesRemoved::match_rule(service_name_ref, None).static_clone();
         let msg_match_removed = connection.add_match(rule_removed).await?.msg_cb(handle_msg.clone());
 
-        let rule_prop = PropertiesPropertiesChanged::match_rule(*SERVICE_NAME_REF, None);
+        let rule_prop = PropertiesPropertiesChanged::match_rule(service_name_ref, None).static_clone();
         let msg_match_prop = connection.add_match(rule_prop).await?.msg_cb(handle_msg.clone());
 
         tokio::spawn(async move {
@@ -529,7 +924,7 @@ impl Event {
                     },
                     sub_opt = sub_rx.next() => {
                         match sub_opt {
-                            Some(SubscriptionReq { path, child_objects, tx, ready_tx }) => {
+                            Some(SubscriptionReq::Events { path, child_objects, tx, ready_tx }) => {
                                 log::trace!("Adding event subscription for {} with child_objects={:?}", &path, &child_objects);
                                 let _ = ready_tx.send(());
                                 let path_subs = subs.entry(path.to_string()).or_default();
@@ -537,6 +932,36 @@ impl Event {
                                     child_objects, tx
                                 });
                             }
+                            Some(SubscriptionReq::ObjectCache { enabled, ready_tx }) => {
+                                let is_enabled = object_cache.lock().unwrap().is_some();
+                                let res = match (enabled, is_enabled) {
+                                    (true, false) => {
+                                        log::trace!("Populating object cache");
+                                        *object_cache.lock().unwrap() = Some(ObjectCache::new());
+                                        let res = all_dbus_objects(&connection).await;
+                                        let mut object_cache = object_cache.lock().unwrap();
+                                        match res {
+                                            Ok(objects) => {
+                                                if let Some(object_cache) = object_cache.as_mut() {
+                                                    object_cache.populate(objects);
+                                                }
+                                                Ok(())
+                                            }
+                                            Err(err) => {
+                                                *object_cache = None;
+                                                Err(err)
+                                            }
+                                        }
+                                    }
+                                    (false, true) => {
+                                        log::trace!("Dropping object cache");
+                                        *object_cache.lock().unwrap() = None;
+                                        Ok(())
+                                    }
+                                    _ => Ok(()),
+                                };
+                                let _ = ready_tx.send(res);
+                            }
                             None => break,
                         }
                     }
@@ -562,7 +987,7 @@ impl Event {
         let (tx, rx) = mpsc::unbounded();
         let (ready_tx, ready_rx) = oneshot::channel();
         sub_tx
-            .send(SubscriptionReq { path, child_objects, tx, ready_tx })
+            .send(SubscriptionReq::Events { path, child_objects, tx, ready_tx })
             .await
             .map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
         ready_rx.await.map_err(|_| Error::new(ErrorKind::Internal(InternalErrorKind::DBusConnectionLost)))?;
@@ -1,9 +1,11 @@
 //! System socket base.
 
 use libc::{c_int, sockaddr, socklen_t, Ioctl, SOCK_CLOEXEC, SOCK_NONBLOCK};
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
+use std::{io::ErrorKind, mem::MaybeUninit};
 use std::{
-    io::{Error, ErrorKind, Result},
-    mem::{size_of, MaybeUninit},
+    io::{Error, Result},
+    mem::size_of,
     os::unix::io::{AsRawFd, IntoRawFd, RawFd},
 };
 use tokio::io::ReadBuf;
@@ -48,13 +50,62 @@ pub trait SysSockAddr: Sized {
     /// System socket address type.
     type SysSockAddr: Sized + 'static;
 
+    /// Minimum length of a system socket address returned by the kernel.
+    ///
+    /// Missing trailing bytes are zeroed.
+    #[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
+    const MIN_SYS_SOCK_ADDR_LEN: usize = size_of::<Self::SysSockAddr>();
+
     /// Convert to system socket address.
     fn into_sys_sock_addr(self) -> Self::SysSockAddr;
 
+    /// Length of the system socket address passed to the kernel.
+    fn sys_sock_addr_len(&self) -> usize {
+        size_of::<Self::SysSockAddr>()
+    }
+
     /// Convert from system socket address.
+    #[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
     fn try_from_sys_sock_addr(addr: Self::SysSockAddr) -> Result<Self>;
 }
 
+/// HCI socket address.
+#[cfg(any(feature = "mgmt", feature = "monitor_capture"))]
+#[derive(Clone, Copy, Debug)]
+pub struct HciAddr {
+    /// Controller index.
+    pub dev: u16,
+    /// HCI channel.
+    pub channel: u16,
+}
+
+#[cfg(any(feature = "mgmt", feature = "monitor_capture"))]
+impl SysSockAddr for HciAddr {
+    type SysSockAddr = crate::sys::sockaddr_hci;
+
+    fn into_sys_sock_addr(self) -> Self::SysSockAddr {
+        crate::sys::sockaddr_hci {
+            hci_family: libc::AF_BLUETOOTH as _,
+            hci_dev: self.dev,
+            hci_channel: self.channel,
+        }
+    }
+
+    #[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
+    fn try_from_sys_sock_addr(saddr: Self::SysSockAddr) -> Result<Self> {
+        if saddr.hci_family != libc::AF_BLUETOOTH as _ {
+            return Err(Error::new(ErrorKind::InvalidInput, "sockaddr_hci::hci_family is not AF_BLUETOOTH"));
+        }
+        Ok(Self { dev: saddr.hci_dev, channel: saddr.hci_channel })
+    }
+}
+
+/// Whether a system socket address of the specified length returned by the kernel is valid.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
+fn valid_sys_sock_addr_len<SA: SysSockAddr>(length: socklen_t) -> bool {
+    (SA::MIN_SYS_SOCK_ADDR_LEN..=size_of::<SA::SysSockAddr>()).contains(&(length as usize))
+}
+
 /// Creates a socket of the specified type and returns its file descriptor.
 ///
 /// The socket is set to non-blocking mode.
@@ -71,15 +122,9 @@ pub fn bind<SA>(socket: &OwnedFd, sa: SA) -> Result<()>
 where
     SA: SysSockAddr,
 {
+    let len = sa.sys_sock_addr_len();
     let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
-    if unsafe {
-        libc::bind(
-            socket.as_raw_fd(),
-            &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
-        )
-    } == 0
-    {
+    if unsafe { libc::bind(socket.as_raw_fd(), &addr as *const _ as *const sockaddr, len as socklen_t) } == 0 {
         Ok(())
     } else {
         Err(Error::last_os_error())
@@ -87,18 +132,19 @@ where
 }
 
 /// Gets the address the socket is bound to.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn getsockname<SA>(socket: &OwnedFd) -> Result<SA>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     if unsafe { libc::getsockname(socket.as_raw_fd(), saddr.as_mut_ptr() as *mut _, &mut length) } == -1 {
         return Err(Error::last_os_error());
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !valid_sys_sock_addr_len::<SA>(length) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length from getsockname"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -106,18 +152,19 @@ where
 }
 
 /// Gets the address the socket is connected to.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn getpeername<SA>(socket: &OwnedFd) -> Result<SA>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     if unsafe { libc::getpeername(socket.as_raw_fd(), saddr.as_mut_ptr() as *mut _, &mut length) } == -1 {
         return Err(Error::last_os_error());
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !valid_sys_sock_addr_len::<SA>(length) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length from getpeername"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -125,6 +172,7 @@ where
 }
 
 /// Puts socket in listen mode.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn listen(socket: &OwnedFd, backlog: i32) -> Result<()> {
     if unsafe { libc::listen(socket.as_raw_fd(), backlog) } == 0 {
         Ok(())
@@ -136,11 +184,12 @@ pub fn listen(socket: &OwnedFd, backlog: i32) -> Result<()> {
 /// Accept a connection on the provided socket.
 ///
 /// The accepted socket is set into non-blocking mode.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn accept<SA>(socket: &OwnedFd) -> Result<(OwnedFd, SA)>
 where
     SA: SysSockAddr,
 {
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
 
     let fd = match unsafe {
@@ -150,7 +199,7 @@ where
         fd => unsafe { OwnedFd::new(fd) },
     };
 
-    if length != size_of::<SA::SysSockAddr>() as socklen_t {
+    if !valid_sys_sock_addr_len::<SA>(length) {
         return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length"));
     }
     let saddr = unsafe { saddr.assume_init() };
@@ -160,19 +209,14 @@ where
 }
 
 /// Initiate a connection on a socket to the specified address.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn connect<SA>(socket: &OwnedFd, sa: SA) -> Result<()>
 where
     SA: SysSockAddr,
 {
+    let len = sa.sys_sock_addr_len();
     let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
-    if unsafe {
-        libc::connect(
-            socket.as_raw_fd(),
-            &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
-        )
-    } == 0
-    {
+    if unsafe { libc::connect(socket.as_raw_fd(), &addr as *const _ as *const sockaddr, len as socklen_t) } == 0 {
         Ok(())
     } else {
         Err(Error::last_os_error())
@@ -180,6 +224,7 @@ where
 }
 
 /// Sends from buffer into socket.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso", feature = "mgmt"))]
 pub fn send(socket: &OwnedFd, buf: &[u8], flags: c_int) -> Result<usize> {
     match unsafe { libc::send(socket.as_raw_fd(), buf.as_ptr() as *const _, buf.len(), flags) } {
         -1 => Err(Error::last_os_error()),
@@ -188,11 +233,13 @@ pub fn send(socket: &OwnedFd, buf: &[u8], flags: c_int) -> Result<usize> {
 }
 
 /// Sends from buffer into socket using destination address.
-pub fn sendto<SA>(socket: &OwnedFd, buf: &[u8], flags: c_int, sa: SA) -> Result<usize>
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
+pub fn sendto<SA>(socket: &OwnedFd, buf: &[u8], flags: c_int, sa: &SA) -> Result<usize>
 where
-    SA: SysSockAddr,
+    SA: SysSockAddr + Clone,
 {
-    let addr: SA::SysSockAddr = sa.into_sys_sock_addr();
+    let len = sa.sys_sock_addr_len();
+    let addr: SA::SysSockAddr = sa.clone().into_sys_sock_addr();
     match unsafe {
         libc::sendto(
             socket.as_raw_fd(),
@@ -200,7 +247,7 @@ where
             buf.len(),
             flags,
             &addr as *const _ as *const sockaddr,
-            size_of::<SA::SysSockAddr>() as socklen_t,
+            len as socklen_t,
         )
     } {
         -1 => Err(Error::last_os_error()),
@@ -225,12 +272,13 @@ pub fn recv(socket: &OwnedFd, buf: &mut ReadBuf, flags: c_int) -> Result<usize>
 }
 
 /// Receive from socket into buffer with source address.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn recvfrom<SA>(socket: &OwnedFd, buf: &mut ReadBuf, flags: c_int) -> Result<(usize, SA)>
 where
     SA: SysSockAddr,
 {
     let unfilled = unsafe { buf.unfilled_mut() };
-    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::uninit();
+    let mut saddr: MaybeUninit<SA::SysSockAddr> = MaybeUninit::zeroed();
     let mut length = size_of::<SA::SysSockAddr>() as socklen_t;
     match unsafe {
         libc::recvfrom(
@@ -250,7 +298,7 @@ where
             }
             buf.advance(n);
 
-            if length != size_of::<SA::SysSockAddr>() as socklen_t {
+            if !valid_sys_sock_addr_len::<SA>(length) {
                 return Err(Error::new(ErrorKind::InvalidInput, "invalid sockaddr length"));
             }
             let saddr = unsafe { saddr.assume_init() };
@@ -261,7 +309,54 @@ where
     }
 }
 
+/// Ancillary data received with a message.
+#[cfg(feature = "sco")]
+#[derive(Clone, Debug)]
+pub struct ControlMessage {
+    /// Originating protocol.
+    pub level: c_int,
+    /// Protocol-specific type.
+    pub ty: c_int,
+    /// Data.
+    pub data: Vec<u8>,
+}
+
+/// Receive from socket into buffer together with ancillary data.
+#[cfg(feature = "sco")]
+pub fn recvmsg(socket: &OwnedFd, buf: &mut ReadBuf, flags: c_int) -> Result<(usize, Vec<ControlMessage>)> {
+    let unfilled = unsafe { buf.unfilled_mut() };
+    let mut iov = libc::iovec { iov_base: unfilled.as_mut_ptr() as *mut _, iov_len: unfilled.len() };
+    let mut control = [0u64; 16];
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut _;
+    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+    let n = match unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) } {
+        -1 => return Err(Error::last_os_error()),
+        n => n as usize,
+    };
+    unsafe {
+        buf.assume_init(n);
+    }
+    buf.advance(n);
+
+    let mut cmsgs = Vec::new();
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+    while !cmsg.is_null() {
+        let hdr = unsafe { &*cmsg };
+        let len = hdr.cmsg_len as usize - unsafe { libc::CMSG_LEN(0) } as usize;
+        let data = unsafe { std::slice::from_raw_parts(libc::CMSG_DATA(cmsg), len) };
+        cmsgs.push(ControlMessage { level: hdr.cmsg_level, ty: hdr.cmsg_type, data: data.to_vec() });
+        cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+    }
+
+    Ok((n, cmsgs))
+}
+
 /// Shut down part of a socket.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn shutdown(socket: &OwnedFd, how: c_int) -> Result<()> {
     if unsafe { libc::shutdown(socket.as_raw_fd(), how) } == 0 {
         Ok(())
@@ -271,6 +366,7 @@ pub fn shutdown(socket: &OwnedFd, how: c_int) -> Result<()> {
 }
 
 /// Get socket option.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn getsockopt<T>(socket: &OwnedFd, level: c_int, optname: c_int) -> Result<T> {
     let mut optval: MaybeUninit<T> = MaybeUninit::uninit();
     let mut optlen: socklen_t = size_of::<T>() as _;
@@ -287,6 +383,7 @@ pub fn getsockopt<T>(socket: &OwnedFd, level: c_int, optname: c_int) -> Result<T
 }
 
 /// Set socket option.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn setsockopt<T>(socket: &OwnedFd, level: c_int, optname: i32, optval: &T) -> Result<()> {
     let optlen: socklen_t = size_of::<T>() as _;
     if unsafe { libc::setsockopt(socket.as_raw_fd(), level, optname, optval as *const _ as *const _, optlen) }
@@ -298,6 +395,7 @@ pub fn setsockopt<T>(socket: &OwnedFd, level: c_int, optname: i32, optval: &T) -
 }
 
 /// Perform an IOCTL that reads a single value.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 pub fn ioctl_read<T>(socket: &OwnedFd, request: Ioctl) -> Result<T> {
     let mut value: MaybeUninit<T> = MaybeUninit::uninit();
     let ret = unsafe { libc::ioctl(socket.as_raw_fd(), request, value.as_mut_ptr()) };
@@ -319,6 +417,9 @@ pub fn ioctl_write<T>(socket: &OwnedFd, request: Ioctl, value: &T) -> Result<c_i
 }
 
 /// Private socket implementation functions.
+///
+/// Specify `stream` for sockets used by the stream types of `stream_util`.
+#[cfg(any(feature = "l2cap", feature = "rfcomm", feature = "sco", feature = "iso"))]
 macro_rules! sock_priv {
     () => {
         async fn accept_priv(&self) -> Result<(Self, SocketAddr)> {
@@ -396,7 +497,7 @@ macro_rules! sock_priv {
         async fn send_to_priv(&self, buf: &[u8], target: SocketAddr) -> Result<usize> {
             loop {
                 let mut guard = self.fd.writable().await?;
-                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target)) {
+                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, &target)) {
                     Ok(result) => return result,
                     Err(_would_block) => continue,
                 }
@@ -407,7 +508,7 @@ macro_rules! sock_priv {
         fn poll_send_to_priv(&self, cx: &mut Context, buf: &[u8], target: SocketAddr) -> Poll<Result<usize>> {
             loop {
                 let mut guard = ready!(self.fd.poll_write_ready(cx))?;
-                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, target)) {
+                match guard.try_io(|inner| sock::sendto(inner.get_ref(), buf, 0, &target)) {
                     Ok(result) => return Poll::Ready(result),
                     Err(_would_block) => continue,
                 }
@@ -459,6 +560,30 @@ macro_rules! sock_priv {
             }
         }
 
+        fn shutdown_priv(&self, how: Shutdown) -> Result<()> {
+            let how = match how {
+                Shutdown::Read => SHUT_RD,
+                Shutdown::Write => SHUT_WR,
+                Shutdown::Both => SHUT_RDWR,
+            };
+            sock::shutdown(self.fd.get_ref(), how)?;
+            Ok(())
+        }
+    };
+
+    (stream) => {
+        sock_priv!();
+
+        fn poll_flush_priv(&self, _cx: &mut Context) -> Poll<Result<()>> {
+            // Flush is a no-op.
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown_priv(&self, _cx: &mut Context, how: Shutdown) -> Poll<Result<()>> {
+            self.shutdown_priv(how)?;
+            Poll::Ready(Ok(()))
+        }
+
         async fn peek_priv(&self, buf: &mut [u8]) -> Result<usize> {
             let mut buf = ReadBuf::new(buf);
             loop {
@@ -479,25 +604,5 @@ macro_rules! sock_priv {
                 }
             }
         }
-
-        fn poll_flush_priv(&self, _cx: &mut Context) -> Poll<Result<()>> {
-            // Flush is a no-op.
-            Poll::Ready(Ok(()))
-        }
-
-        fn shutdown_priv(&self, how: Shutdown) -> Result<()> {
-            let how = match how {
-                Shutdown::Read => SHUT_RD,
-                Shutdown::Write => SHUT_WR,
-                Shutdown::Both => SHUT_RDWR,
-            };
-            sock::shutdown(self.fd.get_ref(), how)?;
-            Ok(())
-        }
-
-        fn poll_shutdown_priv(&self, _cx: &mut Context, how: Shutdown) -> Poll<Result<()>> {
-            self.shutdown_priv(how)?;
-            Poll::Ready(Ok(()))
-        }
     };
 }
@@ -6,6 +6,7 @@ use nix::{request_code_write, sys::ioctl::ioctl_num_type};
 use std::mem::size_of;
 
 pub const SOL_L2CAP: i32 = 6;
+pub const SOL_SCO: i32 = 17;
 pub const SOL_RFCOMM: i32 = 18;
 
 /// Bluetooth security.
@@ -31,14 +32,103 @@ pub struct bt_power {
     pub force_active: u8,
 }
 
+pub const BT_DEFER_SETUP: i32 = 7;
+
 pub const BT_POWER: i32 = 9;
 pub const BT_POWER_FORCE_ACTIVE_OFF: i32 = 0;
 pub const BT_POWER_FORCE_ACTIVE_ON: i32 = 1;
 
+/// Bluetooth voice setting.
+#[repr(C)]
+#[derive(Clone)]
+pub struct bt_voice {
+    pub setting: u16,
+}
+
+pub const BT_VOICE: i32 = 11;
+pub const BT_VOICE_TRANSPARENT: u16 = 0x0003;
+pub const BT_VOICE_CVSD_16BIT: u16 = 0x0060;
+
 pub const BT_SNDMTU: i32 = 12;
 pub const BT_RCVMTU: i32 = 13;
 pub const BT_PHY: i32 = 14;
 pub const BT_MODE: i32 = 15;
+pub const BT_PKT_STATUS: i32 = 16;
+
+pub const BT_SCM_PKT_STATUS: i32 = 0x03;
+
+pub const BT_ISO_QOS: i32 = 17;
+
+/// Unset connected isochronous group (CIG) id.
+pub const BT_ISO_QOS_CIG_UNSET: u8 = 0xff;
+/// Unset connected isochronous stream (CIS) id.
+pub const BT_ISO_QOS_CIS_UNSET: u8 = 0xff;
+/// Unset broadcast isochronous group (BIG) handle.
+pub const BT_ISO_QOS_BIG_UNSET: u8 = 0xff;
+/// Unset broadcast isochronous stream (BIS) index.
+pub const BT_ISO_QOS_BIS_UNSET: u8 = 0xff;
+
+/// LE 1M PHY.
+pub const BT_ISO_PHY_1M: u8 = 0x01;
+/// LE 2M PHY.
+pub const BT_ISO_PHY_2M: u8 = 0x02;
+/// LE Coded PHY.
+pub const BT_ISO_PHY_CODED: u8 = 0x04;
+/// Any PHY.
+pub const BT_ISO_PHY_ANY: u8 = BT_ISO_PHY_1M | BT_ISO_PHY_2M | BT_ISO_PHY_CODED;
+
+/// ISO QoS of one direction.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct bt_iso_io_qos {
+    pub interval: u32,
+    pub latency: u16,
+    pub sdu: u16,
+    pub phy: u8,
+    pub rtn: u8,
+}
+
+/// ISO unicast QoS.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct bt_iso_ucast_qos {
+    pub cig: u8,
+    pub cis: u8,
+    pub sca: u8,
+    pub packing: u8,
+    pub framing: u8,
+    pub in_: bt_iso_io_qos,
+    pub out: bt_iso_io_qos,
+}
+
+/// ISO broadcast QoS.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct bt_iso_bcast_qos {
+    pub big: u8,
+    pub bis: u8,
+    pub sync_factor: u8,
+    pub packing: u8,
+    pub framing: u8,
+    pub in_: bt_iso_io_qos,
+    pub out: bt_iso_io_qos,
+    pub encryption: u8,
+    pub bcode: [u8; 16],
+    pub options: u8,
+    pub skip: u16,
+    pub sync_timeout: u16,
+    pub sync_cte_type: u8,
+    pub mse: u8,
+    pub timeout: u16,
+}
+
+/// ISO QoS.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union bt_iso_qos {
+    pub ucast: bt_iso_ucast_qos,
+    pub bcast: bt_iso_bcast_qos,
+}
 
 /// BR1M1SLOT PHY.
 pub const BR1M1SLOT: i32 = 1 << 0;
@@ -72,7 +162,10 @@ pub const LECODEDTX: i32 = 1 << 13;
 pub const LECODEDRX: i32 = 1 << 14;
 
 pub const BTPROTO_L2CAP: i32 = 0;
+pub const BTPROTO_HCI: i32 = 1;
+pub const BTPROTO_SCO: i32 = 2;
 pub const BTPROTO_RFCOMM: i32 = 3;
+pub const BTPROTO_ISO: i32 = 8;
 
 /// Bluetooth address.
 #[repr(packed)]
@@ -156,6 +249,65 @@ pub struct l2cap_conninfo {
     pub dev_class: [u8; 3],
 }
 
+/// SCO socket address.
+#[repr(C)]
+#[derive(Clone)]
+pub struct sockaddr_sco {
+    pub sco_family: sa_family_t,
+    pub sco_bdaddr: bdaddr_t,
+}
+
+pub const SCO_OPTIONS: i32 = 0x01;
+pub const SCO_CONNINFO: i32 = 0x02;
+
+/// SCO socket options.
+#[repr(C)]
+#[derive(Clone)]
+pub struct sco_options {
+    pub mtu: u16,
+}
+
+/// SCO socket connection information.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct sco_conninfo {
+    /// Host controller interface (HCI) handle for the connection.
+    pub hci_handle: u16,
+    /// Device class.
+    pub dev_class: [u8; 3],
+}
+
+pub const ISO_MAX_NUM_BIS: usize = 0x1f;
+
+/// ISO broadcast socket address.
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct sockaddr_iso_bc {
+    pub bc_bdaddr: bdaddr_t,
+    pub bc_bdaddr_type: u8,
+    pub bc_sid: u8,
+    pub bc_num_bis: u8,
+    pub bc_bis: [u8; ISO_MAX_NUM_BIS],
+}
+
+/// ISO socket address including the broadcast address.
+///
+/// The kernel declares `iso_bc` as a flexible array member that is only present
+/// for broadcast addresses.
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct sockaddr_iso {
+    pub iso_family: sa_family_t,
+    pub iso_bdaddr: bdaddr_t,
+    pub iso_bdaddr_type: u8,
+    pub iso_bc: sockaddr_iso_bc,
+}
+
+/// Length of an ISO socket address without the broadcast address.
+pub const SOCKADDR_ISO_UCAST_LEN: usize = size_of::<sockaddr_iso>() - size_of::<sockaddr_iso_bc>();
+
 /// RFCOMM socket address.
 #[repr(C)]
 #[derive(Clone)]
@@ -198,3 +350,20 @@ pub struct rfcomm_dev_req {
     pub dst: bdaddr_t,
     pub channel: u8,
 }
+
+/// HCI socket address.
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct sockaddr_hci {
+    pub hci_family: sa_family_t,
+    pub hci_dev: c_ushort,
+    pub hci_channel: c_ushort,
+}
+
+pub const HCI_DEV_NONE: u16 = 0xffff;
+
+pub const HCI_CHANNEL_RAW: u16 = 0;
+pub const HCI_CHANNEL_USER: u16 = 1;
+pub const HCI_CHANNEL_MONITOR: u16 = 2;
+pub const HCI_CHANNEL_CONTROL: u16 = 3;
+pub const HCI_CHANNEL_LOGGING: u16 = 4;
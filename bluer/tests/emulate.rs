@@ -0,0 +1,120 @@
+//! Tests loading serialized GATT database snapshots for peripheral emulation.
+
+use bluer::gatt::{
+    database::{self, Database},
+    emulate::{Descriptor, Peripheral, Script},
+    CharacteristicFlags,
+};
+use uuid::Uuid;
+
+const BATTERY_SERVICE: Uuid = Uuid::from_u128(0x0000180f_0000_1000_8000_00805f9b34fb);
+const BATTERY_LEVEL: Uuid = Uuid::from_u128(0x00002a19_0000_1000_8000_00805f9b34fb);
+const CLIENT_CHARACTERISTIC_CONFIGURATION: Uuid = Uuid::from_u128(0x00002902_0000_1000_8000_00805f9b34fb);
+const USER_DESCRIPTION: Uuid = Uuid::from_u128(0x00002901_0000_1000_8000_00805f9b34fb);
+
+/// Snapshot of a device with a battery service and a writable user description.
+fn snapshot() -> Database {
+    let descriptor = |handle, uuid, value: &[u8]| database::Descriptor {
+        handle,
+        uuid,
+        name: None,
+        value: Some(value.to_vec()),
+        _non_exhaustive: (),
+    };
+    Database {
+        services: vec![database::Service {
+            handle: 0x0010,
+            uuid: BATTERY_SERVICE,
+            name: Some("Battery".to_string()),
+            primary: true,
+            includes: Vec::new(),
+            characteristics: vec![database::Characteristic {
+                handle: 0x0011,
+                uuid: BATTERY_LEVEL,
+                name: Some("Battery Level".to_string()),
+                flags: CharacteristicFlags {
+                    read: true,
+                    notify: true,
+                    writable_auxiliaries: true,
+                    ..Default::default()
+                },
+                value: Some(vec![0x64]),
+                descriptors: vec![
+                    descriptor(0x0013, CLIENT_CHARACTERISTIC_CONFIGURATION, &[0x01, 0x00]),
+                    descriptor(0x0014, USER_DESCRIPTION, b"Level"),
+                ],
+                _non_exhaustive: (),
+            }],
+            _non_exhaustive: (),
+        }],
+        _non_exhaustive: (),
+    }
+}
+
+fn check(peripheral: &Peripheral) {
+    assert_eq!(peripheral, &Peripheral::from(snapshot()));
+    let service = &peripheral.services[0];
+    assert_eq!((service.handle, service.uuid, service.primary), (Some(0x0010), BATTERY_SERVICE, true));
+    let c = &service.characteristics[0];
+    assert_eq!((c.handle, c.uuid, c.value.as_deref()), (Some(0x0011), BATTERY_LEVEL, Some(&[0x64][..])));
+    assert!(c.flags.writable_auxiliaries && c.script.is_none());
+    let descriptors: Vec<_> = c.descriptors.iter().map(|d: &Descriptor| (d.handle, d.uuid, d.writable)).collect();
+    assert_eq!(
+        descriptors,
+        [(Some(0x0013), CLIENT_CHARACTERISTIC_CONFIGURATION, false), (Some(0x0014), USER_DESCRIPTION, false)]
+    );
+}
+
+#[test]
+fn json() {
+    let json = serde_json::to_string(&snapshot()).unwrap();
+    assert_eq!(serde_json::from_str::<Database>(&json).unwrap(), snapshot());
+    let peripheral: Peripheral = serde_json::from_str(&json).unwrap();
+    check(&peripheral);
+
+    let json = serde_json::to_string(&peripheral).unwrap();
+    assert_eq!(serde_json::from_str::<Peripheral>(&json).unwrap(), peripheral);
+}
+
+#[test]
+fn toml() {
+    let toml = toml::to_string(&snapshot()).unwrap();
+    assert_eq!(toml::from_str::<Database>(&toml).unwrap(), snapshot());
+    let peripheral: Peripheral = toml::from_str(&toml).unwrap();
+    check(&peripheral);
+
+    let toml = toml::to_string(&peripheral).unwrap();
+    assert_eq!(toml::from_str::<Peripheral>(&toml).unwrap(), peripheral);
+}
+
+#[test]
+fn optional_fields() {
+    let peripheral: Peripheral = serde_json::from_str(
+        r#"{"services": [{"uuid": "0000180f-0000-1000-8000-00805f9b34fb", "characteristics": [
+            {"uuid": "00002a19-0000-1000-8000-00805f9b34fb", "script": {"values": [[1], [2]], "interval_ms": 1000}}
+        ]}]}"#,
+    )
+    .unwrap();
+    let service = &peripheral.services[0];
+    assert!(service.primary);
+    let c = &service.characteristics[0];
+    assert_eq!((c.handle, c.value.as_deref(), c.flags), (None, None, CharacteristicFlags::default()));
+    assert_eq!(c.script, Some(Script { values: vec![vec![1], vec![2]], interval_ms: 1000, repeat: false }));
+
+    let toml: Peripheral = toml::from_str(
+        r#"
+        [[services]]
+        uuid = "0000180f-0000-1000-8000-00805f9b34fb"
+        primary = false
+
+        [[services.characteristics]]
+        uuid = "00002a19-0000-1000-8000-00805f9b34fb"
+        value = [100]
+        flags = { read = true }
+        "#,
+    )
+    .unwrap();
+    assert!(!toml.services[0].primary);
+    let c = &toml.services[0].characteristics[0];
+    assert_eq!((c.value.as_deref(), c.flags.read, c.flags.write), (Some(&[100][..]), true, false));
+}
@@ -0,0 +1,402 @@
+//! Tests running a Bluetooth session against the fake Bluetooth daemon.
+
+use bluer::{
+    adv::Advertisement,
+    gatt::{
+        database::{AttributePath, Change},
+        CharacteristicFlags,
+    },
+    mock::{MockAdapter, MockBluez, MockCharacteristic, MockDescriptor, MockDevice, MockService},
+    sdp::{ProfileDescriptor, ProtocolDescriptor, ServiceRecord},
+    AdapterEvent, Address, ErrorKind,
+};
+use futures::{pin_mut, Stream, StreamExt};
+use std::time::Duration;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+const ADAPTER: &str = "hci0";
+const ADAPTER_ADDRESS: Address = Address::new([0x00, 0x01, 0x02, 0x03, 0x04, 0x05]);
+const DEVICE_ADDRESS: Address = Address::new([0x10, 0x20, 0x30, 0x40, 0x50, 0x60]);
+
+const SERVICE_UUID: Uuid = Uuid::from_u128(0xfeedc0de_0000_1000_8000_00805f9b34fb);
+const CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0xf00dc0de_0000_1000_8000_00805f9b34fb);
+const READ_ONLY_UUID: Uuid = Uuid::from_u128(0xf00dc0df_0000_1000_8000_00805f9b34fb);
+const USER_DESCRIPTION_UUID: Uuid = Uuid::from_u128(0x00002901_0000_1000_8000_00805f9b34fb);
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn mock() -> MockBluez {
+    let mock = MockBluez::new().await.expect("cannot start mock Bluetooth daemon");
+    mock.add_adapter(MockAdapter::new(ADAPTER, ADAPTER_ADDRESS)).await.unwrap();
+    mock
+}
+
+fn device() -> MockDevice {
+    let mut descriptor = MockDescriptor::new(0x10, USER_DESCRIPTION_UUID);
+    descriptor.value = b"Counter".to_vec();
+
+    let mut characteristic = MockCharacteristic::new(
+        0x0b,
+        CHARACTERISTIC_UUID,
+        CharacteristicFlags { read: true, write: true, notify: true, ..Default::default() },
+    );
+    characteristic.value = vec![1, 2, 3];
+    characteristic.descriptors.push(descriptor);
+
+    let mut read_only =
+        MockCharacteristic::new(0x0d, READ_ONLY_UUID, CharacteristicFlags { read: true, ..Default::default() });
+    read_only.value = vec![0xaa];
+
+    let mut service = MockService::new(0x0a, SERVICE_UUID);
+    service.characteristics = vec![characteristic, read_only];
+
+    let mut device = MockDevice::new(DEVICE_ADDRESS);
+    device.name = Some("Counter".to_string());
+    device.rssi = Some(-42);
+    device.uuids.insert(SERVICE_UUID);
+    device.services.push(service);
+    device
+}
+
+async fn next<T>(stream: &mut (impl Stream<Item = T> + Unpin)) -> T {
+    timeout(TIMEOUT, stream.next()).await.expect("timeout waiting for stream item").expect("stream ended")
+}
+
+#[tokio::test]
+async fn adapter_properties() {
+    let mock = mock().await;
+    let session = mock.session().await.unwrap();
+
+    assert_eq!(session.adapter_names().await.unwrap(), vec![ADAPTER.to_string()]);
+    let adapter = session.default_adapter().await.unwrap();
+    assert_eq!(adapter.name(), ADAPTER);
+    assert_eq!(adapter.address().await.unwrap(), ADAPTER_ADDRESS);
+    assert!(adapter.is_powered().await.unwrap());
+
+    adapter.set_powered(false).await.unwrap();
+    assert!(!adapter.is_powered().await.unwrap());
+    adapter.set_alias("renamed".to_string()).await.unwrap();
+    assert_eq!(adapter.alias().await.unwrap(), "renamed");
+}
+
+#[tokio::test]
+async fn adapter_discovery() {
+    let mock = mock().await;
+    mock.add_discoverable_device(ADAPTER, device()).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let adapter = session.default_adapter().await.unwrap();
+    assert!(adapter.device_addresses().await.unwrap().is_empty());
+
+    let mut events = Box::pin(adapter.discover_devices().await.unwrap());
+    let added = loop {
+        if let AdapterEvent::DeviceAdded(addr) = next(&mut events).await {
+            break addr;
+        }
+    };
+    assert_eq!(added, DEVICE_ADDRESS);
+    assert!(adapter.is_discovering().await.unwrap());
+
+    let device = adapter.device(DEVICE_ADDRESS).unwrap();
+    assert_eq!(device.name().await.unwrap().as_deref(), Some("Counter"));
+    assert_eq!(device.rssi().await.unwrap(), Some(-42));
+    assert!(device.uuids().await.unwrap().unwrap().contains(&SERVICE_UUID));
+
+    drop(events);
+    timeout(TIMEOUT, async {
+        while adapter.is_discovering().await.unwrap() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("discovery was not stopped");
+}
+
+#[tokio::test]
+async fn device_connect() {
+    let mock = mock().await;
+    mock.add_device(ADAPTER, device()).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let adapter = session.default_adapter().await.unwrap();
+    assert_eq!(adapter.device_addresses().await.unwrap(), vec![DEVICE_ADDRESS]);
+
+    let device = adapter.device(DEVICE_ADDRESS).unwrap();
+    assert!(!device.is_connected().await.unwrap());
+    assert_eq!(device.services().await.unwrap_err().kind, ErrorKind::ServicesUnresolved);
+
+    device.connect().await.unwrap();
+    assert!(device.is_connected().await.unwrap());
+    assert!(device.is_services_resolved().await.unwrap());
+
+    let services = device.services().await.unwrap();
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].id(), 0x0a);
+    assert_eq!(services[0].uuid().await.unwrap(), SERVICE_UUID);
+    assert!(services[0].primary().await.unwrap());
+
+    let mut uuids = Vec::new();
+    for characteristic in services[0].characteristics().await.unwrap() {
+        uuids.push(characteristic.uuid().await.unwrap());
+    }
+    uuids.sort();
+    assert_eq!(uuids, vec![CHARACTERISTIC_UUID, READ_ONLY_UUID]);
+
+    device.disconnect().await.unwrap();
+    assert!(!device.is_connected().await.unwrap());
+    assert!(!device.is_services_resolved().await.unwrap());
+    assert!(mock.characteristic_value(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0b).await.is_err());
+}
+
+#[tokio::test]
+async fn device_connect_error() {
+    let mock = mock().await;
+    mock.add_device(ADAPTER, device()).await.unwrap();
+    mock.set_method_error(
+        "/org/bluez/hci0/dev_10_20_30_40_50_60",
+        "Connect",
+        Some(ErrorKind::ConnectionAttemptFailed),
+    )
+    .await;
+
+    let session = mock.session().await.unwrap();
+    let device = session.default_adapter().await.unwrap().device(DEVICE_ADDRESS).unwrap();
+    let err = device.connect().await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ConnectionAttemptFailed);
+    assert!(!device.is_connected().await.unwrap());
+
+    mock.set_method_error("/org/bluez/hci0/dev_10_20_30_40_50_60", "Connect", None).await;
+    device.connect().await.unwrap();
+    assert!(device.is_connected().await.unwrap());
+}
+
+#[tokio::test]
+async fn device_service_records() {
+    let serial_port = Uuid::from_u128(0x00001101_0000_1000_8000_00805f9b34fb);
+    let mut record = ServiceRecord::new();
+    record.set_service_class_ids(&[serial_port]);
+    record.set_protocol_descriptors(&[ProtocolDescriptor::l2cap(None), ProtocolDescriptor::rfcomm(3)]);
+    record.set_profile_descriptors(&[ProfileDescriptor { profile: serial_port, version: 0x0102 }]);
+    record.set_service_name("Serial Port");
+
+    let mock = mock().await;
+    let mut device = device();
+    device.service_records.push(record.clone());
+    mock.add_device(ADAPTER, device).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let device = session.default_adapter().await.unwrap().device(DEVICE_ADDRESS).unwrap();
+    let records = device.service_records().await.unwrap();
+    assert_eq!(records, [record]);
+    assert_eq!(records[0].rfcomm_channel(), Some(3));
+    assert_eq!(records[0].profile_descriptors(), [ProfileDescriptor { profile: serial_port, version: 0x0102 }]);
+    assert_eq!(records[0].service_name().as_deref(), Some("Serial Port"));
+}
+
+#[tokio::test]
+async fn gatt_read_write() {
+    let mock = mock().await;
+    mock.add_device(ADAPTER, device()).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let device = session.default_adapter().await.unwrap().device(DEVICE_ADDRESS).unwrap();
+    device.connect().await.unwrap();
+
+    let service = device.service(0x0a).await.unwrap();
+    let characteristic = service.characteristic(0x0b).await.unwrap();
+    assert_eq!(characteristic.read().await.unwrap(), vec![1, 2, 3]);
+
+    characteristic.write(&[4, 5]).await.unwrap();
+    assert_eq!(characteristic.read().await.unwrap(), vec![4, 5]);
+    assert_eq!(mock.characteristic_value(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0b).await.unwrap(), vec![4, 5]);
+
+    let descriptor = characteristic.descriptor(0x10).await.unwrap();
+    assert_eq!(descriptor.uuid().await.unwrap(), USER_DESCRIPTION_UUID);
+    assert_eq!(descriptor.read().await.unwrap(), b"Counter");
+    descriptor.write(b"Count").await.unwrap();
+    assert_eq!(mock.descriptor_value(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0b, 0x10).await.unwrap(), b"Count");
+
+    let read_only = service.characteristic(0x0d).await.unwrap();
+    assert_eq!(read_only.read().await.unwrap(), vec![0xaa]);
+    let err = read_only.write(&[0]).await.unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotPermitted);
+    assert_eq!(mock.characteristic_value(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0d).await.unwrap(), vec![0xaa]);
+}
+
+#[tokio::test]
+async fn gatt_notify() {
+    let mock = mock().await;
+    mock.add_device(ADAPTER, device()).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let device = session.default_adapter().await.unwrap().device(DEVICE_ADDRESS).unwrap();
+    device.connect().await.unwrap();
+    let service = device.service(0x0a).await.unwrap();
+
+    let characteristic = service.characteristic(0x0b).await.unwrap();
+    let notifications = characteristic.notify().await.unwrap();
+    pin_mut!(notifications);
+    assert_eq!(characteristic.notifying().await.unwrap(), Some(true));
+
+    mock.notify(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0b, vec![7]).await.unwrap();
+    assert_eq!(next(&mut notifications).await, vec![7]);
+    mock.notify(ADAPTER, DEVICE_ADDRESS, 0x0a, 0x0b, vec![8, 9]).await.unwrap();
+    assert_eq!(next(&mut notifications).await, vec![8, 9]);
+
+    let read_only = service.characteristic(0x0d).await.unwrap();
+    let err = read_only.notify().await.err().unwrap();
+    assert_eq!(err.kind, ErrorKind::NotPermitted);
+}
+
+#[tokio::test]
+async fn gatt_database() {
+    let mock = mock().await;
+    mock.add_device(ADAPTER, device()).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let device = session.default_adapter().await.unwrap().device(DEVICE_ADDRESS).unwrap();
+    device.connect().await.unwrap();
+
+    let db = device.gatt_database(true).await.unwrap();
+    assert_eq!(db.services.len(), 1);
+    let service = &db.services[0];
+    assert_eq!((service.handle, service.uuid, service.primary), (0x0a, SERVICE_UUID, true));
+    assert!(service.includes.is_empty());
+    let handles: Vec<_> = service.characteristics.iter().map(|c| (c.handle, c.uuid)).collect();
+    assert_eq!(handles, [(0x0b, CHARACTERISTIC_UUID), (0x0d, READ_ONLY_UUID)]);
+    let characteristic = &service.characteristics[0];
+    assert_eq!(
+        characteristic.flags,
+        CharacteristicFlags { read: true, write: true, notify: true, ..Default::default() }
+    );
+    assert_eq!(characteristic.value, Some(vec![1, 2, 3]));
+    assert_eq!(characteristic.descriptors.len(), 1);
+    assert_eq!(characteristic.descriptors[0].handle, 0x10);
+    assert_eq!(characteristic.descriptors[0].uuid, USER_DESCRIPTION_UUID);
+    assert_eq!(characteristic.descriptors[0].value, Some(b"Counter".to_vec()));
+    assert_eq!(service.characteristics[1].value, Some(vec![0xaa]));
+
+    let without_values = device.gatt_database(false).await.unwrap();
+    assert_eq!(without_values.services[0].characteristics[0].value, None);
+    assert!(db.diff(&without_values).is_empty());
+
+    device.service(0x0a).await.unwrap().characteristic(0x0b).await.unwrap().write(&[4]).await.unwrap();
+    let changed = device.gatt_database(true).await.unwrap();
+    assert_eq!(
+        db.diff(&changed),
+        [Change::ValueChanged {
+            path: AttributePath {
+                service: SERVICE_UUID,
+                characteristic: Some(CHARACTERISTIC_UUID),
+                descriptor: None
+            },
+            old: vec![1, 2, 3],
+            new: vec![4],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn advertisement_registration() {
+    let mock = mock().await;
+    let session = mock.session().await.unwrap();
+    let adapter = session.default_adapter().await.unwrap();
+
+    let adv = Advertisement {
+        service_uuids: [SERVICE_UUID].into_iter().collect(),
+        manufacturer_data: [(0xffff, vec![1, 2, 3])].into_iter().collect(),
+        local_name: Some("mock".to_string()),
+        ..Default::default()
+    };
+    let handle = adapter.advertise(adv).await.unwrap();
+    assert_eq!(adapter.active_advertising_instances().await.unwrap(), 1);
+
+    let advs = mock.advertisements(ADAPTER).await;
+    assert_eq!(advs.len(), 1);
+    assert_eq!(advs[0].service_uuids, [SERVICE_UUID].into_iter().collect());
+    assert_eq!(advs[0].manufacturer_data.get(&0xffff), Some(&vec![1, 2, 3]));
+    assert_eq!(advs[0].local_name.as_deref(), Some("mock"));
+
+    drop(handle);
+    timeout(TIMEOUT, async {
+        while !mock.advertisements(ADAPTER).await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("advertisement was not unregistered");
+    assert_eq!(adapter.active_advertising_instances().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn advertisement_reregistration() {
+    let mock = mock().await;
+    let session = mock.session().await.unwrap();
+    session.set_auto_reregister(true);
+    let adapter = session.default_adapter().await.unwrap();
+
+    let adv = Advertisement { local_name: Some("mock".to_string()), ..Default::default() };
+    let _handle = adapter.advertise(adv).await.unwrap();
+    assert_eq!(mock.advertisements(ADAPTER).await.len(), 1);
+
+    mock.remove_adapter(ADAPTER).await.unwrap();
+    assert!(mock.advertisements(ADAPTER).await.is_empty());
+    mock.add_adapter(MockAdapter::new(ADAPTER, ADAPTER_ADDRESS)).await.unwrap();
+    timeout(TIMEOUT, async {
+        while mock.advertisements(ADAPTER).await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("advertisement was not registered again");
+    assert_eq!(mock.advertisements(ADAPTER).await[0].local_name.as_deref(), Some("mock"));
+}
+
+#[tokio::test]
+async fn advertisement_instances_exhausted() {
+    let mock = MockBluez::new().await.unwrap();
+    let mut adapter = MockAdapter::new(ADAPTER, ADAPTER_ADDRESS);
+    adapter.supported_advertisement_instances = 1;
+    mock.add_adapter(adapter).await.unwrap();
+
+    let session = mock.session().await.unwrap();
+    let adapter = session.default_adapter().await.unwrap();
+    let _handle = adapter.advertise(Advertisement::default()).await.unwrap();
+    let err = adapter.advertise(Advertisement::default()).await.err().unwrap();
+    assert_eq!(err.kind, ErrorKind::Failed);
+    assert_eq!(mock.advertisements(ADAPTER).await.len(), 1);
+}
+
+#[tokio::test]
+async fn advertisement_length() {
+    let mock = MockBluez::new().await.unwrap();
+    let mut adapter = MockAdapter::new(ADAPTER, ADAPTER_ADDRESS);
+    adapter.max_advertisement_length = 31;
+    mock.add_adapter(adapter).await.unwrap();
+    mock.add_adapter(MockAdapter {
+        max_advertisement_length: 251,
+        ..MockAdapter::new("hci1", Address::new([0x00, 0x01, 0x02, 0x03, 0x04, 0x06]))
+    })
+    .await
+    .unwrap();
+
+    // Flags (3 bytes) and manufacturer data (4 + 25 bytes) exceed the legacy advertising length.
+    let adv = Advertisement {
+        advertisement_type: bluer::adv::Type::Peripheral,
+        manufacturer_data: [(0xffff, vec![0; 25])].into_iter().collect(),
+        ..Default::default()
+    };
+    assert_eq!(adv.check_len(31), Err(bluer::ad::AdError::TooLong { len: 32, max_len: 31 }));
+    assert_eq!(adv.check_len(251), Ok(32));
+
+    let session = mock.session().await.unwrap();
+    let legacy = session.adapter(ADAPTER).unwrap();
+    let err = legacy.advertise(adv.clone()).await.err().unwrap();
+    assert_eq!(err.kind, ErrorKind::InvalidLength);
+    assert!(mock.advertisements(ADAPTER).await.is_empty());
+
+    let extended = session.adapter("hci1").unwrap();
+    let _handle = extended.advertise(adv).await.unwrap();
+    assert_eq!(mock.advertisements("hci1").await.len(), 1);
+}